@@ -0,0 +1,478 @@
+// Copyright 2018-2023 Parity Technologies (UK) Ltd.
+// This file is part of cargo-contract.
+//
+// cargo-contract is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// cargo-contract is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with cargo-contract.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Static analysis of the storage fields accessed by the messages and constructors.
+//!
+//! The source of the contract is parsed, and within the methods of the `#[ink(storage)]`
+//! struct every `self.<field>` is classified:
+//!
+//! - as a write if it is assigned to, borrowed mutably, initialized by `Self { .. }`, or
+//!   the receiver of a mutating method such as `insert`, `set` or `push`,
+//! - as a read otherwise.
+//!
+//! The accesses of helper methods called with `self.<method>(..)` are attributed to the
+//! messages calling them. The result is an approximation: accesses through macros which
+//! do not take expressions, free functions or trait default methods are not seen.
+
+use anyhow::{
+    Context,
+    Result,
+};
+use contract_metadata::{
+    FieldAccess,
+    StorageAccess,
+};
+use std::{
+    collections::{
+        BTreeMap,
+        BTreeSet,
+    },
+    fs,
+    path::Path,
+};
+use syn::{
+    punctuated::Punctuated,
+    visit::Visit,
+    Expr,
+    ImplItem,
+    Item,
+    Member,
+};
+
+/// The methods of storage types which mutate them.
+const MUTATING_METHODS: &[&str] = &[
+    "append", "clear", "entry", "extend", "get_mut", "insert", "iter_mut", "pop", "push",
+    "remove", "retain", "set", "swap", "take", "truncate",
+];
+
+/// Analyze the accesses of the crate at the root source file, following its `mod`
+/// declarations.
+pub fn analyze_crate(root: &Path) -> Result<StorageAccess> {
+    let mut files = Vec::new();
+    load_files(root, root.parent().unwrap_or(Path::new(".")), &mut files)?;
+    Ok(analyze(&files))
+}
+
+/// Parse the file and the files of its out-of-line modules, in the directory.
+fn load_files(path: &Path, dir: &Path, files: &mut Vec<syn::File>) -> Result<()> {
+    let source = fs::read_to_string(path)
+        .with_context(|| format!("Failed to read {}", path.display()))?;
+    let file = syn::parse_file(&source)
+        .with_context(|| format!("Failed to parse {}", path.display()))?;
+    for item in &file.items {
+        if let Item::Mod(module) = item {
+            if module.content.is_none() {
+                let name = module.ident.to_string();
+                let candidates = [
+                    dir.join(format!("{name}.rs")),
+                    dir.join(&name).join("mod.rs"),
+                ];
+                if let Some(child) = candidates.iter().find(|path| path.exists()) {
+                    load_files(child, &dir.join(&name), files)?;
+                }
+            }
+        }
+    }
+    files.push(file);
+    Ok(())
+}
+
+/// The kind of a method of the storage struct.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Kind {
+    Constructor,
+    Message { mutable: bool },
+    Helper,
+}
+
+/// The direct accesses of a method.
+#[derive(Debug, Default)]
+struct MethodAccess {
+    access: FieldAccess,
+    /// The methods called on `self`.
+    calls: BTreeSet<String>,
+}
+
+fn analyze(files: &[syn::File]) -> StorageAccess {
+    let mut storage = StorageCollector::default();
+    for file in files {
+        storage.visit_file(file);
+    }
+    let Some((name, fields)) = storage.storage else {
+        return StorageAccess::default()
+    };
+    let mut methods = MethodCollector {
+        storage: &name,
+        fields: &fields,
+        trait_name: None,
+        methods: Vec::new(),
+    };
+    for file in files {
+        methods.visit_file(file);
+    }
+
+    let helpers: BTreeMap<_, _> = methods
+        .methods
+        .iter()
+        .map(|(_, name, _, access)| (name.clone(), access))
+        .collect();
+    let mut result = StorageAccess::default();
+    for (kind, _, label, direct) in &methods.methods {
+        let mut access = direct.access.clone();
+        let mut visited = BTreeSet::new();
+        let mut pending: Vec<_> = direct.calls.iter().cloned().collect();
+        while let Some(call) = pending.pop() {
+            if !visited.insert(call.clone()) {
+                continue
+            }
+            if let Some(callee) = helpers.get(&call) {
+                access.reads.extend(callee.access.reads.iter().cloned());
+                access.writes.extend(callee.access.writes.iter().cloned());
+                pending.extend(callee.calls.iter().cloned());
+            }
+        }
+        // Messages taking `&self` can not write, the method names were misleading.
+        if let Kind::Message { mutable: false } = kind {
+            let writes = std::mem::take(&mut access.writes);
+            access.reads.extend(writes);
+        }
+        access.reads.retain(|field| !access.writes.contains(field));
+        match kind {
+            Kind::Constructor => result.constructors.insert(label.clone(), access),
+            Kind::Message { .. } => result.messages.insert(label.clone(), access),
+            Kind::Helper => None,
+        };
+    }
+    result
+}
+
+/// Returns `true` if the item has an `#[ink(..)]` attribute with the argument.
+fn has_ink_attr(attrs: &[syn::Attribute], arg: &str) -> bool {
+    attrs.iter().any(|attr| {
+        if !attr.path().is_ident("ink") {
+            return false
+        }
+        let mut found = false;
+        let _ = attr.parse_nested_meta(|meta| {
+            found |= meta.path.is_ident(arg);
+            // Skip the values of arguments such as `selector = 0x..`.
+            if meta.input.peek(syn::Token![=]) {
+                meta.value()?.parse::<Expr>()?;
+            }
+            Ok(())
+        });
+        found
+    })
+}
+
+/// Finds the `#[ink(storage)]` struct and its fields.
+#[derive(Default)]
+struct StorageCollector {
+    storage: Option<(String, BTreeSet<String>)>,
+}
+
+impl<'ast> Visit<'ast> for StorageCollector {
+    fn visit_item_struct(&mut self, item: &'ast syn::ItemStruct) {
+        if has_ink_attr(&item.attrs, "storage") {
+            let fields = item
+                .fields
+                .iter()
+                .filter_map(|field| Some(field.ident.as_ref()?.to_string()))
+                .collect();
+            self.storage = Some((item.ident.to_string(), fields));
+        }
+    }
+}
+
+/// Collects the direct accesses of the methods of the storage struct.
+struct MethodCollector<'a> {
+    storage: &'a str,
+    fields: &'a BTreeSet<String>,
+    trait_name: Option<String>,
+    /// The kind, name, label and accesses of the methods.
+    methods: Vec<(Kind, String, String, MethodAccess)>,
+}
+
+impl<'ast> Visit<'ast> for MethodCollector<'_> {
+    fn visit_item_impl(&mut self, item: &'ast syn::ItemImpl) {
+        let syn::Type::Path(ref self_ty) = *item.self_ty else {
+            return
+        };
+        if self_ty
+            .path
+            .segments
+            .last()
+            .map_or(true, |segment| segment.ident != self.storage)
+        {
+            return
+        }
+        self.trait_name = item
+            .trait_
+            .as_ref()
+            .and_then(|(_, path, _)| path.segments.last())
+            .map(|segment| segment.ident.to_string());
+        for impl_item in &item.items {
+            if let ImplItem::Fn(method) = impl_item {
+                self.visit_method(method);
+            }
+        }
+    }
+}
+
+impl MethodCollector<'_> {
+    fn visit_method(&mut self, method: &syn::ImplItemFn) {
+        let name = method.sig.ident.to_string();
+        let kind = if has_ink_attr(&method.attrs, "constructor") {
+            Kind::Constructor
+        } else if has_ink_attr(&method.attrs, "message") {
+            let mutable = method.sig.receiver().is_some_and(|receiver| {
+                receiver.mutability.is_some() || receiver.reference.is_none()
+            });
+            Kind::Message { mutable }
+        } else {
+            Kind::Helper
+        };
+        let label = match self.trait_name {
+            Some(ref trait_name) if kind != Kind::Helper => {
+                format!("{trait_name}::{name}")
+            }
+            _ => name.clone(),
+        };
+        let mut body = BodyVisitor {
+            storage: self.storage,
+            fields: self.fields,
+            access: MethodAccess::default(),
+        };
+        body.visit_block(&method.block);
+        self.methods.push((kind, name, label, body.access));
+    }
+}
+
+/// Classifies the accesses of the storage fields in the body of a method.
+struct BodyVisitor<'a> {
+    storage: &'a str,
+    fields: &'a BTreeSet<String>,
+    access: MethodAccess,
+}
+
+impl BodyVisitor<'_> {
+    /// Returns the storage field at the root of the place expression, e.g. `balances`
+    /// for `self.balances[0].amount`.
+    fn root_field(&self, expr: &Expr) -> Option<String> {
+        match expr {
+            Expr::Field(field) => {
+                if is_self(&field.base) {
+                    match field.member {
+                        Member::Named(ref ident)
+                            if self.fields.contains(&ident.to_string()) =>
+                        {
+                            Some(ident.to_string())
+                        }
+                        _ => None,
+                    }
+                } else {
+                    self.root_field(&field.base)
+                }
+            }
+            Expr::Index(index) => self.root_field(&index.expr),
+            Expr::Paren(paren) => self.root_field(&paren.expr),
+            Expr::Unary(unary) => self.root_field(&unary.expr),
+            Expr::MethodCall(call) => self.root_field(&call.receiver),
+            _ => None,
+        }
+    }
+
+    /// Record a write of the place, and visit the expressions within it.
+    fn write(&mut self, place: &Expr) {
+        match self.root_field(place) {
+            Some(field) => {
+                self.access.access.writes.insert(field);
+                self.visit_place_indices(place);
+            }
+            None => self.visit_expr(place),
+        }
+    }
+
+    /// Visit the index and argument expressions of a place expression.
+    fn visit_place_indices(&mut self, place: &Expr) {
+        match place {
+            Expr::Field(field) => self.visit_place_indices(&field.base),
+            Expr::Index(index) => {
+                self.visit_place_indices(&index.expr);
+                self.visit_expr(&index.index);
+            }
+            Expr::Paren(paren) => self.visit_place_indices(&paren.expr),
+            Expr::Unary(unary) => self.visit_place_indices(&unary.expr),
+            Expr::MethodCall(call) => {
+                self.visit_place_indices(&call.receiver);
+                for arg in &call.args {
+                    self.visit_expr(arg);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn is_storage_path(&self, path: &syn::Path) -> bool {
+        path.is_ident("Self") || path.is_ident(self.storage)
+    }
+}
+
+fn is_self(expr: &Expr) -> bool {
+    matches!(expr, Expr::Path(path) if path.path.is_ident("self"))
+}
+
+impl<'ast> Visit<'ast> for BodyVisitor<'_> {
+    fn visit_expr(&mut self, expr: &'ast Expr) {
+        match expr {
+            Expr::Assign(assign) => {
+                self.write(&assign.left);
+                self.visit_expr(&assign.right);
+            }
+            Expr::Binary(binary) if is_compound_assignment(&binary.op) => {
+                self.write(&binary.left);
+                self.visit_expr(&binary.right);
+            }
+            Expr::Reference(reference) if reference.mutability.is_some() => {
+                self.write(&reference.expr)
+            }
+            Expr::MethodCall(call) if is_self(&call.receiver) => {
+                self.access.calls.insert(call.method.to_string());
+                for arg in &call.args {
+                    self.visit_expr(arg);
+                }
+            }
+            Expr::MethodCall(call)
+                if self.root_field(&call.receiver).is_some()
+                    && MUTATING_METHODS.contains(&call.method.to_string().as_str()) =>
+            {
+                self.write(expr)
+            }
+            Expr::Field(_) | Expr::Index(_) => {
+                match self.root_field(expr) {
+                    Some(field) => {
+                        self.access.access.reads.insert(field);
+                        self.visit_place_indices(expr);
+                    }
+                    None => syn::visit::visit_expr(self, expr),
+                }
+            }
+            Expr::Struct(init) if self.is_storage_path(&init.path) => {
+                for field in &init.fields {
+                    if let Member::Named(ref ident) = field.member {
+                        self.access.access.writes.insert(ident.to_string());
+                    }
+                    self.visit_expr(&field.expr);
+                }
+            }
+            _ => syn::visit::visit_expr(self, expr),
+        }
+    }
+
+    fn visit_macro(&mut self, mac: &'ast syn::Macro) {
+        // Macros such as `assert!` and `debug_println!` take expressions.
+        if let Ok(args) =
+            mac.parse_body_with(Punctuated::<Expr, syn::Token![,]>::parse_terminated)
+        {
+            for arg in &args {
+                self.visit_expr(arg);
+            }
+        }
+    }
+}
+
+fn is_compound_assignment(op: &syn::BinOp) -> bool {
+    use syn::BinOp::*;
+    matches!(
+        op,
+        AddAssign(_)
+            | SubAssign(_)
+            | MulAssign(_)
+            | DivAssign(_)
+            | RemAssign(_)
+            | BitXorAssign(_)
+            | BitAndAssign(_)
+            | BitOrAssign(_)
+            | ShlAssign(_)
+            | ShrAssign(_)
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn accesses_of_messages_are_collected() {
+        let source = r#"
+            #[ink::contract]
+            mod erc20 {
+                #[ink(storage)]
+                pub struct Erc20 {
+                    total_supply: Balance,
+                    balances: Mapping<AccountId, Balance>,
+                    owner: AccountId,
+                }
+
+                impl Erc20 {
+                    #[ink(constructor)]
+                    pub fn new(total_supply: Balance) -> Self {
+                        Self { total_supply, balances: Mapping::new(), owner: caller() }
+                    }
+
+                    #[ink(message)]
+                    pub fn total_supply(&self) -> Balance {
+                        self.total_supply
+                    }
+
+                    #[ink(message, selector = 0xCAFE)]
+                    pub fn transfer(&mut self, to: AccountId, value: Balance) {
+                        let from = self.env().caller();
+                        let balance = self.balance_of_impl(&from);
+                        assert!(balance >= value, "{}", self.owner);
+                        self.balances.insert(to, &value);
+                    }
+
+                    #[ink(message)]
+                    pub fn mint(&mut self, value: Balance) {
+                        self.total_supply += value;
+                    }
+
+                    fn balance_of_impl(&self, owner: &AccountId) -> Balance {
+                        self.balances.get(owner).unwrap_or_default()
+                    }
+                }
+            }
+        "#;
+        let access = analyze(&[syn::parse_file(source).unwrap()]);
+        let fields = |fields: &[&str]| -> BTreeSet<String> {
+            fields.iter().map(ToString::to_string).collect()
+        };
+
+        assert_eq!(
+            access.constructors["new"].writes,
+            fields(&["balances", "owner", "total_supply"])
+        );
+        assert_eq!(
+            access.messages["total_supply"].reads,
+            fields(&["total_supply"])
+        );
+        assert_eq!(access.messages["transfer"].reads, fields(&["owner"]));
+        assert_eq!(access.messages["transfer"].writes, fields(&["balances"]));
+        assert_eq!(access.messages["mint"].writes, fields(&["total_supply"]));
+        assert!(!access.messages.contains_key("balance_of_impl"));
+    }
+}
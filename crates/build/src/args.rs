@@ -99,8 +99,16 @@ pub enum BuildArtifacts {
     All,
     /// Only the Wasm is created, generation of metadata and a bundled `<name>.contract`
     /// file is skipped
-    #[clap(name = "code-only")]
+    #[clap(name = "code-only", alias = "wasm")]
     CodeOnly,
+    /// Only the metadata is generated, always, for the Wasm built if it changed. The
+    /// stale `<name>.contract` file is removed
+    #[clap(name = "metadata-only", alias = "metadata")]
+    MetadataOnly,
+    /// Only the bundled `<name>.contract` file is assembled, from the Wasm and the
+    /// metadata of previous builds. Nothing is compiled
+    #[clap(name = "bundle-only", alias = "bundle")]
+    BundleOnly,
     /// No artifacts produced: runs the `cargo check` command for the Wasm target, only
     /// checks for compilation errors.
     #[clap(name = "check-only")]
@@ -114,6 +122,8 @@ impl BuildArtifacts {
         match self {
             BuildArtifacts::All => 4,
             BuildArtifacts::CodeOnly => 3,
+            BuildArtifacts::MetadataOnly => 3,
+            BuildArtifacts::BundleOnly => 1,
             BuildArtifacts::CheckOnly => 1,
         }
     }
@@ -291,6 +301,11 @@ impl Features {
         self.features.push(feature.to_owned())
     }
 
+    /// Returns the features to activate.
+    pub fn features(&self) -> &[String] {
+        &self.features
+    }
+
     /// Appends the raw features args to pass through to the `cargo` invocation.
     pub fn append_to_args(&self, args: &mut Vec<String>) {
         if !self.features.is_empty() {
@@ -0,0 +1,322 @@
+// Copyright 2018-2023 Parity Technologies (UK) Ltd.
+// This file is part of cargo-contract.
+//
+// cargo-contract is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// cargo-contract is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with cargo-contract.  If not, see <http://www.gnu.org/licenses/>.
+
+//! The changelog of the interface of a contract across builds.
+//!
+//! If a `CONTRACT_CHANGELOG.json` file exists next to the `Cargo.toml` of the contract,
+//! every build generating metadata compares the constructors, messages and events of the
+//! contract to the interface of the previous build, and appends the changes to the file.
+
+use crate::CrateMetadata;
+use anyhow::{
+    Context,
+    Result,
+};
+use contract_metadata::ContractMetadata;
+use serde_json::{
+    Map,
+    Value,
+};
+use std::{
+    collections::BTreeMap,
+    fmt,
+    path::{
+        Path,
+        PathBuf,
+    },
+};
+
+/// The file name of the changelog, in the directory of the contract manifest.
+pub const CHANGELOG_FILE: &str = "CONTRACT_CHANGELOG.json";
+
+/// The interface of a contract: the signatures of its constructors, messages and events
+/// by label.
+#[derive(Debug, Default, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct Interface {
+    pub constructors: BTreeMap<String, String>,
+    pub messages: BTreeMap<String, String>,
+    pub events: BTreeMap<String, String>,
+}
+
+/// An item of the interface of a contract.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Item {
+    Constructor,
+    Message,
+    Event,
+}
+
+impl fmt::Display for Item {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Constructor => write!(f, "constructor"),
+            Self::Message => write!(f, "message"),
+            Self::Event => write!(f, "event"),
+        }
+    }
+}
+
+/// A change of the interface of a contract between two builds.
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(tag = "change", rename_all = "kebab-case")]
+pub enum Change {
+    Added {
+        item: Item,
+        name: String,
+        signature: String,
+    },
+    Removed {
+        item: Item,
+        name: String,
+        signature: String,
+    },
+    /// The signature of the item changed, e.g. its arguments or its selector.
+    Changed {
+        item: Item,
+        name: String,
+        from: String,
+        to: String,
+    },
+}
+
+/// The interface changes of a build.
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct ChangelogEntry {
+    /// The version of the contract package.
+    pub version: String,
+    /// The hash of the code of the build.
+    pub code_hash: String,
+    pub changes: Vec<Change>,
+}
+
+/// The contents of the `CONTRACT_CHANGELOG.json` file.
+#[derive(Debug, Default, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct Changelog {
+    /// The interface of the latest build, compared to the interface of the next build.
+    pub interface: Interface,
+    /// The builds changing the interface, oldest first.
+    pub entries: Vec<ChangelogEntry>,
+}
+
+impl Interface {
+    /// Extract the interface from the `abi` section of the contract metadata.
+    pub fn from_abi(abi: &Map<String, Value>) -> Result<Self> {
+        // The spec is nested in a version key in the metadata of ink! 3.
+        let spec = abi
+            .get("spec")
+            .or_else(|| abi.get("V3").and_then(|v3| v3.get("spec")))
+            .context("No contract spec found in the metadata")?;
+        let items = |key: &str, signature: fn(&Value) -> String| {
+            spec.get(key)
+                .and_then(Value::as_array)
+                .into_iter()
+                .flatten()
+                .map(|item| (label(item), signature(item)))
+                .collect::<BTreeMap<_, _>>()
+        };
+        Ok(Self {
+            constructors: items("constructors", message_signature),
+            messages: items("messages", message_signature),
+            events: items("events", event_signature),
+        })
+    }
+
+    /// The changes from this interface to the new one, constructors first, then messages
+    /// and events.
+    pub fn diff(&self, new: &Interface) -> Vec<Change> {
+        let mut changes = Vec::new();
+        for (item, old, new) in [
+            (Item::Constructor, &self.constructors, &new.constructors),
+            (Item::Message, &self.messages, &new.messages),
+            (Item::Event, &self.events, &new.events),
+        ] {
+            for (name, signature) in old {
+                match new.get(name) {
+                    None => {
+                        changes.push(Change::Removed {
+                            item,
+                            name: name.clone(),
+                            signature: signature.clone(),
+                        })
+                    }
+                    Some(to) if to != signature => {
+                        changes.push(Change::Changed {
+                            item,
+                            name: name.clone(),
+                            from: signature.clone(),
+                            to: to.clone(),
+                        })
+                    }
+                    Some(_) => (),
+                }
+            }
+            for (name, signature) in new {
+                if !old.contains_key(name) {
+                    changes.push(Change::Added {
+                        item,
+                        name: name.clone(),
+                        signature: signature.clone(),
+                    })
+                }
+            }
+        }
+        changes
+    }
+}
+
+impl Changelog {
+    /// Load the changelog from the file at the given path.
+    pub fn load(path: &Path) -> Result<Self> {
+        let contents = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read {}", path.display()))?;
+        serde_json::from_str(&contents)
+            .with_context(|| format!("Invalid changelog {}", path.display()))
+    }
+
+    /// Write the changelog to the file at the given path.
+    pub fn write(&self, path: &Path) -> Result<()> {
+        std::fs::write(path, serde_json::to_string_pretty(self)? + "\n")
+            .with_context(|| format!("Failed to write {}", path.display()))
+    }
+
+    /// Record the interface of a build, appending an entry if it changed. Returns the
+    /// changes.
+    pub fn record(
+        &mut self,
+        version: &str,
+        code_hash: &str,
+        interface: Interface,
+    ) -> Vec<Change> {
+        let changes = self.interface.diff(&interface);
+        if !changes.is_empty() {
+            self.entries.push(ChangelogEntry {
+                version: version.to_string(),
+                code_hash: code_hash.to_string(),
+                changes: changes.clone(),
+            });
+        }
+        self.interface = interface;
+        changes
+    }
+}
+
+/// Returns the path of the changelog of the contract.
+pub fn changelog_path(crate_metadata: &CrateMetadata) -> Result<PathBuf> {
+    Ok(crate_metadata
+        .manifest_path
+        .absolute_directory()?
+        .join(CHANGELOG_FILE))
+}
+
+/// Record the interface of the metadata in the changelog of the contract, if the contract
+/// has one. Returns the changes.
+pub fn update(
+    crate_metadata: &CrateMetadata,
+    metadata_path: &Path,
+) -> Result<Option<Vec<Change>>> {
+    let path = changelog_path(crate_metadata)?;
+    if !path.exists() {
+        return Ok(None)
+    }
+    let metadata = ContractMetadata::load(metadata_path)?;
+    let interface = Interface::from_abi(&metadata.abi)?;
+    let mut changelog = Changelog::load(&path)?;
+    let changes = changelog.record(
+        &crate_metadata.root_package.version.to_string(),
+        &format!("0x{}", hex::encode(metadata.source.hash.0)),
+        interface,
+    );
+    changelog.write(&path)?;
+    Ok(Some(changes))
+}
+
+fn label(item: &Value) -> String {
+    item.get("label")
+        .and_then(Value::as_str)
+        .unwrap_or_default()
+        .to_string()
+}
+
+/// The display name of a type of the spec, e.g. `Option<AccountId>`.
+fn type_name(ty: &Value) -> String {
+    let path = ty
+        .get("displayName")
+        .and_then(Value::as_array)
+        .map(|segments| {
+            segments
+                .iter()
+                .filter_map(Value::as_str)
+                .collect::<Vec<_>>()
+                .join("::")
+        })
+        .unwrap_or_default();
+    if path.is_empty() {
+        // Types without a display name are identified by their id in the registry.
+        format!("#{}", ty.get("type").unwrap_or(&Value::Null))
+    } else {
+        path
+    }
+}
+
+fn args(item: &Value, arg: fn(&Value) -> String) -> String {
+    item.get("args")
+        .and_then(Value::as_array)
+        .into_iter()
+        .flatten()
+        .map(arg)
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// The signature of a constructor or message, e.g.
+/// `transfer(to: AccountId, value: Balance) -> Result [0x84a15da1, mutates]`.
+fn message_signature(message: &Value) -> String {
+    let args = args(message, |arg| {
+        format!("{}: {}", label(arg), type_name(&arg["type"]))
+    });
+    let mut signature = format!("{}({args})", label(message));
+    if let Some(return_type) = message.get("returnType").filter(|ty| !ty.is_null()) {
+        signature.push_str(&format!(" -> {}", type_name(return_type)));
+    }
+    let mut attributes = Vec::new();
+    if let Some(selector) = message.get("selector").and_then(Value::as_str) {
+        attributes.push(selector.to_string());
+    }
+    for flag in ["payable", "mutates"] {
+        if message.get(flag).and_then(Value::as_bool) == Some(true) {
+            attributes.push(flag.to_string());
+        }
+    }
+    if !attributes.is_empty() {
+        signature.push_str(&format!(" [{}]", attributes.join(", ")));
+    }
+    signature
+}
+
+/// The signature of an event, with its topics marked, e.g.
+/// `Transfer(#[indexed] from: Option<AccountId>, value: Balance)`.
+fn event_signature(event: &Value) -> String {
+    let args = args(event, |arg| {
+        let indexed = if arg.get("indexed").and_then(Value::as_bool) == Some(true) {
+            "#[indexed] "
+        } else {
+            ""
+        };
+        format!("{indexed}{}: {}", label(arg), type_name(&arg["type"]))
+    });
+    format!("{}({args})", label(event))
+}
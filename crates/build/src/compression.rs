@@ -0,0 +1,119 @@
+// Copyright 2018-2023 Parity Technologies (UK) Ltd.
+// This file is part of cargo-contract.
+//
+// cargo-contract is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// cargo-contract is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with cargo-contract.  If not, see <http://www.gnu.org/licenses/>.
+
+//! zstd compressed contract bundles, `<name>.contract.zst`.
+//!
+//! The compressed bundle is the zstd frame of the `.contract` bundle, with a content
+//! checksum. The code hash in its metadata remains the hash of the uncompressed Wasm
+//! code, which is checked when the bundle is decompressed.
+
+use anyhow::{
+    Context,
+    Result,
+};
+use contract_metadata::ContractMetadata;
+use std::{
+    fs,
+    path::{
+        Path,
+        PathBuf,
+    },
+};
+
+/// The file extension of compressed contract bundles.
+pub const COMPRESSED_BUNDLE_EXTENSION: &str = "contract.zst";
+
+/// The zstd compression level of compressed bundles. Bundles are compressed once and
+/// decompressed often, so a high level is used.
+const COMPRESSION_LEVEL: i32 = 19;
+
+/// Returns `true` if the path is of a compressed contract bundle.
+pub fn is_compressed_bundle(path: &Path) -> bool {
+    path.to_str()
+        .is_some_and(|path| path.ends_with(&format!(".{COMPRESSED_BUNDLE_EXTENSION}")))
+}
+
+/// Write the compressed bundle next to the bundle, returns its path.
+pub fn compress_bundle(bundle: &Path) -> Result<PathBuf> {
+    let contents = fs::read(bundle)?;
+    let dest = bundle.with_extension(COMPRESSED_BUNDLE_EXTENSION);
+    fs::write(&dest, compress(&contents)?)?;
+    Ok(dest)
+}
+
+fn compress(contents: &[u8]) -> Result<Vec<u8>> {
+    let mut encoder = zstd::Encoder::new(Vec::new(), COMPRESSION_LEVEL)?;
+    encoder.include_checksum(true)?;
+    std::io::Write::write_all(&mut encoder, contents)?;
+    Ok(encoder.finish()?)
+}
+
+/// Load the metadata of the compressed bundle, checking that its code hash is the hash
+/// of its code.
+pub fn load_compressed_bundle(path: &Path) -> Result<ContractMetadata> {
+    let contents = fs::read(path)?;
+    decompress_bundle(&contents)
+        .with_context(|| format!("Failed to load the bundle {}", path.display()))
+}
+
+fn decompress_bundle(contents: &[u8]) -> Result<ContractMetadata> {
+    let contents =
+        zstd::decode_all(contents).context("Failed to decompress the zstd frame")?;
+    let metadata: ContractMetadata = serde_json::from_slice(&contents)?;
+    if let Some(ref wasm) = metadata.source.wasm {
+        let hash = crate::code_hash(&wasm.0);
+        anyhow::ensure!(
+            hash == metadata.source.hash.0,
+            "The code hash 0x{} of the metadata is not the hash 0x{} of the code",
+            hex::encode(metadata.source.hash.0),
+            hex::encode(hash)
+        );
+    }
+    Ok(metadata)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bundles_are_compressed_and_verified() {
+        let hash = format!("0x{}", hex::encode(crate::code_hash(&[0, 97, 115, 109])));
+        let mut bundle = serde_json::json!({
+            "source": {
+                "hash": hash,
+                "language": "ink! 4.2.0",
+                "compiler": "rustc 1.69.0",
+                "wasm": "0x0061736d",
+            },
+            "contract": { "name": "flipper", "version": "0.1.0", "authors": [] },
+            "spec": {},
+        });
+        let compressed = compress(bundle.to_string().as_bytes()).unwrap();
+        let metadata = decompress_bundle(&compressed).unwrap();
+        assert_eq!(metadata.contract.name, "flipper");
+        assert!(is_compressed_bundle(Path::new(
+            "target/ink/flipper.contract.zst"
+        )));
+        assert!(!is_compressed_bundle(Path::new(
+            "target/ink/flipper.contract"
+        )));
+
+        bundle["source"]["wasm"] = serde_json::json!("0x0061736d01");
+        let compressed = compress(bundle.to_string().as_bytes()).unwrap();
+        assert!(decompress_bundle(&compressed).is_err());
+    }
+}
@@ -23,6 +23,7 @@ use anyhow::{
     Result,
 };
 use cargo_metadata::{
+    DependencyKind,
     Metadata as CargoMetadata,
     MetadataCommand,
     Package,
@@ -163,6 +164,12 @@ impl CrateMetadata {
         let fname_bundle = format!("{}.contract", self.contract_artifact_name);
         target_directory.join(fname_bundle)
     }
+
+    /// Get the path of the zstd compressed contract bundle.
+    pub fn compressed_bundle_path(&self) -> PathBuf {
+        self.contract_bundle_path()
+            .with_extension(crate::compression::COMPRESSED_BUNDLE_EXTENSION)
+    }
 }
 
 /// Get the result of `cargo metadata`, together with the root package id.
@@ -198,6 +205,80 @@ fn get_cargo_metadata(manifest_path: &ManifestPath) -> Result<(CargoMetadata, Pa
     Ok((metadata, root_package))
 }
 
+/// Returns the names and manifests of the contract crates among the members of the
+/// workspace of the manifest, in dependency order: a contract used as a dependency by
+/// another contract, e.g. for cross-contract calls, comes first.
+///
+/// Contracts are the members depending on `ink`, or on `ink_lang` for ink! 3. If
+/// `packages` are given, only the contracts with these names are returned.
+pub fn workspace_contracts(
+    manifest_path: &ManifestPath,
+    packages: &[String],
+) -> Result<Vec<(String, ManifestPath)>> {
+    let metadata = MetadataCommand::new()
+        .manifest_path(manifest_path.as_ref())
+        .no_deps()
+        .exec()
+        .with_context(|| {
+            format!(
+                "Error invoking `cargo metadata` for {}",
+                manifest_path.as_ref().display()
+            )
+        })?;
+    let contracts = metadata
+        .packages
+        .iter()
+        .filter(|package| metadata.workspace_members.contains(&package.id))
+        .filter(|package| {
+            package.dependencies.iter().any(|dependency| {
+                dependency.name == "ink" || dependency.name == "ink_lang"
+            })
+        })
+        .collect::<Vec<_>>();
+    for package in packages {
+        anyhow::ensure!(
+            contracts.iter().any(|contract| &contract.name == package),
+            "No contract `{package}` found in the workspace"
+        );
+    }
+
+    fn visit<'a>(
+        package: &'a Package,
+        contracts: &[&'a Package],
+        ordered: &mut Vec<&'a Package>,
+    ) {
+        if ordered.iter().any(|ordered| ordered.id == package.id) {
+            return
+        }
+        for dependency in &package.dependencies {
+            if dependency.kind != DependencyKind::Normal {
+                continue
+            }
+            if let Some(contract) = contracts
+                .iter()
+                .find(|contract| contract.name == dependency.name)
+            {
+                visit(contract, contracts, ordered);
+            }
+        }
+        ordered.push(package);
+    }
+    let mut ordered = Vec::new();
+    for contract in &contracts {
+        visit(contract, &contracts, &mut ordered);
+    }
+    ordered
+        .into_iter()
+        .filter(|contract| packages.is_empty() || packages.contains(&contract.name))
+        .map(|contract| {
+            Ok((
+                contract.name.clone(),
+                ManifestPath::new(&contract.manifest_path)?,
+            ))
+        })
+        .collect()
+}
+
 /// Extra metadata not available via `cargo metadata`.
 struct ExtraMetadata {
     documentation: Option<Url>,
@@ -19,12 +19,17 @@
 
 use which as _;
 
+mod access;
 mod args;
+pub mod changelog;
+pub mod compression;
 mod crate_metadata;
 pub mod metadata;
 mod new;
+pub mod selectors;
 #[cfg(test)]
 mod tests;
+mod timings;
 pub mod util;
 mod validate_wasm;
 mod wasm_opt;
@@ -47,13 +52,21 @@ pub use self::{
         Verbosity,
         VerbosityFlags,
     },
-    crate_metadata::CrateMetadata,
+    crate_metadata::{
+        workspace_contracts,
+        CrateMetadata,
+    },
     metadata::{
         BuildInfo,
         MetadataArtifacts,
         WasmOptSettings,
     },
     new::new_contract_project,
+    timings::{
+        BuildLog,
+        BuildPhase,
+        BUILD_LOG_FILE,
+    },
     util::DEFAULT_KEY_COL_WIDTH,
     wasm_opt::{
         OptimizationPasses,
@@ -83,6 +96,7 @@ use parity_wasm::elements::{
 };
 use semver::Version;
 use std::{
+    cell::RefCell,
     fs,
     path::{
         Path,
@@ -90,6 +104,7 @@ use std::{
     },
     process::Command,
     str,
+    time::Instant,
 };
 use strum::IntoEnumIterator;
 
@@ -116,6 +131,8 @@ pub struct ExecuteArgs {
     pub output_type: OutputType,
     pub skip_wasm_validation: bool,
     pub target: Target,
+    /// Also write a zstd compressed `<name>.contract.zst` bundle.
+    pub compress: bool,
 }
 
 /// Result of the build process.
@@ -135,6 +152,8 @@ pub struct BuildResult {
     pub build_artifact: BuildArtifacts,
     /// The verbosity flags.
     pub verbosity: Verbosity,
+    /// The timings and output sizes of the build phases.
+    pub build_log: BuildLog,
     /// The type of formatting to use for the build output.
     #[serde(skip_serializing)]
     pub output_type: OutputType,
@@ -157,10 +176,15 @@ impl BuildResult {
             "\n".to_string()
         };
 
-        let build_mode = format!(
-            "The contract was built in {} mode.\n\n",
-            format!("{}", self.build_mode).to_uppercase().bold(),
-        );
+        // a bundle is assembled from the artifacts of a previous build, of any mode
+        let build_mode = if self.build_artifact == BuildArtifacts::BundleOnly {
+            String::new()
+        } else {
+            format!(
+                "The contract was built in {} mode.\n\n",
+                format!("{}", self.build_mode).to_uppercase().bold(),
+            )
+        };
 
         if self.build_artifact == BuildArtifacts::CodeOnly {
             let out = format!(
@@ -183,12 +207,23 @@ impl BuildResult {
             build_mode,
             self.target_directory.display().to_string().bold(),
         );
-        if let Some(metadata_result) = self.metadata_result.as_ref() {
+        if let Some(metadata_result) = self
+            .metadata_result
+            .as_ref()
+            .filter(|_| self.build_artifact != BuildArtifacts::MetadataOnly)
+        {
             let bundle = format!(
                 "  - {} (code + metadata)\n",
                 util::base_name(&metadata_result.dest_bundle).bold()
             );
             out.push_str(&bundle);
+            if let Some(compressed) = metadata_result.dest_compressed_bundle.as_ref() {
+                let compressed = format!(
+                    "  - {} (the compressed bundle)\n",
+                    util::base_name(compressed).bold()
+                );
+                out.push_str(&compressed);
+            }
         }
         if let Some(dest_wasm) = self.dest_wasm.as_ref() {
             let wasm = format!(
@@ -593,6 +628,7 @@ pub fn execute(args: ExecuteArgs) -> Result<BuildResult> {
         output_type,
         skip_wasm_validation,
         target,
+        compress,
     } = args;
 
     // The CLI flag `optimization-passes` overwrites optimization passes which are
@@ -617,6 +653,19 @@ pub fn execute(args: ExecuteArgs) -> Result<BuildResult> {
         assert_debug_mode_supported(&crate_metadata.ink_version)?;
     }
 
+    let mut build_log = BuildLog::default();
+    if unstable_flags.original_manifest {
+        build_log.warn(
+            "with 'original-manifest' enabled, the contract binary may not be of \
+            optimal size",
+        );
+    }
+    if skip_wasm_validation {
+        build_log.warn("wasm validation was skipped, the contract code may be invalid");
+    }
+    // the phases record themselves from the `build` and `maybe_lint` closures
+    let build_log = RefCell::new(build_log);
+
     let maybe_lint = |steps: &mut BuildSteps| -> Result<()> {
         let total_steps = build_artifact.steps();
         if lint {
@@ -628,7 +677,9 @@ pub fn execute(args: ExecuteArgs) -> Result<BuildResult> {
                 "Checking ink! linting rules".bright_green().bold()
             );
             steps.increment_current();
+            let started = Instant::now();
             exec_cargo_dylint(&crate_metadata, verbosity)?;
+            build_log.borrow_mut().record("lint", started, None);
             Ok(())
         } else {
             steps.set_total_steps(total_steps);
@@ -648,6 +699,7 @@ pub fn execute(args: ExecuteArgs) -> Result<BuildResult> {
                 "Building cargo project".bright_green().bold()
             );
             build_steps.increment_current();
+            let started = Instant::now();
             exec_cargo_for_onchain_target(
                 &crate_metadata,
                 "build",
@@ -658,6 +710,11 @@ pub fn execute(args: ExecuteArgs) -> Result<BuildResult> {
                 &unstable_flags,
                 target,
             )?;
+            build_log.borrow_mut().record(
+                "cargo build",
+                started,
+                Some(&crate_metadata.original_code),
+            );
 
             // we persist the latest target we used so we trigger a rebuild when we switch
             fs::write(&crate_metadata.target_file_path, target.llvm_target())?;
@@ -679,6 +736,8 @@ pub fn execute(args: ExecuteArgs) -> Result<BuildResult> {
                     optimization_passes,
                     keep_debug_symbols,
                 },
+                features: features.features().to_vec(),
+                target,
             };
 
             let post_fingerprint =
@@ -732,16 +791,34 @@ pub fn execute(args: ExecuteArgs) -> Result<BuildResult> {
 
             match target {
                 Target::Wasm => {
+                    let started = Instant::now();
                     post_process_wasm(&crate_metadata, skip_wasm_validation, &verbosity)?;
+                    build_log.borrow_mut().record(
+                        "post-process",
+                        started,
+                        Some(&crate_metadata.dest_code),
+                    );
+                    let started = Instant::now();
                     let handler =
                         WasmOptHandler::new(optimization_passes, keep_debug_symbols)?;
                     handler.optimize(
                         &crate_metadata.dest_code,
                         &crate_metadata.contract_artifact_name,
                     )?;
+                    build_log.borrow_mut().record(
+                        "wasm-opt",
+                        started,
+                        Some(&crate_metadata.dest_code),
+                    );
                 }
                 Target::RiscV => {
+                    let started = Instant::now();
                     fs::copy(&crate_metadata.original_code, &crate_metadata.dest_code)?;
+                    build_log.borrow_mut().record(
+                        "copy",
+                        started,
+                        Some(&crate_metadata.dest_code),
+                    );
                 }
             }
 
@@ -777,6 +854,7 @@ pub fn execute(args: ExecuteArgs) -> Result<BuildResult> {
                 format!("{build_steps}").bold(),
                 "Executing `cargo check`".bright_green().bold()
             );
+            let started = Instant::now();
             exec_cargo_for_onchain_target(
                 &crate_metadata,
                 "check",
@@ -787,6 +865,7 @@ pub fn execute(args: ExecuteArgs) -> Result<BuildResult> {
                 &unstable_flags,
                 target,
             )?;
+            build_log.borrow_mut().record("cargo check", started, None);
             (None, None, None)
         }
         BuildArtifacts::CodeOnly => {
@@ -795,7 +874,7 @@ pub fn execute(args: ExecuteArgs) -> Result<BuildResult> {
             let (opt_result, _, dest_wasm, _) = build()?;
             (opt_result, None, Some(dest_wasm))
         }
-        BuildArtifacts::All => {
+        BuildArtifacts::All | BuildArtifacts::MetadataOnly => {
             let (opt_result, build_info, dest_wasm, build_steps) =
                 build().map_err(|e| {
                     // build error -> bundle is stale
@@ -803,23 +882,30 @@ pub fn execute(args: ExecuteArgs) -> Result<BuildResult> {
                     e
                 })?;
 
-            let metadata_result = MetadataArtifacts {
+            let mut metadata_result = MetadataArtifacts {
                 dest_metadata: crate_metadata.metadata_path(),
                 dest_bundle: crate_metadata.contract_bundle_path(),
+                dest_compressed_bundle: None,
             };
 
+            let bundle = build_artifact == BuildArtifacts::All;
+
             // skip metadata generation if contract unchanged and all metadata artifacts
-            // exist.
+            // exist. `metadata-only` always generates it, the metadata may have changed
+            // without the code, e.g. its documentation.
             if opt_result.is_some()
+                || !bundle
                 || !metadata_result.dest_metadata.exists()
                 || !metadata_result.dest_bundle.exists()
             {
                 // if metadata build fails after a code build it might become stale
                 clean_metadata();
+                let started = Instant::now();
                 metadata::execute(
                     &crate_metadata,
                     dest_wasm.as_path(),
                     &metadata_result,
+                    bundle,
                     &features,
                     network,
                     verbosity,
@@ -827,11 +913,88 @@ pub fn execute(args: ExecuteArgs) -> Result<BuildResult> {
                     &unstable_flags,
                     build_info,
                 )?;
+                let dest = if bundle {
+                    &metadata_result.dest_bundle
+                } else {
+                    &metadata_result.dest_metadata
+                };
+                build_log
+                    .borrow_mut()
+                    .record("metadata", started, Some(dest));
+                if matches!(target, Target::Wasm) && !skip_wasm_validation {
+                    let verification =
+                        selectors::verify(&dest_wasm, &metadata_result.dest_metadata)
+                            .map_err(|e| {
+                                // the metadata does not describe the code
+                                clean_metadata();
+                                e
+                            })?;
+                    if let Some(warning) = verification.warning() {
+                        build_log.borrow_mut().warn(warning);
+                    }
+                }
+                let changes =
+                    changelog::update(&crate_metadata, &metadata_result.dest_metadata)?;
+                if let Some(changes) = changes.filter(|changes| !changes.is_empty()) {
+                    maybe_println!(
+                        verbosity,
+                        "   {} {} interface changes in {}",
+                        "Recorded".bright_green().bold(),
+                        changes.len(),
+                        changelog::CHANGELOG_FILE
+                    );
+                }
+            }
+            if compress && bundle {
+                let started = Instant::now();
+                let dest = compression::compress_bundle(&metadata_result.dest_bundle)?;
+                build_log
+                    .borrow_mut()
+                    .record("compression", started, Some(&dest));
+                metadata_result.dest_compressed_bundle = Some(dest);
             }
             (opt_result, Some(metadata_result), Some(dest_wasm))
         }
+        BuildArtifacts::BundleOnly => {
+            let mut build_steps = BuildSteps::new();
+            build_steps.set_total_steps(build_artifact.steps());
+            maybe_println!(
+                verbosity,
+                " {} {}",
+                format!("{build_steps}").bold(),
+                "Generating bundle".bright_green().bold()
+            );
+            let mut metadata_result = MetadataArtifacts {
+                dest_metadata: crate_metadata.metadata_path(),
+                dest_bundle: crate_metadata.contract_bundle_path(),
+                dest_compressed_bundle: None,
+            };
+            let started = Instant::now();
+            metadata::write_bundle(&crate_metadata.dest_code, &metadata_result)?;
+            build_log.borrow_mut().record(
+                "bundle",
+                started,
+                Some(&metadata_result.dest_bundle),
+            );
+            if compress {
+                let started = Instant::now();
+                let dest = compression::compress_bundle(&metadata_result.dest_bundle)?;
+                build_log
+                    .borrow_mut()
+                    .record("compression", started, Some(&dest));
+                metadata_result.dest_compressed_bundle = Some(dest);
+            }
+            (
+                None,
+                Some(metadata_result),
+                Some(crate_metadata.dest_code.clone()),
+            )
+        }
     };
 
+    let build_log = build_log.into_inner();
+    build_log.write(&crate_metadata.target_directory)?;
+
     Ok(BuildResult {
         dest_wasm,
         metadata_result,
@@ -840,6 +1003,7 @@ pub fn execute(args: ExecuteArgs) -> Result<BuildResult> {
         build_mode,
         build_artifact,
         verbosity,
+        build_log,
         output_type,
     })
 }
@@ -990,7 +1154,17 @@ mod unit_tests {
   },
   "build_mode": "Debug",
   "build_artifact": "All",
-  "verbosity": "Quiet"
+  "verbosity": "Quiet",
+  "build_log": {
+    "phases": [
+      {
+        "name": "cargo build",
+        "duration_secs": 1.5,
+        "output_size": 64000
+      }
+    ],
+    "warnings": []
+  }
 }"#;
 
         let build_result = BuildResult {
@@ -998,6 +1172,7 @@ mod unit_tests {
             metadata_result: Some(MetadataArtifacts {
                 dest_metadata: PathBuf::from("/path/to/contract.json"),
                 dest_bundle: PathBuf::from("/path/to/contract.contract"),
+                dest_compressed_bundle: None,
             }),
             target_directory: PathBuf::from("/path/to/target"),
             optimization_result: Some(OptimizationResult {
@@ -1008,6 +1183,14 @@ mod unit_tests {
             build_mode: Default::default(),
             build_artifact: Default::default(),
             verbosity: Verbosity::Quiet,
+            build_log: BuildLog {
+                phases: vec![BuildPhase {
+                    name: String::from("cargo build"),
+                    duration_secs: 1.5,
+                    output_size: Some(64000),
+                }],
+                warnings: Vec::new(),
+            },
             output_type: OutputType::Json,
         };
 
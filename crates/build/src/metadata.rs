@@ -15,6 +15,7 @@
 // along with cargo-contract.  If not, see <http://www.gnu.org/licenses/>.
 
 use crate::{
+    access,
     code_hash,
     crate_metadata::CrateMetadata,
     maybe_println,
@@ -28,6 +29,7 @@ use crate::{
     Features,
     Network,
     OptimizationPasses,
+    Target,
     UnstableFlags,
     Verbosity,
 };
@@ -43,6 +45,7 @@ use contract_metadata::{
     SourceCompiler,
     SourceLanguage,
     SourceWasm,
+    StorageAccess,
     User,
 };
 use semver::Version;
@@ -62,6 +65,9 @@ pub struct MetadataArtifacts {
     pub dest_metadata: PathBuf,
     /// Path to the bundled file.
     pub dest_bundle: PathBuf,
+    /// Path to the zstd compressed bundle, if one was written.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub dest_compressed_bundle: Option<PathBuf>,
 }
 
 /// Result of generating the extended contract project metadata
@@ -85,6 +91,12 @@ pub struct BuildInfo {
     pub build_mode: BuildMode,
     /// Information about the `wasm-opt` optimization settings.
     pub wasm_opt_settings: WasmOptSettings,
+    /// The features of the contract crate activated for the build.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub features: Vec<String>,
+    /// The bytecode the contract was built into.
+    #[serde(default)]
+    pub target: Target,
 }
 
 impl TryFrom<BuildInfo> for serde_json::Map<String, serde_json::Value> {
@@ -105,7 +117,34 @@ pub struct WasmOptSettings {
     pub keep_debug_symbols: bool,
 }
 
-/// Generates a file with metadata describing the ABI of the smart contract.
+/// Approximate the storage fields accessed by the messages from the source of the
+/// contract. The accesses are omitted from the metadata if the analysis fails.
+fn storage_access(
+    crate_metadata: &CrateMetadata,
+    verbosity: Verbosity,
+) -> Option<StorageAccess> {
+    let lib = crate_metadata.root_package.targets.iter().find(|target| {
+        target
+            .kind
+            .iter()
+            .any(|kind| kind == "lib" || kind == "cdylib")
+    })?;
+    match access::analyze_crate(lib.src_path.as_std_path()) {
+        Ok(access) if access.messages.is_empty() => None,
+        Ok(access) => Some(access),
+        Err(err) => {
+            maybe_println!(
+                verbosity,
+                "{} the storage accesses of the messages were not analyzed: {err:#}",
+                "warning:".yellow().bold(),
+            );
+            None
+        }
+    }
+}
+
+/// Generates a file with metadata describing the ABI of the smart contract, and the
+/// bundle of the metadata and the code unless `bundle` is `false`.
 ///
 /// It does so by generating and invoking a temporary workspace member.
 #[allow(clippy::too_many_arguments)]
@@ -113,6 +152,7 @@ pub(crate) fn execute(
     crate_metadata: &CrateMetadata,
     final_contract_wasm: &Path,
     metadata_artifacts: &MetadataArtifacts,
+    bundle: bool,
     features: &Features,
     network: Network,
     verbosity: Verbosity,
@@ -126,6 +166,7 @@ pub(crate) fn execute(
         contract,
         user,
     } = extended_metadata(crate_metadata, final_contract_wasm, build_info)?;
+    let storage_access = storage_access(crate_metadata, verbosity);
 
     let generate_metadata = |manifest_path: &ManifestPath| -> Result<()> {
         maybe_println!(
@@ -159,7 +200,8 @@ pub(crate) fn execute(
 
         let ink_meta: serde_json::Map<String, serde_json::Value> =
             serde_json::from_slice(&stdout)?;
-        let metadata = ContractMetadata::new(source, contract, user, ink_meta);
+        let mut metadata = ContractMetadata::new(source, contract, user, ink_meta);
+        metadata.storage_access = storage_access;
         {
             let mut metadata = metadata.clone();
             metadata.remove_source_wasm_attribute();
@@ -168,6 +210,9 @@ pub(crate) fn execute(
             build_steps.increment_current();
         }
 
+        if !bundle {
+            return Ok(())
+        }
         maybe_println!(
             verbosity,
             " {} {}",
@@ -198,6 +243,36 @@ pub(crate) fn execute(
     Ok(())
 }
 
+/// Assembles the bundle of the metadata and the code of previous builds, without
+/// compiling anything. The metadata must describe the code: the hash of its source must
+/// be the hash of the code.
+pub(crate) fn write_bundle(
+    final_contract_wasm: &Path,
+    metadata_artifacts: &MetadataArtifacts,
+) -> Result<()> {
+    let dest_metadata = &metadata_artifacts.dest_metadata;
+    anyhow::ensure!(
+        final_contract_wasm.exists() && dest_metadata.exists(),
+        "The bundle is assembled from the code and the metadata of a previous build, \
+        generate them first with `--generate code-only` and `--generate metadata-only`"
+    );
+    let mut metadata = ContractMetadata::load(dest_metadata)?;
+    let wasm = fs::read(final_contract_wasm)?;
+    anyhow::ensure!(
+        metadata.source.hash == code_hash(&wasm).into(),
+        "The metadata {} was generated for other code than {}, generate it again with \
+        `--generate metadata-only`",
+        dest_metadata.display(),
+        final_contract_wasm.display()
+    );
+    metadata.source.wasm = Some(SourceWasm::new(wasm));
+    fs::write(
+        &metadata_artifacts.dest_bundle,
+        serde_json::to_string(&metadata)?,
+    )?;
+    Ok(())
+}
+
 /// Generate the extended contract project metadata
 fn extended_metadata(
     crate_metadata: &CrateMetadata,
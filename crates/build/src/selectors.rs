@@ -0,0 +1,266 @@
+// Copyright 2018-2023 Parity Technologies (UK) Ltd.
+// This file is part of cargo-contract.
+//
+// cargo-contract is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// cargo-contract is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with cargo-contract.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Cross-validation of the selectors in the metadata against the dispatch of the Wasm.
+//!
+//! The ink! codegen dispatches a call by comparing the selector of the input against the
+//! selectors of the constructors and messages, which end up as constants in the code of
+//! the contract. A selector of the metadata missing from the code means the metadata does
+//! not describe the contract it was generated for, and calls to it would fail on chain.
+//! Wildcard selectors match any input and are not dispatched by a constant, they are
+//! skipped.
+
+use anyhow::{
+    Context,
+    Result,
+};
+use colored::Colorize;
+use contract_metadata::ContractMetadata;
+use parity_wasm::elements::{
+    Instruction,
+    Module,
+};
+use serde_json::{
+    Map,
+    Value,
+};
+use std::{
+    collections::HashSet,
+    path::Path,
+};
+
+/// A constructor or message of the metadata.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Selector {
+    pub kind: &'static str,
+    pub label: String,
+    pub selector: [u8; 4],
+}
+
+/// The selector notation of wildcard constructors and messages in the metadata.
+const WILDCARD_SELECTOR: &str = "_";
+
+/// The outcome of comparing the selectors of the metadata to the code.
+#[derive(Debug, PartialEq, Eq)]
+pub enum Verification {
+    /// All the selectors are dispatched by the code.
+    Consistent,
+    /// Only some of the selectors were found, the others are not dispatched by a
+    /// constant this check recognizes, e.g. because of codegen it does not know
+    /// about.
+    Partial(Vec<Selector>),
+}
+
+impl Verification {
+    /// A warning listing the selectors which were not found, `None` if all were.
+    pub fn warning(&self) -> Option<String> {
+        match self {
+            Self::Consistent => None,
+            Self::Partial(missing) => {
+                let missing = missing
+                    .iter()
+                    .map(|selector| {
+                        format!(
+                            "0x{} of the {} `{}`",
+                            hex::encode(selector.selector),
+                            selector.kind,
+                            selector.label
+                        )
+                    })
+                    .collect::<Vec<_>>()
+                    .join(", ");
+                Some(format!(
+                    "the selectors {missing} of the metadata could not be located in \
+                    the contract code, their consistency was not verified"
+                ))
+            }
+        }
+    }
+}
+
+/// Check that the selectors of the metadata at `metadata_path` are dispatched by the
+/// Wasm at `wasm_path`, failing if none of them is.
+pub fn verify(wasm_path: &Path, metadata_path: &Path) -> Result<Verification> {
+    let module = parity_wasm::deserialize_file(wasm_path).context(format!(
+        "Loading of wasm module at '{}' failed",
+        wasm_path.display(),
+    ))?;
+    let metadata = ContractMetadata::load(metadata_path)?;
+    let selectors = metadata_selectors(&metadata.abi)?;
+    verify_module(&module, &selectors)
+}
+
+fn verify_module(module: &Module, selectors: &[Selector]) -> Result<Verification> {
+    let constants = code_constants(module);
+    let (found, missing): (Vec<_>, Vec<_>) = selectors.iter().partition(|selector| {
+        constants.contains(&u32::from_le_bytes(selector.selector))
+            || constants.contains(&u32::from_be_bytes(selector.selector))
+    });
+    if missing.is_empty() {
+        return Ok(Verification::Consistent)
+    }
+    if found.is_empty() {
+        anyhow::bail!(
+            "The metadata does not match the contract code.\n\n{}\n\n\
+            This indicates a mismatch of the ink! codegen, the metadata must not be used \
+            with this code. Ignore with `--skip-wasm-validation`",
+            missing
+                .iter()
+                .map(|selector| {
+                    format!(
+                        "{} The selector 0x{} of the {} `{}` is not dispatched by the \
+                        code",
+                        "ERROR:".to_string().bold(),
+                        hex::encode(selector.selector),
+                        selector.kind,
+                        selector.label
+                    )
+                })
+                .collect::<Vec<_>>()
+                .join("\n")
+        )
+    }
+    Ok(Verification::Partial(
+        missing.into_iter().cloned().collect(),
+    ))
+}
+
+/// The constructors and messages of the `abi` section of the contract metadata.
+fn metadata_selectors(abi: &Map<String, Value>) -> Result<Vec<Selector>> {
+    // The spec is nested in a version key in the metadata of ink! 3.
+    let spec = abi
+        .get("spec")
+        .or_else(|| abi.get("V3").and_then(|v3| v3.get("spec")))
+        .context("No contract spec found in the metadata")?;
+    let mut selectors = Vec::new();
+    for (key, kind) in [("constructors", "constructor"), ("messages", "message")] {
+        for item in spec
+            .get(key)
+            .and_then(Value::as_array)
+            .into_iter()
+            .flatten()
+        {
+            let label = item
+                .get("label")
+                .and_then(Value::as_str)
+                .unwrap_or_default()
+                .to_string();
+            let encoded = item
+                .get("selector")
+                .and_then(Value::as_str)
+                .context(format!("No selector for the {kind} `{label}`"))?;
+            if encoded == WILDCARD_SELECTOR {
+                continue
+            }
+            let bytes = hex::decode(encoded.trim_start_matches("0x"))
+                .context(format!("Invalid selector of the {kind} `{label}`"))?;
+            let selector = bytes.try_into().map_err(|_| {
+                anyhow::anyhow!("The selector of the {kind} `{label}` is not 4 bytes")
+            })?;
+            selectors.push(Selector {
+                kind,
+                label,
+                selector,
+            });
+        }
+    }
+    Ok(selectors)
+}
+
+/// All the `i32.const` operands of the function bodies.
+fn code_constants(module: &Module) -> HashSet<u32> {
+    module
+        .code_section()
+        .into_iter()
+        .flat_map(|section| section.bodies())
+        .flat_map(|body| body.code().elements())
+        .filter_map(|instruction| {
+            match instruction {
+                Instruction::I32Const(value) => Some(*value as u32),
+                _ => None,
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn create_module(contract: &str) -> Module {
+        let wasm = wabt::wat2wasm(contract).expect("invalid wabt");
+        parity_wasm::deserialize_buffer(&wasm).expect("deserializing must work")
+    }
+
+    fn selector(kind: &'static str, label: &str, selector: [u8; 4]) -> Selector {
+        Selector {
+            kind,
+            label: label.to_string(),
+            selector,
+        }
+    }
+
+    #[test]
+    fn detects_selectors_missing_from_the_dispatch() {
+        // given
+        let module = create_module(
+            r#"
+            (module
+                (func (export "call") (param i32) (result i32)
+                    (if (result i32) (i32.eq (local.get 0) (i32.const 0x5e9dae9b))
+                        (then (i32.const 1))
+                        (else (i32.eq (local.get 0) (i32.const 0x633aa551))))))"#,
+        );
+        let new = selector("constructor", "new", [0x9b, 0xae, 0x9d, 0x5e]);
+        let flip = selector("message", "flip", [0x63, 0x3a, 0xa5, 0x51]);
+        let get = selector("message", "get", [0x2f, 0x86, 0x5b, 0xd9]);
+
+        // then
+        assert_eq!(
+            verify_module(&module, &[new.clone(), flip.clone()]).unwrap(),
+            Verification::Consistent
+        );
+        let verification = verify_module(&module, &[new, flip, get.clone()]).unwrap();
+        assert_eq!(verification, Verification::Partial(vec![get.clone()]));
+        assert!(verification
+            .warning()
+            .unwrap()
+            .contains("0x2f865bd9 of the message `get`"));
+        let err = verify_module(&module, &[get]).unwrap_err();
+        assert!(err.to_string().contains("0x2f865bd9 of the message `get`"));
+    }
+
+    #[test]
+    fn reads_selectors_of_the_spec() {
+        let abi = serde_json::json!({
+            "spec": {
+                "constructors": [{ "label": "new", "selector": "0x9bae9d5e" }],
+                "messages": [
+                    { "label": "flip", "selector": "0x633aa551" },
+                    { "label": "fallback", "selector": "_" },
+                ],
+            }
+        });
+        let selectors = metadata_selectors(abi.as_object().unwrap()).unwrap();
+        assert_eq!(
+            selectors,
+            [
+                selector("constructor", "new", [0x9b, 0xae, 0x9d, 0x5e]),
+                selector("message", "flip", [0x63, 0x3a, 0xa5, 0x51]),
+            ]
+        );
+    }
+}
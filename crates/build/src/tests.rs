@@ -79,7 +79,8 @@ build_tests!(
     missing_cargo_dylint_installation_must_be_detected,
     generates_metadata,
     unchanged_contract_skips_optimization_and_metadata_steps,
-    unchanged_contract_no_metadata_artifacts_generates_metadata
+    unchanged_contract_no_metadata_artifacts_generates_metadata,
+    metadata_only_and_bundle_only_generate_their_artifacts
 );
 
 fn build_code_only(manifest_path: &ManifestPath) -> Result<()> {
@@ -168,6 +169,7 @@ fn optimization_passes_from_cli_must_take_precedence_over_profile(
         output_type: OutputType::Json,
         skip_wasm_validation: false,
         target: Default::default(),
+        compress: false,
     };
 
     // when
@@ -210,6 +212,7 @@ fn optimization_passes_from_profile_must_be_used(
         output_type: OutputType::Json,
         skip_wasm_validation: false,
         target: Default::default(),
+        compress: false,
     };
 
     // when
@@ -626,6 +629,45 @@ fn unchanged_contract_no_metadata_artifacts_generates_metadata(
     Ok(())
 }
 
+fn metadata_only_and_bundle_only_generate_their_artifacts(
+    manifest_path: &ManifestPath,
+) -> Result<()> {
+    let build = |build_artifact| {
+        super::execute(ExecuteArgs {
+            manifest_path: manifest_path.clone(),
+            build_artifact,
+            ..Default::default()
+        })
+    };
+    let res1 = build(BuildArtifacts::All).expect("build failed");
+    let metadata_result = res1.metadata_result.unwrap();
+    let metadata_modified1 = file_last_modified(&metadata_result.dest_metadata);
+
+    // the metadata is generated again for the unchanged code, the bundle is stale
+    let res2 = build(BuildArtifacts::MetadataOnly).expect("build failed");
+    assert!(res2.optimization_result.is_none());
+    let metadata_modified2 = file_last_modified(&metadata_result.dest_metadata);
+    assert!(metadata_modified2 > metadata_modified1);
+    assert!(!metadata_result.dest_bundle.exists());
+
+    // the bundle is assembled from the code and the metadata
+    let res3 = build(BuildArtifacts::BundleOnly).expect("build failed");
+    assert!(res3.optimization_result.is_none());
+    let bundle = ContractMetadata::load(&metadata_result.dest_bundle)?;
+    let wasm = fs::read(res3.dest_wasm.unwrap())?;
+    assert_eq!(bundle.source.wasm.unwrap().0, wasm);
+    assert_eq!(
+        file_last_modified(&metadata_result.dest_metadata),
+        metadata_modified2
+    );
+
+    // a bundle needs the metadata of the code
+    fs::remove_file(&metadata_result.dest_metadata)?;
+    assert!(build(BuildArtifacts::BundleOnly).is_err());
+
+    Ok(())
+}
+
 /// Get the last modified date of the given file.
 /// Panics if the file does not exist.
 fn file_last_modified(path: &Path) -> SystemTime {
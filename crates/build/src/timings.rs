@@ -0,0 +1,138 @@
+// Copyright 2018-2023 Parity Technologies (UK) Ltd.
+// This file is part of cargo-contract.
+//
+// cargo-contract is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// cargo-contract is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with cargo-contract.  If not, see <http://www.gnu.org/licenses/>.
+
+use anyhow::Result;
+use std::{
+    fmt::Write,
+    fs,
+    path::Path,
+    time::Instant,
+};
+
+/// The name of the build log written to the target directory.
+pub const BUILD_LOG_FILE: &str = "build-log.json";
+
+/// A phase of the build.
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct BuildPhase {
+    /// The name of the phase, e.g. `cargo build` or `wasm-opt`.
+    pub name: String,
+    /// How long the phase took, in seconds.
+    pub duration_secs: f64,
+    /// The size of the output of the phase in bytes, if it produces one.
+    pub output_size: Option<u64>,
+}
+
+/// The machine-readable log of a build: the timings and output sizes of its phases, and
+/// the warnings raised.
+#[derive(Debug, Default, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct BuildLog {
+    /// The phases in the order they ran.
+    pub phases: Vec<BuildPhase>,
+    /// The warnings raised during the build.
+    pub warnings: Vec<String>,
+}
+
+impl BuildLog {
+    /// Record a phase which started at `started` and ended now, with the size of the
+    /// file it produced, if any.
+    pub fn record(&mut self, name: &str, started: Instant, output: Option<&Path>) {
+        self.phases.push(BuildPhase {
+            name: name.to_string(),
+            duration_secs: started.elapsed().as_secs_f64(),
+            output_size: output
+                .and_then(|path| fs::metadata(path).ok())
+                .map(|metadata| metadata.len()),
+        })
+    }
+
+    /// Record a warning raised during the build.
+    pub fn warn(&mut self, warning: impl Into<String>) {
+        self.warnings.push(warning.into())
+    }
+
+    /// Returns the total duration of all phases, in seconds.
+    pub fn total_secs(&self) -> f64 {
+        self.phases.iter().map(|phase| phase.duration_secs).sum()
+    }
+
+    /// Write the log as JSON into the directory.
+    pub fn write(&self, dir: &Path) -> Result<()> {
+        fs::create_dir_all(dir)?;
+        fs::write(
+            dir.join(BUILD_LOG_FILE),
+            serde_json::to_string_pretty(self)?,
+        )?;
+        Ok(())
+    }
+
+    /// Display the timings and output sizes of the phases as a table.
+    pub fn display_timings(&self) -> String {
+        let total = self.total_secs();
+        let mut out =
+            format!("{:<16} {:>10} {:>7} {:>12}\n", "Phase", "Time", "%", "Size");
+        for phase in &self.phases {
+            let share = if total > 0.0 {
+                phase.duration_secs / total * 100.0
+            } else {
+                0.0
+            };
+            let size = phase
+                .output_size
+                .map(|size| format!("{:.1}K", size as f64 / 1000.0))
+                .unwrap_or_default();
+            let _ = writeln!(
+                out,
+                "{:<16} {:>9.2}s {:>6.1}% {:>12}",
+                phase.name, phase.duration_secs, share, size
+            );
+        }
+        let _ = write!(out, "{:<16} {:>9.2}s", "Total", total);
+        for warning in &self.warnings {
+            let _ = write!(out, "\nwarning: {warning}");
+        }
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn timings_are_displayed_with_their_share() {
+        let log = BuildLog {
+            phases: vec![
+                BuildPhase {
+                    name: String::from("cargo build"),
+                    duration_secs: 3.0,
+                    output_size: Some(64_000),
+                },
+                BuildPhase {
+                    name: String::from("metadata"),
+                    duration_secs: 1.0,
+                    output_size: None,
+                },
+            ],
+            warnings: vec![String::from("wasm validation skipped")],
+        };
+        assert_eq!(log.total_secs(), 4.0);
+        let table = log.display_timings();
+        assert!(table.contains("cargo build           3.00s   75.0%        64.0K"));
+        assert!(table.contains("metadata              1.00s   25.0%"));
+        assert!(table.ends_with("warning: wasm validation skipped"));
+    }
+}
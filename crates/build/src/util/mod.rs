@@ -28,8 +28,8 @@ use std::{
     process::Command,
 };
 
-// Returns the current Rust toolchain formatted by `<channel>-<target-triple>`.
-pub(crate) fn rust_toolchain() -> Result<String> {
+/// Returns the current Rust toolchain formatted by `<channel>-<target-triple>`.
+pub fn rust_toolchain() -> Result<String> {
     let meta = rustc_version::version_meta()?;
     let toolchain = format!("{:?}-{}", meta.channel, meta.host,).to_lowercase();
 
@@ -120,12 +120,15 @@ pub fn decode_hex(input: &str) -> Result<Vec<u8>, hex::FromHexError> {
     hex::decode(input.trim_start_matches("0x"))
 }
 
-/// Prints to stdout if `verbosity.is_verbose()` is `true`.
+/// Prints to stderr if `verbosity.is_verbose()` is `true`.
+///
+/// Progress messages are printed to stderr so that they are kept apart from the output
+/// printed to stdout.
 #[macro_export]
 macro_rules! maybe_println {
     ($verbosity:expr, $($msg:tt)*) => {
         if $verbosity.is_verbose() {
-            ::std::println!($($msg)*);
+            ::std::eprintln!($($msg)*);
         }
     };
 }
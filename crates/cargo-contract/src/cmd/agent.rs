@@ -0,0 +1,608 @@
+// Copyright 2018-2023 Parity Technologies (UK) Ltd.
+// This file is part of cargo-contract.
+//
+// cargo-contract is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// cargo-contract is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with cargo-contract.  If not, see <http://www.gnu.org/licenses/>.
+
+//! An agent holding the unlocked keys of keystore files for a session.
+//!
+//! `agent start` runs in the foreground, like `ssh-agent -D`, and listens on a Unix
+//! socket in a directory only the user may access: `$CARGO_CONTRACT_AGENT_SOCK`, or
+//! `agent.sock` in the `cargo-contract-agent-<user>` directory of `$XDG_RUNTIME_DIR` or
+//! the temporary directory.
+//! Once a keystore file was unlocked with its password, by `agent add <PATH>` or by any
+//! command signing with `--signer keystore:<PATH>`, later commands take its key from the
+//! agent instead of prompting for the password, until the key expires after its
+//! lifetime or `agent lock` drops all keys.
+//!
+//! The keys are only held in memory, and are wiped from it when they expire or are
+//! dropped. The agent never hands out a key, it signs the payloads of the commands
+//! itself. Secret key URIs of `--suri` are not cached.
+
+use super::{
+    extrinsics::unlock_keystore,
+    AsyncCommand,
+};
+use anyhow::{
+    Context,
+    Result,
+};
+use async_trait::async_trait;
+use colored::Colorize;
+use sp_core::{
+    crypto::Ss58Codec,
+    sr25519,
+    Pair,
+};
+use std::{
+    collections::BTreeMap,
+    io::{
+        BufRead,
+        BufReader,
+        Write,
+    },
+    path::{
+        Path,
+        PathBuf,
+    },
+    time::{
+        Duration,
+        Instant,
+    },
+};
+use zeroize::Zeroizing;
+
+/// The environment variable overriding the path of the socket of the agent.
+pub const AGENT_SOCK_ENV: &str = "CARGO_CONTRACT_AGENT_SOCK";
+
+/// How long an unlocked key is held by default.
+const DEFAULT_LIFETIME: &str = "1h";
+
+/// The longest lifetime of a key.
+const MAX_LIFETIME: Duration = Duration::from_secs(7 * 24 * 3_600);
+
+/// How long to wait for the agent to answer a request, and for a client to send its
+/// request.
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// The maximum size of a request, which holds at most a key or a payload.
+const MAX_REQUEST_BYTES: u64 = 64 * 1024;
+
+#[derive(Debug, clap::Args)]
+#[clap(
+    name = "agent",
+    about = "Hold unlocked keystore keys in memory for a session"
+)]
+pub struct AgentCommand {
+    #[clap(subcommand)]
+    action: AgentAction,
+}
+
+#[derive(Debug, clap::Subcommand)]
+enum AgentAction {
+    /// Run the agent in the foreground until interrupted
+    Start {
+        /// How long unlocked keys are held, e.g. `30m` or `8h`.
+        #[clap(long, default_value = DEFAULT_LIFETIME, value_parser = parse_lifetime)]
+        lifetime: Duration,
+    },
+    /// Unlock a keystore file and hand its key to the agent
+    Add {
+        /// The keystore file, as given to `--signer keystore:<PATH>`.
+        keystore: PathBuf,
+        /// Password of the keystore, prompted for unless given.
+        #[clap(long, short)]
+        password: Option<String>,
+        /// How long the key is held, by default the lifetime of the agent.
+        #[clap(long, value_parser = parse_lifetime)]
+        lifetime: Option<Duration>,
+    },
+    /// List the keys held by the agent
+    List,
+    /// Drop all keys held by the agent
+    Lock,
+}
+
+#[async_trait(?Send)]
+impl AsyncCommand for AgentCommand {
+    type Output = Result<()>;
+
+    async fn exec(&self) -> Result<()> {
+        match self.action {
+            AgentAction::Start { lifetime } => {
+                let path = socket_path();
+                let agent = Agent::new(lifetime);
+                tokio::task::spawn_blocking(move || serve(&path, agent)).await?
+            }
+            AgentAction::Add {
+                ref keystore,
+                ref password,
+                lifetime,
+            } => {
+                let pair = unlock_keystore(keystore, password.as_deref())?;
+                request(&Request::Add {
+                    keystore: canonical(keystore)?,
+                    secret: Zeroizing::new(hex::encode(pair.to_raw_vec())),
+                    lifetime_secs: lifetime.map(|lifetime| lifetime.as_secs()),
+                })?;
+                println!(
+                    "{} the key {} of {}",
+                    "Added".green().bold(),
+                    pair.public().to_ss58check(),
+                    keystore.display()
+                );
+                Ok(())
+            }
+            AgentAction::List => {
+                let keys = request(&Request::List)?.keys;
+                if keys.is_empty() {
+                    println!("The agent holds no keys");
+                }
+                for key in keys {
+                    println!(
+                        "{} {} expires in {}s",
+                        key.account,
+                        key.keystore.display(),
+                        key.expires_in_secs
+                    );
+                }
+                Ok(())
+            }
+            AgentAction::Lock => {
+                request(&Request::Lock)?;
+                println!(
+                    "{} the agent, all keys were dropped",
+                    "Locked".green().bold()
+                );
+                Ok(())
+            }
+        }
+    }
+}
+
+/// A key held by a running agent, which signs payloads with it.
+#[derive(Debug, Clone)]
+pub struct AgentKey {
+    public: sr25519::Public,
+}
+
+impl AgentKey {
+    /// The public key.
+    pub fn public(&self) -> sr25519::Public {
+        self.public
+    }
+
+    /// Sign the payload with the key in the agent.
+    pub fn sign(&self, payload: &[u8]) -> Result<sr25519::Signature> {
+        let signature = request(&Request::Sign {
+            account: self.public.to_ss58check(),
+            payload: hex::encode(payload),
+        })?
+        .signature
+        .ok_or_else(|| anyhow::anyhow!("The agent returned no signature"))?;
+        let signature = <[u8; 64]>::try_from(hex::decode(signature)?)
+            .map_err(|_| anyhow::anyhow!("The agent returned an invalid signature"))?;
+        Ok(sr25519::Signature::from_raw(signature))
+    }
+}
+
+/// The key of the keystore file held by a running agent, `None` if no agent is running
+/// or it does not hold the key.
+pub fn cached_key(keystore: &Path) -> Option<AgentKey> {
+    let keystore = canonical(keystore).ok()?;
+    let key = request(&Request::List)
+        .ok()?
+        .keys
+        .into_iter()
+        .find(|key| key.keystore == keystore)?;
+    let public = sr25519::Public::from_ss58check(&key.account).ok()?;
+    Some(AgentKey { public })
+}
+
+/// Hand the unlocked key of the keystore file to the agent, if one is running.
+pub fn offer_key(keystore: &Path, pair: &sr25519::Pair) {
+    let Ok(keystore) = canonical(keystore) else {
+        return
+    };
+    let offered = request(&Request::Add {
+        keystore,
+        secret: Zeroizing::new(hex::encode(pair.to_raw_vec())),
+        lifetime_secs: None,
+    });
+    if let Err(err) = offered {
+        tracing::debug!("The key was not handed to an agent: {err:#}");
+    }
+}
+
+/// The path of the socket of the agent.
+pub fn socket_path() -> PathBuf {
+    if let Some(path) = std::env::var_os(AGENT_SOCK_ENV) {
+        return PathBuf::from(path)
+    }
+    let dir = std::env::var_os("XDG_RUNTIME_DIR")
+        .map(PathBuf::from)
+        .unwrap_or_else(std::env::temp_dir);
+    let user = std::env::var("USER").unwrap_or_else(|_| String::from("default"));
+    dir.join(format!("cargo-contract-agent-{user}"))
+        .join("agent.sock")
+}
+
+/// The keystore files are identified by their canonical path.
+fn canonical(keystore: &Path) -> Result<PathBuf> {
+    keystore.canonicalize().with_context(|| {
+        format!("Failed to read the keystore file {}", keystore.display())
+    })
+}
+
+/// Parse a lifetime, a number of seconds optionally followed by `s`, `m` or `h`.
+fn parse_lifetime(input: &str) -> Result<Duration> {
+    let (number, factor) = match input.char_indices().last() {
+        Some((index, 's')) => (&input[..index], 1),
+        Some((index, 'm')) => (&input[..index], 60),
+        Some((index, 'h')) => (&input[..index], 3_600),
+        _ => (input, 1),
+    };
+    let number: u64 = number.parse().map_err(|_| {
+        anyhow::anyhow!("Invalid lifetime `{input}`, expected e.g. `900`, `30m` or `8h`")
+    })?;
+    anyhow::ensure!(number > 0, "The lifetime must not be zero");
+    let lifetime = number
+        .checked_mul(factor)
+        .map(Duration::from_secs)
+        .filter(|lifetime| *lifetime <= MAX_LIFETIME);
+    lifetime.ok_or_else(|| {
+        anyhow::anyhow!(
+            "The lifetime `{input}` exceeds the maximum of {}h",
+            MAX_LIFETIME.as_secs() / 3_600
+        )
+    })
+}
+
+/// A request to the agent, sent as a line of JSON.
+#[derive(Debug, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "snake_case", tag = "request")]
+enum Request {
+    /// Hold the hex encoded secret key of the keystore file.
+    Add {
+        keystore: PathBuf,
+        secret: Zeroizing<String>,
+        lifetime_secs: Option<u64>,
+    },
+    /// Sign the hex encoded payload with the key of the account.
+    Sign {
+        account: String,
+        payload: String,
+    },
+    List,
+    Lock,
+}
+
+/// The answer of the agent, sent as a line of JSON.
+#[derive(Debug, Default, serde::Serialize, serde::Deserialize)]
+struct Response {
+    /// The hex encoded signature of a `sign` request.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    signature: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    keys: Vec<KeyInfo>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+}
+
+/// A key held by the agent.
+#[derive(Debug, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+struct KeyInfo {
+    keystore: PathBuf,
+    account: String,
+    expires_in_secs: u64,
+}
+
+/// The keys held by the agent.
+struct Agent {
+    lifetime: Duration,
+    keys: BTreeMap<PathBuf, HeldKey>,
+}
+
+/// A secret key, wiped from memory when it is dropped.
+struct HeldKey {
+    account: String,
+    secret: Zeroizing<Vec<u8>>,
+    expires: Instant,
+}
+
+impl Agent {
+    fn new(lifetime: Duration) -> Self {
+        Self {
+            lifetime,
+            keys: BTreeMap::new(),
+        }
+    }
+
+    /// Answer a request, after dropping the expired keys.
+    fn handle(&mut self, request: Request, now: Instant) -> Result<Response> {
+        self.keys.retain(|_, key| key.expires > now);
+        let mut response = Response::default();
+        match request {
+            Request::Add {
+                keystore,
+                secret,
+                lifetime_secs,
+            } => {
+                let secret = Zeroizing::new(
+                    hex::decode(secret.as_str()).context("Invalid hex secret")?,
+                );
+                let pair = sr25519::Pair::from_seed_slice(&secret)
+                    .map_err(|_| anyhow::anyhow!("Invalid secret key"))?;
+                let lifetime = lifetime_secs.map_or(self.lifetime, Duration::from_secs);
+                anyhow::ensure!(
+                    lifetime <= MAX_LIFETIME,
+                    "The lifetime exceeds the maximum of {}s",
+                    MAX_LIFETIME.as_secs()
+                );
+                let expires = now
+                    .checked_add(lifetime)
+                    .ok_or_else(|| anyhow::anyhow!("The lifetime is too long"))?;
+                let key = HeldKey {
+                    account: pair.public().to_ss58check(),
+                    secret,
+                    expires,
+                };
+                self.keys.insert(keystore, key);
+            }
+            Request::Sign { account, payload } => {
+                let key = self
+                    .keys
+                    .values()
+                    .find(|key| key.account == account)
+                    .ok_or_else(|| {
+                        anyhow::anyhow!("The agent holds no key of the account {account}")
+                    })?;
+                let payload = hex::decode(payload).context("Invalid hex payload")?;
+                let pair = sr25519::Pair::from_seed_slice(&key.secret)
+                    .map_err(|_| anyhow::anyhow!("Invalid secret key"))?;
+                response.signature = Some(hex::encode(pair.sign(&payload)));
+            }
+            Request::List => {
+                response.keys = self
+                    .keys
+                    .iter()
+                    .map(|(keystore, key)| {
+                        KeyInfo {
+                            keystore: keystore.clone(),
+                            account: key.account.clone(),
+                            expires_in_secs: (key.expires - now).as_secs(),
+                        }
+                    })
+                    .collect();
+            }
+            Request::Lock => self.keys.clear(),
+        }
+        Ok(response)
+    }
+
+    /// Answer the request of a connection.
+    fn answer<S>(&mut self, stream: S) -> Result<()>
+    where
+        for<'a> &'a S: std::io::Read + Write,
+    {
+        let mut line = Zeroizing::new(String::new());
+        BufReader::new(std::io::Read::take(&stream, MAX_REQUEST_BYTES))
+            .read_line(&mut line)?;
+        let response = serde_json::from_str(&line)
+            .map_err(anyhow::Error::from)
+            .and_then(|request| self.handle(request, Instant::now()))
+            .unwrap_or_else(|err| {
+                Response {
+                    error: Some(format!("{err:#}")),
+                    ..Default::default()
+                }
+            });
+        let mut writer = &stream;
+        serde_json::to_writer(&mut writer, &response)?;
+        writer.write_all(b"\n")?;
+        Ok(())
+    }
+}
+
+/// Send a request to the running agent.
+#[cfg(unix)]
+fn request(request: &Request) -> Result<Response> {
+    let path = socket_path();
+    let stream = std::os::unix::net::UnixStream::connect(&path)
+        .with_context(|| format!("No agent is running at {}", path.display()))?;
+    stream.set_read_timeout(Some(REQUEST_TIMEOUT))?;
+    let mut writer = &stream;
+    serde_json::to_writer(&mut writer, request)?;
+    writer.write_all(b"\n")?;
+    let mut line = Zeroizing::new(String::new());
+    BufReader::new(&stream).read_line(&mut line)?;
+    let response: Response = serde_json::from_str(&line)?;
+    if let Some(error) = response.error {
+        anyhow::bail!("The agent failed: {error}")
+    }
+    Ok(response)
+}
+
+#[cfg(not(unix))]
+fn request(_: &Request) -> Result<Response> {
+    anyhow::bail!("The agent is only supported on Unix")
+}
+
+/// Answer requests on the socket until the process is interrupted.
+#[cfg(unix)]
+fn serve(path: &Path, mut agent: Agent) -> Result<()> {
+    use std::os::unix::{
+        fs::MetadataExt,
+        net::UnixListener,
+    };
+    let dir = path
+        .parent()
+        .filter(|dir| !dir.as_os_str().is_empty())
+        .unwrap_or(Path::new("."));
+    ensure_private_dir(dir)?;
+    if path.exists() {
+        anyhow::ensure!(
+            request(&Request::List).is_err(),
+            "An agent is already running at {}",
+            path.display()
+        );
+        std::fs::remove_file(path)?;
+    }
+    let listener = UnixListener::bind(path)
+        .with_context(|| format!("Failed to listen on {}", path.display()))?;
+    // The socket is created by this process, so a directory owned by another user,
+    // which this process may only access as the superuser, is detected by its owner.
+    if std::fs::symlink_metadata(dir)?.uid() != std::fs::symlink_metadata(path)?.uid() {
+        std::fs::remove_file(path)?;
+        anyhow::bail!("The directory {} is owned by another user", dir.display())
+    }
+    println!(
+        "{} on {}, set {AGENT_SOCK_ENV} to it in other shells if they have another \
+        temporary directory",
+        "Agent listening".green().bold(),
+        path.display()
+    );
+    for stream in listener.incoming() {
+        // a client which does not send its request in time must not block the others
+        let answered = stream.map_err(anyhow::Error::from).and_then(|stream| {
+            stream.set_read_timeout(Some(REQUEST_TIMEOUT))?;
+            stream.set_write_timeout(Some(REQUEST_TIMEOUT))?;
+            agent.answer(stream)
+        });
+        if let Err(err) = answered {
+            tracing::debug!("Failed to answer a request: {err:#}");
+        }
+    }
+    Ok(())
+}
+
+/// Create the directory of the socket, accessible only by the user, or make sure that
+/// an existing directory is, so that no other user may connect to the socket.
+#[cfg(unix)]
+fn ensure_private_dir(dir: &Path) -> Result<()> {
+    use std::os::unix::fs::{
+        DirBuilderExt,
+        PermissionsExt,
+    };
+    match std::fs::DirBuilder::new().mode(0o700).create(dir) {
+        Ok(()) => return Ok(()),
+        Err(err) if err.kind() == std::io::ErrorKind::AlreadyExists => {}
+        Err(err) => {
+            return Err(err).with_context(|| format!("Failed to create {}", dir.display()))
+        }
+    }
+    let metadata = std::fs::symlink_metadata(dir)?;
+    anyhow::ensure!(
+        metadata.is_dir() && metadata.permissions().mode() & 0o077 == 0,
+        "The socket of the agent must be in a directory only the user may access, \
+        {} is not",
+        dir.display()
+    );
+    Ok(())
+}
+
+#[cfg(not(unix))]
+fn serve(_: &Path, _: Agent) -> Result<()> {
+    anyhow::bail!("The agent is only supported on Unix")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn add(keystore: &str, pair: &sr25519::Pair, lifetime_secs: Option<u64>) -> Request {
+        Request::Add {
+            keystore: PathBuf::from(keystore),
+            secret: Zeroizing::new(hex::encode(pair.to_raw_vec())),
+            lifetime_secs,
+        }
+    }
+
+    fn sign(pair: &sr25519::Pair, payload: &[u8]) -> Request {
+        Request::Sign {
+            account: pair.public().to_ss58check(),
+            payload: hex::encode(payload),
+        }
+    }
+
+    #[test]
+    fn keys_are_held_until_they_expire() {
+        let pair = sr25519::Pair::from_string("//Alice", None).unwrap();
+        let bob = sr25519::Pair::from_string("//Bob", None).unwrap();
+        let mut agent = Agent::new(Duration::from_secs(60));
+        let now = Instant::now();
+        agent.handle(add("/keys/alice", &pair, None), now).unwrap();
+        agent.handle(add("/keys/bob", &bob, Some(10)), now).unwrap();
+
+        let response = agent.handle(sign(&pair, b"payload"), now).unwrap();
+        let signature = hex::decode(response.signature.unwrap()).unwrap();
+        let signature = sr25519::Signature::from_raw(signature.try_into().unwrap());
+        assert!(sr25519::Pair::verify(
+            &signature,
+            b"payload",
+            &pair.public()
+        ));
+
+        let later = now + Duration::from_secs(30);
+        let keys = agent.handle(Request::List, later).unwrap().keys;
+        assert_eq!(
+            keys,
+            vec![KeyInfo {
+                keystore: PathBuf::from("/keys/alice"),
+                account: pair.public().to_ss58check(),
+                expires_in_secs: 30,
+            }]
+        );
+        assert!(agent.handle(sign(&bob, b"payload"), later).is_err());
+        agent.handle(Request::Lock, later).unwrap();
+        assert!(agent.handle(sign(&pair, b"payload"), later).is_err());
+    }
+
+    #[test]
+    fn lifetimes_are_limited() {
+        let pair = sr25519::Pair::from_string("//Alice", None).unwrap();
+        let mut agent = Agent::new(Duration::from_secs(60));
+        let now = Instant::now();
+        assert!(agent
+            .handle(add("/keys/alice", &pair, Some(u64::MAX)), now)
+            .is_err());
+        assert!(agent.handle(Request::List, now).unwrap().keys.is_empty());
+        assert!(parse_lifetime(&format!("{}h", u64::MAX)).is_err());
+        assert!(parse_lifetime("169h").is_err());
+        assert!(parse_lifetime("168h").is_ok());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn sockets_are_in_private_directories() {
+        use std::os::unix::fs::PermissionsExt;
+        let tmp = tempfile::tempdir().unwrap();
+        let dir = tmp.path().join("agent");
+        ensure_private_dir(&dir).unwrap();
+        let mode = std::fs::metadata(&dir).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o700);
+        ensure_private_dir(&dir).unwrap();
+
+        std::fs::set_permissions(&dir, std::fs::Permissions::from_mode(0o755)).unwrap();
+        assert!(ensure_private_dir(&dir).is_err());
+    }
+
+    #[test]
+    fn lifetimes_are_parsed() {
+        assert_eq!(parse_lifetime("900").unwrap(), Duration::from_secs(900));
+        assert_eq!(parse_lifetime("30m").unwrap(), Duration::from_secs(1_800));
+        assert_eq!(parse_lifetime("8h").unwrap(), Duration::from_secs(28_800));
+        assert!(parse_lifetime("0s").is_err());
+        assert!(parse_lifetime("1d").is_err());
+    }
+}
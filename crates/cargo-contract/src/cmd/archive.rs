@@ -0,0 +1,236 @@
+// Copyright 2018-2023 Parity Technologies (UK) Ltd.
+// This file is part of cargo-contract.
+//
+// cargo-contract is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// cargo-contract is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with cargo-contract.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Reads of the state of past blocks, falling back to an archive node.
+//!
+//! Full nodes prune the state of old blocks, so that a query at such a block fails. The
+//! query is then run again against the archive node of `--archive-url`, or of the
+//! `archive-url` of the network profile whose `url` is the node queried:
+//!
+//! ```toml
+//! [network.production]
+//! url = "wss://rpc.example.com"
+//! archive-url = "wss://archive.example.com"
+//! ```
+//!
+//! Errors of the connection to a node are retried with `--retries` before giving up.
+
+use super::{
+    config::{
+        Config,
+        CONFIG_FILE_NAME,
+    },
+    extrinsics::url_to_string,
+    Client,
+};
+use anyhow::{
+    Context,
+    Result,
+};
+use std::{
+    fmt,
+    future::Future,
+    time::Duration,
+};
+
+/// The messages of the errors of a node queried at a block it pruned the state of.
+const PRUNED_STATE_ERRORS: [&str; 2] = ["State already discarded", "UnknownBlock"];
+
+/// The delay before retrying a failed request, multiplied by the number of the attempt.
+const RETRY_DELAY: Duration = Duration::from_secs(1);
+
+/// Options for reading the state of past blocks.
+#[derive(Debug, Clone, clap::Args)]
+pub struct ArchiveOpts {
+    /// Websockets url of an archive node, queried if the node pruned the state of the
+    /// block. Defaults to the `archive-url` of the network profile of the node.
+    #[clap(long, value_name = "URL")]
+    archive_url: Option<url::Url>,
+    /// The number of times a request failing with a connection error is retried.
+    #[clap(long, default_value_t = 2)]
+    retries: u32,
+}
+
+impl Default for ArchiveOpts {
+    fn default() -> Self {
+        Self {
+            archive_url: None,
+            retries: 2,
+        }
+    }
+}
+
+/// The node the state was read from.
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize)]
+#[serde(rename_all = "snake_case", tag = "kind", content = "url")]
+pub enum DataSource {
+    /// The node of `--url`.
+    Node(String),
+    /// The archive node, since the node pruned the state of the block.
+    Archive(String),
+}
+
+/// A value read from the state, labelled with the node it was read from.
+#[derive(Debug, serde::Serialize)]
+pub struct Sourced<T> {
+    #[serde(flatten)]
+    pub value: T,
+    pub source: DataSource,
+}
+
+impl DataSource {
+    /// Returns `true` if the state was read from the archive node.
+    pub fn is_archive(&self) -> bool {
+        matches!(self, Self::Archive(_))
+    }
+}
+
+impl fmt::Display for DataSource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Node(url) => write!(f, "node {url}"),
+            Self::Archive(url) => write!(f, "archive node {url}"),
+        }
+    }
+}
+
+impl ArchiveOpts {
+    /// Returns the archive node of `--archive-url` or of the network profile of the node.
+    fn archive_url(&self, url: &url::Url) -> Result<Option<url::Url>> {
+        if let Some(ref archive_url) = self.archive_url {
+            return Ok(Some(archive_url.clone()))
+        }
+        Ok(Config::load()?.archive_url(url).cloned())
+    }
+
+    /// Read the state with `fetch` from the node, or from the archive node if the node
+    /// pruned the state of the block.
+    pub async fn fetch<T, F, Fut>(&self, url: &url::Url, fetch: F) -> Result<Sourced<T>>
+    where
+        F: Fn(Client) -> Fut,
+        Fut: Future<Output = Result<T>>,
+    {
+        let node = url_to_string(url);
+        let err = match self.fetch_from(&node, &fetch).await {
+            Ok(value) => {
+                return Ok(Sourced {
+                    value,
+                    source: DataSource::Node(node),
+                })
+            }
+            Err(err) if is_pruned_state(&err) => err,
+            Err(err) => return Err(err),
+        };
+        let Some(archive_url) = self.archive_url(url)? else {
+            return Err(err.context(format!(
+                "The node {node} pruned the state of the block, configure an archive \
+                node with `--archive-url` or the `archive-url` of the network profile \
+                in `{CONFIG_FILE_NAME}`"
+            )))
+        };
+        let archive = url_to_string(&archive_url);
+        tracing::debug!("The node {node} pruned the state, reading it from {archive}");
+        let value = self.fetch_from(&archive, &fetch).await.with_context(|| {
+            format!("Failed to read the state from the archive node {archive}")
+        })?;
+        Ok(Sourced {
+            value,
+            source: DataSource::Archive(archive),
+        })
+    }
+
+    /// Read the state from the node, retrying connection errors.
+    async fn fetch_from<T, F, Fut>(&self, url: &str, fetch: &F) -> Result<T>
+    where
+        F: Fn(Client) -> Fut,
+        Fut: Future<Output = Result<T>>,
+    {
+        let mut attempt = 0;
+        loop {
+            let result = match Client::from_url(url).await {
+                Ok(client) => fetch(client).await,
+                Err(err) => Err(err.into()),
+            };
+            match result {
+                Err(err) if attempt < self.retries && is_connection_error(&err) => {
+                    attempt += 1;
+                    tracing::debug!("Retrying the request to {url} ({attempt}): {err:#}");
+                    tokio::time::sleep(RETRY_DELAY * attempt).await;
+                }
+                result => return result,
+            }
+        }
+    }
+}
+
+/// Returns `true` if the node failed to answer since it pruned the state of the block.
+pub fn is_pruned_state(err: &anyhow::Error) -> bool {
+    let message = format!("{err:#}");
+    PRUNED_STATE_ERRORS
+        .iter()
+        .any(|pruned| message.contains(pruned))
+}
+
+/// Returns `true` if the request failed to reach the node, rather than being answered
+/// with an error.
+fn is_connection_error(err: &anyhow::Error) -> bool {
+    !is_pruned_state(err)
+        && err.chain().any(|err| {
+            matches!(
+                err.downcast_ref::<subxt::Error>(),
+                Some(subxt::Error::Rpc(subxt::error::RpcError::ClientError(_)))
+                    | Some(subxt::Error::Rpc(
+                        subxt::error::RpcError::SubscriptionDropped
+                    ))
+            )
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pruned_state_errors_are_detected() {
+        let pruned = anyhow::anyhow!(
+            "Client error: UnknownBlock: State already discarded for \
+            BlockId::Hash(0x1234)"
+        );
+        assert!(is_pruned_state(&pruned));
+        let pruned = pruned.context("Failed to fetch the proof");
+        assert!(is_pruned_state(&pruned));
+        assert!(!is_connection_error(&pruned));
+
+        let not_found = anyhow::anyhow!("No contract was found");
+        assert!(!is_pruned_state(&not_found));
+        assert!(!is_connection_error(&not_found));
+    }
+
+    #[test]
+    fn data_sources_are_labelled() {
+        let archive = DataSource::Archive(String::from("wss://archive.example.com:443"));
+        assert!(archive.is_archive());
+        assert_eq!(
+            archive.to_string(),
+            "archive node wss://archive.example.com:443"
+        );
+        let json = serde_json::json!({
+            "kind": "archive",
+            "url": "wss://archive.example.com:443",
+        });
+        assert_eq!(serde_json::to_value(&archive).unwrap(), json);
+    }
+}
@@ -0,0 +1,572 @@
+// Copyright 2018-2023 Parity Technologies (UK) Ltd.
+// This file is part of cargo-contract.
+//
+// cargo-contract is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// cargo-contract is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with cargo-contract.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Signed bundles of the local deployment records for compliance reviews.
+//!
+//! `audit-bundle` gathers the ownership transfers and attestations of the local registry,
+//! the attested metadata, the instantiated contracts and the receipts of upgrades, and
+//! confirms each record on-chain: transfers and attestations by the `System::Remarked`
+//! event of their remark, upgrades by the `ContractCodeUpdated` event of their block,
+//! and deployments by the contract existing. Records of blocks older than `--since` are
+//! left out, records which could not be confirmed are kept and marked as such.
+//!
+//! The bundle is written as JSON and signed with an sr25519 key, the signature is
+//! written to `<bundle>.sig` as for the permissions file.
+
+use super::{
+    extrinsics::{
+        compute_metadata_hash,
+        verify_attestation,
+        verify_transfer,
+        UpgradeReceipt,
+        DEFAULT_RECEIPT_DIR,
+    },
+    info::fetch_contract_info,
+    permissions::{
+        sign,
+        signature_path,
+    },
+    registry::{
+        Attestation,
+        OwnershipTransfer,
+        Registry,
+        SaltRecord,
+    },
+    runtime_api::api,
+    AsyncCommand,
+    Client,
+    CodeHash,
+    DefaultConfig,
+};
+use anyhow::{
+    Context,
+    Result,
+};
+use async_trait::async_trait;
+use colored::Colorize;
+use contract_metadata::ContractMetadata;
+use contract_transcode::rfc3339;
+use scale::Decode;
+use sp_core::{
+    crypto::Ss58Codec,
+    sr25519,
+    Pair,
+};
+use std::{
+    collections::BTreeMap,
+    fs,
+    path::PathBuf,
+    time::{
+        SystemTime,
+        UNIX_EPOCH,
+    },
+};
+use subxt::Config;
+
+type AccountId = <DefaultConfig as Config>::AccountId;
+type Hash = <DefaultConfig as Config>::Hash;
+
+#[derive(Debug, clap::Args)]
+#[clap(
+    name = "audit-bundle",
+    about = "Bundle the local deployment records, confirmed on-chain, into a signed file"
+)]
+pub struct AuditBundleCommand {
+    /// Only bundle the records of blocks since the date, `YYYY-MM-DD` or an RFC 3339
+    /// timestamp in UTC.
+    #[clap(long, value_parser = parse_date)]
+    since: u64,
+    /// Secret key URI of the key signing the bundle.
+    #[clap(long, short)]
+    suri: String,
+    /// Password for the secret key.
+    #[clap(long, short)]
+    password: Option<String>,
+    /// The directory the receipts of upgrades were written to.
+    #[clap(long, value_name = "DIR", default_value = DEFAULT_RECEIPT_DIR)]
+    receipt_dir: PathBuf,
+    /// The file the bundle is written to, `audit-bundle-<since>.json` by default.
+    #[clap(long, value_parser)]
+    output: Option<PathBuf>,
+}
+
+/// The local deployment records since a date, confirmed on-chain.
+#[derive(Debug, serde::Serialize)]
+pub struct AuditBundle {
+    /// When the bundle was created, as an RFC 3339 timestamp.
+    pub created: String,
+    /// The date the records are bundled since, as an RFC 3339 timestamp.
+    pub since: String,
+    /// The account signing the bundle.
+    pub signer: String,
+    /// The transfers, attestations and attested metadata of uploaded code.
+    pub codes: Vec<CodeAudit>,
+    /// The contracts instantiated with salts recorded in the registry.
+    pub deployments: Vec<Confirmed<SaltRecord>>,
+    /// The receipts of upgrades.
+    pub upgrades: Vec<Confirmed<UpgradeReceipt>>,
+}
+
+/// The records of code uploaded to a network.
+#[derive(Debug, serde::Serialize)]
+pub struct CodeAudit {
+    pub code_hash: CodeHash,
+    pub network: String,
+    pub owner: AccountId,
+    pub transfers: Vec<Confirmed<OwnershipTransfer>>,
+    pub attestations: Vec<Confirmed<Attestation>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub metadata: Option<AttestedMetadata>,
+}
+
+/// The metadata of code recorded in the registry, without the Wasm code.
+#[derive(Debug, serde::Serialize)]
+pub struct AttestedMetadata {
+    pub path: PathBuf,
+    /// The hash the metadata is attested with.
+    pub hash: Hash,
+    /// Whether an attestation of the code matches the hash.
+    pub attested: bool,
+    pub metadata: serde_json::Value,
+}
+
+/// A local record and its on-chain confirmation.
+#[derive(Debug, serde::Serialize)]
+pub struct Confirmed<T> {
+    #[serde(flatten)]
+    pub record: T,
+    pub confirmation: Confirmation,
+}
+
+/// The on-chain confirmation of a record.
+#[derive(Debug, Default, Clone, PartialEq, Eq, serde::Serialize)]
+pub struct Confirmation {
+    /// Whether the record was found on-chain.
+    pub confirmed: bool,
+    /// The number of the block of the record.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub block_number: Option<u64>,
+    /// The time of the block of the record, as an RFC 3339 timestamp.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub timestamp: Option<String>,
+    /// Why the record could not be confirmed.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reason: Option<String>,
+    /// The time of the block in milliseconds since the Unix epoch.
+    #[serde(skip)]
+    millis: Option<u64>,
+}
+
+impl Confirmation {
+    fn unconfirmed(reason: impl ToString) -> Self {
+        Self {
+            reason: Some(reason.to_string()),
+            ..Default::default()
+        }
+    }
+
+    /// Returns `true` unless the block of the record is older than `since`. Records of
+    /// unknown blocks are kept.
+    fn is_since(&self, since: u64) -> bool {
+        self.millis.map_or(true, |millis| millis >= since)
+    }
+}
+
+/// The clients of the networks of the records, connected on first use.
+#[derive(Default)]
+struct Networks {
+    clients: BTreeMap<String, Option<Client>>,
+}
+
+impl Networks {
+    /// Returns the client of the network, `None` if the node is unreachable.
+    async fn client(&mut self, network: &str) -> Option<Client> {
+        if !self.clients.contains_key(network) {
+            let client = match Client::from_url(network).await {
+                Ok(client) => Some(client),
+                Err(err) => {
+                    eprintln!(
+                        "{} the records of {network} can not be confirmed: {err}",
+                        "warning:".yellow().bold()
+                    );
+                    None
+                }
+            };
+            self.clients.insert(network.to_string(), client);
+        }
+        self.clients[network].clone()
+    }
+}
+
+#[async_trait(?Send)]
+impl AsyncCommand for AuditBundleCommand {
+    type Output = Result<()>;
+
+    async fn exec(&self) -> Result<()> {
+        let key = sr25519::Pair::from_string(&self.suri, self.password.as_deref())
+            .map_err(|_| anyhow::anyhow!("Secret string error"))?;
+        let registry = Registry::load()?;
+        let mut networks = Networks::default();
+
+        let mut codes = Vec::new();
+        for code in &registry.codes {
+            let client = networks.client(&code.network).await;
+            let mut transfers = Vec::new();
+            for transfer in &code.transfers {
+                let confirmation = match client {
+                    Some(ref client) => {
+                        let verified =
+                            verify_transfer(client, &code.code_hash, transfer).await;
+                        confirm(client, transfer.block_hash, verified).await
+                    }
+                    None => Confirmation::unconfirmed("The node is unreachable"),
+                };
+                transfers.push(Confirmed {
+                    record: transfer.clone(),
+                    confirmation,
+                });
+            }
+            let mut attestations = Vec::new();
+            for attestation in &code.attestations {
+                let confirmation = match client {
+                    Some(ref client) => {
+                        let verified =
+                            verify_attestation(client, &code.code_hash, attestation)
+                                .await;
+                        confirm(client, attestation.block_hash, verified).await
+                    }
+                    None => Confirmation::unconfirmed("The node is unreachable"),
+                };
+                attestations.push(Confirmed {
+                    record: attestation.clone(),
+                    confirmation,
+                });
+            }
+            transfers.retain(|transfer| transfer.confirmation.is_since(self.since));
+            attestations
+                .retain(|attestation| attestation.confirmation.is_since(self.since));
+            if transfers.is_empty() && attestations.is_empty() {
+                continue
+            }
+            let metadata = match code.metadata {
+                Some(ref path) => Some(attested_metadata(path.clone(), &attestations)?),
+                None => None,
+            };
+            codes.push(CodeAudit {
+                code_hash: code.code_hash,
+                network: code.network.clone(),
+                owner: code.owner.clone(),
+                transfers,
+                attestations,
+                metadata,
+            });
+        }
+
+        let mut deployments = Vec::new();
+        for salt in &registry.salts {
+            let confirmation = match networks.client(&salt.network).await {
+                Some(client) => confirm_contract(&client, &salt.contract).await,
+                None => Confirmation::unconfirmed("The node is unreachable"),
+            };
+            deployments.push(Confirmed {
+                record: salt.clone(),
+                confirmation,
+            });
+        }
+
+        let mut upgrades = Vec::new();
+        for receipt in read_receipts(&self.receipt_dir)? {
+            let confirmation = match networks.client(&receipt.network).await {
+                Some(client) => {
+                    let verified = verify_upgrade(&client, &receipt).await;
+                    confirm(&client, receipt.block_hash, verified).await
+                }
+                None => Confirmation::unconfirmed("The node is unreachable"),
+            };
+            if confirmation.is_since(self.since) {
+                upgrades.push(Confirmed {
+                    record: receipt,
+                    confirmation,
+                });
+            }
+        }
+
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|duration| duration.as_millis() as u64)
+            .unwrap_or_default();
+        let since = rfc3339(self.since);
+        let bundle = AuditBundle {
+            created: rfc3339(now),
+            since: since.clone(),
+            signer: key.public().to_ss58check(),
+            codes,
+            deployments,
+            upgrades,
+        };
+        let path = self.output.clone().unwrap_or_else(|| {
+            PathBuf::from(format!("audit-bundle-{}.json", &since[..10]))
+        });
+        let contents = serde_json::to_string_pretty(&bundle)?;
+        fs::write(&path, &contents)
+            .with_context(|| format!("Failed to write {}", path.display()))?;
+        let signature = signature_path(&path);
+        fs::write(&signature, sign(contents.as_bytes(), &key))
+            .with_context(|| format!("Failed to write {}", signature.display()))?;
+
+        let unconfirmed = bundle.unconfirmed();
+        println!(
+            "{} {} with {} code records, {} deployments and {} upgrades, signed by {}",
+            "Bundled".green().bold(),
+            path.display(),
+            bundle.codes.len(),
+            bundle.deployments.len(),
+            bundle.upgrades.len(),
+            bundle.signer
+        );
+        if unconfirmed > 0 {
+            eprintln!(
+                "{} {unconfirmed} records could not be confirmed on-chain",
+                "warning:".yellow().bold()
+            );
+        }
+        Ok(())
+    }
+}
+
+impl AuditBundle {
+    /// Returns the number of records which could not be confirmed on-chain.
+    pub fn unconfirmed(&self) -> usize {
+        let codes = self.codes.iter().flat_map(|code| {
+            code.transfers
+                .iter()
+                .map(|transfer| &transfer.confirmation)
+                .chain(
+                    code.attestations
+                        .iter()
+                        .map(|attestation| &attestation.confirmation),
+                )
+        });
+        codes
+            .chain(
+                self.deployments
+                    .iter()
+                    .map(|deployment| &deployment.confirmation),
+            )
+            .chain(self.upgrades.iter().map(|upgrade| &upgrade.confirmation))
+            .filter(|confirmation| !confirmation.confirmed)
+            .count()
+    }
+}
+
+/// Confirm a record by the block it was included in, `verified` being the result of
+/// checking the events of the block for the record.
+async fn confirm(
+    client: &Client,
+    block_hash: Hash,
+    verified: Result<bool>,
+) -> Confirmation {
+    let (block_number, millis) = match block_time(client, block_hash).await {
+        Ok(block) => block,
+        Err(err) => return Confirmation::unconfirmed(format!("{err:#}")),
+    };
+    let (confirmed, reason) = match verified {
+        Ok(true) => (true, None),
+        Ok(false) => {
+            (
+                false,
+                Some("The block has no event of the record".to_string()),
+            )
+        }
+        Err(err) => (false, Some(format!("{err:#}"))),
+    };
+    Confirmation {
+        confirmed,
+        block_number: Some(block_number),
+        timestamp: Some(rfc3339(millis)),
+        reason,
+        millis: Some(millis),
+    }
+}
+
+/// Returns the number and the time of the block, in milliseconds since the Unix epoch.
+async fn block_time(client: &Client, block_hash: Hash) -> Result<(u64, u64)> {
+    let block = client
+        .blocks()
+        .at(block_hash)
+        .await
+        .with_context(|| format!("The block {block_hash:?} was not found"))?;
+    let now = block
+        .storage()
+        .fetch(&subxt::dynamic::storage_root("Timestamp", "Now"))
+        .await?
+        .context("The chain has no `Timestamp::Now`")?;
+    Ok((block.number().into(), u64::decode(&mut now.encoded())?))
+}
+
+/// Confirm a deployment by the contract existing on-chain.
+async fn confirm_contract(client: &Client, contract: &AccountId) -> Confirmation {
+    match fetch_contract_info(client, contract).await {
+        Ok(Some(_)) => {
+            Confirmation {
+                confirmed: true,
+                ..Default::default()
+            }
+        }
+        Ok(None) => Confirmation::unconfirmed("The contract does not exist"),
+        Err(err) => Confirmation::unconfirmed(format!("{err:#}")),
+    }
+}
+
+/// Check that the block of the upgrade replaced the code of the contract with the
+/// `ContractCodeUpdated` event.
+async fn verify_upgrade(client: &Client, receipt: &UpgradeReceipt) -> Result<bool> {
+    let events = client.events().at(receipt.block_hash).await?;
+    for updated in events.find::<api::contracts::events::ContractCodeUpdated>() {
+        let updated = updated?;
+        if updated.contract == receipt.contract
+            && updated.new_code_hash == receipt.code_hash
+        {
+            return Ok(true)
+        }
+    }
+    Ok(false)
+}
+
+/// Read the receipts of the upgrades, the `upgrade-*.json` files of the directory.
+fn read_receipts(dir: &std::path::Path) -> Result<Vec<UpgradeReceipt>> {
+    if !dir.exists() {
+        return Ok(Vec::new())
+    }
+    let mut paths = fs::read_dir(dir)
+        .with_context(|| format!("Failed to read {}", dir.display()))?
+        .map(|entry| entry.map(|entry| entry.path()))
+        .collect::<std::io::Result<Vec<_>>>()?;
+    paths.sort();
+    let mut receipts = Vec::new();
+    for path in paths {
+        let is_receipt = path
+            .file_name()
+            .and_then(|name| name.to_str())
+            .is_some_and(|name| name.starts_with("upgrade-") && name.ends_with(".json"));
+        if is_receipt {
+            let contents = fs::read_to_string(&path)?;
+            let receipt = serde_json::from_str(&contents)
+                .with_context(|| format!("Invalid upgrade receipt {}", path.display()))?;
+            receipts.push(receipt);
+        }
+    }
+    Ok(receipts)
+}
+
+/// Load the metadata of the code, and check its hash against the attestations.
+fn attested_metadata(
+    path: PathBuf,
+    attestations: &[Confirmed<Attestation>],
+) -> Result<AttestedMetadata> {
+    let mut metadata = ContractMetadata::load(&path)
+        .with_context(|| format!("Failed to load the metadata {}", path.display()))?;
+    let hash = compute_metadata_hash(metadata.clone())?;
+    metadata.remove_source_wasm_attribute();
+    Ok(AttestedMetadata {
+        path,
+        hash,
+        attested: attestations
+            .iter()
+            .any(|attestation| attestation.record.metadata_hash == hash),
+        metadata: serde_json::to_value(metadata)?,
+    })
+}
+
+/// Parse a date, `YYYY-MM-DD` or an RFC 3339 timestamp in UTC such as
+/// `2023-11-14T22:13:20Z`, into milliseconds since the Unix epoch.
+pub fn parse_date(input: &str) -> Result<u64> {
+    let invalid = || {
+        anyhow::anyhow!(
+            "Invalid date `{input}`, expected `YYYY-MM-DD` or `YYYY-MM-DDTHH:MM:SSZ`"
+        )
+    };
+    let (date, time) = match input.split_once('T') {
+        Some((date, time)) => (date, Some(time.strip_suffix('Z').ok_or_else(invalid)?)),
+        None => (input, None),
+    };
+    let fields = |text: &str, separator| {
+        text.split(separator)
+            .map(|field| field.parse::<u64>().map_err(|_| invalid()))
+            .collect::<Result<Vec<_>>>()
+    };
+    let [year, month, day] = fields(date, '-')?[..] else {
+        return Err(invalid())
+    };
+    anyhow::ensure!(
+        (1970..=9999).contains(&year)
+            && (1..=12).contains(&month)
+            && (1..=31).contains(&day),
+        invalid()
+    );
+    let secs = match time {
+        Some(time) => {
+            let [hours, minutes, secs] = fields(time, ':')?[..] else {
+                return Err(invalid())
+            };
+            anyhow::ensure!(hours < 24 && minutes < 60 && secs < 60, invalid());
+            hours * 3_600 + minutes * 60 + secs
+        }
+        None => 0,
+    };
+    // The days since the epoch of the civil date, see
+    // http://howardhinnant.github.io/date_algorithms.html#days_from_civil
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year / 400;
+    let yoe = year - era * 400;
+    let doy = (153 * ((month + 9) % 12) + 2) / 5 + day - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    let days = era * 146_097 + doe - 719_468;
+    Ok((days * 86_400 + secs) * 1_000)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn dates_are_parsed() {
+        assert_eq!(parse_date("1970-01-01").unwrap(), 0);
+        assert_eq!(
+            parse_date("2023-11-14T22:13:20Z").unwrap(),
+            1_700_000_000_000
+        );
+        assert_eq!(
+            rfc3339(parse_date("2024-02-29").unwrap()),
+            "2024-02-29T00:00:00Z"
+        );
+        assert!(parse_date("2023-13-01").is_err());
+        assert!(parse_date("2023-11-14T22:13:20").is_err());
+        assert!(parse_date("14.11.2023").is_err());
+    }
+
+    #[test]
+    fn records_of_unknown_blocks_are_kept() {
+        let confirmation = Confirmation {
+            confirmed: true,
+            millis: Some(1_000),
+            ..Default::default()
+        };
+        assert!(confirmation.is_since(1_000));
+        assert!(!confirmation.is_since(1_001));
+        assert!(Confirmation::unconfirmed("The node is unreachable").is_since(1_001));
+    }
+}
@@ -14,8 +14,12 @@
 // You should have received a copy of the GNU General Public License
 // along with cargo-contract.  If not, see <http://www.gnu.org/licenses/>.
 
-use anyhow::Result;
+use anyhow::{
+    Context,
+    Result,
+};
 use contract_build::{
+    workspace_contracts,
     BuildArtifacts,
     BuildMode,
     BuildResult,
@@ -31,9 +35,20 @@ use contract_build::{
     Verbosity,
     VerbosityFlags,
 };
+use sp_core::{
+    sr25519,
+    Pair,
+};
 use std::{
     convert::TryFrom,
     path::PathBuf,
+    time::SystemTime,
+};
+
+use super::{
+    build_watch,
+    hermetic,
+    provenance,
 };
 
 /// Executes build of the smart contract which produces a Wasm binary that is ready for
@@ -64,8 +79,15 @@ pub struct BuildCommand {
     ///
     /// - `all`: Generate the Wasm, the metadata and a bundled `<name>.contract` file.
     ///
-    /// - `code-only`: Only the Wasm is created, generation of metadata and a bundled
-    ///   `<name>.contract` file is skipped.
+    /// - `code-only` or `wasm`: Only the Wasm is created, generation of metadata and a
+    ///   bundled `<name>.contract` file is skipped.
+    ///
+    /// - `metadata-only` or `metadata`: Only the metadata is generated, always, e.g.
+    ///   after changing its documentation. The Wasm is built first if it changed.
+    ///
+    /// - `bundle-only` or `bundle`: Only the bundled `<name>.contract` file is
+    ///   assembled, from the Wasm and the metadata of previous builds, without
+    ///   compiling.
     ///
     /// - `check-only`: No artifacts produced: runs the `cargo check` command for the
     ///   Wasm target, only checks for compilation errors.
@@ -118,11 +140,142 @@ pub struct BuildCommand {
     /// Which bytecode to build the contract into.
     #[clap(long, default_value = "wasm")]
     target: Target,
+    /// Print the time taken and the output size of every build phase. The timings are
+    /// also written to `build-log.json` in the target directory on every build.
+    #[clap(long, conflicts_with = "output_json")]
+    timings: bool,
+    /// Also write a zstd compressed `<name>.contract.zst` bundle, e.g. to store and
+    /// transfer the artifacts of large contracts in CI pipelines.
+    #[clap(long)]
+    compress: bool,
+    /// Build all contract crates of the workspace, in dependency order. The settings
+    /// of the command apply to all contracts, settings of single contracts can be
+    /// given in their `[package.metadata.contract]` section.
+    #[clap(long)]
+    workspace: bool,
+    /// Build the contract crate of the workspace with the given name. Can be repeated.
+    #[clap(long = "package", short = 'p', value_name = "NAME")]
+    packages: Vec<String>,
+    /// Sign a SLSA provenance statement of the build with the sr25519 key of the secret
+    /// URI, written to `<name>.provenance.json` next to the contract bundle. Verify it
+    /// with `cargo contract verify-provenance`.
+    #[clap(long, value_name = "SURI", env = "CARGO_CONTRACT_PROVENANCE_SURI")]
+    provenance_suri: Option<String>,
+    /// Build the contract again whenever its source changes, printing the changes of
+    /// the code size and of the interface. With `--lint` linting only runs again once
+    /// Rust files changed.
+    #[clap(long, conflicts_with_all = ["output_json", "workspace", "packages"])]
+    watch: bool,
+    /// Build with the Rust toolchain pinned in the `[toolchain]` section of
+    /// `cargo-contract.toml`, downloaded and verified into an isolated directory,
+    /// instead of the toolchains installed on the machine.
+    #[clap(long)]
+    hermetic: bool,
+}
+
+/// The result of building a contract of a workspace.
+#[derive(serde::Serialize)]
+pub struct WorkspaceBuildResult {
+    /// The name of the contract crate.
+    pub contract: String,
+    #[serde(flatten)]
+    pub result: BuildResult,
 }
 
 impl BuildCommand {
+    /// Returns `true` if the timings of the build phases should be printed.
+    pub fn timings(&self) -> bool {
+        self.timings
+    }
+
+    /// Returns `true` if multiple contracts of a workspace are built, with
+    /// `--workspace` or `--package`.
+    pub fn is_workspace(&self) -> bool {
+        self.workspace || !self.packages.is_empty()
+    }
+
+    /// Returns `true` if the contract should be rebuilt on changes, with `--watch`.
+    pub fn watch(&self) -> bool {
+        self.watch
+    }
+
     pub fn exec(&self) -> Result<BuildResult> {
         let manifest_path = ManifestPath::try_from(self.manifest_path.as_ref())?;
+        self.build(manifest_path)
+    }
+
+    /// Build the contract, and again whenever its source changes. Only returns on
+    /// errors of watching the source files, failed builds are printed.
+    pub fn exec_watch(&self) -> Result<()> {
+        let manifest_path = ManifestPath::try_from(self.manifest_path.as_ref())?;
+        let dir = manifest_path.absolute_directory()?.canonicalize()?;
+        build_watch::watch(&dir, self.lint, |lint| {
+            self.build_with(manifest_path.clone(), lint)
+        })
+    }
+
+    /// Build the contracts of the workspace one after the other, stopping at the first
+    /// failure.
+    pub fn exec_workspace(&self) -> Result<Vec<WorkspaceBuildResult>> {
+        let manifest_path = ManifestPath::try_from(self.manifest_path.as_ref())?;
+        let contracts = workspace_contracts(&manifest_path, &self.packages)?;
+        anyhow::ensure!(
+            !contracts.is_empty(),
+            "No contract crates found in the workspace"
+        );
+        contracts
+            .into_iter()
+            .map(|(contract, manifest_path)| {
+                let result = self
+                    .build(manifest_path)
+                    .with_context(|| format!("Failed to build `{contract}`"))?;
+                Ok(WorkspaceBuildResult { contract, result })
+            })
+            .collect()
+    }
+
+    /// Build the contract, and sign the provenance statement of the build if requested.
+    fn build(&self, manifest_path: ManifestPath) -> Result<BuildResult> {
+        self.build_with(manifest_path, self.lint)
+    }
+
+    /// Build the contract as [`Self::build`], linting it if `lint` is set.
+    fn build_with(&self, manifest_path: ManifestPath, lint: bool) -> Result<BuildResult> {
+        let provenance_key = self
+            .provenance_suri
+            .as_ref()
+            .map(|suri| {
+                sr25519::Pair::from_string(suri, None)
+                    .map_err(|_| anyhow::anyhow!("Secret string error"))
+            })
+            .transpose()?;
+        if self.hermetic {
+            let toolchain = hermetic::activate()?;
+            let verbosity = TryFrom::<&VerbosityFlags>::try_from(&self.verbosity)?;
+            if verbosity.is_verbose() {
+                eprintln!(
+                    "Building with the toolchain of {}",
+                    toolchain.dir().display()
+                );
+            }
+        }
+        let started = SystemTime::now();
+        let result =
+            contract_build::execute(self.execute_args(manifest_path.clone(), lint)?)?;
+        if let Some(ref key) = provenance_key {
+            let path = provenance::write(&result, &manifest_path, started, key)?;
+            if result.verbosity.is_verbose() {
+                eprintln!("Provenance statement written to {}", path.display());
+            }
+        }
+        Ok(result)
+    }
+
+    fn execute_args(
+        &self,
+        manifest_path: ManifestPath,
+        lint: bool,
+    ) -> Result<ExecuteArgs> {
         let unstable_flags: UnstableFlags =
             TryFrom::<&UnstableOptions>::try_from(&self.unstable_options)?;
         let mut verbosity = TryFrom::<&VerbosityFlags>::try_from(&self.verbosity)?;
@@ -157,13 +310,13 @@ impl BuildCommand {
             unstable_flags,
             optimization_passes: self.optimization_passes,
             keep_debug_symbols: self.keep_debug_symbols,
-            lint: self.lint,
+            lint,
             output_type,
             skip_wasm_validation: self.skip_wasm_validation,
             target: self.target,
+            compress: self.compress,
         };
-
-        contract_build::execute(args)
+        Ok(args)
     }
 }
 
@@ -204,8 +357,57 @@ impl CheckCommand {
             output_type: OutputType::default(),
             skip_wasm_validation: false,
             target: self.target,
+            compress: false,
         };
 
         contract_build::execute(args)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn workspace_contracts_are_built_in_dependency_order() {
+        let dir = tempfile::tempdir().unwrap();
+        let write = |path: &str, contents: &str| {
+            let path = dir.path().join(path);
+            std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+            std::fs::write(path, contents).unwrap();
+        };
+        write(
+            "Cargo.toml",
+            "[workspace]\nmembers = [\"token\", \"dex\", \"utils\"]\n",
+        );
+        let package = |name: &str, dependencies: &str| {
+            write(&format!("{name}/src/lib.rs"), "");
+            write(
+                &format!("{name}/Cargo.toml"),
+                &format!(
+                    "[package]\nname = \"{name}\"\nversion = \"0.1.0\"\n\n\
+                    [dependencies]\n{dependencies}"
+                ),
+            );
+        };
+        package(
+            "dex",
+            "ink = \"4\"\ntoken = { path = \"../token\", features = [\"ink-as-dependency\"] }\n",
+        );
+        package("token", "ink = \"4\"\n");
+        package("utils", "");
+
+        let manifest_path = ManifestPath::new(dir.path().join("Cargo.toml")).unwrap();
+        let names = |packages: &[String]| {
+            workspace_contracts(&manifest_path, packages).map(|contracts| {
+                contracts
+                    .into_iter()
+                    .map(|(name, _)| name)
+                    .collect::<Vec<_>>()
+            })
+        };
+        assert_eq!(names(&[]).unwrap(), ["token", "dex"]);
+        assert_eq!(names(&["dex".into()]).unwrap(), ["dex"]);
+        assert!(names(&["utils".into()]).is_err());
+    }
+}
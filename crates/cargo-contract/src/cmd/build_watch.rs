@@ -0,0 +1,278 @@
+// Copyright 2018-2023 Parity Technologies (UK) Ltd.
+// This file is part of cargo-contract.
+//
+// cargo-contract is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// cargo-contract is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with cargo-contract.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Rebuilding a contract whenever its source changes, for `build --watch`.
+//!
+//! The Rust files and the `Cargo.toml` of the contract crate are polled for changes of
+//! their modification time or size, skipping hidden files and the `target` directory.
+//! After every build the size of the code and the interface of the contract are compared
+//! to the previous build. Linting is incremental: it only runs again once Rust files
+//! changed since the last successful lint.
+
+use super::changelog::describe;
+use anyhow::Result;
+use colored::Colorize;
+use contract_build::{
+    changelog::Interface,
+    BuildResult,
+};
+use contract_metadata::ContractMetadata;
+use std::{
+    collections::BTreeMap,
+    fs,
+    path::{
+        Path,
+        PathBuf,
+    },
+    time::{
+        Duration,
+        SystemTime,
+    },
+};
+
+/// How often the source files are checked for changes.
+const POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+/// The modification times and sizes of the watched files of a crate.
+#[derive(Debug, Default, PartialEq, Eq)]
+struct Snapshot(BTreeMap<PathBuf, (SystemTime, u64)>);
+
+impl Snapshot {
+    /// Take a snapshot of the watched files in the directory of the crate.
+    fn take(dir: &Path) -> Result<Self> {
+        fn collect(
+            dir: &Path,
+            root: bool,
+            files: &mut BTreeMap<PathBuf, (SystemTime, u64)>,
+        ) -> Result<()> {
+            for entry in fs::read_dir(dir)? {
+                let entry = entry?;
+                let name = entry.file_name().to_string_lossy().to_string();
+                if name.starts_with('.') || (root && name == "target") {
+                    continue
+                }
+                let path = entry.path();
+                let metadata = entry.metadata()?;
+                if metadata.is_dir() {
+                    collect(&path, false, files)?;
+                } else if is_rust_file(&path) || name == "Cargo.toml" {
+                    files.insert(path, (metadata.modified()?, metadata.len()));
+                }
+            }
+            Ok(())
+        }
+        let mut files = BTreeMap::new();
+        collect(dir, true, &mut files)?;
+        Ok(Self(files))
+    }
+
+    /// The files added, removed or modified since the previous snapshot.
+    fn changes(&self, previous: &Snapshot) -> Vec<PathBuf> {
+        let modified = self
+            .0
+            .iter()
+            .filter(|(path, state)| previous.0.get(*path) != Some(state))
+            .map(|(path, _)| path.to_path_buf());
+        let removed = previous
+            .0
+            .keys()
+            .filter(|path| !self.0.contains_key(*path))
+            .cloned();
+        modified.chain(removed).collect()
+    }
+}
+
+fn is_rust_file(path: &Path) -> bool {
+    path.extension().is_some_and(|extension| extension == "rs")
+}
+
+/// The size of the code and the interface of a build, compared between builds.
+#[derive(Debug, Default)]
+struct BuildSummary {
+    /// The size of the code in kilobytes.
+    code_size: Option<f64>,
+    interface: Option<Interface>,
+}
+
+impl BuildSummary {
+    fn new(result: &BuildResult) -> Result<Self> {
+        let code_size = result
+            .dest_wasm
+            .as_ref()
+            .map(|path| fs::metadata(path).map(|metadata| metadata.len() as f64 / 1000.0))
+            .transpose()?;
+        let interface = result
+            .metadata_result
+            .as_ref()
+            .map(|metadata_result| {
+                let metadata = ContractMetadata::load(&metadata_result.dest_metadata)?;
+                Interface::from_abi(&metadata.abi)
+            })
+            .transpose()?;
+        Ok(Self {
+            code_size,
+            interface,
+        })
+    }
+
+    /// Describe the differences to the previous build.
+    fn diff(&self, previous: &BuildSummary) -> Vec<String> {
+        let mut lines = Vec::new();
+        if let (Some(size), Some(previous_size)) = (self.code_size, previous.code_size) {
+            lines.push(format!(
+                "Code size {previous_size:.1}K → {size:.1}K ({:+.1}K)",
+                size - previous_size
+            ));
+        }
+        if let (Some(interface), Some(previous_interface)) =
+            (&self.interface, &previous.interface)
+        {
+            lines.extend(previous_interface.diff(interface).iter().map(describe));
+        }
+        lines
+    }
+}
+
+/// Build the contract in the directory, and again whenever its source changes. The
+/// argument of `build` tells whether the build should lint the contract.
+pub fn watch(
+    dir: &Path,
+    lint: bool,
+    mut build: impl FnMut(bool) -> Result<BuildResult>,
+) -> Result<()> {
+    let mut previous: Option<BuildSummary> = None;
+    let mut lint_pending = lint;
+    loop {
+        let snapshot = Snapshot::take(dir)?;
+        match build(lint_pending).and_then(|result| {
+            BuildSummary::new(&result).map(|summary| (result, summary))
+        }) {
+            Ok((result, summary)) => {
+                lint_pending = false;
+                eprintln!(
+                    "{} in {:.1}s",
+                    "Built".green().bold(),
+                    result.build_log.total_secs()
+                );
+                if let Some(ref previous) = previous {
+                    let diff = summary.diff(previous);
+                    if diff.is_empty() {
+                        eprintln!("  No changes of the code size or the interface");
+                    }
+                    for line in diff {
+                        eprintln!("  {line}");
+                    }
+                }
+                previous = Some(summary);
+            }
+            Err(err) => eprintln!("{} {err:?}", "error:".red().bold()),
+        }
+
+        eprintln!(
+            "{} {} for changes, press Ctrl-C to stop",
+            "Watching".cyan().bold(),
+            dir.display()
+        );
+        let changed = wait_for_changes(dir, snapshot)?;
+        lint_pending |= lint && changed.iter().any(|path| is_rust_file(path));
+        let names = changed
+            .iter()
+            .map(|path| path.strip_prefix(dir).unwrap_or(path).display().to_string())
+            .collect::<Vec<_>>();
+        eprintln!("{} {}", "Changed".cyan().bold(), names.join(", "));
+    }
+}
+
+/// Wait until the files differ from the snapshot and stopped changing for one poll
+/// interval, e.g. while an editor saves several files. Returns the changed files.
+fn wait_for_changes(dir: &Path, snapshot: Snapshot) -> Result<Vec<PathBuf>> {
+    let mut current = Snapshot::take(dir)?;
+    while current == snapshot {
+        std::thread::sleep(POLL_INTERVAL);
+        current = Snapshot::take(dir)?;
+    }
+    loop {
+        std::thread::sleep(POLL_INTERVAL);
+        let next = Snapshot::take(dir)?;
+        if next == current {
+            break
+        }
+        current = next;
+    }
+    Ok(current.changes(&snapshot))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn snapshot_detects_changes_of_watched_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let write = |path: &str, contents: &str| {
+            let path = dir.path().join(path);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, contents).unwrap();
+        };
+        write("Cargo.toml", "[package]");
+        write("lib.rs", "#![no_std]");
+        let before = Snapshot::take(dir.path()).unwrap();
+
+        write("target/ink/flipper.wasm", "\0asm");
+        write(".git/HEAD", "main");
+        write("README.md", "# Flipper");
+        assert_eq!(Snapshot::take(dir.path()).unwrap(), before);
+
+        write("lib.rs", "#![no_std] ");
+        write("src/tests.rs", "");
+        fs::remove_file(dir.path().join("Cargo.toml")).unwrap();
+        let after = Snapshot::take(dir.path()).unwrap();
+        assert_eq!(
+            after.changes(&before),
+            [
+                dir.path().join("lib.rs"),
+                dir.path().join("src/tests.rs"),
+                dir.path().join("Cargo.toml"),
+            ]
+        );
+    }
+
+    #[test]
+    fn summary_diff_reports_size_and_interface_changes() {
+        let interface = |messages: &[(&str, &str)]| {
+            Interface {
+                messages: messages
+                    .iter()
+                    .map(|(name, signature)| (name.to_string(), signature.to_string()))
+                    .collect(),
+                ..Default::default()
+            }
+        };
+        let previous = BuildSummary {
+            code_size: Some(12.3),
+            interface: Some(interface(&[("get", "get() -> bool")])),
+        };
+        let summary = BuildSummary {
+            code_size: Some(12.6),
+            interface: Some(interface(&[("flip", "flip()"), ("get", "get() -> bool")])),
+        };
+        assert_eq!(
+            summary.diff(&previous),
+            ["Code size 12.3K → 12.6K (+0.3K)", "Added message `flip()`"]
+        );
+        assert!(BuildSummary::default().diff(&previous).is_empty());
+    }
+}
@@ -0,0 +1,186 @@
+// Copyright 2018-2023 Parity Technologies (UK) Ltd.
+// This file is part of cargo-contract.
+//
+// cargo-contract is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// cargo-contract is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with cargo-contract.  If not, see <http://www.gnu.org/licenses/>.
+
+//! The account id and balance types of a chain.
+//!
+//! The extrinsics are encoded with the `DefaultConfig` of substrate: 32 byte account ids
+//! and `u128` balances. Chains configured with other types, e.g. 20 byte Ethereum-style
+//! account ids, are detected from their metadata and rejected with an error before
+//! anything is submitted, instead of failing to decode their responses.
+//!
+//! This only guards against such chains, it does not support them: the client, the
+//! signers and the address and balance parsing of all commands are bound to
+//! `DefaultConfig`, and would have to be made generic over the config of the chain.
+
+use super::Client;
+use anyhow::{
+    anyhow,
+    Result,
+};
+use scale_info::{
+    PortableRegistry,
+    TypeDef,
+    TypeDefPrimitive,
+};
+use subxt::{
+    ext::frame_metadata::StorageEntryType,
+    Metadata,
+};
+
+/// The length of the account ids of the `DefaultConfig`.
+const ACCOUNT_ID_LEN: usize = 32;
+/// The length of the balances of the `DefaultConfig`.
+const BALANCE_LEN: usize = 16;
+
+/// The sizes of the account id and balance types of a chain. A size is `None` if the
+/// chain does not have the pallet it is detected from, or its type has no fixed size.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize)]
+pub struct ChainEnvironment {
+    /// The length of the account ids in bytes, the key of the `System.Account` storage.
+    pub account_id_len: Option<usize>,
+    /// The length of the balances in bytes, the type of the
+    /// `Balances.ExistentialDeposit` constant.
+    pub balance_len: Option<usize>,
+}
+
+impl ChainEnvironment {
+    /// Detect the types from the metadata of the chain.
+    pub fn from_metadata(metadata: &Metadata) -> Self {
+        let registry = metadata.types();
+        let account_id_len = metadata
+            .pallet("System")
+            .ok()
+            .and_then(|pallet| pallet.storage("Account").ok())
+            .and_then(|entry| {
+                match entry.ty {
+                    StorageEntryType::Map { ref key, .. } => {
+                        encoded_len(registry, key.id)
+                    }
+                    StorageEntryType::Plain(_) => None,
+                }
+            });
+        let balance_len = metadata
+            .pallet("Balances")
+            .ok()
+            .and_then(|pallet| pallet.constant("ExistentialDeposit").ok())
+            .and_then(|constant| encoded_len(registry, constant.ty.id));
+        Self {
+            account_id_len,
+            balance_len,
+        }
+    }
+
+    /// Returns an error if the types of the chain differ from the `DefaultConfig` the
+    /// extrinsics are encoded with. Types which could not be detected are accepted.
+    pub fn ensure_supported(&self) -> Result<()> {
+        if let Some(len) = self.account_id_len.filter(|len| *len != ACCOUNT_ID_LEN) {
+            let hint = if len == 20 {
+                ", e.g. Ethereum-style addresses"
+            } else {
+                ""
+            };
+            return Err(anyhow!(
+                "The chain uses {len} byte account ids{hint}, but cargo-contract only \
+                supports chains with the {ACCOUNT_ID_LEN} byte account ids of the \
+                substrate default config"
+            ))
+        }
+        if let Some(len) = self.balance_len.filter(|len| *len != BALANCE_LEN) {
+            return Err(anyhow!(
+                "The chain uses {len} byte balances, but cargo-contract only supports \
+                chains with the u128 balances of the substrate default config"
+            ))
+        }
+        Ok(())
+    }
+}
+
+/// Returns an error if the chain of the client is configured with account id or balance
+/// types cargo-contract does not support.
+pub fn ensure_supported_chain(client: &Client) -> Result<()> {
+    ChainEnvironment::from_metadata(&client.metadata()).ensure_supported()
+}
+
+/// The length of the SCALE encoding of the type, `None` if it is not of a fixed size.
+fn encoded_len(registry: &PortableRegistry, id: u32) -> Option<usize> {
+    match registry.resolve(id)?.type_def {
+        TypeDef::Array(ref array) => {
+            Some(array.len as usize * encoded_len(registry, array.type_param.id)?)
+        }
+        TypeDef::Composite(ref composite) => {
+            composite
+                .fields
+                .iter()
+                .map(|field| encoded_len(registry, field.ty.id))
+                .sum()
+        }
+        TypeDef::Tuple(ref tuple) => {
+            tuple
+                .fields
+                .iter()
+                .map(|field| encoded_len(registry, field.id))
+                .sum()
+        }
+        TypeDef::Primitive(ref primitive) => primitive_len(primitive),
+        _ => None,
+    }
+}
+
+fn primitive_len(primitive: &TypeDefPrimitive) -> Option<usize> {
+    match primitive {
+        TypeDefPrimitive::Bool | TypeDefPrimitive::U8 | TypeDefPrimitive::I8 => Some(1),
+        TypeDefPrimitive::U16 | TypeDefPrimitive::I16 => Some(2),
+        TypeDefPrimitive::U32 | TypeDefPrimitive::I32 | TypeDefPrimitive::Char => Some(4),
+        TypeDefPrimitive::U64 | TypeDefPrimitive::I64 => Some(8),
+        TypeDefPrimitive::U128 | TypeDefPrimitive::I128 => Some(16),
+        TypeDefPrimitive::U256 | TypeDefPrimitive::I256 => Some(32),
+        TypeDefPrimitive::Str => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use scale::Decode;
+    use subxt::ext::frame_metadata::RuntimeMetadataPrefixed;
+
+    #[test]
+    fn environment_is_detected_from_the_metadata() {
+        let bytes = include_bytes!("runtime_api/contracts_runtime.scale");
+        let metadata = RuntimeMetadataPrefixed::decode(&mut &bytes[..]).unwrap();
+        let metadata = Metadata::try_from(metadata).unwrap();
+        let environment = ChainEnvironment::from_metadata(&metadata);
+
+        assert_eq!(environment.account_id_len, Some(ACCOUNT_ID_LEN));
+        assert_eq!(environment.balance_len, Some(BALANCE_LEN));
+        assert!(environment.ensure_supported().is_ok());
+
+        let ethereum = ChainEnvironment {
+            account_id_len: Some(20),
+            balance_len: Some(BALANCE_LEN),
+        };
+        assert!(ethereum
+            .ensure_supported()
+            .unwrap_err()
+            .to_string()
+            .contains("20 byte account ids, e.g. Ethereum-style addresses"));
+        let unknown = ChainEnvironment {
+            account_id_len: None,
+            balance_len: None,
+        };
+        assert!(unknown.ensure_supported().is_ok());
+    }
+}
@@ -0,0 +1,447 @@
+// Copyright 2018-2023 Parity Technologies (UK) Ltd.
+// This file is part of cargo-contract.
+//
+// cargo-contract is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// cargo-contract is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with cargo-contract.  If not, see <http://www.gnu.org/licenses/>.
+
+//! The limits and prices `pallet-contracts` is configured with on a chain.
+//!
+//! The constants are decoded dynamically from the metadata of the chain, so that chains
+//! running other versions of the pallet are supported. Constants a chain does not have
+//! are left out.
+//!
+//! The schedule of the chain is compared to the schedule of the runtime cargo-contract
+//! bundles the metadata of, the default `pallet-contracts` configuration gas estimates
+//! of local test environments are based on. Weights differing significantly make those
+//! estimates drift from the dry-runs on the chain.
+
+use super::{
+    code_size::oversized_report,
+    AsyncCommand,
+    Balance,
+    DefaultConfig,
+    DisplayBalance,
+    NumberFormat,
+    TokenMetadata,
+};
+use crate::{
+    name_value_println,
+    DEFAULT_KEY_COL_WIDTH,
+};
+use anyhow::{
+    anyhow,
+    Result,
+};
+use async_trait::async_trait;
+use scale::Decode;
+use std::collections::BTreeMap;
+use subxt::{
+    dynamic::{
+        DecodedValue,
+        DecodedValueThunk,
+    },
+    ext::{
+        frame_metadata::RuntimeMetadataPrefixed,
+        scale_value::{
+            At,
+            Composite,
+            ValueDef,
+        },
+    },
+    metadata::DecodeWithMetadata,
+    Metadata,
+    OnlineClient,
+};
+
+const CONTRACTS_PALLET: &str = "Contracts";
+
+/// The host functions whose weights are reported, the ones dominating the costs of
+/// most contracts.
+const HOST_FNS: &[&str] = &[
+    "call",
+    "instantiate",
+    "transfer",
+    "deposit_event",
+    "get_storage",
+    "set_storage",
+    "clear_storage",
+    "terminate",
+];
+
+/// The deviation from the default schedule, in percent, above which the weight of the
+/// schedule of a chain is reported as differing.
+pub const SCHEDULE_DRIFT_THRESHOLD: u32 = 25;
+
+#[derive(Debug, clap::Args)]
+#[clap(
+    name = "chain-info",
+    about = "Display the limits and prices of pallet-contracts on the chain"
+)]
+pub struct ChainInfoCommand {
+    /// Websockets url of a substrate node.
+    #[clap(long, default_value = "ws://localhost:9944")]
+    url: url::Url,
+    /// Export the constants in JSON format.
+    #[clap(long)]
+    output_json: bool,
+    /// The notation used to display balances in the human readable output.
+    #[clap(long, value_enum, default_value_t)]
+    number_format: NumberFormat,
+}
+
+#[async_trait(?Send)]
+impl AsyncCommand for ChainInfoCommand {
+    type Output = Result<()>;
+
+    async fn exec(&self) -> Result<()> {
+        let client = OnlineClient::<DefaultConfig>::from_url(self.url.as_str()).await?;
+        let limits = ChainLimits::from_metadata(&client.metadata())?;
+        if self.output_json {
+            println!("{}", serde_json::to_string_pretty(&limits)?);
+        } else {
+            let token_metadata = TokenMetadata::query(&client).await?;
+            limits.print(&token_metadata, self.number_format)?;
+        }
+        Ok(())
+    }
+}
+
+/// The constants of `pallet-contracts` on a chain.
+#[derive(Debug, Default, PartialEq, Eq, serde::Serialize)]
+pub struct ChainLimits {
+    /// The maximum size of Wasm code in bytes.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_code_len: Option<u32>,
+    /// The maximum length of storage keys in bytes.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_storage_key_len: Option<u32>,
+    /// The storage deposit charged per byte of storage.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub deposit_per_byte: Option<Balance>,
+    /// The storage deposit charged per storage item.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub deposit_per_item: Option<Balance>,
+    /// Whether contracts may use the unstable host functions.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub unsafe_unstable_interface: Option<bool>,
+    /// The determinism modes code can be uploaded with.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub determinism: Vec<String>,
+    /// The limits of the schedule, e.g. `memory_pages` or `call_depth`.
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub limits: BTreeMap<String, u128>,
+    /// The `ref_time` weights of the main host functions in the schedule.
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub host_fn_weights: BTreeMap<String, u128>,
+    /// The `ref_time` weights of the Wasm instructions in the schedule.
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub instruction_weights: BTreeMap<String, u128>,
+}
+
+/// A weight of the schedule of a chain differing from the default schedule.
+#[derive(Debug, PartialEq, Eq, serde::Serialize)]
+pub struct ScheduleDrift {
+    /// The host function or the instruction, e.g. `host_fn_weights.call`.
+    pub name: String,
+    /// The `ref_time` weight on the chain.
+    pub chain: u128,
+    /// The `ref_time` weight of the default schedule.
+    pub default: u128,
+    /// The deviation of the weight on the chain from the default, in percent.
+    pub percent: i128,
+}
+
+impl std::fmt::Display for ScheduleDrift {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(
+            f,
+            "`{}` weighs {} ref_time, {:+}% of the default {}",
+            self.name, self.chain, self.percent, self.default
+        )
+    }
+}
+
+impl ChainLimits {
+    /// Decode the constants from the metadata of the chain.
+    pub fn from_metadata(metadata: &Metadata) -> Result<Self> {
+        if metadata.pallet(CONTRACTS_PALLET).is_err() {
+            return Err(anyhow!("The chain does not have the Contracts pallet"))
+        }
+        let number = |name| constant(metadata, name).as_ref().and_then(as_u128);
+        let schedule = constant(metadata, "Schedule");
+        let determinism = metadata
+            .types()
+            .types
+            .iter()
+            .find(|ty| ty.ty.path.segments == ["pallet_contracts", "wasm", "Determinism"])
+            .map(|ty| {
+                match ty.ty.type_def {
+                    scale_info::TypeDef::Variant(ref variant) => {
+                        variant
+                            .variants
+                            .iter()
+                            .map(|variant| variant.name.clone())
+                            .collect()
+                    }
+                    _ => Vec::new(),
+                }
+            })
+            .unwrap_or_default();
+
+        Ok(Self {
+            max_code_len: number("MaxCodeLen").and_then(|n| n.try_into().ok()),
+            max_storage_key_len: number("MaxStorageKeyLen")
+                .and_then(|n| n.try_into().ok()),
+            deposit_per_byte: number("DepositPerByte"),
+            deposit_per_item: number("DepositPerItem"),
+            unsafe_unstable_interface: constant(metadata, "UnsafeUnstableInterface")
+                .and_then(|value| value.as_bool()),
+            determinism,
+            limits: fields(schedule.as_ref().and_then(|s| s.at("limits")), |_| true),
+            host_fn_weights: fields(
+                schedule.as_ref().and_then(|s| s.at("host_fn_weights")),
+                |name| HOST_FNS.contains(&name),
+            ),
+            instruction_weights: fields(
+                schedule.as_ref().and_then(|s| s.at("instruction_weights")),
+                |name| name != "version",
+            ),
+        })
+    }
+
+    /// The constants of the runtime whose metadata is bundled with cargo-contract, with
+    /// the default schedule of `pallet-contracts`.
+    pub fn reference() -> Result<Self> {
+        let bytes = include_bytes!("runtime_api/contracts_runtime.scale");
+        let metadata = RuntimeMetadataPrefixed::decode(&mut &bytes[..])?;
+        Self::from_metadata(&Metadata::try_from(metadata)?)
+    }
+
+    /// The weights of the schedule deviating more than `threshold` percent from the
+    /// reference schedule. Weights missing from either schedule are not compared.
+    pub fn schedule_drift(
+        &self,
+        reference: &ChainLimits,
+        threshold: u32,
+    ) -> Vec<ScheduleDrift> {
+        let weights = [
+            (
+                "host_fn_weights",
+                &self.host_fn_weights,
+                &reference.host_fn_weights,
+            ),
+            (
+                "instruction_weights",
+                &self.instruction_weights,
+                &reference.instruction_weights,
+            ),
+        ];
+        weights
+            .into_iter()
+            .flat_map(|(group, chain, reference)| {
+                chain.iter().filter_map(move |(name, &chain)| {
+                    let default = *reference.get(name)?;
+                    if default == 0 {
+                        return None
+                    }
+                    let percent =
+                        (chain as i128 - default as i128) * 100 / default as i128;
+                    (percent.unsigned_abs() > u128::from(threshold)).then(|| {
+                        ScheduleDrift {
+                            name: format!("{group}.{name}"),
+                            chain,
+                            default,
+                            percent,
+                        }
+                    })
+                })
+            })
+            .collect()
+    }
+
+    /// Check that the code can be uploaded to the chain. Code larger than the maximum
+    /// is reported with its biggest contributors and how to reduce its size.
+    pub fn check_code(&self, code: &[u8]) -> Result<()> {
+        match self.max_code_len {
+            Some(max) if code.len() > max as usize => {
+                Err(anyhow!(
+                    "{}\n\nSee `cargo contract code-size` for the full breakdown and \
+                    `cargo contract chain-info` for the limits of the chain.",
+                    oversized_report(code, max as usize)
+                ))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    fn print(
+        &self,
+        token_metadata: &TokenMetadata,
+        number_format: NumberFormat,
+    ) -> Result<()> {
+        let balance = |balance: Balance| -> Result<String> {
+            Ok(DisplayBalance::new(balance, token_metadata)?.format(number_format))
+        };
+        if let Some(max) = self.max_code_len {
+            name_value_println!(
+                "Max code size",
+                format!("{max} bytes"),
+                DEFAULT_KEY_COL_WIDTH
+            );
+        }
+        if let Some(max) = self.max_storage_key_len {
+            name_value_println!(
+                "Max key length",
+                format!("{max} bytes"),
+                DEFAULT_KEY_COL_WIDTH
+            );
+        }
+        if let Some(deposit) = self.deposit_per_byte {
+            name_value_println!(
+                "Deposit per byte",
+                balance(deposit)?,
+                DEFAULT_KEY_COL_WIDTH
+            );
+        }
+        if let Some(deposit) = self.deposit_per_item {
+            name_value_println!(
+                "Deposit per item",
+                balance(deposit)?,
+                DEFAULT_KEY_COL_WIDTH
+            );
+        }
+        if let Some(unstable) = self.unsafe_unstable_interface {
+            name_value_println!(
+                "Unstable host fns",
+                if unstable { "allowed" } else { "forbidden" },
+                DEFAULT_KEY_COL_WIDTH
+            );
+        }
+        if !self.determinism.is_empty() {
+            name_value_println!(
+                "Determinism",
+                self.determinism.join(", "),
+                DEFAULT_KEY_COL_WIDTH
+            );
+        }
+        for (name, limit) in &self.limits {
+            name_value_println!(name, limit.to_string(), DEFAULT_KEY_COL_WIDTH);
+        }
+        for (name, weight) in &self.host_fn_weights {
+            let name = format!("{name} weight");
+            name_value_println!(
+                name,
+                format!("{weight} ref_time"),
+                DEFAULT_KEY_COL_WIDTH
+            );
+        }
+        Ok(())
+    }
+}
+
+/// Decode the constant of `pallet-contracts`, if the chain has it.
+fn constant(metadata: &Metadata, name: &str) -> Option<DecodedValue> {
+    pallet_constant(metadata, CONTRACTS_PALLET, name)
+}
+
+/// Decode the constant of the pallet, if the chain has it.
+pub(crate) fn pallet_constant(
+    metadata: &Metadata,
+    pallet: &str,
+    name: &str,
+) -> Option<DecodedValue> {
+    let constant = metadata.pallet(pallet).ok()?.constant(name).ok()?;
+    DecodedValueThunk::decode_with_metadata(
+        &mut &*constant.value,
+        constant.ty.id,
+        metadata,
+    )
+    .ok()?
+    .to_value()
+    .ok()
+}
+
+/// Returns the value as a number; a weight is reduced to its `ref_time`.
+fn as_u128(value: &DecodedValue) -> Option<u128> {
+    value
+        .as_u128()
+        .or_else(|| value.at("ref_time").and_then(|ref_time| ref_time.as_u128()))
+}
+
+/// Returns the numeric fields of the composite value whose names pass the filter.
+fn fields(
+    value: Option<&DecodedValue>,
+    filter: impl Fn(&str) -> bool,
+) -> BTreeMap<String, u128> {
+    let Some(ValueDef::Composite(Composite::Named(fields))) =
+        value.map(|value| &value.value)
+    else {
+        return BTreeMap::new()
+    };
+    fields
+        .iter()
+        .filter_map(|(name, value)| {
+            let name = name.trim_start_matches("r#");
+            filter(name)
+                .then(|| as_u128(value))
+                .flatten()
+                .map(|value| (name.to_string(), value))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn constants_are_decoded_from_the_metadata() {
+        let limits = ChainLimits::reference().unwrap();
+
+        assert_eq!(limits.max_code_len, Some(262_144));
+        assert_eq!(limits.max_storage_key_len, Some(128));
+        assert_eq!(limits.deposit_per_byte, Some(5_000_000));
+        assert_eq!(limits.unsafe_unstable_interface, Some(true));
+        assert_eq!(limits.determinism, ["Deterministic", "AllowIndeterminism"]);
+        assert_eq!(limits.limits.get("memory_pages"), Some(&16));
+        assert_eq!(limits.host_fn_weights.len(), HOST_FNS.len());
+
+        assert!(limits.check_code(&[0; 262_144]).is_ok());
+        assert!(limits.check_code(&vec![0; 262_145]).is_err());
+    }
+
+    #[test]
+    fn schedule_drift_reports_weights_beyond_the_threshold() {
+        let reference = ChainLimits::reference().unwrap();
+        assert!(reference.schedule_drift(&reference, 0).is_empty());
+
+        let mut limits = ChainLimits::reference().unwrap();
+        let call = limits.host_fn_weights.get_mut("call").unwrap();
+        let default = *call;
+        *call = default * 2;
+        let transfer = limits.host_fn_weights.get_mut("transfer").unwrap();
+        *transfer = *transfer * 110 / 100;
+        limits.instruction_weights.clear();
+
+        assert_eq!(
+            limits.schedule_drift(&reference, SCHEDULE_DRIFT_THRESHOLD),
+            [ScheduleDrift {
+                name: "host_fn_weights.call".into(),
+                chain: default * 2,
+                default,
+                percent: 100,
+            }]
+        );
+        assert_eq!(limits.schedule_drift(&reference, 5).len(), 2);
+    }
+}
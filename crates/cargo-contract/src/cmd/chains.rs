@@ -0,0 +1,474 @@
+// Copyright 2018-2023 Parity Technologies (UK) Ltd.
+// This file is part of cargo-contract.
+//
+// cargo-contract is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// cargo-contract is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with cargo-contract.  If not, see <http://www.gnu.org/licenses/>.
+
+//! The catalogue of known contracts-enabled chains.
+//!
+//! cargo-contract ships a catalogue of chains with their endpoint, SS58 prefix, token,
+//! the storage version of their `Contracts` pallet and their known quirks. The
+//! `[chains]` section of the `cargo-contract.toml` configuration file adds chains to
+//! it, or overrides the built-in entries:
+//!
+//! ```toml
+//! [chains.my-parachain]
+//! name = "My parachain"
+//! url = "wss://rpc.my-parachain.io"
+//! ss58-prefix = 42
+//! decimals = 12
+//! symbol = "MYP"
+//! ```
+//!
+//! A chain of the catalogue can be selected with `--network <NAME>` without a network
+//! profile of its own. Extrinsics submitted to it are preceded by a comparison of the
+//! chain with its entry, warning about a changed token or an upgraded pallet.
+
+use super::{
+    config::{
+        Config,
+        NetworkProfile,
+        CONFIG_FILE_NAME,
+    },
+    AsyncCommand,
+    Client,
+    DefaultConfig,
+};
+use crate::{
+    name_value_println,
+    DEFAULT_KEY_COL_WIDTH,
+};
+use anyhow::{
+    anyhow,
+    Result,
+};
+use async_trait::async_trait;
+use colored::Colorize as _;
+use scale::Decode;
+use sp_core::hashing::twox_128;
+use std::collections::BTreeMap;
+use subxt::OnlineClient;
+
+/// The built-in catalogue.
+const BUILTIN_CHAINS: &str = include_str!("chains.toml");
+
+const CONTRACTS_PALLET: &str = "Contracts";
+
+#[derive(Debug, clap::Args)]
+#[clap(name = "chains", about = "List the known contracts-enabled chains")]
+pub struct ChainsCommand {
+    #[clap(subcommand)]
+    action: ChainsAction,
+}
+
+#[derive(Debug, clap::Subcommand)]
+enum ChainsAction {
+    /// List the chains of the catalogue
+    List {
+        /// Export the catalogue in JSON format.
+        #[clap(long)]
+        output_json: bool,
+    },
+    /// Display a chain of the catalogue
+    Show {
+        /// The name of the chain, e.g. `shibuya`.
+        chain: String,
+        /// Export the chain in JSON format.
+        #[clap(long)]
+        output_json: bool,
+    },
+    /// Compare a chain with its entry in the catalogue
+    Check {
+        /// The name of the chain, e.g. `shibuya`.
+        chain: String,
+        /// Websockets url of a node of the chain, instead of the url of the catalogue.
+        #[clap(long)]
+        url: Option<url::Url>,
+    },
+}
+
+#[async_trait(?Send)]
+impl AsyncCommand for ChainsCommand {
+    type Output = Result<()>;
+
+    async fn exec(&self) -> Result<()> {
+        let catalogue = Catalogue::load()?;
+        match self.action {
+            ChainsAction::List { output_json } => {
+                if output_json {
+                    println!("{}", serde_json::to_string_pretty(&catalogue.chains)?);
+                    return Ok(())
+                }
+                for (key, chain) in &catalogue.chains {
+                    println!(
+                        "{key:<20} {:<6} {:>2} decimals  ss58 {:<3} {}",
+                        chain.symbol, chain.decimals, chain.ss58_prefix, chain.url
+                    );
+                }
+                Ok(())
+            }
+            ChainsAction::Show {
+                ref chain,
+                output_json,
+            } => {
+                let chain = catalogue.chain(chain)?;
+                if output_json {
+                    println!("{}", serde_json::to_string_pretty(chain)?);
+                } else {
+                    chain.print();
+                }
+                Ok(())
+            }
+            ChainsAction::Check { ref chain, ref url } => {
+                let chain = catalogue.chain(chain)?;
+                let url = url.as_ref().unwrap_or(&chain.url);
+                let client =
+                    OnlineClient::<DefaultConfig>::from_url(url.as_str()).await?;
+                let properties = ChainProperties::query(&client).await?;
+                properties.print();
+                let mismatches = chain.mismatches(&properties);
+                if mismatches.is_empty() {
+                    println!("The chain matches its entry in the catalogue");
+                }
+                for warning in mismatches {
+                    eprintln!("{} {warning}", "warning:".yellow().bold());
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+/// A chain of the catalogue.
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+pub struct Chain {
+    /// The display name of the chain.
+    pub name: String,
+    /// Websockets url of a public node of the chain.
+    pub url: url::Url,
+    /// Websockets url of a public archive node of the chain.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub archive_url: Option<url::Url>,
+    /// The SS58 prefix of the addresses of the chain.
+    pub ss58_prefix: u16,
+    /// The decimals of the token of the chain.
+    pub decimals: u8,
+    /// The symbol of the token of the chain.
+    pub symbol: String,
+    /// The storage version of the `Contracts` pallet the entry was checked with.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub contracts_version: Option<u16>,
+    /// The public node bans clients sending bursts of requests.
+    #[serde(default)]
+    pub public_endpoint: bool,
+    /// The known quirks of the chain, printed before submitting extrinsics to it.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub quirks: Vec<String>,
+}
+
+impl Chain {
+    /// Returns the network profile selected by `--network` with the name of the chain,
+    /// if the configuration file has no profile of that name.
+    pub fn profile(&self) -> NetworkProfile {
+        NetworkProfile {
+            url: Some(self.url.clone()),
+            archive_url: self.archive_url.clone(),
+            public_endpoint: self.public_endpoint,
+            ..Default::default()
+        }
+    }
+
+    /// Returns the differences of the properties of the chain from the entry.
+    pub fn mismatches(&self, properties: &ChainProperties) -> Vec<String> {
+        let mut mismatches = Vec::new();
+        if let Some(prefix) = properties.ss58_prefix.filter(|p| *p != self.ss58_prefix) {
+            mismatches.push(format!(
+                "The chain uses the SS58 prefix {prefix}, not {} as catalogued",
+                self.ss58_prefix
+            ));
+        }
+        if let Some(decimals) = properties.decimals.filter(|d| *d != self.decimals) {
+            mismatches.push(format!(
+                "The token of the chain has {decimals} decimals, not {} as catalogued, \
+                check the balances given in planck",
+                self.decimals
+            ));
+        }
+        if let Some(symbol) = properties.symbol.as_ref().filter(|s| **s != self.symbol) {
+            mismatches.push(format!(
+                "The token of the chain is {symbol}, not {} as catalogued",
+                self.symbol
+            ));
+        }
+        if let Some(catalogued) = self.contracts_version {
+            match properties.contracts_version {
+                None => {
+                    mismatches.push(String::from(
+                        "The chain no longer has the `Contracts` pallet",
+                    ))
+                }
+                Some(version) if version != catalogued => {
+                    mismatches.push(format!(
+                        "The `Contracts` pallet of the chain is at storage version \
+                        {version}, the catalogue was checked with version {catalogued}. \
+                        The pallet was upgraded, its calls may differ from the ones \
+                        cargo-contract encodes"
+                    ))
+                }
+                Some(_) => (),
+            }
+        }
+        mismatches
+    }
+
+    fn print(&self) {
+        name_value_println!("Name", self.name, DEFAULT_KEY_COL_WIDTH);
+        name_value_println!("Url", self.url.to_string(), DEFAULT_KEY_COL_WIDTH);
+        if let Some(ref archive_url) = self.archive_url {
+            name_value_println!(
+                "Archive url",
+                archive_url.to_string(),
+                DEFAULT_KEY_COL_WIDTH
+            );
+        }
+        name_value_println!(
+            "SS58 prefix",
+            self.ss58_prefix.to_string(),
+            DEFAULT_KEY_COL_WIDTH
+        );
+        name_value_println!(
+            "Token",
+            format!("{} ({} decimals)", self.symbol, self.decimals),
+            DEFAULT_KEY_COL_WIDTH
+        );
+        if let Some(version) = self.contracts_version {
+            name_value_println!(
+                "Contracts",
+                format!("storage version {version}"),
+                DEFAULT_KEY_COL_WIDTH
+            );
+        }
+        for quirk in &self.quirks {
+            name_value_println!("Quirk", quirk, DEFAULT_KEY_COL_WIDTH);
+        }
+    }
+}
+
+/// The built-in chains, extended by the chains of the configuration file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Catalogue {
+    chains: BTreeMap<String, Chain>,
+}
+
+impl Catalogue {
+    /// Returns the built-in chains.
+    pub fn builtin() -> Self {
+        let chains =
+            toml::from_str(BUILTIN_CHAINS).expect("the built-in catalogue is valid");
+        Self { chains }
+    }
+
+    /// Returns the built-in chains, extended by the chains of the configuration file.
+    pub fn load() -> Result<Self> {
+        Ok(Config::load()?.catalogue())
+    }
+
+    /// Add the chains to the catalogue, replacing the entries of the same name.
+    pub fn extend(&mut self, chains: &BTreeMap<String, Chain>) {
+        self.chains.extend(
+            chains
+                .iter()
+                .map(|(key, chain)| (key.clone(), chain.clone())),
+        )
+    }
+
+    /// Returns the chain with the given name, if it is in the catalogue.
+    pub fn get(&self, name: &str) -> Option<&Chain> {
+        self.chains.get(name)
+    }
+
+    /// Returns the chain with the given name.
+    pub fn chain(&self, name: &str) -> Result<&Chain> {
+        self.get(name).ok_or_else(|| {
+            anyhow!(
+                "No chain `{name}` in the catalogue, known chains are: {}. Add it to the \
+                `[chains]` section of the `{CONFIG_FILE_NAME}` file",
+                self.chains.keys().cloned().collect::<Vec<_>>().join(", ")
+            )
+        })
+    }
+}
+
+/// The properties of a chain its catalogue entry is compared with.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ChainProperties {
+    /// The `ss58Format` of the system properties.
+    pub ss58_prefix: Option<u16>,
+    /// The `tokenDecimals` of the system properties.
+    pub decimals: Option<u8>,
+    /// The `tokenSymbol` of the system properties.
+    pub symbol: Option<String>,
+    /// The storage version of the `Contracts` pallet, `None` if the chain has none.
+    pub contracts_version: Option<u16>,
+}
+
+impl ChainProperties {
+    /// Query the properties of the chain of the client.
+    pub async fn query(client: &Client) -> Result<Self> {
+        let properties = client.rpc().system_properties().await?;
+        // Chains with several tokens list the properties of each, the native one first.
+        let property = |name| {
+            properties.get(name).and_then(|value| {
+                value
+                    .as_array()
+                    .map_or(Some(value), |values| values.first())
+                    .cloned()
+            })
+        };
+        let number = |name| property(name).and_then(|value| value.as_u64());
+        let contracts_version = if client.metadata().pallet(CONTRACTS_PALLET).is_ok() {
+            let key = [
+                twox_128(CONTRACTS_PALLET.as_bytes()),
+                twox_128(b":__STORAGE_VERSION__:"),
+            ]
+            .concat();
+            let version = client.storage().at_latest().await?.fetch_raw(&key).await?;
+            Some(match version {
+                Some(version) => u16::decode(&mut &version[..])?,
+                None => 0,
+            })
+        } else {
+            None
+        };
+        Ok(Self {
+            ss58_prefix: number("ss58Format").and_then(|n| n.try_into().ok()),
+            decimals: number("tokenDecimals").and_then(|n| n.try_into().ok()),
+            symbol: property("tokenSymbol")
+                .and_then(|value| value.as_str().map(String::from)),
+            contracts_version,
+        })
+    }
+
+    fn print(&self) {
+        let display = |value: Option<String>| value.unwrap_or_else(|| "-".into());
+        name_value_println!(
+            "SS58 prefix",
+            display(self.ss58_prefix.map(|p| p.to_string())),
+            DEFAULT_KEY_COL_WIDTH
+        );
+        name_value_println!(
+            "Token",
+            format!(
+                "{} ({} decimals)",
+                display(self.symbol.clone()),
+                display(self.decimals.map(|d| d.to_string()))
+            ),
+            DEFAULT_KEY_COL_WIDTH
+        );
+        name_value_println!(
+            "Contracts",
+            display(
+                self.contracts_version
+                    .map(|v| format!("storage version {v}"))
+            ),
+            DEFAULT_KEY_COL_WIDTH
+        );
+    }
+}
+
+/// Warn about the differences of the chain of the client from the catalogue entry of the
+/// network, and print its known quirks. Networks not in the catalogue are not checked.
+pub async fn preflight(client: &Client, network: &str) -> Result<()> {
+    let catalogue = Catalogue::load()?;
+    let Some(chain) = catalogue.get(network) else {
+        return Ok(())
+    };
+    let properties = ChainProperties::query(client).await?;
+    for warning in chain.mismatches(&properties) {
+        eprintln!("{} {warning}", "warning:".yellow().bold());
+    }
+    for quirk in &chain.quirks {
+        eprintln!("{} {quirk}", "note:".cyan().bold());
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn catalogue_is_extended_by_the_configuration() {
+        let mut catalogue = Catalogue::builtin();
+        let shibuya = catalogue.chain("shibuya").unwrap().clone();
+        assert_eq!((shibuya.ss58_prefix, shibuya.decimals), (5, 18));
+        assert_eq!(shibuya.profile().url, Some(shibuya.url.clone()));
+        assert!(shibuya.profile().public_endpoint);
+        assert!(catalogue.chain("unknown").is_err());
+
+        let config = Config::parse(
+            r#"
+            [chains.shibuya]
+            name = "Shibuya"
+            url = "wss://shibuya.example.com"
+            ss58-prefix = 5
+            decimals = 18
+            symbol = "SBY"
+
+            [chains.custom]
+            name = "Custom"
+            url = "ws://localhost:9955"
+            ss58-prefix = 42
+            decimals = 10
+            symbol = "CUS"
+            "#,
+        )
+        .unwrap();
+        catalogue.extend(&config.chains);
+        assert_eq!(
+            catalogue.chain("shibuya").unwrap().url.as_str(),
+            "wss://shibuya.example.com/"
+        );
+        assert_eq!(catalogue.chain("custom").unwrap().decimals, 10);
+        assert_eq!(
+            config.network_profile("custom").unwrap().url,
+            Some("ws://localhost:9955".parse().unwrap())
+        );
+        assert!(config.network_profile("unknown").is_err());
+        assert!(catalogue.get("astar").is_some());
+    }
+
+    #[test]
+    fn chains_are_compared_with_their_entry() {
+        let chain = Catalogue::builtin().chain("shibuya").unwrap().clone();
+        let mut properties = ChainProperties {
+            ss58_prefix: Some(5),
+            decimals: Some(18),
+            symbol: Some(String::from("SBY")),
+            contracts_version: chain.contracts_version,
+        };
+        assert!(chain.mismatches(&properties).is_empty());
+
+        properties.decimals = Some(12);
+        properties.contracts_version = Some(99);
+        let mismatches = chain.mismatches(&properties);
+        assert_eq!(mismatches.len(), 2, "{mismatches:?}");
+        assert!(mismatches[0].contains("12 decimals, not 18"));
+        assert!(mismatches[1].contains("storage version 99"));
+
+        properties.contracts_version = None;
+        assert!(chain.mismatches(&properties)[1].contains("no longer has"));
+        assert_eq!(chain.mismatches(&ChainProperties::default()).len(), 1);
+    }
+}
@@ -0,0 +1,198 @@
+// Copyright 2018-2023 Parity Technologies (UK) Ltd.
+// This file is part of cargo-contract.
+//
+// cargo-contract is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// cargo-contract is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with cargo-contract.  If not, see <http://www.gnu.org/licenses/>.
+
+use super::AsyncCommand;
+use anyhow::Result;
+use async_trait::async_trait;
+use contract_build::{
+    changelog::{
+        self,
+        Change,
+        Changelog,
+        Interface,
+    },
+    CrateMetadata,
+    Target,
+};
+use contract_metadata::ContractMetadata;
+use std::{
+    fmt::Write,
+    path::PathBuf,
+};
+
+/// Render the changelog of the interface of the contract, recorded by the builds in the
+/// `CONTRACT_CHANGELOG.json` file next to the `Cargo.toml`.
+#[derive(Debug, clap::Args)]
+#[clap(name = "changelog")]
+pub struct ChangelogCommand {
+    /// Path to the `Cargo.toml` of the contract.
+    #[clap(long, value_parser)]
+    manifest_path: Option<PathBuf>,
+    /// Create the changelog, so that the following builds record their interface
+    /// changes. The interface of the latest build is the base of the first entry.
+    #[clap(long)]
+    init: bool,
+    /// Export the changelog in JSON format.
+    #[clap(long, conflicts_with = "init")]
+    output_json: bool,
+}
+
+#[async_trait(?Send)]
+impl AsyncCommand for ChangelogCommand {
+    type Output = Result<()>;
+
+    async fn exec(&self) -> Result<()> {
+        let crate_metadata =
+            CrateMetadata::from_manifest_path(self.manifest_path.as_ref(), Target::Wasm)?;
+        let path = changelog::changelog_path(&crate_metadata)?;
+        if self.init {
+            anyhow::ensure!(!path.exists(), "{} already exists", path.display());
+            let metadata_path = crate_metadata.metadata_path();
+            let interface = if metadata_path.exists() {
+                Interface::from_abi(&ContractMetadata::load(&metadata_path)?.abi)?
+            } else {
+                Interface::default()
+            };
+            let changelog = Changelog {
+                interface,
+                entries: Vec::new(),
+            };
+            changelog.write(&path)?;
+            println!("Created {}", path.display());
+            return Ok(())
+        }
+
+        anyhow::ensure!(
+            path.exists(),
+            "No changelog found at {}, create it with `cargo contract changelog --init`",
+            path.display()
+        );
+        let changelog = Changelog::load(&path)?;
+        if self.output_json {
+            println!("{}", serde_json::to_string_pretty(&changelog.entries)?);
+        } else {
+            print!(
+                "{}",
+                render(&crate_metadata.contract_artifact_name, &changelog)
+            );
+        }
+        Ok(())
+    }
+}
+
+/// Render the changelog as markdown, the latest build first.
+fn render(contract: &str, changelog: &Changelog) -> String {
+    let mut out = format!("# Interface changelog of `{contract}`\n");
+    if changelog.entries.is_empty() {
+        out.push_str("\nNo interface changes recorded.\n");
+    }
+    for entry in changelog.entries.iter().rev() {
+        let _ = write!(out, "\n## {} ({})\n\n", entry.version, entry.code_hash);
+        for change in &entry.changes {
+            let _ = writeln!(out, "- {}", describe(change));
+        }
+    }
+    out
+}
+
+/// Describe the change of the interface, e.g. ``Added message `flip()` ``.
+pub fn describe(change: &Change) -> String {
+    match change {
+        Change::Added {
+            item,
+            name: _,
+            signature,
+        } => format!("Added {item} `{signature}`"),
+        Change::Removed {
+            item,
+            name: _,
+            signature,
+        } => format!("Removed {item} `{signature}`"),
+        Change::Changed {
+            item,
+            name,
+            from,
+            to,
+        } => format!("Changed {item} `{name}`: `{from}` → `{to}`"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn interface(spec: serde_json::Value) -> Interface {
+        let abi = serde_json::json!({ "spec": spec });
+        Interface::from_abi(abi.as_object().unwrap()).unwrap()
+    }
+
+    #[test]
+    fn interface_changes_are_recorded_and_rendered() {
+        let flip = serde_json::json!({
+            "label": "flip",
+            "selector": "0x633aa551",
+            "mutates": true,
+            "args": [],
+            "returnType": null
+        });
+        let get = |ty: &str| {
+            serde_json::json!({
+                "label": "get",
+                "selector": "0x2f865bd9",
+                "mutates": false,
+                "args": [],
+                "returnType": { "displayName": ["ink", ty], "type": 0 }
+            })
+        };
+        let flipped = serde_json::json!({
+            "label": "Flipped",
+            "args": [{ "label": "value", "indexed": true, "type": { "displayName": ["bool"], "type": 1 } }]
+        });
+        let v1 = interface(serde_json::json!({
+            "constructors": [],
+            "messages": [flip, get("MessageResult")],
+            "events": []
+        }));
+        let v2 = interface(serde_json::json!({
+            "constructors": [],
+            "messages": [get("Result")],
+            "events": [flipped]
+        }));
+
+        let mut changelog = Changelog::default();
+        assert_eq!(changelog.record("0.1.0", "0x01", v1.clone()).len(), 2);
+        assert!(changelog.record("0.1.0", "0x01", v1).is_empty());
+        assert_eq!(changelog.record("0.2.0", "0x02", v2).len(), 3);
+        assert_eq!(changelog.entries.len(), 2);
+
+        assert_eq!(
+            render("flipper", &changelog),
+            "# Interface changelog of `flipper`\n\
+            \n\
+            ## 0.2.0 (0x02)\n\
+            \n\
+            - Removed message `flip() [0x633aa551, mutates]`\n\
+            - Changed message `get`: `get() -> ink::MessageResult [0x2f865bd9]` → \
+            `get() -> ink::Result [0x2f865bd9]`\n\
+            - Added event `Flipped(#[indexed] value: bool)`\n\
+            \n\
+            ## 0.1.0 (0x01)\n\
+            \n\
+            - Added message `flip() [0x633aa551, mutates]`\n\
+            - Added message `get() -> ink::MessageResult [0x2f865bd9]`\n"
+        );
+    }
+}
@@ -0,0 +1,224 @@
+// Copyright 2018-2023 Parity Technologies (UK) Ltd.
+// This file is part of cargo-contract.
+//
+// cargo-contract is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// cargo-contract is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with cargo-contract.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Checkpoints of long-running scans, e.g. of the events in a range of blocks or of the
+//! storage of the chain.
+//!
+//! With `--checkpoint <PATH>` the progress of a scan is written to the file
+//! periodically and when the scan fails, so that an interrupted scan can be continued
+//! with `--resume <PATH>` instead of repeating all RPC requests. The file is removed
+//! once the scan completes.
+//!
+//! A checkpoint records the scan it belongs to, e.g. the node and the contract, and can
+//! only resume the same scan.
+
+use super::lock;
+use anyhow::{
+    Context,
+    Result,
+};
+use serde::{
+    de::DeserializeOwned,
+    Serialize,
+};
+use std::{
+    path::{
+        Path,
+        PathBuf,
+    },
+    time::{
+        Duration,
+        Instant,
+    },
+};
+
+/// How often the progress of a scan is written to its checkpoint.
+pub const CHECKPOINT_INTERVAL: Duration = Duration::from_secs(30);
+
+/// Options for writing and resuming checkpoints of a scan.
+#[derive(Debug, Default, Clone, clap::Args)]
+pub struct CheckpointOpts {
+    /// Write the progress of the scan to the file periodically, so that an interrupted
+    /// scan can be resumed with `--resume`.
+    #[clap(long, value_name = "PATH", conflicts_with = "resume")]
+    checkpoint: Option<PathBuf>,
+    /// Resume an interrupted scan from its checkpoint file, which is updated as the scan
+    /// proceeds.
+    #[clap(long, value_name = "CHECKPOINT")]
+    resume: Option<PathBuf>,
+}
+
+/// The contents of a checkpoint file.
+#[derive(serde::Serialize, serde::Deserialize)]
+struct CheckpointFile<S> {
+    /// The description of the scan, e.g. `events of ws://localhost:9944/`.
+    scan: String,
+    /// The progress of the scan.
+    state: S,
+}
+
+impl CheckpointOpts {
+    /// Open the checkpoint of the scan, returns the state to resume the scan from if
+    /// `--resume` is given.
+    pub fn open<S: DeserializeOwned>(
+        &self,
+        scan: &str,
+    ) -> Result<(Checkpoint, Option<S>)> {
+        let state = match self.resume {
+            Some(ref path) => Some(read_checkpoint(path, scan)?),
+            None => None,
+        };
+        let checkpoint = Checkpoint {
+            path: self.resume.clone().or_else(|| self.checkpoint.clone()),
+            scan: scan.to_string(),
+            last_saved: Instant::now(),
+        };
+        Ok((checkpoint, state))
+    }
+}
+
+fn read_checkpoint<S: DeserializeOwned>(path: &Path, scan: &str) -> Result<S> {
+    let contents = std::fs::read_to_string(path)
+        .with_context(|| format!("Failed to read the checkpoint {}", path.display()))?;
+    let file: CheckpointFile<S> = serde_json::from_str(&contents)
+        .with_context(|| format!("Invalid checkpoint file {}", path.display()))?;
+    anyhow::ensure!(
+        file.scan == scan,
+        "The checkpoint {} is of the scan `{}`, not of `{scan}`",
+        path.display(),
+        file.scan
+    );
+    Ok(file.state)
+}
+
+/// The checkpoint file of a running scan, nothing is written without a path.
+#[derive(Debug)]
+pub struct Checkpoint {
+    path: Option<PathBuf>,
+    scan: String,
+    last_saved: Instant,
+}
+
+impl Checkpoint {
+    /// Write the progress of the scan, if [`CHECKPOINT_INTERVAL`] has passed since it was
+    /// last written.
+    pub fn save<S: Serialize>(&mut self, state: &S) -> Result<()> {
+        if self.last_saved.elapsed() < CHECKPOINT_INTERVAL {
+            return Ok(())
+        }
+        self.save_now(state)
+    }
+
+    /// Write the progress of the scan.
+    pub fn save_now<S: Serialize>(&mut self, state: &S) -> Result<()> {
+        let Some(ref path) = self.path else {
+            return Ok(())
+        };
+        let file = CheckpointFile {
+            scan: self.scan.clone(),
+            state,
+        };
+        lock::write_atomic(path, serde_json::to_string(&file)?)?;
+        self.last_saved = Instant::now();
+        Ok(())
+    }
+
+    /// Write the progress of a failed scan, and add how to resume it to the error.
+    pub fn interrupted<S: Serialize>(
+        &mut self,
+        state: &S,
+        err: anyhow::Error,
+    ) -> anyhow::Error {
+        let Some(path) = self.path.clone() else {
+            return err
+                .context("Scan interrupted, pass `--checkpoint` to make it resumable");
+        };
+        match self.save_now(state) {
+            Ok(()) => {
+                err.context(format!(
+                    "Scan interrupted, resume it with `--resume {}`",
+                    path.display()
+                ))
+            }
+            Err(save_err) => {
+                err.context(format!(
+                    "Scan interrupted, writing the checkpoint failed: {save_err:#}"
+                ))
+            }
+        }
+    }
+
+    /// Remove the checkpoint of the completed scan.
+    pub fn finish(self) -> Result<()> {
+        match self.path {
+            Some(ref path) if path.exists() => {
+                std::fs::remove_file(path).with_context(|| {
+                    format!("Failed to remove the checkpoint {}", path.display())
+                })
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn checkpoints_resume_the_same_scan() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("scan.checkpoint");
+        let opts = CheckpointOpts {
+            checkpoint: Some(path.clone()),
+            resume: None,
+        };
+        let (mut checkpoint, state) = opts.open::<u64>("events of node").unwrap();
+        assert_eq!(state, None);
+        checkpoint.save(&7u64).unwrap();
+        assert!(!path.exists(), "saved before the interval passed");
+        checkpoint.save_now(&42u64).unwrap();
+
+        let resume = CheckpointOpts {
+            checkpoint: None,
+            resume: Some(path.clone()),
+        };
+        let (checkpoint, state) = resume.open::<u64>("events of node").unwrap();
+        assert_eq!(state, Some(42));
+        assert!(resume.open::<u64>("events of other node").is_err());
+
+        checkpoint.finish().unwrap();
+        assert!(!path.exists());
+    }
+
+    #[test]
+    fn interrupted_scans_are_resumable() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("scan.checkpoint");
+        let opts = CheckpointOpts {
+            checkpoint: Some(path.clone()),
+            resume: None,
+        };
+        let (mut checkpoint, _) = opts.open::<u64>("scan").unwrap();
+        let err = checkpoint.interrupted(&3u64, anyhow::anyhow!("connection lost"));
+        assert!(format!("{err:#}").contains("--resume"));
+        assert_eq!(read_checkpoint::<u64>(&path, "scan").unwrap(), 3);
+
+        let (mut checkpoint, _) = CheckpointOpts::default().open::<u64>("scan").unwrap();
+        let err = checkpoint.interrupted(&3u64, anyhow::anyhow!("connection lost"));
+        assert!(format!("{err:#}").contains("--checkpoint"));
+    }
+}
@@ -0,0 +1,549 @@
+// Copyright 2018-2023 Parity Technologies (UK) Ltd.
+// This file is part of cargo-contract.
+//
+// cargo-contract is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// cargo-contract is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with cargo-contract.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Analysis of the size of the Wasm code of a contract, and guidance for code exceeding
+//! the maximum code size of a chain.
+//!
+//! The size is broken down by section, by function and by the crate the functions are
+//! from, using the name section of the code. Contracts built with `--keep-debug-symbols`
+//! keep the name section, otherwise the functions are identified by their index. The
+//! guidance suggests the strategies matching the biggest contributors, down to moving
+//! logic into a secondary contract called with `delegate_call`, whose scaffold is
+//! generated with `--scaffold-split`.
+
+use super::{
+    chain_info::ChainLimits,
+    determinism::function_names,
+    extrinsics::ContractArtifacts,
+    AsyncCommand,
+    DefaultConfig,
+};
+use anyhow::{
+    Context,
+    Result,
+};
+use async_trait::async_trait;
+use colored::Colorize;
+use heck::ToUpperCamelCase;
+use std::{
+    collections::BTreeMap,
+    fmt::Write,
+    path::PathBuf,
+};
+use subxt::OnlineClient;
+use wasmparser::{
+    Parser,
+    Payload,
+    TypeRef,
+};
+
+#[derive(Debug, clap::Args)]
+#[clap(
+    name = "code-size",
+    about = "Report the size of the Wasm code and its biggest contributors"
+)]
+pub struct CodeSizeCommand {
+    /// Path to a contract build artifact file: a raw `.wasm` file, a `.contract` bundle
+    /// or a zstd compressed `.contract.zst` bundle.
+    #[clap(value_parser, conflicts_with = "manifest_path")]
+    file: Option<PathBuf>,
+    /// Path to the `Cargo.toml` of the contract.
+    #[clap(long, value_parser)]
+    manifest_path: Option<PathBuf>,
+    /// Websockets url of a substrate node, whose maximum code size the code is checked
+    /// against.
+    #[clap(long, conflicts_with = "max")]
+    url: Option<url::Url>,
+    /// The maximum code size in bytes the code is checked against.
+    #[clap(long, value_name = "BYTES")]
+    max: Option<usize>,
+    /// The number of functions and crates listed.
+    #[clap(long, default_value_t = 10)]
+    top: usize,
+    /// Create the scaffold of a secondary contract with the given name, which the
+    /// contract calls with `delegate_call` to run the logic moved into it.
+    #[clap(long, value_name = "NAME")]
+    scaffold_split: Option<String>,
+    /// Export the report in JSON format.
+    #[clap(long)]
+    output_json: bool,
+}
+
+/// The size of the code of a contract, checked against a maximum.
+#[derive(Debug, serde::Serialize)]
+struct Report {
+    #[serde(flatten)]
+    size: CodeSize,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    max: Option<usize>,
+    suggestions: Vec<String>,
+}
+
+#[async_trait(?Send)]
+impl AsyncCommand for CodeSizeCommand {
+    type Output = Result<()>;
+
+    async fn exec(&self) -> Result<()> {
+        let artifacts = ContractArtifacts::from_manifest_or_file(
+            self.manifest_path.as_ref(),
+            self.file.as_ref(),
+        )?;
+        let code = artifacts.code.as_ref().with_context(|| {
+            format!(
+                "Contract code not found from artifact file {}",
+                artifacts.artifact_path().display()
+            )
+        })?;
+        let code = code.as_bytes();
+        let max = match self.url {
+            Some(ref url) => {
+                let client =
+                    OnlineClient::<DefaultConfig>::from_url(url.as_str()).await?;
+                let limits = ChainLimits::from_metadata(&client.metadata())?;
+                limits.max_code_len.map(|max| max as usize)
+            }
+            None => self.max,
+        };
+        let size = CodeSize::analyze(code)?;
+        let report = Report {
+            suggestions: size.suggestions(),
+            size,
+            max,
+        };
+
+        if self.output_json {
+            println!("{}", serde_json::to_string_pretty(&report)?);
+        } else {
+            print!("{}", report.size.breakdown(self.top));
+            if let Some(max) = max {
+                println!("{} {max} bytes", "Maximum".bright_white().bold());
+            }
+            if max.is_some_and(|max| report.size.total > max) {
+                println!("\nTo reduce the size of the code:");
+                for suggestion in &report.suggestions {
+                    println!("  - {suggestion}");
+                }
+            }
+        }
+
+        if let Some(ref name) = self.scaffold_split {
+            let contract = artifacts
+                .metadata()
+                .map(|metadata| metadata.contract.name)
+                .unwrap_or_else(|_| "primary".to_string());
+            scaffold_split(name, &contract)?;
+            eprintln!(
+                "Created the secondary contract {name}, move the logic of {contract} into \
+                it and call it with `delegate_call`"
+            );
+        }
+        match max {
+            Some(max) if report.size.total > max => {
+                anyhow::bail!(
+                    "The code is {} bytes larger than the maximum of {max} bytes",
+                    report.size.total - max
+                )
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+/// The size of a section, a function or the functions of a crate.
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize)]
+pub struct Contributor {
+    /// The name of the section, function or crate.
+    pub name: String,
+    /// The size in bytes.
+    pub size: usize,
+}
+
+/// The breakdown of the size of Wasm code.
+#[derive(Debug, Default, PartialEq, Eq, serde::Serialize)]
+pub struct CodeSize {
+    /// The size of the code in bytes.
+    pub total: usize,
+    /// The sections of the code, in the order of the code.
+    pub sections: Vec<Contributor>,
+    /// The function bodies, the biggest first.
+    pub functions: Vec<Contributor>,
+    /// The function bodies summed by crate, the biggest first. Empty without a name
+    /// section.
+    pub crates: Vec<Contributor>,
+}
+
+impl CodeSize {
+    /// Break the size of the code down by section, function and crate.
+    pub fn analyze(code: &[u8]) -> Result<Self> {
+        let mut imported = 0;
+        let mut sections = Vec::new();
+        let mut bodies = Vec::new();
+        let mut names = BTreeMap::new();
+        for payload in Parser::new(0).parse_all(code) {
+            let payload = payload.context("Failed to parse the Wasm code")?;
+            if let Some((id, range)) = payload.as_section() {
+                let name = match payload {
+                    Payload::CustomSection(ref section) => {
+                        format!("custom \"{}\"", section.name())
+                    }
+                    _ => section_name(id).to_string(),
+                };
+                sections.push(Contributor {
+                    name,
+                    size: range.len(),
+                });
+            }
+            match payload {
+                Payload::ImportSection(imports) => {
+                    for import in imports {
+                        if let TypeRef::Func(_) = import?.ty {
+                            imported += 1;
+                        }
+                    }
+                }
+                Payload::CodeSectionEntry(body) => bodies.push(body.range().len()),
+                Payload::CustomSection(section) if section.name() == "name" => {
+                    names = function_names(section.data(), section.data_offset())
+                        .unwrap_or_default();
+                }
+                _ => (),
+            }
+        }
+
+        let mut functions = Vec::new();
+        let mut crates = BTreeMap::<String, usize>::new();
+        for (index, size) in (imported..).zip(bodies) {
+            let name = match names.get(&index) {
+                Some(name) => {
+                    *crates.entry(crate_of(name).to_string()).or_default() += size;
+                    name.clone()
+                }
+                None => format!("func[{index}]"),
+            };
+            functions.push(Contributor { name, size });
+        }
+        functions.sort_by_key(|function| std::cmp::Reverse(function.size));
+        let mut crates = crates
+            .into_iter()
+            .map(|(name, size)| Contributor { name, size })
+            .collect::<Vec<_>>();
+        crates.sort_by_key(|krate| std::cmp::Reverse(krate.size));
+        Ok(Self {
+            total: code.len(),
+            sections,
+            functions,
+            crates,
+        })
+    }
+
+    fn section(&self, name: &str) -> usize {
+        self.sections
+            .iter()
+            .filter(|section| section.name == name)
+            .map(|section| section.size)
+            .sum()
+    }
+
+    /// The strategies to reduce the size of the code, the ones matching its biggest
+    /// contributors first.
+    pub fn suggestions(&self) -> Vec<String> {
+        let mut suggestions = Vec::new();
+        let names = self.section("custom \"name\"");
+        if names > 0 {
+            suggestions.push(format!(
+                "The name section takes {names} bytes, build without \
+                `--keep-debug-symbols`"
+            ));
+        }
+        let data = self.section("data");
+        if data * 10 > self.total {
+            suggestions.push(format!(
+                "Static data takes {data} bytes, shorten string literals such as the \
+                messages of `panic!`, `expect` and `assert!`"
+            ));
+        }
+        let formatting = self
+            .functions
+            .iter()
+            .filter(|function| function.name.contains("core::fmt"))
+            .map(|function| function.size)
+            .sum::<usize>();
+        if formatting * 10 > self.total {
+            suggestions.push(format!(
+                "Formatting code takes {formatting} bytes, avoid `format!`, `Debug` \
+                implementations and panics with formatted messages"
+            ));
+        }
+        suggestions.push(
+            "Build with `--release` and `--optimization-passes z`, and disable unneeded \
+            default features of dependencies"
+                .to_string(),
+        );
+        suggestions.push(
+            "Move logic into a secondary contract called with `delegate_call`, \
+            `cargo contract code-size --scaffold-split <NAME>` creates its scaffold"
+                .to_string(),
+        );
+        suggestions
+    }
+
+    /// The breakdown of the size listing the given number of functions and crates.
+    pub fn breakdown(&self, top: usize) -> String {
+        let mut out = String::new();
+        let line = |out: &mut String, name: &str, size: usize| {
+            let percent = size as f64 * 100.0 / self.total.max(1) as f64;
+            let _ = writeln!(out, "  {name:<48} {size:>8} bytes {percent:>5.1}%");
+        };
+        let _ = writeln!(
+            out,
+            "{} {} bytes",
+            "Code size".bright_white().bold(),
+            self.total
+        );
+        let _ = writeln!(out, "{}", "Sections".bright_white().bold());
+        for section in &self.sections {
+            line(&mut out, &section.name, section.size);
+        }
+        if !self.crates.is_empty() {
+            let _ = writeln!(out, "{}", "Crates".bright_white().bold());
+            for krate in self.crates.iter().take(top) {
+                line(&mut out, &krate.name, krate.size);
+            }
+        }
+        let _ = writeln!(out, "{}", "Functions".bright_white().bold());
+        for function in self.functions.iter().take(top) {
+            line(&mut out, &truncate(&function.name, 48), function.size);
+        }
+        out
+    }
+}
+
+/// The report for code larger than the maximum code size of a chain: by how much, the
+/// biggest contributors and the strategies to reduce its size.
+pub fn oversized_report(code: &[u8], max: usize) -> String {
+    let len = code.len();
+    let mut report = format!(
+        "The code is {len} bytes, {} bytes ({:.1}%) larger than the maximum of {max} bytes \
+        of the chain.",
+        len - max,
+        (len - max) as f64 * 100.0 / max as f64
+    );
+    // The bare numbers are reported for code which does not parse.
+    if let Ok(size) = CodeSize::analyze(code) {
+        let _ = write!(
+            report,
+            "\n\nThe biggest contributors:\n{}",
+            size.breakdown(5)
+        );
+        report.push_str("\nTo reduce the size of the code:");
+        for suggestion in size.suggestions() {
+            let _ = write!(report, "\n  - {suggestion}");
+        }
+    }
+    report
+}
+
+/// The name of the section with the id.
+fn section_name(id: u8) -> &'static str {
+    match id {
+        1 => "type",
+        2 => "import",
+        3 => "function",
+        4 => "table",
+        5 => "memory",
+        6 => "global",
+        7 => "export",
+        8 => "start",
+        9 => "element",
+        10 => "code",
+        11 => "data",
+        12 => "data count",
+        13 => "tag",
+        _ => "unknown",
+    }
+}
+
+/// The crate of a Rust function name, e.g. `alloc` for `<alloc::vec::Vec<T> as
+/// core::clone::Clone>::clone`.
+fn crate_of(name: &str) -> &str {
+    let name = name.trim_start_matches(['<', '&', '*']);
+    let end = name
+        .find(|c: char| !(c.is_alphanumeric() || c == '_' || c == '$' || c == '.'))
+        .unwrap_or(name.len());
+    if end == 0 || !name[end..].starts_with("::") {
+        return "(others)"
+    }
+    &name[..end]
+}
+
+fn truncate(name: &str, width: usize) -> String {
+    if name.chars().count() <= width {
+        return name.to_string()
+    }
+    let mut truncated = name.chars().take(width - 1).collect::<String>();
+    truncated.push('…');
+    truncated
+}
+
+/// Create the project of a secondary contract, called by the primary contract with
+/// `delegate_call` to run the logic moved into it.
+fn scaffold_split(name: &str, primary: &str) -> Result<()> {
+    contract_build::new_contract_project(name, None::<PathBuf>)?;
+    let camel_name = name.to_upper_camel_case();
+    let lib = SPLIT_TEMPLATE
+        .replace("{{name}}", name)
+        .replace("{{camel_name}}", &camel_name)
+        .replace("{{primary}}", primary);
+    let path = PathBuf::from(name).join("lib.rs");
+    std::fs::write(&path, lib)
+        .with_context(|| format!("Failed to write {}", path.display()))
+}
+
+const SPLIT_TEMPLATE: &str = r#"#![cfg_attr(not(feature = "std"), no_std, no_main)]
+
+//! Logic moved out of the `{{primary}}` contract to reduce the size of its code.
+//!
+//! `{{primary}}` calls the messages of this contract with `delegate_call`, so they run
+//! on the storage and with the balance of `{{primary}}`. Upload the code of this
+//! contract, store its code hash in `{{primary}}` and forward the calls:
+//!
+//! ```ignore
+//! use ink::env::call::{build_call, ExecutionInput, Selector};
+//!
+//! build_call::<ink::env::DefaultEnvironment>()
+//!     .delegate(self.logic_code_hash)
+//!     .exec_input(ExecutionInput::new(Selector::new(ink::selector_bytes!("run"))))
+//!     .returns::<()>()
+//!     .invoke();
+//! ```
+
+#[ink::contract]
+mod {{name}} {
+    /// The storage of `{{primary}}`. The fields must be the same, in the same order and
+    /// with the same types, as the ones of the storage struct of `{{primary}}`, since the
+    /// messages of this contract read and write its storage.
+    #[ink(storage)]
+    pub struct {{camel_name}} {
+        value: bool,
+    }
+
+    impl {{camel_name}} {
+        /// The constructor is never called through `delegate_call`, it only exists for
+        /// the code to be uploadable.
+        #[ink(constructor)]
+        pub fn new() -> Self {
+            Self { value: false }
+        }
+
+        /// A message moved out of `{{primary}}`, called with the selector of `run`.
+        #[ink(message)]
+        pub fn run(&mut self) {
+            self.value = !self.value;
+        }
+    }
+}
+"#;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A module with the function bodies of the given sizes and a name section naming
+    /// them.
+    fn module(bodies: &[usize], names: &[&str]) -> Vec<u8> {
+        let section = |id: u8, contents: Vec<u8>| {
+            [vec![id, contents.len() as u8], contents].concat()
+        };
+        let mut code = vec![bodies.len() as u8];
+        for size in bodies {
+            // No locals, `nop`s and `end`.
+            code.extend([*size as u8, 0]);
+            code.extend(vec![0x01; size - 2]);
+            code.push(0x0b);
+        }
+        let mut function_names = vec![names.len() as u8];
+        for (index, name) in names.iter().enumerate() {
+            function_names.extend([index as u8, name.len() as u8]);
+            function_names.extend(name.as_bytes());
+        }
+        let mut name_section = [&[4][..], b"name"].concat();
+        name_section.extend(section(1, function_names));
+        [
+            b"\0asm\x01\0\0\0".to_vec(),
+            section(1, vec![1, 0x60, 0, 0]),
+            section(
+                3,
+                [vec![bodies.len() as u8], vec![0; bodies.len()]].concat(),
+            ),
+            section(10, code),
+            section(0, name_section),
+        ]
+        .concat()
+    }
+
+    #[test]
+    fn size_is_broken_down_by_function_and_crate() {
+        let code = module(
+            &[10, 40, 20],
+            &[
+                "flipper::flipper::Flipper::flip",
+                "core::fmt::write",
+                "<alloc::vec::Vec<T> as core::clone::Clone>::clone",
+            ],
+        );
+        let size = CodeSize::analyze(&code).unwrap();
+
+        assert_eq!(size.total, code.len());
+        let names = |contributors: &[Contributor]| {
+            contributors
+                .iter()
+                .map(|contributor| (contributor.name.clone(), contributor.size))
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(
+            names(&size.crates),
+            [
+                ("core".to_string(), 40),
+                ("alloc".to_string(), 20),
+                ("flipper".to_string(), 10)
+            ]
+        );
+        assert_eq!(size.functions[0].name, "core::fmt::write");
+        assert_eq!(size.section("code"), 74);
+
+        let suggestions = size.suggestions();
+        assert!(suggestions[0].contains("`--keep-debug-symbols`"));
+        assert!(suggestions[1].starts_with("Formatting code takes 40 bytes"));
+    }
+
+    #[test]
+    fn oversized_report_tells_how_much_the_code_is_over() {
+        let code = module(&[10], &[]);
+        let report = oversized_report(&code, code.len() - 10);
+        assert!(
+            report.starts_with(&format!("The code is {} bytes, 10 bytes", code.len()))
+        );
+        assert!(report.contains("func[0]"));
+        assert!(report.contains("`delegate_call`"));
+
+        let report = oversized_report(&[0; 20], 10);
+        assert_eq!(
+            report,
+            "The code is 20 bytes, 10 bytes (100.0%) larger than the maximum of 10 bytes \
+            of the chain."
+        );
+    }
+}
@@ -0,0 +1,678 @@
+// Copyright 2018-2023 Parity Technologies (UK) Ltd.
+// This file is part of cargo-contract.
+//
+// cargo-contract is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// cargo-contract is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with cargo-contract.  If not, see <http://www.gnu.org/licenses/>.
+
+//! The optional `cargo-contract.toml` configuration file.
+//!
+//! The file is looked up in the current directory and its ancestors, or loaded from the
+//! path in the `CARGO_CONTRACT_CONFIG` environment variable. It holds named network
+//! profiles, selected with `--network <NAME>`:
+//!
+//! ```toml
+//! [network.shibuya]
+//! url = "wss://rpc.shibuya.astar.network"
+//! ```
+//!
+//! Setting `read-only = true`, globally or for a network profile, forbids submitting any
+//! extrinsic. An `archive-url` is queried for the state of past blocks pruned by the node
+//! of the profile, e.g. by `storage proof --at`.
+//!
+//! A network profile can restrict the signers of its extrinsics, e.g. allowing only
+//! extrinsics signed offline by a multisig for deployments to production, while a known
+//! account may still sign calls with a secret key URI:
+//!
+//! ```toml
+//! [network.production.signers]
+//! allow = ["offline"]
+//!
+//! [network.production.signers.commands.call]
+//! allow = ["suri", "offline"]
+//! accounts = ["5GrwvaEF5zXb26Fz9rcQpDWS57CtERHpNehXCPcNoHGKutQY"]
+//! ```
+//!
+//! Contract types can be given a custom notation for the arguments of `call` and
+//! `instantiate` and for decoded output, keyed by the path of the type:
+//!
+//! ```toml
+//! [types."my_contract::Amount"]
+//! format = "token"
+//! decimals = 12
+//! symbol = "UNIT"
+//! ```
+//!
+//! A network can also be selected by the name of a chain of the catalogue of `cargo
+//! contract chains`, which a `[chains]` section extends.
+//!
+//! A `[toolchain]` section pins the Rust toolchain of `build --hermetic`, a `[ci]`
+//! section configures the pipelines generated by `init-ci`, and a `[permissions]`
+//! section enforces the signed whitelist of messages of a `permissions.toml` file.
+//!
+//! Command aliases are expanded before the arguments are parsed, with the remaining
+//! arguments appended:
+//!
+//! ```toml
+//! [alias]
+//! flip = "call --message flip --suri //Alice --skip-confirm"
+//! ```
+
+use super::{
+    chains::{
+        Catalogue,
+        Chain,
+    },
+    identity::Identities,
+};
+use anyhow::{
+    Context,
+    Result,
+};
+use contract_metadata::ContractMetadata;
+use contract_transcode::{
+    env_types::{
+        self,
+        PathKey,
+    },
+    ContractMessageTranscoder,
+    TranscoderBuilder,
+};
+use scale_info::TypeDefPrimitive;
+use std::{
+    collections::BTreeMap,
+    fmt,
+    path::{
+        Path,
+        PathBuf,
+    },
+};
+
+/// The file name of the configuration file.
+pub const CONFIG_FILE_NAME: &str = "cargo-contract.toml";
+
+/// The environment variable overriding the path of the configuration file.
+pub const CONFIG_PATH_ENV: &str = "CARGO_CONTRACT_CONFIG";
+
+/// The contents of the `cargo-contract.toml` configuration file.
+#[derive(Debug, Default, serde::Deserialize)]
+pub struct Config {
+    /// Forbid submitting any extrinsic, commands are only dry-run.
+    #[serde(default, rename = "read-only")]
+    pub read_only: bool,
+    /// The network profiles by name.
+    #[serde(default)]
+    pub network: BTreeMap<String, NetworkProfile>,
+    /// The chains added to the built-in catalogue, by name.
+    #[serde(default)]
+    pub chains: BTreeMap<String, super::chains::Chain>,
+    /// The custom notations of contract types, by the path of the type.
+    #[serde(default)]
+    pub types: BTreeMap<String, TypeOverride>,
+    /// The Rust toolchain pinned for hermetic builds.
+    pub toolchain: Option<super::hermetic::ToolchainPin>,
+    /// The command aliases by name.
+    #[serde(default)]
+    pub alias: BTreeMap<String, Alias>,
+    /// The settings of the pipelines generated by `init-ci`.
+    pub ci: Option<super::init_ci::CiConfig>,
+    /// The signed whitelist of the messages `call` may submit by network and role.
+    pub permissions: Option<super::permissions::PermissionsConfig>,
+}
+
+/// The arguments a command alias expands to.
+#[derive(Debug, Clone, PartialEq, Eq, serde::Deserialize)]
+#[serde(untagged)]
+pub enum Alias {
+    /// The arguments separated by whitespace.
+    Command(String),
+    /// The arguments, which may contain whitespace.
+    Args(Vec<String>),
+}
+
+impl Alias {
+    /// Returns the arguments the alias expands to.
+    pub fn args(&self) -> Vec<String> {
+        match self {
+            Self::Command(command) => {
+                command.split_whitespace().map(String::from).collect()
+            }
+            Self::Args(args) => args.clone(),
+        }
+    }
+}
+
+/// The settings for a network, selected with `--network <NAME>`.
+#[derive(Debug, Clone, Default, serde::Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct NetworkProfile {
+    /// Websockets url of a node of the network.
+    pub url: Option<url::Url>,
+    /// Websockets url of an archive node of the network, queried for the state of the
+    /// blocks pruned by the node of `url`.
+    pub archive_url: Option<url::Url>,
+    /// Forbid submitting any extrinsic to the network, commands are only dry-run.
+    #[serde(default)]
+    pub read_only: bool,
+    /// Pace the RPC requests to the network conservatively, for community RPC providers
+    /// banning clients sending bursts of requests.
+    #[serde(default)]
+    pub public_endpoint: bool,
+    /// The backend used to deploy contracts to the network.
+    #[serde(default)]
+    pub backend: Backend,
+    /// Settings for the `phala` backend.
+    pub phala: Option<super::extrinsics::phala::PhalaProfile>,
+    /// Settings for the dApp staking integration of Astar family chains.
+    #[cfg(feature = "astar")]
+    pub astar: Option<super::extrinsics::astar::AstarProfile>,
+    /// The signers allowed to sign the extrinsics submitted to the network.
+    pub signers: Option<SignerPolicy>,
+}
+
+/// The signers allowed to sign the extrinsics of the commands, by the way of signing and
+/// by account.
+#[derive(Debug, Clone, Default, PartialEq, Eq, serde::Deserialize)]
+pub struct SignerPolicy {
+    /// The rule of the commands without a rule of their own.
+    #[serde(flatten)]
+    pub default: SignerRule,
+    /// The rules of the commands by name, e.g. `upload`.
+    #[serde(default)]
+    pub commands: BTreeMap<String, SignerRule>,
+}
+
+/// The signers allowed to sign the extrinsics of a command.
+#[derive(Debug, Clone, Default, PartialEq, Eq, serde::Deserialize)]
+pub struct SignerRule {
+    /// The ways of signing allowed, any if not given.
+    pub allow: Option<Vec<SignerKind>>,
+    /// The accounts allowed to sign, any if not given.
+    pub accounts: Option<Vec<subxt::utils::AccountId32>>,
+}
+
+/// The ways of signing an extrinsic.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum SignerKind {
+    /// Signed by cargo-contract with the secret key URI given by `--suri`.
+    Suri,
+    /// Signed outside of cargo-contract, after building the extrinsic with `--offline`.
+    Offline,
+    /// Signed by cargo-contract with the key of a keystore file, `--signer keystore:`.
+    Keystore,
+    /// Signed by a remote signing service, `--signer remote:`.
+    Remote,
+    /// Signed by a Ledger device, `--signer ledger`.
+    Ledger,
+}
+
+impl fmt::Display for SignerKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Suri => write!(f, "suri"),
+            Self::Offline => write!(f, "offline"),
+            Self::Keystore => write!(f, "keystore"),
+            Self::Remote => write!(f, "remote"),
+            Self::Ledger => write!(f, "ledger"),
+        }
+    }
+}
+
+impl SignerPolicy {
+    /// Returns an error if the account is not allowed to sign the extrinsics of the
+    /// command in the given way.
+    pub fn check(
+        &self,
+        command: &str,
+        kind: SignerKind,
+        account: &subxt::utils::AccountId32,
+    ) -> Result<()> {
+        let rule = self.commands.get(command).unwrap_or(&self.default);
+        if let Some(ref allow) = rule.allow {
+            anyhow::ensure!(
+                allow.contains(&kind),
+                "Signing `{command}` with the `{kind}` signer is forbidden by the signer \
+                policy, allowed: {}",
+                allow
+                    .iter()
+                    .map(|kind| format!("`{kind}`"))
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            );
+        }
+        if let Some(ref accounts) = rule.accounts {
+            anyhow::ensure!(
+                accounts.contains(account),
+                "The account {account} is not allowed to sign `{command}` by the signer \
+                policy"
+            );
+        }
+        Ok(())
+    }
+}
+
+/// The backend used to deploy contracts.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, serde::Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Backend {
+    /// Contracts are executed on-chain by `pallet-contracts`.
+    #[default]
+    PalletContracts,
+    /// Phat contracts are executed off-chain by a cluster of Phala workers.
+    Phala,
+}
+
+/// A custom notation for the values of a contract type.
+#[derive(Debug, Clone, PartialEq, Eq, serde::Deserialize)]
+#[serde(tag = "format", rename_all = "kebab-case")]
+pub enum TypeOverride {
+    /// An amount of tokens, entered as a decimal number of tokens. The type must be
+    /// encoded as a `u128`, e.g. a newtype wrapping a `Balance`.
+    Token {
+        /// The number of decimals of the token.
+        decimals: u8,
+        /// The symbol the amounts are displayed with.
+        symbol: Option<String>,
+    },
+    /// An account id, entered and displayed as an SS58 address.
+    AccountId,
+    /// An account id, displayed as an SS58 address followed by the display name of its
+    /// on-chain identity, looked up on the node the command connects to.
+    Identity,
+    /// A 256 bit unsigned integer, e.g. a `U256`, entered and displayed in decimal
+    /// notation.
+    Decimal,
+    /// A timestamp encoded as a `u64`, displayed as an RFC 3339 UTC date and time.
+    Timestamp {
+        /// The unit of the timestamp, by default milliseconds as the ink! `Timestamp`.
+        #[serde(default)]
+        unit: TimestampUnit,
+    },
+}
+
+/// The unit of a timestamp with the `timestamp` notation.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, serde::Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum TimestampUnit {
+    /// Milliseconds since the Unix epoch.
+    #[default]
+    Milliseconds,
+    /// Seconds since the Unix epoch.
+    Seconds,
+}
+
+impl From<TimestampUnit> for env_types::TimestampUnit {
+    fn from(unit: TimestampUnit) -> Self {
+        match unit {
+            TimestampUnit::Milliseconds => Self::Milliseconds,
+            TimestampUnit::Seconds => Self::Seconds,
+        }
+    }
+}
+
+impl TypeOverride {
+    /// Register the custom notation for the type with the given path, the `identity`
+    /// notation displays the names of the given identities.
+    fn register(
+        &self,
+        builder: TranscoderBuilder,
+        path: &str,
+        identities: &Identities,
+    ) -> Result<TranscoderBuilder> {
+        let path_key = PathKey::from(path);
+        match self {
+            Self::Token { decimals, symbol } => {
+                if let Some(primitive) = builder.primitive_type_of(&path_key) {
+                    anyhow::ensure!(
+                        primitive == TypeDefPrimitive::U128,
+                        "The `token` format of type `{path}` requires it to be encoded as \
+                        a u128, not as a {primitive:?}"
+                    );
+                }
+                let token_amount =
+                    env_types::TokenAmount::new(*decimals, symbol.clone())?;
+                Ok(builder
+                    .register_custom_type_transcoder_for_path(path_key, token_amount))
+            }
+            Self::AccountId => {
+                Ok(builder.register_custom_type_transcoder_for_path(
+                    path_key,
+                    env_types::AccountId,
+                ))
+            }
+            Self::Identity => {
+                let identities = identities.clone();
+                Ok(builder.register_custom_type_renderer_for_path(
+                    path_key,
+                    env_types::NamedAccountId::new(move |account| {
+                        identities.get(&account.0.into())
+                    }),
+                ))
+            }
+            Self::Decimal => {
+                Ok(builder.register_custom_type_transcoder_for_path(
+                    path_key,
+                    env_types::U256Decimal,
+                ))
+            }
+            Self::Timestamp { unit } => {
+                if let Some(primitive) = builder.primitive_type_of(&path_key) {
+                    anyhow::ensure!(
+                        primitive == TypeDefPrimitive::U64,
+                        "The `timestamp` format of type `{path}` requires it to be \
+                        encoded as a u64, not as a {primitive:?}"
+                    );
+                }
+                Ok(builder.register_custom_type_renderer_for_path(
+                    path_key,
+                    env_types::Timestamp::new((*unit).into()),
+                ))
+            }
+        }
+    }
+}
+
+impl Config {
+    /// Load the configuration file, returns the default configuration if there is none.
+    pub fn load() -> Result<Self> {
+        match Self::path()? {
+            Some(path) => Self::load_from(&path),
+            None => Ok(Self::default()),
+        }
+    }
+
+    /// Load the configuration from the file at the given path.
+    pub fn load_from(path: &Path) -> Result<Self> {
+        let contents = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read {}", path.display()))?;
+        Self::parse(&contents).with_context(|| format!("Invalid {}", path.display()))
+    }
+
+    /// Parse the configuration from the contents of a configuration file.
+    pub fn parse(contents: &str) -> Result<Self> {
+        Ok(toml::from_str(contents)?)
+    }
+
+    /// Returns the path of the configuration file, if one exists.
+    pub fn path() -> Result<Option<PathBuf>> {
+        if let Some(path) = std::env::var_os(CONFIG_PATH_ENV) {
+            return Ok(Some(PathBuf::from(path)))
+        }
+        let cwd = std::env::current_dir()?;
+        Ok(cwd
+            .ancestors()
+            .map(|dir| dir.join(CONFIG_FILE_NAME))
+            .find(|path| path.exists()))
+    }
+
+    /// Construct a transcoder for the contract metadata, with the custom notations of the
+    /// `types` configuration.
+    pub fn contract_transcoder(
+        &self,
+        metadata: ContractMetadata,
+    ) -> Result<ContractMessageTranscoder> {
+        self.contract_transcoder_with_identities(metadata, &Identities::default())
+    }
+
+    /// Construct a transcoder for the contract metadata, with the custom notations of the
+    /// `types` configuration. The `identity` notation displays the names of the given
+    /// identities.
+    pub fn contract_transcoder_with_identities(
+        &self,
+        metadata: ContractMetadata,
+        identities: &Identities,
+    ) -> Result<ContractMessageTranscoder> {
+        ContractMessageTranscoder::try_from_with_custom_types(metadata, |builder| {
+            self.types
+                .iter()
+                .try_fold(builder, |builder, (path, type_override)| {
+                    type_override
+                        .register(builder, path, identities)
+                        .with_context(|| {
+                            format!("Invalid configuration of type `{path}`")
+                        })
+                })
+        })
+    }
+
+    /// Returns `true` if a type is displayed with the `identity` notation.
+    pub fn uses_identity_notation(&self) -> bool {
+        self.types
+            .values()
+            .any(|type_override| *type_override == TypeOverride::Identity)
+    }
+
+    /// Returns the arguments the command expands to if it is an alias. An alias may start
+    /// with another alias, names of the builtin commands are never expanded.
+    pub fn expand_alias(
+        &self,
+        command: &str,
+        is_builtin: impl Fn(&str) -> bool,
+    ) -> Result<Option<Vec<String>>> {
+        let mut expanded: Vec<&str> = Vec::new();
+        let mut args = vec![command.to_string()];
+        while let Some((name, alias)) = args
+            .first()
+            .filter(|name| !is_builtin(name))
+            .and_then(|name| self.alias.get_key_value(name))
+        {
+            if expanded.contains(&name.as_str()) {
+                anyhow::bail!("The alias `{command}` expands to itself")
+            }
+            expanded.push(name);
+            args.splice(0..1, alias.args());
+        }
+        Ok((!expanded.is_empty()).then_some(args))
+    }
+
+    /// Returns the network profile with the given name.
+    pub fn network(&self, name: &str) -> Result<&NetworkProfile> {
+        self.network.get(name).with_context(|| {
+            format!("No network profile `{name}` found in the `{CONFIG_FILE_NAME}` file")
+        })
+    }
+
+    /// Returns the network profile with the given name, or the profile of the chain of
+    /// the catalogue with that name if the file has no such profile.
+    pub fn network_profile(&self, name: &str) -> Result<NetworkProfile> {
+        let chain = self.catalogue().get(name).map(Chain::profile);
+        match chain {
+            Some(profile) if !self.network.contains_key(name) => Ok(profile),
+            _ => self.network(name).cloned(),
+        }
+    }
+
+    /// Returns the built-in catalogue of chains, extended by the `chains` of the file.
+    pub fn catalogue(&self) -> Catalogue {
+        let mut catalogue = Catalogue::builtin();
+        catalogue.extend(&self.chains);
+        catalogue
+    }
+
+    /// Returns the archive node of the network profile of the node with the given url.
+    pub fn archive_url(&self, url: &url::Url) -> Option<&url::Url> {
+        self.network
+            .values()
+            .find(|profile| profile.url.as_ref() == Some(url))
+            .and_then(|profile| profile.archive_url.as_ref())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn network_profiles_are_parsed() {
+        let config = Config::parse(
+            r#"
+            [network.local]
+            url = "ws://localhost:9944"
+
+            [network.production]
+            url = "wss://rpc.example.com"
+            archive-url = "wss://archive.example.com"
+
+            [network.empty]
+            "#,
+        )
+        .expect("config must be valid");
+
+        assert_eq!(
+            config.network("local").unwrap().url,
+            Some("ws://localhost:9944".parse().unwrap())
+        );
+        assert_eq!(config.network("empty").unwrap().url, None);
+        assert!(config.network("missing").is_err());
+        assert_eq!(
+            config.archive_url(&"wss://rpc.example.com".parse().unwrap()),
+            Some(&"wss://archive.example.com".parse().unwrap())
+        );
+        assert_eq!(
+            config.archive_url(&"ws://localhost:9944".parse().unwrap()),
+            None
+        );
+    }
+
+    #[test]
+    fn read_only_is_parsed() {
+        let config = Config::parse(
+            r#"
+            [network.production]
+            url = "wss://rpc.example.com"
+            read-only = true
+
+            [network.local]
+            "#,
+        )
+        .expect("config must be valid");
+
+        assert!(!config.read_only);
+        assert!(config.network("production").unwrap().read_only);
+        assert!(!config.network("local").unwrap().read_only);
+        assert!(Config::parse("read-only = true").unwrap().read_only);
+    }
+
+    #[test]
+    fn signer_policies_are_enforced() {
+        let config = Config::parse(
+            r#"
+            [network.production.signers]
+            allow = ["offline"]
+
+            [network.production.signers.commands.call]
+            allow = ["suri", "offline"]
+            accounts = ["5GrwvaEF5zXb26Fz9rcQpDWS57CtERHpNehXCPcNoHGKutQY"]
+            "#,
+        )
+        .expect("config must be valid");
+        let policy = config
+            .network("production")
+            .unwrap()
+            .signers
+            .clone()
+            .unwrap();
+        let alice: subxt::utils::AccountId32 =
+            "5GrwvaEF5zXb26Fz9rcQpDWS57CtERHpNehXCPcNoHGKutQY"
+                .parse()
+                .unwrap();
+        let bob: subxt::utils::AccountId32 =
+            "5FHneW46xGXgs5mUiveU4sbTyGBzmstUspZC92UhjJM694ty"
+                .parse()
+                .unwrap();
+
+        assert!(policy.check("upload", SignerKind::Offline, &bob).is_ok());
+        assert!(policy.check("upload", SignerKind::Suri, &alice).is_err());
+        assert!(policy.check("call", SignerKind::Suri, &alice).is_ok());
+        assert!(policy.check("call", SignerKind::Offline, &bob).is_err());
+        assert!(SignerPolicy::default()
+            .check("upload", SignerKind::Suri, &bob)
+            .is_ok());
+        assert!(Config::parse("[network.a.signers]\nallow = [\"ledger\"]").is_ok());
+        assert!(Config::parse("[network.a.signers]\nallow = [\"yubikey\"]").is_err());
+    }
+
+    #[test]
+    fn type_overrides_are_parsed() {
+        let config = Config::parse(
+            r#"
+            [types."my_contract::Amount"]
+            format = "token"
+            decimals = 12
+            symbol = "UNIT"
+
+            [types."my_contract::Owner"]
+            format = "account-id"
+
+            [types."my_contract::Deadline"]
+            format = "timestamp"
+            unit = "seconds"
+            "#,
+        )
+        .expect("config must be valid");
+
+        assert_eq!(
+            config.types["my_contract::Amount"],
+            TypeOverride::Token {
+                decimals: 12,
+                symbol: Some(String::from("UNIT"))
+            }
+        );
+        assert_eq!(config.types["my_contract::Owner"], TypeOverride::AccountId);
+        assert_eq!(
+            config.types["my_contract::Deadline"],
+            TypeOverride::Timestamp {
+                unit: TimestampUnit::Seconds
+            }
+        );
+        assert!(Config::parse("[types.\"a::B\"]\nformat = \"unknown\"").is_err());
+    }
+
+    #[test]
+    fn aliases_are_expanded() {
+        let config = Config::parse(
+            r#"
+            [alias]
+            flip = "call --message flip --suri //Alice"
+            flip-local = ["flip", "--url", "ws://localhost:9944"]
+            loop = "loop --verbose"
+            build = "build --release"
+            "#,
+        )
+        .expect("config must be valid");
+        let is_builtin = |name: &str| name == "build" || name == "call";
+        let expand = |command| config.expand_alias(command, is_builtin);
+
+        assert_eq!(
+            expand("flip-local").unwrap().unwrap(),
+            [
+                "call",
+                "--message",
+                "flip",
+                "--suri",
+                "//Alice",
+                "--url",
+                "ws://localhost:9944"
+            ]
+        );
+        assert_eq!(expand("build").unwrap(), None);
+        assert_eq!(expand("unknown").unwrap(), None);
+        assert!(expand("loop").is_err());
+    }
+
+    #[test]
+    fn empty_config_is_valid() {
+        let config = Config::parse("").expect("config must be valid");
+        assert!(config.network.is_empty());
+    }
+}
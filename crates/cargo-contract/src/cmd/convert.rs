@@ -0,0 +1,446 @@
+// Copyright 2018-2023 Parity Technologies (UK) Ltd.
+// This file is part of cargo-contract.
+//
+// cargo-contract is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// cargo-contract is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with cargo-contract.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Conversions between the units of the token of a chain, and between block counts,
+//! durations, block numbers and timestamps, using the constants of the chain.
+//!
+//! The block time is the `ExpectedBlockTime` of `Babe`, or twice the `MinimumPeriod` of
+//! `Timestamp` on chains authoring blocks with `Aura`. Block numbers and timestamps are
+//! extrapolated from the latest block with the block time, so they are estimates.
+
+use super::{
+    chain_info::pallet_constant,
+    extrinsics::BalanceVariant,
+    AsyncCommand,
+    Balance,
+    Client,
+    TokenMetadata,
+};
+use anyhow::{
+    anyhow,
+    Context,
+    Result,
+};
+use async_trait::async_trait;
+use scale::Decode;
+use std::{
+    str::FromStr,
+    time::Duration,
+};
+use subxt::{
+    Metadata,
+    OnlineClient,
+};
+
+#[derive(Debug, clap::Args)]
+#[clap(
+    name = "convert",
+    about = "Convert amounts, block counts, durations and timestamps with the chain constants"
+)]
+pub struct ConvertCommand {
+    /// The quantity to convert, e.g. `1.5DOT`, `1000000`, `100blocks`, `2h`, `#1000` or
+    /// `1700000000unix`.
+    quantity: String,
+    /// The unit of the quantity, if it is not part of it: a unit of the token such as
+    /// `DOT` or `mDOT`, `planck`, `blocks`, `ms`, `s`, `min`, `h`, `d`, `block` or
+    /// `unix`.
+    unit: Option<String>,
+    /// The unit to convert to: `planck`, the token symbol, `blocks`, `duration`,
+    /// `block` or `timestamp`.
+    #[clap(long)]
+    to: Target,
+    /// Websockets url of a substrate node.
+    #[clap(long, default_value = "ws://localhost:9944")]
+    url: url::Url,
+    /// Export the converted value in JSON format.
+    #[clap(long)]
+    output_json: bool,
+}
+
+#[async_trait(?Send)]
+impl AsyncCommand for ConvertCommand {
+    type Output = Result<()>;
+
+    async fn exec(&self) -> Result<()> {
+        let quantity = Quantity::parse(&self.quantity, self.unit.as_deref())?;
+        let client = OnlineClient::from_url(self.url.as_str()).await?;
+        let token_metadata = TokenMetadata::query(&client).await?;
+        let clock = match quantity {
+            Quantity::Amount(_) => None,
+            _ => Some(BlockClock::query(&client).await?),
+        };
+        let converted = convert(&quantity, &self.to, &token_metadata, clock.as_ref())?;
+        if self.output_json {
+            println!("{}", serde_json::to_string_pretty(&converted)?);
+        } else {
+            println!("{}", converted.value);
+        }
+        Ok(())
+    }
+}
+
+/// A quantity given to `convert`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Quantity {
+    /// An amount of the token, in planck or denominated.
+    Amount(BalanceVariant),
+    /// A number of blocks.
+    Blocks(u64),
+    /// A duration.
+    Duration(Duration),
+    /// The number of a block.
+    BlockNumber(u64),
+    /// A unix timestamp in seconds.
+    Timestamp(u64),
+}
+
+impl Quantity {
+    /// Parse the quantity, with the unit given separately or as its suffix.
+    pub fn parse(quantity: &str, unit: Option<&str>) -> Result<Self> {
+        let quantity = quantity.trim().replace('_', "");
+        if let Some(number) = quantity.strip_prefix('#') {
+            anyhow::ensure!(unit.is_none(), "A block number `#{number}` has no unit");
+            return Ok(Self::BlockNumber(parse_integer(number)?))
+        }
+        let (number, unit) = match unit {
+            Some(unit) => (quantity.as_str(), unit.trim()),
+            None => {
+                quantity.split_at(
+                    quantity
+                        .find(|ch: char| !(ch.is_ascii_digit() || ch == '.'))
+                        .unwrap_or(quantity.len()),
+                )
+            }
+        };
+        let seconds = |factor: f64| -> Result<Self> {
+            let number = f64::from_str(number)
+                .map_err(|_| anyhow!("Invalid duration `{number}`"))?;
+            anyhow::ensure!(number >= 0.0, "A duration can not be negative");
+            Ok(Self::Duration(Duration::from_secs_f64(number * factor)))
+        };
+        match unit {
+            "" | "planck" => {
+                Ok(Self::Amount(BalanceVariant::Default(parse_integer(
+                    number,
+                )?)))
+            }
+            "blocks" => Ok(Self::Blocks(parse_integer(number)?)),
+            "block" => Ok(Self::BlockNumber(parse_integer(number)?)),
+            "unix" => Ok(Self::Timestamp(parse_integer(number)?)),
+            "ms" => seconds(0.001),
+            "s" | "sec" | "secs" => seconds(1.0),
+            "min" | "mins" => seconds(60.0),
+            "h" => seconds(3_600.0),
+            "d" => seconds(86_400.0),
+            unit => {
+                Ok(Self::Amount(BalanceVariant::from_str(&format!(
+                    "{number}{unit}"
+                ))?))
+            }
+        }
+    }
+}
+
+/// The unit to convert a quantity to.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Target {
+    Planck,
+    /// The token of the chain, denominated with the best fitting unit prefix.
+    Token(String),
+    Blocks,
+    Duration,
+    BlockNumber,
+    Timestamp,
+}
+
+impl FromStr for Target {
+    type Err = anyhow::Error;
+
+    fn from_str(target: &str) -> Result<Self> {
+        Ok(match target {
+            "planck" => Self::Planck,
+            "blocks" => Self::Blocks,
+            "duration" => Self::Duration,
+            "block" => Self::BlockNumber,
+            "timestamp" => Self::Timestamp,
+            "" => anyhow::bail!("No unit to convert to"),
+            symbol => Self::Token(symbol.to_string()),
+        })
+    }
+}
+
+/// A converted quantity.
+#[derive(Debug, PartialEq, Eq, serde::Serialize)]
+pub struct Converted {
+    /// The converted value, as displayed.
+    pub value: String,
+    /// The converted value as a plain number: planck, blocks, seconds, a block number
+    /// or a unix timestamp in seconds.
+    pub number: String,
+}
+
+impl Converted {
+    fn number(number: impl ToString) -> Self {
+        let number = number.to_string();
+        Self {
+            value: number.clone(),
+            number,
+        }
+    }
+}
+
+/// The block time of a chain, and the number and timestamp of its latest block.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BlockClock {
+    pub block_time: Duration,
+    pub latest_block: u64,
+    /// The unix timestamp of the latest block in milliseconds.
+    pub latest_timestamp: u64,
+}
+
+impl BlockClock {
+    /// Query the block time and the latest block of the chain.
+    pub async fn query(client: &Client) -> Result<Self> {
+        let block_time = block_time(&client.metadata())?;
+        let block = client.blocks().at_latest().await?;
+        let now = block
+            .storage()
+            .fetch(&subxt::dynamic::storage_root("Timestamp", "Now"))
+            .await?
+            .context("The chain has no `Timestamp::Now`")?;
+        Ok(Self {
+            block_time,
+            latest_block: block.number().into(),
+            latest_timestamp: u64::decode(&mut now.encoded())?,
+        })
+    }
+
+    /// The number of blocks produced in the duration, rounded up.
+    pub fn blocks_in(&self, duration: Duration) -> u64 {
+        let blocks = duration
+            .as_millis()
+            .div_ceil(self.block_time.as_millis().max(1));
+        blocks.try_into().unwrap_or(u64::MAX)
+    }
+
+    /// The time it takes to produce the number of blocks.
+    pub fn duration_of(&self, blocks: u64) -> Duration {
+        self.block_time
+            .saturating_mul(blocks.try_into().unwrap_or(u32::MAX))
+    }
+
+    /// The estimated unix timestamp, in seconds, of the block.
+    pub fn timestamp_of(&self, block: u64) -> u64 {
+        let block_time = self.block_time.as_millis() as i128;
+        let offset = (block as i128 - self.latest_block as i128) * block_time;
+        ((self.latest_timestamp as i128 + offset).max(0) / 1_000) as u64
+    }
+
+    /// The estimated number of the block produced at the unix timestamp in seconds.
+    pub fn block_at(&self, timestamp: u64) -> Result<u64> {
+        let block_time = self.block_time.as_millis().max(1) as i128;
+        let offset = (timestamp as i128 * 1_000 - self.latest_timestamp as i128)
+            .div_euclid(block_time);
+        u64::try_from(self.latest_block as i128 + offset)
+            .map_err(|_| anyhow!("The chain did not exist at the timestamp {timestamp}"))
+    }
+}
+
+/// The block time of the chain, from the constants of `Babe` or `Timestamp`.
+pub fn block_time(metadata: &Metadata) -> Result<Duration> {
+    let millis = |pallet, name| {
+        pallet_constant(metadata, pallet, name)
+            .and_then(|value| value.as_u128())
+            .and_then(|value| u64::try_from(value).ok())
+    };
+    millis("Babe", "ExpectedBlockTime")
+        .or_else(|| millis("Timestamp", "MinimumPeriod").map(|period| period * 2))
+        .filter(|millis| *millis > 0)
+        .map(Duration::from_millis)
+        .ok_or_else(|| {
+            anyhow!(
+                "The block time of the chain is unknown, it has neither \
+                `Babe::ExpectedBlockTime` nor `Timestamp::MinimumPeriod`"
+            )
+        })
+}
+
+/// Convert the quantity to the target unit, the block clock is required for any
+/// conversion but between amounts.
+pub fn convert(
+    quantity: &Quantity,
+    target: &Target,
+    token_metadata: &TokenMetadata,
+    clock: Option<&BlockClock>,
+) -> Result<Converted> {
+    let clock = || clock.context("The conversion requires the block time of the chain");
+    Ok(match (quantity, target) {
+        (Quantity::Amount(amount), Target::Planck) => {
+            Converted::number(amount.denominate_balance(token_metadata)?)
+        }
+        (Quantity::Amount(amount), Target::Token(symbol)) => {
+            anyhow::ensure!(
+                symbol.eq_ignore_ascii_case(&token_metadata.symbol),
+                "`{symbol}` is not the token of the chain, `{}`",
+                token_metadata.symbol
+            );
+            let planck: Balance = amount.denominate_balance(token_metadata)?;
+            Converted {
+                value: BalanceVariant::from(planck, Some(token_metadata))?.to_string(),
+                number: planck.to_string(),
+            }
+        }
+        (Quantity::Blocks(blocks), Target::Duration) => {
+            display_duration(clock()?.duration_of(*blocks))
+        }
+        (Quantity::Duration(duration), Target::Blocks) => {
+            Converted::number(clock()?.blocks_in(*duration))
+        }
+        (Quantity::BlockNumber(block), Target::Timestamp) => {
+            Converted::number(clock()?.timestamp_of(*block))
+        }
+        (Quantity::Timestamp(timestamp), Target::BlockNumber) => {
+            Converted::number(clock()?.block_at(*timestamp)?)
+        }
+        (quantity, target) => {
+            anyhow::bail!(
+                "A {} can not be converted to {}",
+                quantity.kind(),
+                target.kind()
+            )
+        }
+    })
+}
+
+impl Quantity {
+    fn kind(&self) -> &'static str {
+        match self {
+            Self::Amount(_) => "balance",
+            Self::Blocks(_) => "number of blocks",
+            Self::Duration(_) => "duration",
+            Self::BlockNumber(_) => "block number",
+            Self::Timestamp(_) => "timestamp",
+        }
+    }
+}
+
+impl Target {
+    fn kind(&self) -> String {
+        match self {
+            Self::Planck => "planck".into(),
+            Self::Token(symbol) => symbol.clone(),
+            Self::Blocks => "blocks".into(),
+            Self::Duration => "a duration".into(),
+            Self::BlockNumber => "a block number".into(),
+            Self::Timestamp => "a timestamp".into(),
+        }
+    }
+}
+
+/// Display the duration in days, hours, minutes and seconds, e.g. `1d 2h 30s`.
+fn display_duration(duration: Duration) -> Converted {
+    let secs = duration.as_secs();
+    let value = [
+        (secs / 86_400, "d"),
+        (secs / 3_600 % 24, "h"),
+        (secs / 60 % 60, "m"),
+    ]
+    .into_iter()
+    .chain([(secs % 60, "s")])
+    .filter(|(amount, _)| *amount > 0)
+    .map(|(amount, unit)| format!("{amount}{unit}"))
+    .collect::<Vec<_>>()
+    .join(" ");
+    Converted {
+        value: if value.is_empty() { "0s".into() } else { value },
+        number: secs.to_string(),
+    }
+}
+
+fn parse_integer<T: FromStr>(number: &str) -> Result<T> {
+    number
+        .parse()
+        .map_err(|_| anyhow!("`{number}` is not a whole number"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn dot() -> TokenMetadata {
+        TokenMetadata {
+            token_decimals: 10,
+            symbol: "DOT".into(),
+        }
+    }
+
+    fn clock() -> BlockClock {
+        BlockClock {
+            block_time: Duration::from_secs(6),
+            latest_block: 1_000,
+            latest_timestamp: 1_700_000_000_000,
+        }
+    }
+
+    fn converted(quantity: &str, unit: Option<&str>, target: &str) -> Result<String> {
+        let quantity = Quantity::parse(quantity, unit)?;
+        let converted = convert(&quantity, &target.parse()?, &dot(), Some(&clock()))?;
+        Ok(converted.value)
+    }
+
+    #[test]
+    fn amounts_are_converted_with_the_token_metadata() {
+        assert_eq!(
+            converted("1.5", Some("DOT"), "planck").unwrap(),
+            "15000000000"
+        );
+        assert_eq!(converted("1.5DOT", None, "planck").unwrap(), "15000000000");
+        assert_eq!(converted("500mDOT", None, "planck").unwrap(), "5000000000");
+        assert_eq!(converted("15_000_000_000", None, "DOT").unwrap(), "1.5DOT");
+        assert_eq!(
+            converted("5000000000", Some("planck"), "dot").unwrap(),
+            "500mDOT"
+        );
+
+        assert!(converted("1.5", Some("KSM"), "planck").is_err());
+        assert!(converted("1", None, "KSM").is_err());
+        assert!(converted("1.5", None, "planck").is_err());
+    }
+
+    #[test]
+    fn blocks_and_times_are_converted_with_the_block_time() {
+        assert_eq!(converted("100", Some("blocks"), "duration").unwrap(), "10m");
+        assert_eq!(converted("14401blocks", None, "duration").unwrap(), "1d 6s");
+        assert_eq!(converted("1h", None, "blocks").unwrap(), "600");
+        assert_eq!(converted("7", Some("s"), "blocks").unwrap(), "2");
+        assert_eq!(converted("1.5", Some("min"), "blocks").unwrap(), "15");
+
+        assert_eq!(converted("#1010", None, "timestamp").unwrap(), "1700000060");
+        assert_eq!(
+            converted("990", Some("block"), "timestamp").unwrap(),
+            "1699999940"
+        );
+        assert_eq!(converted("1700000061unix", None, "block").unwrap(), "1010");
+        assert_eq!(
+            converted("1699999940", Some("unix"), "block").unwrap(),
+            "990"
+        );
+        assert!(converted("0", Some("unix"), "block").is_err());
+
+        assert!(converted("1h", None, "planck").is_err());
+        assert!(convert(&Quantity::Blocks(1), &Target::Duration, &dot(), None).is_err());
+    }
+}
@@ -14,17 +14,23 @@
 // You should have received a copy of the GNU General Public License
 // along with cargo-contract.  If not, see <http://www.gnu.org/licenses/>.
 
+use super::{
+    config::Config,
+    AsyncCommand,
+};
 use crate::DEFAULT_KEY_COL_WIDTH;
 use anyhow::{
     Context,
     Result,
 };
+use async_trait::async_trait;
 use colored::Colorize as _;
 use contract_build::{
     util,
     CrateMetadata,
 };
-use contract_transcode::ContractMessageTranscoder;
+use contract_metadata::ContractMetadata;
+use contract_transcode::BytesFormat;
 
 #[derive(Debug, Clone, clap::Args)]
 #[clap(
@@ -38,6 +44,10 @@ pub struct DecodeCommand {
     /// The data to decode; this has to be a hex value starting with `0x`.
     #[clap(short, long)]
     data: String,
+    /// Display bytes as raw sequences of integers, instead of as UTF-8 strings, hex or
+    /// SS58 addresses.
+    #[clap(long)]
+    raw_bytes: bool,
 }
 
 #[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, clap::ValueEnum)]
@@ -47,11 +57,22 @@ enum DataType {
     Constructor,
 }
 
-impl DecodeCommand {
-    pub fn run(&self) -> Result<()> {
+#[async_trait(?Send)]
+impl AsyncCommand for DecodeCommand {
+    type Output = Result<()>;
+
+    async fn exec(&self) -> Result<()> {
         let crate_metadata =
             CrateMetadata::from_manifest_path(None, contract_build::Target::Wasm)?;
-        let transcoder = ContractMessageTranscoder::load(crate_metadata.metadata_path())?;
+        let metadata = ContractMetadata::load(crate_metadata.metadata_path())?;
+        let bytes_format = if self.raw_bytes {
+            BytesFormat::Raw
+        } else {
+            BytesFormat::Pretty
+        };
+        let transcoder = Config::load()?
+            .contract_transcoder(metadata)?
+            .with_bytes_format(bytes_format);
 
         const ERR_MSG: &str = "Failed to decode specified data as a hex value";
         let decoded_data = match self.r#type {
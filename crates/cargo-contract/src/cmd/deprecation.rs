@@ -0,0 +1,161 @@
+// Copyright 2018-2023 Parity Technologies (UK) Ltd.
+// This file is part of cargo-contract.
+//
+// cargo-contract is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// cargo-contract is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with cargo-contract.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Deprecated messages and constructors of a contract.
+//!
+//! ink! does not carry `#[deprecated]` attributes into the metadata, so a message is
+//! marked as deprecated by a line of its doc comment starting with `Deprecated`, e.g.
+//!
+//! ```ignore
+//! /// Flips the value.
+//! ///
+//! /// Deprecated: use `toggle` instead.
+//! #[ink(message)]
+//! pub fn flip(&mut self) { .. }
+//! ```
+//!
+//! or by the `deprecated` table of the user metadata in the `Cargo.toml` of the
+//! contract, mapping the labels to notes:
+//!
+//! ```toml
+//! [package.metadata.contract.user.deprecated]
+//! flip = "Use `toggle` instead."
+//! ```
+
+use colored::Colorize;
+use contract_metadata::ContractMetadata;
+use serde_json::Value;
+use std::collections::BTreeMap;
+
+/// The deprecated messages and constructors of a contract, by label.
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct Deprecations(BTreeMap<String, Deprecation>);
+
+/// A deprecated message or constructor.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Deprecation {
+    /// Why it is deprecated or what to use instead.
+    pub note: Option<String>,
+}
+
+impl Deprecations {
+    /// Collect the deprecations of the doc comments and the user metadata.
+    pub fn from_metadata(metadata: &ContractMetadata) -> Self {
+        let mut deprecations = BTreeMap::new();
+        let spec = metadata.abi.get("spec");
+        let callables = ["constructors", "messages"]
+            .into_iter()
+            .filter_map(|kind| spec?.get(kind)?.as_array())
+            .flatten();
+        for callable in callables {
+            let Some(label) = callable.get("label").and_then(Value::as_str) else {
+                continue
+            };
+            let deprecated = callable
+                .get("docs")
+                .and_then(Value::as_array)
+                .into_iter()
+                .flatten()
+                .filter_map(Value::as_str)
+                .find_map(deprecation_note);
+            if let Some(note) = deprecated {
+                deprecations.insert(label.to_string(), Deprecation { note });
+            }
+        }
+        let user = metadata
+            .user
+            .as_ref()
+            .and_then(|user| user.json.get("deprecated"))
+            .and_then(Value::as_object);
+        for (label, note) in user.into_iter().flatten() {
+            let note = note.as_str().filter(|note| !note.is_empty());
+            deprecations.insert(
+                label.clone(),
+                Deprecation {
+                    note: note.map(ToString::to_string),
+                },
+            );
+        }
+        Self(deprecations)
+    }
+
+    /// Returns the deprecation of the message or constructor, if it is deprecated.
+    pub fn get(&self, label: &str) -> Option<&Deprecation> {
+        self.0.get(label)
+    }
+
+    /// Print a warning if the message or constructor is deprecated.
+    pub fn warn(&self, label: &str) {
+        if let Some(deprecation) = self.get(label) {
+            eprintln!(
+                "{} `{label}` is deprecated{}",
+                "warning:".yellow().bold(),
+                deprecation
+                    .note
+                    .as_ref()
+                    .map(|note| format!(": {note}"))
+                    .unwrap_or_default()
+            );
+        }
+    }
+}
+
+/// Returns the note of a doc line marking a deprecation, `Some(None)` if it has none.
+fn deprecation_note(line: &str) -> Option<Option<String>> {
+    let line = line.trim().trim_start_matches('#').trim_start();
+    let rest = line
+        .strip_prefix("Deprecated")
+        .or_else(|| line.strip_prefix("DEPRECATED"))?;
+    if rest.starts_with(|c: char| c.is_alphanumeric()) {
+        // e.g. `Deprecatedness`
+        return None
+    }
+    let note = rest.trim_start_matches([':', '.', ',', '-']).trim();
+    Some((!note.is_empty()).then(|| note.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn deprecations_are_read_from_docs_and_user_metadata() {
+        let metadata = json!({
+            "source": { "hash": format!("0x{}", "00".repeat(32)), "language": "ink! 4.2.0", "compiler": "rustc 1.69.0" },
+            "contract": { "name": "flipper", "version": "0.1.0", "authors": [] },
+            "user": { "deprecated": { "set": "Use `toggle` instead.", "old": "" } },
+            "spec": {
+                "constructors": [{ "label": "new", "docs": [" Creates a new flipper."] }],
+                "messages": [
+                    { "label": "flip", "docs": [" Flips the value.", "", " Deprecated: use `toggle` instead."] },
+                    { "label": "get", "docs": [" # Deprecated"] },
+                    { "label": "toggle", "docs": [" Deprecatedness is not a marker."] }
+                ]
+            }
+        });
+        let metadata: ContractMetadata = serde_json::from_value(metadata).unwrap();
+        let deprecations = Deprecations::from_metadata(&metadata);
+        let note = |label| deprecations.get(label).map(|d| d.note.as_deref());
+
+        assert_eq!(note("flip"), Some(Some("use `toggle` instead.")));
+        assert_eq!(note("get"), Some(None));
+        assert_eq!(note("set"), Some(Some("Use `toggle` instead.")));
+        assert_eq!(note("old"), Some(None));
+        assert_eq!(note("toggle"), None);
+        assert_eq!(note("new"), None);
+    }
+}
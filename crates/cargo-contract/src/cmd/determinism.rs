@@ -0,0 +1,394 @@
+// Copyright 2018-2023 Parity Technologies (UK) Ltd.
+// This file is part of cargo-contract.
+//
+// cargo-contract is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// cargo-contract is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with cargo-contract.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Scanning the Wasm code of a contract for the instructions failing the validation of
+//! `pallet-contracts` for `Deterministic` code.
+//!
+//! The instructions are reported with the names of the functions containing them, taken
+//! from the name section of the code. Contracts built with `--keep-debug-symbols` keep
+//! the name section, otherwise the functions are identified by their index.
+
+use super::{
+    extrinsics::ContractArtifacts,
+    AsyncCommand,
+};
+use anyhow::{
+    Context,
+    Result,
+};
+use async_trait::async_trait;
+use colored::Colorize;
+use std::{
+    collections::{
+        BTreeMap,
+        BTreeSet,
+    },
+    path::PathBuf,
+};
+use wasmparser::{
+    Name,
+    NameSectionReader,
+    Operator,
+    Parser,
+    Payload,
+    TypeRef,
+};
+
+#[derive(Debug, clap::Args)]
+#[clap(
+    name = "scan-determinism",
+    about = "Scan the Wasm code for instructions failing the deterministic validation"
+)]
+pub struct ScanDeterminismCommand {
+    /// Path to a contract build artifact file: a raw `.wasm` file, a `.contract` bundle
+    /// or a zstd compressed `.contract.zst` bundle.
+    #[clap(value_parser, conflicts_with = "manifest_path")]
+    file: Option<PathBuf>,
+    /// Path to the `Cargo.toml` of the contract.
+    #[clap(long, value_parser)]
+    manifest_path: Option<PathBuf>,
+    /// Export the findings in JSON format.
+    #[clap(long)]
+    output_json: bool,
+}
+
+#[async_trait(?Send)]
+impl AsyncCommand for ScanDeterminismCommand {
+    type Output = Result<()>;
+
+    async fn exec(&self) -> Result<()> {
+        let artifacts = ContractArtifacts::from_manifest_or_file(
+            self.manifest_path.as_ref(),
+            self.file.as_ref(),
+        )?;
+        let code = artifacts.code.as_ref().with_context(|| {
+            format!(
+                "Contract code not found from artifact file {}",
+                artifacts.artifact_path().display()
+            )
+        })?;
+        let findings = scan(code.as_bytes())?;
+        if self.output_json {
+            println!("{}", serde_json::to_string_pretty(&findings)?);
+        } else {
+            for finding in &findings {
+                println!(
+                    "{} {} in {}: {}",
+                    finding.count,
+                    finding.kind.description(),
+                    finding.function.bright_white().bold(),
+                    finding.instructions.join(", ")
+                );
+            }
+        }
+        if findings.is_empty() {
+            if !self.output_json {
+                println!(
+                    "The code contains no instructions failing the deterministic \
+                    validation"
+                );
+            }
+            return Ok(())
+        }
+        let kinds = findings
+            .iter()
+            .map(|finding| finding.kind)
+            .collect::<BTreeSet<_>>();
+        let hints = kinds
+            .iter()
+            .map(|kind| format!("\n  - {}", kind.hint()))
+            .collect::<String>();
+        anyhow::bail!(
+            "The code can not be uploaded with `Determinism::Deterministic`, remove the \
+            causes at the source:{hints}"
+        )
+    }
+}
+
+/// The kinds of instructions failing the deterministic validation.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, serde::Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Violation {
+    /// Floating-point instructions, whose NaN results differ between platforms.
+    Float,
+    /// Instructions of the bulk memory proposal, which is not enabled.
+    BulkMemory,
+    /// Instructions of the sign extension proposal, which is not enabled.
+    SignExtension,
+    /// SIMD instructions.
+    Simd,
+    /// Atomic instructions of the threads proposal.
+    Atomics,
+    /// Instructions of other proposals which are not enabled, e.g. reference types.
+    OtherProposal,
+}
+
+impl Violation {
+    /// The kind of the instruction, if it fails the deterministic validation.
+    fn of(operator: &Operator) -> Option<Self> {
+        match proposal(operator) {
+            "mvp" => {
+                let name = instruction_name(operator);
+                (name.contains("F32") || name.contains("F64")).then_some(Self::Float)
+            }
+            "saturating_float_to_int" => Some(Self::Float),
+            "bulk_memory" => Some(Self::BulkMemory),
+            "sign_extension" => Some(Self::SignExtension),
+            "simd" | "relaxed_simd" => Some(Self::Simd),
+            "threads" => Some(Self::Atomics),
+            _ => Some(Self::OtherProposal),
+        }
+    }
+
+    fn description(&self) -> &'static str {
+        match self {
+            Self::Float => "floating-point instructions",
+            Self::BulkMemory => "bulk memory instructions",
+            Self::SignExtension => "sign extension instructions",
+            Self::Simd => "SIMD instructions",
+            Self::Atomics => "atomic instructions",
+            Self::OtherProposal => "instructions of unsupported proposals",
+        }
+    }
+
+    fn hint(&self) -> &'static str {
+        match self {
+            Self::Float => {
+                "floating-point instructions: replace `f32` and `f64` by integer or \
+                fixed-point arithmetic, also in dependencies"
+            }
+            Self::BulkMemory => {
+                "bulk memory instructions: build with `cargo contract build`, which lowers \
+                them, and without `-C target-feature=+bulk-memory`"
+            }
+            Self::SignExtension => {
+                "sign extension instructions: build with `cargo contract build`, which \
+                lowers them, and without `-C target-feature=+sign-ext`"
+            }
+            Self::Simd => {
+                "SIMD instructions: build without `-C target-feature=+simd128`"
+            }
+            Self::Atomics => {
+                "atomic instructions: build without `-C target-feature=+atomics`"
+            }
+            Self::OtherProposal => {
+                "instructions of unsupported proposals: build without enabling the target \
+                features of Wasm proposals"
+            }
+        }
+    }
+}
+
+/// The instructions of a kind failing the deterministic validation in a function.
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize)]
+pub struct Finding {
+    /// The name of the function, or `func[<index>]` without a name section.
+    pub function: String,
+    pub kind: Violation,
+    /// The number of instructions of the kind in the function.
+    pub count: usize,
+    /// The distinct instructions of the kind in the function.
+    pub instructions: Vec<String>,
+}
+
+/// Scan the Wasm code for the instructions failing the deterministic validation, grouped
+/// by function and kind in the order of the functions.
+pub fn scan(code: &[u8]) -> Result<Vec<Finding>> {
+    let mut imported = 0;
+    let mut functions = Vec::new();
+    let mut names = BTreeMap::new();
+    for payload in Parser::new(0).parse_all(code) {
+        match payload.context("Failed to parse the Wasm code")? {
+            Payload::ImportSection(imports) => {
+                for import in imports {
+                    if let TypeRef::Func(_) = import?.ty {
+                        imported += 1;
+                    }
+                }
+            }
+            Payload::CodeSectionEntry(body) => {
+                let mut kinds = BTreeMap::<Violation, (usize, Vec<String>)>::new();
+                for operator in body.get_operators_reader()? {
+                    let operator = operator?;
+                    if let Some(kind) = Violation::of(&operator) {
+                        let (count, instructions) = kinds.entry(kind).or_default();
+                        *count += 1;
+                        let name = instruction_name(&operator);
+                        if !instructions.contains(&name) {
+                            instructions.push(name);
+                        }
+                    }
+                }
+                functions.push(kinds);
+            }
+            Payload::CustomSection(section) if section.name() == "name" => {
+                // The code is scanned without function names if the name section is
+                // malformed.
+                names = function_names(section.data(), section.data_offset())
+                    .unwrap_or_default();
+            }
+            _ => (),
+        }
+    }
+
+    let mut findings = Vec::new();
+    for (index, kinds) in (imported..).zip(functions) {
+        let function = names
+            .get(&index)
+            .cloned()
+            .unwrap_or_else(|| format!("func[{index}]"));
+        findings.extend(kinds.into_iter().map(|(kind, (count, instructions))| {
+            Finding {
+                function: function.clone(),
+                kind,
+                count,
+                instructions,
+            }
+        }));
+    }
+    Ok(findings)
+}
+
+/// The names of the functions in the name section, by function index.
+pub(crate) fn function_names(
+    data: &[u8],
+    offset: usize,
+) -> Result<BTreeMap<u32, String>> {
+    let mut names = BTreeMap::new();
+    for name in NameSectionReader::new(data, offset) {
+        if let Name::Function(functions) = name? {
+            for naming in functions {
+                let naming = naming?;
+                names.insert(naming.index, naming.name.to_string());
+            }
+        }
+    }
+    Ok(names)
+}
+
+/// The name of the instruction, without its immediates.
+fn instruction_name(operator: &Operator) -> String {
+    let name = format!("{operator:?}");
+    match name.split_once([' ', '(']) {
+        Some((name, _)) => name.to_string(),
+        None => name,
+    }
+}
+
+macro_rules! define_proposal {
+    ($( @$proposal:ident $op:ident $({ $($arg:ident: $argty:ty),* })? => $visit:ident)*) => {
+        /// The Wasm proposal introducing the operator, `mvp` for the operators of the
+        /// initial release.
+        fn proposal(operator: &Operator) -> &'static str {
+            match operator {
+                $( Operator::$op { .. } => stringify!($proposal), )*
+            }
+        }
+    };
+}
+wasmparser::for_each_operator!(define_proposal);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A module of functions without parameters and results with the given bodies, and
+    /// a name section naming the functions.
+    fn module(bodies: &[&[u8]], names: &[(u8, &str)]) -> Vec<u8> {
+        let section = |id: u8, contents: Vec<u8>| {
+            [vec![id, contents.len() as u8], contents].concat()
+        };
+        let count = bodies.len() as u8;
+        let mut code = vec![count];
+        for body in bodies {
+            // No locals, the instructions and `end`.
+            code.extend([body.len() as u8 + 2, 0]);
+            code.extend(*body);
+            code.push(0x0b);
+        }
+        let mut function_names = vec![names.len() as u8];
+        for (index, name) in names {
+            function_names.extend([*index, name.len() as u8]);
+            function_names.extend(name.as_bytes());
+        }
+        let mut name_section = [&[4][..], b"name"].concat();
+        name_section.extend(section(1, function_names));
+        [
+            b"\0asm\x01\0\0\0".to_vec(),
+            section(1, vec![1, 0x60, 0, 0]),
+            section(3, [vec![count], vec![0; bodies.len()]].concat()),
+            section(10, code),
+            section(0, name_section),
+        ]
+        .concat()
+    }
+
+    #[test]
+    fn forbidden_instructions_are_reported_per_function() {
+        let f64_const = [&[0x44][..], &[0; 8]].concat();
+        let floats_and_fill = [
+            &f64_const[..],
+            &f64_const,
+            // f64.add, drop
+            &[0xa0, 0x1a],
+            &f64_const,
+            // drop, the operands of memory.fill and memory.fill
+            &[0x1a, 0x41, 0, 0x41, 0, 0x41, 0, 0xfc, 0x0b, 0],
+        ]
+        .concat();
+        let code = module(
+            &[
+                // i32.const 0, drop
+                &[0x41, 0, 0x1a],
+                &floats_and_fill,
+                // i32.const 1, f32.convert_i32_s, i32.extend8_s on the float
+                &[0x41, 1, 0xb2, 0xc0, 0x1a],
+            ],
+            &[(0, "deploy"), (1, "ink_env::balance_as_float")],
+        );
+
+        let findings = scan(&code).unwrap();
+        let finding = |function: &str, kind, count, instructions: &[&str]| {
+            Finding {
+                function: function.to_string(),
+                kind,
+                count,
+                instructions: instructions.iter().map(ToString::to_string).collect(),
+            }
+        };
+        assert_eq!(
+            findings,
+            [
+                finding(
+                    "ink_env::balance_as_float",
+                    Violation::Float,
+                    4,
+                    &["F64Const", "F64Add"]
+                ),
+                finding(
+                    "ink_env::balance_as_float",
+                    Violation::BulkMemory,
+                    1,
+                    &["MemoryFill"]
+                ),
+                finding("func[2]", Violation::Float, 1, &["F32ConvertI32S"]),
+                finding("func[2]", Violation::SignExtension, 1, &["I32Extend8S"]),
+            ]
+        );
+        assert!(scan(&module(&[&[0x41, 0, 0x1a]], &[])).unwrap().is_empty());
+        assert!(scan(b"\0asm\x01").is_err());
+    }
+}
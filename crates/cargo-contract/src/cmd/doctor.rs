@@ -0,0 +1,399 @@
+// Copyright 2018-2023 Parity Technologies (UK) Ltd.
+// This file is part of cargo-contract.
+//
+// cargo-contract is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// cargo-contract is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with cargo-contract.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Diagnostics of the environment `cargo-contract` runs in.
+//!
+//! `doctor` checks the toolchain contracts are built with, the node extrinsics are sent
+//! to and the contract in the current directory, and prints how to fix the problems
+//! found.
+
+use super::{
+    runtime_api::api::{
+        self,
+        runtime_types::pallet_contracts::wasm::Determinism,
+    },
+    AsyncCommand,
+    DefaultConfig,
+};
+use anyhow::{
+    anyhow,
+    Result,
+};
+use async_trait::async_trait;
+use colored::Colorize;
+use contract_build::{
+    CrateMetadata,
+    Target,
+};
+use sp_weights::Weight;
+use std::{
+    path::PathBuf,
+    process::Command,
+    time::Duration,
+};
+use subxt::OnlineClient;
+
+/// How long to wait for the node before reporting it as unreachable.
+const NODE_TIMEOUT: Duration = Duration::from_secs(10);
+
+#[derive(Debug, clap::Args)]
+#[clap(
+    name = "doctor",
+    about = "Check the toolchain, the node and the contract for setup problems"
+)]
+pub struct DoctorCommand {
+    /// Websockets url of the node to check.
+    #[clap(long, default_value = "ws://localhost:9944")]
+    url: url::Url,
+    /// Skip checking the node, e.g. when working offline.
+    #[clap(long)]
+    skip_node: bool,
+    /// Path to the `Cargo.toml` of the contract to check, by default the contract in the
+    /// current directory if there is one.
+    #[clap(long, value_parser)]
+    manifest_path: Option<PathBuf>,
+    /// Export the diagnostics in JSON format.
+    #[clap(long)]
+    output_json: bool,
+}
+
+/// The outcome of a check.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Status {
+    /// Everything is set up.
+    Ok,
+    /// Something optional is missing, or could not be checked.
+    Warning,
+    /// Something required is missing or incompatible.
+    Error,
+}
+
+/// The diagnosis of a part of the environment.
+#[derive(Debug, serde::Serialize)]
+pub struct Diagnosis {
+    /// What was checked.
+    pub check: &'static str,
+    pub status: Status,
+    /// What was found.
+    pub detail: String,
+    /// How to fix the problem.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub fix: Option<String>,
+}
+
+impl Diagnosis {
+    fn ok(check: &'static str, detail: impl Into<String>) -> Self {
+        Self {
+            check,
+            status: Status::Ok,
+            detail: detail.into(),
+            fix: None,
+        }
+    }
+
+    fn problem(
+        check: &'static str,
+        status: Status,
+        detail: impl Into<String>,
+        fix: impl Into<String>,
+    ) -> Self {
+        Self {
+            check,
+            status,
+            detail: detail.into(),
+            fix: Some(fix.into()),
+        }
+    }
+
+    fn print(&self) {
+        let status = match self.status {
+            Status::Ok => "ok".bright_green().bold(),
+            Status::Warning => "warning".yellow().bold(),
+            Status::Error => "error".bright_red().bold(),
+        };
+        println!("{:>12} {}: {}", status, self.check.bold(), self.detail);
+        if let Some(ref fix) = self.fix {
+            println!("{:>12} {fix}", "fix:".bold());
+        }
+    }
+}
+
+impl DoctorCommand {
+    /// Check the ink! version of the contract, if there is one.
+    fn contract(&self) -> Option<Diagnosis> {
+        let default_manifest = PathBuf::from("Cargo.toml");
+        if self.manifest_path.is_none() && !default_manifest.exists() {
+            return None
+        }
+        let diagnosis = match CrateMetadata::from_manifest_path(
+            self.manifest_path.as_ref(),
+            Target::Wasm,
+        ) {
+            Ok(crate_metadata) => ink_version(&crate_metadata.ink_version),
+            Err(err) => {
+                Diagnosis::problem(
+                    "contract",
+                    Status::Warning,
+                    format!("the manifest is not an ink! contract: {err}"),
+                    "Pass the `Cargo.toml` of the contract with `--manifest-path`.",
+                )
+            }
+        };
+        Some(diagnosis)
+    }
+}
+
+#[async_trait(?Send)]
+impl AsyncCommand for DoctorCommand {
+    type Output = Result<()>;
+
+    async fn exec(&self) -> Result<()> {
+        let mut diagnoses =
+            vec![rustc(), rust_src(), wasm_target(), binaryen(), dylint()];
+        if !self.skip_node {
+            diagnoses.extend(node(&self.url).await);
+        }
+        if let Some(diagnosis) = self.contract() {
+            diagnoses.push(diagnosis);
+        }
+
+        if self.output_json {
+            println!("{}", serde_json::to_string_pretty(&diagnoses)?);
+        } else {
+            for diagnosis in &diagnoses {
+                diagnosis.print();
+            }
+        }
+        let errors = diagnoses
+            .iter()
+            .filter(|diagnosis| diagnosis.status == Status::Error)
+            .count();
+        if errors > 0 {
+            return Err(anyhow!("{errors} problems must be fixed, see above"))
+        }
+        Ok(())
+    }
+}
+
+/// Run the program with the arguments, returns its trimmed stdout if it succeeded.
+fn output(program: &str, args: &[&str]) -> Option<String> {
+    let output = Command::new(program).args(args).output().ok()?;
+    output
+        .status
+        .success()
+        .then(|| String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
+
+/// Returns `true` if the list printed by `rustup` contains the item.
+fn is_listed(list: &str, item: &str) -> bool {
+    list.lines()
+        .any(|line| line.split_whitespace().next() == Some(item))
+}
+
+fn rustc() -> Diagnosis {
+    match output("rustc", &["--version"]) {
+        Some(version) => Diagnosis::ok("rustc", version),
+        None => {
+            Diagnosis::problem(
+                "rustc",
+                Status::Error,
+                "the Rust compiler was not found",
+                "Install Rust with `rustup`, see https://rustup.rs.",
+            )
+        }
+    }
+}
+
+fn rust_src() -> Diagnosis {
+    match output("rustup", &["component", "list", "--installed"]) {
+        Some(list) if is_listed(&list, "rust-src") => {
+            Diagnosis::ok("rust-src", "installed")
+        }
+        Some(_) => {
+            Diagnosis::problem(
+                "rust-src",
+                Status::Error,
+                "the `rust-src` component is missing, it is required to build the \
+                standard library for contracts",
+                "rustup component add rust-src",
+            )
+        }
+        None => {
+            Diagnosis::problem(
+                "rust-src",
+                Status::Warning,
+                "`rustup` was not found, the `rust-src` component could not be checked",
+                "Install Rust with `rustup`, see https://rustup.rs.",
+            )
+        }
+    }
+}
+
+fn wasm_target() -> Diagnosis {
+    let target = Target::Wasm.llvm_target();
+    match output("rustup", &["target", "list", "--installed"]) {
+        Some(list) if is_listed(&list, target) => Diagnosis::ok("wasm target", target),
+        Some(_) => {
+            Diagnosis::problem(
+                "wasm target",
+                Status::Error,
+                format!("the `{target}` target is missing"),
+                format!("rustup target add {target}"),
+            )
+        }
+        None => {
+            Diagnosis::problem(
+                "wasm target",
+                Status::Warning,
+                format!(
+                    "`rustup` was not found, the `{target}` target could not be checked"
+                ),
+                "Install Rust with `rustup`, see https://rustup.rs.",
+            )
+        }
+    }
+}
+
+fn binaryen() -> Diagnosis {
+    // The Wasm code is optimized by the bundled library, an installed `wasm-opt` is
+    // not used.
+    match output("wasm-opt", &["--version"]) {
+        Some(version) => {
+            Diagnosis::ok(
+                "binaryen",
+                format!(
+                    "bundled with cargo-contract, the installed `{version}` is not used"
+                ),
+            )
+        }
+        None => Diagnosis::ok("binaryen", "bundled with cargo-contract"),
+    }
+}
+
+fn dylint() -> Diagnosis {
+    let cargo = std::env::var("CARGO").unwrap_or_else(|_| String::from("cargo"));
+    let cargo_dylint = output(&cargo, &["dylint", "--version"]);
+    let dylint_link = which::which("dylint-link").is_ok();
+    match (cargo_dylint, dylint_link) {
+        (Some(version), true) => Diagnosis::ok("dylint", version),
+        (cargo_dylint, _) => {
+            let missing = match cargo_dylint {
+                Some(_) => "dylint-link",
+                None if dylint_link => "cargo-dylint",
+                None => "cargo-dylint dylint-link",
+            };
+            Diagnosis::problem(
+                "dylint",
+                Status::Warning,
+                format!("`{missing}` not found, it is only required by `build --lint`"),
+                format!("cargo install {missing}"),
+            )
+        }
+    }
+}
+
+/// Check that the node is reachable and its `pallet-contracts` is compatible.
+async fn node(url: &url::Url) -> Vec<Diagnosis> {
+    let connect = OnlineClient::<DefaultConfig>::from_url(url.as_str());
+    let client = match tokio::time::timeout(NODE_TIMEOUT, connect).await {
+        Ok(Ok(client)) => client,
+        Ok(Err(err)) => {
+            return vec![Diagnosis::problem(
+                "node",
+                Status::Warning,
+                format!("{url} is not reachable: {err}"),
+                "Start a node, e.g. `substrate-contracts-node`, or pass its url with \
+                `--url`. Skip this check with `--skip-node`.",
+            )]
+        }
+        Err(_) => {
+            return vec![Diagnosis::problem(
+                "node",
+                Status::Warning,
+                format!("{url} did not respond within {}s", NODE_TIMEOUT.as_secs()),
+                "Check the url and the network connection, or skip this check with \
+                `--skip-node`.",
+            )]
+        }
+    };
+    let version = client.runtime_version();
+    let mut diagnoses = vec![Diagnosis::ok(
+        "node",
+        format!("{url} runs spec version {}", version.spec_version),
+    )];
+
+    // The calls are only validated against the metadata of the node, not submitted.
+    let upload =
+        api::tx()
+            .contracts()
+            .upload_code(Vec::new(), None, Determinism::Deterministic);
+    let instantiate = api::tx().contracts().instantiate(
+        0,
+        Weight::zero().into(),
+        None,
+        Default::default(),
+        Vec::new(),
+        Vec::new(),
+    );
+    let compatible = match client.tx().validate(&upload) {
+        Ok(()) => client.tx().validate(&instantiate),
+        Err(err) => Err(err),
+    };
+    diagnoses.push(match compatible {
+        Ok(()) => Diagnosis::ok("pallet-contracts", "compatible"),
+        Err(err) => {
+            Diagnosis::problem(
+                "pallet-contracts",
+                Status::Error,
+                format!("the calls of the node are incompatible: {err}"),
+                "The node does not run `pallet-contracts`, or a version this release of \
+                cargo-contract does not support. Use a cargo-contract release matching \
+                the `pallet-contracts` version of the node.",
+            )
+        }
+    });
+    diagnoses
+}
+
+/// Check that the ink! version of the contract is supported.
+fn ink_version(version: &semver::Version) -> Diagnosis {
+    if version.major < 4 {
+        return Diagnosis::problem(
+            "ink!",
+            Status::Error,
+            format!("the contract depends on ink! {version}, cargo-contract 2 requires ink! 4"),
+            "Upgrade the contract to ink! 4, or build it with cargo-contract 1.",
+        )
+    }
+    Diagnosis::ok("ink!", version.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn installed_items_are_found_in_rustup_lists() {
+        let list = "rust-src\nrust-std-x86_64-unknown-linux-gnu\nwasm32-unknown-unknown";
+        assert!(is_listed(list, "rust-src"));
+        assert!(is_listed(list, "wasm32-unknown-unknown"));
+        assert!(!is_listed(list, "rust-std"));
+
+        assert_eq!(ink_version(&"4.2.0".parse().unwrap()).status, Status::Ok);
+        assert_eq!(ink_version(&"3.4.0".parse().unwrap()).status, Status::Error);
+    }
+}
@@ -14,10 +14,14 @@
 // You should have received a copy of the GNU General Public License
 // along with cargo-contract.  If not, see <http://www.gnu.org/licenses/>.
 
+use async_trait::async_trait;
 use std::path::PathBuf;
 
 use crate::{
-    cmd::extrinsics::ContractArtifacts,
+    cmd::{
+        extrinsics::ContractArtifacts,
+        AsyncCommand,
+    },
     DEFAULT_KEY_COL_WIDTH,
 };
 use anyhow::Result;
@@ -44,8 +48,11 @@ pub struct EncodeCommand {
     args: Vec<String>,
 }
 
-impl EncodeCommand {
-    pub fn run(&self) -> Result<()> {
+#[async_trait(?Send)]
+impl AsyncCommand for EncodeCommand {
+    type Output = Result<()>;
+
+    async fn exec(&self) -> Result<()> {
         let artifacts = ContractArtifacts::from_manifest_or_file(
             self.manifest_path.as_ref(),
             self.file.as_ref(),
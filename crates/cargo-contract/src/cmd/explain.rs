@@ -0,0 +1,359 @@
+// Copyright 2018-2023 Parity Technologies (UK) Ltd.
+// This file is part of cargo-contract.
+//
+// cargo-contract is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// cargo-contract is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with cargo-contract.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Explanations and likely fixes of common errors of `pallet-contracts` and ink!.
+//!
+//! The explanations are shown by `explain-error` and appended as a hint to failures of
+//! the extrinsic commands.
+
+use super::{
+    AsyncCommand,
+    DefaultConfig,
+    ErrorVariant,
+};
+use crate::{
+    name_value_println,
+    DEFAULT_KEY_COL_WIDTH,
+};
+use anyhow::{
+    anyhow,
+    Result,
+};
+use async_trait::async_trait;
+use subxt::OnlineClient;
+
+/// An explanation of an error and its likely fix.
+#[derive(Debug, PartialEq, Eq)]
+pub struct Explanation {
+    /// The pallet raising the error, `LangError` for errors of ink! itself.
+    pub pallet: &'static str,
+    /// The name of the error variant.
+    pub error: &'static str,
+    /// What the error means.
+    pub explanation: &'static str,
+    /// How to fix it.
+    pub fix: &'static str,
+}
+
+const fn explanation(
+    pallet: &'static str,
+    error: &'static str,
+    explanation: &'static str,
+    fix: &'static str,
+) -> Explanation {
+    Explanation {
+        pallet,
+        error,
+        explanation,
+        fix,
+    }
+}
+
+/// The known errors.
+const KNOWLEDGE_BASE: &[Explanation] = &[
+    explanation(
+        "Contracts",
+        "OutOfGas",
+        "The execution ran out of gas before it finished.",
+        "Raise `--gas` and `--proof-size`, or omit them and let the dry-run estimate the \
+        weight required.",
+    ),
+    explanation(
+        "Contracts",
+        "StorageDepositLimitExhausted",
+        "The storage added by the call needs a larger deposit than the storage deposit \
+        limit allows.",
+        "Raise `--storage-deposit-limit`, or omit it to allow any deposit.",
+    ),
+    explanation(
+        "Contracts",
+        "StorageDepositNotEnoughFunds",
+        "The signer can not pay the deposit for the storage added by the call.",
+        "Fund the signer account, the required deposit is shown by the dry-run.",
+    ),
+    explanation(
+        "Contracts",
+        "ContractTrapped",
+        "The contract panicked, e.g. on an `unwrap` of `None`, an arithmetic overflow or \
+        an explicit `panic!`.",
+        "Dry-run the call with `--verbose` to see the debug message of the contract, and \
+        build it with `--debug` to get the panic message.",
+    ),
+    explanation(
+        "Contracts",
+        "ContractReverted",
+        "The contract returned an error and reverted all state changes.",
+        "Dry-run the call to decode the error returned by the contract.",
+    ),
+    explanation(
+        "Contracts",
+        "ContractNotFound",
+        "There is no contract at the given address.",
+        "Check `--contract` and `--url`, the contract might have been deployed to another \
+        chain or terminated.",
+    ),
+    explanation(
+        "Contracts",
+        "CodeNotFound",
+        "No code is stored for the code hash.",
+        "Upload the code with `cargo contract upload`, or check `--code-hash`.",
+    ),
+    explanation(
+        "Contracts",
+        "CodeInUse",
+        "The code can not be removed while contracts are instantiated from it.",
+        "Terminate or upgrade the contracts using the code before removing it.",
+    ),
+    explanation(
+        "Contracts",
+        "CodeRejected",
+        "The chain rejected the Wasm code, e.g. because it uses floating point \
+        instructions or imports functions the chain does not provide.",
+        "Build the contract with a `cargo-contract` version matching the ink! and \
+        `pallet-contracts` versions of the chain.",
+    ),
+    explanation(
+        "Contracts",
+        "CodeTooLarge",
+        "The Wasm code is larger than the chain allows.",
+        "Build the contract in release mode and reduce its dependencies. The maximum size \
+        is reported by `cargo contract chain-info`.",
+    ),
+    explanation(
+        "Contracts",
+        "DuplicateContract",
+        "A contract with the same code, constructor data and salt was already \
+        instantiated by the signer.",
+        "Pass a different `--salt` to instantiate another contract from the same code.",
+    ),
+    explanation(
+        "Contracts",
+        "TransferFailed",
+        "The value could not be transferred, the sender has not enough free balance or \
+        the transfer would reap an account below the existential deposit.",
+        "Lower `--value` or fund the sender.",
+    ),
+    explanation(
+        "Contracts",
+        "MaxCallDepthReached",
+        "Contracts called each other more deeply than the chain allows.",
+        "Reduce the depth of cross-contract calls.",
+    ),
+    explanation(
+        "Contracts",
+        "ReentranceDenied",
+        "The contract was called again while it was executing, which ink! denies by \
+        default.",
+        "Allow reentrancy for the cross-contract call with `CallFlags`, if it is \
+        intended.",
+    ),
+    explanation(
+        "Contracts",
+        "ValueTooLarge",
+        "A value written to the storage of the contract exceeds the size limit.",
+        "Split large values into several storage entries, e.g. with a `Mapping`.",
+    ),
+    explanation(
+        "Contracts",
+        "DecodingFailed",
+        "The input could not be decoded by the chain.",
+        "Check that the metadata of the contract matches the deployed code.",
+    ),
+    explanation(
+        "Contracts",
+        "Indeterministic",
+        "The code may behave indeterministically and can not be used for on-chain \
+        execution.",
+        "Rebuild the contract without floating point operations.",
+    ),
+    explanation(
+        "Balances",
+        "InsufficientBalance",
+        "The account has not enough free balance for the operation.",
+        "Fund the signer account.",
+    ),
+    explanation(
+        "LangError",
+        "CouldNotReadInput",
+        "The contract could not decode the call, the message selector is unknown or the \
+        arguments do not match the types of the message.",
+        "Make sure the contract artifacts used by `cargo-contract` belong to the deployed \
+        code, e.g. check the code hash with `cargo contract info`.",
+    ),
+];
+
+/// Returns the explanation of the error of the pallet, if it is known.
+pub fn explain(pallet: &str, error: &str) -> Option<&'static Explanation> {
+    KNOWLEDGE_BASE
+        .iter()
+        .find(|known| known.pallet == pallet && known.error == error)
+}
+
+/// Returns the hint added to a failure with the error of the pallet, if it is known.
+pub fn hint(pallet: &str, error: &str) -> Option<String> {
+    explain(pallet, error).map(|known| format!("{} {}", known.explanation, known.fix))
+}
+
+#[derive(Debug, clap::Args)]
+#[clap(
+    name = "explain-error",
+    about = "Explain an error of pallet-contracts or ink! and its likely fix"
+)]
+pub struct ExplainErrorCommand {
+    /// The error, e.g. `ContractTrapped`, `Contracts::OutOfGas` or
+    /// `LangError::CouldNotReadInput`.
+    #[clap(required_unless_present = "module_index")]
+    error: Option<String>,
+    /// The index of the pallet of a module error, resolved with the metadata of the
+    /// chain.
+    #[clap(long, requires = "error_index", conflicts_with = "error")]
+    module_index: Option<u8>,
+    /// The index of the error variant of a module error.
+    #[clap(long, requires = "module_index")]
+    error_index: Option<u8>,
+    /// Websockets url of a substrate node, used to resolve `--module-index`.
+    #[clap(long, default_value = "ws://localhost:9944")]
+    url: url::Url,
+    /// Export the explanation in JSON format.
+    #[clap(long)]
+    output_json: bool,
+}
+
+/// An explained error for displaying.
+#[derive(Debug, serde::Serialize)]
+struct ExplainedError {
+    pallet: String,
+    error: String,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    docs: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    explanation: Option<&'static str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    fix: Option<&'static str>,
+}
+
+#[async_trait(?Send)]
+impl AsyncCommand for ExplainErrorCommand {
+    type Output = Result<(), ErrorVariant>;
+
+    async fn exec(&self) -> Result<(), ErrorVariant> {
+        let (pallet, error, docs) =
+            match (&self.error, self.module_index, self.error_index) {
+                (_, Some(module_index), Some(error_index)) => {
+                    let client =
+                        OnlineClient::<DefaultConfig>::from_url(self.url.clone()).await?;
+                    let metadata = client.metadata();
+                    let details = metadata
+                        .error(module_index, error_index)
+                        .map_err(anyhow::Error::from)?;
+                    (
+                        details.pallet().to_string(),
+                        details.error().to_string(),
+                        details.docs().to_vec(),
+                    )
+                }
+                (Some(error), ..) => {
+                    let (pallet, error) = resolve_name(error)?;
+                    (pallet.to_string(), error.to_string(), Vec::new())
+                }
+                _ => unreachable!("clap requires an error or its indices; qed"),
+            };
+        let known = explain(&pallet, &error);
+        let explained = ExplainedError {
+            pallet,
+            error,
+            docs,
+            explanation: known.map(|known| known.explanation),
+            fix: known.map(|known| known.fix),
+        };
+
+        if self.output_json {
+            let json =
+                serde_json::to_string_pretty(&explained).map_err(anyhow::Error::from)?;
+            println!("{json}");
+            return Ok(())
+        }
+        name_value_println!(
+            "Error",
+            format!("{}::{}", explained.pallet, explained.error),
+            DEFAULT_KEY_COL_WIDTH
+        );
+        if !explained.docs.is_empty() {
+            name_value_println!("Docs", explained.docs.join(" "), DEFAULT_KEY_COL_WIDTH);
+        }
+        match (explained.explanation, explained.fix) {
+            (Some(explanation), Some(fix)) => {
+                name_value_println!("Explanation", explanation, DEFAULT_KEY_COL_WIDTH);
+                name_value_println!("Fix", fix, DEFAULT_KEY_COL_WIDTH);
+            }
+            _ => {
+                name_value_println!(
+                    "Explanation",
+                    "No explanation of this error is known, see its docs",
+                    DEFAULT_KEY_COL_WIDTH
+                );
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Resolve the name of an error, with or without its pallet, to a known error.
+fn resolve_name(name: &str) -> Result<(&str, &str)> {
+    if let Some((pallet, error)) = name.split_once("::") {
+        return Ok((pallet, error))
+    }
+    let mut matches = KNOWLEDGE_BASE.iter().filter(|known| known.error == name);
+    match (matches.next(), matches.next()) {
+        (Some(known), None) => Ok((known.pallet, known.error)),
+        (Some(_), Some(_)) => {
+            Err(anyhow!(
+                "`{name}` is raised by several pallets, prefix it with the pallet, e.g. \
+                `Contracts::{name}`"
+            ))
+        }
+        (None, _) => {
+            Err(anyhow!(
+                "Unknown error `{name}`, prefix it with its pallet, e.g. \
+                `Contracts::{name}`, or pass `--module-index` and `--error-index`"
+            ))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn errors_are_resolved_by_name() {
+        assert_eq!(
+            resolve_name("ContractTrapped").unwrap(),
+            ("Contracts", "ContractTrapped")
+        );
+        assert_eq!(
+            resolve_name("LangError::CouldNotReadInput").unwrap(),
+            ("LangError", "CouldNotReadInput")
+        );
+        assert_eq!(
+            resolve_name("Assets::NoPermission").unwrap(),
+            ("Assets", "NoPermission")
+        );
+        assert!(resolve_name("NoPermission").is_err());
+        assert!(hint("Contracts", "OutOfGas").unwrap().contains("--gas"));
+        assert_eq!(hint("Assets", "NoPermission"), None);
+    }
+}
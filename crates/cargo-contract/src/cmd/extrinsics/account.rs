@@ -0,0 +1,405 @@
+// Copyright 2018-2023 Parity Technologies (UK) Ltd.
+// This file is part of cargo-contract.
+//
+// cargo-contract is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// cargo-contract is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with cargo-contract.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Mapping of accounts to contract addresses on runtimes requiring it, and generation of
+//! new deployment keys.
+//!
+//! Runtimes with `pallet-revive` address contracts and their callers by 20 byte
+//! addresses. The address of an account is the last 20 bytes of the Keccak-256 hash of
+//! the account, or its first 20 bytes for an account derived from an Ethereum address,
+//! which ends with 12 `0xEE` bytes. The runtime refuses calls and instantiations from an
+//! account until it is mapped with `Revive::map_account`, which stores the account under
+//! its address in `Revive::OriginalAccount`.
+//!
+//! `account generate` creates a BIP39 mnemonic and stores it in a key file of a Substrate
+//! keystore, to sign with by `--signer keystore:<PATH>`, without installing `subkey`.
+
+use super::{
+    display_dry_run_result_warning,
+    pair_signer,
+    signer::{
+        read_new_password,
+        write_keystore,
+        Password,
+    },
+    submit_dynamic_call,
+    Client,
+    DefaultConfig,
+    ErrorVariant,
+    ExtrinsicOpts,
+};
+use crate::{
+    cmd::{
+        config::Config,
+        AsyncCommand,
+    },
+    DEFAULT_KEY_COL_WIDTH,
+};
+use anyhow::{
+    anyhow,
+    Result,
+};
+use async_trait::async_trait;
+use bip39::{
+    Language,
+    Mnemonic,
+    MnemonicType,
+};
+use colored::Colorize as _;
+use contract_build::name_value_println;
+use sp_core::{
+    crypto::{
+        Ss58AddressFormat,
+        Ss58Codec,
+    },
+    hashing::keccak_256,
+    Pair,
+};
+use std::path::{
+    Path,
+    PathBuf,
+};
+use subxt::{
+    dynamic::Value,
+    Config,
+    Metadata,
+    OnlineClient,
+};
+
+type AccountId = <DefaultConfig as Config>::AccountId;
+
+pub const REVIVE_PALLET: &str = "Revive";
+
+/// The suffix of the accounts derived from an Ethereum address.
+const ETH_DERIVED_SUFFIX: [u8; 12] = [0xEE; 12];
+
+#[derive(Debug, clap::Args)]
+#[clap(
+    name = "account",
+    about = "Generate deployment keys, and map accounts to contract addresses"
+)]
+pub struct AccountCommand {
+    #[clap(subcommand)]
+    action: AccountAction,
+}
+
+#[derive(Debug, clap::Subcommand)]
+#[allow(clippy::large_enum_variant)]
+enum AccountAction {
+    /// Map the account of the signer to its contract address with `Revive::map_account`
+    Map {
+        #[clap(flatten)]
+        extrinsic_opts: ExtrinsicOpts,
+        /// Export the output in JSON format.
+        #[clap(long, conflicts_with = "verbose")]
+        output_json: bool,
+    },
+    /// Display the contract address of an account, and whether it is mapped
+    Status {
+        /// The account.
+        account: AccountId,
+        /// Websockets url of a substrate node.
+        #[clap(long, value_parser, default_value = "ws://localhost:9944")]
+        url: url::Url,
+    },
+    /// Generate a key with a new mnemonic and store it in a keystore directory
+    Generate {
+        /// The number of words of the mnemonic: 12, 15, 18, 21 or 24.
+        #[clap(long, default_value_t = 12)]
+        words: usize,
+        /// The signature scheme of the key.
+        #[clap(long, value_enum, default_value_t = Scheme::Sr25519)]
+        scheme: Scheme,
+        /// The keystore directory the key file is written to.
+        #[clap(long, default_value = "keystore")]
+        keystore: PathBuf,
+        /// Password of the keystore, which encrypts the key file, prompted for unless
+        /// given.
+        #[clap(long)]
+        password: Option<Password>,
+        /// The chain of the catalogue the address and the funding instructions are
+        /// displayed for, see `cargo contract chains list`.
+        #[clap(long)]
+        network: Option<String>,
+    },
+}
+
+/// The signature schemes of generated keys.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+enum Scheme {
+    /// Schnorr signatures on Ristretto, the scheme cargo-contract signs with.
+    Sr25519,
+}
+
+impl AccountCommand {
+    pub fn is_json(&self) -> bool {
+        matches!(
+            self.action,
+            AccountAction::Map {
+                output_json: true,
+                ..
+            }
+        )
+    }
+}
+
+#[async_trait(?Send)]
+impl AsyncCommand for AccountCommand {
+    type Output = Result<(), ErrorVariant>;
+
+    async fn exec(&self) -> Result<(), ErrorVariant> {
+        match self.action {
+            AccountAction::Map {
+                ref extrinsic_opts,
+                output_json,
+            } => map_account(extrinsic_opts, output_json).await,
+            AccountAction::Status {
+                ref account,
+                ref url,
+            } => {
+                let client = OnlineClient::from_url(url.clone()).await?;
+                let status = if !requires_mapping(&client.metadata()) {
+                    "not required by the runtime"
+                } else if is_mapped(&client, account).await? {
+                    "mapped"
+                } else {
+                    "not mapped"
+                };
+                name_value_println!(
+                    "Account",
+                    account.to_string(),
+                    DEFAULT_KEY_COL_WIDTH
+                );
+                name_value_println!(
+                    "Address",
+                    format!("0x{}", hex::encode(contract_address(account))),
+                    DEFAULT_KEY_COL_WIDTH
+                );
+                name_value_println!("Mapping", status, DEFAULT_KEY_COL_WIDTH);
+                Ok(())
+            }
+            AccountAction::Generate {
+                words,
+                scheme,
+                ref keystore,
+                ref password,
+                ref network,
+            } => {
+                generate_account(
+                    words,
+                    scheme,
+                    keystore,
+                    password.as_ref().map(Password::as_str),
+                    network.as_deref(),
+                )?;
+                Ok(())
+            }
+        }
+    }
+}
+
+async fn map_account(
+    opts: &ExtrinsicOpts,
+    output_json: bool,
+) -> Result<(), ErrorVariant> {
+    opts.apply_process_opts()?;
+    let signer = opts.signer("account map")?;
+    let account = AccountId::from(signer.public().0);
+
+    let client = OnlineClient::from_url(opts.url_to_string()?).await?;
+    if !requires_mapping(&client.metadata()) {
+        return Err(anyhow::anyhow!(
+            "The runtime has no `{REVIVE_PALLET}::map_account` call, its accounts need \
+            no mapping"
+        )
+        .into())
+    }
+    let print_details = || {
+        name_value_println!(
+            "Call",
+            format!("{REVIVE_PALLET}::map_account"),
+            DEFAULT_KEY_COL_WIDTH
+        );
+        name_value_println!("Account", account.to_string(), DEFAULT_KEY_COL_WIDTH);
+        name_value_println!(
+            "Address",
+            format!("0x{}", hex::encode(contract_address(&account))),
+            DEFAULT_KEY_COL_WIDTH
+        );
+    };
+    if is_mapped(&client, &account).await? {
+        print_details();
+        println!("The account is already mapped, there is nothing to do");
+        return Ok(())
+    }
+    if !opts.execute()? {
+        print_details();
+        display_dry_run_result_warning("account map");
+        return Ok(())
+    }
+    opts.confirm(print_details)?;
+
+    let call = subxt::dynamic::tx(REVIVE_PALLET, "map_account", Vec::<Value>::new());
+    let output =
+        submit_dynamic_call(&client, &call, &pair_signer(signer), opts, output_json)
+            .await?;
+    println!("{output}");
+    Ok(())
+}
+
+/// Generate a key with a new mnemonic, store it in the keystore directory and display
+/// its account with the instructions to fund it.
+fn generate_account(
+    words: usize,
+    scheme: Scheme,
+    keystore: &Path,
+    password: Option<&str>,
+    network: Option<&str>,
+) -> Result<()> {
+    let chain = network
+        .map(|name| Config::load()?.catalogue().chain(name).cloned())
+        .transpose()?;
+    let mnemonic = new_mnemonic(words)?;
+    let password = match password {
+        Some(password) => (!password.is_empty()).then(|| password.to_string()),
+        None => read_new_password("Password of the new key")?,
+    }
+    .ok_or_else(|| anyhow!("A password is required to encrypt the key file"))?;
+    let (path, pair) = match scheme {
+        Scheme::Sr25519 => write_keystore(keystore, mnemonic.phrase(), &password)?,
+    };
+    let account = match chain {
+        Some(ref chain) => {
+            pair.public()
+                .to_ss58check_with_version(Ss58AddressFormat::custom(chain.ss58_prefix))
+        }
+        None => pair.public().to_ss58check(),
+    };
+
+    name_value_println!("Account", account, DEFAULT_KEY_COL_WIDTH);
+    name_value_println!(
+        "Keystore",
+        path.display().to_string(),
+        DEFAULT_KEY_COL_WIDTH
+    );
+    println!(
+        "\n{} Write the mnemonic down and keep it secret, it restores the account \
+        in any wallet:\n\n    {}\n",
+        "warning:".yellow().bold(),
+        mnemonic.phrase()
+    );
+    let token = match chain {
+        Some(ref chain) => format!("{} on {}", chain.symbol, chain.name),
+        None => String::from("the token of the chain"),
+    };
+    println!(
+        "Fund the account with {token} to pay for deployments, by a transfer from \
+        another account or from the faucet of a testnet. Then sign with `--signer \
+        keystore:{}`, the password is prompted for unless given with `--password`.",
+        path.display()
+    );
+    Ok(())
+}
+
+/// Generate a new English BIP39 mnemonic of the given number of words.
+fn new_mnemonic(words: usize) -> Result<Mnemonic> {
+    let kind = MnemonicType::for_word_count(words)
+        .map_err(|_| anyhow!("A mnemonic has 12, 15, 18, 21 or 24 words, not {words}"))?;
+    Ok(Mnemonic::new(kind, Language::English))
+}
+
+/// Returns the 20 byte contract address of the account.
+pub fn contract_address(account: &AccountId) -> [u8; 20] {
+    let mut address = [0u8; 20];
+    if is_eth_derived(account) {
+        address.copy_from_slice(&account.0[..20]);
+    } else {
+        address.copy_from_slice(&keccak_256(&account.0)[12..]);
+    }
+    address
+}
+
+/// Returns `true` if the account is derived from an Ethereum address, which is mapped
+/// implicitly.
+fn is_eth_derived(account: &AccountId) -> bool {
+    account.0[20..] == ETH_DERIVED_SUFFIX
+}
+
+/// Returns `true` if the runtime refuses contract interactions of unmapped accounts.
+pub fn requires_mapping(metadata: &Metadata) -> bool {
+    metadata
+        .pallet(REVIVE_PALLET)
+        .is_ok_and(|pallet| pallet.call_index("map_account").is_ok())
+}
+
+/// Returns `true` if the account is mapped to its contract address.
+pub async fn is_mapped(client: &Client, account: &AccountId) -> Result<bool> {
+    if is_eth_derived(account) {
+        return Ok(true)
+    }
+    let address = subxt::dynamic::storage(
+        REVIVE_PALLET,
+        "OriginalAccount",
+        vec![Value::from_bytes(contract_address(account))],
+    );
+    let original = client.storage().at_latest().await?.fetch(&address).await?;
+    Ok(original.is_some())
+}
+
+/// Fail if the runtime requires the account to be mapped before interacting with
+/// contracts, and it is not mapped yet.
+pub async fn ensure_mapped(client: &Client, account: &AccountId) -> Result<()> {
+    if !requires_mapping(&client.metadata()) || is_mapped(client, account).await? {
+        return Ok(())
+    }
+    anyhow::bail!(
+        "The account {account} is not mapped to a contract address, which the runtime \
+        requires before interacting with contracts. Map it with `cargo contract account \
+        map`, signed by the same account"
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn contract_addresses_are_derived_from_accounts() {
+        let account = AccountId::from([1u8; 32]);
+        assert_eq!(contract_address(&account)[..], keccak_256(&[1u8; 32])[12..]);
+        assert!(!is_eth_derived(&account));
+
+        let mut eth_derived = [0xEE; 32];
+        eth_derived[..20].copy_from_slice(&[2u8; 20]);
+        let account = AccountId::from(eth_derived);
+        assert_eq!(contract_address(&account), [2u8; 20]);
+        assert!(is_eth_derived(&account));
+    }
+
+    #[test]
+    fn mnemonics_have_the_given_number_of_words() {
+        for words in [12, 24] {
+            let mnemonic = new_mnemonic(words).unwrap();
+            assert_eq!(mnemonic.phrase().split_whitespace().count(), words);
+            assert!(Mnemonic::validate(mnemonic.phrase(), Language::English).is_ok());
+        }
+        assert!(new_mnemonic(13).is_err());
+        assert_ne!(
+            new_mnemonic(24).unwrap().phrase(),
+            new_mnemonic(24).unwrap().phrase()
+        );
+    }
+}
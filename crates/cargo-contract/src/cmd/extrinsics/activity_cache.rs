@@ -0,0 +1,438 @@
+// Copyright 2018-2023 Parity Technologies (UK) Ltd.
+// This file is part of cargo-contract.
+//
+// cargo-contract is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// cargo-contract is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with cargo-contract.  If not, see <http://www.gnu.org/licenses/>.
+
+//! A local SQLite cache of the contract activity.
+//!
+//! The cache is populated with the events of the contracts pallet by the `events` and
+//! `watch-deployments` commands, which scan the blocks of the node, and with the receipts
+//! of the extrinsics submitted by the other commands. Chains are identified by their
+//! genesis hash, so the history of a chain is shared by all the urls of its nodes. The
+//! blocks which were scanned completely are recorded, so they are never fetched again.
+
+use super::{
+    events::DisplayEvents,
+    history::ContractEvent,
+    Client,
+    DefaultConfig,
+    TokenMetadata,
+};
+use anyhow::{
+    Context,
+    Result,
+};
+use rusqlite::{
+    params,
+    Connection,
+    OptionalExtension,
+};
+use std::path::{
+    Path,
+    PathBuf,
+};
+use subxt::{
+    blocks::ExtrinsicEvents,
+    tx::TxPayload,
+};
+
+/// The default file name of the cache, relative to the current directory.
+pub const ACTIVITY_CACHE_FILE_NAME: &str = "cargo-contract-activity.sqlite";
+
+/// The environment variable overriding the path of the cache.
+pub const ACTIVITY_CACHE_PATH_ENV: &str = "CARGO_CONTRACT_ACTIVITY_CACHE";
+
+const SCHEMA: &str = "
+    CREATE TABLE IF NOT EXISTS blocks (
+        network TEXT NOT NULL,
+        number INTEGER NOT NULL,
+        hash TEXT NOT NULL,
+        PRIMARY KEY (network, number)
+    );
+    CREATE TABLE IF NOT EXISTS events (
+        network TEXT NOT NULL,
+        block_number INTEGER NOT NULL,
+        block_hash TEXT NOT NULL,
+        event_index INTEGER NOT NULL,
+        extrinsic_index INTEGER,
+        contract TEXT,
+        pallet TEXT NOT NULL,
+        name TEXT NOT NULL,
+        fields TEXT NOT NULL,
+        PRIMARY KEY (network, block_hash, event_index)
+    );
+    CREATE INDEX IF NOT EXISTS events_by_contract ON events (network, contract);
+    CREATE TABLE IF NOT EXISTS calls (
+        network TEXT NOT NULL,
+        extrinsic_hash TEXT NOT NULL,
+        block_number INTEGER NOT NULL,
+        block_hash TEXT NOT NULL,
+        extrinsic_index INTEGER NOT NULL,
+        contract TEXT,
+        pallet TEXT NOT NULL,
+        call TEXT NOT NULL,
+        PRIMARY KEY (network, extrinsic_hash)
+    );
+";
+
+/// The receipt of an extrinsic submitted to the chain.
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize)]
+pub struct CallReceipt {
+    pub extrinsic_hash: String,
+    pub block_number: u64,
+    pub block_hash: String,
+    pub extrinsic_index: u32,
+    /// The contract of the first event of the contracts pallet, if there is one.
+    pub contract: Option<String>,
+    pub pallet: String,
+    pub call: String,
+}
+
+/// The summary of the cached activity of a contract.
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize)]
+pub struct ContractActivity {
+    pub contract: String,
+    pub events: u64,
+    pub calls: u64,
+    pub first_block: u64,
+    pub last_block: u64,
+    pub last_event: String,
+}
+
+/// The cache of the activity of a chain.
+pub struct ActivityCache {
+    connection: Connection,
+    network: String,
+}
+
+impl ActivityCache {
+    /// The path of the cache, overridden by [`ACTIVITY_CACHE_PATH_ENV`].
+    pub fn path() -> Result<PathBuf> {
+        match std::env::var_os(ACTIVITY_CACHE_PATH_ENV) {
+            Some(path) => Ok(PathBuf::from(path)),
+            None => Ok(std::env::current_dir()?.join(ACTIVITY_CACHE_FILE_NAME)),
+        }
+    }
+
+    /// Open the cache at the default path for the chain of the client.
+    pub fn open_for(client: &Client) -> Result<Self> {
+        let network = format!("{:?}", client.genesis_hash());
+        Self::open(&Self::path()?, &network)
+    }
+
+    /// Open the cache at the path for the network, creating it if it does not exist.
+    pub fn open(path: &Path, network: &str) -> Result<Self> {
+        let connection = Connection::open(path).with_context(|| {
+            format!("Failed to open the activity cache {}", path.display())
+        })?;
+        // Other processes may be recording into the same cache.
+        connection.busy_timeout(std::time::Duration::from_secs(10))?;
+        connection.execute_batch(SCHEMA)?;
+        Ok(Self {
+            connection,
+            network: network.to_string(),
+        })
+    }
+
+    /// Returns `true` if all the events of the block are cached.
+    pub fn has_block(&self, number: u64) -> Result<bool> {
+        let hash: Option<String> = self
+            .connection
+            .query_row(
+                "SELECT hash FROM blocks WHERE network = ?1 AND number = ?2",
+                params![self.network, number],
+                |row| row.get(0),
+            )
+            .optional()?;
+        Ok(hash.is_some())
+    }
+
+    /// Store the events of the contracts pallet of the completely scanned block.
+    pub fn insert_block(
+        &mut self,
+        number: u64,
+        hash: &str,
+        events: &[ContractEvent],
+    ) -> Result<()> {
+        let transaction = self.connection.transaction()?;
+        insert_events(&transaction, &self.network, events)?;
+        transaction.execute(
+            "INSERT OR REPLACE INTO blocks (network, number, hash) VALUES (?1, ?2, ?3)",
+            params![self.network, number, hash],
+        )?;
+        Ok(transaction.commit()?)
+    }
+
+    /// Store the receipt of an extrinsic with its events of the contracts pallet.
+    pub fn insert_call(
+        &mut self,
+        receipt: &CallReceipt,
+        events: &[ContractEvent],
+    ) -> Result<()> {
+        let transaction = self.connection.transaction()?;
+        insert_events(&transaction, &self.network, events)?;
+        transaction.execute(
+            "INSERT OR REPLACE INTO calls (network, extrinsic_hash, block_number, \
+             block_hash, extrinsic_index, contract, pallet, call) \
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+            params![
+                self.network,
+                receipt.extrinsic_hash,
+                receipt.block_number,
+                receipt.block_hash,
+                receipt.extrinsic_index,
+                receipt.contract,
+                receipt.pallet,
+                receipt.call
+            ],
+        )?;
+        Ok(transaction.commit()?)
+    }
+
+    /// The cached events, optionally of the contract only and in the range of blocks,
+    /// in the order they were emitted in.
+    pub fn events(
+        &self,
+        contract: Option<&str>,
+        from_block: Option<u64>,
+        to_block: Option<u64>,
+    ) -> Result<Vec<ContractEvent>> {
+        let mut statement = self.connection.prepare(
+            "SELECT block_number, block_hash, event_index, extrinsic_index, contract, \
+             pallet, name, fields FROM events WHERE network = ?1 \
+             AND (?2 IS NULL OR contract = ?2) \
+             AND (?3 IS NULL OR block_number >= ?3) \
+             AND (?4 IS NULL OR block_number <= ?4) \
+             ORDER BY block_number, event_index",
+        )?;
+        let rows = statement.query_map(
+            params![self.network, contract, from_block, to_block],
+            |row| {
+                Ok((
+                    ContractEvent {
+                        block_number: row.get(0)?,
+                        block_hash: row.get(1)?,
+                        event_index: row.get(2)?,
+                        extrinsic_index: row.get(3)?,
+                        contract: row.get(4)?,
+                        pallet: row.get(5)?,
+                        name: row.get(6)?,
+                        fields: serde_json::Value::Null,
+                    },
+                    row.get::<_, String>(7)?,
+                ))
+            },
+        )?;
+        rows.map(|row| {
+            let (mut event, fields) = row?;
+            event.fields = serde_json::from_str(&fields)?;
+            Ok(event)
+        })
+        .collect()
+    }
+
+    /// The summary of the cached activity of every contract, the most recently active
+    /// contract first.
+    pub fn dashboard(&self) -> Result<Vec<ContractActivity>> {
+        let mut statement = self.connection.prepare(
+            "SELECT contract, COUNT(*), MIN(block_number), MAX(block_number), \
+             (SELECT COUNT(*) FROM calls \
+                WHERE calls.network = events.network AND calls.contract = events.contract), \
+             (SELECT last.pallet || '::' || last.name FROM events AS last \
+                WHERE last.network = events.network AND last.contract = events.contract \
+                ORDER BY last.block_number DESC, last.event_index DESC LIMIT 1) \
+             FROM events WHERE network = ?1 AND contract IS NOT NULL \
+             GROUP BY contract ORDER BY MAX(block_number) DESC, contract",
+        )?;
+        let rows = statement.query_map(params![self.network], |row| {
+            Ok(ContractActivity {
+                contract: row.get(0)?,
+                events: row.get(1)?,
+                first_block: row.get(2)?,
+                last_block: row.get(3)?,
+                calls: row.get(4)?,
+                last_event: row.get(5)?,
+            })
+        })?;
+        Ok(rows.collect::<rusqlite::Result<_>>()?)
+    }
+}
+
+fn insert_events(
+    transaction: &rusqlite::Transaction,
+    network: &str,
+    events: &[ContractEvent],
+) -> Result<()> {
+    let mut statement = transaction.prepare(
+        "INSERT OR REPLACE INTO events (network, block_number, block_hash, event_index, \
+         extrinsic_index, contract, pallet, name, fields) \
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)",
+    )?;
+    for event in events {
+        statement.execute(params![
+            network,
+            event.block_number,
+            event.block_hash,
+            event.event_index,
+            event.extrinsic_index,
+            event.contract,
+            event.pallet,
+            event.name,
+            event.fields.to_string()
+        ])?;
+    }
+    Ok(())
+}
+
+/// Record the receipt of the submitted extrinsic in the cache. Failures are only
+/// reported, since the extrinsic was already included.
+pub async fn record_receipt<Call: TxPayload>(
+    client: &Client,
+    call: &Call,
+    result: &ExtrinsicEvents<DefaultConfig>,
+) {
+    if let Err(err) = try_record_receipt(client, call, result).await {
+        tracing::warn!("Failed to record the receipt in the activity cache: {err:?}");
+    }
+}
+
+async fn try_record_receipt<Call: TxPayload>(
+    client: &Client,
+    call: &Call,
+    result: &ExtrinsicEvents<DefaultConfig>,
+) -> Result<()> {
+    let block = client.blocks().at(result.block_hash()).await?;
+    let block_number = u64::from(block.number());
+    let block_hash = format!("{:?}", result.block_hash());
+    let token_metadata = TokenMetadata::query(client).await?;
+    let display_events =
+        DisplayEvents::from_events(result, None, &client.metadata(), &token_metadata)?;
+    let mut events = Vec::new();
+    for (details, event) in result.iter().zip(display_events.into_events()) {
+        let event = ContractEvent::new(block_number, &block_hash, &details?, event)?;
+        if event.pallet == "Contracts" {
+            events.push(event);
+        }
+    }
+    let (pallet, call) = match call.validation_details() {
+        Some(details) => {
+            (
+                details.pallet_name.to_string(),
+                details.call_name.to_string(),
+            )
+        }
+        None => (String::from("unknown"), String::from("unknown")),
+    };
+    let receipt = CallReceipt {
+        extrinsic_hash: format!("{:?}", result.extrinsic_hash()),
+        block_number,
+        block_hash,
+        extrinsic_index: result.extrinsic_index(),
+        contract: events.iter().find_map(|event| event.contract.clone()),
+        pallet,
+        call,
+    };
+    ActivityCache::open_for(client)?.insert_call(&receipt, &events)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn event(
+        block_number: u64,
+        event_index: u32,
+        contract: &str,
+        name: &str,
+    ) -> ContractEvent {
+        ContractEvent {
+            block_number,
+            block_hash: format!("0x{block_number:064x}"),
+            event_index,
+            extrinsic_index: Some(1),
+            contract: Some(contract.to_string()),
+            pallet: String::from("Contracts"),
+            name: name.to_string(),
+            fields: serde_json::json!([{ "name": "contract", "value": contract }]),
+        }
+    }
+
+    #[test]
+    fn activity_is_cached_per_network() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join(ACTIVITY_CACHE_FILE_NAME);
+        let mut cache = ActivityCache::open(&path, "0x01").unwrap();
+
+        assert!(!cache.has_block(7).unwrap());
+        cache
+            .insert_block(7, "0x07", &[event(7, 2, "flipper", "Instantiated")])
+            .unwrap();
+        let receipt = CallReceipt {
+            extrinsic_hash: String::from("0xaa"),
+            block_number: 9,
+            block_hash: format!("0x{:064x}", 9),
+            extrinsic_index: 1,
+            contract: Some(String::from("flipper")),
+            pallet: String::from("Contracts"),
+            call: String::from("call"),
+        };
+        cache
+            .insert_call(
+                &receipt,
+                &[
+                    event(9, 3, "flipper", "Called"),
+                    event(9, 4, "erc20", "ContractEmitted"),
+                ],
+            )
+            .unwrap();
+        assert!(cache.has_block(7).unwrap());
+        assert!(!cache.has_block(9).unwrap());
+
+        let events = cache.events(Some("flipper"), None, None).unwrap();
+        assert_eq!(
+            events,
+            [
+                event(7, 2, "flipper", "Instantiated"),
+                event(9, 3, "flipper", "Called")
+            ]
+        );
+        assert_eq!(cache.events(None, Some(8), Some(9)).unwrap().len(), 2);
+
+        assert_eq!(
+            cache.dashboard().unwrap(),
+            [
+                ContractActivity {
+                    contract: String::from("erc20"),
+                    events: 1,
+                    calls: 0,
+                    first_block: 9,
+                    last_block: 9,
+                    last_event: String::from("Contracts::ContractEmitted"),
+                },
+                ContractActivity {
+                    contract: String::from("flipper"),
+                    events: 2,
+                    calls: 1,
+                    first_block: 7,
+                    last_block: 9,
+                    last_event: String::from("Contracts::Called"),
+                }
+            ]
+        );
+
+        let other = ActivityCache::open(&path, "0x02").unwrap();
+        assert!(!other.has_block(7).unwrap());
+        assert!(other.dashboard().unwrap().is_empty());
+    }
+}
@@ -0,0 +1,473 @@
+// Copyright 2018-2023 Parity Technologies (UK) Ltd.
+// This file is part of cargo-contract.
+//
+// cargo-contract is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// cargo-contract is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with cargo-contract.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Loading of the artifacts of a contract, shared by all commands.
+//!
+//! The artifacts are loaded from the target directory of the contract of the manifest,
+//! or from a source given with `--file`:
+//!
+//! - a `.contract` bundle or a `.contract.zst` compressed bundle, with code and metadata;
+//! - a `.json` metadata file, without code;
+//! - a `.wasm` file, with the metadata of the `.json` file of the same name if it exists;
+//! - the hash of uploaded code, with the metadata recorded for the code in the local
+//!   registry, without code;
+//! - an `http://` or `https://` URL of one of these files, downloaded into the cache.
+//!
+//! The format of a file without one of these extensions is detected from its contents.
+//! The accessors of the artifacts name the piece which is missing, and what the source
+//! holds instead.
+
+use super::{
+    parse_code_hash,
+    ContractMessageTranscoder,
+};
+use crate::cmd::{
+    node::{
+        cache_root,
+        download,
+    },
+    registry::Registry,
+    CodeHash,
+};
+use anyhow::{
+    anyhow,
+    Context,
+    Result,
+};
+use contract_build::{
+    compression,
+    CrateMetadata,
+};
+use contract_metadata::ContractMetadata;
+use sp_core::hashing::blake2_256;
+use std::{
+    fmt,
+    io::Read,
+    path::{
+        Path,
+        PathBuf,
+    },
+};
+
+/// The magic number at the start of Wasm modules.
+const WASM_MAGIC: [u8; 4] = *b"\0asm";
+
+/// The magic number at the start of zstd frames.
+const ZSTD_MAGIC: [u8; 4] = [0x28, 0xB5, 0x2F, 0xFD];
+
+/// The source of the artifacts of a contract.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ArtifactSource {
+    /// A file in one of the artifact formats.
+    File(PathBuf),
+    /// The hash of uploaded code.
+    CodeHash(CodeHash),
+    /// The URL of a file in one of the artifact formats.
+    Url(url::Url),
+}
+
+impl ArtifactSource {
+    /// Parse the source given with `--file`. Existing files take precedence over code
+    /// hashes and URLs.
+    pub fn parse(input: &Path) -> Self {
+        let file = || Self::File(input.to_path_buf());
+        let Some(text) = input.to_str().filter(|_| !input.exists()) else {
+            return file()
+        };
+        if text.starts_with("http://") || text.starts_with("https://") {
+            return url::Url::parse(text)
+                .map(Self::Url)
+                .unwrap_or_else(|_| file())
+        }
+        if text.starts_with("0x") && text.len() == 66 {
+            return parse_code_hash(text)
+                .map(Self::CodeHash)
+                .unwrap_or_else(|_| file())
+        }
+        file()
+    }
+}
+
+impl fmt::Display for ArtifactSource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::File(path) => write!(f, "{}", path.display()),
+            Self::CodeHash(code_hash) => write!(f, "the code hash {code_hash:?}"),
+            Self::Url(url) => write!(f, "{url}"),
+        }
+    }
+}
+
+/// The format of an artifact file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ArtifactFormat {
+    /// A `.contract` bundle or a `.json` metadata file.
+    Metadata,
+    /// A zstd compressed `.contract.zst` bundle.
+    CompressedBundle,
+    /// A raw `.wasm` file.
+    Wasm,
+}
+
+impl ArtifactFormat {
+    /// Detect the format of the file from its extension, or from its contents if the
+    /// extension is not one of the artifact extensions.
+    pub fn detect(path: &Path) -> Result<Self> {
+        match path.extension().and_then(|ext| ext.to_str()) {
+            Some("zst") if compression::is_compressed_bundle(path) => {
+                return Ok(Self::CompressedBundle)
+            }
+            Some("contract") | Some("json") => return Ok(Self::Metadata),
+            Some("wasm") => return Ok(Self::Wasm),
+            _ => {}
+        }
+        let mut head = Vec::new();
+        std::fs::File::open(path)
+            .with_context(|| format!("Failed to open {}", path.display()))?
+            .take(64)
+            .read_to_end(&mut head)?;
+        Self::from_contents(&head).with_context(|| {
+            format!(
+                "Failed to detect the format of {}, expected a `.contract`, \
+                `.contract.zst`, `.json` or `.wasm` file",
+                path.display()
+            )
+        })
+    }
+
+    /// Detect the format from the first bytes of a file.
+    fn from_contents(head: &[u8]) -> Option<Self> {
+        if head.starts_with(&WASM_MAGIC) {
+            Some(Self::Wasm)
+        } else if head.starts_with(&ZSTD_MAGIC) {
+            Some(Self::CompressedBundle)
+        } else if head.iter().find(|b| !b.is_ascii_whitespace()) == Some(&b'{') {
+            Some(Self::Metadata)
+        } else {
+            None
+        }
+    }
+}
+
+/// Contract artifacts for use with extrinsic commands.
+#[derive(Debug)]
+pub struct ContractArtifacts {
+    /// The original artifact path
+    artifacts_path: PathBuf,
+    /// The source the artifacts were loaded from.
+    source: ArtifactSource,
+    /// Where the metadata was looked for, reported if it is missing.
+    metadata_location: String,
+    /// The deserialized contract metadata if the expected metadata file exists.
+    metadata: Option<ContractMetadata>,
+    /// The hash of the code, if the artifacts were loaded from a code hash.
+    code_hash: Option<CodeHash>,
+    /// The Wasm code of the contract if available.
+    pub code: Option<WasmCode>,
+}
+
+impl ContractArtifacts {
+    /// Load contract artifacts.
+    pub fn from_manifest_or_file(
+        manifest_path: Option<&PathBuf>,
+        file: Option<&PathBuf>,
+    ) -> Result<ContractArtifacts> {
+        let artifact_path = match (manifest_path, file) {
+            (manifest_path, None) => {
+                let crate_metadata = CrateMetadata::from_manifest_path(
+                    manifest_path,
+                    contract_build::Target::Wasm,
+                )?;
+
+                if crate_metadata.contract_bundle_path().exists() {
+                    crate_metadata.contract_bundle_path()
+                } else if crate_metadata.compressed_bundle_path().exists() {
+                    crate_metadata.compressed_bundle_path()
+                } else if crate_metadata.metadata_path().exists() {
+                    crate_metadata.metadata_path()
+                } else {
+                    anyhow::bail!(
+                        "Failed to find any contract artifacts in target directory. \n\
+                        Run `cargo contract build --release` to generate the artifacts."
+                    )
+                }
+            }
+            (None, Some(artifact_file)) => {
+                return Self::load(ArtifactSource::parse(artifact_file))
+            }
+            (Some(_), Some(_)) => {
+                anyhow::bail!("conflicting options: --manifest-path and --file")
+            }
+        };
+        Self::from_artifact_path(artifact_path.as_path())
+    }
+
+    /// Load the contract artifacts from the source.
+    pub fn load(source: ArtifactSource) -> Result<Self> {
+        match source {
+            ArtifactSource::File(ref path) => Self::from_artifact_path(path),
+            ArtifactSource::CodeHash(code_hash) => Self::from_code_hash(code_hash),
+            ArtifactSource::Url(ref url) => {
+                let path = download_artifact(url)?;
+                let mut artifacts = Self::from_artifact_path(&path)?;
+                artifacts.source = source;
+                Ok(artifacts)
+            }
+        }
+    }
+
+    /// Given a contract artifact path, load the contract code and metadata where
+    /// possible.
+    fn from_artifact_path(path: &Path) -> Result<Self> {
+        tracing::debug!("Loading contracts artifacts from `{}`", path.display());
+        let (metadata_path, metadata, code) = match ArtifactFormat::detect(path)? {
+            ArtifactFormat::CompressedBundle => {
+                let metadata = compression::load_compressed_bundle(path)?;
+                let code = metadata.clone().source.wasm.map(|wasm| WasmCode(wasm.0));
+                (PathBuf::from(path), Some(metadata), code)
+            }
+            ArtifactFormat::Metadata => {
+                let metadata = ContractMetadata::load(path)?;
+                let code = metadata.clone().source.wasm.map(|wasm| WasmCode(wasm.0));
+                (PathBuf::from(path), Some(metadata), code)
+            }
+            ArtifactFormat::Wasm => {
+                let file_name = path
+                    .file_stem()
+                    .context("WASM bundle file has unreadable name")?
+                    .to_str()
+                    .context("Error parsing filename string")?;
+                let code = Some(WasmCode(std::fs::read(path)?));
+                let dir = path.parent().map_or_else(PathBuf::new, PathBuf::from);
+                let metadata_path = dir.join(format!("{file_name}.json"));
+                if !metadata_path.exists() {
+                    (metadata_path, None, code)
+                } else {
+                    let metadata = ContractMetadata::load(&metadata_path)?;
+                    (metadata_path, Some(metadata), code)
+                }
+            }
+        };
+        Ok(Self {
+            artifacts_path: path.into(),
+            source: ArtifactSource::File(path.into()),
+            metadata_location: format!("Expected file {}", metadata_path.display()),
+            metadata,
+            code_hash: None,
+            code,
+        })
+    }
+
+    /// Load the artifacts of uploaded code, from the metadata recorded for the code in
+    /// the local registry.
+    fn from_code_hash(code_hash: CodeHash) -> Result<Self> {
+        let registry = Registry::load()?;
+        let metadata_path = registry
+            .codes
+            .iter()
+            .filter(|code| code.code_hash == code_hash)
+            .find_map(|code| code.metadata.clone());
+        let mut artifacts = match metadata_path {
+            Some(path) => Self::from_artifact_path(&path)?,
+            None => {
+                Self {
+                    artifacts_path: PathBuf::from(format!("{code_hash:?}")),
+                    source: ArtifactSource::CodeHash(code_hash),
+                    metadata_location: format!(
+                        "No metadata of the code is recorded in the registry {}",
+                        Registry::path()?.display()
+                    ),
+                    metadata: None,
+                    code_hash: None,
+                    code: None,
+                }
+            }
+        };
+        if let Some(ref metadata) = artifacts.metadata {
+            anyhow::ensure!(
+                metadata.source.hash.0 == code_hash.0,
+                "The metadata {} recorded for the code {code_hash:?} is of the code 0x{}",
+                artifacts.artifacts_path.display(),
+                hex::encode(metadata.source.hash.0)
+            );
+        }
+        artifacts.source = ArtifactSource::CodeHash(code_hash);
+        artifacts.code_hash = Some(code_hash);
+        Ok(artifacts)
+    }
+
+    /// Get the path of the artifact file used to load the artifacts.
+    pub fn artifact_path(&self) -> &Path {
+        self.artifacts_path.as_path()
+    }
+
+    /// Get contract metadata, if available.
+    ///
+    /// ## Errors
+    /// - No contract metadata could be found.
+    /// - Invalid contract metadata.
+    pub fn metadata(&self) -> Result<ContractMetadata> {
+        self.metadata.clone().ok_or_else(|| {
+            anyhow!(
+                "No contract metadata found in {}, which holds only the {}. {}",
+                self.source,
+                if self.code.is_some() {
+                    "code"
+                } else {
+                    "code hash"
+                },
+                self.metadata_location
+            )
+        })
+    }
+
+    /// Get the contract code, if available.
+    ///
+    /// ## Errors
+    /// - The artifacts hold only the metadata or the code hash of the contract.
+    pub fn code(&self) -> Result<&WasmCode> {
+        self.code.as_ref().ok_or_else(|| {
+            anyhow!(
+                "No contract code found in {}, which holds only the {}. Pass the \
+                `.contract` bundle or the `.wasm` file of the contract instead",
+                self.source,
+                if self.metadata.is_some() {
+                    "metadata"
+                } else {
+                    "code hash"
+                }
+            )
+        })
+    }
+
+    /// Get the code hash from the contract metadata, the code or the source.
+    pub fn code_hash(&self) -> Result<[u8; 32]> {
+        if let Some(ref metadata) = self.metadata {
+            return Ok(metadata.source.hash.0)
+        }
+        if let Some(code_hash) = self.code_hash {
+            return Ok(code_hash.0)
+        }
+        match self.code {
+            Some(ref code) => Ok(code.code_hash()),
+            None => self.metadata().map(|metadata| metadata.source.hash.0),
+        }
+    }
+
+    /// Construct a [`ContractMessageTranscoder`] from contract metadata.
+    pub fn contract_transcoder(&self) -> Result<ContractMessageTranscoder> {
+        self.contract_transcoder_with_identities(&Identities::default())
+    }
+
+    /// Construct a [`ContractMessageTranscoder`] from contract metadata, which displays
+    /// the names of the given identities with the `identity` notation.
+    pub fn contract_transcoder_with_identities(
+        &self,
+        identities: &Identities,
+    ) -> Result<ContractMessageTranscoder> {
+        let metadata = self.metadata()?;
+        super::event_signatures::record(&metadata);
+        crate::cmd::config::Config::load()?
+            .contract_transcoder_with_identities(metadata, identities)
+            .context("Failed to deserialize ink project metadata from contract metadata")
+    }
+}
+
+/// Download the artifact file into the cache, keeping the name of the file so that its
+/// format is detected from its extension.
+fn download_artifact(url: &url::Url) -> Result<PathBuf> {
+    let name = url
+        .path_segments()
+        .and_then(|mut segments| segments.next_back())
+        .filter(|name| !name.is_empty())
+        .unwrap_or("artifact");
+    let dir = cache_root()?
+        .join("artifacts")
+        .join(hex::encode(&blake2_256(url.as_str().as_bytes())[..8]));
+    std::fs::create_dir_all(&dir)
+        .with_context(|| format!("Failed to create {}", dir.display()))?;
+    let path = dir.join(name);
+    download(url.as_str(), &path)?;
+    Ok(path)
+}
+
+/// The Wasm code of a contract.
+#[derive(Debug, Clone)]
+pub struct WasmCode(Vec<u8>);
+
+impl WasmCode {
+    /// The hash of the contract code: uniquely identifies the contract code on-chain.
+    pub fn code_hash(&self) -> [u8; 32] {
+        contract_build::code_hash(&self.0)
+    }
+
+    /// The raw bytes of the contract code.
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sources_are_parsed() {
+        let code_hash =
+            "0xd43593c715fdd31c61141abd04a99fd6822c8558854ccde39a5684e7a56da27d";
+        assert_eq!(
+            ArtifactSource::parse(Path::new(code_hash)),
+            ArtifactSource::CodeHash(parse_code_hash(code_hash).unwrap())
+        );
+        assert_eq!(
+            ArtifactSource::parse(Path::new("https://example.com/flipper.contract")),
+            ArtifactSource::Url("https://example.com/flipper.contract".parse().unwrap())
+        );
+        assert_eq!(
+            ArtifactSource::parse(Path::new("target/ink/flipper.contract")),
+            ArtifactSource::File(PathBuf::from("target/ink/flipper.contract"))
+        );
+    }
+
+    #[test]
+    fn formats_are_detected_from_contents() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("flipper");
+        std::fs::write(&path, b"\0asm\x01\0\0\0").unwrap();
+        assert_eq!(ArtifactFormat::detect(&path).unwrap(), ArtifactFormat::Wasm);
+        std::fs::write(&path, b"\n  {\"source\": {}}").unwrap();
+        assert_eq!(
+            ArtifactFormat::detect(&path).unwrap(),
+            ArtifactFormat::Metadata
+        );
+        std::fs::write(&path, [0x28, 0xB5, 0x2F, 0xFD, 0]).unwrap();
+        assert_eq!(
+            ArtifactFormat::detect(&path).unwrap(),
+            ArtifactFormat::CompressedBundle
+        );
+        std::fs::write(&path, b"flipper").unwrap();
+        assert!(ArtifactFormat::detect(&path).is_err());
+
+        let artifacts = ContractArtifacts::from_artifact_path(&dir.path().join("x.wasm"));
+        assert!(artifacts.is_err());
+        std::fs::write(dir.path().join("x.wasm"), b"\0asm\x01\0\0\0").unwrap();
+        let artifacts =
+            ContractArtifacts::from_artifact_path(&dir.path().join("x.wasm")).unwrap();
+        assert!(artifacts.code().is_ok());
+        let err = artifacts.metadata().unwrap_err().to_string();
+        assert!(err.contains("which holds only the code"), "{err}");
+    }
+}
@@ -0,0 +1,270 @@
+// Copyright 2018-2023 Parity Technologies (UK) Ltd.
+// This file is part of cargo-contract.
+//
+// cargo-contract is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// cargo-contract is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with cargo-contract.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Helpers for the `DappsStaking` pallet of Astar family chains (Astar, Shiden, Shibuya).
+//!
+//! Registration for dApp staking after instantiation is configured in the network
+//! profile:
+//!
+//! ```toml
+//! [network.shibuya]
+//! url = "wss://rpc.shibuya.astar.network"
+//!
+//! [network.shibuya.astar]
+//! register-dapp = true
+//! ```
+
+use super::{
+    display_dry_run_result_warning,
+    extrinsic_params,
+    pair_signer,
+    simulate::simulate_extrinsic,
+    submit_dynamic_call,
+    Client,
+    DefaultConfig,
+    ErrorVariant,
+    ExtrinsicOpts,
+    PairSigner,
+};
+use crate::{
+    cmd::AsyncCommand,
+    DEFAULT_KEY_COL_WIDTH,
+};
+use anyhow::Result;
+use async_trait::async_trait;
+use contract_build::name_value_println;
+use subxt::{
+    dynamic::Value,
+    tx::DynamicPayload,
+    Config,
+    OnlineClient,
+};
+
+type AccountId = <DefaultConfig as Config>::AccountId;
+
+/// The name of the dApp staking pallet in the runtimes of Astar family chains.
+const DAPPS_STAKING_PALLET: &str = "DappsStaking";
+
+/// The dApp staking settings of a network profile.
+#[derive(Debug, Clone, Default, serde::Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct AstarProfile {
+    /// Register contracts for dApp staking after they were instantiated.
+    #[serde(default)]
+    pub register_dapp: bool,
+    /// The developer account of the registration, defaults to the signer.
+    pub developer: Option<AccountId>,
+}
+
+#[derive(Debug, clap::Args)]
+#[clap(name = "astar", about = "dApp staking helpers for Astar family chains")]
+pub struct AstarCommand {
+    #[clap(subcommand)]
+    action: AstarAction,
+    #[clap(flatten)]
+    extrinsic_opts: ExtrinsicOpts,
+    /// Export the output in JSON format.
+    #[clap(long, conflicts_with = "verbose")]
+    output_json: bool,
+}
+
+#[derive(Debug, clap::Subcommand)]
+enum AstarAction {
+    /// Register a contract for dApp staking
+    Register {
+        /// The address of the contract to register.
+        #[clap(name = "contract", long, env = "CONTRACT")]
+        contract: AccountId,
+        /// The developer account of the dApp, defaults to the signer.
+        #[clap(long)]
+        developer: Option<AccountId>,
+    },
+    /// Claim the developer rewards of a registered contract for an era
+    ClaimDapp {
+        /// The address of the registered contract.
+        #[clap(name = "contract", long, env = "CONTRACT")]
+        contract: AccountId,
+        /// The era to claim the rewards for.
+        #[clap(long)]
+        era: u32,
+    },
+}
+
+impl AstarCommand {
+    pub fn is_json(&self) -> bool {
+        self.output_json
+    }
+}
+
+#[async_trait(?Send)]
+impl AsyncCommand for AstarCommand {
+    type Output = Result<(), ErrorVariant>;
+
+    async fn exec(&self) -> Result<(), ErrorVariant> {
+        self.extrinsic_opts.apply_process_opts()?;
+        let signer = pair_signer(self.extrinsic_opts.signer("astar")?);
+        let (name, call) = match self.action {
+            AstarAction::Register {
+                ref contract,
+                ref developer,
+            } => {
+                let developer = developer
+                    .clone()
+                    .unwrap_or_else(|| signer.account_id().clone());
+                ("register", register_call(&developer, contract))
+            }
+            AstarAction::ClaimDapp { ref contract, era } => {
+                ("claim_dapp", claim_dapp_call(contract, era))
+            }
+        };
+
+        let url = self.extrinsic_opts.url_to_string()?;
+        let client = OnlineClient::from_url(url).await?;
+
+        if !self.extrinsic_opts.execute()? {
+            dry_run_dynamic_call(&client, &call, &signer).await?;
+            print_call(name);
+            name_value_println!("Result", "Success!", DEFAULT_KEY_COL_WIDTH);
+            display_dry_run_result_warning(name);
+            return Ok(())
+        }
+        if !self.extrinsic_opts.skip_dry_run {
+            dry_run_dynamic_call(&client, &call, &signer).await?;
+        }
+        self.extrinsic_opts.confirm(|| print_call(name))?;
+        let output = submit_dynamic_call(
+            &client,
+            &call,
+            &signer,
+            &self.extrinsic_opts,
+            self.output_json,
+        )
+        .await?;
+        println!("{output}");
+        Ok(())
+    }
+}
+
+/// Register the newly instantiated contract for dApp staking, if configured in the
+/// profile of the `--network`.
+pub async fn register_instantiated(
+    opts: &ExtrinsicOpts,
+    client: &Client,
+    signer: &PairSigner,
+    contract: &AccountId,
+    output_json: bool,
+) -> Result<(), ErrorVariant> {
+    let Some(astar) = opts.network_profile()?.and_then(|profile| profile.astar) else {
+        return Ok(())
+    };
+    if !astar.register_dapp {
+        return Ok(())
+    }
+    let developer = astar
+        .developer
+        .unwrap_or_else(|| signer.account_id().clone());
+    let call = register_call(&developer, contract);
+    // the registration is a second extrinsic, dry-run and confirmed on its own
+    if !opts.skip_dry_run {
+        dry_run_dynamic_call(client, &call, signer).await?;
+    }
+    opts.confirm(|| {
+        print_call("register");
+        name_value_println!("Developer", developer.to_string(), DEFAULT_KEY_COL_WIDTH);
+    })?;
+    let output = submit_dynamic_call(client, &call, signer, opts, output_json).await?;
+    println!("{output}");
+    Ok(())
+}
+
+/// Dry-run the call as an extrinsic signed by the signer at the best block, returns an
+/// error if it would fail.
+async fn dry_run_dynamic_call(
+    client: &Client,
+    call: &DynamicPayload,
+    signer: &PairSigner,
+) -> Result<()> {
+    let (params, _) = extrinsic_params(client, None, 0).await?;
+    let extrinsic = client.tx().create_signed(call, signer, params).await?;
+    simulate_extrinsic(client, &extrinsic).await?;
+    Ok(())
+}
+
+/// Print the call of the `DappsStaking` pallet.
+fn print_call(name: &str) {
+    name_value_println!(
+        "Call",
+        format!("{DAPPS_STAKING_PALLET}::{name}"),
+        DEFAULT_KEY_COL_WIDTH
+    );
+}
+
+/// The `SmartContract` identifier of a Wasm contract in the `DappsStaking` pallet.
+fn smart_contract(contract: &AccountId) -> Value {
+    Value::unnamed_variant("Wasm", [Value::from_bytes(contract.0)])
+}
+
+/// Create the call registering the contract for dApp staking.
+fn register_call(developer: &AccountId, contract: &AccountId) -> DynamicPayload {
+    subxt::dynamic::tx(
+        DAPPS_STAKING_PALLET,
+        "register",
+        vec![Value::from_bytes(developer.0), smart_contract(contract)],
+    )
+}
+
+/// Create the call claiming the developer rewards of the contract for the era.
+fn claim_dapp_call(contract: &AccountId, era: u32) -> DynamicPayload {
+    subxt::dynamic::tx(
+        DAPPS_STAKING_PALLET,
+        "claim_dapp",
+        vec![smart_contract(contract), Value::u128(era.into())],
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::cmd::config::Config;
+
+    #[test]
+    fn astar_profile_is_parsed() {
+        let config = Config::parse(
+            r#"
+            [network.shibuya]
+            url = "wss://rpc.shibuya.astar.network"
+
+            [network.shibuya.astar]
+            register-dapp = true
+            developer = "5GrwvaEF5zXb26Fz9rcQpDWS57CtERHpNehXCPcNoHGKutQY"
+            "#,
+        )
+        .expect("config must be valid");
+
+        let astar = config
+            .network("shibuya")
+            .unwrap()
+            .astar
+            .clone()
+            .expect("astar profile must be set");
+        assert!(astar.register_dapp);
+        assert_eq!(
+            astar.developer.map(|account| account.to_string()),
+            Some(String::from(
+                "5GrwvaEF5zXb26Fz9rcQpDWS57CtERHpNehXCPcNoHGKutQY"
+            ))
+        );
+    }
+}
@@ -0,0 +1,267 @@
+// Copyright 2018-2023 Parity Technologies (UK) Ltd.
+// This file is part of cargo-contract.
+//
+// cargo-contract is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// cargo-contract is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with cargo-contract.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Attestation of the metadata and source of uploaded code.
+//!
+//! The owner of the code links its hash to the hash of its metadata and to the content
+//! identifier of its source, e.g. an IPFS CID, with a `System::remark_with_event`
+//! following the convention
+//!
+//! ```text
+//! cargo-contract:attest:<code hash>:<metadata hash>:<source cid>
+//! ```
+//!
+//! The metadata hash is the BLAKE2-256 hash of the metadata JSON without the Wasm code.
+//! The attestation is recorded in the local registry, so it can be looked up and
+//! checked against the `System::Remarked` event of its block by `info`.
+
+use super::{
+    display_dry_run_result_warning,
+    parse_code_hash,
+    submit_extrinsic,
+    Client,
+    DefaultConfig,
+    ErrorVariant,
+    ExtrinsicOpts,
+    TokenMetadata,
+};
+use crate::{
+    cmd::{
+        extrinsics::events::DisplayEvents,
+        info::fetch_code_owner,
+        registry::{
+            Attestation,
+            Registry,
+        },
+        runtime_api::api,
+        AsyncCommand,
+        CodeHash,
+    },
+    name_value_println,
+    DEFAULT_KEY_COL_WIDTH,
+};
+use anyhow::{
+    anyhow,
+    Result,
+};
+use async_trait::async_trait;
+use contract_metadata::ContractMetadata;
+use subxt::{
+    Config,
+    OnlineClient,
+};
+
+type Hash = <DefaultConfig as Config>::Hash;
+
+/// The prefix of the remarks attesting code.
+pub const ATTEST_REMARK_PREFIX: &str = "cargo-contract:attest";
+
+#[derive(Debug, clap::Args)]
+#[clap(
+    name = "attest",
+    about = "Attest the metadata and source of uploaded code on-chain"
+)]
+pub struct AttestCommand {
+    /// The hash of the code, defaults to the hash of the contract artifacts.
+    #[clap(long, value_parser = parse_code_hash)]
+    code_hash: Option<CodeHash>,
+    /// The content identifier of the source code the code was built from, e.g. the
+    /// IPFS CID of the source archive.
+    #[clap(long)]
+    source_cid: String,
+    /// The hash of the metadata, defaults to the hash of the metadata of the contract
+    /// artifacts.
+    #[clap(long, value_parser = parse_code_hash)]
+    metadata_hash: Option<Hash>,
+    #[clap(flatten)]
+    extrinsic_opts: ExtrinsicOpts,
+    /// Export the call output as JSON.
+    #[clap(long, conflicts_with = "verbose")]
+    output_json: bool,
+}
+
+impl AttestCommand {
+    pub fn is_json(&self) -> bool {
+        self.output_json
+    }
+}
+
+#[async_trait(?Send)]
+impl AsyncCommand for AttestCommand {
+    type Output = Result<(), ErrorVariant>;
+
+    async fn exec(&self) -> Result<(), ErrorVariant> {
+        if self.extrinsic_opts.phala_backend()?.is_some() {
+            return Err(
+                anyhow!("Attesting code is not supported by the `phala` backend").into(),
+            )
+        }
+        if self.source_cid.is_empty() || self.source_cid.contains(':') {
+            return Err(anyhow!("The source CID must not be empty or contain `:`").into())
+        }
+        self.extrinsic_opts.apply_process_opts()?;
+        let (code_hash, metadata_hash) = match (self.code_hash, self.metadata_hash) {
+            (Some(code_hash), Some(metadata_hash)) => (code_hash, metadata_hash),
+            (code_hash, metadata_hash) => {
+                let artifacts = self.extrinsic_opts.contract_artifacts()?;
+                let code_hash = match code_hash {
+                    Some(code_hash) => code_hash,
+                    None => CodeHash::from(artifacts.code_hash()?),
+                };
+                let metadata_hash = match metadata_hash {
+                    Some(metadata_hash) => metadata_hash,
+                    None => compute_metadata_hash(artifacts.metadata()?)?,
+                };
+                (code_hash, metadata_hash)
+            }
+        };
+        let signer = super::pair_signer(self.extrinsic_opts.signer("attest")?);
+        let network = self.extrinsic_opts.url_to_string()?;
+        let registry = Registry::load()?;
+
+        let client = OnlineClient::from_url(&network).await?;
+        let owner = match registry.code(&code_hash, &network) {
+            Some(code) => code.owner.clone(),
+            None => {
+                fetch_code_owner(&client, code_hash).await?.ok_or_else(|| {
+                    anyhow!("No code was found for the code hash {code_hash:?}")
+                })?
+            }
+        };
+        if &owner != signer.account_id() {
+            return Err(anyhow!(
+                "The code {code_hash:?} is owned by {owner}, only the owner can \
+                attest it"
+            )
+            .into())
+        }
+
+        let remark = attest_remark(&code_hash, &metadata_hash, &self.source_cid);
+        let print_details = || {
+            name_value_println!(
+                "Code hash",
+                format!("{code_hash:?}"),
+                DEFAULT_KEY_COL_WIDTH
+            );
+            name_value_println!(
+                "Metadata hash",
+                format!("{metadata_hash:?}"),
+                DEFAULT_KEY_COL_WIDTH
+            );
+            name_value_println!("Source CID", self.source_cid, DEFAULT_KEY_COL_WIDTH);
+            name_value_println!("Attester", format!("{owner}"), DEFAULT_KEY_COL_WIDTH);
+        };
+        if !self.extrinsic_opts.execute()? {
+            print_details();
+            display_dry_run_result_warning("attest");
+            return Ok(())
+        }
+        self.extrinsic_opts.confirm(print_details)?;
+
+        let token_metadata = TokenMetadata::query(&client).await?;
+        let submit_options = self.extrinsic_opts.submit_options(&token_metadata)?;
+        let call = api::tx().system().remark_with_event(remark.into_bytes());
+        let result = submit_extrinsic(&client, &call, &signer, &submit_options).await?;
+
+        Registry::update(|registry| {
+            registry.attest_code(
+                code_hash,
+                &network,
+                &owner,
+                Attestation {
+                    attester: owner.clone(),
+                    metadata_hash,
+                    source_cid: self.source_cid.clone(),
+                    block_hash: result.all_events_in_block().block_hash(),
+                },
+            )
+        })?;
+
+        let display_events = DisplayEvents::from_events(
+            &result,
+            None,
+            &client.metadata(),
+            &token_metadata,
+        )?;
+        let output = if self.output_json {
+            display_events.to_json()?
+        } else {
+            display_events.display_events(
+                self.extrinsic_opts.verbosity()?,
+                self.extrinsic_opts.number_format,
+                self.extrinsic_opts.max_output_bytes,
+            )?
+        };
+        println!("{output}");
+        Ok(())
+    }
+}
+
+/// Returns the hash the metadata is attested with: the BLAKE2-256 hash of its JSON
+/// without the Wasm code.
+pub fn compute_metadata_hash(mut metadata: ContractMetadata) -> Result<Hash> {
+    metadata.remove_source_wasm_attribute();
+    let json = serde_json::to_vec(&metadata)?;
+    Ok(Hash::from(sp_core::hashing::blake2_256(&json)))
+}
+
+/// The remark attesting the metadata and source of the code.
+fn attest_remark(code_hash: &CodeHash, metadata_hash: &Hash, source_cid: &str) -> String {
+    format!("{ATTEST_REMARK_PREFIX}:{code_hash:?}:{metadata_hash:?}:{source_cid}")
+}
+
+/// Check that the attestation of the code was recorded on-chain by the attester, with
+/// the `System::Remarked` event of its remark in its block.
+pub async fn verify_attestation(
+    client: &Client,
+    code_hash: &CodeHash,
+    attestation: &Attestation,
+) -> Result<bool> {
+    let remark = attest_remark(
+        code_hash,
+        &attestation.metadata_hash,
+        &attestation.source_cid,
+    );
+    let remark_hash = Hash::from(sp_core::hashing::blake2_256(remark.as_bytes()));
+    let events = client.events().at(attestation.block_hash).await?;
+    for remarked in events.find::<api::system::events::Remarked>() {
+        let remarked = remarked?;
+        if remarked.sender == attestation.attester && remarked.hash == remark_hash {
+            return Ok(true)
+        }
+    }
+    Ok(false)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn attest_remark_follows_convention() {
+        assert_eq!(
+            attest_remark(
+                &CodeHash::repeat_byte(0xab),
+                &Hash::repeat_byte(0xcd),
+                "bafybeigdyrzt5sfp7udm7hu76uh7y26nf3efuylqabf3oclgtqy55fbzdi"
+            ),
+            "cargo-contract:attest:\
+            0xabababababababababababababababababababababababababababababababab:\
+            0xcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcd:\
+            bafybeigdyrzt5sfp7udm7hu76uh7y26nf3efuylqabf3oclgtqy55fbzdi"
+        );
+    }
+}
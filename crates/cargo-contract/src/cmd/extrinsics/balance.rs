@@ -18,12 +18,17 @@ use std::{
     fmt::Display,
     result::Result::Ok,
     str::FromStr,
+    sync::atomic::{
+        AtomicBool,
+        Ordering,
+    },
 };
 
 use rust_decimal::{
     prelude::FromPrimitive,
     Decimal,
 };
+use serde::ser::SerializeStruct;
 use serde_json::json;
 
 use super::Client;
@@ -52,6 +57,11 @@ pub struct TokenMetadata {
     pub symbol: String,
 }
 
+/// A balance in units of the token of a chain, e.g. `1.5DOT` or `500mDOT`.
+///
+/// A parsed balance keeps its unit prefix as part of the symbol, e.g. `mDOT`, because
+/// the prefix can only be told apart from the symbol once the token of the chain is
+/// known, e.g. for a token called `MOVR`.
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct DenominatedBalance {
     value: Decimal,
@@ -59,6 +69,43 @@ pub struct DenominatedBalance {
     symbol: String,
 }
 
+/// The decimals of common tokens, e.g. 10 of DOT, 12 of KSM and 18 of EVM chains.
+const COMMON_DECIMALS: [usize; 5] = [6, 8, 10, 12, 18];
+
+/// Whether the balances in the output are denominated, see [`disable_denomination`].
+static DENOMINATE: AtomicBool = AtomicBool::new(true);
+
+/// Display the balances in the output of the rest of the process in planck, without
+/// denominating them by the token metadata of the chain.
+pub fn disable_denomination() {
+    DENOMINATE.store(false, Ordering::Relaxed);
+}
+
+/// A balance for display in the output of a command: both as the raw amount in the
+/// smallest unit of the chain (planck) and as the amount denominated by the chain's
+/// [`TokenMetadata`].
+///
+/// When serialized the planck amount is written as a string, so that big numbers can
+/// not lose precision in JSON parsers which read numbers as floating point values.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DisplayBalance {
+    planck: Balance,
+    denominated: BalanceVariant,
+}
+
+/// The notation used to display numbers, such as balances, in the command output.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, clap::ValueEnum)]
+pub enum NumberFormat {
+    /// Plain digits without any separators, e.g. `1000000.5`.
+    #[default]
+    Raw,
+    /// Digits grouped by thousands with the separators of the current locale (taken
+    /// from `LC_ALL`, `LC_NUMERIC` or `LANG`), e.g. `1,000,000.5`.
+    Grouped,
+    /// Scientific notation, e.g. `1.0000005e6`.
+    Scientific,
+}
+
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub enum UnitPrefix {
     Giga,
@@ -95,6 +142,138 @@ impl TokenMetadata {
     }
 }
 
+impl DisplayBalance {
+    /// Create a [`DisplayBalance`] from a raw balance, denominated by the given
+    /// [`TokenMetadata`].
+    pub fn new(planck: Balance, token_metadata: &TokenMetadata) -> Result<Self> {
+        let token_metadata = DENOMINATE.load(Ordering::Relaxed).then_some(token_metadata);
+        Ok(Self {
+            planck,
+            denominated: BalanceVariant::from(planck, token_metadata)?,
+        })
+    }
+
+    /// Display the denominated balance using the given [`NumberFormat`].
+    pub fn format(&self, number_format: NumberFormat) -> String {
+        self.denominated.format(number_format)
+    }
+
+    /// Display the balance both in planck and denominated, e.g. `1000000000000
+    /// (100UNIT)`, for confirming a transfer.
+    pub fn describe(&self) -> String {
+        match self.denominated {
+            BalanceVariant::Default(_) => self.planck.to_string(),
+            BalanceVariant::Denominated(ref denominated) => {
+                format!("{} ({denominated})", self.planck)
+            }
+        }
+    }
+}
+
+impl serde::Serialize for DisplayBalance {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        let mut balance = serializer.serialize_struct("DisplayBalance", 2)?;
+        balance.serialize_field("planck", &self.planck.to_string())?;
+        balance.serialize_field("denominated", &self.denominated.to_string())?;
+        balance.end()
+    }
+}
+
+impl NumberFormat {
+    /// Format a plain decimal number, as produced by the `Display` implementations of
+    /// integers and [`Decimal`], e.g. `-1234.56`.
+    pub fn format(&self, number: &str) -> String {
+        match self {
+            NumberFormat::Raw => number.to_string(),
+            NumberFormat::Grouped => {
+                let (group_separator, decimal_separator) = locale_separators();
+                group_digits(number, group_separator, decimal_separator)
+            }
+            NumberFormat::Scientific => scientific_notation(number),
+        }
+    }
+}
+
+/// Returns the digit group separator and the decimal separator of the locale configured
+/// for the current process.
+fn locale_separators() -> (char, char) {
+    let locale = ["LC_ALL", "LC_NUMERIC", "LANG"]
+        .iter()
+        .find_map(|var| std::env::var(var).ok().filter(|locale| !locale.is_empty()))
+        .unwrap_or_default();
+    separators_for_locale(&locale)
+}
+
+/// Returns the digit group separator and the decimal separator for a POSIX locale name,
+/// e.g. `de_DE.UTF-8`. Falls back to the English separators for unknown locales.
+fn separators_for_locale(locale: &str) -> (char, char) {
+    // strip the codeset and modifier, e.g. `.UTF-8@euro`
+    let locale = locale.split(['.', '@']).next().unwrap_or_default();
+    let (language, territory) = locale.split_once('_').unwrap_or((locale, ""));
+    match (language, territory) {
+        ("de" | "it" | "fr", "CH") => ('\'', '.'),
+        ("de" | "es" | "it" | "nl" | "pt" | "id" | "tr" | "da" | "el", _) => ('.', ','),
+        ("fr" | "ru" | "pl" | "cs" | "sk" | "sv" | "fi" | "nb" | "uk" | "hu", _) => {
+            (' ', ',')
+        }
+        _ => (',', '.'),
+    }
+}
+
+/// Groups the digits of the integer part of a plain decimal number by thousands.
+fn group_digits(number: &str, group_separator: char, decimal_separator: char) -> String {
+    let (sign, unsigned) = match number.strip_prefix('-') {
+        Some(unsigned) => ("-", unsigned),
+        None => ("", number),
+    };
+    let (integer, fraction) = match unsigned.split_once('.') {
+        Some((integer, fraction)) => (integer, Some(fraction)),
+        None => (unsigned, None),
+    };
+    let mut grouped = String::from(sign);
+    for (i, digit) in integer.chars().enumerate() {
+        if i > 0 && (integer.len() - i) % 3 == 0 {
+            grouped.push(group_separator);
+        }
+        grouped.push(digit);
+    }
+    if let Some(fraction) = fraction {
+        grouped.push(decimal_separator);
+        grouped.push_str(fraction);
+    }
+    grouped
+}
+
+/// Converts a plain decimal number into scientific notation, e.g. `1500` into `1.5e3`.
+fn scientific_notation(number: &str) -> String {
+    let (sign, unsigned) = match number.strip_prefix('-') {
+        Some(unsigned) => ("-", unsigned),
+        None => ("", number),
+    };
+    let (integer, fraction) = unsigned.split_once('.').unwrap_or((unsigned, ""));
+    let integer = integer.trim_start_matches('0');
+    let (digits, exponent) = if !integer.is_empty() {
+        (format!("{integer}{fraction}"), integer.len() as isize - 1)
+    } else {
+        let significant = fraction.trim_start_matches('0');
+        let leading_zeros = fraction.len() - significant.len();
+        (significant.to_string(), -(leading_zeros as isize) - 1)
+    };
+    let digits = digits.trim_end_matches('0');
+    if digits.is_empty() {
+        return String::from("0")
+    }
+    let (first, rest) = digits.split_at(1);
+    if rest.is_empty() {
+        format!("{sign}{first}e{exponent}")
+    } else {
+        format!("{sign}{first}.{rest}e{exponent}")
+    }
+}
+
 impl FromStr for BalanceVariant {
     type Err = anyhow::Error;
 
@@ -118,42 +297,63 @@ impl FromStr for DenominatedBalance {
     type Err = anyhow::Error;
 
     fn from_str(value: &str) -> Result<Self, Self::Err> {
-        let symbols = value
+        let symbol = value
             .trim_start_matches(|ch: char| ch.is_numeric() || ch == '.' || ch == ',');
-        let unit_char = symbols
-            .chars()
-            .next()
-            .context("no units or symbols present")?;
-        let unit: UnitPrefix = match unit_char {
-            'G' => UnitPrefix::Giga,
-            'M' => UnitPrefix::Mega,
-            'k' => UnitPrefix::Kilo,
-            'm' => UnitPrefix::Milli,
-            '\u{3bc}' => UnitPrefix::Micro,
-            'n' => UnitPrefix::Nano,
-            _ => UnitPrefix::One,
-        };
-        let symbol = if unit != UnitPrefix::One {
-            let (start, _) = symbols
-                .char_indices()
-                .nth(1)
-                .context("cannot find the first char's index")?;
-            symbols[start..].to_string()
-        } else {
-            String::new()
-        };
+        if symbol.is_empty() {
+            return Err(anyhow!("no units or symbols present"))
+        }
         let value = value.trim_end_matches(|ch: char| ch.is_alphabetic());
         let value = Decimal::from_str_exact(value)
             .context("Error while parsing the value. Please denominate and normalize the balance first.")?
             .normalize();
         Ok(Self {
             value,
-            unit,
-            symbol,
+            unit: UnitPrefix::One,
+            symbol: symbol.to_string(),
+        })
+    }
+}
+
+impl DenominatedBalance {
+    /// The unit prefix of the balance in units of the token of the chain. Fails if the
+    /// symbol of the balance is not the symbol of the token, optionally with a prefix.
+    fn unit(&self, token_metadata: &TokenMetadata) -> Result<UnitPrefix> {
+        let token = &token_metadata.symbol;
+        let unit = if self.symbol.eq_ignore_ascii_case(token) {
+            Some(self.unit.clone())
+        } else if self.unit == UnitPrefix::One {
+            let mut chars = self.symbol.chars();
+            chars
+                .next()
+                .and_then(UnitPrefix::from_char)
+                .filter(|_| chars.as_str().eq_ignore_ascii_case(token))
+        } else {
+            None
+        };
+        unit.ok_or_else(|| {
+            anyhow!(
+                "`{}` is not a unit of the token of the chain, e.g. `1.5{token}` or \
+                `500m{token}`",
+                self.symbol
+            )
         })
     }
 }
 
+impl UnitPrefix {
+    fn from_char(prefix: char) -> Option<Self> {
+        match prefix {
+            'G' => Some(UnitPrefix::Giga),
+            'M' => Some(UnitPrefix::Mega),
+            'k' => Some(UnitPrefix::Kilo),
+            'm' => Some(UnitPrefix::Milli),
+            '\u{3bc}' => Some(UnitPrefix::Micro),
+            'n' => Some(UnitPrefix::Nano),
+            _ => None,
+        }
+    }
+}
+
 impl BalanceVariant {
     /// Converts BalanceVariant into Balance.
     ///
@@ -202,7 +402,7 @@ impl BalanceVariant {
             BalanceVariant::Default(balance) => Ok(*balance),
             BalanceVariant::Denominated(den_balance) => {
                 let zeros: usize = (token_metadata.token_decimals as isize
-                    + match den_balance.unit {
+                    + match den_balance.unit(token_metadata)? {
                         UnitPrefix::Giga => 9,
                         UnitPrefix::Mega => 6,
                         UnitPrefix::Kilo => 3,
@@ -355,17 +555,56 @@ impl BalanceVariant {
     }
 }
 
-impl Display for BalanceVariant {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+impl BalanceVariant {
+    /// Display the balance with its numeric part written in the given [`NumberFormat`].
+    pub fn format(&self, number_format: NumberFormat) -> String {
         match self {
-            BalanceVariant::Default(balance) => f.write_str(&balance.to_string()),
-            BalanceVariant::Denominated(input) => f.write_str(&input.to_string()),
+            BalanceVariant::Default(balance) => {
+                number_format.format(&balance.to_string())
+            }
+            BalanceVariant::Denominated(input) => input.format(number_format),
         }
     }
 }
 
-impl Display for DenominatedBalance {
+impl BalanceVariant {
+    /// Returns a warning if the balance is given in planck as a round number of tokens
+    /// of common decimals other than the decimals of the chain, e.g. `1000000000000`,
+    /// which is 1 token of 12 decimals but 100 tokens on a chain with 10 decimals.
+    pub fn decimals_mismatch(&self, token_metadata: &TokenMetadata) -> Option<String> {
+        let BalanceVariant::Default(planck) = *self else {
+            return None
+        };
+        let digits = planck.to_string();
+        let zeros = digits.len() - digits.trim_end_matches('0').len();
+        let decimals = COMMON_DECIMALS
+            .into_iter()
+            .filter(|decimals| *decimals <= zeros)
+            .last()?;
+        if planck == 0 || decimals == token_metadata.token_decimals {
+            return None
+        }
+        let tokens = planck / 10u128.pow(decimals as u32);
+        let denominated = BalanceVariant::from(planck, Some(token_metadata)).ok()?;
+        Some(format!(
+            "the value {planck} is {denominated} on this chain with {} decimals, \
+            not {tokens}{} as with {decimals} decimals. Pass the value with its unit, \
+            e.g. `{denominated}`, to be sure of the amount",
+            token_metadata.token_decimals, token_metadata.symbol
+        ))
+    }
+}
+
+impl Display for BalanceVariant {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.format(NumberFormat::Raw))
+    }
+}
+
+impl DenominatedBalance {
+    /// Display the denominated balance with its value written in the given
+    /// [`NumberFormat`].
+    fn format(&self, number_format: NumberFormat) -> String {
         let prefix = match self.unit {
             UnitPrefix::Giga => "G",
             UnitPrefix::Mega => "M",
@@ -375,7 +614,18 @@ impl Display for DenominatedBalance {
             UnitPrefix::Micro => "μ",
             UnitPrefix::Nano => "n",
         };
-        f.write_fmt(format_args!("{}{}{}", self.value, prefix, self.symbol))
+        format!(
+            "{}{}{}",
+            number_format.format(&self.value.to_string()),
+            prefix,
+            self.symbol
+        )
+    }
+}
+
+impl Display for DenominatedBalance {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.format(NumberFormat::Raw))
     }
 }
 
@@ -383,6 +633,35 @@ impl Display for DenominatedBalance {
 mod tests {
     use super::*;
 
+    #[test]
+    fn raw_values_of_other_decimals_are_detected() {
+        let tm = TokenMetadata {
+            token_decimals: 10,
+            symbol: String::from("DOT"),
+        };
+        let warning = BalanceVariant::Default(1_000_000_000_000)
+            .decimals_mismatch(&tm)
+            .expect("1 token of 12 decimals");
+        assert!(warning.contains("is 100DOT"), "{warning}");
+        assert!(
+            warning.contains("not 1DOT as with 12 decimals"),
+            "{warning}"
+        );
+        assert!(BalanceVariant::Default(50_000_000_000)
+            .decimals_mismatch(&tm)
+            .is_none());
+        assert!(BalanceVariant::Default(12_345)
+            .decimals_mismatch(&tm)
+            .is_none());
+        assert!(BalanceVariant::Default(0).decimals_mismatch(&tm).is_none());
+        assert!(BalanceVariant::from_str("100DOT")
+            .unwrap()
+            .decimals_mismatch(&tm)
+            .is_none());
+        let display = DisplayBalance::new(1_000_000_000_000, &tm).unwrap();
+        assert_eq!(display.describe(), "1000000000000 (100DOT)");
+    }
+
     #[test]
     fn correct_balances_parses_success() {
         assert!(
@@ -678,4 +957,79 @@ mod tests {
         });
         assert_eq!(sample, denominated_balance);
     }
+
+    #[test]
+    fn group_digits_by_thousands() {
+        assert_eq!(group_digits("0", ',', '.'), "0");
+        assert_eq!(group_digits("999", ',', '.'), "999");
+        assert_eq!(group_digits("1000", ',', '.'), "1,000");
+        assert_eq!(group_digits("1234567.891", ',', '.'), "1,234,567.891");
+        assert_eq!(group_digits("-123456", ',', '.'), "-123,456");
+        assert_eq!(group_digits("1234567.5", '.', ','), "1.234.567,5");
+    }
+
+    #[test]
+    fn locale_separators_are_detected() {
+        assert_eq!(separators_for_locale("en_US.UTF-8"), (',', '.'));
+        assert_eq!(separators_for_locale("de_DE.UTF-8@euro"), ('.', ','));
+        assert_eq!(separators_for_locale("de_CH"), ('\'', '.'));
+        assert_eq!(separators_for_locale("fr_FR"), (' ', ','));
+        assert_eq!(separators_for_locale("C"), (',', '.'));
+        assert_eq!(separators_for_locale(""), (',', '.'));
+    }
+
+    #[test]
+    fn format_scientific_notation() {
+        assert_eq!(scientific_notation("0"), "0");
+        assert_eq!(scientific_notation("7"), "7e0");
+        assert_eq!(scientific_notation("1000000"), "1e6");
+        assert_eq!(scientific_notation("1500000"), "1.5e6");
+        assert_eq!(scientific_notation("500.5"), "5.005e2");
+        assert_eq!(scientific_notation("0.0015"), "1.5e-3");
+        assert_eq!(scientific_notation("-1200"), "-1.2e3");
+    }
+
+    #[test]
+    fn display_balance_serializes_planck_and_denominated() {
+        let tm = TokenMetadata {
+            token_decimals: 10,
+            symbol: String::from("DOT"),
+        };
+        let balance: Balance = 5_005_000_000_000_000_000_000;
+        let display_balance =
+            DisplayBalance::new(balance, &tm).expect("successful conversion");
+        let json = serde_json::to_value(&display_balance).expect("serialization works");
+        assert_eq!(
+            json,
+            json!({
+                "planck": "5005000000000000000000",
+                "denominated": "500.50GDOT"
+            })
+        );
+        assert_eq!(
+            display_balance.format(NumberFormat::Scientific),
+            "5.005e2GDOT"
+        );
+    }
+
+    #[test]
+    fn units_are_told_apart_from_the_symbol_of_the_chain() {
+        let tm = TokenMetadata {
+            token_decimals: 18,
+            symbol: String::from("MOVR"),
+        };
+        let denominate = |input: &str| {
+            BalanceVariant::from_str(input)
+                .expect("successful parsing. qed")
+                .denominate_balance(&tm)
+        };
+        assert_eq!(denominate("1.5MOVR").unwrap(), 1_500_000_000_000_000_000);
+        assert_eq!(denominate("2kMOVR").unwrap(), 2_000 * 10u128.pow(18));
+        assert_eq!(denominate("500mmovr").unwrap(), 500_000_000_000_000_000);
+        assert_eq!(
+            denominate("1DOT").unwrap_err().to_string(),
+            "`DOT` is not a unit of the token of the chain, e.g. `1.5MOVR` or `500mMOVR`"
+        );
+        assert!(denominate("1xMOVR").is_err());
+    }
 }
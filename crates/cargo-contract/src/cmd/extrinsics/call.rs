@@ -15,34 +15,53 @@
 // along with cargo-contract.  If not, see <http://www.gnu.org/licenses/>.
 
 use super::{
+    best_block_hash,
     display_contract_exec_result,
-    prompt_confirm_tx,
+    ensure_mapped,
+    host_functions,
+    offline::submit_or_print_unsigned,
+    pair_signer,
+    read_cache::ReadCache,
+    relayer::RelayerOpts,
+    senders::Senders,
     state_call,
-    submit_extrinsic,
+    state_call_at,
+    sudo::SudoOpts,
     BalanceVariant,
     Client,
     ContractMessageTranscoder,
     DefaultConfig,
+    DisplayBalance,
     ExtrinsicOpts,
-    PairSigner,
+    ExtrinsicSigner,
     StorageDeposit,
+    SubmitOptions,
     TokenMetadata,
     MAX_KEY_COL_WIDTH,
 };
 
 use crate::{
     cmd::{
+        chain_env,
+        deprecation::Deprecations,
         extrinsics::{
             display_contract_exec_result_debug,
             display_dry_run_result_warning,
             events::DisplayEvents,
             ErrorVariant,
         },
+        identity::Identities,
+        output,
         runtime_api::api,
+        schema::OutputSchema,
+        session,
+        AsyncCommand,
         Balance,
     },
     DEFAULT_KEY_COL_WIDTH,
 };
+use async_trait::async_trait;
+use colored::Colorize as _;
 use contract_build::name_value_println;
 
 use anyhow::{
@@ -53,11 +72,21 @@ use anyhow::{
 
 use contract_transcode::Value;
 use pallet_contracts_primitives::ContractExecResult;
-use scale::Encode;
+use scale::{
+    Decode,
+    Encode,
+};
 use sp_weights::Weight;
 
-use std::fmt::Debug;
+use std::{
+    fmt::Debug,
+    path::{
+        Path,
+        PathBuf,
+    },
+};
 use subxt::{
+    tx::TxPayload,
     Config,
     OnlineClient,
 };
@@ -92,6 +121,52 @@ pub struct CallCommand {
     /// Export the call output in JSON format.
     #[clap(long, conflicts_with = "verbose")]
     output_json: bool,
+    /// Print the JSON Schema of the `--output-json` output and exit.
+    #[clap(long)]
+    schema: bool,
+    /// When the dry-run of the message is rejected with an access control error, also
+    /// dry-run the `--admin-getter` message to report who would be allowed to call it.
+    #[clap(long, conflicts_with = "execute")]
+    check_access: bool,
+    /// The message without arguments which returns the account allowed to call
+    /// access-controlled messages, used by `--check-access`.
+    #[clap(long, default_value = "owner")]
+    admin_getter: String,
+    /// Dry-run the message as the given account instead of the signer, e.g. to check
+    /// the access rights of another account with `--check-access`.
+    #[clap(long, value_name = "ACCOUNT", conflicts_with = "execute")]
+    origin: Option<<DefaultConfig as Config>::AccountId>,
+    /// Cache the results of dry-runs in the given directory, so that identical dry-runs
+    /// at the same block are answered without executing them again.
+    #[clap(long, value_name = "DIR", env = "CARGO_CONTRACT_READ_CACHE")]
+    read_cache: Option<PathBuf>,
+    /// Write the decoded return value of the dry-run into the file instead of the
+    /// terminal, or the whole output with `--output-json`.
+    #[clap(long, value_name = "FILE", conflicts_with = "execute")]
+    out: Option<PathBuf>,
+    /// Check that the node provides the host functions imported by the code of the
+    /// contract, and warn about the missing ones.
+    #[clap(long)]
+    check_host_functions: bool,
+    #[clap(flatten)]
+    relayer: RelayerOpts,
+    #[clap(flatten)]
+    sudo: SudoOpts,
+    /// Submit the call the given number of times, the signers of `--suri` and
+    /// `--signers` taking turns. The nonces of the signers are tracked locally, so that
+    /// the transactions are broadcast without waiting for each other to be included.
+    #[clap(
+        long,
+        value_name = "TIMES",
+        default_value_t = 1,
+        value_parser = clap::value_parser!(u32).range(1..),
+        requires = "execute"
+    )]
+    repeat: u32,
+    /// Secret key URIs of further funded accounts submitting the `--repeat`ed call in
+    /// turns with `--suri`, separated by commas.
+    #[clap(long, value_name = "SURIS", value_delimiter = ',', requires = "repeat")]
+    signers: Vec<String>,
 }
 
 impl CallCommand {
@@ -99,153 +174,335 @@ impl CallCommand {
         self.output_json
     }
 
-    pub fn run(&self) -> Result<(), ErrorVariant> {
-        let artifacts = self.extrinsic_opts.contract_artifacts()?;
-        let transcoder = artifacts.contract_transcoder()?;
-
-        let call_data = transcoder.encode(&self.message, &self.args)?;
-        tracing::debug!("Message data: {:?}", hex::encode(&call_data));
-
-        let signer = super::pair_signer(self.extrinsic_opts.signer()?);
-
-        async_std::task::block_on(async {
-            let url = self.extrinsic_opts.url_to_string();
-            let client = OnlineClient::from_url(url.clone()).await?;
-
-            if !self.extrinsic_opts.execute {
-                let result = self
-                    .call_dry_run(call_data.clone(), &client, &signer)
-                    .await?;
-                match result.result {
-                    Ok(ref ret_val) => {
-                        let value = transcoder
-                            .decode_return(&self.message, &mut &ret_val.data[..])
-                            .context(format!(
-                                "Failed to decode return value {:?}",
-                                &ret_val
-                            ))?;
-                        let dry_run_result = CallDryRunResult {
-                            result: String::from("Success!"),
-                            reverted: ret_val.did_revert(),
-                            data: value,
-                            gas_consumed: result.gas_consumed,
-                            gas_required: result.gas_required,
-                            storage_deposit: StorageDeposit::from(
-                                &result.storage_deposit,
-                            ),
-                        };
-                        if self.output_json {
-                            println!("{}", dry_run_result.to_json()?);
-                        } else {
-                            dry_run_result.print();
-                            display_contract_exec_result_debug::<_, DEFAULT_KEY_COL_WIDTH>(
-                                &result,
-                            )?;
-                            display_dry_run_result_warning("message");
-                        };
-                    }
-                    Err(ref err) => {
-                        let metadata = client.metadata();
-                        let object = ErrorVariant::from_dispatch_error(err, &metadata)?;
-                        if self.output_json {
-                            return Err(object)
-                        } else {
-                            name_value_println!("Result", object, MAX_KEY_COL_WIDTH);
-                            display_contract_exec_result::<_, MAX_KEY_COL_WIDTH>(
-                                &result,
-                            )?;
-                        }
-                    }
-                }
-            } else {
-                self.call(&client, call_data, &signer, &transcoder).await?;
-            }
-            Ok(())
-        })
+    async fn call_dry_run(
+        &self,
+        input_data: Vec<u8>,
+        signer: &ExtrinsicSigner,
+        token_metadata: &TokenMetadata,
+    ) -> Result<ContractExecResult<Balance>> {
+        let value = self.value.denominate_balance(token_metadata)?;
+        self.dry_run_message(input_data, value, signer, token_metadata)
+            .await
     }
 
-    async fn call_dry_run(
+    /// Dry-run a message of the target contract with the given encoded input and value.
+    async fn dry_run_message(
         &self,
         input_data: Vec<u8>,
-        client: &Client,
-        signer: &PairSigner,
+        value: Balance,
+        signer: &ExtrinsicSigner,
+        token_metadata: &TokenMetadata,
     ) -> Result<ContractExecResult<Balance>> {
-        let url = self.extrinsic_opts.url_to_string();
-        let token_metadata = TokenMetadata::query(client).await?;
+        let url = self.extrinsic_opts.url_to_string()?;
         let storage_deposit_limit = self
             .extrinsic_opts
             .storage_deposit_limit
             .as_ref()
-            .map(|bv| bv.denominate_balance(&token_metadata))
+            .map(|bv| bv.denominate_balance(token_metadata))
             .transpose()?;
         let call_request = CallRequest {
-            origin: signer.account_id().clone(),
+            origin: self.dry_run_origin(signer),
             dest: self.contract.clone(),
-            value: self.value.denominate_balance(&token_metadata)?,
+            value,
             gas_limit: None,
             storage_deposit_limit,
             input_data,
         };
-        state_call(&url, "ContractsApi_call", call_request).await
+        let Some(ref read_cache) = self.read_cache else {
+            return state_call(&url, "ContractsApi_call", call_request).await
+        };
+        let cache = ReadCache::new(read_cache, &url);
+        let block_hash = best_block_hash(&url).await?;
+        let request = call_request.encode();
+        let result = match cache.get(&block_hash, &request) {
+            Some(result) => {
+                tracing::debug!("Dry-run result found in the read cache");
+                result
+            }
+            None => {
+                let result =
+                    state_call_at(&url, "ContractsApi_call", &request, Some(block_hash))
+                        .await?;
+                cache.insert(&block_hash, &request, &result)?;
+                result
+            }
+        };
+        Ok(ContractExecResult::decode(&mut &result[..])?)
+    }
+
+    /// The account the message is dry-run as, `--origin` or else the signer.
+    fn dry_run_origin(
+        &self,
+        signer: &ExtrinsicSigner,
+    ) -> <DefaultConfig as Config>::AccountId {
+        self.origin
+            .clone()
+            .unwrap_or_else(|| signer.account_id().clone())
+    }
+
+    /// Check whether the dry-run of the message was denied because the origin lacks the
+    /// required access rights. If so, dry-run the `--admin-getter` message to find out
+    /// who is allowed to call the message instead.
+    async fn check_access(
+        &self,
+        transcoder: &ContractMessageTranscoder,
+        signer: &ExtrinsicSigner,
+        token_metadata: &TokenMetadata,
+        reverted_value: Option<&Value>,
+    ) -> Result<AccessCheck> {
+        let mut access_check = AccessCheck {
+            origin: self.dry_run_origin(signer).to_string(),
+            denied: reverted_value.map_or(false, is_access_error),
+            admin_getter: None,
+            allowed: None,
+            error: None,
+        };
+        if !access_check.denied {
+            return Ok(access_check)
+        }
+        access_check.admin_getter = Some(self.admin_getter.clone());
+
+        let getter_data =
+            match transcoder.encode(&self.admin_getter, Vec::<String>::new()) {
+                Ok(data) => data,
+                Err(err) => {
+                    access_check.error = Some(err.to_string());
+                    return Ok(access_check)
+                }
+            };
+        let result = self
+            .dry_run_message(getter_data, 0, signer, token_metadata)
+            .await?;
+        match result.result {
+            Ok(ref ret_val) if !ret_val.did_revert() => {
+                match transcoder.decode_return(&self.admin_getter, &mut &ret_val.data[..])
+                {
+                    Ok(allowed) => access_check.allowed = Some(allowed),
+                    Err(err) => access_check.error = Some(err.to_string()),
+                }
+            }
+            Ok(_) => {
+                access_check.error =
+                    Some(format!("The `{}` message reverted", self.admin_getter))
+            }
+            Err(_) => {
+                access_check.error =
+                    Some(format!("The `{}` message failed", self.admin_getter))
+            }
+        }
+        Ok(access_check)
     }
 
     async fn call(
         &self,
         client: &Client,
         data: Vec<u8>,
-        signer: &PairSigner,
+        signer: &ExtrinsicSigner,
         transcoder: &ContractMessageTranscoder,
+        token_metadata: &TokenMetadata,
     ) -> Result<(), ErrorVariant> {
         tracing::debug!("calling contract {:?}", self.contract);
 
         let gas_limit = self
-            .pre_submit_dry_run_gas_estimate(client, data.clone(), signer)
+            .pre_submit_dry_run_gas_estimate(client, data.clone(), signer, token_metadata)
             .await?;
 
-        if !self.extrinsic_opts.skip_confirm {
-            prompt_confirm_tx(|| {
-                name_value_println!("Message", self.message, DEFAULT_KEY_COL_WIDTH);
-                name_value_println!("Args", self.args.join(" "), DEFAULT_KEY_COL_WIDTH);
+        let value = DisplayBalance::new(
+            self.value.denominate_balance(token_metadata)?,
+            token_metadata,
+        )?;
+        self.extrinsic_opts.confirm(|| {
+            name_value_println!("Message", self.message, DEFAULT_KEY_COL_WIDTH);
+            name_value_println!("Args", self.args.join(" "), DEFAULT_KEY_COL_WIDTH);
+            name_value_println!("Value", value.describe(), DEFAULT_KEY_COL_WIDTH);
+            name_value_println!(
+                "Gas limit",
+                gas_limit.to_string(),
+                DEFAULT_KEY_COL_WIDTH
+            );
+            if self.repeat > 1 {
                 name_value_println!(
-                    "Gas limit",
-                    gas_limit.to_string(),
+                    "Submissions",
+                    format!("{} by {} signers", self.repeat, self.signers.len() + 1),
                     DEFAULT_KEY_COL_WIDTH
                 );
-            })?;
-        }
+            }
+            self.relayer.print_details();
+            self.sudo.print_details();
+        })?;
 
-        let token_metadata = TokenMetadata::query(client).await?;
+        if self.relayer.enabled() {
+            let payload = self
+                .relayer
+                .payload(
+                    client,
+                    self.contract.clone(),
+                    self.value.denominate_balance(token_metadata)?,
+                    gas_limit,
+                    self.extrinsic_opts
+                        .storage_deposit_limit(token_metadata)?
+                        .map(|limit| limit.0),
+                    data,
+                )
+                .await?;
+            let response = self.relayer.relay(&payload, signer.pair()?).await?;
+            if self.output_json {
+                let json = serde_json::to_string_pretty(&response)
+                    .map_err(anyhow::Error::from)?;
+                println!("{json}");
+            } else {
+                name_value_println!(
+                    "Relayed",
+                    response.to_string(),
+                    DEFAULT_KEY_COL_WIDTH
+                );
+            }
+            return Ok(())
+        }
 
-        let call = api::tx().contracts().call(
+        let call = self.sudo.wrap(api::tx().contracts().call(
             self.contract.clone().into(),
-            self.value.denominate_balance(&token_metadata)?,
+            self.value.denominate_balance(token_metadata)?,
             gas_limit.into(),
-            self.extrinsic_opts.storage_deposit_limit(&token_metadata)?,
+            self.extrinsic_opts.storage_deposit_limit(token_metadata)?,
             data,
-        );
+        ));
 
-        let result = submit_extrinsic(client, &call, signer).await?;
+        let submit_options = self.extrinsic_opts.submit_options(token_metadata)?;
+        if self.repeat > 1 {
+            return self
+                .submit_repeated(
+                    client,
+                    &call,
+                    transcoder,
+                    token_metadata,
+                    &submit_options,
+                )
+                .await
+        }
+        let Some(result) =
+            submit_or_print_unsigned(client, &call, signer, &submit_options).await?
+        else {
+            return Ok(())
+        };
+        self.sudo.check_result(&result, &client.metadata())?;
 
-        let display_events =
-            DisplayEvents::from_events(&result, Some(transcoder), &client.metadata())?;
+        let display_events = DisplayEvents::from_events(
+            &result,
+            Some(transcoder),
+            &client.metadata(),
+            token_metadata,
+        )?;
+        session::record(&display_events);
 
         let output = if self.output_json {
             display_events.to_json()?
         } else {
-            display_events
-                .display_events(self.extrinsic_opts.verbosity()?, &token_metadata)?
+            display_events.display_events(
+                self.extrinsic_opts.verbosity()?,
+                self.extrinsic_opts.number_format,
+                self.extrinsic_opts.max_output_bytes,
+            )?
         };
         println!("{output}");
 
         Ok(())
     }
 
+    /// Submit the call `--repeat` times, the signers taking turns, and display the
+    /// events of every submission.
+    async fn submit_repeated<Call: TxPayload>(
+        &self,
+        client: &Client,
+        call: &Call,
+        transcoder: &ContractMessageTranscoder,
+        token_metadata: &TokenMetadata,
+        submit_options: &SubmitOptions,
+    ) -> Result<(), ErrorVariant> {
+        let mut signers = vec![pair_signer(self.extrinsic_opts.signer("call")?)];
+        for suri in &self.signers {
+            signers.push(pair_signer(
+                self.extrinsic_opts.additional_signer("call", suri)?,
+            ));
+        }
+        let mut senders = Senders::fetch(client, signers).await?;
+        let submissions = senders
+            .submit(client, call, self.repeat, submit_options)
+            .await?;
+
+        let mut failed = 0;
+        let mut json = Vec::new();
+        for (number, submission) in (1..).zip(&submissions) {
+            let heading = format!(
+                "#{number} by {} (nonce {})",
+                submission.signer, submission.nonce
+            );
+            match submission.result {
+                Ok(ref events) => {
+                    let display_events = DisplayEvents::from_events(
+                        events,
+                        Some(transcoder),
+                        &client.metadata(),
+                        token_metadata,
+                    )?;
+                    if self.output_json {
+                        json.push(serde_json::json!({
+                            "signer": submission.signer.to_string(),
+                            "nonce": submission.nonce,
+                            "events": display_events,
+                        }));
+                    } else {
+                        name_value_println!("Submission", heading, DEFAULT_KEY_COL_WIDTH);
+                        println!(
+                            "{}",
+                            display_events.display_events(
+                                self.extrinsic_opts.verbosity()?,
+                                self.extrinsic_opts.number_format,
+                                self.extrinsic_opts.max_output_bytes,
+                            )?
+                        );
+                    }
+                }
+                Err(ref err) => {
+                    failed += 1;
+                    if self.output_json {
+                        json.push(serde_json::json!({
+                            "signer": submission.signer.to_string(),
+                            "nonce": submission.nonce,
+                            "error": err.to_string(),
+                        }));
+                    } else {
+                        name_value_println!(
+                            "Submission",
+                            format!("{heading} failed: {err}"),
+                            DEFAULT_KEY_COL_WIDTH
+                        );
+                    }
+                }
+            }
+        }
+        if self.output_json {
+            let json =
+                serde_json::to_string_pretty(&json).map_err(anyhow::Error::from)?;
+            println!("{json}");
+        }
+        if failed > 0 {
+            return Err(anyhow!(
+                "{failed} of the {} submissions were not included",
+                submissions.len()
+            )
+            .into())
+        }
+        Ok(())
+    }
+
     /// Dry run the call before tx submission. Returns the gas required estimate.
     async fn pre_submit_dry_run_gas_estimate(
         &self,
         client: &Client,
         data: Vec<u8>,
-        signer: &PairSigner,
+        signer: &ExtrinsicSigner,
+        token_metadata: &TokenMetadata,
     ) -> Result<Weight> {
         if self.extrinsic_opts.skip_dry_run {
             return match (self.gas_limit, self.proof_size) {
@@ -260,7 +517,7 @@ impl CallCommand {
         if !self.output_json {
             super::print_dry_running_status(&self.message);
         }
-        let call_result = self.call_dry_run(data, client, signer).await?;
+        let call_result = self.call_dry_run(data, signer, token_metadata).await?;
         match call_result.result {
             Ok(_) => {
                 if !self.output_json {
@@ -281,7 +538,11 @@ impl CallCommand {
                     Err(anyhow!("{}", serde_json::to_string_pretty(&object)?))
                 } else {
                     name_value_println!("Result", object, MAX_KEY_COL_WIDTH);
-                    display_contract_exec_result::<_, MAX_KEY_COL_WIDTH>(&call_result)?;
+                    display_contract_exec_result::<_, MAX_KEY_COL_WIDTH>(
+                        &call_result,
+                        token_metadata,
+                        self.extrinsic_opts.number_format,
+                    )?;
                     Err(anyhow!("Pre-submission dry-run failed. Use --skip-dry-run to skip this step."))
                 }
             }
@@ -289,17 +550,271 @@ impl CallCommand {
     }
 }
 
+#[async_trait(?Send)]
+impl AsyncCommand for CallCommand {
+    type Output = Result<(), ErrorVariant>;
+
+    async fn exec(&self) -> Result<(), ErrorVariant> {
+        if self.schema {
+            return Ok(OutputSchema::Call.print()?)
+        }
+        if self.extrinsic_opts.phala_backend()?.is_some() {
+            return Err(anyhow!(
+                "Calling Phat contracts requires encrypted queries to the workers of the \
+                cluster, which is not supported by the `phala` backend"
+            )
+            .into())
+        }
+        if self.repeat > 1 && self.relayer.enabled() {
+            return Err(anyhow!(
+                "`--repeat` can not be combined with submitting the call via a relayer"
+            )
+            .into())
+        }
+        if self.sudo.enabled() && (self.repeat > 1 || self.relayer.enabled()) {
+            return Err(anyhow!(
+                "`--sudo` can not be combined with `--repeat` or a relayer"
+            )
+            .into())
+        }
+        self.extrinsic_opts.apply_process_opts()?;
+        let artifacts = self.extrinsic_opts.contract_artifacts()?;
+        let identities = Identities::default();
+        let transcoder = artifacts
+            .contract_transcoder_with_identities(&identities)?
+            .with_bytes_format(self.extrinsic_opts.bytes_format());
+
+        Deprecations::from_metadata(&artifacts.metadata()?).warn(&self.message);
+
+        let call_data = transcoder.encode(&self.message, &self.args)?;
+        tracing::debug!("Message data: {:?}", hex::encode(&call_data));
+
+        let signer = self.extrinsic_opts.extrinsic_signer("call")?;
+
+        let url = self.extrinsic_opts.url_to_string()?;
+        let client = OnlineClient::from_url(url.clone()).await?;
+        identities.resolve(&client).await?;
+        chain_env::ensure_supported_chain(&client)?;
+        ensure_mapped(&client, signer.account_id()).await?;
+        self.extrinsic_opts.preflight(&client).await?;
+        let token_metadata = TokenMetadata::query(&client).await?;
+        if let Some(warning) = self.value.decimals_mismatch(&token_metadata) {
+            eprintln!("{} {warning}", "warning:".yellow().bold());
+        }
+        if self.check_host_functions {
+            host_functions::check_host_functions(
+                &client,
+                &url,
+                signer.account_id(),
+                &self.contract,
+                artifacts.code.as_ref(),
+            )
+            .await?;
+        }
+
+        if !self.extrinsic_opts.execute()? {
+            let result = self
+                .call_dry_run(call_data.clone(), &signer, &token_metadata)
+                .await?;
+            match result.result {
+                Ok(ref ret_val) => {
+                    let value = transcoder
+                        .decode_return(&self.message, &mut &ret_val.data[..])
+                        .context(format!(
+                            "Failed to decode return value {:?}",
+                            &ret_val
+                        ))?;
+                    let access_check = if self.check_access {
+                        Some(
+                            self.check_access(
+                                &transcoder,
+                                &signer,
+                                &token_metadata,
+                                ret_val.did_revert().then_some(&value),
+                            )
+                            .await?,
+                        )
+                    } else {
+                        None
+                    };
+                    let dry_run_result = CallDryRunResult {
+                        result: String::from("Success!"),
+                        reverted: ret_val.did_revert(),
+                        data: value,
+                        access_check,
+                        gas_consumed: result.gas_consumed,
+                        gas_required: result.gas_required,
+                        storage_deposit: StorageDeposit::new(
+                            &result.storage_deposit,
+                            &token_metadata,
+                        )?,
+                    };
+                    session::record(&dry_run_result);
+                    if self.output_json {
+                        let json = dry_run_result.to_json()?;
+                        match self.out {
+                            Some(ref path) => {
+                                output::write_to_file(path, &json)?;
+                            }
+                            None => println!("{json}"),
+                        }
+                    } else {
+                        dry_run_result.print(
+                            self.out.as_deref(),
+                            self.extrinsic_opts.max_output_bytes,
+                        )?;
+                        display_contract_exec_result_debug::<_, DEFAULT_KEY_COL_WIDTH>(
+                            &result,
+                        )?;
+                        display_dry_run_result_warning("message");
+                    };
+                }
+                Err(ref err) => {
+                    let metadata = client.metadata();
+                    let object = ErrorVariant::from_dispatch_error(err, &metadata)?;
+                    if self.output_json {
+                        return Err(object)
+                    } else {
+                        name_value_println!("Result", object, MAX_KEY_COL_WIDTH);
+                        display_contract_exec_result::<_, MAX_KEY_COL_WIDTH>(
+                            &result,
+                            &token_metadata,
+                            self.extrinsic_opts.number_format,
+                        )?;
+                    }
+                }
+            }
+        } else {
+            self.extrinsic_opts.check_permission(&self.message)?;
+            if self.sudo.enabled() {
+                self.sudo
+                    .ensure_allowed(&client, signer.account_id())
+                    .await?;
+            }
+            self.call(&client, call_data, &signer, &transcoder, &token_metadata)
+                .await?;
+        }
+        Ok(())
+    }
+}
+
 /// A struct that encodes RPC parameters required for a call to a smart contract.
 ///
 /// Copied from `pallet-contracts-rpc-runtime-api`.
 #[derive(Encode)]
 pub struct CallRequest {
-    origin: <DefaultConfig as Config>::AccountId,
-    dest: <DefaultConfig as Config>::AccountId,
-    value: Balance,
-    gas_limit: Option<Weight>,
-    storage_deposit_limit: Option<Balance>,
-    input_data: Vec<u8>,
+    pub(super) origin: <DefaultConfig as Config>::AccountId,
+    pub(super) dest: <DefaultConfig as Config>::AccountId,
+    pub(super) value: Balance,
+    pub(super) gas_limit: Option<Weight>,
+    pub(super) storage_deposit_limit: Option<Balance>,
+    pub(super) input_data: Vec<u8>,
+}
+
+/// Names of error variants which signal that the caller lacks the access rights for a
+/// message, compared with the lowercased alphanumeric characters of a variant name.
+const ACCESS_ERROR_VARIANTS: [&str; 11] = [
+    "accesscontrol",
+    "accessdenied",
+    "callerisnotowner",
+    "forbidden",
+    "missingrole",
+    "notadmin",
+    "notauthorized",
+    "notowner",
+    "onlyowner",
+    "permissiondenied",
+    "unauthorized",
+];
+
+/// Returns `true` if the decoded return value of a reverted message is an access
+/// control error, e.g. `Err(NotOwner)` or `Err(AccessControl(MissingRole))`.
+///
+/// Only the variant of the returned error is inspected, and the variant wrapped by it,
+/// so that the data of other errors can not be mistaken for an access control error.
+fn is_access_error(value: &Value) -> bool {
+    let Some(error) = returned_error(value) else {
+        return false
+    };
+    let is_access_variant = |value: &Value| {
+        variant_name(value).map_or(false, |name| {
+            let name = name
+                .chars()
+                .filter(|c| c.is_alphanumeric())
+                .collect::<String>()
+                .to_lowercase();
+            ACCESS_ERROR_VARIANTS.contains(&name.as_str())
+        })
+    };
+    is_access_variant(error) || variant_field(error).map_or(false, is_access_variant)
+}
+
+/// Returns the error of a `Result` returned by a message, also if it is wrapped by the
+/// `Ok` of the `Result` for the `LangError` added by ink! 4.
+fn returned_error(value: &Value) -> Option<&Value> {
+    match variant_name(value)?.as_str() {
+        "Err" => variant_field(value),
+        "Ok" => returned_error(variant_field(value)?),
+        _ => None,
+    }
+}
+
+/// Returns the name of an enum variant.
+fn variant_name(value: &Value) -> Option<String> {
+    match value {
+        Value::Tuple(tuple) => tuple.ident(),
+        Value::Map(map) => map.ident(),
+        _ => None,
+    }
+}
+
+/// Returns the only field of an enum variant.
+fn variant_field(value: &Value) -> Option<&Value> {
+    let mut fields: Box<dyn Iterator<Item = &Value>> = match value {
+        Value::Tuple(tuple) => Box::new(tuple.values()),
+        Value::Map(map) => Box::new(map.values()),
+        _ => return None,
+    };
+    fields.next().filter(|_| fields.next().is_none())
+}
+
+/// Result of checking the access rights of the origin for a message.
+#[derive(serde::Serialize)]
+pub struct AccessCheck {
+    /// The origin the message was dry-run as
+    pub origin: String,
+    /// Was the message rejected with an access control error
+    pub denied: bool,
+    /// The message queried to find who is allowed to call the message
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub admin_getter: Option<String>,
+    /// The value returned by the admin getter
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub allowed: Option<Value>,
+    /// Why the admin getter could not be queried
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+impl AccessCheck {
+    pub fn print(&self) {
+        let access = if self.denied { "Denied" } else { "Granted" };
+        name_value_println!(
+            "Access",
+            format!("{access} for {}", self.origin),
+            DEFAULT_KEY_COL_WIDTH
+        );
+        if let Some(ref allowed) = self.allowed {
+            name_value_println!("Allowed", format!("{allowed}"), DEFAULT_KEY_COL_WIDTH);
+        }
+        if let Some(ref error) = self.error {
+            name_value_println!(
+                "Allowed",
+                format!("unknown: {error}"),
+                DEFAULT_KEY_COL_WIDTH
+            );
+        }
+    }
 }
 
 /// Result of the contract call
@@ -310,6 +825,9 @@ pub struct CallDryRunResult {
     /// Was the operation reverted
     pub reverted: bool,
     pub data: Value,
+    /// Result of the `--check-access` check
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub access_check: Option<AccessCheck>,
     pub gas_consumed: Weight,
     pub gas_required: Weight,
     /// Storage deposit after the operation
@@ -322,13 +840,77 @@ impl CallDryRunResult {
         Ok(serde_json::to_string_pretty(self)?)
     }
 
-    pub fn print(&self) {
+    /// Print the result, with the data truncated to `max_data_bytes`, or written into
+    /// the file `out`.
+    pub fn print(&self, out: Option<&Path>, max_data_bytes: usize) -> Result<()> {
         name_value_println!("Result", self.result, DEFAULT_KEY_COL_WIDTH);
         name_value_println!(
             "Reverted",
             format!("{:?}", self.reverted),
             DEFAULT_KEY_COL_WIDTH
         );
-        name_value_println!("Data", format!("{}", self.data), DEFAULT_KEY_COL_WIDTH);
+        let data = match out {
+            Some(path) => {
+                let len = output::write_to_file(path, &self.data)?;
+                format!("written to {} ({len} bytes)", path.display())
+            }
+            None => output::truncate(&self.data, max_data_bytes),
+        };
+        name_value_println!("Data", data, DEFAULT_KEY_COL_WIDTH);
+        if let Some(ref access_check) = self.access_check {
+            access_check.print();
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use contract_transcode::Tuple;
+
+    fn err_variant(name: &str) -> Value {
+        Value::Tuple(Tuple::new(
+            Some("Err"),
+            vec![Value::Tuple(Tuple::new(Some(name), vec![]))],
+        ))
+    }
+
+    #[test]
+    fn access_errors_are_detected() {
+        assert!(is_access_error(&err_variant("NotOwner")));
+        assert!(is_access_error(&err_variant("CallerIsNotOwner")));
+        assert!(is_access_error(&err_variant("Unauthorized")));
+        assert!(is_access_error(&Value::Tuple(Tuple::new(
+            Some("Err"),
+            vec![Value::Tuple(Tuple::new(
+                Some("AccessControl"),
+                vec![Value::Tuple(Tuple::new(Some("MissingRole"), vec![]))]
+            ))]
+        ))));
+        assert!(is_access_error(&Value::Tuple(Tuple::new(
+            Some("Ok"),
+            vec![err_variant("NotOwner")]
+        ))));
+        assert!(!is_access_error(&err_variant("InsufficientBalance")));
+        assert!(!is_access_error(&Value::Bool(false)));
+    }
+
+    #[test]
+    fn access_errors_are_not_detected_in_error_data() {
+        // the data of an error mentioning an access control variant
+        assert!(!is_access_error(&Value::Tuple(Tuple::new(
+            Some("Err"),
+            vec![Value::Tuple(Tuple::new(
+                Some("Custom"),
+                vec![Value::String(String::from("NotOwner"))]
+            ))]
+        ))));
+        assert!(!is_access_error(&err_variant("NotOwnerOfToken")));
+        // a successful return value
+        assert!(!is_access_error(&Value::Tuple(Tuple::new(
+            Some("Ok"),
+            vec![Value::Tuple(Tuple::new(Some("Unauthorized"), vec![]))]
+        ))));
     }
 }
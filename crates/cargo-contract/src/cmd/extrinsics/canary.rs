@@ -0,0 +1,406 @@
+// Copyright 2018-2023 Parity Technologies (UK) Ltd.
+// This file is part of cargo-contract.
+//
+// cargo-contract is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// cargo-contract is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with cargo-contract.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Canary deployments of new contract code.
+//!
+//! With `upgrade --canary` the new code is first instantiated as a separate canary
+//! contract, and the `--check` messages are dry-run against it. The production contract
+//! is only upgraded if all checks pass.
+
+use super::{
+    call::CallRequest,
+    instantiate::{
+        Code,
+        InstantiateRequest,
+    },
+    multicall::split_read,
+    state_call,
+    state_call_batch,
+    submit_extrinsic,
+    Client,
+    ContractMessageTranscoder,
+    DefaultConfig,
+    ErrorVariant,
+    ExtrinsicOpts,
+    PairSigner,
+    TokenMetadata,
+};
+use crate::{
+    cmd::{
+        runtime_api::api,
+        Balance,
+        CodeHash,
+    },
+    name_value_println,
+    DEFAULT_KEY_COL_WIDTH,
+};
+use anyhow::{
+    anyhow,
+    Result,
+};
+use contract_transcode::Value;
+use pallet_contracts_primitives::{
+    ContractExecResult,
+    ContractInstantiateResult,
+};
+use sp_weights::Weight;
+use subxt::Config;
+
+type AccountId = <DefaultConfig as Config>::AccountId;
+
+/// Options for verifying new code on a canary contract before upgrading.
+#[derive(Debug, clap::Args)]
+pub struct CanaryOpts {
+    /// Instantiate the new code as a canary contract and run the `--check` messages
+    /// against it before upgrading the contract.
+    #[clap(long)]
+    canary: bool,
+    /// The constructor instantiating the canary contract.
+    #[clap(long, default_value = "new", requires = "canary")]
+    canary_constructor: String,
+    /// The arguments of the constructor instantiating the canary contract.
+    #[clap(long, num_args = 0.., requires = "canary")]
+    canary_args: Vec<String>,
+    /// A message dry-run against the canary contract followed by its arguments,
+    /// optionally followed by `=>` and the expected return value, e.g.
+    /// `--check "get => Ok(true)"`. The upgrade is aborted if a check fails. Can be
+    /// repeated.
+    #[clap(
+        long = "check",
+        value_name = "MESSAGE [ARGS] [=> EXPECTED]",
+        requires = "canary"
+    )]
+    checks: Vec<String>,
+}
+
+/// A message dry-run against the canary contract.
+#[derive(Debug, PartialEq, Eq)]
+struct Check {
+    message: String,
+    args: Vec<String>,
+    expected: Option<String>,
+}
+
+impl Check {
+    fn parse(input: &str) -> Result<Self> {
+        let (call, expected) = match input.split_once("=>") {
+            Some((call, expected)) => (call, Some(expected.trim().to_string())),
+            None => (input, None),
+        };
+        let (message, args) = split_read(call)?;
+        Ok(Self {
+            message,
+            args,
+            expected,
+        })
+    }
+}
+
+/// The outcome of a check against the canary contract.
+#[derive(Debug, serde::Serialize)]
+pub struct CheckResult {
+    /// The check as given with `--check`.
+    pub check: String,
+    /// Did the check pass.
+    pub passed: bool,
+    /// The decoded return value.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub data: Option<Value>,
+    /// Why the check failed.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+impl CheckResult {
+    pub fn print(&self) {
+        let outcome = if self.passed { "passed" } else { "failed" };
+        name_value_println!(
+            "Check",
+            format!("{} ({outcome})", self.check),
+            DEFAULT_KEY_COL_WIDTH
+        );
+        if let Some(ref data) = self.data {
+            name_value_println!("Data", format!("{data}"), DEFAULT_KEY_COL_WIDTH);
+        }
+        if let Some(ref error) = self.error {
+            name_value_println!("Error", error, DEFAULT_KEY_COL_WIDTH);
+        }
+    }
+}
+
+/// The canary contract and the outcome of the checks against it.
+#[derive(Debug, serde::Serialize)]
+pub struct CanaryReport {
+    /// The address of the canary contract.
+    pub contract: AccountId,
+    /// The outcome of the checks.
+    pub checks: Vec<CheckResult>,
+}
+
+/// The deployment of a canary contract of uploaded code.
+pub struct Canary<'a> {
+    opts: &'a CanaryOpts,
+    checks: Vec<Check>,
+    client: &'a Client,
+    url: &'a str,
+    signer: &'a PairSigner,
+    transcoder: &'a ContractMessageTranscoder,
+    code_hash: CodeHash,
+    data: Vec<u8>,
+    salt: Vec<u8>,
+    storage_deposit_limit: Option<Balance>,
+}
+
+impl CanaryOpts {
+    /// Returns `true` if a canary contract should be deployed before upgrading.
+    pub fn enabled(&self) -> bool {
+        self.canary
+    }
+
+    /// Prepare the deployment of a canary contract of the uploaded code.
+    pub fn canary<'a>(
+        &'a self,
+        client: &'a Client,
+        url: &'a str,
+        signer: &'a PairSigner,
+        transcoder: &'a ContractMessageTranscoder,
+        code_hash: CodeHash,
+        storage_deposit_limit: Option<Balance>,
+    ) -> Result<Canary<'a>> {
+        let checks = self
+            .checks
+            .iter()
+            .map(|check| Check::parse(check))
+            .collect::<Result<Vec<_>>>()?;
+        let data = transcoder.encode(&self.canary_constructor, &self.canary_args)?;
+        // A fresh salt, so that every canary gets a new address.
+        let salt = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)?
+            .as_nanos()
+            .to_le_bytes()
+            .to_vec();
+        Ok(Canary {
+            opts: self,
+            checks,
+            client,
+            url,
+            signer,
+            transcoder,
+            code_hash,
+            data,
+            salt,
+            storage_deposit_limit,
+        })
+    }
+}
+
+impl Canary<'_> {
+    pub fn print_details(&self) {
+        name_value_println!(
+            "Canary",
+            format!(
+                "{} {}",
+                self.opts.canary_constructor,
+                self.opts.canary_args.join(" ")
+            ),
+            DEFAULT_KEY_COL_WIDTH
+        );
+        for check in &self.opts.checks {
+            name_value_println!("Check", check, DEFAULT_KEY_COL_WIDTH);
+        }
+    }
+
+    /// Dry-run the instantiation of the canary contract, returns the gas required.
+    pub async fn dry_run(&self) -> Result<Weight, ErrorVariant> {
+        let request = InstantiateRequest {
+            origin: self.signer.account_id().clone(),
+            value: 0,
+            gas_limit: None,
+            storage_deposit_limit: self.storage_deposit_limit,
+            code: Code::Existing(self.code_hash),
+            data: self.data.clone(),
+            salt: self.salt.clone(),
+        };
+        let result: ContractInstantiateResult<AccountId, Balance> =
+            state_call(self.url, "ContractsApi_instantiate", request).await?;
+        match result.result {
+            Ok(ref ret_val) if ret_val.result.did_revert() => {
+                Err(ErrorVariant::revert(
+                    &self.opts.canary_constructor,
+                    format!("0x{}", hex::encode(&ret_val.result.data)),
+                ))
+            }
+            Ok(_) => Ok(result.gas_required),
+            Err(ref err) => {
+                Err(ErrorVariant::from_dispatch_error(
+                    err,
+                    &self.client.metadata(),
+                )?)
+            }
+        }
+    }
+
+    /// Instantiate the canary contract and run the checks against it. Fails if any check
+    /// fails.
+    pub async fn deploy_and_check(
+        &self,
+        extrinsic_opts: &ExtrinsicOpts,
+        token_metadata: &TokenMetadata,
+        output_json: bool,
+    ) -> Result<CanaryReport, ErrorVariant> {
+        let gas_limit = self.dry_run().await?;
+        let call = api::tx().contracts().instantiate(
+            0,
+            gas_limit.into(),
+            self.storage_deposit_limit.map(Into::into),
+            self.code_hash,
+            self.data.clone(),
+            self.salt.clone(),
+        );
+        let submit_options = extrinsic_opts.submit_options(token_metadata)?;
+        let result =
+            submit_extrinsic(self.client, &call, self.signer, &submit_options).await?;
+        let contract = result
+            .find_first::<api::contracts::events::Instantiated>()?
+            .ok_or_else(|| anyhow!("Failed to find Instantiated event"))?
+            .contract;
+        if !output_json {
+            name_value_println!("Canary", format!("{contract}"), DEFAULT_KEY_COL_WIDTH);
+        }
+
+        let checks = self.run_checks(&contract).await?;
+        if !output_json {
+            for check in &checks {
+                check.print();
+            }
+        }
+        let failed = checks.iter().filter(|check| !check.passed).count();
+        if failed > 0 {
+            return Err(anyhow!(
+                "{failed} of {} checks against the canary contract {contract} failed, \
+                the upgrade is aborted",
+                checks.len()
+            )
+            .into())
+        }
+        Ok(CanaryReport { contract, checks })
+    }
+
+    async fn run_checks(&self, contract: &AccountId) -> Result<Vec<CheckResult>> {
+        let requests = self
+            .checks
+            .iter()
+            .map(|check| {
+                Ok(CallRequest {
+                    origin: self.signer.account_id().clone(),
+                    dest: contract.clone(),
+                    value: 0,
+                    gas_limit: None,
+                    storage_deposit_limit: self.storage_deposit_limit,
+                    input_data: self.transcoder.encode(&check.message, &check.args)?,
+                })
+            })
+            .collect::<Result<Vec<_>>>()?;
+        let responses: Vec<Result<ContractExecResult<Balance>>> =
+            state_call_batch(self.url, "ContractsApi_call", requests).await?;
+
+        let mut results = Vec::new();
+        for ((check, input), response) in
+            self.checks.iter().zip(&self.opts.checks).zip(responses)
+        {
+            let mut result = CheckResult {
+                check: input.trim().to_string(),
+                passed: false,
+                data: None,
+                error: None,
+            };
+            match response.map(|response| response.result) {
+                Ok(Ok(ret_val)) => {
+                    match self
+                        .transcoder
+                        .decode_return(&check.message, &mut &ret_val.data[..])
+                    {
+                        Ok(value) => {
+                            if ret_val.did_revert() {
+                                result.error = Some(String::from("The message reverted"))
+                            } else if let Some(ref expected) = check.expected {
+                                if matches_expected(&value, expected) {
+                                    result.passed = true;
+                                } else {
+                                    result.error = Some(format!("Expected {expected}"))
+                                }
+                            } else {
+                                result.passed = true;
+                            }
+                            result.data = Some(value);
+                        }
+                        Err(err) => result.error = Some(format!("{err:#}")),
+                    }
+                }
+                Ok(Err(ref err)) => {
+                    let object =
+                        ErrorVariant::from_dispatch_error(err, &self.client.metadata())?;
+                    result.error = Some(object.to_string());
+                }
+                Err(err) => result.error = Some(format!("{err:#}")),
+            }
+            results.push(result);
+        }
+        Ok(results)
+    }
+}
+
+/// Compare the displayed return value with the expected value, ignoring whitespace.
+fn matches_expected(value: &Value, expected: &str) -> bool {
+    let strip = |s: &str| s.split_whitespace().collect::<String>();
+    strip(&value.to_string()) == strip(expected)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn checks_are_parsed_with_optional_expected_value() {
+        assert_eq!(
+            Check::parse("get").unwrap(),
+            Check {
+                message: String::from("get"),
+                args: Vec::new(),
+                expected: None,
+            }
+        );
+        assert_eq!(
+            Check::parse(r#"balance_of 5C4h => Ok(100)"#).unwrap(),
+            Check {
+                message: String::from("balance_of"),
+                args: vec![String::from("5C4h")],
+                expected: Some(String::from("Ok(100)")),
+            }
+        );
+        assert!(Check::parse("=> Ok(true)").is_err());
+    }
+
+    #[test]
+    fn expected_values_ignore_whitespace() {
+        let value = Value::Tuple(contract_transcode::Tuple::new(
+            Some("Ok"),
+            vec![Value::Bool(true)],
+        ));
+        assert!(matches_expected(&value, " Ok( true )"));
+        assert!(!matches_expected(&value, "Ok(false)"));
+    }
+}
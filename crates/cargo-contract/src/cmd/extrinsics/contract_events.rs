@@ -0,0 +1,399 @@
+// Copyright 2018-2023 Parity Technologies (UK) Ltd.
+// This file is part of cargo-contract.
+//
+// cargo-contract is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// cargo-contract is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with cargo-contract.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Decoding the data of the `ContractEmitted` events against the events spec of the
+//! contract metadata, shared by the commands displaying the events of an extrinsic.
+//!
+//! The events of contracts other than the one whose metadata is given are decoded with
+//! the metadata recorded for their code in the local registry, of the code version the
+//! contract ran at the block of the event. Events which can not be decoded are displayed
+//! with their raw topics and data, labeled with the events of other metadata seen with
+//! the same signature topic.
+
+use super::{
+    event_signatures::SignatureDatabase,
+    ContractArtifacts,
+    DefaultConfig,
+};
+use crate::cmd::{
+    output,
+    registry::{
+        Registry,
+        UpgradeRecord,
+    },
+    CodeHash,
+};
+use colored::Colorize as _;
+use contract_transcode::{
+    ContractMessageTranscoder,
+    Value,
+};
+use std::{
+    cell::RefCell,
+    collections::HashMap,
+    fmt::Write,
+};
+use subxt::Config;
+
+type AccountId = <DefaultConfig as Config>::AccountId;
+
+/// An event emitted by a contract, decoded against the metadata of the contract.
+#[derive(Debug, Clone, PartialEq, serde::Serialize)]
+pub struct EmittedEvent {
+    /// The label of the event in the metadata.
+    pub name: String,
+    /// The arguments of the event, in the order of the metadata.
+    pub fields: Vec<EmittedField>,
+}
+
+/// An argument of an event emitted by a contract.
+#[derive(Debug, Clone, PartialEq, serde::Serialize)]
+pub struct EmittedField {
+    pub name: String,
+    pub value: Value,
+}
+
+impl EmittedEvent {
+    /// The event of the value decoded by
+    /// [`ContractMessageTranscoder::decode_contract_event`], `None` if the value is
+    /// not a decoded event.
+    pub fn from_value(value: &Value) -> Option<Self> {
+        let Value::Map(map) = value else { return None };
+        let fields = map
+            .iter()
+            .map(|(name, value)| {
+                let name = match name {
+                    Value::String(name) => name.clone(),
+                    name => name.to_string(),
+                };
+                EmittedField {
+                    name,
+                    value: value.clone(),
+                }
+            })
+            .collect();
+        Some(Self {
+            name: map.ident()?,
+            fields,
+        })
+    }
+
+    /// Writes the event name and its fields below each other, indented by `indent`.
+    pub fn display(&self, out: &mut String, indent: usize, max_value_bytes: usize) {
+        let _ = writeln!(
+            out,
+            "{:indent$}{}",
+            "",
+            self.name.bright_cyan().bold(),
+            indent = indent
+        );
+        for field in &self.fields {
+            let _ = writeln!(
+                out,
+                "{:indent$}{}: {}",
+                "",
+                field.name.bright_white(),
+                output::truncate(&field.value, max_value_bytes),
+                indent = indent + 2
+            );
+        }
+    }
+}
+
+/// An event emitted by a contract whose metadata is not available.
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize)]
+pub struct RawContractEvent {
+    /// The address of the contract which emitted the event.
+    pub contract: String,
+    /// The hex encoded topics of the event.
+    pub topics: Vec<String>,
+    /// The hex encoded data of the event.
+    pub data: String,
+    /// The names of the events of known metadata with the signature topic of the event,
+    /// most likely first.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub candidates: Vec<String>,
+}
+
+impl RawContractEvent {
+    pub fn new(
+        contract: String,
+        topics: &[<DefaultConfig as Config>::Hash],
+        data: &[u8],
+        signatures: &SignatureDatabase,
+    ) -> Self {
+        Self {
+            contract,
+            topics: topics.iter().map(|topic| format!("{topic:?}")).collect(),
+            data: format!("0x{}", hex::encode(data)),
+            candidates: signatures.lookup(topics),
+        }
+    }
+
+    /// Writes the emitting contract, the topics and the data below each other, indented
+    /// by `indent`.
+    pub fn display(&self, out: &mut String, indent: usize, max_value_bytes: usize) {
+        let candidates = if self.candidates.is_empty() {
+            String::new()
+        } else {
+            format!(", possibly {}", self.candidates.join(" or "))
+        };
+        let _ = writeln!(
+            out,
+            "{:indent$}{} of {}{candidates}",
+            "",
+            "Unknown event".bright_cyan().bold(),
+            self.contract,
+            indent = indent
+        );
+        for (index, topic) in self.topics.iter().enumerate() {
+            let _ = writeln!(
+                out,
+                "{:indent$}{}: {}",
+                "",
+                format!("topic {index}").bright_white(),
+                topic,
+                indent = indent + 2
+            );
+        }
+        let _ = writeln!(
+            out,
+            "{:indent$}{}: {}",
+            "",
+            "data".bright_white(),
+            output::truncate(&self.data, max_value_bytes),
+            indent = indent + 2
+        );
+    }
+}
+
+/// Decodes the data of the `ContractEmitted` events with the transcoder of a contract.
+#[derive(Clone, Copy)]
+pub struct ContractEventDecoder<'a> {
+    transcoder: Option<&'a ContractMessageTranscoder>,
+}
+
+impl<'a> ContractEventDecoder<'a> {
+    pub fn new(transcoder: Option<&'a ContractMessageTranscoder>) -> Self {
+        Self { transcoder }
+    }
+
+    /// Decode the SCALE encoded data of a `ContractEmitted` event, returns `None` without
+    /// a transcoder or if the event does not match the metadata, e.g. because it was
+    /// emitted by another contract.
+    ///
+    /// The data is only consumed if it is decoded.
+    pub fn decode(&self, data: &mut &[u8]) -> Option<(Value, EmittedEvent)> {
+        decode_event(self.transcoder?, data)
+    }
+}
+
+/// Decodes the data of the `ContractEmitted` events of the contracts instantiated
+/// according to the local registry, with the metadata recorded for their code.
+///
+/// A contract upgraded since its instantiation has several versions of its code. The
+/// version the contract ran at the block of the event is tried first, then the other
+/// versions, so that the events emitted before an upgrade are decoded with the metadata
+/// of their time. Besides the upgrades recorded in the registry, the code hash changes
+/// of the `ContractCodeUpdated` events decoded before are taken into account.
+///
+/// The transcoders are loaded on the first event of each code version.
+#[derive(Default)]
+pub struct RegistryEventDecoder {
+    registry: RefCell<Registry>,
+    transcoders: RefCell<HashMap<CodeHash, Option<ContractMessageTranscoder>>>,
+}
+
+impl RegistryEventDecoder {
+    /// Loads the local registry, an unreadable registry is only reported.
+    pub fn load() -> Self {
+        match Registry::load() {
+            Ok(registry) => Self::new(registry),
+            Err(err) => {
+                tracing::warn!("Loading the registry failed: {:?}", err);
+                Self::default()
+            }
+        }
+    }
+
+    pub fn new(registry: Registry) -> Self {
+        Self {
+            registry: RefCell::new(registry),
+            transcoders: Default::default(),
+        }
+    }
+
+    /// Decode the SCALE encoded data of a `ContractEmitted` event of the contract in the
+    /// block, by default the latest block. Returns `None` if the metadata of none of the
+    /// code versions of the contract is in the registry or the event matches none of
+    /// them.
+    ///
+    /// The data is only consumed if it is decoded.
+    pub fn decode(
+        &self,
+        contract: &AccountId,
+        block_number: Option<u64>,
+        data: &mut &[u8],
+    ) -> Option<(Value, EmittedEvent)> {
+        let code_hashes = self
+            .registry
+            .borrow()
+            .contract_code_hashes(contract, block_number);
+        let mut transcoders = self.transcoders.borrow_mut();
+        code_hashes.into_iter().find_map(|code_hash| {
+            let transcoder = transcoders
+                .entry(code_hash)
+                .or_insert_with(|| self.load_transcoder(&code_hash));
+            decode_event(transcoder.as_ref()?, data)
+        })
+    }
+
+    /// Take the change of the code of the contract in the block into account when
+    /// decoding the events of the blocks after it.
+    pub fn observe_upgrade(
+        &self,
+        contract: AccountId,
+        code_hash: CodeHash,
+        block_number: u64,
+    ) {
+        self.registry.borrow_mut().record_upgrade(UpgradeRecord {
+            contract,
+            network: String::new(),
+            code_hash,
+            block_number,
+        })
+    }
+
+    fn load_transcoder(&self, code_hash: &CodeHash) -> Option<ContractMessageTranscoder> {
+        let path = self
+            .registry
+            .borrow()
+            .code_metadata(code_hash)?
+            .to_path_buf();
+        tracing::debug!(
+            "Loading the metadata of the code {:?} from `{}`",
+            code_hash,
+            path.display()
+        );
+        ContractArtifacts::from_manifest_or_file(None, Some(&path))
+            .and_then(|artifacts| artifacts.contract_transcoder())
+            .map_err(|err| {
+                tracing::warn!(
+                    "Loading the metadata of the code {:?} failed: {:?}",
+                    code_hash,
+                    err
+                )
+            })
+            .ok()
+    }
+}
+
+/// Decode the data of a `ContractEmitted` event with the transcoder, only consuming the
+/// data if it is decoded.
+fn decode_event(
+    transcoder: &ContractMessageTranscoder,
+    data: &mut &[u8],
+) -> Option<(Value, EmittedEvent)> {
+    let mut input = *data;
+    match transcoder.decode_contract_event(&mut input) {
+        Ok(value) => {
+            let event = EmittedEvent::from_value(&value)?;
+            *data = input;
+            Some((value, event))
+        }
+        Err(err) => {
+            tracing::warn!(
+                "Decoding contract event failed: {:?}. It might have come from another contract.",
+                err
+            );
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use contract_transcode::Map;
+
+    #[test]
+    fn decoded_events_are_displayed_with_their_fields() {
+        let value = Value::Map(Map::new(
+            Some("Transfer"),
+            [
+                (Value::String("from".into()), Value::Literal("None".into())),
+                (Value::String("value".into()), Value::UInt(1_000)),
+            ]
+            .into_iter()
+            .collect(),
+        ));
+        let event = EmittedEvent::from_value(&value).unwrap();
+        assert_eq!(event.name, "Transfer");
+        assert_eq!(
+            serde_json::to_value(&event).unwrap(),
+            serde_json::json!({
+                "name": "Transfer",
+                "fields": [
+                    { "name": "from", "value": { "Literal": "None" } },
+                    { "name": "value", "value": { "UInt": 1000 } },
+                ],
+            })
+        );
+
+        let mut out = String::new();
+        event.display(&mut out, 4, 1024);
+        let lines = out.lines().collect::<Vec<_>>();
+        assert_eq!(lines.len(), 3);
+        assert!(lines[0].starts_with("    ") && lines[0].contains("Transfer"));
+        assert!(lines[1].starts_with("      ") && lines[1].ends_with(": None"));
+        assert!(lines[2].starts_with("      ") && lines[2].ends_with(": 1000"));
+
+        assert_eq!(EmittedEvent::from_value(&Value::UInt(1)), None);
+        assert!(ContractEventDecoder::new(None)
+            .decode(&mut &[0u8][..])
+            .is_none());
+    }
+
+    #[test]
+    fn unknown_events_are_displayed_with_their_topics_and_data() {
+        let contract = "5FHneW46xGXgs5mUiveU4sbTyGBzmstUspZC92UhjJM694ty";
+        let data = [0x2a, 0x00];
+        let decoder = RegistryEventDecoder::default();
+        let mut input = &data[..];
+        assert!(decoder
+            .decode(&contract.parse().unwrap(), None, &mut input)
+            .is_none());
+        assert_eq!(input, &data[..]);
+
+        let event = RawContractEvent::new(
+            contract.to_string(),
+            &[<DefaultConfig as Config>::Hash::repeat_byte(1)],
+            &data,
+            &SignatureDatabase::default(),
+        );
+        assert_eq!(event.data, "0x2a00");
+        assert_eq!(event.topics, vec![format!("0x{}", "01".repeat(32))]);
+        assert!(event.candidates.is_empty());
+
+        let mut out = String::new();
+        event.display(&mut out, 4, 1024);
+        let lines = out.lines().collect::<Vec<_>>();
+        assert_eq!(lines.len(), 3);
+        assert!(lines[0].contains("Unknown event") && lines[0].ends_with(contract));
+        assert!(lines[1].starts_with("      ") && lines[1].ends_with(&event.topics[0]));
+        assert!(lines[2].ends_with(": 0x2a00"));
+    }
+}
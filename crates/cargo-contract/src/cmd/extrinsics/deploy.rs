@@ -0,0 +1,678 @@
+// Copyright 2018-2023 Parity Technologies (UK) Ltd.
+// This file is part of cargo-contract.
+//
+// cargo-contract is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// cargo-contract is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with cargo-contract.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Deployment of several interdependent contracts from a manifest.
+//!
+//! The manifest lists the contracts with their constructors, arguments, salts and
+//! endowments. An argument can refer to the address or code hash of another contract of
+//! the manifest, as `${<name>.address}` or `${<name>.code_hash}`, and the contracts are
+//! instantiated after the contracts they refer to. Every deployed contract is written to
+//! the record of the deployment right away, so that a deployment which failed midway is
+//! resumed by running the command again.
+
+use super::{
+    instantiate::{
+        Code,
+        InstantiateRequest,
+    },
+    state_call,
+    submit_extrinsic,
+    BalanceVariant,
+    Client,
+    ContractArtifacts,
+    ContractMessageTranscoder,
+    DefaultConfig,
+    ErrorVariant,
+    ExtrinsicOpts,
+    PairSigner,
+    TokenMetadata,
+};
+use crate::{
+    cmd::{
+        chain_env,
+        extrinsics::display_dry_run_result_warning,
+        lock,
+        runtime_api::api,
+        AsyncCommand,
+        Balance,
+        CodeHash,
+    },
+    name_value_println,
+    DEFAULT_KEY_COL_WIDTH,
+};
+use anyhow::{
+    anyhow,
+    Context,
+    Result,
+};
+use async_trait::async_trait;
+use pallet_contracts_primitives::ContractInstantiateResult;
+use sp_weights::Weight;
+use std::{
+    collections::BTreeMap,
+    path::{
+        Path,
+        PathBuf,
+    },
+};
+use subxt::{
+    Config,
+    OnlineClient,
+};
+
+type AccountId = <DefaultConfig as Config>::AccountId;
+type Hash = <DefaultConfig as Config>::Hash;
+
+#[derive(Debug, clap::Args)]
+#[clap(
+    name = "deploy",
+    about = "Deploy the contracts of a deployment manifest in dependency order"
+)]
+pub struct DeployCommand {
+    /// Path to the deployment manifest.
+    #[clap(long, value_parser, default_value = "deploy.toml")]
+    manifest: PathBuf,
+    /// Path to the record of the deployed contracts, by default the manifest path with
+    /// the extension `deployed.json`. Contracts in the record are not deployed again.
+    #[clap(long, value_parser)]
+    record: Option<PathBuf>,
+    #[clap(flatten)]
+    extrinsic_opts: ExtrinsicOpts,
+    /// Export the deployed contracts in JSON format.
+    #[clap(long, conflicts_with = "verbose")]
+    output_json: bool,
+}
+
+/// The contracts of a deployment manifest.
+#[derive(Debug, serde::Deserialize)]
+#[serde(deny_unknown_fields)]
+struct Manifest {
+    #[serde(rename = "contract", default)]
+    contracts: Vec<ContractSpec>,
+}
+
+/// A contract of the deployment manifest.
+#[derive(Debug, Clone, PartialEq, Eq, serde::Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ContractSpec {
+    /// The name other contracts refer to the contract by.
+    name: String,
+    /// The `.contract` bundle, the `.json` metadata of uploaded code or the `Cargo.toml`
+    /// of the contract, relative to the manifest.
+    path: PathBuf,
+    #[serde(default = "default_constructor")]
+    constructor: String,
+    #[serde(default)]
+    args: Vec<String>,
+    /// The salt of the address derivation, hex encoded.
+    salt: Option<String>,
+    /// The initial balance transferred to the contract, e.g. `1.5DOT`.
+    value: Option<String>,
+}
+
+fn default_constructor() -> String {
+    String::from("new")
+}
+
+/// A value of a deployed contract an argument refers to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Field {
+    Address,
+    CodeHash,
+}
+
+/// The record of a deployment, written after every deployed contract.
+#[derive(Debug, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct DeploymentRecord {
+    /// The URL of the node of the network the contracts are deployed on.
+    pub network: String,
+    /// The deployed contracts, in deployment order.
+    pub contracts: Vec<DeployedContract>,
+}
+
+/// A contract of the deployment.
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct DeployedContract {
+    pub name: String,
+    pub address: AccountId,
+    pub code_hash: CodeHash,
+    /// The hash of the block the contract was instantiated in, `None` for the predicted
+    /// address of a dry-run.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub block_hash: Option<Hash>,
+}
+
+impl DeploymentRecord {
+    fn load(path: &Path, network: &str) -> Result<Self> {
+        if !path.exists() {
+            return Ok(Self {
+                network: network.to_string(),
+                contracts: Vec::new(),
+            })
+        }
+        let contents = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read {}", path.display()))?;
+        let record: Self = serde_json::from_str(&contents)
+            .with_context(|| format!("Invalid deployment record {}", path.display()))?;
+        anyhow::ensure!(
+            record.network == network,
+            "The record {} is of a deployment on {}, pass another `--record` for {network}",
+            path.display(),
+            record.network
+        );
+        Ok(record)
+    }
+
+    fn save(&self, path: &Path) -> Result<()> {
+        lock::write_atomic(path, serde_json::to_string_pretty(self)? + "\n")
+    }
+
+    fn get(&self, name: &str) -> Option<&DeployedContract> {
+        self.contracts.iter().find(|contract| contract.name == name)
+    }
+}
+
+/// A contract ready to be deployed, with the references of its arguments resolved.
+struct Deployment {
+    name: String,
+    constructor: String,
+    code: Code,
+    code_hash: CodeHash,
+    data: Vec<u8>,
+    salt: Vec<u8>,
+    value: Balance,
+}
+
+impl DeployCommand {
+    pub fn is_json(&self) -> bool {
+        self.output_json
+    }
+
+    fn record_path(&self) -> PathBuf {
+        self.record
+            .clone()
+            .unwrap_or_else(|| self.manifest.with_extension("deployed.json"))
+    }
+
+    /// Dry-run the instantiation, returns the address of the contract and the gas limit
+    /// for instantiating it.
+    async fn dry_run(
+        &self,
+        client: &Client,
+        url: &str,
+        signer: &PairSigner,
+        deployment: &Deployment,
+        storage_deposit_limit: Option<Balance>,
+    ) -> Result<(AccountId, Weight), ErrorVariant> {
+        if !self.output_json {
+            super::print_dry_running_status(&format!(
+                "{} {}",
+                deployment.name, deployment.constructor
+            ));
+        }
+        let request = InstantiateRequest {
+            origin: signer.account_id().clone(),
+            value: deployment.value,
+            gas_limit: None,
+            storage_deposit_limit,
+            code: deployment.code.clone(),
+            data: deployment.data.clone(),
+            salt: deployment.salt.clone(),
+        };
+        let result: ContractInstantiateResult<AccountId, Balance> =
+            state_call(url, "ContractsApi_instantiate", request).await?;
+        match result.result {
+            Ok(ref ret_val) if ret_val.result.did_revert() => {
+                Err(ErrorVariant::revert(
+                    &deployment.constructor,
+                    format!("0x{}", hex::encode(&ret_val.result.data)),
+                ))
+            }
+            Ok(ret_val) => {
+                if !self.output_json {
+                    super::print_gas_required_success(result.gas_required);
+                }
+                Ok((ret_val.account_id, result.gas_required))
+            }
+            Err(ref err) => {
+                Err(ErrorVariant::from_dispatch_error(err, &client.metadata())?)
+            }
+        }
+    }
+
+    /// Instantiate the contract, uploading its code if the bundle contains it.
+    async fn instantiate(
+        &self,
+        client: &Client,
+        signer: &PairSigner,
+        deployment: Deployment,
+        gas_limit: Weight,
+        token_metadata: &TokenMetadata,
+    ) -> Result<DeployedContract> {
+        let storage_deposit_limit =
+            self.extrinsic_opts.storage_deposit_limit(token_metadata)?;
+        let submit_options = self.extrinsic_opts.submit_options(token_metadata)?;
+        let result = match deployment.code {
+            Code::Upload(code) => {
+                let call = api::tx().contracts().instantiate_with_code(
+                    deployment.value,
+                    gas_limit.into(),
+                    storage_deposit_limit,
+                    code,
+                    deployment.data,
+                    deployment.salt,
+                );
+                submit_extrinsic(client, &call, signer, &submit_options).await?
+            }
+            Code::Existing(code_hash) => {
+                let call = api::tx().contracts().instantiate(
+                    deployment.value,
+                    gas_limit.into(),
+                    storage_deposit_limit,
+                    code_hash,
+                    deployment.data,
+                    deployment.salt,
+                );
+                submit_extrinsic(client, &call, signer, &submit_options).await?
+            }
+        };
+        let instantiated = result
+            .find_last::<api::contracts::events::Instantiated>()?
+            .ok_or_else(|| anyhow!("Failed to find Instantiated event"))?;
+        Ok(DeployedContract {
+            name: deployment.name,
+            address: instantiated.contract,
+            code_hash: deployment.code_hash,
+            block_hash: Some(result.all_events_in_block().block_hash()),
+        })
+    }
+}
+
+#[async_trait(?Send)]
+impl AsyncCommand for DeployCommand {
+    type Output = Result<(), ErrorVariant>;
+
+    async fn exec(&self) -> Result<(), ErrorVariant> {
+        if self.extrinsic_opts.phala_backend()?.is_some() {
+            return Err(anyhow!(
+                "Deploying Phat contracts is not supported by the `phala` backend"
+            )
+            .into())
+        }
+        self.extrinsic_opts.apply_process_opts()?;
+        let contents = std::fs::read_to_string(&self.manifest)
+            .with_context(|| format!("Failed to read {}", self.manifest.display()))?;
+        let manifest: Manifest = toml::from_str(&contents).with_context(|| {
+            format!("Invalid deployment manifest {}", self.manifest.display())
+        })?;
+        let order = deployment_order(&manifest.contracts)?;
+        let base_dir = self.manifest.parent().unwrap_or(Path::new(""));
+        let signer = super::pair_signer(self.extrinsic_opts.signer("deploy")?);
+        let url = self.extrinsic_opts.url_to_string()?;
+        let record_path = self.record_path();
+        let mut record = DeploymentRecord::load(&record_path, &url)?;
+        let execute = self.extrinsic_opts.execute()?;
+
+        let client = OnlineClient::from_url(&url).await?;
+        chain_env::ensure_supported_chain(&client)?;
+        let token_metadata = TokenMetadata::query(&client).await?;
+        let storage_deposit_limit = self
+            .extrinsic_opts
+            .storage_deposit_limit
+            .as_ref()
+            .map(|bv| bv.denominate_balance(&token_metadata))
+            .transpose()?;
+
+        let pending = order
+            .iter()
+            .map(|index| &manifest.contracts[*index])
+            .filter(|spec| record.get(&spec.name).is_none())
+            .collect::<Vec<_>>();
+        if execute && !pending.is_empty() {
+            self.extrinsic_opts.confirm(|| {
+                for spec in &pending {
+                    name_value_println!(
+                        "Deploy",
+                        format!(
+                            "{} `{} {}`",
+                            spec.name,
+                            spec.constructor,
+                            spec.args.join(" ")
+                        ),
+                        DEFAULT_KEY_COL_WIDTH
+                    );
+                }
+            })?;
+        }
+
+        // The addresses predicted by the dry-runs of contracts which are not
+        // deployed, for resolving the references of the following contracts.
+        let mut predicted = Vec::new();
+        for index in order {
+            let spec = &manifest.contracts[index];
+            let (transcoder, code) = load_artifacts(base_dir, spec)?;
+            let code_hash = match code {
+                Code::Upload(ref code) => contract_build::code_hash(code).into(),
+                Code::Existing(code_hash) => code_hash,
+            };
+            if let Some(deployed) = record.get(&spec.name) {
+                if deployed.code_hash != code_hash {
+                    return Err(anyhow!(
+                        "The code of `{}` changed since it was deployed as {}, remove \
+                        it from {} to deploy it again",
+                        spec.name,
+                        deployed.address,
+                        record_path.display()
+                    )
+                    .into())
+                }
+                if !self.output_json {
+                    name_value_println!(
+                        "Deployed",
+                        format!("{} at {}", spec.name, deployed.address),
+                        DEFAULT_KEY_COL_WIDTH
+                    );
+                }
+                continue
+            }
+
+            let known = record.contracts.iter().chain(&predicted);
+            let args = spec
+                .args
+                .iter()
+                .map(|arg| resolve(arg, known.clone()))
+                .collect::<Result<Vec<_>>>()?;
+            let deployment = Deployment {
+                name: spec.name.clone(),
+                constructor: spec.constructor.clone(),
+                data: transcoder.encode(&spec.constructor, &args)?,
+                code,
+                code_hash,
+                salt: spec
+                    .salt
+                    .as_deref()
+                    .map(contract_build::util::decode_hex)
+                    .transpose()
+                    .with_context(|| format!("Invalid salt of `{}`", spec.name))?
+                    .unwrap_or_default(),
+                value: spec
+                    .value
+                    .as_deref()
+                    .map(|value| {
+                        value
+                            .parse::<BalanceVariant>()?
+                            .denominate_balance(&token_metadata)
+                    })
+                    .transpose()
+                    .with_context(|| format!("Invalid value of `{}`", spec.name))?
+                    .unwrap_or_default(),
+            };
+
+            let (address, gas_limit) = self
+                .dry_run(&client, &url, &signer, &deployment, storage_deposit_limit)
+                .await?;
+            if !execute {
+                if !self.output_json {
+                    name_value_println!(
+                        "Contract",
+                        format!("{} at {address} (predicted)", deployment.name),
+                        DEFAULT_KEY_COL_WIDTH
+                    );
+                }
+                predicted.push(DeployedContract {
+                    name: deployment.name,
+                    address,
+                    code_hash,
+                    block_hash: None,
+                });
+                continue
+            }
+
+            let deployed = self
+                .instantiate(&client, &signer, deployment, gas_limit, &token_metadata)
+                .await
+                .map_err(|err| {
+                    anyhow!(
+                        "{err}. The deployed contracts are recorded in {}, run the \
+                        command again to resume the deployment",
+                        record_path.display()
+                    )
+                })?;
+            if !self.output_json {
+                name_value_println!(
+                    "Contract",
+                    format!("{} at {}", deployed.name, deployed.address),
+                    DEFAULT_KEY_COL_WIDTH
+                );
+            }
+            record.contracts.push(deployed);
+            record.save(&record_path)?;
+        }
+
+        if self.output_json {
+            record.contracts.extend(predicted);
+            println!(
+                "{}",
+                serde_json::to_string_pretty(&record).map_err(anyhow::Error::from)?
+            );
+        } else if !execute {
+            display_dry_run_result_warning("deploy");
+        } else {
+            name_value_println!(
+                "Record",
+                format!("{}", record_path.display()),
+                DEFAULT_KEY_COL_WIDTH
+            );
+        }
+        Ok(())
+    }
+}
+
+/// Load the transcoder and the code of the contract. Contracts given by their metadata
+/// are instantiated from the uploaded code.
+fn load_artifacts(
+    base_dir: &Path,
+    spec: &ContractSpec,
+) -> Result<(ContractMessageTranscoder, Code)> {
+    let path = base_dir.join(&spec.path);
+    let artifacts = if path.file_name().is_some_and(|name| name == "Cargo.toml") {
+        ContractArtifacts::from_manifest_or_file(Some(&path), None)
+    } else {
+        ContractArtifacts::from_manifest_or_file(None, Some(&path))
+    }
+    .with_context(|| format!("Failed to load the artifacts of `{}`", spec.name))?;
+    let transcoder = artifacts.contract_transcoder()?;
+    let code = match artifacts.code {
+        Some(ref code) => Code::Upload(code.0.clone()),
+        None => Code::Existing(artifacts.code_hash()?.into()),
+    };
+    Ok((transcoder, code))
+}
+
+/// Returns the references of the argument to other contracts, by the name of the
+/// contract.
+fn references(arg: &str) -> Result<Vec<(&str, Field)>> {
+    let mut references = Vec::new();
+    let mut rest = arg;
+    while let Some(start) = rest.find("${") {
+        let end = rest[start..]
+            .find('}')
+            .ok_or_else(|| anyhow!("Unterminated reference in `{arg}`"))?;
+        let reference = &rest[start + 2..start + end];
+        let field = match reference.rsplit_once('.') {
+            Some((name, "address")) => (name, Field::Address),
+            Some((name, "code_hash")) => (name, Field::CodeHash),
+            _ => {
+                return Err(anyhow!(
+                    "Invalid reference `${{{reference}}}`, expected \
+                    `${{<name>.address}}` or `${{<name>.code_hash}}`"
+                ))
+            }
+        };
+        references.push(field);
+        rest = &rest[start + end + 1..];
+    }
+    Ok(references)
+}
+
+/// Replace the references of the argument with the values of the deployed contracts.
+fn resolve<'a>(
+    arg: &str,
+    deployed: impl IntoIterator<Item = &'a DeployedContract>,
+) -> Result<String> {
+    let deployed = deployed
+        .into_iter()
+        .map(|contract| (contract.name.as_str(), contract))
+        .collect::<BTreeMap<_, _>>();
+    let mut resolved = arg.to_string();
+    for (name, field) in references(arg)? {
+        let contract = deployed
+            .get(name)
+            .ok_or_else(|| anyhow!("The contract `{name}` is not deployed"))?;
+        let (placeholder, value) = match field {
+            Field::Address => {
+                (format!("${{{name}.address}}"), contract.address.to_string())
+            }
+            Field::CodeHash => {
+                (
+                    format!("${{{name}.code_hash}}"),
+                    format!("{:?}", contract.code_hash),
+                )
+            }
+        };
+        resolved = resolved.replace(&placeholder, &value);
+    }
+    Ok(resolved)
+}
+
+/// Returns the indices of the contracts in deployment order: every contract after the
+/// contracts its arguments refer to, otherwise in the order of the manifest.
+fn deployment_order(contracts: &[ContractSpec]) -> Result<Vec<usize>> {
+    let mut indices = BTreeMap::new();
+    for (index, contract) in contracts.iter().enumerate() {
+        if indices.insert(contract.name.as_str(), index).is_some() {
+            anyhow::bail!("The contract `{}` is listed more than once", contract.name)
+        }
+    }
+    let mut dependencies = Vec::with_capacity(contracts.len());
+    for contract in contracts {
+        let mut deps = Vec::new();
+        for arg in &contract.args {
+            for (name, _) in references(arg)? {
+                let index = *indices.get(name).ok_or_else(|| {
+                    anyhow!(
+                        "`{}` refers to `{name}`, which is not in the manifest",
+                        contract.name
+                    )
+                })?;
+                deps.push(index);
+            }
+        }
+        dependencies.push(deps);
+    }
+
+    let mut order = Vec::with_capacity(contracts.len());
+    let mut done = vec![false; contracts.len()];
+    while order.len() < contracts.len() {
+        let next = (0..contracts.len()).find(|index| {
+            !done[*index] && dependencies[*index].iter().all(|dep| done[*dep])
+        });
+        let Some(next) = next else {
+            let cycle = (0..contracts.len())
+                .filter(|index| !done[*index])
+                .map(|index| format!("`{}`", contracts[index].name))
+                .collect::<Vec<_>>();
+            anyhow::bail!(
+                "The contracts {} refer to each other in a cycle",
+                cycle.join(", ")
+            )
+        };
+        done[next] = true;
+        order.push(next);
+    }
+    Ok(order)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+
+    fn manifest(toml: &str) -> Vec<ContractSpec> {
+        toml::from_str::<Manifest>(toml).unwrap().contracts
+    }
+
+    #[test]
+    fn contracts_are_deployed_after_the_contracts_they_refer_to() {
+        let contracts = manifest(
+            r#"
+            [[contract]]
+            name = "dex"
+            path = "dex/target/ink/dex.contract"
+            args = ["${token.address}", "${pool.code_hash}"]
+
+            [[contract]]
+            name = "token"
+            path = "token/target/ink/token.contract"
+            args = ["1000000"]
+            value = "1.5UNIT"
+
+            [[contract]]
+            name = "pool"
+            path = "pool/target/ink/pool.json"
+            constructor = "default"
+            salt = "0x01"
+            "#,
+        );
+        assert_eq!(contracts[1].constructor, "new");
+        assert_eq!(contracts[2].constructor, "default");
+        assert_eq!(deployment_order(&contracts).unwrap(), [1, 2, 0]);
+
+        let mut cyclic = contracts.clone();
+        cyclic[1].args = vec![String::from("${dex.address}")];
+        assert_eq!(
+            deployment_order(&cyclic).unwrap_err().to_string(),
+            "The contracts `dex`, `token` refer to each other in a cycle"
+        );
+        let mut unknown = contracts;
+        unknown[1].args = vec![String::from("${erc20.address}")];
+        assert!(deployment_order(&unknown).is_err());
+    }
+
+    #[test]
+    fn references_are_resolved() {
+        let token = DeployedContract {
+            name: String::from("token"),
+            address: AccountId::from_str(
+                "5GrwvaEF5zXb26Fz9rcQpDWS57CtERHpNehXCPcNoHGKutQY",
+            )
+            .unwrap(),
+            code_hash: CodeHash::from([1; 32]),
+            block_hash: None,
+        };
+        assert_eq!(
+            resolve("Some(${token.address})", [&token]).unwrap(),
+            "Some(5GrwvaEF5zXb26Fz9rcQpDWS57CtERHpNehXCPcNoHGKutQY)"
+        );
+        assert_eq!(
+            resolve("${token.code_hash}", [&token]).unwrap(),
+            format!("0x{}", "01".repeat(32))
+        );
+        assert!(resolve("${pool.address}", [&token]).is_err());
+        assert!(references("${token.balance}").is_err());
+        assert!(references("${token.address").is_err());
+    }
+}
@@ -0,0 +1,155 @@
+// Copyright 2018-2023 Parity Technologies (UK) Ltd.
+// This file is part of cargo-contract.
+//
+// cargo-contract is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// cargo-contract is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with cargo-contract.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Writing the hashes of uploaded code into files consumed by dependent contracts and
+//! off-chain services.
+//!
+//! A `.rs` file is a module of Rust constants, any other file a `.env` file. The entry
+//! of the contract is replaced if it exists, so the file can hold the hashes of several
+//! contracts and be regenerated on every upload.
+
+use crate::cmd::lock::{
+    self,
+    FileLock,
+};
+use anyhow::{
+    Context,
+    Result,
+};
+use std::{
+    fmt::Write,
+    path::Path,
+};
+
+const RUST_HEADER: &str =
+    "// The hashes of the uploaded contract code, written by `cargo contract upload`.\n";
+
+/// Write the code hash of the contract into the file, replacing its previous entry.
+pub fn write_code_hash(
+    path: &Path,
+    contract_name: &str,
+    code_hash: [u8; 32],
+) -> Result<()> {
+    let _lock = FileLock::acquire(path)?;
+    let contents = if path.exists() {
+        std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read {}", path.display()))?
+    } else {
+        String::new()
+    };
+    let name = constant_name(contract_name);
+    let contents = if path.extension().is_some_and(|ext| ext == "rs") {
+        let contents = if contents.is_empty() {
+            RUST_HEADER.to_string()
+        } else {
+            contents
+        };
+        upsert(
+            &contents,
+            &format!("pub const {name}: [u8; 32] = "),
+            &rust_array(&code_hash),
+        )
+    } else {
+        upsert(
+            &contents,
+            &format!("{name}="),
+            &format!("0x{}", hex::encode(code_hash)),
+        )
+    };
+    lock::write_atomic(path, contents)
+}
+
+/// Returns the name of the constant holding the code hash of the contract, e.g.
+/// `FLIPPER_CODE_HASH`.
+fn constant_name(contract_name: &str) -> String {
+    let name = contract_name
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() {
+                c.to_ascii_uppercase()
+            } else {
+                '_'
+            }
+        })
+        .collect::<String>();
+    format!("{name}_CODE_HASH")
+}
+
+/// Returns the hash as a Rust array literal.
+fn rust_array(hash: &[u8; 32]) -> String {
+    let mut array = String::from("[");
+    for (i, byte) in hash.iter().enumerate() {
+        if i > 0 {
+            array.push_str(", ");
+        }
+        let _ = write!(array, "0x{byte:02x}");
+    }
+    array.push_str("];");
+    array
+}
+
+/// Replace the value of the line starting with the prefix, or append the line.
+fn upsert(contents: &str, prefix: &str, value: &str) -> String {
+    let line = format!("{prefix}{value}");
+    let mut found = false;
+    let mut lines = contents
+        .lines()
+        .map(|existing| {
+            if existing.starts_with(prefix) {
+                found = true;
+                line.clone()
+            } else {
+                existing.to_string()
+            }
+        })
+        .collect::<Vec<_>>();
+    if !found {
+        lines.push(line);
+    }
+    lines.join("\n") + "\n"
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn entries_of_contracts_are_replaced() {
+        let dir = tempfile::tempdir().unwrap();
+        let env = dir.path().join(".env");
+        std::fs::write(&env, "NODE_URL=ws://localhost:9944\n").unwrap();
+        write_code_hash(&env, "flipper", [1; 32]).unwrap();
+        write_code_hash(&env, "my-erc20", [2; 32]).unwrap();
+        write_code_hash(&env, "flipper", [3; 32]).unwrap();
+        assert_eq!(
+            std::fs::read_to_string(&env).unwrap(),
+            format!(
+                "NODE_URL=ws://localhost:9944\nFLIPPER_CODE_HASH=0x{}\nMY_ERC20_CODE_HASH=0x{}\n",
+                "03".repeat(32),
+                "02".repeat(32)
+            )
+        );
+
+        let module = dir.path().join("deployments.rs");
+        write_code_hash(&module, "flipper", [0xab; 32]).unwrap();
+        let module = std::fs::read_to_string(&module).unwrap();
+        assert!(module.starts_with(RUST_HEADER));
+        assert!(
+            module.contains("pub const FLIPPER_CODE_HASH: [u8; 32] = [0xab, 0xab, 0xab,")
+        );
+        assert!(module.ends_with("0xab];\n"));
+    }
+}
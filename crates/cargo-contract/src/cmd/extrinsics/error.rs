@@ -14,6 +14,7 @@
 // You should have received a copy of the GNU General Public License
 // along with cargo-contract.  If not, see <http://www.gnu.org/licenses/>.
 
+use crate::cmd::explain;
 use sp_runtime::DispatchError;
 use std::fmt::{
     self,
@@ -21,56 +22,73 @@ use std::fmt::{
     Display,
 };
 
+/// A failure of a command, serialized as JSON in `--output-json` mode.
+///
+/// Each kind of failure is a separate variant, so that wrappers can handle errors by
+/// their kind instead of matching on the error messages.
 #[derive(serde::Serialize)]
 pub enum ErrorVariant {
+    /// A dispatch error raised by a pallet.
     #[serde(rename = "module_error")]
     Module(ModuleError),
+    /// A dispatch error not raised by a pallet, e.g. `BadOrigin`.
+    #[serde(rename = "dispatch_error")]
+    Dispatch(GenericError),
+    /// A failed request to the node.
+    #[serde(rename = "rpc_error")]
+    Rpc(GenericError),
+    /// Data returned by the node or the contract which could not be decoded.
+    #[serde(rename = "decode_error")]
+    Decode(GenericError),
+    /// A contract message or constructor which reverted.
+    #[serde(rename = "revert_error")]
+    Revert(RevertError),
     #[serde(rename = "generic_error")]
     Generic(GenericError),
 }
 
 impl From<subxt::Error> for ErrorVariant {
     fn from(error: subxt::Error) -> Self {
-        match error {
-            subxt::Error::Runtime(subxt::error::DispatchError::Module(module_err)) => {
-                module_err
-                    .details()
-                    .map(|details| {
-                        ErrorVariant::Module(ModuleError {
-                            pallet: details.pallet().to_string(),
-                            error: details.error().to_string(),
-                            docs: details.docs().to_vec(),
-                        })
-                    })
-                    .unwrap_or_else(|err| {
-                        ErrorVariant::Generic(GenericError::from_message(format!(
-                            "Error extracting subxt error details: {}",
-                            err
-                        )))
-                    })
-            }
-            err => ErrorVariant::Generic(GenericError::from_message(err.to_string())),
-        }
+        Self::from_subxt_error(&error, error.to_string())
     }
 }
 
 impl From<anyhow::Error> for ErrorVariant {
     fn from(error: anyhow::Error) -> Self {
-        Self::Generic(GenericError::from_message(format!("{error:?}")))
-    }
-}
-
-impl From<&str> for ErrorVariant {
-    fn from(err: &str) -> Self {
-        Self::Generic(GenericError::from_message(err.to_owned()))
+        let message = format!("{error:?}");
+        for cause in error.chain() {
+            if let Some(error) = cause.downcast_ref::<subxt::Error>() {
+                return Self::from_subxt_error(error, message)
+            }
+            if cause.is::<jsonrpsee::core::Error>() {
+                return Self::Rpc(GenericError::from_message(message))
+            }
+            if cause.is::<scale::Error>() {
+                return Self::Decode(GenericError::from_message(message))
+            }
+        }
+        Self::Generic(GenericError::from_message(message))
     }
 }
-
 #[derive(serde::Serialize)]
 pub struct ModuleError {
     pub pallet: String,
     pub error: String,
     pub docs: Vec<String>,
+    /// The explanation and likely fix of the error, if it is a known error.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub hint: Option<String>,
+}
+
+impl ModuleError {
+    pub fn new(pallet: &str, error: &str, docs: Vec<String>) -> Self {
+        ModuleError {
+            pallet: pallet.to_owned(),
+            error: error.to_owned(),
+            docs,
+            hint: explain::hint(pallet, error),
+        }
+    }
 }
 
 #[derive(serde::Serialize)]
@@ -78,6 +96,15 @@ pub struct GenericError {
     error: String,
 }
 
+/// A contract message or constructor which reverted.
+#[derive(serde::Serialize)]
+pub struct RevertError {
+    /// The reverted message or constructor.
+    pub message: String,
+    /// The returned data, decoded if possible and hex encoded otherwise.
+    pub data: String,
+}
+
 impl GenericError {
     pub fn from_message(error: String) -> Self {
         GenericError { error }
@@ -85,6 +112,49 @@ impl GenericError {
 }
 
 impl ErrorVariant {
+    /// A message or constructor which reverted, returning the given data.
+    pub fn revert(message: &str, data: String) -> Self {
+        Self::Revert(RevertError {
+            message: message.to_owned(),
+            data,
+        })
+    }
+
+    /// Classify the subxt error, reported with the given message.
+    fn from_subxt_error(error: &subxt::Error, message: String) -> Self {
+        match error {
+            subxt::Error::Runtime(subxt::error::DispatchError::Module(module_err)) => {
+                module_err
+                    .details()
+                    .map(|details| {
+                        ErrorVariant::Module(ModuleError::new(
+                            details.pallet(),
+                            details.error(),
+                            details.docs().to_vec(),
+                        ))
+                    })
+                    .unwrap_or_else(|err| {
+                        ErrorVariant::Generic(GenericError::from_message(format!(
+                            "Error extracting subxt error details: {}",
+                            err
+                        )))
+                    })
+            }
+            subxt::Error::Runtime(_) => {
+                ErrorVariant::Dispatch(GenericError::from_message(message))
+            }
+            subxt::Error::Rpc(_) | subxt::Error::Io(_) => {
+                ErrorVariant::Rpc(GenericError::from_message(message))
+            }
+            subxt::Error::Codec(_)
+            | subxt::Error::Decode(_)
+            | subxt::Error::Unknown(_) => {
+                ErrorVariant::Decode(GenericError::from_message(message))
+            }
+            _ => ErrorVariant::Generic(GenericError::from_message(message)),
+        }
+    }
+
     pub fn from_dispatch_error(
         error: &DispatchError,
         metadata: &subxt::Metadata,
@@ -92,14 +162,14 @@ impl ErrorVariant {
         match error {
             DispatchError::Module(err) => {
                 let details = metadata.error(err.index, err.error[0])?;
-                Ok(ErrorVariant::Module(ModuleError {
-                    pallet: details.pallet().to_owned(),
-                    error: details.error().to_owned(),
-                    docs: details.docs().to_owned(),
-                }))
+                Ok(ErrorVariant::Module(ModuleError::new(
+                    details.pallet(),
+                    details.error(),
+                    details.docs().to_owned(),
+                )))
             }
             err => {
-                Ok(ErrorVariant::Generic(GenericError::from_message(format!(
+                Ok(ErrorVariant::Dispatch(GenericError::from_message(format!(
                     "DispatchError: {err:?}"
                 ))))
             }
@@ -120,9 +190,49 @@ impl Display for ErrorVariant {
                 f.write_fmt(format_args!(
                     "ModuleError: {}::{}: {:?}",
                     err.pallet, err.error, err.docs
-                ))
+                ))?;
+                match err.hint {
+                    Some(ref hint) => write!(f, "\nHint: {hint}"),
+                    None => Ok(()),
+                }
+            }
+            ErrorVariant::Dispatch(err)
+            | ErrorVariant::Rpc(err)
+            | ErrorVariant::Decode(err)
+            | ErrorVariant::Generic(err) => write!(f, "{}", err.error),
+            ErrorVariant::Revert(err) => {
+                write!(f, "`{}` reverted: {}", err.message, err.data)
             }
-            ErrorVariant::Generic(err) => write!(f, "{}", err.error),
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn json(error: ErrorVariant) -> serde_json::Value {
+        serde_json::to_value(error).unwrap()
+    }
+
+    #[test]
+    fn errors_are_serialized_by_kind() {
+        let decode = anyhow::Error::from(scale::Error::from("Not enough data"))
+            .context("Failed to decode the return value");
+        assert_eq!(
+            json(decode.into())["decode_error"]["error"],
+            "Failed to decode the return value\n\nCaused by:\n    Not enough data"
+        );
+        assert_eq!(
+            json(ErrorVariant::revert("flip", String::from("0x01"))),
+            serde_json::json!({
+                "revert_error": { "message": "flip", "data": "0x01" }
+            })
+        );
+        assert!(json(anyhow::anyhow!("Invalid argument").into())
+            .get("generic_error")
+            .is_some());
+        let rpc = subxt::Error::Rpc(subxt::error::RpcError::SubscriptionDropped);
+        assert!(json(rpc.into()).get("rpc_error").is_some());
+    }
+}
@@ -0,0 +1,445 @@
+// Copyright 2018-2023 Parity Technologies (UK) Ltd.
+// This file is part of cargo-contract.
+//
+// cargo-contract is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// cargo-contract is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with cargo-contract.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Estimation of the costs of calling a message or a constructor of a contract.
+//!
+//! The message or the constructor is dry-run for the gas and the storage deposit it
+//! requires. The extrinsic submitting it with the suggested gas limits is then signed,
+//! without being submitted, and its fee is queried from the `TransactionPaymentApi` of
+//! the chain.
+
+use super::{
+    call::CallRequest,
+    extrinsic_params,
+    instantiate::{
+        parse_hex_bytes,
+        Code,
+        InstantiateRequest,
+    },
+    pair_signer,
+    state_call,
+    state_call_at,
+    BalanceVariant,
+    Client,
+    DefaultConfig,
+    DisplayBalance,
+    ErrorVariant,
+    ExtrinsicOpts,
+    PairSigner,
+    StorageDeposit,
+    TokenMetadata,
+};
+use crate::{
+    cmd::{
+        chain_env,
+        chain_info::{
+            ChainLimits,
+            ScheduleDrift,
+            SCHEDULE_DRIFT_THRESHOLD,
+        },
+        identity::Identities,
+        runtime_api::api,
+        AsyncCommand,
+        Balance,
+    },
+    name_value_println,
+    DEFAULT_KEY_COL_WIDTH,
+};
+use anyhow::{
+    anyhow,
+    Result,
+};
+use async_trait::async_trait;
+use colored::Colorize;
+use pallet_contracts_primitives::{
+    ContractExecResult,
+    ContractInstantiateResult,
+};
+use scale::{
+    Decode,
+    Encode,
+};
+use sp_core::Bytes;
+use sp_weights::Weight;
+use subxt::{
+    tx::{
+        self,
+        TxPayload,
+    },
+    Config,
+    OnlineClient,
+};
+
+type AccountId = <DefaultConfig as Config>::AccountId;
+
+#[derive(Debug, clap::Args)]
+#[clap(
+    name = "estimate",
+    about = "Estimate the gas, storage deposit and fee of a message or constructor"
+)]
+pub struct EstimateCommand {
+    /// The address of the contract to call the message of. Without it the instantiation
+    /// with the constructor is estimated.
+    #[clap(name = "contract", long, requires = "message")]
+    contract: Option<AccountId>,
+    /// The name of the contract message to estimate.
+    #[clap(long, short, requires = "contract", conflicts_with = "constructor")]
+    message: Option<String>,
+    /// The name of the contract constructor to estimate, when no `--contract` is given.
+    #[clap(long, default_value = "new")]
+    constructor: String,
+    /// The arguments of the message or constructor.
+    #[clap(long, num_args = 0..)]
+    args: Vec<String>,
+    #[clap(flatten)]
+    extrinsic_opts: ExtrinsicOpts,
+    /// The value to be transferred as part of the call.
+    #[clap(name = "value", long, default_value = "0")]
+    value: BalanceVariant,
+    /// A salt used in the address derivation of the new contract.
+    #[clap(long, value_parser = parse_hex_bytes, conflicts_with = "contract")]
+    salt: Option<Bytes>,
+    /// The percentage added to the gas required by the dry-run for the suggested
+    /// `--gas` and `--proof-size` values, covering state changes before the submission.
+    #[clap(long, value_name = "PCT", default_value_t = 10)]
+    safety_margin: u32,
+    /// Export the estimate in JSON format.
+    #[clap(long, conflicts_with = "verbose")]
+    output_json: bool,
+}
+
+/// The estimated costs of a message or a constructor.
+#[derive(Debug, serde::Serialize)]
+pub struct Estimate {
+    /// The message or constructor
+    pub name: String,
+    /// The gas consumed by the dry-run
+    pub gas_consumed: Weight,
+    /// The gas limit required for the execution to succeed
+    pub gas_required: Weight,
+    /// The percentage added to the required gas for the suggested limits
+    pub safety_margin: u32,
+    /// The suggested `--gas` value
+    pub suggested_gas: u64,
+    /// The suggested `--proof-size` value
+    pub suggested_proof_size: u64,
+    /// The storage deposit charged or refunded
+    pub storage_deposit: StorageDeposit,
+    /// The fee of the extrinsic with the suggested limits, excluding the tip
+    pub fee: DisplayBalance,
+    /// The weights of the schedule of the chain differing significantly from the default
+    /// schedule, which estimates of local test environments assume
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub schedule_drift: Vec<ScheduleDrift>,
+}
+
+impl EstimateCommand {
+    pub fn is_json(&self) -> bool {
+        self.output_json
+    }
+
+    fn print(&self, estimate: &Estimate) {
+        let number_format = self.extrinsic_opts.number_format;
+        let kind = if self.contract.is_some() {
+            "Message"
+        } else {
+            "Constructor"
+        };
+        name_value_println!(kind, estimate.name, DEFAULT_KEY_COL_WIDTH);
+        name_value_println!(
+            "Gas consumed",
+            estimate.gas_consumed.to_string(),
+            DEFAULT_KEY_COL_WIDTH
+        );
+        name_value_println!(
+            "Gas required",
+            estimate.gas_required.to_string(),
+            DEFAULT_KEY_COL_WIDTH
+        );
+        name_value_println!(
+            "Deposit",
+            estimate.storage_deposit.format(number_format),
+            DEFAULT_KEY_COL_WIDTH
+        );
+        name_value_println!(
+            "Fee",
+            estimate.fee.format(number_format),
+            DEFAULT_KEY_COL_WIDTH
+        );
+        name_value_println!(
+            "Suggested",
+            format!(
+                "--gas {} --proof-size {} ({}% margin)",
+                estimate.suggested_gas,
+                estimate.suggested_proof_size,
+                estimate.safety_margin
+            ),
+            DEFAULT_KEY_COL_WIDTH
+        );
+    }
+}
+
+#[async_trait(?Send)]
+impl AsyncCommand for EstimateCommand {
+    type Output = Result<(), ErrorVariant>;
+
+    async fn exec(&self) -> Result<(), ErrorVariant> {
+        if self.extrinsic_opts.phala_backend()?.is_some() {
+            return Err(anyhow!(
+                "The `phala` backend executes contracts off-chain, so no gas or fees \
+                can be estimated"
+            )
+            .into())
+        }
+        self.extrinsic_opts.apply_process_opts()?;
+        let artifacts = self.extrinsic_opts.contract_artifacts()?;
+        let identities = Identities::default();
+        let transcoder = artifacts.contract_transcoder_with_identities(&identities)?;
+        let name = self.message.as_ref().unwrap_or(&self.constructor);
+        let data = transcoder.encode(name, &self.args)?;
+        let signer = pair_signer(self.extrinsic_opts.signer("estimate")?);
+        let url = self.extrinsic_opts.url_to_string()?;
+
+        let client = OnlineClient::from_url(url.clone()).await?;
+        identities.resolve(&client).await?;
+        chain_env::ensure_supported_chain(&client)?;
+        let token_metadata = TokenMetadata::query(&client).await?;
+        let value = self.value.denominate_balance(&token_metadata)?;
+        let storage_deposit_limit =
+            self.extrinsic_opts.storage_deposit_limit(&token_metadata)?;
+        let origin = tx::Signer::account_id(&signer).clone();
+        let schedule_drift = ChainLimits::from_metadata(&client.metadata())?
+            .schedule_drift(&ChainLimits::reference()?, SCHEDULE_DRIFT_THRESHOLD);
+
+        let (gas_consumed, gas_required, storage_deposit, suggested, fee) =
+            if let Some(ref contract) = self.contract {
+                let request = CallRequest {
+                    origin,
+                    dest: contract.clone(),
+                    value,
+                    gas_limit: None,
+                    storage_deposit_limit: storage_deposit_limit.map(|limit| limit.0),
+                    input_data: data.clone(),
+                };
+                let result: ContractExecResult<Balance> =
+                    state_call(&url, "ContractsApi_call", request).await?;
+                let reverted = match result.result {
+                    Ok(ref ret_val) => ret_val.did_revert(),
+                    Err(ref err) => {
+                        return Err(ErrorVariant::from_dispatch_error(
+                            err,
+                            &client.metadata(),
+                        )?)
+                    }
+                };
+                ensure_not_reverted(name, reverted)?;
+                let suggested = with_margin(result.gas_required, self.safety_margin);
+                let call = api::tx().contracts().call(
+                    contract.clone().into(),
+                    value,
+                    suggested.into(),
+                    storage_deposit_limit,
+                    data,
+                );
+                let fee = query_fee(&client, &url, &call, &signer).await?;
+                (
+                    result.gas_consumed,
+                    result.gas_required,
+                    result.storage_deposit,
+                    suggested,
+                    fee,
+                )
+            } else {
+                let code = match artifacts.code {
+                    Some(ref code) => Code::Upload(code.0.clone()),
+                    None => Code::Existing(artifacts.code_hash()?.into()),
+                };
+                let salt = self.salt.clone().map(|salt| salt.0).unwrap_or_default();
+                let request = InstantiateRequest {
+                    origin,
+                    value,
+                    gas_limit: None,
+                    storage_deposit_limit: storage_deposit_limit.map(|limit| limit.0),
+                    code: code.clone(),
+                    data: data.clone(),
+                    salt: salt.clone(),
+                };
+                let result: ContractInstantiateResult<AccountId, Balance> =
+                    state_call(&url, "ContractsApi_instantiate", &request).await?;
+                let reverted = match result.result {
+                    Ok(ref ret_val) => ret_val.result.did_revert(),
+                    Err(ref err) => {
+                        return Err(ErrorVariant::from_dispatch_error(
+                            err,
+                            &client.metadata(),
+                        )?)
+                    }
+                };
+                ensure_not_reverted(name, reverted)?;
+                let suggested = with_margin(result.gas_required, self.safety_margin);
+                let fee = match code {
+                    Code::Upload(code) => {
+                        let call = api::tx().contracts().instantiate_with_code(
+                            value,
+                            suggested.into(),
+                            storage_deposit_limit,
+                            code,
+                            data,
+                            salt,
+                        );
+                        query_fee(&client, &url, &call, &signer).await?
+                    }
+                    Code::Existing(code_hash) => {
+                        let call = api::tx().contracts().instantiate(
+                            value,
+                            suggested.into(),
+                            storage_deposit_limit,
+                            code_hash,
+                            data,
+                            salt,
+                        );
+                        query_fee(&client, &url, &call, &signer).await?
+                    }
+                };
+                (
+                    result.gas_consumed,
+                    result.gas_required,
+                    result.storage_deposit,
+                    suggested,
+                    fee,
+                )
+            };
+
+        let estimate = Estimate {
+            name: name.clone(),
+            gas_consumed,
+            gas_required,
+            safety_margin: self.safety_margin,
+            suggested_gas: suggested.ref_time(),
+            suggested_proof_size: suggested.proof_size(),
+            storage_deposit: StorageDeposit::new(&storage_deposit, &token_metadata)?,
+            fee: DisplayBalance::new(fee, &token_metadata)?,
+            schedule_drift,
+        };
+        if self.output_json {
+            println!(
+                "{}",
+                serde_json::to_string_pretty(&estimate).map_err(anyhow::Error::from)?
+            );
+        } else {
+            warn_schedule_drift(&estimate.schedule_drift);
+            self.print(&estimate);
+        }
+        Ok(())
+    }
+}
+
+/// Warn that estimates assuming the default schedule, e.g. of local test environments,
+/// drift from the dry-runs on the chain.
+fn warn_schedule_drift(drift: &[ScheduleDrift]) {
+    if drift.is_empty() {
+        return
+    }
+    eprintln!(
+        "{} The schedule of the chain differs by more than {SCHEDULE_DRIFT_THRESHOLD}% \
+        from the default schedule of pallet-contracts, estimates assuming the defaults \
+        drift from this one:",
+        "warning:".yellow().bold()
+    );
+    for weight in drift {
+        eprintln!("  {weight}");
+    }
+}
+
+/// The costs of a reverted execution are not the costs of a successful one.
+fn ensure_not_reverted(name: &str, reverted: bool) -> Result<()> {
+    if reverted {
+        anyhow::bail!(
+            "The dry-run of `{name}` reverted, so its costs can not be estimated. Run the \
+            dry-run with `call` or `instantiate` to see the returned error"
+        )
+    }
+    Ok(())
+}
+
+/// The weight increased by the given percentage, rounded up.
+fn with_margin(weight: Weight, percent: u32) -> Weight {
+    let increase = |value: u64| {
+        let increased = (u128::from(value) * (100 + u128::from(percent))).div_ceil(100);
+        u64::try_from(increased).unwrap_or(u64::MAX)
+    };
+    Weight::from_parts(increase(weight.ref_time()), increase(weight.proof_size()))
+}
+
+/// The information about the fees of an extrinsic returned by the
+/// `TransactionPaymentApi`.
+///
+/// Copied from `pallet-transaction-payment`.
+#[derive(Decode)]
+struct RuntimeDispatchInfo {
+    _weight: Weight,
+    _class: u8,
+    partial_fee: Balance,
+}
+
+/// Sign the call with the current nonce of the signer and query the fee of the
+/// extrinsic, excluding the tip.
+async fn query_fee<Call: TxPayload>(
+    client: &Client,
+    url: &str,
+    call: &Call,
+    signer: &PairSigner,
+) -> Result<Balance> {
+    let (params, _) = extrinsic_params(client, None, 0).await?;
+    let extrinsic = client.tx().create_signed(call, signer, params).await?;
+    let info = state_call_at(
+        url,
+        "TransactionPaymentApi_query_info",
+        &query_info_args(extrinsic.encoded()),
+        None,
+    )
+    .await?;
+    Ok(RuntimeDispatchInfo::decode(&mut &info[..])?.partial_fee)
+}
+
+/// The arguments of `TransactionPaymentApi_query_info`: the encoded extrinsic followed
+/// by its length.
+fn query_info_args(extrinsic: &[u8]) -> Vec<u8> {
+    let mut args = extrinsic.to_vec();
+    (extrinsic.len() as u32).encode_to(&mut args);
+    args
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn suggested_weight_adds_the_margin_rounded_up() {
+        let weight = Weight::from_parts(1_000_001, 64);
+        assert_eq!(with_margin(weight, 0), weight);
+        assert_eq!(with_margin(weight, 10), Weight::from_parts(1_100_002, 71));
+        assert_eq!(
+            with_margin(Weight::from_parts(u64::MAX, 0), 50),
+            Weight::from_parts(u64::MAX, 0)
+        );
+    }
+
+    #[test]
+    fn query_info_args_append_the_length() {
+        assert_eq!(query_info_args(&[0x04, 0xff]), [0x04, 0xff, 2, 0, 0, 0]);
+    }
+}
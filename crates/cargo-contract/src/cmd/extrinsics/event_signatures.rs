@@ -0,0 +1,288 @@
+// Copyright 2018-2023 Parity Technologies (UK) Ltd.
+// This file is part of cargo-contract.
+//
+// cargo-contract is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// cargo-contract is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with cargo-contract.  If not, see <http://www.gnu.org/licenses/>.
+
+//! The local database of the event signatures of every contract metadata loaded.
+//!
+//! The first topic of an ink! event is derived from the name of the storage struct of
+//! the contract and the label of the event, e.g. `Erc20::Transfer`: the SCALE encoding of
+//! the name with an empty prefix, hashed with BLAKE2b-256 if it is longer than a topic.
+//! Whenever metadata is loaded, the topics of its events are recorded in the
+//! `event-signatures.json` file of the cache. Events of contracts whose metadata is not
+//! at hand are then labeled with the events whose topic they carry, as a best-effort
+//! guess: another contract may emit an event of the same name with other fields, and
+//! events without a signature topic are never matched.
+
+use super::DefaultConfig;
+use crate::cmd::{
+    lock::{
+        self,
+        FileLock,
+    },
+    node::cache_root,
+};
+use anyhow::{
+    Context,
+    Result,
+};
+use contract_metadata::ContractMetadata;
+use scale::Encode;
+use serde_json::Value;
+use sp_core::hashing::blake2_256;
+use std::{
+    collections::{
+        BTreeMap,
+        BTreeSet,
+    },
+    path::{
+        Path,
+        PathBuf,
+    },
+};
+use subxt::Config;
+
+type Hash = <DefaultConfig as Config>::Hash;
+
+/// The file name of the database in the cache directory.
+const DATABASE_FILE_NAME: &str = "event-signatures.json";
+
+/// An event of a contract metadata.
+#[derive(
+    Debug, Clone, PartialEq, Eq, PartialOrd, Ord, serde::Serialize, serde::Deserialize,
+)]
+pub struct EventSignature {
+    /// The name of the event, prefixed by the name of the storage struct of the
+    /// contract, e.g. `Erc20::Transfer`.
+    pub name: String,
+    /// The number of topics of the event: its signature topic and its indexed fields.
+    pub topics: usize,
+}
+
+/// The event signatures of the metadata seen, by their signature topic.
+#[derive(Debug, Default, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct SignatureDatabase {
+    signatures: BTreeMap<Hash, BTreeSet<EventSignature>>,
+}
+
+impl SignatureDatabase {
+    /// Returns the path of the database file.
+    pub fn path() -> Result<PathBuf> {
+        Ok(cache_root()?.join(DATABASE_FILE_NAME))
+    }
+
+    /// Load the database, an unreadable database is only reported.
+    pub fn load() -> Self {
+        match Self::path().and_then(|path| Self::load_from(&path)) {
+            Ok(database) => database,
+            Err(err) => {
+                tracing::warn!("Loading the event signatures failed: {:?}", err);
+                Self::default()
+            }
+        }
+    }
+
+    /// Load the database from the file at the given path, returns an empty database if
+    /// the file does not exist yet.
+    pub fn load_from(path: &Path) -> Result<Self> {
+        if !path.exists() {
+            return Ok(Self::default())
+        }
+        let contents = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read {}", path.display()))?;
+        serde_json::from_str(&contents)
+            .with_context(|| format!("Invalid event signatures file {}", path.display()))
+    }
+
+    /// Add the events of the metadata to the database file, holding a lock on it. The
+    /// file is only written if it lacks any of them.
+    pub fn record_at(path: &Path, metadata: &ContractMetadata) -> Result<()> {
+        let signatures = event_signatures(metadata);
+        if signatures.is_empty() {
+            return Ok(())
+        }
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        let _lock = FileLock::acquire(path)?;
+        let mut database = Self::load_from(path)?;
+        if database.insert(signatures) {
+            lock::write_atomic(path, serde_json::to_string_pretty(&database)?)?;
+        }
+        Ok(())
+    }
+
+    /// Add the signatures, returns `true` if any of them was new.
+    fn insert(&mut self, signatures: Vec<(Hash, EventSignature)>) -> bool {
+        signatures
+            .into_iter()
+            .fold(false, |added, (topic, signature)| {
+                self.signatures.entry(topic).or_default().insert(signature) || added
+            })
+    }
+
+    /// Returns the names of the events emitting the topics, most likely first: the
+    /// events with as many topics as emitted are listed before the others.
+    pub fn lookup(&self, topics: &[Hash]) -> Vec<String> {
+        let Some(signatures) =
+            topics.first().and_then(|topic| self.signatures.get(topic))
+        else {
+            return Vec::new()
+        };
+        let (matching, others): (Vec<_>, Vec<_>) = signatures
+            .iter()
+            .partition(|signature| signature.topics == topics.len());
+        matching
+            .into_iter()
+            .chain(others)
+            .map(|signature| signature.name.clone())
+            .collect()
+    }
+}
+
+/// Record the events of the metadata in the database of the cache. Failures are only
+/// reported, the database is a best-effort aid.
+pub fn record(metadata: &ContractMetadata) {
+    if let Err(err) = SignatureDatabase::path()
+        .and_then(|path| SignatureDatabase::record_at(&path, metadata))
+    {
+        tracing::warn!("Recording the event signatures failed: {:?}", err);
+    }
+}
+
+/// Returns the events of the metadata with their signature topics. Metadata whose
+/// storage struct is not named in its types has none.
+fn event_signatures(metadata: &ContractMetadata) -> Vec<(Hash, EventSignature)> {
+    let abi = Value::Object(metadata.abi.clone());
+    let Some(contract) = storage_struct(&abi) else {
+        return Vec::new()
+    };
+    let Some(events) = abi.pointer("/spec/events").and_then(Value::as_array) else {
+        return Vec::new()
+    };
+    events
+        .iter()
+        .filter_map(|event| {
+            let label = event.get("label")?.as_str()?;
+            let indexed = event
+                .get("args")
+                .and_then(Value::as_array)
+                .map(|args| {
+                    args.iter()
+                        .filter(|arg| arg.get("indexed") == Some(&Value::Bool(true)))
+                        .count()
+                })
+                .unwrap_or_default();
+            let signature = EventSignature {
+                name: format!("{contract}::{label}"),
+                topics: indexed + 1,
+            };
+            Some((signature_topic(&signature.name), signature))
+        })
+        .collect()
+}
+
+/// Returns the name of the storage struct of the contract, the last segment of the path
+/// of the root type of its storage layout.
+fn storage_struct(abi: &Value) -> Option<&str> {
+    let root = abi.pointer("/storage/root/ty")?.as_u64()?;
+    abi.get("types")?
+        .as_array()?
+        .iter()
+        .find(|ty| ty.get("id").and_then(Value::as_u64) == Some(root))?
+        .pointer("/type/path")?
+        .as_array()?
+        .last()?
+        .as_str()
+}
+
+/// The signature topic of the event with the given name.
+fn signature_topic(name: &str) -> Hash {
+    // The empty prefix is encoded as a byte slice, with its length.
+    let mut encoded = Vec::<u8>::new().encode();
+    encoded.extend_from_slice(name.as_bytes());
+    let mut topic = Hash::zero();
+    if encoded.len() <= topic.as_bytes().len() {
+        topic.as_bytes_mut()[..encoded.len()].copy_from_slice(&encoded);
+    } else {
+        topic = Hash::from(blake2_256(&encoded));
+    }
+    topic
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn metadata(events: Value) -> ContractMetadata {
+        let metadata = json!({
+            "source": { "hash": format!("0x{}", "00".repeat(32)), "language": "ink! 4.2.0", "compiler": "rustc 1.69.0" },
+            "contract": { "name": "erc20", "version": "1.0.0", "authors": [] },
+            "spec": { "events": events },
+            "storage": { "root": { "ty": 7 } },
+            "types": [{ "id": 7, "type": { "path": ["erc20", "erc20", "Erc20"] } }]
+        });
+        serde_json::from_value(metadata).unwrap()
+    }
+
+    #[test]
+    fn events_are_looked_up_by_their_signature_topic() {
+        let transfer = json!({
+            "label": "Transfer",
+            "args": [
+                { "label": "from", "indexed": true },
+                { "label": "to", "indexed": true },
+                { "label": "value", "indexed": false },
+            ],
+        });
+        let signatures = event_signatures(&metadata(json!([
+            transfer,
+            { "label": "Approval", "args": [] },
+        ])));
+        assert_eq!(signatures.len(), 2);
+        assert_eq!(signatures[0].1.name, "Erc20::Transfer");
+        assert_eq!(signatures[0].1.topics, 3);
+
+        let mut expected = [0u8; 32];
+        expected[1..16].copy_from_slice(b"Erc20::Transfer");
+        assert_eq!(signatures[0].0, Hash::from(expected));
+        let long = signature_topic("AVeryLongContractName::AVeryLongEvent");
+        assert_ne!(long, Hash::zero());
+
+        let mut database = SignatureDatabase::default();
+        assert!(database.insert(signatures.clone()));
+        assert!(!database.insert(signatures));
+        let topic = Hash::from(expected);
+        assert_eq!(
+            database.lookup(&[topic, Hash::zero(), Hash::zero()]),
+            vec!["Erc20::Transfer"]
+        );
+        assert!(database.lookup(&[Hash::zero()]).is_empty());
+        assert!(database.lookup(&[]).is_empty());
+    }
+
+    #[test]
+    fn signatures_are_recorded_in_the_database_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join(DATABASE_FILE_NAME);
+        let events = json!([{ "label": "Flipped", "args": [] }]);
+        SignatureDatabase::record_at(&path, &metadata(events)).unwrap();
+
+        let database = SignatureDatabase::load_from(&path).unwrap();
+        assert_eq!(database.signatures.len(), 1);
+        let topic = signature_topic("Erc20::Flipped");
+        assert_eq!(database.lookup(&[topic]), vec!["Erc20::Flipped"]);
+    }
+}
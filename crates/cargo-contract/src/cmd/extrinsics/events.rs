@@ -15,12 +15,26 @@
 // along with cargo-contract.  If not, see <http://www.gnu.org/licenses/>.
 
 use super::{
-    BalanceVariant,
+    contract_events::{
+        ContractEventDecoder,
+        EmittedEvent,
+        RawContractEvent,
+        RegistryEventDecoder,
+    },
+    event_signatures::SignatureDatabase,
     DefaultConfig,
+    DisplayBalance,
+    NumberFormat,
     TokenMetadata,
 };
 use crate::{
-    cmd::runtime_api::api::contracts::events::ContractEmitted,
+    cmd::{
+        output,
+        runtime_api::api::contracts::events::{
+            ContractCodeUpdated,
+            ContractEmitted,
+        },
+    },
     DEFAULT_KEY_COL_WIDTH,
 };
 use colored::Colorize as _;
@@ -28,11 +42,16 @@ use contract_build::Verbosity;
 use contract_transcode::{
     ContractMessageTranscoder,
     Hex,
+    Transcoder,
     TranscoderBuilder,
     Value,
 };
 
 use anyhow::Result;
+use scale::{
+    Decode,
+    Encode as _,
+};
 use scale_info::form::PortableForm;
 use std::{
     fmt::Write,
@@ -41,9 +60,15 @@ use std::{
 use subxt::{
     self,
     blocks::ExtrinsicEvents,
-    events::StaticEvent,
+    events::{
+        EventDetails,
+        StaticEvent,
+    },
+    Config,
 };
 
+type AccountId = <DefaultConfig as Config>::AccountId;
+
 /// Field that represent data of an event from invoking a contract extrinsic.
 #[derive(serde::Serialize)]
 pub struct Field {
@@ -51,6 +76,17 @@ pub struct Field {
     pub name: String,
     /// value of a field
     pub value: Value,
+    /// The value of the field in planck and denominated, if the field is a balance
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub balance: Option<DisplayBalance>,
+    /// The event emitted by a contract, if the field is the data of a `ContractEmitted`
+    /// event decoded against the metadata of the contract
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub contract_event: Option<EmittedEvent>,
+    /// The event emitted by a contract, if the field is the data of a `ContractEmitted`
+    /// event of a contract whose metadata is not available
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub raw_contract_event: Option<RawContractEvent>,
     /// The name of a type as defined in the pallet Source Code
     #[serde(skip_serializing)]
     pub type_name: Option<String>,
@@ -61,9 +97,20 @@ impl Field {
         Field {
             name,
             value,
+            balance: None,
+            contract_event: None,
+            raw_contract_event: None,
             type_name,
         }
     }
+
+    /// Returns `true` if the field holds a balance according to its type name.
+    fn is_balance(&self) -> bool {
+        matches!(
+            self.type_name.as_deref(),
+            Some("T::Balance") | Some("BalanceOf<T>")
+        )
+    }
 }
 
 /// An event produced from from invoking a contract extrinsic.
@@ -77,6 +124,19 @@ pub struct Event {
     pub fields: Vec<Field>,
 }
 
+impl Event {
+    /// The contract of an event of the contracts pallet, if the event has one.
+    pub fn contract(&self) -> Option<String> {
+        if self.pallet != "Contracts" {
+            return None
+        }
+        self.fields
+            .iter()
+            .find(|field| field.name == "contract")
+            .map(|field| field.value.to_string())
+    }
+}
+
 /// Displays events produced from invoking a contract extrinsic.
 #[derive(serde::Serialize)]
 pub struct DisplayEvents(Vec<Event>);
@@ -87,78 +147,28 @@ impl DisplayEvents {
         result: &ExtrinsicEvents<DefaultConfig>,
         transcoder: Option<&ContractMessageTranscoder>,
         subxt_metadata: &subxt::Metadata,
+        token_metadata: &TokenMetadata,
     ) -> Result<DisplayEvents> {
-        let mut events: Vec<Event> = vec![];
-
-        let runtime_metadata = subxt_metadata.runtime_metadata();
-        let events_transcoder = TranscoderBuilder::new(&runtime_metadata.types)
-            .with_default_custom_type_transcoders()
-            .done();
-
-        for event in result.iter() {
-            let event = event?;
-            tracing::debug!("displaying event {:?}", event);
-
-            let event_metadata =
-                subxt_metadata.event(event.pallet_index(), event.variant_index())?;
-            let event_fields = event_metadata.fields();
-
-            let mut event_entry = Event {
-                pallet: event.pallet_name().to_string(),
-                name: event.variant_name().to_string(),
-                fields: vec![],
-            };
-
-            let event_data = &mut event.field_bytes();
-            let mut unnamed_field_name = 0;
-            for field_metadata in event_fields {
-                if <ContractEmitted as StaticEvent>::is_event(
-                    event.pallet_name(),
-                    event.variant_name(),
-                ) && field_metadata.name == Some("data".to_string())
-                {
-                    tracing::debug!("event data: {:?}", hex::encode(&event_data));
-                    let field = contract_event_data_field(
-                        transcoder,
-                        field_metadata,
-                        event_data,
-                    )?;
-                    event_entry.fields.push(field);
-                } else {
-                    let field_name = field_metadata
-                        .name
-                        .as_ref()
-                        .map(|s| s.to_string())
-                        .unwrap_or_else(|| {
-                            let name = unnamed_field_name.to_string();
-                            unnamed_field_name += 1;
-                            name
-                        });
-
-                    let decoded_field = events_transcoder.decode(
-                        &runtime_metadata.types,
-                        field_metadata.ty.id,
-                        event_data,
-                    )?;
-                    let field = Field::new(
-                        field_name,
-                        decoded_field,
-                        field_metadata.type_name.as_ref().map(|s| s.to_string()),
-                    );
-                    event_entry.fields.push(field);
-                }
-            }
-            events.push(event_entry);
-        }
-
+        let decoder = EventDecoder::new(transcoder, subxt_metadata, token_metadata);
+        let events = result
+            .iter()
+            .map(|event| decoder.decode(&event?))
+            .collect::<Result<_>>()?;
         Ok(DisplayEvents(events))
     }
 
+    /// Returns the decoded events.
+    #[cfg(feature = "activity-cache")]
+    pub fn into_events(self) -> Vec<Event> {
+        self.0
+    }
+
     /// Displays events in a human readable format
     pub fn display_events(
         &self,
         verbosity: Verbosity,
-        token_metadata: &TokenMetadata,
+        number_format: NumberFormat,
+        max_value_bytes: usize,
     ) -> Result<String> {
         let event_field_indent: usize = DEFAULT_KEY_COL_WIDTH - 3;
         let mut out = format!(
@@ -177,16 +187,17 @@ impl DisplayEvents {
             );
 
             for field in &event.fields {
-                if verbosity.is_verbose() {
-                    let mut value: String = field.value.to_string();
-                    if field.type_name == Some("T::Balance".to_string())
-                        || field.type_name == Some("BalanceOf<T>".to_string())
-                    {
-                        if let Value::UInt(balance) = field.value {
-                            value = BalanceVariant::from(balance, Some(token_metadata))?
-                                .to_string();
-                        }
-                    }
+                // The events emitted by contracts are displayed with their fields
+                // regardless of the verbosity.
+                if let Some(ref contract_event) = field.contract_event {
+                    contract_event.display(&mut out, event_field_indent, max_value_bytes);
+                } else if let Some(ref raw_event) = field.raw_contract_event {
+                    raw_event.display(&mut out, event_field_indent, max_value_bytes);
+                } else if verbosity.is_verbose() {
+                    let value = match field.balance {
+                        Some(ref balance) => balance.format(number_format),
+                        None => output::truncate(&field.value, max_value_bytes),
+                    };
                     let _ = writeln!(
                         out,
                         "{:width$}{}: {}",
@@ -207,30 +218,173 @@ impl DisplayEvents {
     }
 }
 
-/// Construct the contract event data field, attempting to decode the event using the
-/// [`ContractMessageTranscoder`] if available.
-fn contract_event_data_field(
-    transcoder: Option<&ContractMessageTranscoder>,
-    field_metadata: &scale_info::Field<PortableForm>,
-    event_data: &mut &[u8],
-) -> Result<Field> {
-    let event_value = if let Some(transcoder) = transcoder {
-        match transcoder.decode_contract_event(event_data) {
-            Ok(contract_event) => contract_event,
-            Err(err) => {
-                tracing::warn!(
-                    "Decoding contract event failed: {:?}. It might have come from another contract.",
-                    err
+/// Decodes the events of the runtime, and the events emitted by a contract if its
+/// transcoder is given or the metadata of its code is in the local registry.
+pub struct EventDecoder<'a> {
+    contract_events: ContractEventDecoder<'a>,
+    registry_events: RegistryEventDecoder,
+    signatures: SignatureDatabase,
+    subxt_metadata: &'a subxt::Metadata,
+    token_metadata: &'a TokenMetadata,
+    events_transcoder: Transcoder,
+}
+
+impl<'a> EventDecoder<'a> {
+    pub fn new(
+        transcoder: Option<&'a ContractMessageTranscoder>,
+        subxt_metadata: &'a subxt::Metadata,
+        token_metadata: &'a TokenMetadata,
+    ) -> Self {
+        let events_transcoder =
+            TranscoderBuilder::new(&subxt_metadata.runtime_metadata().types)
+                .with_default_custom_type_transcoders()
+                .done();
+        Self {
+            contract_events: ContractEventDecoder::new(transcoder),
+            registry_events: RegistryEventDecoder::load(),
+            signatures: SignatureDatabase::load(),
+            subxt_metadata,
+            token_metadata,
+            events_transcoder,
+        }
+    }
+
+    /// Decode the fields of the event.
+    pub fn decode(&self, event: &EventDetails) -> Result<Event> {
+        self.decode_at(event, None)
+    }
+
+    /// Decode the fields of the event of the block with the given number, the events of
+    /// contracts are decoded with the metadata of the code they ran at the block.
+    ///
+    /// The `ContractCodeUpdated` events are taken into account for the events of the
+    /// later blocks.
+    pub fn decode_at(
+        &self,
+        event: &EventDetails,
+        block_number: Option<u64>,
+    ) -> Result<Event> {
+        tracing::debug!("displaying event {:?}", event);
+
+        if let Some(block_number) = block_number {
+            if let Some(updated) = event.as_event::<ContractCodeUpdated>()? {
+                self.registry_events.observe_upgrade(
+                    updated.contract,
+                    updated.new_code_hash,
+                    block_number,
+                );
+            }
+        }
+
+        let event_metadata = self
+            .subxt_metadata
+            .event(event.pallet_index(), event.variant_index())?;
+        let event_fields = event_metadata.fields();
+
+        let mut event_entry = Event {
+            pallet: event.pallet_name().to_string(),
+            name: event.variant_name().to_string(),
+            fields: vec![],
+        };
+
+        let event_data = &mut event.field_bytes();
+        let mut unnamed_field_name = 0;
+        for field_metadata in event_fields {
+            if <ContractEmitted as StaticEvent>::is_event(
+                event.pallet_name(),
+                event.variant_name(),
+            ) && field_metadata.name == Some("data".to_string())
+            {
+                tracing::debug!("event data: {:?}", hex::encode(&event_data));
+                let contract = event_entry.contract().unwrap_or_default();
+                let field = self.contract_event_data_field(
+                    event,
+                    &contract,
+                    block_number,
+                    field_metadata,
+                    event_data,
+                )?;
+                event_entry.fields.push(field);
+            } else {
+                let field_name = field_metadata
+                    .name
+                    .as_ref()
+                    .map(|s| s.to_string())
+                    .unwrap_or_else(|| {
+                        let name = unnamed_field_name.to_string();
+                        unnamed_field_name += 1;
+                        name
+                    });
+
+                let decoded_field = self.events_transcoder.decode(
+                    &self.subxt_metadata.runtime_metadata().types,
+                    field_metadata.ty.id,
+                    event_data,
+                )?;
+                let mut field = Field::new(
+                    field_name,
+                    decoded_field,
+                    field_metadata.type_name.as_ref().map(|s| s.to_string()),
                 );
-                Value::Hex(Hex::from_str(&hex::encode(&event_data))?)
+                if field.is_balance() {
+                    if let Value::UInt(balance) = field.value {
+                        field.balance =
+                            Some(DisplayBalance::new(balance, self.token_metadata)?);
+                    }
+                }
+                event_entry.fields.push(field);
             }
         }
-    } else {
-        Value::Hex(Hex::from_str(&hex::encode(event_data))?)
-    };
-    Ok(Field::new(
-        String::from("data"),
-        event_value,
-        field_metadata.type_name.as_ref().map(|s| s.to_string()),
-    ))
+        Ok(event_entry)
+    }
+
+    /// Construct the contract event data field, attempting to decode the event using the
+    /// [`ContractEventDecoder`], then the [`RegistryEventDecoder`] of the emitting
+    /// contract, and falling back to the raw topics and data of the event, labeled with
+    /// the events of the [`SignatureDatabase`] matching its signature topic.
+    fn contract_event_data_field(
+        &self,
+        event: &EventDetails,
+        contract: &str,
+        block_number: Option<u64>,
+        field_metadata: &scale_info::Field<PortableForm>,
+        event_data: &mut &[u8],
+    ) -> Result<Field> {
+        let decoded = self.contract_events.decode(event_data).or_else(|| {
+            let contract = AccountId::from_str(contract).ok()?;
+            self.registry_events
+                .decode(&contract, block_number, event_data)
+        });
+        let (event_value, contract_event, raw_contract_event) = match decoded {
+            Some((value, contract_event)) => (value, Some(contract_event), None),
+            None => {
+                let raw_event = RawContractEvent::new(
+                    contract.to_string(),
+                    &event_topics(event),
+                    event_data,
+                    &self.signatures,
+                );
+                let value = Value::Hex(Hex::from_str(&hex::encode(event_data))?);
+                (value, None, Some(raw_event))
+            }
+        };
+        let mut field = Field::new(
+            String::from("data"),
+            event_value,
+            field_metadata.type_name.as_ref().map(|s| s.to_string()),
+        );
+        field.contract_event = contract_event;
+        field.raw_contract_event = raw_contract_event;
+        Ok(field)
+    }
+}
+
+/// The topics of the event, which follow its fields in the encoded event record.
+fn event_topics(event: &EventDetails) -> Vec<<DefaultConfig as Config>::Hash> {
+    let offset = event.phase().encoded_size() + 2 + event.field_bytes().len();
+    event
+        .bytes()
+        .get(offset..)
+        .and_then(|mut topics| Decode::decode(&mut topics).ok())
+        .unwrap_or_default()
 }
@@ -0,0 +1,581 @@
+// Copyright 2018-2023 Parity Technologies (UK) Ltd.
+// This file is part of cargo-contract.
+//
+// cargo-contract is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// cargo-contract is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with cargo-contract.  If not, see <http://www.gnu.org/licenses/>.
+
+//! The history of the activity of the contracts, read from the events of the contracts
+//! pallet in the blocks of the node, or with the `activity-cache` feature from the local
+//! cache, including the timeline of the code hash changes of a contract.
+
+#[cfg(feature = "activity-cache")]
+use super::activity_cache::ActivityCache;
+use super::{
+    events::{
+        Event,
+        EventDecoder,
+    },
+    url_to_string,
+    Client,
+    ContractArtifacts,
+    DefaultConfig,
+    TokenMetadata,
+};
+#[cfg(not(feature = "activity-cache"))]
+use crate::cmd::checkpoint::CheckpointOpts;
+use crate::{
+    cmd::{
+        identity::Identities,
+        AsyncCommand,
+    },
+    DEFAULT_KEY_COL_WIDTH,
+};
+use anyhow::{
+    Context,
+    Result,
+};
+use async_trait::async_trait;
+use colored::Colorize as _;
+use scale::Decode;
+use std::{
+    ops::RangeInclusive,
+    path::PathBuf,
+    str::FromStr,
+};
+use subxt::{
+    events::{
+        EventDetails,
+        Phase,
+    },
+    utils::MultiAddress,
+    Config,
+};
+
+type AccountId = <DefaultConfig as Config>::AccountId;
+type Hash = <DefaultConfig as Config>::Hash;
+
+/// The number of blocks scanned by default, ending at the latest block.
+const DEFAULT_BLOCKS: u64 = 100;
+
+/// An event of the contracts pallet.
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct ContractEvent {
+    pub block_number: u64,
+    pub block_hash: String,
+    /// The index of the event in the block.
+    pub event_index: u32,
+    /// The index of the extrinsic which emitted the event, if any.
+    pub extrinsic_index: Option<u32>,
+    pub contract: Option<String>,
+    pub pallet: String,
+    pub name: String,
+    /// The decoded fields.
+    pub fields: serde_json::Value,
+}
+
+impl ContractEvent {
+    pub fn new(
+        block_number: u64,
+        block_hash: &str,
+        details: &EventDetails,
+        event: Event,
+    ) -> Result<Self> {
+        let extrinsic_index = match details.phase() {
+            Phase::ApplyExtrinsic(index) => Some(index),
+            Phase::Initialization | Phase::Finalization => None,
+        };
+        Ok(Self {
+            block_number,
+            block_hash: block_hash.to_string(),
+            event_index: details.index(),
+            extrinsic_index,
+            contract: event.contract(),
+            fields: serde_json::to_value(&event.fields)?,
+            pallet: event.pallet,
+            name: event.name,
+        })
+    }
+
+    fn display(&self) {
+        println!(
+            "{:>width$} #{} {} ➜ {}",
+            "Event".bright_green().bold(),
+            self.block_number,
+            self.pallet.bright_white(),
+            self.name.bright_white().bold(),
+            width = DEFAULT_KEY_COL_WIDTH
+        );
+        for field in self.fields.as_array().into_iter().flatten() {
+            let value = match field["value"] {
+                serde_json::Value::String(ref value) => value.clone(),
+                ref value => value.to_string(),
+            };
+            println!(
+                "{:width$}{}: {}",
+                "",
+                field["name"].as_str().unwrap_or_default().bright_white(),
+                value,
+                width = DEFAULT_KEY_COL_WIDTH - 3
+            );
+        }
+    }
+}
+
+/// Fetch the events of the contracts pallet emitted in the block, returns the hash of the
+/// block and the events.
+pub async fn fetch_block_events(
+    client: &Client,
+    number: u64,
+    decoder: &EventDecoder<'_>,
+) -> Result<(String, Vec<ContractEvent>)> {
+    let hash = client
+        .rpc()
+        .block_hash(Some(number.into()))
+        .await?
+        .with_context(|| format!("There is no block #{number}"))?;
+    let block_hash = format!("{hash:?}");
+    let mut events = Vec::new();
+    for details in client.blocks().at(hash).await?.events().await?.iter() {
+        let details = details?;
+        if details.pallet_name() == "Contracts" {
+            let event = decoder.decode_at(&details, Some(number))?;
+            events.push(ContractEvent::new(number, &block_hash, &details, event)?);
+        }
+    }
+    Ok((block_hash, events))
+}
+
+/// Cache the events of the blocks which are not cached yet.
+#[cfg(feature = "activity-cache")]
+pub async fn cache_blocks(
+    cache: &mut ActivityCache,
+    client: &Client,
+    decoder: &EventDecoder<'_>,
+    blocks: std::ops::RangeInclusive<u64>,
+) -> Result<()> {
+    for number in blocks {
+        if !cache.has_block(number)? {
+            let (hash, events) = fetch_block_events(client, number, decoder).await?;
+            cache.insert_block(number, &hash, &events)?;
+        }
+    }
+    Ok(())
+}
+
+/// A change of the code hash of a contract, e.g. an upgrade by `set_code_hash`.
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize)]
+pub struct CodeHashChange {
+    pub block_number: u64,
+    pub block_hash: String,
+    pub old_code_hash: String,
+    pub new_code_hash: String,
+    /// The extrinsic which changed the code hash, if it was changed by one.
+    pub extrinsic: Option<ExtrinsicRef>,
+}
+
+/// An extrinsic of a block.
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize)]
+pub struct ExtrinsicRef {
+    /// The index of the extrinsic in its block.
+    pub index: u32,
+    /// The pallet and name of the call, e.g. `Contracts::call`.
+    pub call: String,
+    /// The address of the signer, if the extrinsic is signed.
+    pub signer: Option<String>,
+}
+
+impl CodeHashChange {
+    /// The change of the code hash recorded by a `ContractCodeUpdated` event of the
+    /// contract, `None` for other events.
+    fn from_event(event: &ContractEvent, contract: &str) -> Option<Self> {
+        if event.pallet != "Contracts"
+            || event.name != "ContractCodeUpdated"
+            || event.contract.as_deref() != Some(contract)
+        {
+            return None
+        }
+        Some(Self {
+            block_number: event.block_number,
+            block_hash: event.block_hash.clone(),
+            old_code_hash: hash_field(event, "old_code_hash")?,
+            new_code_hash: hash_field(event, "new_code_hash")?,
+            extrinsic: None,
+        })
+    }
+
+    pub fn display(&self) {
+        println!(
+            "{:>width$} #{} {} ➜ {}",
+            "Upgraded".bright_green().bold(),
+            self.block_number,
+            self.old_code_hash,
+            self.new_code_hash.bright_white().bold(),
+            width = DEFAULT_KEY_COL_WIDTH
+        );
+        let by = match self.extrinsic {
+            Some(ref extrinsic) => {
+                let signer = match extrinsic.signer {
+                    Some(ref signer) => format!(", signed by {signer}"),
+                    None => String::new(),
+                };
+                format!(
+                    "extrinsic {}-{} {}{signer}",
+                    self.block_number, extrinsic.index, extrinsic.call
+                )
+            }
+            None => String::from("no extrinsic"),
+        };
+        println!(
+            "{:width$}{} {}, {by}",
+            "",
+            "block".bright_white(),
+            self.block_hash,
+            width = DEFAULT_KEY_COL_WIDTH + 1
+        );
+    }
+}
+
+/// The hash in the field of an event, as a hex string.
+fn hash_field(event: &ContractEvent, name: &str) -> Option<String> {
+    let field = event
+        .fields
+        .as_array()?
+        .iter()
+        .find(|field| field["name"] == name)?;
+    let value = &field["value"];
+    match value["Hex"]["s"].as_str() {
+        Some(hex) => Some(format!("0x{}", hex.trim_start_matches("0x"))),
+        None => value["Literal"].as_str().map(ToString::to_string),
+    }
+}
+
+/// The range of blocks from `from_block` to `to_block`, by default the latest 100 blocks.
+async fn block_range(
+    client: &Client,
+    from_block: Option<u64>,
+    to_block: Option<u64>,
+) -> Result<RangeInclusive<u64>> {
+    let latest = u64::from(client.blocks().at_latest().await?.number());
+    let to_block = to_block.unwrap_or(latest).min(latest);
+    let from_block =
+        from_block.unwrap_or_else(|| to_block.saturating_sub(DEFAULT_BLOCKS - 1));
+    Ok(from_block..=to_block)
+}
+
+/// Reconstruct the timeline of the code hash changes of the contract in the range of
+/// blocks, by default the latest 100 blocks, from the `ContractCodeUpdated` events of
+/// the blocks. With the `activity-cache` feature the events of the blocks cached before
+/// are not fetched again.
+pub async fn code_hash_history(
+    client: &Client,
+    contract: &AccountId,
+    from_block: Option<u64>,
+    to_block: Option<u64>,
+) -> Result<Vec<CodeHashChange>> {
+    let blocks = block_range(client, from_block, to_block).await?;
+    let metadata = client.metadata();
+    let token_metadata = TokenMetadata::query(client).await?;
+    let decoder = EventDecoder::new(None, &metadata, &token_metadata);
+    let contract = contract.to_string();
+
+    #[cfg(feature = "activity-cache")]
+    let events = {
+        let mut cache = ActivityCache::open_for(client)?;
+        cache_blocks(&mut cache, client, &decoder, blocks.clone()).await?;
+        cache.events(Some(&contract), Some(*blocks.start()), Some(*blocks.end()))?
+    };
+    #[cfg(not(feature = "activity-cache"))]
+    let events = {
+        let mut events = Vec::new();
+        for number in blocks {
+            events.extend(fetch_block_events(client, number, &decoder).await?.1);
+        }
+        events
+    };
+
+    let mut changes = Vec::new();
+    for event in &events {
+        let Some(mut change) = CodeHashChange::from_event(event, &contract) else {
+            continue
+        };
+        if let Some(index) = event.extrinsic_index {
+            change.extrinsic =
+                Some(fetch_extrinsic(client, &event.block_hash, index).await?);
+        }
+        changes.push(change);
+    }
+    Ok(changes)
+}
+
+/// Fetch the call and the signer of the extrinsic with the given index in the block.
+async fn fetch_extrinsic(
+    client: &Client,
+    block_hash: &str,
+    index: u32,
+) -> Result<ExtrinsicRef> {
+    let hash = Hash::from_str(block_hash)
+        .with_context(|| format!("Invalid block hash {block_hash}"))?;
+    let body = client.blocks().at(hash).await?.body().await?;
+    for extrinsic in body.extrinsics().iter() {
+        let extrinsic = extrinsic?;
+        if extrinsic.index() != index {
+            continue
+        }
+        let signer = match extrinsic.address_bytes() {
+            Some(mut bytes) => {
+                match <DefaultConfig as Config>::Address::decode(&mut bytes)? {
+                    MultiAddress::Id(account) => Some(account.to_string()),
+                    address => Some(format!("{address:?}")),
+                }
+            }
+            None => None,
+        };
+        return Ok(ExtrinsicRef {
+            index,
+            call: format!(
+                "{}::{}",
+                extrinsic.pallet_name()?,
+                extrinsic.variant_name()?
+            ),
+            signer,
+        })
+    }
+    anyhow::bail!("There is no extrinsic {index} in the block {block_hash}")
+}
+
+#[derive(Debug, clap::Args)]
+#[clap(
+    name = "events",
+    about = "Display the events of the contracts pallet in a range of blocks"
+)]
+pub struct EventsCommand {
+    /// Websockets url of a substrate node.
+    #[clap(long, value_parser, default_value = "ws://localhost:9944")]
+    url: url::Url,
+    /// Only display the events of the contract.
+    #[clap(long)]
+    contract: Option<AccountId>,
+    /// The first block of the range, by default 100 blocks before the last block.
+    #[clap(long)]
+    from_block: Option<u64>,
+    /// The last block of the range, by default the latest block.
+    #[clap(long)]
+    to_block: Option<u64>,
+    /// Path to the contract bundle or metadata decoding the data of the events emitted
+    /// by the contract.
+    #[clap(long, value_parser)]
+    file: Option<PathBuf>,
+    /// Only read the events recorded in the activity cache, without scanning the
+    /// blocks.
+    #[cfg(feature = "activity-cache")]
+    #[clap(long)]
+    from_cache: bool,
+    #[cfg(not(feature = "activity-cache"))]
+    #[clap(flatten)]
+    checkpoint: CheckpointOpts,
+    /// Export the events in JSON format.
+    #[clap(long)]
+    output_json: bool,
+}
+
+impl EventsCommand {
+    async fn events(&self) -> Result<Vec<ContractEvent>> {
+        let client = Client::from_url(url_to_string(&self.url)).await?;
+        let contract = self.contract.as_ref().map(ToString::to_string);
+        #[cfg(feature = "activity-cache")]
+        let mut cache = ActivityCache::open_for(&client)?;
+        #[cfg(feature = "activity-cache")]
+        if self.from_cache {
+            return cache.events(contract.as_deref(), self.from_block, self.to_block)
+        }
+
+        let blocks = block_range(&client, self.from_block, self.to_block).await?;
+        let (from_block, to_block) = (*blocks.start(), *blocks.end());
+        let identities = Identities::default();
+        let transcoder = self
+            .file
+            .as_ref()
+            .map(|file| {
+                ContractArtifacts::from_manifest_or_file(None, Some(file))?
+                    .contract_transcoder_with_identities(&identities)
+            })
+            .transpose()?;
+        if transcoder.is_some() {
+            identities.resolve(&client).await?;
+        }
+        let metadata = client.metadata();
+        let token_metadata = TokenMetadata::query(&client).await?;
+        let decoder = EventDecoder::new(transcoder.as_ref(), &metadata, &token_metadata);
+
+        #[cfg(feature = "activity-cache")]
+        {
+            // Blocks cached before keep the events decoded at the time.
+            cache_blocks(&mut cache, &client, &decoder, from_block..=to_block).await?;
+            cache.events(contract.as_deref(), Some(from_block), Some(to_block))
+        }
+        #[cfg(not(feature = "activity-cache"))]
+        {
+            let mut scan = format!("events of {}", url_to_string(&self.url));
+            if let Some(ref contract) = contract {
+                scan.push_str(&format!(" emitted by {contract}"));
+            }
+            // A resumed scan continues with the range of blocks of its checkpoint.
+            let (mut checkpoint, resumed) = self.checkpoint.open(&scan)?;
+            let mut state = resumed.unwrap_or(EventsCheckpoint {
+                next_block: from_block,
+                to_block,
+                events: Vec::new(),
+            });
+            while state.next_block <= state.to_block {
+                let block_events =
+                    match fetch_block_events(&client, state.next_block, &decoder).await {
+                        Ok((_, block_events)) => block_events,
+                        Err(err) => return Err(checkpoint.interrupted(&state, err)),
+                    };
+                state
+                    .events
+                    .extend(block_events.into_iter().filter(|event| {
+                        contract.is_none() || event.contract.as_ref() == contract.as_ref()
+                    }));
+                state.next_block += 1;
+                checkpoint.save(&state)?;
+            }
+            checkpoint.finish()?;
+            Ok(state.events)
+        }
+    }
+}
+
+#[async_trait(?Send)]
+impl AsyncCommand for EventsCommand {
+    type Output = Result<()>;
+
+    async fn exec(&self) -> Result<()> {
+        let events = self.events().await?;
+        if self.output_json {
+            println!("{}", serde_json::to_string_pretty(&events)?);
+        } else if events.is_empty() {
+            println!("No events in the range of blocks");
+        } else {
+            for event in &events {
+                event.display();
+            }
+        }
+        Ok(())
+    }
+}
+
+/// The progress of a scan of the events in a range of blocks.
+#[cfg(not(feature = "activity-cache"))]
+#[derive(serde::Serialize, serde::Deserialize)]
+struct EventsCheckpoint {
+    /// The next block to scan.
+    next_block: u64,
+    /// The last block of the range.
+    to_block: u64,
+    /// The events found in the blocks scanned so far.
+    events: Vec<ContractEvent>,
+}
+
+#[cfg(feature = "activity-cache")]
+#[derive(Debug, clap::Args)]
+#[clap(
+    name = "dashboard",
+    about = "Display a summary of the cached activity of the contracts"
+)]
+pub struct DashboardCommand {
+    /// Websockets url of a substrate node, identifying the chain.
+    #[clap(long, value_parser, default_value = "ws://localhost:9944")]
+    url: url::Url,
+    /// Export the summary in JSON format.
+    #[clap(long)]
+    output_json: bool,
+}
+
+#[cfg(feature = "activity-cache")]
+#[async_trait(?Send)]
+impl AsyncCommand for DashboardCommand {
+    type Output = Result<()>;
+
+    async fn exec(&self) -> Result<()> {
+        let client = Client::from_url(url_to_string(&self.url)).await?;
+        let activity = ActivityCache::open_for(&client)?.dashboard()?;
+        if self.output_json {
+            println!("{}", serde_json::to_string_pretty(&activity)?);
+            return Ok(())
+        }
+        if activity.is_empty() {
+            println!("No contract activity is cached for the chain");
+            return Ok(())
+        }
+        println!(
+            "{:<48} {:>8} {:>8} {:>12} {:>12}  {}",
+            "Contract".bold(),
+            "Events".bold(),
+            "Calls".bold(),
+            "First block".bold(),
+            "Last block".bold(),
+            "Last event".bold()
+        );
+        for contract in &activity {
+            println!(
+                "{:<48} {:>8} {:>8} {:>12} {:>12}  {}",
+                contract.contract,
+                contract.events,
+                contract.calls,
+                contract.first_block,
+                contract.last_block,
+                contract.last_event
+            );
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn event(name: &str, contract: &str) -> ContractEvent {
+        ContractEvent {
+            block_number: 42,
+            block_hash: String::from("0x01"),
+            event_index: 3,
+            extrinsic_index: Some(1),
+            contract: Some(contract.to_string()),
+            pallet: String::from("Contracts"),
+            name: name.to_string(),
+            fields: serde_json::json!([
+                { "name": "contract", "value": { "Literal": contract } },
+                { "name": "new_code_hash", "value": { "Hex": { "s": "bb" } } },
+                { "name": "old_code_hash", "value": { "Hex": { "s": "aa" } } },
+            ]),
+        }
+    }
+
+    #[test]
+    fn code_hash_changes_are_read_from_code_updated_events() {
+        let contract = "5GrwvaEF5zXb26Fz9rcQpDWS57CtERHpNehXCPcNoHGKutQY";
+        let updated = event("ContractCodeUpdated", contract);
+        let change = CodeHashChange::from_event(&updated, contract)
+            .expect("the event records a change");
+
+        assert_eq!(change.block_number, 42);
+        assert_eq!(change.old_code_hash, "0xaa");
+        assert_eq!(change.new_code_hash, "0xbb");
+        let called = event("Called", contract);
+        assert!(CodeHashChange::from_event(&called, contract).is_none());
+        let bob = "5FHneW46xGXgs5mUiveU4sbTyGBzmstUspZC92UhjJM694ty";
+        assert!(CodeHashChange::from_event(&updated, bob).is_none());
+    }
+}
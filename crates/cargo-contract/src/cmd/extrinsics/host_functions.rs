@@ -0,0 +1,281 @@
+// Copyright 2018-2023 Parity Technologies (UK) Ltd.
+// This file is part of cargo-contract.
+//
+// cargo-contract is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// cargo-contract is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with cargo-contract.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Probing the host functions imported by the code of a contract, enabled with
+//! `call --check-host-functions`.
+//!
+//! The node does not list the host functions its runtime provides, e.g. `seal1::call`.
+//! Instead, the code is validated with a dry-run of its upload: the runtime rejects code
+//! importing functions it does not provide, which is the case for contracts built for a
+//! newer runtime after the runtime was downgraded. Calls of these contracts trap. If the
+//! code is rejected, every imported function is probed with a minimal module importing
+//! only that function, to report the missing ones.
+
+use super::{
+    state_call,
+    state_call_batch,
+    upload::CodeUploadRequest,
+    Client,
+    DefaultConfig,
+    ErrorVariant,
+    WasmCode,
+};
+use crate::cmd::{
+    info::fetch_contract_info,
+    runtime_api::api::{
+        self,
+        runtime_types::pallet_contracts::wasm::Determinism,
+    },
+    Balance,
+    CodeHash,
+};
+use anyhow::{
+    Context,
+    Result,
+};
+use colored::Colorize;
+use pallet_contracts_primitives::CodeUploadResult;
+use parity_wasm::elements::{
+    CodeSection,
+    ExportEntry,
+    ExportSection,
+    External,
+    Func,
+    FuncBody,
+    FunctionSection,
+    FunctionType,
+    ImportEntry,
+    ImportSection,
+    Instruction,
+    Instructions,
+    Internal,
+    Module,
+    Section,
+    Type,
+    TypeSection,
+};
+use std::fmt;
+use subxt::Config;
+
+type AccountId = <DefaultConfig as Config>::AccountId;
+
+/// A host function imported by the code of a contract.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HostFunction {
+    /// The module of the import, e.g. `seal1`.
+    pub module: String,
+    /// The name of the function, e.g. `call`.
+    pub name: String,
+    signature: FunctionType,
+}
+
+impl fmt::Display for HostFunction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}::{}", self.module, self.name)
+    }
+}
+
+/// Returns the host functions imported by the code.
+pub fn imported_functions(code: &[u8]) -> Result<Vec<HostFunction>> {
+    let module: Module = parity_wasm::deserialize_buffer(code)
+        .context("Failed to parse the Wasm code of the contract")?;
+    let types = module
+        .type_section()
+        .map(TypeSection::types)
+        .unwrap_or_default();
+    let imports = module
+        .import_section()
+        .map(ImportSection::entries)
+        .unwrap_or_default();
+    imports
+        .iter()
+        .filter_map(|import| {
+            match import.external() {
+                External::Function(index) => Some((import, *index)),
+                _ => None,
+            }
+        })
+        .map(|(import, index)| {
+            let Some(Type::Function(signature)) = types.get(index as usize) else {
+                anyhow::bail!("Invalid type of the import {}", import.field())
+            };
+            Ok(HostFunction {
+                module: import.module().to_string(),
+                name: import.field().to_string(),
+                signature: signature.clone(),
+            })
+        })
+        .collect()
+}
+
+/// Returns a module importing the memory of the code and only the host function, with
+/// the `call` and `deploy` exports every contract has.
+fn probe_module(
+    memory: Option<&ImportEntry>,
+    function: &HostFunction,
+) -> Result<Vec<u8>> {
+    let nullary = FunctionType::new(Vec::new(), Vec::new());
+    let mut imports: Vec<_> = memory.into_iter().cloned().collect();
+    imports.push(ImportEntry::new(
+        function.module.clone(),
+        function.name.clone(),
+        External::Function(0),
+    ));
+    let body = || FuncBody::new(Vec::new(), Instructions::new(vec![Instruction::End]));
+    let module = Module::new(vec![
+        Section::Type(TypeSection::with_types(vec![
+            Type::Function(function.signature.clone()),
+            Type::Function(nullary),
+        ])),
+        Section::Import(ImportSection::with_entries(imports)),
+        Section::Function(FunctionSection::with_entries(vec![
+            Func::new(1),
+            Func::new(1),
+        ])),
+        Section::Export(ExportSection::with_entries(vec![
+            ExportEntry::new("call".to_string(), Internal::Function(1)),
+            ExportEntry::new("deploy".to_string(), Internal::Function(2)),
+        ])),
+        Section::Code(CodeSection::with_bodies(vec![body(), body()])),
+    ]);
+    Ok(parity_wasm::serialize(module)?)
+}
+
+/// Check that the node provides the host functions imported by the code of the
+/// contract, printing a warning otherwise. The local code is used if it is the code of
+/// the contract, otherwise the code is fetched from the chain.
+pub async fn check_host_functions(
+    client: &Client,
+    url: &str,
+    origin: &AccountId,
+    contract: &AccountId,
+    local_code: Option<&WasmCode>,
+) -> Result<()> {
+    let Some(info) = fetch_contract_info(client, contract).await? else {
+        return Ok(())
+    };
+    let code = match local_code {
+        Some(code) if CodeHash::from(code.code_hash()) == info.code_hash => {
+            code.0.clone()
+        }
+        _ => {
+            let pristine_code = api::storage().contracts().pristine_code(info.code_hash);
+            let code = client
+                .storage()
+                .at_latest()
+                .await?
+                .fetch(&pristine_code)
+                .await?;
+            match code {
+                Some(code) => code.0,
+                None => return Ok(()),
+            }
+        }
+    };
+    let request = |code| {
+        CodeUploadRequest {
+            origin: origin.clone(),
+            code,
+            storage_deposit_limit: None,
+            determinism: Determinism::Deterministic,
+        }
+    };
+    let is_rejected = |result: &CodeUploadResult<CodeHash, Balance>| {
+        let Err(err) = result else { return Ok(false) };
+        let error = ErrorVariant::from_dispatch_error(err, &client.metadata())?;
+        tracing::debug!("The dry-run of the upload failed: {error}");
+        Ok::<_, anyhow::Error>(matches!(
+            error,
+            ErrorVariant::Module(ref err) if err.pallet == "Contracts" && err.error == "CodeRejected"
+        ))
+    };
+    let result: CodeUploadResult<CodeHash, Balance> =
+        state_call(url, "ContractsApi_upload_code", request(code.clone())).await?;
+    if !is_rejected(&result)? {
+        return Ok(())
+    }
+
+    let module: Module = parity_wasm::deserialize_buffer(&code)
+        .context("Failed to parse the Wasm code of the contract")?;
+    let memory = module.import_section().and_then(|imports| {
+        imports
+            .entries()
+            .iter()
+            .find(|import| matches!(import.external(), External::Memory(_)))
+    });
+    let functions = imported_functions(&code)?;
+    let probes = functions
+        .iter()
+        .map(|function| probe_module(memory, function).map(request))
+        .collect::<Result<Vec<_>>>()?;
+    let results: Vec<Result<CodeUploadResult<CodeHash, Balance>>> =
+        state_call_batch(url, "ContractsApi_upload_code", probes).await?;
+    let mut missing = Vec::new();
+    for (function, result) in functions.iter().zip(results) {
+        if is_rejected(&result?)? {
+            missing.push(function.to_string());
+        }
+    }
+    if missing.is_empty() {
+        eprintln!(
+            "{} the node rejects the code of the contract {contract}, calls of it may \
+            trap. Run with `RUST_LOG=debug` for details.",
+            "warning:".yellow().bold(),
+        );
+    } else {
+        eprintln!(
+            "{} the code of the contract {contract} imports host functions the node does \
+            not provide: {}. Calls reaching them trap, e.g. after a downgrade of the \
+            runtime.",
+            "warning:".yellow().bold(),
+            missing.join(", ")
+        );
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn probe_modules_import_a_single_host_function() {
+        let function = HostFunction {
+            module: "seal1".to_string(),
+            name: "call".to_string(),
+            signature: FunctionType::new(
+                vec![parity_wasm::elements::ValueType::I32; 2],
+                vec![parity_wasm::elements::ValueType::I32],
+            ),
+        };
+        let memory = ImportEntry::new(
+            "env".to_string(),
+            "memory".to_string(),
+            External::Memory(parity_wasm::elements::MemoryType::new(2, Some(16))),
+        );
+        let probe = probe_module(Some(&memory), &function).unwrap();
+
+        let functions = imported_functions(&probe).unwrap();
+        assert_eq!(functions, vec![function]);
+        assert_eq!(functions[0].to_string(), "seal1::call");
+        let module: Module = parity_wasm::deserialize_buffer(&probe).unwrap();
+        let exports = module.export_section().unwrap().entries();
+        assert_eq!(
+            exports.iter().map(ExportEntry::field).collect::<Vec<_>>(),
+            ["call", "deploy"]
+        );
+    }
+}
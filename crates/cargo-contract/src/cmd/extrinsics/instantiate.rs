@@ -16,20 +16,38 @@
 
 use super::{
     display_contract_exec_result,
-    prompt_confirm_tx,
+    ensure_mapped,
+    invariants::{
+        InvariantOpts,
+        Invariants,
+    },
+    offline::{
+        submit_or_print_unsigned,
+        ExtrinsicSigner,
+    },
+    phala::{
+        instantiated_contract,
+        PhalaBackend,
+    },
+    salt,
     state_call,
     submit_extrinsic,
+    sudo::SudoOpts,
     BalanceVariant,
     Client,
     ContractMessageTranscoder,
     DefaultConfig,
+    DisplayBalance,
     ExtrinsicOpts,
-    PairSigner,
+    NumberFormat,
     StorageDeposit,
     MAX_KEY_COL_WIDTH,
 };
 use crate::{
     cmd::{
+        chain_env,
+        chain_info::ChainLimits,
+        deprecation::Deprecations,
         extrinsics::{
             display_contract_exec_result_debug,
             display_dry_run_result_warning,
@@ -37,7 +55,15 @@ use crate::{
             ErrorVariant,
             TokenMetadata,
         },
+        identity::Identities,
+        registry::{
+            Registry,
+            SaltRecord,
+        },
         runtime_api::api,
+        schema::OutputSchema,
+        session,
+        AsyncCommand,
         Balance,
         CodeHash,
     },
@@ -47,6 +73,8 @@ use anyhow::{
     anyhow,
     Result,
 };
+use async_trait::async_trait;
+use colored::Colorize as _;
 use contract_build::{
     name_value_println,
     util::decode_hex,
@@ -55,11 +83,15 @@ use contract_build::{
 
 use pallet_contracts_primitives::ContractInstantiateResult;
 
-use scale::Encode;
+use scale::{
+    Decode,
+    Encode,
+};
 use sp_core::Bytes;
 use sp_weights::Weight;
 use subxt::{
     blocks::ExtrinsicEvents,
+    tx::DynamicPayload,
     Config,
     OnlineClient,
 };
@@ -74,9 +106,10 @@ pub struct InstantiateCommand {
     args: Vec<String>,
     #[clap(flatten)]
     extrinsic_opts: ExtrinsicOpts,
-    /// Transfers an initial balance to the instantiated contract
+    /// Transfers an initial balance to the instantiated contract, `auto` for the minimal
+    /// viable value computed with a dry-run
     #[clap(name = "value", long, default_value = "0")]
-    value: BalanceVariant,
+    value: Endowment,
     /// Maximum amount of gas to be used for this command.
     /// If not specified will perform a dry-run to estimate the gas consumed for the
     /// instantiation.
@@ -90,13 +123,68 @@ pub struct InstantiateCommand {
     /// instances of the same contract code from the same account.
     #[clap(long, value_parser = parse_hex_bytes)]
     salt: Option<Bytes>,
+    /// Increment the salt until it produces the address of no existing contract,
+    /// skipping the salts recorded in the local registry.
+    #[clap(long)]
+    auto_salt: bool,
     /// Export the instantiate output in JSON format.
     #[clap(long, conflicts_with = "verbose")]
     output_json: bool,
+    /// Print the JSON Schema of the `--output-json` output and exit.
+    #[clap(long)]
+    schema: bool,
+    #[clap(flatten)]
+    invariants: InvariantOpts,
+    #[clap(flatten)]
+    sudo: SudoOpts,
+}
+
+/// The initial balance transferred to the instantiated contract.
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Endowment {
+    /// The minimal viable value, see [`minimal_value`].
+    Auto,
+    Value(BalanceVariant),
+}
+
+impl std::str::FromStr for Endowment {
+    type Err = anyhow::Error;
+
+    fn from_str(input: &str) -> Result<Self> {
+        match input {
+            "auto" => Ok(Self::Auto),
+            _ => Ok(Self::Value(input.parse()?)),
+        }
+    }
+}
+
+/// The minimal value keeping the new contract alive: the existential deposit, so that
+/// the account of the contract is not reaped, plus the storage deposit charged for the
+/// instantiation.
+fn minimal_value(
+    existential_deposit: Balance,
+    storage_deposit: &pallet_contracts_primitives::StorageDeposit<Balance>,
+) -> Balance {
+    match storage_deposit {
+        pallet_contracts_primitives::StorageDeposit::Charge(charge) => {
+            existential_deposit.saturating_add(*charge)
+        }
+        pallet_contracts_primitives::StorageDeposit::Refund(_) => existential_deposit,
+    }
+}
+
+/// The existential deposit of the chain, `None` if it has no balances pallet.
+fn existential_deposit(metadata: &subxt::Metadata) -> Option<Balance> {
+    let constant = metadata
+        .pallet("Balances")
+        .ok()?
+        .constant("ExistentialDeposit")
+        .ok()?;
+    Balance::decode(&mut &*constant.value).ok()
 }
 
 /// Parse hex encoded bytes.
-fn parse_hex_bytes(input: &str) -> Result<Bytes> {
+pub(super) fn parse_hex_bytes(input: &str) -> Result<Bytes> {
     let bytes = decode_hex(input)?;
     Ok(bytes.into())
 }
@@ -105,17 +193,31 @@ impl InstantiateCommand {
     pub fn is_json(&self) -> bool {
         self.output_json
     }
+}
+
+#[async_trait(?Send)]
+impl AsyncCommand for InstantiateCommand {
+    type Output = Result<(), ErrorVariant>;
+
     /// Instantiate a contract stored at the supplied code hash.
     /// Returns the account id of the instantiated contract if successful.
     ///
     /// Creates an extrinsic with the `Contracts::instantiate` Call, submits via RPC, then
     /// waits for the `ContractsEvent::Instantiated` event.
-    pub fn run(&self) -> Result<(), ErrorVariant> {
+    async fn exec(&self) -> Result<(), ErrorVariant> {
+        if self.schema {
+            return Ok(OutputSchema::Instantiate.print()?)
+        }
+        self.extrinsic_opts.apply_process_opts()?;
         let artifacts = self.extrinsic_opts.contract_artifacts()?;
-        let transcoder = artifacts.contract_transcoder()?;
+        let identities = Identities::default();
+        let transcoder = artifacts
+            .contract_transcoder_with_identities(&identities)?
+            .with_bytes_format(self.extrinsic_opts.bytes_format());
+        Deprecations::from_metadata(&artifacts.metadata()?).warn(&self.constructor);
         let data = transcoder.encode(&self.constructor, &self.args)?;
-        let signer = super::pair_signer(self.extrinsic_opts.signer()?);
-        let url = self.extrinsic_opts.url_to_string();
+        let signer = self.extrinsic_opts.extrinsic_signer("instantiate")?;
+        let url = self.extrinsic_opts.url_to_string()?;
         let verbosity = self.extrinsic_opts.verbosity()?;
         let code = if let Some(code) = artifacts.code {
             Code::Upload(code.0)
@@ -124,42 +226,80 @@ impl InstantiateCommand {
             Code::Existing(code_hash.into())
         };
         let salt = self.salt.clone().map(|s| s.0).unwrap_or_default();
+        let invariants = self.invariants.load()?;
+
+        let client = OnlineClient::from_url(url.clone()).await?;
+        identities.resolve(&client).await?;
+        chain_env::ensure_supported_chain(&client)?;
+        ensure_mapped(&client, signer.account_id()).await?;
+        self.extrinsic_opts.preflight(&client).await?;
+        if let Code::Upload(ref code) = code {
+            ChainLimits::from_metadata(&client.metadata())?.check_code(code)?;
+        }
 
-        async_std::task::block_on(async move {
-            let client = OnlineClient::from_url(url.clone()).await?;
-
-            let token_metadata = TokenMetadata::query(&client).await?;
-
-            let args = InstantiateArgs {
-                constructor: self.constructor.clone(),
-                raw_args: self.args.clone(),
-                value: self.value.denominate_balance(&token_metadata)?,
-                gas_limit: self.gas_limit,
-                proof_size: self.proof_size,
-                storage_deposit_limit: self
-                    .extrinsic_opts
-                    .storage_deposit_limit
-                    .as_ref()
-                    .map(|bv| bv.denominate_balance(&token_metadata))
-                    .transpose()?,
-                code,
-                data,
-                salt,
+        let token_metadata = TokenMetadata::query(&client).await?;
+        let salt = if self.extrinsic_opts.phala_backend()?.is_some() {
+            salt
+        } else {
+            let code_hash = match code {
+                Code::Upload(ref code) => contract_build::code_hash(code).into(),
+                Code::Existing(code_hash) => code_hash,
             };
+            salt::choose_salt(
+                &client,
+                &url,
+                signer.account_id(),
+                &code_hash,
+                &data,
+                salt,
+                self.auto_salt,
+            )
+            .await?
+        };
 
-            let exec = Exec {
-                args,
-                opts: self.extrinsic_opts.clone(),
-                url,
-                client,
-                verbosity,
-                signer,
-                transcoder,
-                output_json: self.output_json,
-            };
+        let args = InstantiateArgs {
+            constructor: self.constructor.clone(),
+            raw_args: self.args.clone(),
+            value: match self.value {
+                Endowment::Auto => 0,
+                Endowment::Value(ref value) => {
+                    if let Some(warning) = value.decimals_mismatch(&token_metadata) {
+                        eprintln!("{} {warning}", "warning:".yellow().bold());
+                    }
+                    value.denominate_balance(&token_metadata)?
+                }
+            },
+            gas_limit: self.gas_limit,
+            proof_size: self.proof_size,
+            storage_deposit_limit: self
+                .extrinsic_opts
+                .storage_deposit_limit
+                .as_ref()
+                .map(|bv| bv.denominate_balance(&token_metadata))
+                .transpose()?,
+            code,
+            data,
+            salt,
+        };
 
-            exec.exec(self.extrinsic_opts.execute).await
-        })
+        let mut exec = Exec {
+            args,
+            opts: self.extrinsic_opts.clone(),
+            url,
+            client,
+            verbosity,
+            signer,
+            transcoder,
+            token_metadata,
+            output_json: self.output_json,
+            invariants,
+            sudo: self.sudo.clone(),
+        };
+
+        if self.value == Endowment::Auto {
+            exec.args.value = exec.auto_value().await?;
+        }
+        exec.exec(self.extrinsic_opts.execute()?).await
     }
 }
 
@@ -187,18 +327,58 @@ pub struct Exec {
     verbosity: Verbosity,
     url: String,
     client: Client,
-    signer: PairSigner,
+    signer: ExtrinsicSigner,
     transcoder: ContractMessageTranscoder,
+    token_metadata: TokenMetadata,
     output_json: bool,
+    invariants: Option<Invariants>,
+    sudo: SudoOpts,
 }
 
 impl Exec {
+    /// Compute the minimal viable value with a dry-run.
+    async fn auto_value(&self) -> Result<Balance> {
+        if self.opts.phala_backend()?.is_some() || self.opts.skip_dry_run {
+            return Err(anyhow!(
+                "`--value auto` requires a dry-run of the instantiation, pass the value"
+            ))
+        }
+        let metadata = self.client.metadata();
+        let existential_deposit = existential_deposit(&metadata).ok_or_else(|| {
+            anyhow!("The chain has no existential deposit, pass the value")
+        })?;
+        let result = self.instantiate_dry_run().await?;
+        let value = minimal_value(existential_deposit, &result.storage_deposit);
+        if !self.output_json {
+            name_value_println!(
+                "Value",
+                format!(
+                    "{} (auto)",
+                    DisplayBalance::new(value, &self.token_metadata)?
+                        .format(self.opts.number_format)
+                ),
+                DEFAULT_KEY_COL_WIDTH
+            );
+        }
+        Ok(value)
+    }
+
     async fn exec(&self, execute: bool) -> Result<(), ErrorVariant> {
         tracing::debug!("instantiate data {:?}", self.args.data);
+        if let Some(phala) = self.opts.phala_backend()? {
+            if self.sudo.enabled() {
+                return Err(
+                    anyhow!("`--sudo` is not supported by the `phala` backend").into()
+                )
+            }
+            return self.exec_phala(&phala, execute).await
+        }
         if !execute {
             let result = self.instantiate_dry_run().await?;
             match result.result {
                 Ok(ref ret_val) => {
+                    let minimal_value = existential_deposit(&self.client.metadata())
+                        .map(|ed| minimal_value(ed, &result.storage_deposit));
                     let dry_run_result = InstantiateDryRunResult {
                         result: String::from("Success!"),
                         contract: ret_val.account_id.to_string(),
@@ -206,12 +386,29 @@ impl Exec {
                         data: ret_val.result.data.clone().into(),
                         gas_consumed: result.gas_consumed,
                         gas_required: result.gas_required,
-                        storage_deposit: StorageDeposit::from(&result.storage_deposit),
+                        storage_deposit: StorageDeposit::new(
+                            &result.storage_deposit,
+                            &self.token_metadata,
+                        )?,
+                        minimal_value: minimal_value
+                            .map(|value| DisplayBalance::new(value, &self.token_metadata))
+                            .transpose()?,
                     };
+                    session::record(&dry_run_result);
                     if self.output_json {
                         println!("{}", dry_run_result.to_json()?);
                     } else {
-                        dry_run_result.print();
+                        dry_run_result.print(self.opts.number_format);
+                        if let Some(minimal_value) = minimal_value {
+                            if self.args.value < minimal_value {
+                                eprintln!(
+                                    "{} the value is below the minimal viable value, the \
+                                    contract may be reaped. Pass `--value auto` to \
+                                    transfer the minimal viable value.",
+                                    "warning:".yellow().bold()
+                                );
+                            }
+                        }
                         display_contract_exec_result_debug::<_, DEFAULT_KEY_COL_WIDTH>(
                             &result,
                         )?;
@@ -225,43 +422,176 @@ impl Exec {
                         return Err(object)
                     } else {
                         name_value_println!("Result", object, MAX_KEY_COL_WIDTH);
-                        display_contract_exec_result::<_, MAX_KEY_COL_WIDTH>(&result)?;
+                        display_contract_exec_result::<_, MAX_KEY_COL_WIDTH>(
+                            &result,
+                            &self.token_metadata,
+                            self.opts.number_format,
+                        )?;
                     }
                 }
             }
         } else {
+            if self.sudo.enabled() {
+                self.sudo
+                    .ensure_allowed(&self.client, self.signer.account_id())
+                    .await?;
+            }
             let gas_limit = self.pre_submit_dry_run_gas_estimate().await?;
-            match self.args.code.clone() {
-                Code::Upload(code) => {
-                    self.instantiate_with_code(code, gas_limit).await?;
-                }
+            let contract = match self.args.code.clone() {
+                Code::Upload(code) => self.instantiate_with_code(code, gas_limit).await?,
                 Code::Existing(code_hash) => {
-                    self.instantiate(code_hash, gas_limit).await?;
+                    self.instantiate(code_hash, gas_limit).await?
                 }
+            };
+            // Only the unsigned payload was printed with an offline signer.
+            let Some(contract) = contract else {
+                return Ok(())
+            };
+            self.record_salt(&contract)?;
+            if let Some(ref invariants) = self.invariants {
+                invariants
+                    .check(
+                        &self.url,
+                        self.signer.pair()?,
+                        &contract,
+                        &self.transcoder,
+                        self.args.storage_deposit_limit,
+                        self.output_json,
+                    )
+                    .await?;
             }
+            #[cfg(feature = "astar")]
+            super::astar::register_instantiated(
+                &self.opts,
+                &self.client,
+                self.signer.pair()?,
+                &contract,
+                self.output_json,
+            )
+            .await?;
         }
         Ok(())
     }
 
+    /// Instantiate the contract in the cluster of the Phala backend. Phat contracts are
+    /// executed off-chain, so neither a dry-run nor a gas estimate is available.
+    async fn exec_phala(
+        &self,
+        phala: &PhalaBackend,
+        execute: bool,
+    ) -> Result<(), ErrorVariant> {
+        let gas_limit = self.args.gas_limit.ok_or_else(|| {
+            anyhow!("`--gas` is required for the `phala` backend, Phat contracts can not be dry-run")
+        })?;
+        let preview = || {
+            name_value_println!(
+                "Cluster",
+                format!("{:?}", phala.cluster()),
+                DEFAULT_KEY_COL_WIDTH
+            );
+            self.print_default_instantiate_preview(Weight::from_parts(gas_limit, 0));
+        };
+        if !execute {
+            preview();
+            display_dry_run_result_warning("instantiate");
+            return Ok(())
+        }
+        self.opts.confirm(preview)?;
+
+        let code_hash = match self.args.code {
+            Code::Upload(ref code) => {
+                let call = phala.upload_code_call(code.clone());
+                let result = self.submit_phala_call(&call).await?;
+                // the JSON output is the single object of the instantiation
+                if !self.output_json {
+                    let events = DisplayEvents::from_events(
+                        &result,
+                        None,
+                        &self.client.metadata(),
+                        &self.token_metadata,
+                    )?;
+                    println!(
+                        "{}",
+                        events.display_events(
+                            self.verbosity,
+                            self.opts.number_format,
+                            self.opts.max_output_bytes
+                        )?
+                    );
+                }
+                contract_build::code_hash(code).into()
+            }
+            Code::Existing(code_hash) => code_hash,
+        };
+        let call = phala.instantiate_call(
+            code_hash,
+            self.args.data.clone(),
+            self.args.salt.clone(),
+            self.args.value,
+            gas_limit,
+            self.args.storage_deposit_limit,
+        );
+        let result = self.submit_phala_call(&call).await?;
+        let contract = instantiated_contract(&result)?;
+        let uploaded_code_hash =
+            matches!(self.args.code, Code::Upload(_)).then_some(code_hash);
+        self.display_result(&result, uploaded_code_hash, &contract)
+            .await
+    }
+
+    /// Record the salt of the instantiated contract in the local registry.
+    fn record_salt(&self, contract: &<DefaultConfig as Config>::AccountId) -> Result<()> {
+        let code_hash = match self.args.code {
+            Code::Upload(ref code) => contract_build::code_hash(code).into(),
+            Code::Existing(code_hash) => code_hash,
+        };
+        Registry::update(|registry| {
+            registry.record_salt(SaltRecord {
+                code_hash,
+                network: self.url.clone(),
+                deployer: self.signer.account_id().clone(),
+                salt: self.args.salt.clone().into(),
+                contract: contract.clone(),
+            })
+        })
+    }
+
+    async fn submit_phala_call(
+        &self,
+        call: &DynamicPayload,
+    ) -> Result<ExtrinsicEvents<DefaultConfig>> {
+        let submit_options = self.opts.submit_options(&self.token_metadata)?;
+        Ok(
+            submit_extrinsic(&self.client, call, self.signer.pair()?, &submit_options)
+                .await?,
+        )
+    }
+
     async fn instantiate_with_code(
         &self,
         code: Vec<u8>,
         gas_limit: Weight,
-    ) -> Result<(), ErrorVariant> {
-        if !self.opts.skip_confirm {
-            prompt_confirm_tx(|| self.print_default_instantiate_preview(gas_limit))?;
-        }
+    ) -> Result<Option<<DefaultConfig as Config>::AccountId>, ErrorVariant> {
+        self.opts
+            .confirm(|| self.print_default_instantiate_preview(gas_limit))?;
 
-        let call = api::tx().contracts().instantiate_with_code(
+        let call = self.sudo.wrap(api::tx().contracts().instantiate_with_code(
             self.args.value,
             gas_limit.into(),
             self.args.storage_deposit_limit_compact(),
             code.to_vec(),
             self.args.data.clone(),
             self.args.salt.clone(),
-        );
-
-        let result = submit_extrinsic(&self.client, &call, &self.signer).await?;
+        ));
+
+        let submit_options = self.opts.submit_options(&self.token_metadata)?;
+        let Some(result) =
+            submit_or_print_unsigned(&self.client, &call, &self.signer, &submit_options)
+                .await?
+        else {
+            return Ok(None)
+        };
+        self.sudo.check_result(&result, &self.client.metadata())?;
 
         // The CodeStored event is only raised if the contract has not already been
         // uploaded.
@@ -273,70 +603,82 @@ impl Exec {
             .find_last::<api::contracts::events::Instantiated>()?
             .ok_or_else(|| anyhow!("Failed to find Instantiated event"))?;
 
-        let token_metadata = TokenMetadata::query(&self.client).await?;
-        self.display_result(&result, code_hash, instantiated.contract, &token_metadata)
-            .await
+        self.display_result(&result, code_hash, &instantiated.contract)
+            .await?;
+        Ok(Some(instantiated.contract))
     }
 
     async fn instantiate(
         &self,
         code_hash: CodeHash,
         gas_limit: Weight,
-    ) -> Result<(), ErrorVariant> {
-        if !self.opts.skip_confirm {
-            prompt_confirm_tx(|| {
-                self.print_default_instantiate_preview(gas_limit);
-                name_value_println!(
-                    "Code hash",
-                    format!("{code_hash:?}"),
-                    DEFAULT_KEY_COL_WIDTH
-                );
-            })?;
-        }
-
-        let call = api::tx().contracts().instantiate(
+    ) -> Result<Option<<DefaultConfig as Config>::AccountId>, ErrorVariant> {
+        self.opts.confirm(|| {
+            self.print_default_instantiate_preview(gas_limit);
+            name_value_println!(
+                "Code hash",
+                format!("{code_hash:?}"),
+                DEFAULT_KEY_COL_WIDTH
+            );
+        })?;
+
+        let call = self.sudo.wrap(api::tx().contracts().instantiate(
             self.args.value,
             gas_limit.into(),
             self.args.storage_deposit_limit_compact(),
             code_hash,
             self.args.data.clone(),
             self.args.salt.clone(),
-        );
-
-        let result = submit_extrinsic(&self.client, &call, &self.signer).await?;
+        ));
+
+        let submit_options = self.opts.submit_options(&self.token_metadata)?;
+        let Some(result) =
+            submit_or_print_unsigned(&self.client, &call, &self.signer, &submit_options)
+                .await?
+        else {
+            return Ok(None)
+        };
+        self.sudo.check_result(&result, &self.client.metadata())?;
 
         let instantiated = result
             .find_first::<api::contracts::events::Instantiated>()?
             .ok_or_else(|| anyhow!("Failed to find Instantiated event"))?;
 
-        let token_metadata = TokenMetadata::query(&self.client).await?;
-        self.display_result(&result, None, instantiated.contract, &token_metadata)
-            .await
+        self.display_result(&result, None, &instantiated.contract)
+            .await?;
+        Ok(Some(instantiated.contract))
     }
 
     async fn display_result(
         &self,
         result: &ExtrinsicEvents<DefaultConfig>,
         code_hash: Option<CodeHash>,
-        contract_address: subxt::utils::AccountId32,
-        token_metadata: &TokenMetadata,
+        contract_address: &subxt::utils::AccountId32,
     ) -> Result<(), ErrorVariant> {
         let events = DisplayEvents::from_events(
             result,
             Some(&self.transcoder),
             &self.client.metadata(),
+            &self.token_metadata,
         )?;
-        let contract_address = contract_address.to_string();
+        let display_instantiate_result = InstantiateResult {
+            code_hash: code_hash.map(|ch| format!("{ch:?}")),
+            contract: Some(contract_address.to_string()),
+            events,
+        };
+        session::record(&display_instantiate_result);
 
         if self.output_json {
-            let display_instantiate_result = InstantiateResult {
-                code_hash: code_hash.map(|ch| format!("{ch:?}")),
-                contract: Some(contract_address),
-                events,
-            };
             println!("{}", display_instantiate_result.to_json()?)
         } else {
-            println!("{}", events.display_events(self.verbosity, token_metadata)?);
+            println!(
+                "{}",
+                display_instantiate_result.events.display_events(
+                    self.verbosity,
+                    self.opts.number_format,
+                    self.opts.max_output_bytes
+                )?
+            );
             if let Some(code_hash) = code_hash {
                 name_value_println!("Code hash", format!("{code_hash:?}"));
             }
@@ -348,7 +690,12 @@ impl Exec {
     fn print_default_instantiate_preview(&self, gas_limit: Weight) {
         name_value_println!("Constructor", self.args.constructor, DEFAULT_KEY_COL_WIDTH);
         name_value_println!("Args", self.args.raw_args.join(" "), DEFAULT_KEY_COL_WIDTH);
+        let value = DisplayBalance::new(self.args.value, &self.token_metadata)
+            .map(|value| value.describe())
+            .unwrap_or_else(|_| self.args.value.to_string());
+        name_value_println!("Value", value, DEFAULT_KEY_COL_WIDTH);
         name_value_println!("Gas limit", gas_limit.to_string(), DEFAULT_KEY_COL_WIDTH);
+        self.sudo.print_details();
     }
 
     async fn instantiate_dry_run(
@@ -409,6 +756,8 @@ impl Exec {
                     name_value_println!("Result", object, MAX_KEY_COL_WIDTH);
                     display_contract_exec_result::<_, MAX_KEY_COL_WIDTH>(
                         &instantiate_result,
+                        &self.token_metadata,
+                        self.opts.number_format,
                     )?;
                     Err(anyhow!("Pre-submission dry-run failed. Use --skip-dry-run to skip this step."))
                 }
@@ -450,6 +799,10 @@ pub struct InstantiateDryRunResult {
     pub gas_required: Weight,
     /// Storage deposit after the operation
     pub storage_deposit: StorageDeposit,
+    /// The minimal value keeping the new contract alive, the existential deposit plus
+    /// the storage deposit charged for the instantiation
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub minimal_value: Option<DisplayBalance>,
 }
 
 impl InstantiateDryRunResult {
@@ -458,7 +811,7 @@ impl InstantiateDryRunResult {
         Ok(serde_json::to_string_pretty(self)?)
     }
 
-    pub fn print(&self) {
+    pub fn print(&self, number_format: NumberFormat) {
         name_value_println!("Result", self.result, DEFAULT_KEY_COL_WIDTH);
         name_value_println!("Contract", self.contract, DEFAULT_KEY_COL_WIDTH);
         name_value_println!(
@@ -467,26 +820,56 @@ impl InstantiateDryRunResult {
             DEFAULT_KEY_COL_WIDTH
         );
         name_value_println!("Data", format!("{:?}", self.data), DEFAULT_KEY_COL_WIDTH);
+        if let Some(ref minimal_value) = self.minimal_value {
+            name_value_println!(
+                "Min. value",
+                minimal_value.format(number_format),
+                DEFAULT_KEY_COL_WIDTH
+            );
+        }
     }
 }
 
 /// A struct that encodes RPC parameters required to instantiate a new smart contract.
 #[derive(Encode)]
-struct InstantiateRequest {
-    origin: <DefaultConfig as Config>::AccountId,
-    value: Balance,
-    gas_limit: Option<Weight>,
-    storage_deposit_limit: Option<Balance>,
-    code: Code,
-    data: Vec<u8>,
-    salt: Vec<u8>,
+pub(super) struct InstantiateRequest {
+    pub(super) origin: <DefaultConfig as Config>::AccountId,
+    pub(super) value: Balance,
+    pub(super) gas_limit: Option<Weight>,
+    pub(super) storage_deposit_limit: Option<Balance>,
+    pub(super) code: Code,
+    pub(super) data: Vec<u8>,
+    pub(super) salt: Vec<u8>,
 }
 
 /// Reference to an existing code hash or a new Wasm module.
 #[derive(Clone, Encode)]
-enum Code {
+pub(super) enum Code {
     /// A Wasm module as raw bytes.
     Upload(Vec<u8>),
     /// The code hash of an on-chain Wasm blob.
     Existing(<DefaultConfig as Config>::Hash),
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pallet_contracts_primitives::StorageDeposit;
+
+    #[test]
+    fn minimal_value_covers_existential_and_storage_deposits() {
+        assert_eq!("auto".parse::<Endowment>().unwrap(), Endowment::Auto);
+        assert_eq!(
+            "1_000".parse::<Endowment>().unwrap(),
+            Endowment::Value(BalanceVariant::Default(1000))
+        );
+        assert!("lots".parse::<Endowment>().is_err());
+
+        assert_eq!(minimal_value(500, &StorageDeposit::Charge(1200)), 1700);
+        assert_eq!(minimal_value(500, &StorageDeposit::Refund(1200)), 500);
+        assert_eq!(
+            minimal_value(Balance::MAX, &StorageDeposit::Charge(1)),
+            Balance::MAX
+        );
+    }
+}
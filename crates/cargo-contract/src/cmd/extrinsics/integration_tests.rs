@@ -155,7 +155,7 @@ pub fn init_tracing_subscriber() {
 /// Requires [`substrate-contracts-node`](https://github.com/paritytech/substrate-contracts-node/) to
 /// be installed and available on the `PATH`, and the no other process running using the
 /// default port `9944`.
-#[async_std::test]
+#[tokio::test]
 async fn build_upload_instantiate_call() {
     init_tracing_subscriber();
 
@@ -235,7 +235,7 @@ async fn build_upload_instantiate_call() {
 
 /// Sanity test the whole lifecycle of:
 /// build -> upload -> remove
-#[async_std::test]
+#[tokio::test]
 async fn build_upload_remove() {
     init_tracing_subscriber();
 
@@ -283,6 +283,7 @@ async fn build_upload_remove() {
         .arg("remove")
         .args(["--suri", "//Alice"])
         .args(["--code-hash", code_hash])
+        .args(["--execute", "--yes"])
         .output()
         .expect("failed to execute process");
     let stderr = str::from_utf8(&output.stderr).unwrap();
@@ -300,7 +301,7 @@ async fn build_upload_remove() {
 /// Requires [`substrate-contracts-node`](https://github.com/paritytech/substrate-contracts-node/) to
 /// be installed and available on the `PATH`, and the no other process running using the
 /// default port `9944`.
-#[async_std::test]
+#[tokio::test]
 async fn build_upload_instantiate_info() {
     init_tracing_subscriber();
 
@@ -0,0 +1,486 @@
+// Copyright 2018-2023 Parity Technologies (UK) Ltd.
+// This file is part of cargo-contract.
+//
+// cargo-contract is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// cargo-contract is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with cargo-contract.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Invariants of the state of a contract, checked after instantiating or upgrading it.
+//!
+//! The invariants file lists messages of the contract with a predicate on their return
+//! value, all messages are dry-run in a single batch request:
+//!
+//! ```yaml
+//! - name: supply covers the sampled balances
+//!   message: total_supply
+//!   expect: ">= sum(balance_of 5GrwvaEF5zXb26Fz9rcQpDWS57CtERHpNehXCPcNoHGKutQY; \
+//!     balance_of 5FHneW46xGXgs5mUiveU4sbTyGBzmstUspZC92UhjJM694ty)"
+//! - message: paused
+//!   expect: "== false"
+//! ```
+
+use super::{
+    call::CallRequest,
+    multicall::split_read,
+    state_call_batch,
+    ContractMessageTranscoder,
+    DefaultConfig,
+    ErrorVariant,
+    PairSigner,
+};
+use crate::{
+    cmd::Balance,
+    name_value_println,
+    DEFAULT_KEY_COL_WIDTH,
+};
+use anyhow::{
+    anyhow,
+    Context,
+    Result,
+};
+use contract_transcode::Value;
+use pallet_contracts_primitives::ContractExecResult;
+use std::{
+    fmt,
+    path::{
+        Path,
+        PathBuf,
+    },
+};
+use subxt::Config;
+
+type AccountId = <DefaultConfig as Config>::AccountId;
+
+/// Options for checking the invariants of a contract after the operation.
+#[derive(Debug, Clone, clap::Args)]
+pub struct InvariantOpts {
+    /// Check the invariants in the given YAML file, e.g. `invariants.yaml`, by
+    /// dry-running their messages after the extrinsic was submitted.
+    #[clap(long, value_name = "FILE")]
+    invariants: Option<PathBuf>,
+}
+
+impl InvariantOpts {
+    /// Load the invariants file, if one was given.
+    pub fn load(&self) -> Result<Option<Invariants>> {
+        self.invariants.as_deref().map(Invariants::load).transpose()
+    }
+}
+
+/// An entry of the invariants file.
+#[derive(Debug, serde::Deserialize)]
+#[serde(deny_unknown_fields)]
+struct InvariantSpec {
+    name: Option<String>,
+    message: String,
+    #[serde(default)]
+    args: Vec<String>,
+    expect: String,
+}
+
+/// A predicate on the return value of a message.
+#[derive(Debug)]
+struct Invariant {
+    name: String,
+    message: String,
+    args: Vec<String>,
+    op: Op,
+    operand: Operand,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(super) enum Op {
+    Eq,
+    Ne,
+    Le,
+    Ge,
+    Lt,
+    Gt,
+}
+
+impl Op {
+    /// The operators by their notation, two character operators first.
+    pub(super) const ALL: [(&'static str, Op); 6] = [
+        ("==", Op::Eq),
+        ("!=", Op::Ne),
+        ("<=", Op::Le),
+        (">=", Op::Ge),
+        ("<", Op::Lt),
+        (">", Op::Gt),
+    ];
+
+    pub(super) fn holds<T: PartialOrd>(self, lhs: T, rhs: T) -> bool {
+        match self {
+            Op::Eq => lhs == rhs,
+            Op::Ne => lhs != rhs,
+            Op::Le => lhs <= rhs,
+            Op::Ge => lhs >= rhs,
+            Op::Lt => lhs < rhs,
+            Op::Gt => lhs > rhs,
+        }
+    }
+}
+
+impl fmt::Display for Op {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let (notation, _) = Op::ALL
+            .iter()
+            .find(|(_, op)| op == self)
+            .expect("all operators have a notation; qed");
+        f.write_str(notation)
+    }
+}
+
+/// The right hand side of a predicate.
+#[derive(Debug, PartialEq, Eq)]
+enum Operand {
+    /// A value, compared numerically if it is a number.
+    Literal(String),
+    /// The sum of the return values of the messages with their arguments.
+    Sum(Vec<(String, Vec<String>)>),
+}
+
+impl Invariant {
+    fn from_spec(spec: InvariantSpec) -> Result<Self> {
+        let expect = spec.expect.trim();
+        let (notation, op) = Op::ALL
+            .iter()
+            .find(|(notation, _)| expect.starts_with(notation))
+            .ok_or_else(|| {
+                anyhow!(
+                    "`{expect}` must start with one of the operators ==, !=, <=, >=, < or >"
+                )
+            })?;
+        let operand = expect[notation.len()..].trim();
+        let operand = match operand
+            .strip_prefix("sum(")
+            .and_then(|calls| calls.strip_suffix(')'))
+        {
+            Some(calls) => {
+                Operand::Sum(
+                    calls
+                        .split(';')
+                        .map(split_read)
+                        .collect::<Result<Vec<_>>>()?,
+                )
+            }
+            None => Operand::Literal(operand.to_string()),
+        };
+        let name = spec.name.unwrap_or_else(|| {
+            std::iter::once(&spec.message)
+                .chain(&spec.args)
+                .cloned()
+                .collect::<Vec<_>>()
+                .join(" ")
+        });
+        Ok(Self {
+            name,
+            message: spec.message,
+            args: spec.args,
+            op: *op,
+            operand,
+        })
+    }
+
+    /// The messages to dry-run for checking the invariant, starting with its own.
+    fn calls(&self) -> Vec<(&str, &[String])> {
+        let mut calls = vec![(self.message.as_str(), self.args.as_slice())];
+        if let Operand::Sum(ref sum) = self.operand {
+            calls.extend(
+                sum.iter()
+                    .map(|(message, args)| (message.as_str(), args.as_slice())),
+            );
+        }
+        calls
+    }
+
+    /// Evaluate the invariant on the return values of its messages, returns why it does
+    /// not hold if it is violated.
+    fn evaluate(&self, value: &Value, operands: &[Value]) -> Result<(), String> {
+        let value = unwrap_ok(value);
+        let number = |value: &Value| {
+            as_number(value)
+                .ok_or_else(|| format!("The return value {value} is not a number"))
+        };
+        let (lhs, rhs, holds) = match self.operand {
+            Operand::Sum(_) => {
+                let sum = operands.iter().try_fold(0i128, |sum, operand| {
+                    sum.checked_add(number(unwrap_ok(operand))?)
+                        .ok_or_else(|| String::from("The sum overflows"))
+                })?;
+                let lhs = number(value)?;
+                (lhs.to_string(), sum.to_string(), self.op.holds(lhs, sum))
+            }
+            Operand::Literal(ref literal) => {
+                match literal.replace('_', "").parse::<i128>() {
+                    Ok(rhs) => {
+                        let lhs = number(value)?;
+                        (lhs.to_string(), rhs.to_string(), self.op.holds(lhs, rhs))
+                    }
+                    Err(_) => {
+                        let strip = |s: &str| s.split_whitespace().collect::<String>();
+                        let equal = strip(&value.to_string()) == strip(literal);
+                        let holds = match self.op {
+                            Op::Eq => equal,
+                            Op::Ne => !equal,
+                            op => {
+                                return Err(format!(
+                                    "`{op}` requires a number, not `{literal}`"
+                                ))
+                            }
+                        };
+                        (value.to_string(), literal.clone(), holds)
+                    }
+                }
+            }
+        };
+        if holds {
+            Ok(())
+        } else {
+            Err(format!("{lhs} {} {rhs} does not hold", self.op))
+        }
+    }
+}
+
+/// Unwrap the `Ok` of the `Result` wrapping the return values of ink! messages.
+pub(super) fn unwrap_ok(value: &Value) -> &Value {
+    match value {
+        Value::Tuple(tuple) if tuple.ident().as_deref() == Some("Ok") => {
+            let mut values = tuple.values();
+            match (values.next(), values.next()) {
+                (Some(value), None) => unwrap_ok(value),
+                _ => value,
+            }
+        }
+        value => value,
+    }
+}
+
+pub(super) fn as_number(value: &Value) -> Option<i128> {
+    match *value {
+        Value::UInt(n) => i128::try_from(n).ok(),
+        Value::Int(n) => Some(n),
+        _ => None,
+    }
+}
+
+/// The invariants of a contract.
+#[derive(Debug)]
+pub struct Invariants {
+    invariants: Vec<Invariant>,
+}
+
+/// The outcome of checking an invariant.
+#[derive(Debug, serde::Serialize)]
+pub struct InvariantResult {
+    /// The name of the invariant.
+    pub name: String,
+    /// Does the invariant hold.
+    pub holds: bool,
+    /// Why the invariant is violated.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+impl Invariants {
+    /// Load the invariants from the YAML file at the given path.
+    pub fn load(path: &Path) -> Result<Self> {
+        let contents = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read {}", path.display()))?;
+        Self::parse(&contents).with_context(|| format!("Invalid {}", path.display()))
+    }
+
+    fn parse(contents: &str) -> Result<Self> {
+        let specs: Vec<InvariantSpec> = serde_yaml::from_str(contents)?;
+        let invariants = specs
+            .into_iter()
+            .map(Invariant::from_spec)
+            .collect::<Result<_>>()?;
+        Ok(Self { invariants })
+    }
+
+    /// Dry-run the messages of the invariants on the contract and evaluate them. Fails
+    /// if any invariant is violated.
+    pub async fn check(
+        &self,
+        url: &str,
+        signer: &PairSigner,
+        contract: &AccountId,
+        transcoder: &ContractMessageTranscoder,
+        storage_deposit_limit: Option<Balance>,
+        output_json: bool,
+    ) -> Result<Vec<InvariantResult>, ErrorVariant> {
+        let calls = self
+            .invariants
+            .iter()
+            .flat_map(Invariant::calls)
+            .collect::<Vec<_>>();
+        let requests = calls
+            .iter()
+            .map(|(message, args)| {
+                Ok(CallRequest {
+                    origin: signer.account_id().clone(),
+                    dest: contract.clone(),
+                    value: 0,
+                    gas_limit: None,
+                    storage_deposit_limit,
+                    input_data: transcoder.encode(message, *args)?,
+                })
+            })
+            .collect::<Result<Vec<_>>>()?;
+        let responses: Vec<Result<ContractExecResult<Balance>>> =
+            state_call_batch(url, "ContractsApi_call", requests).await?;
+        let mut values = calls.iter().zip(responses).map(|((message, _), response)| {
+            let ret_val = response
+                .map_err(|err| format!("{err:#}"))?
+                .result
+                .map_err(|err| format!("`{message}` failed: {err:?}"))?;
+            if ret_val.did_revert() {
+                return Err(format!("`{message}` reverted"))
+            }
+            transcoder
+                .decode_return(message, &mut &ret_val.data[..])
+                .map_err(|err| format!("{err:#}"))
+        });
+
+        let mut results = Vec::new();
+        for invariant in &self.invariants {
+            let invariant_values = values
+                .by_ref()
+                .take(invariant.calls().len())
+                .collect::<Result<Vec<_>, _>>();
+            let outcome = invariant_values.and_then(|values| {
+                let (value, operands) = values
+                    .split_first()
+                    .expect("an invariant has a message; qed");
+                invariant.evaluate(value, operands)
+            });
+            results.push(InvariantResult {
+                name: invariant.name.clone(),
+                holds: outcome.is_ok(),
+                error: outcome.err(),
+            });
+        }
+
+        if !output_json {
+            for result in &results {
+                result.print();
+            }
+        }
+        let violated = results
+            .iter()
+            .filter(|result| !result.holds)
+            .map(|result| {
+                format!("{}: {}", result.name, result.error.as_deref().unwrap_or(""))
+            })
+            .collect::<Vec<_>>();
+        if !violated.is_empty() {
+            return Err(anyhow!(
+                "{} of {} invariants of the contract {contract} are violated: {}",
+                violated.len(),
+                results.len(),
+                violated.join(", ")
+            )
+            .into())
+        }
+        Ok(results)
+    }
+}
+
+impl InvariantResult {
+    pub fn print(&self) {
+        let outcome = if self.holds { "holds" } else { "violated" };
+        name_value_println!(
+            "Invariant",
+            format!("{} ({outcome})", self.name),
+            DEFAULT_KEY_COL_WIDTH
+        );
+        if let Some(ref error) = self.error {
+            name_value_println!("Error", error, DEFAULT_KEY_COL_WIDTH);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use contract_transcode::Tuple;
+
+    fn ok(value: Value) -> Value {
+        Value::Tuple(Tuple::new(Some("Ok"), vec![value]))
+    }
+
+    #[test]
+    fn invariants_file_is_parsed() {
+        let invariants = Invariants::parse(
+            r#"
+            - name: supply covers balances
+              message: total_supply
+              expect: ">= sum(balance_of 5Grw; balance_of 5FHn)"
+            - message: balance_of
+              args: [5Grw]
+              expect: "!= 0"
+            "#,
+        )
+        .expect("invariants must be valid");
+        let [supply, balance] = &invariants.invariants[..] else {
+            panic!("two invariants expected")
+        };
+
+        assert_eq!(supply.name, "supply covers balances");
+        assert_eq!(supply.op, Op::Ge);
+        assert_eq!(
+            supply.calls(),
+            vec![
+                ("total_supply", &[][..]),
+                ("balance_of", &[String::from("5Grw")][..]),
+                ("balance_of", &[String::from("5FHn")][..]),
+            ]
+        );
+        assert_eq!(balance.name, "balance_of 5Grw");
+        assert_eq!(balance.operand, Operand::Literal(String::from("0")));
+        assert!(Invariants::parse("- message: get\n  expect: \"~ 1\"").is_err());
+    }
+
+    #[test]
+    fn invariants_are_evaluated() {
+        let parse = |expect: &str| {
+            Invariant::from_spec(InvariantSpec {
+                name: None,
+                message: String::from("get"),
+                args: Vec::new(),
+                expect: expect.to_string(),
+            })
+            .unwrap()
+        };
+
+        let sum = parse(">= sum(a; b)");
+        assert!(sum
+            .evaluate(
+                &ok(Value::UInt(30)),
+                &[ok(Value::UInt(10)), Value::UInt(20)]
+            )
+            .is_ok());
+        assert_eq!(
+            sum.evaluate(&Value::UInt(29), &[Value::UInt(10), Value::UInt(20)]),
+            Err(String::from("29 >= 30 does not hold"))
+        );
+        assert!(parse("> 1_000").evaluate(&Value::UInt(1001), &[]).is_ok());
+        assert!(parse("== false")
+            .evaluate(&ok(Value::Bool(false)), &[])
+            .is_ok());
+        assert!(parse("!= false")
+            .evaluate(&ok(Value::Bool(false)), &[])
+            .is_err());
+        assert!(parse("< true").evaluate(&Value::Bool(false), &[]).is_err());
+    }
+}
@@ -0,0 +1,465 @@
+// Copyright 2018-2023 Parity Technologies (UK) Ltd.
+// This file is part of cargo-contract.
+//
+// cargo-contract is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// cargo-contract is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with cargo-contract.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Registration of the delegate dependencies of a contract.
+//!
+//! A contract delegating calls to uploaded code, e.g. a proxy instantiated with
+//! `instantiate_with_code_hash`, locks the code as a delegate dependency so that it can
+//! not be removed. Dependencies are added and removed from within the contract, with the
+//! `lock_delegate_dependency` and `unlock_delegate_dependency` host functions, so the
+//! `link-dependency` command calls a message of the contract doing so with the code hash
+//! as its only argument. Afterwards the dependencies of the contract are read from the
+//! chain, to check that the dependency was registered and to report its deposit.
+
+use super::{
+    call::CallRequest,
+    display_dry_run_result_warning,
+    parse_code_hash,
+    state_call,
+    submit_extrinsic,
+    Client,
+    ContractMessageTranscoder,
+    DefaultConfig,
+    DisplayBalance,
+    ErrorVariant,
+    ExtrinsicOpts,
+    PairSigner,
+    TokenMetadata,
+};
+use crate::{
+    cmd::{
+        extrinsics::events::DisplayEvents,
+        identity::{
+            collect_bytes,
+            Identities,
+        },
+        info::fetch_code_owner,
+        runtime_api::api,
+        AsyncCommand,
+        Balance,
+        CodeHash,
+    },
+    name_value_println,
+    DEFAULT_KEY_COL_WIDTH,
+};
+use anyhow::{
+    anyhow,
+    Result,
+};
+use async_trait::async_trait;
+use pallet_contracts_primitives::{
+    ContractExecResult,
+    StorageDeposit,
+};
+use sp_weights::Weight;
+use std::collections::BTreeMap;
+use subxt::{
+    dynamic::Value,
+    ext::scale_value::{
+        At,
+        ValueDef,
+    },
+    Config,
+    OnlineClient,
+};
+
+type AccountId = <DefaultConfig as Config>::AccountId;
+
+/// The default message registering a delegate dependency.
+const DEFAULT_ADD_MESSAGE: &str = "add_delegate_dependency";
+/// The default message unregistering a delegate dependency.
+const DEFAULT_REMOVE_MESSAGE: &str = "remove_delegate_dependency";
+
+#[derive(Debug, clap::Args)]
+#[clap(
+    name = "link-dependency",
+    about = "Register or unregister a delegate dependency of a contract"
+)]
+pub struct LinkDependencyCommand {
+    /// The address of the contract delegating calls to the code.
+    #[clap(name = "contract", long, env = "CONTRACT")]
+    contract: AccountId,
+    /// The hash of the uploaded code the contract delegates calls to.
+    #[clap(long, value_parser = parse_code_hash)]
+    code_hash: CodeHash,
+    /// Unregister the dependency, which refunds its deposit.
+    #[clap(long)]
+    unlink: bool,
+    /// The message of the contract adding or removing the dependency, called with the
+    /// code hash as its only argument. Defaults to `add_delegate_dependency`, or to
+    /// `remove_delegate_dependency` with `--unlink`.
+    #[clap(long, short)]
+    message: Option<String>,
+    #[clap(flatten)]
+    extrinsic_opts: ExtrinsicOpts,
+    /// Maximum amount of gas (execution time) to be used for the call.
+    /// If not specified will perform a dry-run to estimate the gas consumed.
+    #[clap(name = "gas", long)]
+    gas_limit: Option<u64>,
+    /// Maximum proof size for the call.
+    /// If not specified will perform a dry-run to estimate the proof size required.
+    #[clap(long)]
+    proof_size: Option<u64>,
+    /// Export the output in JSON format.
+    #[clap(long, conflicts_with = "verbose")]
+    output_json: bool,
+}
+
+/// The delegate dependency of a contract after it was linked or unlinked.
+#[derive(Debug, serde::Serialize)]
+pub struct DependencyReport {
+    pub contract: AccountId,
+    pub code_hash: CodeHash,
+    /// Whether the code is a dependency of the contract.
+    pub linked: bool,
+    /// The deposit held for the dependency, or refunded when it was unlinked.
+    pub deposit: DisplayBalance,
+    /// The number of delegate dependencies of the contract.
+    pub dependencies: usize,
+}
+
+impl LinkDependencyCommand {
+    pub fn is_json(&self) -> bool {
+        self.output_json
+    }
+
+    fn message(&self) -> &str {
+        match self.message {
+            Some(ref message) => message,
+            None if self.unlink => DEFAULT_REMOVE_MESSAGE,
+            None => DEFAULT_ADD_MESSAGE,
+        }
+    }
+
+    /// Dry-run the call, returns the gas limit for submitting it and the storage deposit
+    /// estimated by the dry-run.
+    async fn dry_run(
+        &self,
+        client: &Client,
+        url: &str,
+        input_data: Vec<u8>,
+        signer: &PairSigner,
+        transcoder: &ContractMessageTranscoder,
+    ) -> Result<(Weight, Option<StorageDeposit<Balance>>), ErrorVariant> {
+        if self.extrinsic_opts.skip_dry_run {
+            return match (self.gas_limit, self.proof_size) {
+                (Some(ref_time), Some(proof_size)) => {
+                    Ok((Weight::from_parts(ref_time, proof_size), None))
+                }
+                _ => {
+                    Err(anyhow!(
+                        "Weight args `--gas` and `--proof-size` required if \
+                        `--skip-dry-run` specified"
+                    )
+                    .into())
+                }
+            }
+        }
+        if !self.output_json {
+            super::print_dry_running_status(self.message());
+        }
+        let call_request = CallRequest {
+            origin: signer.account_id().clone(),
+            dest: self.contract.clone(),
+            value: 0,
+            gas_limit: None,
+            storage_deposit_limit: None,
+            input_data,
+        };
+        let result: ContractExecResult<Balance> =
+            state_call(url, "ContractsApi_call", call_request).await?;
+        match result.result {
+            Ok(ref ret_val) if ret_val.did_revert() => {
+                let value = transcoder
+                    .decode_return(self.message(), &mut &ret_val.data[..])
+                    .map(|value| value.to_string())
+                    .unwrap_or_else(|_| format!("0x{}", hex::encode(&ret_val.data)));
+                Err(ErrorVariant::revert(self.message(), value))
+            }
+            Ok(_) => {
+                if !self.output_json {
+                    super::print_gas_required_success(result.gas_required);
+                }
+                let gas_limit = Weight::from_parts(
+                    self.gas_limit
+                        .unwrap_or_else(|| result.gas_required.ref_time()),
+                    self.proof_size
+                        .unwrap_or_else(|| result.gas_required.proof_size()),
+                );
+                Ok((gas_limit, Some(result.storage_deposit)))
+            }
+            Err(ref err) => {
+                Err(ErrorVariant::from_dispatch_error(err, &client.metadata())?)
+            }
+        }
+    }
+}
+
+#[async_trait(?Send)]
+impl AsyncCommand for LinkDependencyCommand {
+    type Output = Result<(), ErrorVariant>;
+
+    async fn exec(&self) -> Result<(), ErrorVariant> {
+        self.extrinsic_opts.apply_process_opts()?;
+        let artifacts = self.extrinsic_opts.contract_artifacts()?;
+        let identities = Identities::default();
+        let transcoder = artifacts
+            .contract_transcoder_with_identities(&identities)?
+            .with_bytes_format(self.extrinsic_opts.bytes_format());
+        let code_hash = self.code_hash;
+        let data = transcoder.encode(self.message(), [format!("{code_hash:?}")])?;
+        let signer = super::pair_signer(self.extrinsic_opts.signer("link-dependency")?);
+        let url = self.extrinsic_opts.url_to_string()?;
+
+        let client = OnlineClient::from_url(&url).await?;
+        identities.resolve(&client).await?;
+        crate::cmd::chain_env::ensure_supported_chain(&client)?;
+        let dependencies = fetch_delegate_dependencies(&client, &self.contract).await?;
+        let previous_deposit = dependencies.get(&code_hash).copied();
+        match previous_deposit {
+            Some(_) if !self.unlink => {
+                return Err(anyhow!(
+                    "The code {code_hash:?} is already a delegate dependency of the \
+                    contract {}",
+                    self.contract
+                )
+                .into())
+            }
+            None if self.unlink => {
+                return Err(anyhow!(
+                    "The code {code_hash:?} is not a delegate dependency of the \
+                    contract {}",
+                    self.contract
+                )
+                .into())
+            }
+            _ => (),
+        }
+        if !self.unlink && fetch_code_owner(&client, code_hash).await?.is_none() {
+            return Err(anyhow!(
+                "The code {code_hash:?} is not uploaded, upload it first with \
+                `cargo contract upload`"
+            )
+            .into())
+        }
+        let token_metadata = TokenMetadata::query(&client).await?;
+        let (gas_limit, storage_deposit) = self
+            .dry_run(&client, &url, data.clone(), &signer, &transcoder)
+            .await?;
+
+        let action = if self.unlink { "Unlink" } else { "Link" };
+        let print_details = || {
+            name_value_println!(
+                "Contract",
+                format!("{}", self.contract),
+                DEFAULT_KEY_COL_WIDTH
+            );
+            name_value_println!(action, format!("{code_hash:?}"), DEFAULT_KEY_COL_WIDTH);
+            name_value_println!("Message", self.message(), DEFAULT_KEY_COL_WIDTH);
+            name_value_println!(
+                "Gas limit",
+                gas_limit.to_string(),
+                DEFAULT_KEY_COL_WIDTH
+            );
+        };
+        if !self.extrinsic_opts.execute()? {
+            print_details();
+            if let Some(ref storage_deposit) = storage_deposit {
+                let (key, amount) = match storage_deposit {
+                    StorageDeposit::Charge(amount) => ("Deposit charged", amount),
+                    StorageDeposit::Refund(amount) => ("Deposit refunded", amount),
+                };
+                name_value_println!(
+                    key,
+                    DisplayBalance::new(*amount, &token_metadata)?
+                        .format(self.extrinsic_opts.number_format),
+                    DEFAULT_KEY_COL_WIDTH
+                );
+            }
+            display_dry_run_result_warning("link-dependency");
+            return Ok(())
+        }
+        self.extrinsic_opts.confirm(print_details)?;
+
+        let call = api::tx().contracts().call(
+            self.contract.clone().into(),
+            0,
+            gas_limit.into(),
+            self.extrinsic_opts.storage_deposit_limit(&token_metadata)?,
+            data,
+        );
+        let submit_options = self.extrinsic_opts.submit_options(&token_metadata)?;
+        let result = submit_extrinsic(&client, &call, &signer, &submit_options).await?;
+
+        let dependencies = fetch_delegate_dependencies(&client, &self.contract).await?;
+        let deposit = match (dependencies.get(&code_hash), previous_deposit) {
+            (Some(deposit), None) => *deposit,
+            (None, Some(refund)) => refund,
+            _ => {
+                return Err(anyhow!(
+                    "The `{}` message succeeded, but did not {} the code {code_hash:?} \
+                    as a delegate dependency of the contract",
+                    self.message(),
+                    if self.unlink { "remove" } else { "add" }
+                )
+                .into())
+            }
+        };
+        let report = DependencyReport {
+            contract: self.contract.clone(),
+            code_hash,
+            linked: !self.unlink,
+            deposit: DisplayBalance::new(deposit, &token_metadata)?,
+            dependencies: dependencies.len(),
+        };
+
+        let display_events = DisplayEvents::from_events(
+            &result,
+            Some(&transcoder),
+            &client.metadata(),
+            &token_metadata,
+        )?;
+        if self.output_json {
+            let output = serde_json::json!({
+                "events": display_events,
+                "dependency": report,
+            });
+            println!(
+                "{}",
+                serde_json::to_string_pretty(&output).map_err(anyhow::Error::from)?
+            );
+        } else {
+            println!(
+                "{}",
+                display_events.display_events(
+                    self.extrinsic_opts.verbosity()?,
+                    self.extrinsic_opts.number_format,
+                    self.extrinsic_opts.max_output_bytes,
+                )?
+            );
+            let deposit_key = if self.unlink {
+                "Deposit refunded"
+            } else {
+                "Deposit held"
+            };
+            name_value_println!(
+                deposit_key,
+                report.deposit.format(self.extrinsic_opts.number_format),
+                DEFAULT_KEY_COL_WIDTH
+            );
+            name_value_println!(
+                "Dependencies",
+                report.dependencies.to_string(),
+                DEFAULT_KEY_COL_WIDTH
+            );
+        }
+        Ok(())
+    }
+}
+
+/// Fetch the delegate dependencies of the contract with their deposits.
+///
+/// The contract info is decoded with the metadata of the chain, since the dependencies
+/// are missing from the info of older versions of `pallet-contracts`.
+pub async fn fetch_delegate_dependencies(
+    client: &Client,
+    contract: &AccountId,
+) -> Result<BTreeMap<CodeHash, Balance>> {
+    let address = subxt::dynamic::storage(
+        "Contracts",
+        "ContractInfoOf",
+        vec![Value::from_bytes(contract.0)],
+    );
+    let info = client
+        .storage()
+        .at_latest()
+        .await?
+        .fetch(&address)
+        .await?
+        .ok_or_else(|| anyhow!("No contract found at {contract}"))?;
+    delegate_dependencies(&info.to_value()?).ok_or_else(|| {
+        anyhow!("The chain does not support delegate dependencies of contracts")
+    })
+}
+
+/// Extract the delegate dependencies from the info of a contract, `None` if the info has
+/// no dependencies field.
+fn delegate_dependencies<T>(info: &Value<T>) -> Option<BTreeMap<CodeHash, Balance>> {
+    let mut dependencies = BTreeMap::new();
+    collect_dependencies(info.at("delegate_dependencies")?, &mut dependencies);
+    Some(dependencies)
+}
+
+/// The bounded map of dependencies is a composite wrapping a sequence of tuples of the
+/// code hash and the deposit.
+fn collect_dependencies<T>(
+    value: &Value<T>,
+    dependencies: &mut BTreeMap<CodeHash, Balance>,
+) {
+    let ValueDef::Composite(ref composite) = value.value else {
+        return
+    };
+    let fields = composite.values().collect::<Vec<_>>();
+    if let [code_hash, deposit] = fields[..] {
+        let mut bytes = Vec::new();
+        collect_bytes(code_hash, &mut bytes);
+        if let (Ok(bytes), Some(deposit)) =
+            (<[u8; 32]>::try_from(bytes), deposit.as_u128())
+        {
+            dependencies.insert(CodeHash::from(bytes), deposit);
+            return
+        }
+    }
+    for field in fields {
+        collect_dependencies(field, dependencies)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(byte: u8, deposit: u128) -> Value {
+        let code_hash = Value::unnamed_composite([Value::from_bytes([byte; 32])]);
+        Value::unnamed_composite([code_hash, Value::u128(deposit)])
+    }
+
+    #[test]
+    fn delegate_dependencies_are_extracted_from_the_contract_info() {
+        let info = |dependencies: Vec<Value>| {
+            Value::named_composite([
+                ("trie_id", Value::from_bytes([7; 16])),
+                ("code_hash", Value::from_bytes([9; 32])),
+                ("storage_base_deposit", Value::u128(1000)),
+                (
+                    "delegate_dependencies",
+                    Value::unnamed_composite([Value::unnamed_composite(dependencies)]),
+                ),
+            ])
+        };
+
+        assert_eq!(delegate_dependencies(&info(vec![])), Some(BTreeMap::new()));
+        assert_eq!(
+            delegate_dependencies(&info(vec![entry(1, 300), entry(2, 5000)])),
+            Some(BTreeMap::from([
+                (CodeHash::from([1; 32]), 300),
+                (CodeHash::from([2; 32]), 5000),
+            ]))
+        );
+        let legacy = Value::named_composite([("code_hash", Value::from_bytes([9; 32]))]);
+        assert_eq!(delegate_dependencies(&legacy), None);
+    }
+}
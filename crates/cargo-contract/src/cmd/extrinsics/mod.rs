@@ -14,13 +14,46 @@
 // You should have received a copy of the GNU General Public License
 // along with cargo-contract.  If not, see <http://www.gnu.org/licenses/>.
 
+mod account;
+#[cfg(feature = "activity-cache")]
+mod activity_cache;
+mod artifacts;
+#[cfg(feature = "astar")]
+pub mod astar;
+mod attest;
 mod balance;
 mod call;
+mod canary;
+mod contract_events;
+mod deploy;
+mod deployments;
 mod error;
+mod estimate;
+mod event_signatures;
 mod events;
+mod history;
+mod host_functions;
 mod instantiate;
+mod invariants;
+mod link_dependency;
+mod multicall;
+mod offline;
+pub mod phala;
+mod read_cache;
+mod relayer;
 mod remove;
+mod rent;
+mod salt;
+mod senders;
+mod signer;
+mod simulate;
+mod storage_clear;
+mod sudo;
+mod transfer_code;
+mod upgrade;
+mod upgrade_wizard;
 mod upload;
+mod wait_until;
 
 #[cfg(test)]
 #[cfg(feature = "integration-tests")]
@@ -34,7 +67,10 @@ use anyhow::{
 };
 use colored::Colorize;
 use jsonrpsee::{
-    core::client::ClientT,
+    core::{
+        client::ClientT,
+        params::BatchRequestBuilder,
+    },
     rpc_params,
     ws_client::WsClientBuilder,
 };
@@ -44,10 +80,17 @@ use std::{
         Write,
     },
     path::PathBuf,
+    time::Duration,
 };
 
 use crate::{
     cmd::{
+        config::{
+            Backend,
+            NetworkProfile,
+            SignerKind,
+        },
+        output::DEFAULT_MAX_OUTPUT_BYTES,
         Balance,
         Client,
     },
@@ -56,7 +99,6 @@ use crate::{
 
 use contract_build::{
     name_value_println,
-    CrateMetadata,
     Verbosity,
     VerbosityFlags,
 };
@@ -73,71 +115,221 @@ use sp_core::{
 use sp_weights::Weight;
 use subxt::{
     blocks,
-    config,
-    tx,
+    config::{
+        polkadot::{
+            Era,
+            PlainTip,
+            PolkadotExtrinsicParamsBuilder,
+        },
+        ExtrinsicParams,
+    },
+    error::{
+        RpcError,
+        TransactionError,
+    },
+    tx::{
+        self,
+        TxStatus,
+    },
+    utils::AccountId32,
     Config,
-    OnlineClient,
 };
 
-use std::{
-    option::Option,
-    path::Path,
-};
+use std::option::Option;
 
+pub(crate) use account::ensure_mapped;
+pub use account::AccountCommand;
+#[cfg(feature = "activity-cache")]
+pub(crate) use activity_cache::ActivityCache;
+pub use artifacts::{
+    ContractArtifacts,
+    WasmCode,
+};
+#[cfg(feature = "astar")]
+pub use astar::AstarCommand;
+pub use attest::AttestCommand;
+pub(crate) use attest::{
+    compute_metadata_hash,
+    verify_attestation,
+};
 pub use balance::{
+    disable_denomination,
     BalanceVariant,
+    DisplayBalance,
+    NumberFormat,
     TokenMetadata,
 };
 pub use call::CallCommand;
-use contract_metadata::ContractMetadata;
+use contract_transcode::BytesFormat;
 pub use contract_transcode::ContractMessageTranscoder;
+pub use deploy::DeployCommand;
 pub use error::ErrorVariant;
+pub use estimate::EstimateCommand;
+#[cfg(feature = "activity-cache")]
+pub(crate) use events::EventDecoder;
+pub use history::EventsCommand;
+#[cfg(feature = "activity-cache")]
+pub(crate) use history::{
+    cache_blocks,
+    DashboardCommand,
+};
+pub(crate) use history::{
+    code_hash_history,
+    CodeHashChange,
+};
 pub use instantiate::InstantiateCommand;
+pub use link_dependency::LinkDependencyCommand;
+pub use multicall::MulticallCommand;
+use offline::ExtrinsicSigner;
+pub use offline::SubmitCommand;
+use phala::PhalaBackend;
 pub use remove::RemoveCommand;
+pub use rent::RentCommand;
+pub(crate) use rent::{
+    fetch_legacy_contract_info,
+    storage_rent_version,
+};
+pub(crate) use signer::unlock_keystore;
+use signer::{
+    SignerSpec,
+    SigningKey,
+};
+pub use storage_clear::ClearStorageCommand;
 pub use subxt::PolkadotConfig as DefaultConfig;
+pub(crate) use transfer_code::verify_transfer;
+pub use transfer_code::TransferCodeCommand;
+pub use upgrade::UpgradeCommand;
+pub(crate) use upgrade::{
+    UpgradeReceipt,
+    DEFAULT_RECEIPT_DIR,
+};
 pub use upload::UploadCommand;
+pub use wait_until::WaitUntilCommand;
 
-type PairSigner = tx::PairSigner<DefaultConfig, sr25519::Pair>;
+type PairSigner = signer::KeySigner;
 
 /// Arguments required for creating and sending an extrinsic to a substrate node.
 #[derive(Clone, Debug, clap::Args)]
 pub struct ExtrinsicOpts {
     /// Path to a contract build artifact file: a raw `.wasm` file, a `.contract` bundle,
-    /// or a `.json` metadata file.
+    /// a zstd compressed `.contract.zst` bundle, or a `.json` metadata file. Also
+    /// accepts the hash of uploaded code whose metadata is recorded in the registry, or
+    /// the URL of an artifact file.
     #[clap(value_parser, conflicts_with = "manifest_path")]
     file: Option<PathBuf>,
     /// Path to the `Cargo.toml` of the contract.
     #[clap(long, value_parser)]
     manifest_path: Option<PathBuf>,
-    /// Websockets url of a substrate node.
+    /// Websockets url of a substrate node. Defaults to the url of the `--network`
+    /// profile, or to `ws://localhost:9944`.
+    #[clap(name = "url", long, value_parser)]
+    url: Option<url::Url>,
+    /// The name of a network profile from the `cargo-contract.toml` configuration file,
+    /// or of a chain of the catalogue of `cargo contract chains`.
+    #[clap(long, env = "CARGO_CONTRACT_NETWORK")]
+    network: Option<String>,
+    /// Secret key URI for the account deploying the contract.
     #[clap(
-        name = "url",
+        name = "suri",
         long,
-        value_parser,
-        default_value = "ws://localhost:9944"
+        short,
+        required_unless_present_any = ["offline", "signer"]
     )]
-    url: url::Url,
-    /// Secret key URI for the account deploying the contract.
-    #[clap(name = "suri", long, short)]
-    suri: String,
-    /// Password for the secret key.
+    suri: Option<String>,
+    /// The signer of the extrinsics instead of `--suri`: `suri:<SURI>`,
+    /// `keystore:<PATH>` for a key file of a Substrate keystore,
+    /// `remote:<ACCOUNT>@<URL>` for a remote signing service, or `ledger`.
+    #[clap(long, value_name = "SIGNER", conflicts_with = "suri")]
+    signer: Option<SignerSpec>,
+    /// Password for the secret key, or of the keystore of `--signer keystore:<PATH>`.
     #[clap(name = "password", long, short)]
     password: Option<String>,
+    /// The role the extrinsics are submitted in, which must be allowed to call the
+    /// message on the network by the signed `permissions.toml` file.
+    #[clap(long, env = "CARGO_CONTRACT_ROLE")]
+    role: Option<String>,
     #[clap(flatten)]
     verbosity: VerbosityFlags,
-    /// Submit the extrinsic for on-chain execution.
+    /// Submit the extrinsic for on-chain execution: it is dry-run, confirmed and
+    /// submitted.
     #[clap(short('x'), long)]
     execute: bool,
+    /// Only dry-run the extrinsic, also when `--execute` is given, e.g. by an alias.
+    #[clap(long, conflicts_with = "skip_dry_run")]
+    dry_run: bool,
     /// The maximum amount of balance that can be charged from the caller to pay for the
     /// storage. consumed.
     #[clap(long)]
     storage_deposit_limit: Option<BalanceVariant>,
     /// Before submitting a transaction, do not dry-run it via RPC first.
-    #[clap(long)]
+    #[clap(long, requires = "execute")]
     skip_dry_run: bool,
     /// Before submitting a transaction, do not ask the user for confirmation.
-    #[clap(long)]
+    #[clap(long, short = 'y', visible_alias = "yes")]
     skip_confirm: bool,
+    /// The notation used to display balances in the human readable output.
+    #[clap(long, value_enum, default_value_t)]
+    number_format: NumberFormat,
+    /// Display balances denominated by the token of the chain, e.g. `1.5mDOT`. With
+    /// `--denominate=false` they are displayed in planck, the smallest unit.
+    #[clap(
+        long,
+        value_name = "BOOL",
+        default_value_t = true,
+        num_args = 0..=1,
+        default_missing_value = "true",
+        action = clap::ArgAction::Set
+    )]
+    denominate: bool,
+    /// Display bytes in decoded return values and events as raw sequences of integers,
+    /// instead of as UTF-8 strings, hex or SS58 addresses.
+    #[clap(long)]
+    raw_bytes: bool,
+    /// Truncate decoded values larger than the given number of bytes in the human
+    /// readable output, `0` to never truncate.
+    #[clap(long, value_name = "BYTES", default_value_t = DEFAULT_MAX_OUTPUT_BYTES)]
+    max_output_bytes: usize,
+    /// Forbid submitting any extrinsic: commands are only dry-run, even with
+    /// `--execute`. Can also be enabled with the `read-only` configuration setting.
+    #[clap(long, env = READ_ONLY_ENV)]
+    dry_run_only: bool,
+    /// Submit a mortal transaction, which is only valid for the given number of blocks.
+    /// The period is rounded to a power of two between 4 and 65536.
+    #[clap(long, value_name = "BLOCKS")]
+    mortality: Option<u64>,
+    /// The tip paid to the block author for including the transaction.
+    #[clap(long)]
+    tip: Option<BalanceVariant>,
+    /// If the mortal transaction expires without being included in a block, re-sign it
+    /// with a fresh era and submit it again, up to the given number of times.
+    #[clap(
+        long,
+        value_name = "TIMES",
+        requires = "mortality",
+        default_value_t = 0
+    )]
+    auto_rebroadcast: u32,
+    /// The amount the tip is increased by on every re-broadcast.
+    #[clap(long, requires = "auto_rebroadcast")]
+    rebroadcast_tip: Option<BalanceVariant>,
+    /// Pace the RPC requests conservatively and disable re-broadcasts, for community RPC
+    /// providers banning clients sending bursts of requests. Can also be enabled with
+    /// the `public-endpoint` setting of the network profile.
+    #[clap(long)]
+    public_endpoint: bool,
+    /// Simulate the whole signed extrinsic before broadcasting it, checking its
+    /// signature, nonce, fees and signed extensions in addition to the dispatch.
+    #[clap(long)]
+    simulate_extrinsic: bool,
+    /// Build the extrinsic for the account without signing it, and print the unsigned
+    /// payload to be signed offline and broadcast with `cargo contract submit`.
+    /// Supported by `upload`, `instantiate` and `call`.
+    #[clap(
+        long,
+        value_name = "ACCOUNT",
+        conflicts_with_all = ["suri", "signer", "password", "auto_rebroadcast", "simulate_extrinsic"]
+    )]
+    offline: Option<<DefaultConfig as Config>::AccountId>,
 }
 
 impl ExtrinsicOpts {
@@ -149,10 +341,104 @@ impl ExtrinsicOpts {
         )
     }
 
-    /// Returns the signer for contract extrinsics.
-    pub fn signer(&self) -> Result<sr25519::Pair> {
-        Pair::from_string(&self.suri, self.password.as_ref().map(String::as_ref))
-            .map_err(|_| anyhow::anyhow!("Secret string error"))
+    /// Returns the signer for the extrinsics of the command, if allowed by the signer
+    /// policy of the network profile.
+    pub fn signer(&self, command: &str) -> Result<SigningKey> {
+        let spec = self.signer_spec()?;
+        let Some(pair) = spec.key(self.password.as_deref())? else {
+            anyhow::bail!(
+                "The `{}` signer is only supported by `upload`, `instantiate`, `call` and \
+                `remove`",
+                spec.kind()
+            )
+        };
+        let account = AccountId32::from(pair.public().0);
+        self.check_signer(command, spec.kind(), &account)?;
+        Ok(pair)
+    }
+
+    /// The signer selected with `--signer`, or the secret key URI of `--suri`.
+    fn signer_spec(&self) -> Result<SignerSpec> {
+        match (&self.signer, &self.suri) {
+            (Some(spec), _) => Ok(spec.clone()),
+            (None, Some(suri)) => Ok(SignerSpec::Suri(suri.clone())),
+            (None, None) => {
+                anyhow::bail!(
+                    "The command can not be signed offline, `--offline` is only supported \
+                    by `upload`, `instantiate` and `call`"
+                )
+            }
+        }
+    }
+
+    /// Returns the signer of another secret key URI for the extrinsics of the command, if
+    /// allowed by the signer policy of the network profile. The `--password` only
+    /// applies to `--suri`, the password of the URI is part of the URI.
+    pub fn additional_signer(&self, command: &str, suri: &str) -> Result<sr25519::Pair> {
+        let pair = sr25519::Pair::from_string(suri, None)
+            .map_err(|_| anyhow::anyhow!("Secret string error"))?;
+        let account = AccountId32::from(pair.public().0);
+        self.check_signer(command, SignerKind::Suri, &account)?;
+        Ok(pair)
+    }
+
+    /// Returns the signer for the extrinsics of the commands supporting `--offline`, if
+    /// allowed by the signer policy of the network profile.
+    pub fn extrinsic_signer(&self, command: &str) -> Result<ExtrinsicSigner> {
+        match self.offline {
+            Some(ref account) => {
+                self.check_signer(command, SignerKind::Offline, account)?;
+                Ok(ExtrinsicSigner::Offline(account.clone()))
+            }
+            None => self.online_signer(command),
+        }
+    }
+
+    /// Returns the signer for the extrinsics of the commands supporting remote signers
+    /// but not `--offline`, if allowed by the signer policy of the network profile.
+    pub fn online_signer(&self, command: &str) -> Result<ExtrinsicSigner> {
+        let spec = self.signer_spec()?;
+        match spec.key(self.password.as_deref())? {
+            Some(pair) => {
+                let account = AccountId32::from(pair.public().0);
+                self.check_signer(command, spec.kind(), &account)?;
+                Ok(ExtrinsicSigner::Pair(pair_signer(pair)))
+            }
+            None => {
+                let remote = spec.remote()?;
+                self.check_signer(command, spec.kind(), remote.account_id())?;
+                Ok(ExtrinsicSigner::Remote(remote))
+            }
+        }
+    }
+
+    /// Returns an error if the signer policy of the network profile forbids the account
+    /// to sign the extrinsics of the command in the given way.
+    fn check_signer(
+        &self,
+        command: &str,
+        kind: SignerKind,
+        account: &AccountId32,
+    ) -> Result<()> {
+        let Some(policy) = self.network_profile()?.and_then(|profile| profile.signers)
+        else {
+            return Ok(())
+        };
+        policy.check(command, kind, account).with_context(|| {
+            format!(
+                "The signer is not allowed on the network `{}`",
+                self.network.as_deref().unwrap_or_default()
+            )
+        })
+    }
+
+    /// Returns how bytes are decoded in return values and events.
+    pub fn bytes_format(&self) -> BytesFormat {
+        if self.raw_bytes {
+            BytesFormat::Raw
+        } else {
+            BytesFormat::Pretty
+        }
     }
 
     /// Returns the verbosity
@@ -160,18 +446,141 @@ impl ExtrinsicOpts {
         TryFrom::try_from(&self.verbosity)
     }
 
-    /// Convert URL to String without omitting the default port
-    pub fn url_to_string(&self) -> String {
-        let mut res = self.url.to_string();
-        match (self.url.port(), self.url.port_or_known_default()) {
-            (None, Some(port)) => {
-                res.insert_str(res.len() - 1, &format!(":{port}"));
-                res
+    /// Returns `true` if submitting extrinsics is forbidden, by `--dry-run-only`, the
+    /// `CARGO_CONTRACT_READ_ONLY` environment variable or the configuration file.
+    pub fn read_only(&self) -> Result<bool> {
+        read_only(self.dry_run_only, self.network.as_deref())
+    }
+
+    /// Returns `true` if the extrinsic should be submitted for on-chain execution. With
+    /// `--dry-run` or in read-only mode the extrinsic is only dry-run, even if
+    /// `--execute` is given.
+    pub fn execute(&self) -> Result<bool> {
+        if !self.execute || self.dry_run {
+            return Ok(false)
+        }
+        if self.read_only()? {
+            eprintln!(
+                "{} read-only mode is enabled, the extrinsic is dry-run instead of \
+                being submitted.",
+                "warning:".yellow().bold(),
+            );
+            return Ok(false)
+        }
+        Ok(true)
+    }
+
+    /// Ask for the confirmation of the extrinsic to be submitted, unless `--yes` is
+    /// given. Submitting without a dry-run and without confirmation is announced by a
+    /// banner, the extrinsic is not checked at all before being paid for.
+    pub fn confirm(&self, show_details: impl FnOnce()) -> Result<()> {
+        match (self.skip_dry_run, self.skip_confirm) {
+            (true, true) => {
+                eprintln!(
+                    "{} submitting without dry-run and without confirmation \
+                    (`--skip-dry-run --yes`): the extrinsic is not checked before it is \
+                    submitted, if it fails on-chain its fees are paid all the same.",
+                    "warning:".yellow().bold(),
+                );
+                Ok(())
+            }
+            (false, true) => Ok(()),
+            (skip_dry_run, false) => {
+                if skip_dry_run {
+                    eprintln!(
+                        "{} the extrinsic was not dry-run (`--skip-dry-run`), it may fail \
+                        on-chain and still pay its fees.",
+                        "warning:".yellow().bold(),
+                    );
+                }
+                prompt_confirm_tx(show_details)
+            }
+        }
+    }
+
+    /// Returns an error if the `--role` is not allowed to call the message on the
+    /// `--network` by the signed permissions file of the configuration.
+    pub fn check_permission(&self, message: &str) -> Result<()> {
+        let config = crate::cmd::config::Config::load()?;
+        let Some(permissions) = config.permissions else {
+            return Ok(())
+        };
+        permissions
+            .load()?
+            .check(self.network.as_deref(), self.role.as_deref(), message)
+    }
+
+    /// Returns an error if submitting extrinsics is forbidden, for commands which can not
+    /// be dry-run.
+    pub fn ensure_not_read_only(&self, command: &str) -> Result<()> {
+        if self.read_only()? {
+            anyhow::bail!(
+                "The {command} command can not be dry-run and is forbidden in read-only mode"
+            )
+        }
+        Ok(())
+    }
+
+    /// Apply the options affecting the whole process: enable the public endpoint mode if
+    /// selected by `--public-endpoint` or the network profile and disable the
+    /// denomination of balances with `--denominate=false`. Must be called before the
+    /// first request to the node.
+    pub fn apply_process_opts(&self) -> Result<()> {
+        let profile = self.network_profile()?;
+        if self.public_endpoint || profile.is_some_and(|profile| profile.public_endpoint)
+        {
+            crate::cmd::pacing::enable_public_endpoint();
+        }
+        if !self.denominate {
+            balance::disable_denomination();
+        }
+        Ok(())
+    }
+
+    /// Returns the selected network profile, if any.
+    pub fn network_profile(&self) -> Result<Option<NetworkProfile>> {
+        self.network
+            .as_ref()
+            .map(|name| crate::cmd::config::Config::load()?.network_profile(name))
+            .transpose()
+    }
+
+    /// Warn about the differences of the chain from its entry in the catalogue of
+    /// `cargo contract chains`, if the `--network` is the name of a catalogued chain.
+    pub async fn preflight(&self, client: &Client) -> Result<()> {
+        match self.network {
+            Some(ref name) => crate::cmd::chains::preflight(client, name).await,
+            None => Ok(()),
+        }
+    }
+
+    /// Returns the Phala backend if selected by the network profile.
+    pub fn phala_backend(&self) -> Result<Option<PhalaBackend>> {
+        let Some(profile) = self.network_profile()? else {
+            return Ok(None)
+        };
+        match profile.backend {
+            Backend::PalletContracts => Ok(None),
+            Backend::Phala => {
+                let phala = profile.phala.as_ref().ok_or_else(|| {
+                    anyhow!("The `phala` backend requires the `phala.cluster` setting")
+                })?;
+                Ok(Some(PhalaBackend::from_profile(phala)?))
             }
-            _ => res,
         }
     }
 
+    /// Returns the URL of the node, taken from `--url`, the network profile or the
+    /// default.
+    pub fn url(&self) -> Result<url::Url> {
+        resolve_url(self.url.as_ref(), self.network.as_deref())
+    }
+
+    /// Convert URL to String without omitting the default port
+    pub fn url_to_string(&self) -> Result<String> {
+        Ok(url_to_string(&self.url()?))
+    }
+
     /// Get the storage deposit limit converted to compact for passing to extrinsics.
     pub fn storage_deposit_limit(
         &self,
@@ -184,142 +593,85 @@ impl ExtrinsicOpts {
             .transpose()?
             .map(Into::into))
     }
-}
-
-/// Contract artifacts for use with extrinsic commands.
-#[derive(Debug)]
-pub struct ContractArtifacts {
-    /// The original artifact path
-    artifacts_path: PathBuf,
-    /// The expected path of the file containing the contract metadata.
-    metadata_path: PathBuf,
-    /// The deserialized contract metadata if the expected metadata file exists.
-    metadata: Option<ContractMetadata>,
-    /// The Wasm code of the contract if available.
-    pub code: Option<WasmCode>,
-}
 
-impl ContractArtifacts {
-    /// Load contract artifacts.
-    pub fn from_manifest_or_file(
-        manifest_path: Option<&PathBuf>,
-        file: Option<&PathBuf>,
-    ) -> Result<ContractArtifacts> {
-        let artifact_path = match (manifest_path, file) {
-            (manifest_path, None) => {
-                let crate_metadata = CrateMetadata::from_manifest_path(
-                    manifest_path,
-                    contract_build::Target::Wasm,
-                )?;
-
-                if crate_metadata.contract_bundle_path().exists() {
-                    crate_metadata.contract_bundle_path()
-                } else if crate_metadata.metadata_path().exists() {
-                    crate_metadata.metadata_path()
-                } else {
-                    anyhow::bail!(
-                        "Failed to find any contract artifacts in target directory. \n\
-                        Run `cargo contract build --release` to generate the artifacts."
-                    )
-                }
-            }
-            (None, Some(artifact_file)) => artifact_file.clone(),
-            (Some(_), Some(_)) => {
-                anyhow::bail!("conflicting options: --manifest-path and --file")
-            }
+    /// Get the options for signing and submitting the extrinsic.
+    pub fn submit_options(
+        &self,
+        token_metadata: &TokenMetadata,
+    ) -> Result<SubmitOptions> {
+        let denominate = |balance: &Option<BalanceVariant>| {
+            balance
+                .as_ref()
+                .map(|bv| bv.denominate_balance(token_metadata))
+                .transpose()
+                .map(Option::unwrap_or_default)
         };
-        Self::from_artifact_path(artifact_path.as_path())
-    }
-    /// Given a contract artifact path, load the contract code and metadata where
-    /// possible.
-    fn from_artifact_path(path: &Path) -> Result<Self> {
-        tracing::debug!("Loading contracts artifacts from `{}`", path.display());
-        let (metadata_path, metadata, code) =
-            match path.extension().and_then(|ext| ext.to_str()) {
-                Some("contract") | Some("json") => {
-                    let metadata = ContractMetadata::load(path)?;
-                    let code = metadata.clone().source.wasm.map(|wasm| WasmCode(wasm.0));
-                    (PathBuf::from(path), Some(metadata), code)
-                }
-                Some("wasm") => {
-                    let file_name = path.file_stem()
-                        .context("WASM bundle file has unreadable name")?
-                        .to_str()
-                        .context("Error parsing filename string")?;
-                    let code = Some(WasmCode(std::fs::read(path)?));
-                    let dir = path.parent().map_or_else(PathBuf::new, PathBuf::from);
-                    let metadata_path = dir.join(format!("{file_name}.json"));
-                    if !metadata_path.exists() {
-                        (metadata_path, None, code)
-                    } else {
-                        let metadata = ContractMetadata::load(&metadata_path)?;
-                        (metadata_path, Some(metadata), code)
-                    }
-                }
-                Some(ext) => anyhow::bail!(
-                    "Invalid artifact extension {ext}, expected `.contract`, `.json` or `.wasm`"
-                ),
-                None => {
-                    anyhow::bail!(
-                        "Artifact path has no extension, expected `.contract`, `.json`, or `.wasm`"
-                    )
-                }
-            };
-        Ok(Self {
-            artifacts_path: path.into(),
-            metadata_path,
-            metadata,
-            code,
+        if self.auto_rebroadcast > 0 && crate::cmd::pacing::public_endpoint() {
+            anyhow::bail!(
+                "`--auto-rebroadcast` is not allowed in the public endpoint mode, which \
+                disables aggressive retries"
+            )
+        }
+        Ok(SubmitOptions {
+            mortality: self.mortality,
+            tip: denominate(&self.tip)?,
+            rebroadcasts: self.auto_rebroadcast,
+            rebroadcast_tip: denominate(&self.rebroadcast_tip)?,
+            simulate: self.simulate_extrinsic,
         })
     }
+}
 
-    /// Get the path of the artifact file used to load the artifacts.
-    pub fn artifact_path(&self) -> &Path {
-        self.artifacts_path.as_path()
-    }
+/// The environment variable enabling the read-only mode, which forbids submitting any
+/// extrinsic.
+pub const READ_ONLY_ENV: &str = "CARGO_CONTRACT_READ_ONLY";
 
-    /// Get contract metadata, if available.
-    ///
-    /// ## Errors
-    /// - No contract metadata could be found.
-    /// - Invalid contract metadata.
-    pub fn metadata(&self) -> Result<ContractMetadata> {
-        self.metadata.clone().ok_or_else(|| {
-            anyhow!(
-                "No contract metadata found. Expected file {}",
-                self.metadata_path.as_path().display()
-            )
-        })
-    }
+/// The URL of a locally running node, used if no other URL is configured.
+pub const DEFAULT_URL: &str = "ws://localhost:9944";
 
-    /// Get the code hash from the contract metadata.
-    pub fn code_hash(&self) -> Result<[u8; 32]> {
-        let metadata = self.metadata()?;
-        Ok(metadata.source.hash.0)
+/// Returns `true` if submitting extrinsics is forbidden, by `dry_run_only`, the
+/// configuration file or the profile of the `network`.
+pub fn read_only(dry_run_only: bool, network: Option<&str>) -> Result<bool> {
+    if dry_run_only {
+        return Ok(true)
     }
-
-    /// Construct a [`ContractMessageTranscoder`] from contract metadata.
-    pub fn contract_transcoder(&self) -> Result<ContractMessageTranscoder> {
-        let metadata = self.metadata()?;
-        ContractMessageTranscoder::try_from(metadata)
-            .context("Failed to deserialize ink project metadata from contract metadata")
+    let config = crate::cmd::config::Config::load()?;
+    match network {
+        Some(name) => Ok(config.read_only || config.network_profile(name)?.read_only),
+        None => Ok(config.read_only),
     }
 }
 
-/// The Wasm code of a contract.
-#[derive(Debug)]
-pub struct WasmCode(Vec<u8>);
+/// Returns the URL of the node, taken from `url`, the profile of the `network` or the
+/// default.
+pub fn resolve_url(url: Option<&url::Url>, network: Option<&str>) -> Result<url::Url> {
+    if let Some(url) = url {
+        return Ok(url.clone())
+    }
+    let profile = network
+        .map(|name| crate::cmd::config::Config::load()?.network_profile(name))
+        .transpose()?;
+    match profile.and_then(|profile| profile.url) {
+        Some(url) => Ok(url),
+        None => Ok(url::Url::parse(DEFAULT_URL)?),
+    }
+}
 
-impl WasmCode {
-    /// The hash of the contract code: uniquely identifies the contract code on-chain.
-    pub fn code_hash(&self) -> [u8; 32] {
-        contract_build::code_hash(&self.0)
+/// Convert URL to String without omitting the default port
+pub fn url_to_string(url: &url::Url) -> String {
+    let mut res = url.to_string();
+    match (url.port(), url.port_or_known_default()) {
+        (None, Some(port)) => {
+            res.insert_str(res.len() - 1, &format!(":{port}"));
+            res
+        }
+        _ => res,
     }
 }
 
-/// Create a new [`PairSigner`] from the given [`sr25519::Pair`].
-pub fn pair_signer(pair: sr25519::Pair) -> PairSigner {
-    PairSigner::new(pair)
+/// Create a new [`PairSigner`] from the given key pair or key held by the agent.
+pub fn pair_signer(key: impl Into<SigningKey>) -> PairSigner {
+    PairSigner::new(key)
 }
 
 const STORAGE_DEPOSIT_KEY: &str = "Storage Deposit";
@@ -328,6 +680,8 @@ pub const MAX_KEY_COL_WIDTH: usize = STORAGE_DEPOSIT_KEY.len() + 1;
 /// Print to stdout the fields of the result of a `instantiate` or `call` dry-run via RPC.
 pub fn display_contract_exec_result<R, const WIDTH: usize>(
     result: &ContractResult<R, Balance>,
+    token_metadata: &TokenMetadata,
+    number_format: NumberFormat,
 ) -> Result<()> {
     let mut debug_message_lines = std::str::from_utf8(&result.debug_message)
         .context("Error decoding UTF8 debug message bytes")?
@@ -336,7 +690,8 @@ pub fn display_contract_exec_result<R, const WIDTH: usize>(
     name_value_println!("Gas Required", format!("{:?}", result.gas_required), WIDTH);
     name_value_println!(
         STORAGE_DEPOSIT_KEY,
-        format!("{:?}", result.storage_deposit),
+        StorageDeposit::new(&result.storage_deposit, token_metadata)?
+            .format(number_format),
         WIDTH
     );
 
@@ -375,8 +730,39 @@ pub fn display_dry_run_result_warning(command: &str) {
         );
 }
 
+/// Options for signing and submitting an extrinsic, see
+/// [`ExtrinsicOpts::submit_options`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct SubmitOptions {
+    /// The number of blocks a mortal transaction is valid for, immortal if `None`.
+    mortality: Option<u64>,
+    /// The tip paid to the block author.
+    tip: Balance,
+    /// How often an expired mortal transaction is re-signed and re-submitted.
+    rebroadcasts: u32,
+    /// The amount the tip is increased by on every re-broadcast.
+    rebroadcast_tip: Balance,
+    /// Whether the signed extrinsic is simulated before it is broadcast.
+    simulate: bool,
+}
+
+impl SubmitOptions {
+    /// The tip paid for the given attempt, where the first submission is attempt `0`.
+    fn tip_for_attempt(&self, attempt: u32) -> Balance {
+        self.tip
+            .saturating_add(self.rebroadcast_tip.saturating_mul(attempt.into()))
+    }
+}
+
+/// How often the chain is checked for the expiry of a pending mortal transaction.
+const EXPIRY_POLL_INTERVAL: Duration = Duration::from_secs(3);
+
 /// Wait for the transaction to be included successfully into a block.
 ///
+/// A mortal transaction which is not included before its era ends is reported as expired
+/// instead of waiting forever. If configured, it is then re-signed with a fresh era and
+/// an increased tip.
+///
 /// # Errors
 ///
 /// If a runtime Module error occurs, this will only display the pallet and error indices.
@@ -387,39 +773,251 @@ pub fn display_dry_run_result_warning(command: &str) {
 ///
 /// Currently this will report success once the transaction is included in a block. In the
 /// future there could be a flag to wait for finality before reporting success.
-async fn submit_extrinsic<T, Call, Signer>(
-    client: &OnlineClient<T>,
+///
+/// # Activity cache
+///
+/// With the `activity-cache` feature the receipt of the included extrinsic is recorded in
+/// the local activity cache.
+async fn submit_extrinsic<Call, Signer>(
+    client: &Client,
     call: &Call,
     signer: &Signer,
-) -> core::result::Result<blocks::ExtrinsicEvents<T>, subxt::Error>
+    opts: &SubmitOptions,
+) -> core::result::Result<blocks::ExtrinsicEvents<DefaultConfig>, subxt::Error>
 where
-    T: Config,
     Call: tx::TxPayload,
-    Signer: tx::Signer<T>,
-    <T::ExtrinsicParams as config::ExtrinsicParams<T::Index, T::Hash>>::OtherParams:
-        Default,
+    Signer: tx::Signer<DefaultConfig>,
 {
-    client
-        .tx()
-        .sign_and_submit_then_watch_default(call, signer)
-        .await?
-        .wait_for_in_block()
+    let result = submit_and_watch(client, call, signer, opts).await?;
+    #[cfg(feature = "activity-cache")]
+    activity_cache::record_receipt(client, call, &result).await;
+    core::result::Result::Ok(result)
+}
+
+async fn submit_and_watch<Call, Signer>(
+    client: &Client,
+    call: &Call,
+    signer: &Signer,
+    opts: &SubmitOptions,
+) -> core::result::Result<blocks::ExtrinsicEvents<DefaultConfig>, subxt::Error>
+where
+    Call: tx::TxPayload,
+    Signer: tx::Signer<DefaultConfig>,
+{
+    for attempt in 0..=opts.rebroadcasts {
+        let tip = opts.tip_for_attempt(attempt);
+        if attempt > 0 {
+            eprintln!(
+                "Re-broadcasting the transaction with a fresh era and a tip of {tip} \
+                (attempt {attempt} of {})",
+                opts.rebroadcasts
+            );
+        }
+        let (params, era) = extrinsic_params(client, opts.mortality, tip).await?;
+        let extrinsic = client.tx().create_signed(call, signer, params).await?;
+        if opts.simulate && attempt == 0 {
+            simulate::simulate_extrinsic(client, &extrinsic).await?;
+        }
+        match watch_extrinsic(client, &extrinsic, era).await? {
+            Submission::Included(events) => return core::result::Result::Ok(events),
+            Submission::Expired(MortalEra { birth, death }) => {
+                eprintln!(
+                    "{} the transaction expired: it was not included in a block between \
+                    block #{birth} and block #{death}.",
+                    "warning:".yellow().bold(),
+                );
+            }
+        }
+    }
+    Err(subxt::Error::Other(format!(
+        "The mortal transaction expired without being included in a block{}",
+        if opts.rebroadcasts > 0 {
+            format!(" after {} re-broadcasts", opts.rebroadcasts)
+        } else {
+            String::from(", retry with --auto-rebroadcast")
+        }
+    )))
+}
+
+/// The parameters of the extrinsic which are not provided by the client.
+type OtherParams = <<DefaultConfig as Config>::ExtrinsicParams as ExtrinsicParams<
+    <DefaultConfig as Config>::Index,
+    <DefaultConfig as Config>::Hash,
+>>::OtherParams;
+
+/// The first and the last block a mortal transaction is valid in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct MortalEra {
+    birth: u64,
+    death: u64,
+}
+
+/// Build the parameters of the extrinsic, with an era starting at the current best block
+/// for a mortal transaction valid for the given number of blocks.
+async fn extrinsic_params(
+    client: &Client,
+    mortality: Option<u64>,
+    tip: Balance,
+) -> core::result::Result<(OtherParams, Option<MortalEra>), subxt::Error> {
+    let params = PolkadotExtrinsicParamsBuilder::new().tip(PlainTip::new(tip));
+    let Some(period) = mortality else {
+        return core::result::Result::Ok((params, None))
+    };
+    let current = u64::from(client.blocks().at_latest().await?.number());
+    let era = sp_runtime::generic::Era::mortal(period, current);
+    let (birth, death) = (era.birth(current), era.death(current));
+    let birth_hash = client
+        .rpc()
+        .block_hash(Some(birth.into()))
         .await?
-        .wait_for_success()
+        .ok_or(TransactionError::BlockNotFound)?;
+    let params = params.era(Era::mortal(period, current), birth_hash);
+    core::result::Result::Ok((params, Some(MortalEra { birth, death })))
+}
+
+/// The outcome of submitting a transaction.
+enum Submission {
+    /// The transaction was included successfully into a block.
+    Included(blocks::ExtrinsicEvents<DefaultConfig>),
+    /// The era of the mortal transaction ended before it was included into a block.
+    Expired(MortalEra),
+}
+
+/// Submit the signed extrinsic and wait until it is either included into a block or,
+/// for a mortal transaction, expired.
+async fn watch_extrinsic(
+    client: &Client,
+    extrinsic: &tx::SubmittableExtrinsic<DefaultConfig, Client>,
+    era: Option<MortalEra>,
+) -> core::result::Result<Submission, subxt::Error> {
+    // The statuses are matched against `Result`, not `anyhow::Result`.
+    use core::result::Result::Ok;
+
+    let mut progress = extrinsic.submit_and_watch().await?;
+    let expired = || {
+        async move {
+            let Some(era) = era else { return Ok(None) };
+            let best = u64::from(client.blocks().at_latest().await?.number());
+            Ok::<_, subxt::Error>((best >= era.death).then_some(era))
+        }
+    };
+    loop {
+        let status = match tokio::time::timeout(
+            EXPIRY_POLL_INTERVAL,
+            progress.next_item(),
+        )
         .await
+        {
+            Ok(Some(status)) => status?,
+            Ok(None) => return Err(RpcError::SubscriptionDropped.into()),
+            Err(_) => {
+                if let Some(era) = expired().await? {
+                    return Ok(Submission::Expired(era))
+                }
+                continue
+            }
+        };
+        let error = match status {
+            TxStatus::InBlock(in_block) | TxStatus::Finalized(in_block) => {
+                return in_block.wait_for_success().await.map(Submission::Included)
+            }
+            TxStatus::FinalityTimeout(_) => TransactionError::FinalityTimeout,
+            TxStatus::Invalid => TransactionError::Invalid,
+            TxStatus::Usurped(_) => TransactionError::Usurped,
+            TxStatus::Dropped => TransactionError::Dropped,
+            _ => continue,
+        };
+        // The pool drops transactions once their era has ended.
+        if let Some(era) = expired().await? {
+            return Ok(Submission::Expired(era))
+        }
+        return Err(error.into())
+    }
+}
+
+/// Submit a call constructed at runtime, e.g. for pallets which are not part of the
+/// static runtime metadata, and return the emitted events for display.
+async fn submit_dynamic_call(
+    client: &Client,
+    call: &subxt::tx::DynamicPayload,
+    signer: &PairSigner,
+    opts: &ExtrinsicOpts,
+    output_json: bool,
+) -> Result<String> {
+    let token_metadata = TokenMetadata::query(client).await?;
+    let submit_options = opts.submit_options(&token_metadata)?;
+    let result = submit_extrinsic(client, call, signer, &submit_options).await?;
+    let display_events = events::DisplayEvents::from_events(
+        &result,
+        None,
+        &client.metadata(),
+        &token_metadata,
+    )?;
+    if output_json {
+        display_events.to_json()
+    } else {
+        display_events.display_events(
+            opts.verbosity()?,
+            opts.number_format,
+            opts.max_output_bytes,
+        )
+    }
 }
 
 async fn state_call<A: Encode, R: Decode>(url: &str, func: &str, args: A) -> Result<R> {
+    let bytes = state_call_at(url, func, &args.encode(), None).await?;
+    Ok(R::decode(&mut bytes.as_ref())?)
+}
+
+/// Perform the runtime API call with the encoded arguments at the given block, or at the
+/// best block if none is given, returns the encoded result.
+async fn state_call_at(
+    url: &str,
+    func: &str,
+    args: &[u8],
+    at: Option<<DefaultConfig as Config>::Hash>,
+) -> Result<Vec<u8>> {
+    crate::cmd::pacing::pace().await;
     let cli = WsClientBuilder::default().build(&url).await?;
-    let params = rpc_params![func, Bytes(args.encode())];
+    let params = rpc_params![func, Bytes(args.to_vec()), at];
     let bytes: Bytes = cli.request("state_call", params).await?;
-    Ok(R::decode(&mut bytes.as_ref())?)
+    Ok(bytes.0)
+}
+
+/// Returns the hash of the best block.
+async fn best_block_hash(url: &str) -> Result<<DefaultConfig as Config>::Hash> {
+    crate::cmd::pacing::pace().await;
+    let cli = WsClientBuilder::default().build(&url).await?;
+    Ok(cli.request("chain_getBlockHash", rpc_params![]).await?)
+}
+
+/// Perform the runtime API calls with the given arguments in a single batch request,
+/// returns the results in the order of the arguments.
+async fn state_call_batch<A: Encode, R: Decode>(
+    url: &str,
+    func: &str,
+    args: impl IntoIterator<Item = A>,
+) -> Result<Vec<Result<R>>> {
+    crate::cmd::pacing::pace().await;
+    let cli = WsClientBuilder::default().build(&url).await?;
+    let mut batch = BatchRequestBuilder::new();
+    for args in args {
+        batch.insert("state_call", rpc_params![func, Bytes(args.encode())])?;
+    }
+    let responses = cli.batch_request::<Bytes>(batch).await?;
+    Ok(responses
+        .into_iter()
+        .map(|response| {
+            let bytes = response.map_err(|err| anyhow::anyhow!("{err}"))?;
+            Ok(R::decode(&mut bytes.as_ref())?)
+        })
+        .collect())
 }
 
 /// Prompt the user to confirm transaction submission.
 fn prompt_confirm_tx<F: FnOnce()>(show_details: F) -> Result<()> {
     println!(
-        "{} (skip with --skip-confirm)",
+        "{} (skip with --yes)",
         "Confirm transaction details:".bright_white().bold()
     );
     show_details();
@@ -471,29 +1069,42 @@ pub fn parse_code_hash(input: &str) -> Result<<DefaultConfig as Config>::Hash> {
 
 /// Copy of `pallet_contracts_primitives::StorageDeposit` which implements `Serialize`,
 /// required for json output.
-#[derive(Eq, PartialEq, Ord, PartialOrd, Clone, serde::Serialize)]
+#[derive(Debug, Eq, PartialEq, Clone, serde::Serialize)]
 pub enum StorageDeposit {
     /// The transaction reduced storage consumption.
     ///
     /// This means that the specified amount of balance was transferred from the involved
     /// contracts to the call origin.
-    Refund(Balance),
+    Refund(DisplayBalance),
     /// The transaction increased overall storage usage.
     ///
     /// This means that the specified amount of balance was transferred from the call
     /// origin to the contracts involved.
-    Charge(Balance),
+    Charge(DisplayBalance),
 }
 
-impl From<&pallet_contracts_primitives::StorageDeposit<Balance>> for StorageDeposit {
-    fn from(deposit: &pallet_contracts_primitives::StorageDeposit<Balance>) -> Self {
-        match deposit {
+impl StorageDeposit {
+    /// Convert the storage deposit of a dry-run result, denominating the balance with
+    /// the given [`TokenMetadata`].
+    pub fn new(
+        deposit: &pallet_contracts_primitives::StorageDeposit<Balance>,
+        token_metadata: &TokenMetadata,
+    ) -> Result<Self> {
+        Ok(match deposit {
             pallet_contracts_primitives::StorageDeposit::Refund(balance) => {
-                Self::Refund(*balance)
+                Self::Refund(DisplayBalance::new(*balance, token_metadata)?)
             }
             pallet_contracts_primitives::StorageDeposit::Charge(balance) => {
-                Self::Charge(*balance)
+                Self::Charge(DisplayBalance::new(*balance, token_metadata)?)
             }
+        })
+    }
+
+    /// Display the storage deposit using the given [`NumberFormat`].
+    pub fn format(&self, number_format: NumberFormat) -> String {
+        match self {
+            Self::Refund(balance) => format!("Refund({})", balance.format(number_format)),
+            Self::Charge(balance) => format!("Charge({})", balance.format(number_format)),
         }
     }
 }
@@ -515,4 +1126,19 @@ mod tests {
         )
         .is_ok())
     }
+
+    #[test]
+    fn rebroadcast_tip_is_increased_per_attempt() {
+        let opts = SubmitOptions {
+            mortality: Some(64),
+            tip: 100,
+            rebroadcasts: 2,
+            rebroadcast_tip: 50,
+            simulate: false,
+        };
+        assert_eq!(opts.tip_for_attempt(0), 100);
+        assert_eq!(opts.tip_for_attempt(1), 150);
+        assert_eq!(opts.tip_for_attempt(2), 200);
+        assert_eq!(SubmitOptions::default().tip_for_attempt(3), 0);
+    }
 }
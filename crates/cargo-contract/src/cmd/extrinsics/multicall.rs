@@ -0,0 +1,264 @@
+// Copyright 2018-2023 Parity Technologies (UK) Ltd.
+// This file is part of cargo-contract.
+//
+// cargo-contract is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// cargo-contract is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with cargo-contract.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Dry-run of many messages of a contract in a single batch RPC request.
+
+use super::{
+    call::CallRequest,
+    state_call_batch,
+    DefaultConfig,
+    ErrorVariant,
+    ExtrinsicOpts,
+    TokenMetadata,
+};
+use crate::{
+    cmd::{
+        identity::Identities,
+        AsyncCommand,
+        Balance,
+    },
+    DEFAULT_KEY_COL_WIDTH,
+};
+use anyhow::{
+    anyhow,
+    Result,
+};
+use async_trait::async_trait;
+use contract_build::name_value_println;
+use contract_transcode::Value;
+use pallet_contracts_primitives::ContractExecResult;
+use subxt::{
+    Config,
+    OnlineClient,
+};
+
+#[derive(Debug, clap::Args)]
+#[clap(
+    name = "multicall",
+    about = "Dry-run many messages of a contract in one RPC round trip"
+)]
+pub struct MulticallCommand {
+    /// The address of the the contract to call.
+    #[clap(name = "contract", long, env = "CONTRACT")]
+    contract: <DefaultConfig as Config>::AccountId,
+    /// A message to dry-run followed by its arguments, e.g. `--read "balance_of 5C..."`.
+    /// Arguments containing spaces are enclosed in double quotes. Can be repeated.
+    #[clap(long = "read", value_name = "MESSAGE [ARGS]", required = true)]
+    reads: Vec<String>,
+    #[clap(flatten)]
+    extrinsic_opts: ExtrinsicOpts,
+    /// Export the results in JSON format.
+    #[clap(long, conflicts_with = "verbose")]
+    output_json: bool,
+}
+
+/// The outcome of reading a single message.
+#[derive(Debug, serde::Serialize)]
+pub struct ReadResult {
+    /// The message which was dry-run.
+    pub message: String,
+    /// The arguments of the message.
+    pub args: Vec<String>,
+    /// Was the message reverted.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reverted: Option<bool>,
+    /// The decoded return value.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub data: Option<Value>,
+    /// The error if the message failed or could not be encoded or decoded.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+impl MulticallCommand {
+    pub fn is_json(&self) -> bool {
+        self.output_json
+    }
+}
+
+#[async_trait(?Send)]
+impl AsyncCommand for MulticallCommand {
+    type Output = Result<(), ErrorVariant>;
+
+    async fn exec(&self) -> Result<(), ErrorVariant> {
+        if self.extrinsic_opts.phala_backend()?.is_some() {
+            return Err(anyhow!(
+                "Calling Phat contracts requires encrypted queries to the workers of the \
+                cluster, which is not supported by the `phala` backend"
+            )
+            .into())
+        }
+        self.extrinsic_opts.apply_process_opts()?;
+        let artifacts = self.extrinsic_opts.contract_artifacts()?;
+        let identities = Identities::default();
+        let transcoder = artifacts
+            .contract_transcoder_with_identities(&identities)?
+            .with_bytes_format(self.extrinsic_opts.bytes_format());
+        let reads = self
+            .reads
+            .iter()
+            .map(|read| split_read(read))
+            .collect::<Result<Vec<_>>>()?;
+        let input_data = reads
+            .iter()
+            .map(|(message, args)| transcoder.encode(message, args))
+            .collect::<Result<Vec<_>>>()?;
+        let signer = super::pair_signer(self.extrinsic_opts.signer("multicall")?);
+
+        let url = self.extrinsic_opts.url_to_string()?;
+        let client = OnlineClient::<DefaultConfig>::from_url(url.clone()).await?;
+        identities.resolve(&client).await?;
+        let token_metadata = TokenMetadata::query(&client).await?;
+        let storage_deposit_limit = self
+            .extrinsic_opts
+            .storage_deposit_limit
+            .as_ref()
+            .map(|bv| bv.denominate_balance(&token_metadata))
+            .transpose()?;
+        let requests = input_data.into_iter().map(|input_data| {
+            CallRequest {
+                origin: signer.account_id().clone(),
+                dest: self.contract.clone(),
+                value: 0,
+                gas_limit: None,
+                storage_deposit_limit,
+                input_data,
+            }
+        });
+        let responses: Vec<Result<ContractExecResult<Balance>>> =
+            state_call_batch(&url, "ContractsApi_call", requests).await?;
+
+        let mut results = Vec::new();
+        for ((message, args), response) in reads.into_iter().zip(responses) {
+            let mut result = ReadResult {
+                message,
+                args,
+                reverted: None,
+                data: None,
+                error: None,
+            };
+            match response.map(|response| response.result) {
+                Ok(Ok(ret_val)) => {
+                    result.reverted = Some(ret_val.did_revert());
+                    match transcoder
+                        .decode_return(&result.message, &mut &ret_val.data[..])
+                    {
+                        Ok(value) => result.data = Some(value),
+                        Err(err) => result.error = Some(format!("{err:#}")),
+                    }
+                }
+                Ok(Err(ref err)) => {
+                    let object =
+                        ErrorVariant::from_dispatch_error(err, &client.metadata())?;
+                    result.error = Some(object.to_string());
+                }
+                Err(err) => result.error = Some(format!("{err:#}")),
+            }
+            results.push(result);
+        }
+
+        if self.output_json {
+            let json =
+                serde_json::to_string_pretty(&results).map_err(anyhow::Error::from)?;
+            println!("{json}");
+        } else {
+            for result in &results {
+                result.print();
+            }
+        }
+        Ok(())
+    }
+}
+
+impl ReadResult {
+    pub fn print(&self) {
+        let read = std::iter::once(&self.message)
+            .chain(&self.args)
+            .cloned()
+            .collect::<Vec<_>>()
+            .join(" ");
+        name_value_println!("Message", read, DEFAULT_KEY_COL_WIDTH);
+        if let Some(reverted) = self.reverted {
+            name_value_println!(
+                "Reverted",
+                format!("{reverted:?}"),
+                DEFAULT_KEY_COL_WIDTH
+            );
+        }
+        if let Some(ref data) = self.data {
+            name_value_println!("Data", format!("{data}"), DEFAULT_KEY_COL_WIDTH);
+        }
+        if let Some(ref error) = self.error {
+            name_value_println!("Error", error, DEFAULT_KEY_COL_WIDTH);
+        }
+    }
+}
+
+/// Split a `--read` or a `--check` into the message and its arguments, separated by
+/// whitespace. Double quoted arguments may contain whitespace, the quotes are kept so
+/// that the argument is parsed as a string.
+pub(super) fn split_read(read: &str) -> Result<(String, Vec<String>)> {
+    let mut words = Vec::new();
+    let mut word = String::new();
+    let mut quoted = false;
+    for c in read.chars() {
+        match c {
+            '"' => {
+                quoted = !quoted;
+                word.push(c);
+            }
+            c if c.is_whitespace() && !quoted => {
+                if !word.is_empty() {
+                    words.push(std::mem::take(&mut word));
+                }
+            }
+            c => word.push(c),
+        }
+    }
+    if quoted {
+        anyhow::bail!("Unterminated quote in `{read}`")
+    }
+    if !word.is_empty() {
+        words.push(word);
+    }
+    let mut words = words.into_iter();
+    let message = words
+        .next()
+        .ok_or_else(|| anyhow!("The name of a message is required"))?;
+    Ok((message, words.collect()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reads_are_split_into_message_and_args() {
+        assert_eq!(
+            split_read("get").unwrap(),
+            (String::from("get"), Vec::new())
+        );
+        assert_eq!(
+            split_read(r#"  set_name  "Alice Liddell" 42 "#).unwrap(),
+            (
+                String::from("set_name"),
+                vec![String::from(r#""Alice Liddell""#), String::from("42")]
+            )
+        );
+        assert!(split_read("   ").is_err());
+        assert!(split_read(r#"set_name "Alice"#).is_err());
+    }
+}
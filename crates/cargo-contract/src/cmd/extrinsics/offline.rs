@@ -0,0 +1,498 @@
+// Copyright 2018-2023 Parity Technologies (UK) Ltd.
+// This file is part of cargo-contract.
+//
+// cargo-contract is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// cargo-contract is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with cargo-contract.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Offline signing of the extrinsics.
+//!
+//! With `--offline <ACCOUNT>` the extrinsic is built for the account, but instead of
+//! being signed and submitted its unsigned payload is printed. The payload is signed
+//! with a key kept off the machine, e.g. in a hardware wallet, and the extrinsic is then
+//! broadcast with `cargo contract submit`.
+
+use super::{
+    events::DisplayEvents,
+    extrinsic_params,
+    read_only,
+    resolve_url,
+    signer::RemoteSigner,
+    submit_extrinsic,
+    url_to_string,
+    watch_extrinsic,
+    Client,
+    ContractArtifacts,
+    DefaultConfig,
+    MortalEra,
+    NumberFormat,
+    OtherParams,
+    PairSigner,
+    Submission,
+    SubmitOptions,
+    TokenMetadata,
+    READ_ONLY_ENV,
+};
+use crate::cmd::{
+    output::DEFAULT_MAX_OUTPUT_BYTES,
+    AsyncCommand,
+};
+use anyhow::{
+    Context,
+    Result,
+};
+use async_trait::async_trait;
+use contract_build::VerbosityFlags;
+use scale::Encode;
+use sp_core::{
+    ecdsa,
+    ed25519,
+    sr25519,
+};
+use sp_runtime::{
+    traits::Verify,
+    MultiSignature,
+};
+use std::path::PathBuf;
+use subxt::{
+    blocks::ExtrinsicEvents,
+    client::OfflineClientT,
+    config::ExtrinsicParams,
+    tx::{
+        self,
+        TxPayload,
+    },
+    Config,
+};
+
+type AccountId = <DefaultConfig as Config>::AccountId;
+
+/// Signed payloads longer than this are hashed before they are signed.
+const MAX_UNHASHED_PAYLOAD_LEN: usize = 256;
+
+/// The signer of the extrinsics of a command.
+#[allow(clippy::large_enum_variant)]
+pub enum ExtrinsicSigner {
+    /// The extrinsics are signed with the key pair and submitted.
+    Pair(PairSigner),
+    /// The extrinsics are signed by a remote signing service and submitted.
+    Remote(RemoteSigner),
+    /// Only the account is known, the unsigned payloads of the extrinsics are printed.
+    Offline(AccountId),
+}
+
+impl ExtrinsicSigner {
+    /// The account signing the extrinsics.
+    pub fn account_id(&self) -> &AccountId {
+        match self {
+            Self::Pair(signer) => tx::Signer::account_id(signer),
+            Self::Remote(signer) => signer.account_id(),
+            Self::Offline(account_id) => account_id,
+        }
+    }
+
+    /// The key pair signing the extrinsics, for the submissions which can not be signed
+    /// offline.
+    pub fn pair(&self) -> Result<&PairSigner> {
+        match self {
+            Self::Pair(signer) => Ok(signer),
+            Self::Remote(_) => {
+                anyhow::bail!(
+                    "The extrinsic can not be signed by a remote signer, pass `--suri`"
+                )
+            }
+            Self::Offline(_) => {
+                anyhow::bail!("The extrinsic can not be signed offline, pass `--suri`")
+            }
+        }
+    }
+
+    /// Sign and submit the extrinsic, failing for an offline signer.
+    pub async fn submit<Call: TxPayload>(
+        &self,
+        client: &Client,
+        call: &Call,
+        opts: &SubmitOptions,
+    ) -> Result<ExtrinsicEvents<DefaultConfig>> {
+        match self {
+            Self::Pair(signer) => Ok(submit_extrinsic(client, call, signer, opts).await?),
+            Self::Remote(signer) => signer.submit(client, call, opts).await,
+            Self::Offline(_) => {
+                anyhow::bail!("The extrinsic can not be signed offline, pass `--suri`")
+            }
+        }
+    }
+}
+
+/// Sign and submit the extrinsic, or with an offline signer print its unsigned payload
+/// instead and return `None`.
+pub async fn submit_or_print_unsigned<Call: TxPayload>(
+    client: &Client,
+    call: &Call,
+    signer: &ExtrinsicSigner,
+    opts: &SubmitOptions,
+) -> Result<Option<ExtrinsicEvents<DefaultConfig>>> {
+    let ExtrinsicSigner::Offline(account_id) = signer else {
+        return Ok(Some(signer.submit(client, call, opts).await?))
+    };
+    let (params, era) = extrinsic_params(client, opts.mortality, opts.tip).await?;
+    let nonce = client.rpc().system_account_next_index(account_id).await?;
+    let unsigned = UnsignedExtrinsic::new(client, call, account_id, nonce, params, era)?;
+    println!("{}", serde_json::to_string_pretty(&unsigned)?);
+    eprintln!(
+        "Sign the `signer_payload` with the key of {account_id}, then broadcast the \
+        extrinsic with `cargo contract submit --payload <PATH> --signature <SIGNATURE>`"
+    );
+    Ok(None)
+}
+
+/// An extrinsic built for a signer, waiting for its signature.
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct UnsignedExtrinsic {
+    /// The account signing the extrinsic.
+    pub signer: String,
+    /// The bytes to sign, hex encoded: the call data, the signed extensions and the
+    /// additional data, or their hash if they are longer than 256 bytes.
+    pub signer_payload: String,
+    /// The SCALE encoded call, hex encoded.
+    pub call_data: String,
+    /// The SCALE encoded signed extensions, the era, nonce and tip, hex encoded.
+    pub extra: String,
+    pub nonce: u32,
+    pub genesis_hash: String,
+    pub spec_version: u32,
+    pub transaction_version: u32,
+    /// The blocks a mortal transaction is valid in.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub era: Option<MortalEra>,
+}
+
+impl UnsignedExtrinsic {
+    /// Build the extrinsic of the call for the signer, with the nonce of the signer and
+    /// the runtime version of the client.
+    #[allow(clippy::result_large_err)]
+    pub fn new<C: OfflineClientT<DefaultConfig>>(
+        client: &C,
+        call: &impl TxPayload,
+        signer: &AccountId,
+        nonce: u32,
+        params: OtherParams,
+        era: Option<MortalEra>,
+    ) -> Result<Self, subxt::Error> {
+        let call_data = client.tx().call_data(call)?;
+        let runtime = client.runtime_version();
+        let params =
+            <<DefaultConfig as Config>::ExtrinsicParams as ExtrinsicParams<_, _>>::new(
+                runtime.spec_version,
+                runtime.transaction_version,
+                nonce,
+                client.genesis_hash(),
+                params,
+            );
+        let mut extra = Vec::new();
+        params.encode_extra_to(&mut extra);
+        let mut signer_payload = [&call_data[..], &extra].concat();
+        params.encode_additional_to(&mut signer_payload);
+        if signer_payload.len() > MAX_UNHASHED_PAYLOAD_LEN {
+            signer_payload = sp_core::blake2_256(&signer_payload).to_vec();
+        }
+        Ok(Self {
+            signer: signer.to_string(),
+            signer_payload: to_hex(&signer_payload),
+            call_data: to_hex(&call_data),
+            extra: to_hex(&extra),
+            nonce,
+            genesis_hash: format!("{:?}", client.genesis_hash()),
+            spec_version: runtime.spec_version,
+            transaction_version: runtime.transaction_version,
+            era,
+        })
+    }
+
+    /// The account signing the extrinsic.
+    fn signer(&self) -> Result<AccountId> {
+        self.signer
+            .parse()
+            .map_err(|err| anyhow::anyhow!("Invalid signer {}: {err:?}", self.signer))
+    }
+
+    /// Check that the signature was produced by the signer for the payload.
+    fn verify(&self, signature: &MultiSignature) -> Result<()> {
+        let payload = from_hex("signer_payload", &self.signer_payload)?;
+        let signer = sp_runtime::AccountId32::from(self.signer()?.0);
+        anyhow::ensure!(
+            signature.verify(&payload[..], &signer),
+            "The signature does not match the signer payload and the account {}",
+            self.signer
+        );
+        Ok(())
+    }
+
+    /// The encoded extrinsic, signed with the signature.
+    pub fn signed(&self, signature: &MultiSignature) -> Result<Vec<u8>> {
+        let address = <DefaultConfig as Config>::Address::from(self.signer()?);
+        // The extrinsic is signed, in version 4 of the extrinsic format.
+        let mut extrinsic = vec![0b1000_0000 + 4u8];
+        address.encode_to(&mut extrinsic);
+        signature.encode_to(&mut extrinsic);
+        extrinsic.extend(from_hex("extra", &self.extra)?);
+        extrinsic.extend(from_hex("call_data", &self.call_data)?);
+        let len = scale::Compact(u32::try_from(extrinsic.len())?);
+        Ok([len.encode(), extrinsic].concat())
+    }
+}
+
+fn to_hex(bytes: &[u8]) -> String {
+    format!("0x{}", hex::encode(bytes))
+}
+
+fn from_hex(name: &str, input: &str) -> Result<Vec<u8>> {
+    hex::decode(input.trim_start_matches("0x"))
+        .with_context(|| format!("`{name}` must be hex encoded"))
+}
+
+/// The signature scheme of the key signing the payload.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, clap::ValueEnum)]
+pub enum SignatureScheme {
+    #[default]
+    Sr25519,
+    Ed25519,
+    Ecdsa,
+}
+
+impl SignatureScheme {
+    /// The signature of the scheme from its raw bytes.
+    fn signature(&self, bytes: &[u8]) -> Result<MultiSignature> {
+        let invalid = || {
+            anyhow::anyhow!(
+                "Invalid {self:?} signature of {} bytes, the raw signature is expected",
+                bytes.len()
+            )
+        };
+        Ok(match self {
+            Self::Sr25519 => {
+                sr25519::Signature::from_raw(bytes.try_into().map_err(|_| invalid())?)
+                    .into()
+            }
+            Self::Ed25519 => {
+                ed25519::Signature::from_raw(bytes.try_into().map_err(|_| invalid())?)
+                    .into()
+            }
+            Self::Ecdsa => {
+                ecdsa::Signature::from_raw(bytes.try_into().map_err(|_| invalid())?)
+                    .into()
+            }
+        })
+    }
+}
+
+#[derive(Debug, clap::Args)]
+#[clap(
+    name = "submit",
+    about = "Broadcast an extrinsic signed offline, built with `--offline`"
+)]
+pub struct SubmitCommand {
+    /// Path to the unsigned payload printed with `--offline`.
+    #[clap(long, value_name = "PATH", requires = "signature")]
+    payload: Option<PathBuf>,
+    /// The raw signature of the `signer_payload` of the payload, hex encoded.
+    #[clap(long, requires = "payload")]
+    signature: Option<String>,
+    /// The signature scheme of the key producing the signature.
+    #[clap(long, value_enum, default_value_t)]
+    signature_scheme: SignatureScheme,
+    /// A fully signed extrinsic, hex encoded, instead of a payload and its signature.
+    #[clap(long, conflicts_with = "payload", required_unless_present = "payload")]
+    extrinsic: Option<String>,
+    /// Websockets url of a substrate node. Defaults to the url of the `--network`
+    /// profile, or to `ws://localhost:9944`.
+    #[clap(long, value_parser)]
+    url: Option<url::Url>,
+    /// The name of a network profile from the `cargo-contract.toml` configuration file,
+    /// or of a chain of the catalogue of `cargo contract chains`.
+    #[clap(long, env = "CARGO_CONTRACT_NETWORK")]
+    network: Option<String>,
+    /// Forbid broadcasting the extrinsic, as for the other commands submitting
+    /// extrinsics. Can also be enabled with the `read-only` configuration setting.
+    #[clap(long, env = READ_ONLY_ENV)]
+    dry_run_only: bool,
+    /// Path to the contract bundle or metadata decoding the events emitted by the
+    /// contract.
+    #[clap(long, value_parser)]
+    file: Option<PathBuf>,
+    #[clap(flatten)]
+    verbosity: VerbosityFlags,
+    /// The notation used to display balances in the human readable output.
+    #[clap(long, value_enum, default_value_t)]
+    number_format: NumberFormat,
+    /// Export the events in JSON format.
+    #[clap(long)]
+    output_json: bool,
+}
+
+#[async_trait(?Send)]
+impl AsyncCommand for SubmitCommand {
+    type Output = Result<()>;
+
+    async fn exec(&self) -> Result<()> {
+        if read_only(self.dry_run_only, self.network.as_deref())? {
+            anyhow::bail!("Broadcasting extrinsics is forbidden in read-only mode")
+        }
+        let (extrinsic, unsigned) = match (&self.payload, &self.extrinsic) {
+            (Some(path), _) => {
+                let contents = std::fs::read_to_string(path)
+                    .with_context(|| format!("Failed to read {}", path.display()))?;
+                let unsigned: UnsignedExtrinsic = serde_json::from_str(&contents)
+                    .with_context(|| format!("Invalid payload {}", path.display()))?;
+                let signature = self.signature.as_deref().unwrap_or_default();
+                let signature = self
+                    .signature_scheme
+                    .signature(&from_hex("signature", signature)?)?;
+                unsigned.verify(&signature)?;
+                (unsigned.signed(&signature)?, Some(unsigned))
+            }
+            (None, Some(extrinsic)) => (from_hex("extrinsic", extrinsic)?, None),
+            (None, None) => anyhow::bail!("Pass `--payload` or `--extrinsic`"),
+        };
+
+        let url = resolve_url(self.url.as_ref(), self.network.as_deref())?;
+        let client = Client::from_url(url_to_string(&url)).await?;
+        if let Some(ref unsigned) = unsigned {
+            check_runtime(&client, unsigned)?;
+        }
+        let era = unsigned.and_then(|unsigned| unsigned.era);
+        let extrinsic = tx::SubmittableExtrinsic::from_bytes(client.clone(), extrinsic);
+        let result = match watch_extrinsic(&client, &extrinsic, era).await? {
+            Submission::Included(events) => events,
+            Submission::Expired(MortalEra { birth, death }) => {
+                anyhow::bail!(
+                    "The transaction expired: it was not included in a block between \
+                    block #{birth} and block #{death}, build and sign a new payload"
+                )
+            }
+        };
+        let transcoder = self
+            .file
+            .as_ref()
+            .map(|file| {
+                ContractArtifacts::from_manifest_or_file(None, Some(file))?
+                    .contract_transcoder()
+            })
+            .transpose()?;
+        let token_metadata = TokenMetadata::query(&client).await?;
+        let display_events = DisplayEvents::from_events(
+            &result,
+            transcoder.as_ref(),
+            &client.metadata(),
+            &token_metadata,
+        )?;
+        let output = if self.output_json {
+            display_events.to_json()?
+        } else {
+            display_events.display_events(
+                TryFrom::try_from(&self.verbosity)?,
+                self.number_format,
+                DEFAULT_MAX_OUTPUT_BYTES,
+            )?
+        };
+        println!("{output}");
+        Ok(())
+    }
+}
+
+/// Check that the payload was built for the chain and its current runtime, the signature
+/// is rejected otherwise.
+fn check_runtime(client: &Client, unsigned: &UnsignedExtrinsic) -> Result<()> {
+    anyhow::ensure!(
+        format!("{:?}", client.genesis_hash()) == unsigned.genesis_hash,
+        "The payload was built for the chain with the genesis hash {}",
+        unsigned.genesis_hash
+    );
+    let runtime = client.runtime_version();
+    anyhow::ensure!(
+        runtime.spec_version == unsigned.spec_version
+            && runtime.transaction_version == unsigned.transaction_version,
+        "The runtime of the chain was upgraded since the payload was built, build and \
+        sign a new payload"
+    );
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cmd::runtime_api::api;
+    use scale::Decode;
+    use sp_core::Pair as _;
+    use subxt::{
+        config::polkadot::{
+            PlainTip,
+            PolkadotExtrinsicParamsBuilder,
+        },
+        ext::frame_metadata::RuntimeMetadataPrefixed,
+        rpc::types::RuntimeVersion,
+        OfflineClient,
+    };
+
+    #[test]
+    fn unsigned_extrinsics_are_signed_offline() {
+        let bytes = include_bytes!("../runtime_api/contracts_runtime.scale");
+        let metadata = RuntimeMetadataPrefixed::decode(&mut &bytes[..]).unwrap();
+        let client = OfflineClient::<DefaultConfig>::new(
+            Default::default(),
+            RuntimeVersion {
+                spec_version: 100,
+                transaction_version: 1,
+                other: Default::default(),
+            },
+            subxt::Metadata::try_from(metadata).unwrap(),
+        );
+        let pair = sr25519::Pair::from_string("//Alice", None).unwrap();
+        let account_id = AccountId::from(pair.public().0);
+        let params = || PolkadotExtrinsicParamsBuilder::new().tip(PlainTip::new(7));
+
+        for code in [vec![0u8; 8], vec![0u8; 512]] {
+            let call = api::tx().contracts().upload_code(
+                code,
+                None,
+                api::runtime_types::pallet_contracts::wasm::Determinism::Deterministic,
+            );
+            let unsigned =
+                UnsignedExtrinsic::new(&client, &call, &account_id, 3, params(), None)
+                    .unwrap();
+            let partial = client
+                .tx()
+                .create_partial_signed_with_nonce(&call, 3, params())
+                .unwrap();
+            assert_eq!(unsigned.signer_payload, to_hex(&partial.signer_payload()));
+
+            let signature = SignatureScheme::Sr25519
+                .signature(&pair.sign(&partial.signer_payload()).0)
+                .unwrap();
+            unsigned.verify(&signature).unwrap();
+            let expected = partial
+                .sign_with_address_and_signature(&account_id.clone().into(), &signature);
+            assert_eq!(unsigned.signed(&signature).unwrap(), expected.encoded());
+        }
+
+        let call = api::tx().contracts().remove_code(Default::default());
+        let unsigned =
+            UnsignedExtrinsic::new(&client, &call, &account_id, 0, params(), None)
+                .unwrap();
+        let other = sr25519::Pair::from_string("//Bob", None).unwrap();
+        let signature = SignatureScheme::Sr25519
+            .signature(&other.sign(b"payload").0)
+            .unwrap();
+        assert!(unsigned.verify(&signature).is_err());
+        assert!(SignatureScheme::Ed25519.signature(&[0; 65]).is_err());
+    }
+}
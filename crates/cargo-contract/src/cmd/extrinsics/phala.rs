@@ -0,0 +1,192 @@
+// Copyright 2018-2023 Parity Technologies (UK) Ltd.
+// This file is part of cargo-contract.
+//
+// cargo-contract is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// cargo-contract is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with cargo-contract.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Deployment backend for Phala Phat contracts.
+//!
+//! Phat contracts are not executed by `pallet-contracts`, but off-chain by the workers of
+//! a cluster. Code is uploaded to and instantiated in a cluster via the
+//! `PhalaPhatContracts` pallet. Since the execution happens off-chain, deployments can
+//! not be dry-run via RPC.
+//!
+//! The backend is selected in the network profile:
+//!
+//! ```toml
+//! [network.phala-testnet]
+//! url = "wss://poc5.phala.network/ws"
+//! backend = "phala"
+//!
+//! [network.phala-testnet.phala]
+//! cluster = "0x0000000000000000000000000000000000000000000000000000000000000001"
+//! ```
+
+use super::{
+    parse_code_hash,
+    DefaultConfig,
+};
+use crate::cmd::{
+    Balance,
+    CodeHash,
+};
+use anyhow::{
+    Context,
+    Result,
+};
+use scale::Decode;
+use subxt::{
+    blocks::ExtrinsicEvents,
+    dynamic::Value,
+    tx::DynamicPayload,
+    utils::AccountId32,
+};
+
+/// The name of the pallet managing Phat contracts in the Phala runtimes.
+const PHAT_CONTRACTS_PALLET: &str = "PhalaPhatContracts";
+
+/// The event announcing the id of a contract instantiated in a cluster.
+const INSTANTIATING_EVENT: &str = "Instantiating";
+
+/// The `deposit` of `instantiate_contract`: the balance transferred to the account of
+/// the new contract in the cluster, paying for its off-chain execution. Nothing is
+/// deposited, the cluster account can be funded after the instantiation.
+const CLUSTER_DEPOSIT: Balance = 0;
+
+/// The Phala settings of a network profile.
+#[derive(Debug, Clone, serde::Deserialize)]
+pub struct PhalaProfile {
+    /// The hex encoded id of the cluster to deploy the contracts to.
+    pub cluster: String,
+}
+
+/// Deploys contracts to a cluster of Phala workers.
+#[derive(Debug, Clone)]
+pub struct PhalaBackend {
+    cluster: CodeHash,
+}
+
+impl PhalaBackend {
+    /// Create the backend for the cluster configured in the profile.
+    pub fn from_profile(profile: &PhalaProfile) -> Result<Self> {
+        let cluster = parse_code_hash(&profile.cluster)
+            .with_context(|| format!("Invalid Phala cluster id {}", profile.cluster))?;
+        Ok(Self { cluster })
+    }
+
+    /// The id of the cluster the contracts are deployed to.
+    pub fn cluster(&self) -> CodeHash {
+        self.cluster
+    }
+
+    /// Create the call uploading the Wasm code of a contract to the cluster.
+    pub fn upload_code_call(&self, code: Vec<u8>) -> DynamicPayload {
+        subxt::dynamic::tx(
+            PHAT_CONTRACTS_PALLET,
+            "cluster_upload_resource",
+            vec![
+                Value::from_bytes(self.cluster.0),
+                Value::unnamed_variant("InkCode", []),
+                Value::from_bytes(code),
+            ],
+        )
+    }
+
+    /// Create the call instantiating a contract from uploaded code in the cluster.
+    #[allow(clippy::too_many_arguments)]
+    pub fn instantiate_call(
+        &self,
+        code_hash: CodeHash,
+        data: Vec<u8>,
+        salt: Vec<u8>,
+        transfer: Balance,
+        gas_limit: u64,
+        storage_deposit_limit: Option<Balance>,
+    ) -> DynamicPayload {
+        let storage_deposit_limit = match storage_deposit_limit {
+            Some(limit) => Value::unnamed_variant("Some", [Value::u128(limit)]),
+            None => Value::unnamed_variant("None", []),
+        };
+        subxt::dynamic::tx(
+            PHAT_CONTRACTS_PALLET,
+            "instantiate_contract",
+            vec![
+                Value::unnamed_variant("WasmCode", [Value::from_bytes(code_hash.0)]),
+                Value::from_bytes(data),
+                Value::from_bytes(salt),
+                Value::from_bytes(self.cluster.0),
+                Value::u128(transfer),
+                Value::u128(gas_limit.into()),
+                storage_deposit_limit,
+                Value::u128(CLUSTER_DEPOSIT),
+            ],
+        )
+    }
+}
+
+/// Returns the id of the contract instantiated by the extrinsic, taken from its
+/// `Instantiating` event.
+pub fn instantiated_contract(
+    events: &ExtrinsicEvents<DefaultConfig>,
+) -> Result<AccountId32> {
+    for event in events.iter() {
+        let event = event?;
+        if event.pallet_name() == PHAT_CONTRACTS_PALLET
+            && event.variant_name() == INSTANTIATING_EVENT
+        {
+            // the id of the contract is the first field, followed by the cluster and
+            // the deployer
+            let contract = <[u8; 32]>::decode(&mut event.field_bytes())?;
+            return Ok(contract.into())
+        }
+    }
+    anyhow::bail!("Failed to find the {INSTANTIATING_EVENT} event of the contract")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cmd::config::{
+        Backend,
+        Config,
+    };
+
+    #[test]
+    fn phala_profile_is_parsed() {
+        let config = Config::parse(
+            r#"
+            [network.phala]
+            url = "ws://localhost:19944"
+            backend = "phala"
+
+            [network.phala.phala]
+            cluster = "0x0000000000000000000000000000000000000000000000000000000000000001"
+            "#,
+        )
+        .expect("config must be valid");
+
+        let profile = config.network("phala").unwrap();
+        assert_eq!(profile.backend, Backend::Phala);
+        let backend = PhalaBackend::from_profile(profile.phala.as_ref().unwrap())
+            .expect("cluster id must be valid");
+        assert_eq!(backend.cluster().0[31], 1);
+    }
+
+    #[test]
+    fn invalid_cluster_id_is_rejected() {
+        let profile = PhalaProfile {
+            cluster: String::from("0x01"),
+        };
+        assert!(PhalaBackend::from_profile(&profile).is_err());
+    }
+}
@@ -0,0 +1,113 @@
+// Copyright 2018-2023 Parity Technologies (UK) Ltd.
+// This file is part of cargo-contract.
+//
+// cargo-contract is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// cargo-contract is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with cargo-contract.  If not, see <http://www.gnu.org/licenses/>.
+
+//! An on-disk cache of the results of dry-run calls, keyed by the block they were
+//! executed at.
+//!
+//! The result of a dry-run only depends on the state of the block it was executed at, so
+//! identical dry-runs at the same block can be answered from the cache. The cache is laid
+//! out as `<dir>/<node>/<block hash>/<request hash>`, where the node and the request are
+//! identified by the hash of the url and of the encoded request. Only the results of the
+//! latest block are kept: storing a result prunes those of the other blocks of the node.
+
+use super::DefaultConfig;
+use anyhow::{
+    Context,
+    Result,
+};
+use sp_core::hashing::blake2_256;
+use std::path::{
+    Path,
+    PathBuf,
+};
+use subxt::Config;
+
+type Hash = <DefaultConfig as Config>::Hash;
+
+/// The cache of the dry-run results of a node.
+pub struct ReadCache {
+    node_dir: PathBuf,
+}
+
+impl ReadCache {
+    /// Open the cache in the given directory for the node at the url.
+    pub fn new(dir: &Path, url: &str) -> Self {
+        let node = hex::encode(&blake2_256(url.as_bytes())[..8]);
+        Self {
+            node_dir: dir.join(node),
+        }
+    }
+
+    fn path(&self, block_hash: &Hash, request: &[u8]) -> PathBuf {
+        self.node_dir
+            .join(hex::encode(block_hash))
+            .join(hex::encode(blake2_256(request)))
+    }
+
+    /// Returns the cached result of the request at the block, if there is one.
+    pub fn get(&self, block_hash: &Hash, request: &[u8]) -> Option<Vec<u8>> {
+        std::fs::read(self.path(block_hash, request)).ok()
+    }
+
+    /// Store the result of the request at the block, pruning the results of other blocks.
+    pub fn insert(&self, block_hash: &Hash, request: &[u8], result: &[u8]) -> Result<()> {
+        let path = self.path(block_hash, request);
+        let block_dir = path.parent().expect("the path has a block directory; qed");
+        if self.node_dir.exists() {
+            for entry in std::fs::read_dir(&self.node_dir)? {
+                let entry = entry?;
+                if entry.path() != block_dir {
+                    // Another process may be pruning the same results.
+                    match std::fs::remove_dir_all(entry.path()) {
+                        Err(err) if err.kind() != std::io::ErrorKind::NotFound => {
+                            return Err(err.into())
+                        }
+                        _ => {}
+                    }
+                }
+            }
+        }
+        std::fs::create_dir_all(block_dir).with_context(|| {
+            format!("Failed to create the read cache {}", block_dir.display())
+        })?;
+        crate::cmd::lock::write_atomic(&path, result)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn results_of_the_latest_block_are_cached() {
+        let dir = tempfile::tempdir().unwrap();
+        let cache = ReadCache::new(dir.path(), "ws://localhost:9944/");
+        let (old_block, block) = (Hash::repeat_byte(1), Hash::repeat_byte(2));
+
+        assert_eq!(cache.get(&old_block, b"get"), None);
+        cache.insert(&old_block, b"get", b"old").unwrap();
+        assert_eq!(cache.get(&old_block, b"get"), Some(b"old".to_vec()));
+
+        cache.insert(&block, b"get", b"new").unwrap();
+        cache.insert(&block, b"flip", b"flipped").unwrap();
+        assert_eq!(cache.get(&block, b"get"), Some(b"new".to_vec()));
+        assert_eq!(cache.get(&block, b"flip"), Some(b"flipped".to_vec()));
+        assert_eq!(cache.get(&old_block, b"get"), None);
+
+        let other_node = ReadCache::new(dir.path(), "wss://rpc.example.com/");
+        assert_eq!(other_node.get(&block, b"get"), None);
+    }
+}
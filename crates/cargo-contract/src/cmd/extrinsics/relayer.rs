@@ -0,0 +1,221 @@
+// Copyright 2018-2023 Parity Technologies (UK) Ltd.
+// This file is part of cargo-contract.
+//
+// cargo-contract is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// cargo-contract is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with cargo-contract.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Submission of calls through a relayer.
+//!
+//! With `call --relayer <URL>` the signer does not submit the call itself. Instead it
+//! signs the call as a meta-transaction, which is sent to the relayer with a JSON-RPC
+//! request. The relayer submits the call on-chain and pays its fees, e.g. for contracts
+//! offering gasless calls to their users.
+
+use super::{
+    Client,
+    DefaultConfig,
+    PairSigner,
+};
+use crate::{
+    cmd::{
+        pacing,
+        Balance,
+    },
+    name_value_println,
+    DEFAULT_KEY_COL_WIDTH,
+};
+use anyhow::{
+    Context,
+    Result,
+};
+use jsonrpsee::{
+    core::client::ClientT,
+    http_client::HttpClientBuilder,
+    rpc_params,
+    ws_client::WsClientBuilder,
+};
+use scale::Encode;
+use sp_core::Pair;
+use sp_weights::Weight;
+use subxt::Config;
+
+type AccountId = <DefaultConfig as Config>::AccountId;
+type Hash = <DefaultConfig as Config>::Hash;
+
+/// The JSON-RPC method relayers are called with by default.
+const DEFAULT_RELAYER_METHOD: &str = "relayer_submit";
+
+/// Options for submitting calls through a relayer.
+#[derive(Debug, clap::Args)]
+pub struct RelayerOpts {
+    /// Send the signed call to the relayer at the given http(s) or ws(s) url, which
+    /// submits it and pays its fees, instead of submitting it with the signer.
+    #[clap(long, value_name = "URL")]
+    relayer: Option<url::Url>,
+    /// The JSON-RPC method the signed call is sent to the relayer with.
+    #[clap(long, default_value = DEFAULT_RELAYER_METHOD, requires = "relayer")]
+    relayer_method: String,
+}
+
+/// The fields of a call signed for a relayer, signed in their SCALE encoding in the
+/// order of declaration.
+#[derive(Debug, Encode)]
+pub struct RelayPayload {
+    /// The genesis hash of the chain, so the call can not be replayed on other chains.
+    pub genesis_hash: Hash,
+    /// The number of the best block when the call was signed.
+    pub block_number: u64,
+    /// The contract to call.
+    pub contract: AccountId,
+    /// The value transferred to the contract.
+    pub value: Balance,
+    /// The gas limit of the call.
+    pub gas_limit: Weight,
+    /// The storage deposit limit of the call.
+    pub storage_deposit_limit: Option<Balance>,
+    /// The encoded message and arguments.
+    pub data: Vec<u8>,
+}
+
+/// A call signed for a relayer, as sent to the relayer.
+#[derive(Debug, serde::Serialize)]
+struct MetaTransaction {
+    genesis_hash: String,
+    block_number: u64,
+    contract: String,
+    origin: String,
+    value: String,
+    ref_time: u64,
+    proof_size: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    storage_deposit_limit: Option<String>,
+    data: String,
+    signature: String,
+}
+
+impl MetaTransaction {
+    fn new(payload: &RelayPayload, signer: &PairSigner) -> Self {
+        let signature = signer.key().sign(&payload.encode());
+        Self {
+            genesis_hash: format!("{:?}", payload.genesis_hash),
+            block_number: payload.block_number,
+            contract: payload.contract.to_string(),
+            origin: signer.account_id().to_string(),
+            value: payload.value.to_string(),
+            ref_time: payload.gas_limit.ref_time(),
+            proof_size: payload.gas_limit.proof_size(),
+            storage_deposit_limit: payload.storage_deposit_limit.map(|l| l.to_string()),
+            data: format!("0x{}", hex::encode(&payload.data)),
+            signature: format!("0x{}", hex::encode(signature)),
+        }
+    }
+}
+
+impl RelayerOpts {
+    /// Returns `true` if calls should be submitted through the relayer.
+    pub fn enabled(&self) -> bool {
+        self.relayer.is_some()
+    }
+
+    /// Prepare the payload of a call at the best block of the chain.
+    pub async fn payload(
+        &self,
+        client: &Client,
+        contract: AccountId,
+        value: Balance,
+        gas_limit: Weight,
+        storage_deposit_limit: Option<Balance>,
+        data: Vec<u8>,
+    ) -> Result<RelayPayload> {
+        let block_number = client.blocks().at_latest().await?.number();
+        Ok(RelayPayload {
+            genesis_hash: client.genesis_hash(),
+            block_number: block_number.into(),
+            contract,
+            value,
+            gas_limit,
+            storage_deposit_limit,
+            data,
+        })
+    }
+
+    /// Sign the payload and send it to the relayer, returns the response of the relayer.
+    pub async fn relay(
+        &self,
+        payload: &RelayPayload,
+        signer: &PairSigner,
+    ) -> Result<serde_json::Value> {
+        let relayer = self
+            .relayer
+            .as_ref()
+            .expect("the relayer is only used if enabled; qed");
+        let meta_transaction = MetaTransaction::new(payload, signer);
+        let params = rpc_params![meta_transaction];
+        pacing::pace().await;
+        let response = match relayer.scheme() {
+            "ws" | "wss" => {
+                WsClientBuilder::default()
+                    .build(relayer.as_str())
+                    .await?
+                    .request(&self.relayer_method, params)
+                    .await
+            }
+            _ => {
+                HttpClientBuilder::default()
+                    .build(relayer.as_str())?
+                    .request(&self.relayer_method, params)
+                    .await
+            }
+        };
+        response.with_context(|| format!("The relayer at {relayer} rejected the call"))
+    }
+
+    pub fn print_details(&self) {
+        if let Some(ref relayer) = self.relayer {
+            name_value_println!("Relayer", relayer.as_str(), DEFAULT_KEY_COL_WIDTH);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn meta_transactions_are_signed_by_the_origin() {
+        let pair = sp_core::sr25519::Pair::from_string("//Alice", None).unwrap();
+        let signer = super::super::pair_signer(pair.clone());
+        let payload = RelayPayload {
+            genesis_hash: Hash::repeat_byte(1),
+            block_number: 42,
+            contract: AccountId::from([2; 32]),
+            value: 1_000,
+            gas_limit: Weight::from_parts(10, 20),
+            storage_deposit_limit: None,
+            data: vec![0xde, 0xad],
+        };
+        let meta_transaction = MetaTransaction::new(&payload, &signer);
+        assert_eq!(meta_transaction.data, "0xdead");
+        assert_eq!(meta_transaction.origin, signer.account_id().to_string());
+        assert_eq!(meta_transaction.value, "1000");
+
+        let signature = hex::decode(&meta_transaction.signature[2..]).unwrap();
+        let signature =
+            sp_core::sr25519::Signature::from_slice(&signature).expect("64 bytes");
+        assert!(sp_core::sr25519::Pair::verify(
+            &signature,
+            payload.encode(),
+            &pair.public()
+        ));
+    }
+}
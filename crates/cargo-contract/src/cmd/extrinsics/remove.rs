@@ -15,12 +15,12 @@
 // along with cargo-contract.  If not, see <http://www.gnu.org/licenses/>.
 
 use super::{
-    submit_extrinsic,
+    display_dry_run_result_warning,
     Client,
     ContractMessageTranscoder,
     DefaultConfig,
     ExtrinsicOpts,
-    PairSigner,
+    ExtrinsicSigner,
     TokenMetadata,
 };
 use crate::{
@@ -30,15 +30,19 @@ use crate::{
             parse_code_hash,
             ErrorVariant,
         },
+        identity::Identities,
         runtime_api::api::{
             self,
             contracts::events::CodeRemoved,
         },
+        schema::OutputSchema,
+        AsyncCommand,
         CodeHash,
     },
     name_value_println,
 };
 use anyhow::Result;
+use async_trait::async_trait;
 use std::fmt::Debug;
 use subxt::{
     Config,
@@ -56,6 +60,9 @@ pub struct RemoveCommand {
     /// Export the call output as JSON.
     #[clap(long, conflicts_with = "verbose")]
     output_json: bool,
+    /// Print the JSON Schema of the `--output-json` output and exit.
+    #[clap(long)]
+    schema: bool,
 }
 
 impl RemoveCommand {
@@ -63,10 +70,64 @@ impl RemoveCommand {
         self.output_json
     }
 
-    pub fn run(&self) -> Result<(), ErrorVariant> {
+    async fn remove_code(
+        &self,
+        client: &Client,
+        code_hash: CodeHash,
+        signer: &ExtrinsicSigner,
+        transcoder: &ContractMessageTranscoder,
+    ) -> Result<Option<CodeRemoved>, ErrorVariant> {
+        let call = api::tx()
+            .contracts()
+            .remove_code(sp_core::H256(code_hash.0));
+
+        let token_metadata = TokenMetadata::query(client).await?;
+        let submit_options = self.extrinsic_opts.submit_options(&token_metadata)?;
+        let result = signer.submit(client, &call, &submit_options).await?;
+        let display_events = DisplayEvents::from_events(
+            &result,
+            Some(transcoder),
+            &client.metadata(),
+            &token_metadata,
+        )?;
+
+        let output = if self.output_json {
+            display_events.to_json()?
+        } else {
+            display_events.display_events(
+                self.extrinsic_opts.verbosity()?,
+                self.extrinsic_opts.number_format,
+                self.extrinsic_opts.max_output_bytes,
+            )?
+        };
+        println!("{output}");
+        let code_removed = result.find_first::<CodeRemoved>()?;
+        Ok(code_removed)
+    }
+}
+
+#[async_trait(?Send)]
+impl AsyncCommand for RemoveCommand {
+    type Output = Result<(), ErrorVariant>;
+
+    async fn exec(&self) -> Result<(), ErrorVariant> {
+        if self.schema {
+            return Ok(OutputSchema::Events.print()?)
+        }
+        if self.extrinsic_opts.phala_backend()?.is_some() {
+            return Err(anyhow::anyhow!(
+                "Removing code is not supported by the `phala` backend"
+            )
+            .into())
+        }
+        self.extrinsic_opts.apply_process_opts()?;
+        self.extrinsic_opts.ensure_not_read_only("remove")?;
         let artifacts = self.extrinsic_opts.contract_artifacts()?;
-        let transcoder = artifacts.contract_transcoder()?;
-        let signer = super::pair_signer(self.extrinsic_opts.signer()?);
+        let identities = Identities::default();
+        let transcoder = artifacts
+            .contract_transcoder_with_identities(&identities)?
+            .with_bytes_format(self.extrinsic_opts.bytes_format());
+        let signer = self.extrinsic_opts.online_signer("remove")?;
 
         let artifacts_path = artifacts.artifact_path().to_path_buf();
 
@@ -87,61 +148,47 @@ impl RemoveCommand {
             }
         }?;
 
-        async_std::task::block_on(async {
-            let url = self.extrinsic_opts.url_to_string();
-            let client = OnlineClient::from_url(url.clone()).await?;
-            if let Some(code_removed) = self
-                .remove_code(
-                    &client,
-                    sp_core::H256(final_code_hash),
-                    &signer,
-                    &transcoder,
-                )
-                .await?
-            {
-                let remove_result = code_removed.code_hash;
+        let url = self.extrinsic_opts.url_to_string()?;
+        let client = OnlineClient::from_url(url.clone()).await?;
+        identities.resolve(&client).await?;
+        let print_details = || {
+            name_value_println!(
+                "Code hash",
+                format!("0x{}", hex::encode(final_code_hash))
+            );
+        };
+        if !self.extrinsic_opts.execute()? {
+            if !self.output_json {
+                print_details();
+                display_dry_run_result_warning("remove");
+            }
+            return Ok(())
+        }
+        self.extrinsic_opts.confirm(print_details)?;
+        if let Some(code_removed) = self
+            .remove_code(
+                &client,
+                sp_core::H256(final_code_hash),
+                &signer,
+                &transcoder,
+            )
+            .await?
+        {
+            let remove_result = code_removed.code_hash;
 
-                if self.output_json {
-                    println!("{}", &remove_result);
-                } else {
-                    name_value_println!("Code hash", format!("{remove_result:?}"));
-                }
-                Result::<(), ErrorVariant>::Ok(())
+            if self.output_json {
+                println!("{}", &remove_result);
             } else {
-                let error_code_hash = hex::encode(final_code_hash);
-                Err(anyhow::anyhow!(
-                    "Error removing the code for the supplied code hash: {}",
-                    error_code_hash
-                )
-                .into())
+                name_value_println!("Code hash", format!("{remove_result:?}"));
             }
-        })
-    }
-
-    async fn remove_code(
-        &self,
-        client: &Client,
-        code_hash: CodeHash,
-        signer: &PairSigner,
-        transcoder: &ContractMessageTranscoder,
-    ) -> Result<Option<CodeRemoved>, ErrorVariant> {
-        let call = api::tx()
-            .contracts()
-            .remove_code(sp_core::H256(code_hash.0));
-
-        let result = submit_extrinsic(client, &call, signer).await?;
-        let display_events =
-            DisplayEvents::from_events(&result, Some(transcoder), &client.metadata())?;
-
-        let output = if self.output_json {
-            display_events.to_json()?
+            Result::<(), ErrorVariant>::Ok(())
         } else {
-            let token_metadata = TokenMetadata::query(client).await?;
-            display_events
-                .display_events(self.extrinsic_opts.verbosity()?, &token_metadata)?
-        };
-        println!("{output}");
-        let code_removed = result.find_first::<CodeRemoved>()?;
-        Ok(code_removed)
+            let error_code_hash = hex::encode(final_code_hash);
+            Err(anyhow::anyhow!(
+                "Error removing the code for the supplied code hash: {}",
+                error_code_hash
+            )
+            .into())
+        }
     }
 }
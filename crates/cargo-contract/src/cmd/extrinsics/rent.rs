@@ -0,0 +1,499 @@
+// Copyright 2018-2023 Parity Technologies (UK) Ltd.
+// This file is part of cargo-contract.
+//
+// cargo-contract is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// cargo-contract is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with cargo-contract.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Storage rent of contracts on chains running a legacy `pallet-contracts`.
+//!
+//! Older versions of the pallet charged contracts rent for their storage. A contract
+//! which can not pay the rent is evicted, by anyone claiming the surcharge with
+//! `Contracts::claim_surcharge` for a reward: its storage is removed and a tombstone is
+//! left at its address, the hash of the root of its child trie and its code hash.
+//!
+//! A tombstoned contract is restored by another contract, the restorer, calling
+//! `seal_restore_to` with the code hash and a delta of storage keys: the restorer takes
+//! the place of the tombstone if its storage without the keys of the delta hashes to the
+//! tombstone. `rent restore` checks this before the restorer is called.
+//!
+//! The commands are only available on chains whose `Contracts` pallet still has the
+//! `claim_surcharge` call. Like every command, they require the node to expose V14
+//! metadata.
+
+use super::{
+    display_dry_run_result_warning,
+    pair_signer,
+    submit_dynamic_call,
+    Client,
+    DefaultConfig,
+    ErrorVariant,
+    ExtrinsicOpts,
+};
+use crate::{
+    cmd::{
+        identity::collect_bytes,
+        storage::read_child_storage,
+        AsyncCommand,
+        Balance,
+        CodeHash,
+    },
+    DEFAULT_KEY_COL_WIDTH,
+};
+use anyhow::{
+    anyhow,
+    Result,
+};
+use async_trait::async_trait;
+use contract_build::name_value_println;
+use scale::{
+    Decode,
+    Encode,
+};
+use sp_core::{
+    hashing::{
+        blake2_256,
+        twox_128,
+    },
+    Blake2Hasher,
+    Bytes,
+    H256,
+};
+use sp_trie::{
+    LayoutV0,
+    TrieConfiguration,
+};
+use subxt::{
+    dynamic::Value,
+    ext::scale_value::{
+        At,
+        ValueDef,
+    },
+    Config,
+    OnlineClient,
+};
+
+type AccountId = <DefaultConfig as Config>::AccountId;
+
+const CONTRACTS_PALLET: &str = "Contracts";
+
+#[derive(Debug, clap::Args)]
+#[clap(
+    name = "rent",
+    about = "Storage rent helpers for chains running a legacy pallet-contracts"
+)]
+pub struct RentCommand {
+    #[clap(subcommand)]
+    action: RentAction,
+}
+
+#[derive(Debug, clap::Subcommand)]
+#[allow(clippy::large_enum_variant)]
+enum RentAction {
+    /// Claim the surcharge of a contract whose rent is due, evicting it for a reward
+    ClaimSurcharge {
+        /// The address of the contract.
+        #[clap(name = "contract", long, env = "CONTRACT")]
+        contract: AccountId,
+        #[clap(flatten)]
+        extrinsic_opts: ExtrinsicOpts,
+        /// Export the output in JSON format.
+        #[clap(long, conflicts_with = "verbose")]
+        output_json: bool,
+    },
+    /// Check that a restorer contract can restore a tombstoned contract, and print the
+    /// arguments of its `seal_restore_to` call
+    Restore {
+        /// The address of the tombstoned contract.
+        #[clap(name = "contract", long, env = "CONTRACT")]
+        contract: AccountId,
+        /// The address of the alive contract restoring the tombstone.
+        #[clap(long)]
+        restorer: AccountId,
+        /// The hash of the code of the tombstoned contract.
+        #[clap(long, value_parser = super::parse_code_hash)]
+        code_hash: CodeHash,
+        /// The storage keys of the restorer which are not part of the restored storage,
+        /// hex encoded.
+        #[clap(long, value_parser = parse_storage_key)]
+        delta: Vec<Bytes>,
+        /// Websockets url of a substrate node.
+        #[clap(long, value_parser, default_value = "ws://localhost:9944")]
+        url: url::Url,
+    },
+}
+
+fn parse_storage_key(input: &str) -> Result<Bytes> {
+    Ok(Bytes(hex::decode(input.trim_start_matches("0x"))?))
+}
+
+impl RentCommand {
+    pub fn is_json(&self) -> bool {
+        matches!(
+            self.action,
+            RentAction::ClaimSurcharge {
+                output_json: true,
+                ..
+            }
+        )
+    }
+}
+
+#[async_trait(?Send)]
+impl AsyncCommand for RentCommand {
+    type Output = Result<(), ErrorVariant>;
+
+    async fn exec(&self) -> Result<(), ErrorVariant> {
+        match self.action {
+            RentAction::ClaimSurcharge {
+                ref contract,
+                ref extrinsic_opts,
+                output_json,
+            } => claim_surcharge(contract, extrinsic_opts, output_json).await,
+            RentAction::Restore {
+                ref contract,
+                ref restorer,
+                code_hash,
+                ref delta,
+                ref url,
+            } => {
+                let client = OnlineClient::from_url(url.clone()).await?;
+                check_restoration(&client, contract, restorer, code_hash, delta).await?;
+                Ok(())
+            }
+        }
+    }
+}
+
+async fn claim_surcharge(
+    contract: &AccountId,
+    opts: &ExtrinsicOpts,
+    output_json: bool,
+) -> Result<(), ErrorVariant> {
+    opts.apply_process_opts()?;
+    let signer = pair_signer(opts.signer("rent")?);
+
+    let client = OnlineClient::from_url(opts.url_to_string()?).await?;
+    let version = ensure_storage_rent(&client).await?;
+    let info = fetch_legacy_contract_info(&client, contract)
+        .await?
+        .ok_or_else(|| anyhow!("No contract was found at {contract}"))?;
+    if let LegacyContractInfo::Tombstone { .. } = info {
+        return Err(anyhow!("The contract {contract} is already a tombstone").into())
+    }
+
+    let print_details = || {
+        name_value_println!(
+            "Call",
+            format!("{CONTRACTS_PALLET}::claim_surcharge"),
+            DEFAULT_KEY_COL_WIDTH
+        );
+        name_value_println!("Contract", contract.to_string(), DEFAULT_KEY_COL_WIDTH);
+        name_value_println!("Pallet version", version.to_string(), DEFAULT_KEY_COL_WIDTH);
+    };
+    if !opts.execute()? {
+        print_details();
+        display_dry_run_result_warning("rent claim-surcharge");
+        return Ok(())
+    }
+    opts.confirm(print_details)?;
+    // The reward goes to the signer, the auxiliary sender is only for unsigned
+    // claims of block authors.
+    let call = subxt::dynamic::tx(
+        CONTRACTS_PALLET,
+        "claim_surcharge",
+        vec![
+            Value::from_bytes(contract.0),
+            Value::unnamed_variant("None", []),
+        ],
+    );
+    let output = submit_dynamic_call(&client, &call, &signer, opts, output_json).await?;
+    println!("{output}");
+    Ok(())
+}
+
+/// Check that the restorer can restore the tombstone with the delta, and print the
+/// arguments of its `seal_restore_to` call.
+async fn check_restoration(
+    client: &Client,
+    contract: &AccountId,
+    restorer: &AccountId,
+    code_hash: CodeHash,
+    delta: &[Bytes],
+) -> Result<()> {
+    ensure_storage_rent(client).await?;
+    let Some(LegacyContractInfo::Tombstone { tombstone }) =
+        fetch_legacy_contract_info(client, contract).await?
+    else {
+        anyhow::bail!("The contract {contract} is not a tombstone")
+    };
+    let Some(LegacyContractInfo::Alive { trie_id, .. }) =
+        fetch_legacy_contract_info(client, restorer).await?
+    else {
+        anyhow::bail!("The restorer {restorer} is not an alive contract")
+    };
+    let block_hash = client.blocks().at_latest().await?.hash();
+    let pairs = read_child_storage(client, &trie_id, block_hash).await?;
+    let restored = restored_tombstone(pairs, delta, &code_hash);
+    anyhow::ensure!(
+        restored == tombstone,
+        "The storage of the restorer without the delta does not match the tombstone \
+        {tombstone:?}: it hashes to {restored:?} with the code hash {code_hash:?}. Check \
+        the code hash and the keys of the delta."
+    );
+
+    name_value_println!("Tombstone", format!("{tombstone:?}"), DEFAULT_KEY_COL_WIDTH);
+    name_value_println!("dest", contract.to_string(), DEFAULT_KEY_COL_WIDTH);
+    name_value_println!("code_hash", format!("{code_hash:?}"), DEFAULT_KEY_COL_WIDTH);
+    name_value_println!(
+        "delta",
+        format!(
+            "[{}]",
+            delta
+                .iter()
+                .map(|key| format!("0x{}", hex::encode(&key.0)))
+                .collect::<Vec<_>>()
+                .join(", ")
+        ),
+        DEFAULT_KEY_COL_WIDTH
+    );
+    println!(
+        "The restorer {restorer} can restore the contract by calling `seal_restore_to` \
+        with these arguments."
+    );
+    Ok(())
+}
+
+/// Returns the tombstone the storage would leave with the code hash, after the keys of
+/// the delta are removed from it.
+fn restored_tombstone(
+    mut pairs: Vec<(Vec<u8>, Vec<u8>)>,
+    delta: &[Bytes],
+    code_hash: &CodeHash,
+) -> H256 {
+    // Legacy child tries store the values under the `blake2_256` hash of their key.
+    let removed = delta
+        .iter()
+        .map(|key| blake2_256(&key.0).to_vec())
+        .collect::<Vec<_>>();
+    pairs.retain(|(key, _)| !removed.contains(key));
+    let root = LayoutV0::<Blake2Hasher>::trie_root(pairs);
+    tombstone_hash(root.as_bytes(), code_hash)
+}
+
+/// Returns the tombstone of a contract with the storage root and code hash.
+fn tombstone_hash(storage_root: &[u8], code_hash: &CodeHash) -> H256 {
+    H256((storage_root, code_hash).using_encoded(blake2_256))
+}
+
+/// The storage version of the `Contracts` pallet, if it still charges rent for the
+/// storage of contracts, otherwise `None`.
+pub async fn storage_rent_version(client: &Client) -> Result<Option<u16>> {
+    let has_rent = client
+        .metadata()
+        .pallet(CONTRACTS_PALLET)
+        .is_ok_and(|pallet| pallet.call("claim_surcharge").is_ok());
+    if !has_rent {
+        return Ok(None)
+    }
+    let key = [
+        twox_128(CONTRACTS_PALLET.as_bytes()),
+        twox_128(b":__STORAGE_VERSION__:"),
+    ]
+    .concat();
+    let version = client.storage().at_latest().await?.fetch_raw(&key).await?;
+    Ok(Some(match version {
+        Some(version) => u16::decode(&mut &version[..])?,
+        None => 0,
+    }))
+}
+
+async fn ensure_storage_rent(client: &Client) -> Result<u16> {
+    storage_rent_version(client).await?.ok_or_else(|| {
+        anyhow!(
+            "The `Contracts` pallet of the chain does not charge storage rent, contracts \
+            are not evicted"
+        )
+    })
+}
+
+/// The info of a contract in a legacy `Contracts` pallet.
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize)]
+#[serde(tag = "state", rename_all = "snake_case")]
+pub enum LegacyContractInfo {
+    Alive {
+        trie_id: Bytes,
+        code_hash: CodeHash,
+        storage_size: u32,
+        pair_count: u32,
+        rent_allowance: Balance,
+        rent_paid: Balance,
+        deduct_block: u32,
+    },
+    Tombstone {
+        tombstone: H256,
+    },
+}
+
+impl LegacyContractInfo {
+    fn from_value<T: std::fmt::Debug>(value: &subxt::dynamic::Value<T>) -> Result<Self> {
+        let ValueDef::Variant(ref variant) = value.value else {
+            anyhow::bail!("Unexpected contract info {value:?}")
+        };
+        let inner = variant
+            .values
+            .values()
+            .next()
+            .ok_or_else(|| anyhow!("Unexpected contract info {value:?}"))?;
+        let bytes = |value: Option<&subxt::dynamic::Value<T>>| {
+            let mut bytes = Vec::new();
+            if let Some(value) = value {
+                collect_bytes(value, &mut bytes);
+            }
+            bytes
+        };
+        let hash = |value| {
+            let bytes = bytes(value);
+            (bytes.len() == 32)
+                .then(|| H256::from_slice(&bytes))
+                .ok_or_else(|| anyhow!("Unexpected hash in the contract info"))
+        };
+        let number = |field: &str| {
+            inner
+                .at(field)
+                .and_then(subxt::dynamic::Value::as_u128)
+                .ok_or_else(|| anyhow!("Missing `{field}` in the contract info"))
+        };
+        match variant.name.as_str() {
+            "Tombstone" => {
+                Ok(Self::Tombstone {
+                    tombstone: hash(Some(inner))?,
+                })
+            }
+            "Alive" => {
+                Ok(Self::Alive {
+                    trie_id: Bytes(bytes(inner.at("trie_id"))),
+                    code_hash: hash(inner.at("code_hash"))?,
+                    storage_size: number("storage_size")?.try_into()?,
+                    pair_count: number("pair_count")?.try_into()?,
+                    rent_allowance: number("rent_allowance")?,
+                    rent_paid: number("rent_paid")?,
+                    deduct_block: number("deduct_block")?.try_into()?,
+                })
+            }
+            name => anyhow::bail!("Unexpected contract info variant `{name}`"),
+        }
+    }
+
+    /// Display the info in the format of `info`.
+    pub fn display(&self) {
+        match self {
+            Self::Alive {
+                trie_id,
+                code_hash,
+                storage_size,
+                pair_count,
+                rent_allowance,
+                rent_paid,
+                deduct_block,
+            } => {
+                name_value_println!("TrieId:", hex::encode(&trie_id.0));
+                name_value_println!("Code hash:", format!("{code_hash:?}"));
+                name_value_println!("Storage size:", storage_size.to_string());
+                name_value_println!("Storage items:", pair_count.to_string());
+                name_value_println!("Rent allowance:", rent_allowance.to_string());
+                name_value_println!("Rent paid:", rent_paid.to_string());
+                name_value_println!("Rent deducted at:", deduct_block.to_string());
+            }
+            Self::Tombstone { tombstone } => {
+                name_value_println!("Tombstone:", format!("{tombstone:?}"));
+                println!(
+                    "The contract was evicted, it can be restored with `seal_restore_to`, \
+                    see `cargo contract rent restore`."
+                );
+            }
+        }
+    }
+}
+
+/// Fetch the info of the contract from a legacy `Contracts` pallet, if there is a
+/// contract or a tombstone at the address.
+pub async fn fetch_legacy_contract_info(
+    client: &Client,
+    contract: &AccountId,
+) -> Result<Option<LegacyContractInfo>> {
+    let address = subxt::dynamic::storage(
+        CONTRACTS_PALLET,
+        "ContractInfoOf",
+        vec![Value::from_bytes(contract.0)],
+    );
+    let info = client.storage().at_latest().await?.fetch(&address).await?;
+    info.map(|info| LegacyContractInfo::from_value(&info.to_value()?))
+        .transpose()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tombstones_are_decoded_and_recomputed() {
+        let hash_value = |hash: H256| Value::from_bytes(hash.0);
+        let code_hash = CodeHash::repeat_byte(7);
+        let pairs = vec![
+            (blake2_256(&[1]).to_vec(), vec![1]),
+            (blake2_256(&[2]).to_vec(), vec![2]),
+        ];
+        let root = LayoutV0::<Blake2Hasher>::trie_root(pairs[..1].to_vec());
+        let tombstone = tombstone_hash(root.as_bytes(), &code_hash);
+
+        let info = Value::unnamed_variant(
+            "Tombstone",
+            [Value::unnamed_composite([hash_value(tombstone)])],
+        );
+        assert_eq!(
+            LegacyContractInfo::from_value(&info).unwrap(),
+            LegacyContractInfo::Tombstone { tombstone }
+        );
+        assert_eq!(
+            restored_tombstone(pairs.clone(), &[Bytes(vec![2])], &code_hash),
+            tombstone
+        );
+        assert_ne!(restored_tombstone(pairs, &[], &code_hash), tombstone);
+
+        let alive = Value::unnamed_variant(
+            "Alive",
+            [Value::named_composite([
+                ("trie_id", Value::from_bytes([1, 2])),
+                ("storage_size", Value::u128(64)),
+                ("pair_count", Value::u128(2)),
+                ("code_hash", hash_value(code_hash)),
+                ("rent_allowance", Value::u128(1000)),
+                ("rent_paid", Value::u128(10)),
+                ("deduct_block", Value::u128(42)),
+                ("last_write", Value::unnamed_variant("None", [])),
+            ])],
+        );
+        assert_eq!(
+            LegacyContractInfo::from_value(&alive).unwrap(),
+            LegacyContractInfo::Alive {
+                trie_id: Bytes(vec![1, 2]),
+                code_hash,
+                storage_size: 64,
+                pair_count: 2,
+                rent_allowance: 1000,
+                rent_paid: 10,
+                deduct_block: 42,
+            }
+        );
+    }
+}
@@ -0,0 +1,152 @@
+// Copyright 2018-2023 Parity Technologies (UK) Ltd.
+// This file is part of cargo-contract.
+//
+// cargo-contract is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// cargo-contract is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with cargo-contract.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Instantiation salts which do not collide with existing contracts.
+//!
+//! The address of a contract is derived from the deployer, the code hash, the input
+//! data and the salt, so instantiating the same code with the same constructor
+//! arguments and salt twice fails with `DuplicateContract`. The salts used are recorded
+//! in the local registry, and a salt producing the address of an existing contract is
+//! either warned about or, with `--auto-salt`, incremented until the address is free.
+
+use super::{
+    Client,
+    DefaultConfig,
+};
+use crate::cmd::{
+    info::fetch_contract_info,
+    registry::Registry,
+    CodeHash,
+};
+use anyhow::Result;
+use colored::Colorize;
+use scale::Encode;
+use subxt::Config;
+
+type AccountId = <DefaultConfig as Config>::AccountId;
+
+/// The maximum number of salts tried by `--auto-salt`.
+const MAX_AUTO_SALT_ATTEMPTS: usize = 256;
+
+/// Returns the address of the contract instantiated with the salt, as derived by the
+/// default address generator of `pallet-contracts`.
+pub fn contract_address(
+    deployer: &AccountId,
+    code_hash: &CodeHash,
+    input_data: &[u8],
+    salt: &[u8],
+) -> AccountId {
+    let entropy = (b"contract_addr_v1", deployer, code_hash, input_data, salt)
+        .using_encoded(sp_core::blake2_256);
+    AccountId::from(entropy)
+}
+
+/// Returns the salt following the salt, as a big-endian number.
+fn next_salt(salt: &[u8]) -> Vec<u8> {
+    let mut next = salt.to_vec();
+    for byte in next.iter_mut().rev() {
+        let (sum, overflow) = byte.overflowing_add(1);
+        *byte = sum;
+        if !overflow {
+            return next
+        }
+    }
+    next.insert(0, 1);
+    next
+}
+
+fn display_salt(salt: &[u8]) -> String {
+    format!("0x{}", hex::encode(salt))
+}
+
+/// Returns the salt to instantiate the code with, incremented past the salts of
+/// existing contracts if `auto` is set. Otherwise a warning is printed if the salt
+/// would produce the address of an existing contract.
+pub async fn choose_salt(
+    client: &Client,
+    network: &str,
+    deployer: &AccountId,
+    code_hash: &CodeHash,
+    input_data: &[u8],
+    salt: Vec<u8>,
+    auto: bool,
+) -> Result<Vec<u8>> {
+    let registry = Registry::load()?;
+    let used = registry
+        .salts(code_hash, network, deployer)
+        .map(|record| record.salt.0.clone())
+        .collect::<Vec<_>>();
+    let mut candidate = salt.clone();
+    for _ in 0..MAX_AUTO_SALT_ATTEMPTS {
+        // Salts in the registry are known to be taken and skipped without a query.
+        let recorded = auto && used.contains(&candidate);
+        let address = contract_address(deployer, code_hash, input_data, &candidate);
+        if !recorded && fetch_contract_info(client, &address).await?.is_none() {
+            if candidate != salt {
+                eprintln!(
+                    "{} the salt {} is taken, using the salt {}",
+                    "note:".bold(),
+                    display_salt(&salt),
+                    display_salt(&candidate)
+                );
+            }
+            return Ok(candidate)
+        }
+        if !auto {
+            eprintln!(
+                "{} the salt {} produces the address {address} of an existing contract, \
+                the instantiation fails with `DuplicateContract`. Use a different \
+                `--salt` or `--auto-salt`.",
+                "warning:".yellow().bold(),
+                display_salt(&salt)
+            );
+            return Ok(salt)
+        }
+        candidate = next_salt(&candidate);
+    }
+    anyhow::bail!(
+        "No free salt found after {} within {MAX_AUTO_SALT_ATTEMPTS} attempts",
+        display_salt(&salt)
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+
+    #[test]
+    fn salts_are_incremented_and_addresses_derived() {
+        assert_eq!(next_salt(&[]), vec![1]);
+        assert_eq!(next_salt(&[0x00, 0xff]), vec![0x01, 0x00]);
+        assert_eq!(next_salt(&[0xff, 0xff]), vec![0x01, 0x00, 0x00]);
+
+        let alice =
+            AccountId::from_str("5GrwvaEF5zXb26Fz9rcQpDWS57CtERHpNehXCPcNoHGKutQY")
+                .unwrap();
+        let code_hash = CodeHash::repeat_byte(1);
+        let input_data = [0x9b, 0xae, 0x9d, 0x5e];
+        let address = contract_address(&alice, &code_hash, &input_data, &[]);
+        assert_eq!(
+            hex::encode(address.0),
+            "71a6f497853dc4986e14b45db5e100ee35cc885488c52bed5dcf5dfaf0992c7a"
+        );
+        assert_ne!(
+            address,
+            contract_address(&alice, &code_hash, &input_data, &[1])
+        );
+    }
+}
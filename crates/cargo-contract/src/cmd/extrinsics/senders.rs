@@ -0,0 +1,177 @@
+// Copyright 2018-2023 Parity Technologies (UK) Ltd.
+// This file is part of cargo-contract.
+//
+// cargo-contract is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// cargo-contract is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with cargo-contract.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Distributing repeated submissions of a call across several signers.
+//!
+//! The nonces of the signers are fetched once and tracked locally, so that all the
+//! submissions are signed and broadcast up front, without waiting for the previous ones
+//! to be included. The transactions of each signer are queued in the pool in the order
+//! of their nonces.
+
+use super::{
+    extrinsic_params,
+    Client,
+    DefaultConfig,
+    PairSigner,
+    SubmitOptions,
+};
+use anyhow::Result;
+use subxt::{
+    blocks::ExtrinsicEvents,
+    tx::{
+        self,
+        TxPayload,
+    },
+    Config,
+};
+
+type AccountId = <DefaultConfig as Config>::AccountId;
+type Index = <DefaultConfig as Config>::Index;
+
+/// The signers of repeated submissions, taking turns in round-robin order.
+pub struct Senders {
+    signers: Vec<PairSigner>,
+    /// The next nonce of each signer.
+    nonces: Vec<Index>,
+    /// The signer of the next submission.
+    next: usize,
+}
+
+/// A submission of a call by one of the [`Senders`].
+pub struct SenderSubmission {
+    pub signer: AccountId,
+    pub nonce: Index,
+    /// The events of the included extrinsic, or why it was not included.
+    pub result: Result<ExtrinsicEvents<DefaultConfig>, subxt::Error>,
+}
+
+impl Senders {
+    /// Fetch the next nonces of the signers from the chain.
+    pub async fn fetch(client: &Client, signers: Vec<PairSigner>) -> Result<Self> {
+        let mut nonces = Vec::with_capacity(signers.len());
+        for signer in &signers {
+            let account_id = tx::Signer::account_id(signer);
+            nonces.push(client.rpc().system_account_next_index(account_id).await?);
+        }
+        Ok(Self::new(signers, nonces))
+    }
+
+    fn new(signers: Vec<PairSigner>, nonces: Vec<Index>) -> Self {
+        assert!(!signers.is_empty(), "at least one signer is required");
+        Self {
+            signers,
+            nonces,
+            next: 0,
+        }
+    }
+
+    /// The index and the nonce of the signer of the next submission, the signers take
+    /// turns.
+    fn next(&mut self) -> (usize, Index) {
+        let index = self.next;
+        self.next = (self.next + 1) % self.signers.len();
+        (index, self.nonces[index])
+    }
+
+    /// Record that the transaction with the current nonce of the signer was accepted by
+    /// the pool. The nonce of a rejected transaction is used again by the next one.
+    fn confirm(&mut self, index: usize) {
+        self.nonces[index] += 1;
+    }
+
+    /// Sign and broadcast the call the given number of times, then wait for all the
+    /// transactions to be included. The submissions are returned in broadcast order.
+    pub async fn submit<Call: TxPayload>(
+        &mut self,
+        client: &Client,
+        call: &Call,
+        times: u32,
+        opts: &SubmitOptions,
+    ) -> Result<Vec<SenderSubmission>> {
+        let (params, _) = extrinsic_params(client, opts.mortality, opts.tip).await?;
+        let mut pending = Vec::with_capacity(times as usize);
+        for _ in 0..times {
+            let (index, nonce) = self.next();
+            let signer = &self.signers[index];
+            let account_id = tx::Signer::account_id(signer).clone();
+            let extrinsic = client
+                .tx()
+                .create_signed_with_nonce(call, signer, nonce, params)?;
+            let progress = extrinsic.submit_and_watch().await;
+            if progress.is_ok() {
+                self.confirm(index);
+            }
+            pending.push((account_id, nonce, progress));
+        }
+
+        let mut submissions = Vec::with_capacity(pending.len());
+        for (signer, nonce, progress) in pending {
+            let result = match progress {
+                Ok(progress) => {
+                    match progress.wait_for_in_block().await {
+                        Ok(in_block) => in_block.wait_for_success().await,
+                        Err(err) => Err(err),
+                    }
+                }
+                Err(err) => Err(err),
+            };
+            #[cfg(feature = "activity-cache")]
+            if let Ok(ref events) = result {
+                super::activity_cache::record_receipt(client, call, events).await;
+            }
+            submissions.push(SenderSubmission {
+                signer,
+                nonce,
+                result,
+            });
+        }
+        Ok(submissions)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cmd::extrinsics::pair_signer;
+    use sp_core::{
+        sr25519,
+        Pair,
+    };
+
+    #[test]
+    fn signers_take_turns_with_their_own_nonces() {
+        let signer = |suri| pair_signer(sr25519::Pair::from_string(suri, None).unwrap());
+        let mut senders = Senders::new(
+            vec![signer("//Alice"), signer("//Bob"), signer("//Charlie")],
+            vec![5, 0, 9],
+        );
+
+        let mut turns = Vec::new();
+        for turn in 0..7 {
+            let (index, nonce) = senders.next();
+            // The second transaction of Bob is rejected by the pool.
+            if turn != 4 {
+                senders.confirm(index);
+            }
+            turns.push((index, nonce));
+        }
+        assert_eq!(
+            turns,
+            [(0, 5), (1, 0), (2, 9), (0, 6), (1, 1), (2, 10), (0, 7)]
+        );
+        assert_eq!((senders.next(), senders.next()), ((1, 1), (2, 11)));
+    }
+}
@@ -0,0 +1,759 @@
+// Copyright 2018-2023 Parity Technologies (UK) Ltd.
+// This file is part of cargo-contract.
+//
+// cargo-contract is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// cargo-contract is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with cargo-contract.  If not, see <http://www.gnu.org/licenses/>.
+
+//! The signers of extrinsics, selected with `--signer`.
+//!
+//! - `suri:<SURI>` signs with a secret key URI, like `--suri`.
+//! - `keystore:<PATH>` signs with the key of a file of a Substrate keystore, which holds
+//!   the secret phrase or seed of the key. The password of the keystore is taken from
+//!   `--password`, or prompted for unless the key is held by a running `agent`. `account
+//!   generate` writes such key files for new keys, with the phrase encrypted by the
+//!   password like a polkadot-js account file.
+//! - `remote:<ACCOUNT>@<URL>` sends the signer payload of the extrinsic to a signing
+//!   service, as the `signRaw` request of the polkadot-js signer protocol over JSON-RPC.
+//!   The signature is verified before the extrinsic is submitted.
+//! - `ledger` selects the Polkadot app of a Ledger device. There is no USB HID transport
+//!   yet, the `--offline` workflow is suggested instead.
+//!
+//! The keys of remote signers and devices never enter cargo-contract: the extrinsic is
+//! built as a partial extrinsic, whose payload is signed by the signer and then attached
+//! to the extrinsic.
+
+use super::{
+    extrinsic_params,
+    simulate,
+    watch_extrinsic,
+    Client,
+    DefaultConfig,
+    Submission,
+    SubmitOptions,
+};
+use crate::cmd::{
+    agent,
+    config::SignerKind,
+    pacing,
+};
+use anyhow::{
+    anyhow,
+    Context,
+    Result,
+};
+use base64::{
+    engine::general_purpose::STANDARD as BASE64,
+    Engine as _,
+};
+use jsonrpsee::{
+    core::client::ClientT,
+    http_client::HttpClientBuilder,
+    rpc_params,
+    ws_client::WsClientBuilder,
+};
+use scale::Decode;
+use sp_core::{
+    crypto::{
+        key_types,
+        Ss58Codec,
+    },
+    sr25519,
+    Pair,
+};
+use sp_runtime::{
+    traits::Verify,
+    MultiSignature,
+};
+use std::{
+    convert::Infallible,
+    fmt,
+    io::{
+        self,
+        Write,
+    },
+    path::{
+        Path,
+        PathBuf,
+    },
+    str::FromStr,
+};
+use subxt::{
+    blocks::ExtrinsicEvents,
+    tx::{
+        self,
+        TxPayload,
+    },
+    Config,
+};
+use xsalsa20poly1305::{
+    aead::{
+        rand_core::RngCore,
+        Aead,
+        AeadCore,
+        KeyInit,
+        OsRng,
+    },
+    Key,
+    Nonce,
+    XSalsa20Poly1305,
+};
+use zeroize::Zeroizing;
+
+type AccountId = <DefaultConfig as Config>::AccountId;
+
+/// The JSON-RPC method remote signers are called with.
+const SIGN_RAW_METHOD: &str = "signer_signRaw";
+
+/// The scrypt parameters of encrypted key files, the defaults of polkadot-js: `N = 2^15`,
+/// `p = 1` and `r = 8`.
+const SCRYPT_LOG_N: u8 = 15;
+const SCRYPT_P: u32 = 1;
+const SCRYPT_R: u32 = 8;
+
+/// The largest scrypt parameters of key files which are decrypted, as a key file could
+/// otherwise make the key derivation take unbounded memory and time.
+const MAX_SCRYPT_LOG_N: u8 = 20;
+const MAX_SCRYPT_P: u32 = 1;
+const MAX_SCRYPT_R: u32 = 8;
+
+/// The length of the scrypt salt of encrypted key files.
+const SALT_LEN: usize = 32;
+
+/// The length of the xsalsa20-poly1305 nonce of encrypted key files.
+const NONCE_LEN: usize = 24;
+
+/// The signer selected with `--signer`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SignerSpec {
+    /// A secret key URI.
+    Suri(String),
+    /// A key file of a Substrate keystore.
+    Keystore(PathBuf),
+    /// A signing service holding the key of the account.
+    Remote { account: AccountId, url: url::Url },
+    /// The Polkadot app of a Ledger device.
+    Ledger,
+}
+
+impl FromStr for SignerSpec {
+    type Err = anyhow::Error;
+
+    fn from_str(input: &str) -> Result<Self> {
+        if input == "ledger" {
+            return Ok(Self::Ledger)
+        }
+        let (kind, value) = input.split_once(':').ok_or_else(|| {
+            anyhow!(
+                "Invalid signer `{input}`, expected `ledger`, `keystore:<PATH>`, \
+                `suri:<SURI>` or `remote:<ACCOUNT>@<URL>`"
+            )
+        })?;
+        match kind {
+            "suri" => Ok(Self::Suri(value.to_string())),
+            "keystore" => Ok(Self::Keystore(PathBuf::from(value))),
+            "remote" => {
+                let (account, url) = value.split_once('@').ok_or_else(|| {
+                    anyhow!("Invalid remote signer `{value}`, expected `<ACCOUNT>@<URL>`")
+                })?;
+                let account = account
+                    .parse()
+                    .map_err(|err| anyhow!("Invalid account {account}: {err:?}"))?;
+                let url = url::Url::parse(url)
+                    .with_context(|| format!("Invalid url of the remote signer {url}"))?;
+                Ok(Self::Remote { account, url })
+            }
+            _ => Err(anyhow!("Unknown signer `{kind}`")),
+        }
+    }
+}
+
+impl SignerSpec {
+    /// The way of signing, as restricted by the signer policy of network profiles.
+    pub fn kind(&self) -> SignerKind {
+        match self {
+            Self::Suri(_) => SignerKind::Suri,
+            Self::Keystore(_) => SignerKind::Keystore,
+            Self::Remote { .. } => SignerKind::Remote,
+            Self::Ledger => SignerKind::Ledger,
+        }
+    }
+
+    /// The key of the secret key URI or the keystore file, `None` for the signers
+    /// holding the key outside of cargo-contract.
+    pub fn key(&self, password: Option<&str>) -> Result<Option<SigningKey>> {
+        match self {
+            Self::Suri(suri) => {
+                sr25519::Pair::from_string(suri, password)
+                    .map(|pair| Some(pair.into()))
+                    .map_err(|_| anyhow!("Secret string error"))
+            }
+            Self::Keystore(path) => Ok(Some(keystore_key(path, password)?)),
+            Self::Remote { .. } | Self::Ledger => Ok(None),
+        }
+    }
+
+    /// The signer holding the key outside of cargo-contract.
+    pub fn remote(&self) -> Result<RemoteSigner> {
+        match self {
+            Self::Remote { account, url } => {
+                Ok(RemoteSigner {
+                    account: account.clone(),
+                    url: url.clone(),
+                })
+            }
+            Self::Ledger => {
+                Err(anyhow!(
+                    "Signing with a Ledger device is not supported by this build of \
+                    cargo-contract, it has no USB HID transport. Build the extrinsic with \
+                    `--offline <ACCOUNT>`, sign the payload with the Ledger and broadcast it \
+                    with `cargo contract submit`"
+                ))
+            }
+            Self::Suri(_) | Self::Keystore(_) => {
+                Err(anyhow!("The signer holds its key in cargo-contract"))
+            }
+        }
+    }
+}
+
+/// Load the key of a key file of a Substrate keystore, held by a running agent unless a
+/// password is given. The unlocked key is handed to the agent for later commands.
+fn keystore_key(path: &Path, password: Option<&str>) -> Result<SigningKey> {
+    if password.is_none() {
+        if let Some(key) = agent::cached_key(path) {
+            return Ok(SigningKey::Agent(key))
+        }
+    }
+    let pair = unlock_keystore(path, password)?;
+    agent::offer_key(path, &pair);
+    Ok(pair.into())
+}
+
+/// The key extrinsics are signed with: a key pair, or the key of a keystore file held by
+/// a running agent, which signs the payloads without handing out the key.
+#[derive(Clone)]
+pub enum SigningKey {
+    Pair(sr25519::Pair),
+    Agent(agent::AgentKey),
+}
+
+impl From<sr25519::Pair> for SigningKey {
+    fn from(pair: sr25519::Pair) -> Self {
+        Self::Pair(pair)
+    }
+}
+
+impl SigningKey {
+    /// The public key.
+    pub fn public(&self) -> sr25519::Public {
+        match self {
+            Self::Pair(pair) => pair.public(),
+            Self::Agent(key) => key.public(),
+        }
+    }
+
+    /// Sign the payload.
+    ///
+    /// # Panics
+    ///
+    /// If the agent holding the key fails to sign, e.g. because it was stopped or its
+    /// key expired during the command: signing extrinsics can not fail.
+    pub fn sign(&self, payload: &[u8]) -> sr25519::Signature {
+        match self {
+            Self::Pair(pair) => pair.sign(payload),
+            Self::Agent(key) => {
+                key.sign(payload).unwrap_or_else(|err| {
+                    panic!("The agent failed to sign the payload: {err:#}")
+                })
+            }
+        }
+    }
+}
+
+/// Signs extrinsics with a [`SigningKey`].
+#[derive(Clone)]
+pub struct KeySigner {
+    account_id: AccountId,
+    key: SigningKey,
+}
+
+impl KeySigner {
+    pub fn new(key: impl Into<SigningKey>) -> Self {
+        let key = key.into();
+        Self {
+            account_id: AccountId::from(key.public().0),
+            key,
+        }
+    }
+
+    /// The account of the key.
+    pub fn account_id(&self) -> &AccountId {
+        &self.account_id
+    }
+
+    /// The key the extrinsics are signed with.
+    pub fn key(&self) -> &SigningKey {
+        &self.key
+    }
+}
+
+impl tx::Signer<DefaultConfig> for KeySigner {
+    fn account_id(&self) -> &AccountId {
+        &self.account_id
+    }
+
+    fn address(&self) -> <DefaultConfig as Config>::Address {
+        self.account_id.clone().into()
+    }
+
+    fn sign(&self, signer_payload: &[u8]) -> <DefaultConfig as Config>::Signature {
+        self.key.sign(signer_payload).into()
+    }
+}
+
+/// The contents of a key file: the secret phrase or seed as a JSON string, as in the
+/// keystore of a node, or encrypted as written by `account generate`.
+#[derive(serde::Deserialize)]
+#[serde(untagged)]
+enum KeyFile {
+    Plain(Zeroizing<String>),
+    Encrypted(EncryptedKey),
+}
+
+/// A secret phrase encrypted like a polkadot-js account file: the base64 encoded scrypt
+/// salt and parameters, the nonce and the xsalsa20-poly1305 sealed phrase.
+#[derive(Debug, serde::Serialize, serde::Deserialize)]
+struct EncryptedKey {
+    address: String,
+    encoded: String,
+    encoding: KeyEncoding,
+}
+
+#[derive(Debug, serde::Serialize, serde::Deserialize)]
+struct KeyEncoding {
+    content: Vec<String>,
+    #[serde(rename = "type")]
+    kind: Vec<String>,
+    version: String,
+}
+
+impl EncryptedKey {
+    /// Encrypt the secret phrase of the account with the password.
+    fn encrypt(phrase: &str, password: &str, account: String) -> Result<Self> {
+        let mut salt = [0u8; SALT_LEN];
+        OsRng.fill_bytes(&mut salt);
+        let nonce = XSalsa20Poly1305::generate_nonce(&mut OsRng);
+        let key = scrypt_key(password, &salt, SCRYPT_LOG_N, SCRYPT_P, SCRYPT_R)?;
+        let sealed = XSalsa20Poly1305::new(Key::from_slice(key.as_slice()))
+            .encrypt(&nonce, phrase.as_bytes())
+            .map_err(|_| anyhow!("Failed to encrypt the secret phrase"))?;
+        let mut encoded = salt.to_vec();
+        for param in [1u32 << SCRYPT_LOG_N, SCRYPT_P, SCRYPT_R] {
+            encoded.extend(param.to_le_bytes());
+        }
+        encoded.extend(nonce);
+        encoded.extend(sealed);
+        Ok(Self {
+            address: account,
+            encoded: BASE64.encode(encoded),
+            encoding: KeyEncoding {
+                content: vec![String::from("phrase"), String::from("sr25519")],
+                kind: vec![String::from("scrypt"), String::from("xsalsa20-poly1305")],
+                version: String::from("3"),
+            },
+        })
+    }
+
+    /// Decrypt the secret phrase with the password, fails if the password is wrong.
+    fn decrypt(&self, password: &str) -> Result<Zeroizing<String>> {
+        anyhow::ensure!(
+            self.encoding.kind == ["scrypt", "xsalsa20-poly1305"]
+                && self.encoding.content.first().map(String::as_str) == Some("phrase"),
+            "Unsupported encryption, only the key files of `account generate` can be \
+            decrypted, not polkadot-js account files"
+        );
+        let encoded = BASE64.decode(&self.encoded)?;
+        anyhow::ensure!(
+            encoded.len() > SALT_LEN + 12 + NONCE_LEN,
+            "The encrypted secret is truncated"
+        );
+        let (salt, rest) = encoded.split_at(SALT_LEN);
+        let (params, rest) = rest.split_at(12);
+        let (nonce, sealed) = rest.split_at(NONCE_LEN);
+        let param = |index: usize| {
+            let bytes = &params[index * 4..index * 4 + 4];
+            u32::from_le_bytes(bytes.try_into().expect("4 bytes; qed"))
+        };
+        let (n, p, r) = (param(0), param(1), param(2));
+        anyhow::ensure!(n.is_power_of_two(), "Invalid scrypt parameter N = {n}");
+        let log_n = n.trailing_zeros() as u8;
+        anyhow::ensure!(
+            log_n <= MAX_SCRYPT_LOG_N && p <= MAX_SCRYPT_P && r <= MAX_SCRYPT_R,
+            "Unsupported scrypt parameters N = {n}, p = {p} and r = {r}, at most \
+            N = 2^{MAX_SCRYPT_LOG_N}, p = {MAX_SCRYPT_P} and r = {MAX_SCRYPT_R}"
+        );
+        let key = scrypt_key(password, salt, log_n, p, r)?;
+        let phrase = XSalsa20Poly1305::new(Key::from_slice(key.as_slice()))
+            .decrypt(Nonce::from_slice(nonce), sealed)
+            .map_err(|_| anyhow!("Wrong password"))?;
+        String::from_utf8(phrase)
+            .map(Zeroizing::new)
+            .map_err(|_| anyhow!("The encrypted secret is not a phrase or seed"))
+    }
+}
+
+/// Derive the encryption key of a key file from the password.
+fn scrypt_key(
+    password: &str,
+    salt: &[u8],
+    log_n: u8,
+    p: u32,
+    r: u32,
+) -> Result<Zeroizing<[u8; 32]>> {
+    let params = scrypt::Params::new(log_n, r, p, 32)
+        .map_err(|err| anyhow!("Invalid scrypt parameters: {err}"))?;
+    let mut key = Zeroizing::new([0u8; 32]);
+    scrypt::scrypt(password.as_bytes(), salt, &params, key.as_mut_slice())
+        .map_err(|err| anyhow!("Failed to derive the encryption key: {err}"))?;
+    Ok(key)
+}
+
+/// Unlock a key file of a Substrate keystore. The file holds the secret phrase or seed
+/// of the key as a JSON string, from which the key is derived with the password as in a
+/// node keystore, or encrypted with the password by `account generate`. The password of
+/// the keystore is asked for unless given.
+pub fn unlock_keystore(path: &Path, password: Option<&str>) -> Result<sr25519::Pair> {
+    let contents = std::fs::read_to_string(path).with_context(|| {
+        format!("Failed to read the keystore file {}", path.display())
+    })?;
+    let key_file: KeyFile = serde_json::from_str(&contents).with_context(|| {
+        format!(
+            "The keystore file {} does not hold a secret phrase or seed, encrypted \
+            polkadot-js account files are not supported",
+            path.display()
+        )
+    })?;
+    let password = match password {
+        Some(password) => Zeroizing::new(password.to_string()),
+        None => {
+            Zeroizing::new(read_password(&format!("Password of {}", path.display()))?)
+        }
+    };
+    let (phrase, derivation_password) = match key_file {
+        KeyFile::Plain(phrase) => {
+            (phrase, (!password.is_empty()).then_some(password.as_str()))
+        }
+        KeyFile::Encrypted(encrypted) => {
+            let phrase = encrypted.decrypt(&password).with_context(|| {
+                format!("Failed to unlock the keystore file {}", path.display())
+            })?;
+            (phrase, None)
+        }
+    };
+    sr25519::Pair::from_string(&phrase, derivation_password)
+        .map_err(|_| anyhow!("Invalid secret in the keystore file {}", path.display()))
+}
+
+/// Write the secret phrase of a new key to a key file of the Substrate keystore in the
+/// directory. As in a node keystore, the file is named after the key type and the public
+/// key. The key is derived from the phrase alone, as by other wallets, and the phrase is
+/// stored encrypted with the password.
+///
+/// Returns the path of the file and the key pair.
+pub fn write_keystore(
+    dir: &Path,
+    phrase: &str,
+    password: &str,
+) -> Result<(PathBuf, sr25519::Pair)> {
+    anyhow::ensure!(
+        !password.is_empty(),
+        "A password is required to encrypt the key file"
+    );
+    let pair = sr25519::Pair::from_phrase(phrase, None)
+        .map_err(|err| anyhow!("Invalid secret phrase: {err:?}"))?
+        .0;
+    let encrypted =
+        EncryptedKey::encrypt(phrase, password, pair.public().to_ss58check())?;
+    std::fs::create_dir_all(dir)
+        .with_context(|| format!("Failed to create {}", dir.display()))?;
+    let path = dir.join(format!(
+        "{}{}",
+        hex::encode(key_types::ACCOUNT.0),
+        hex::encode(pair.public())
+    ));
+    let mut options = std::fs::OpenOptions::new();
+    options.write(true).create_new(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+    let mut file = options
+        .open(&path)
+        .with_context(|| format!("Failed to create the key file {}", path.display()))?;
+    file.write_all(serde_json::to_string_pretty(&encrypted)?.as_bytes())?;
+    Ok((path, pair))
+}
+
+/// A password given on the command line, which is not displayed by `Debug`.
+#[derive(Clone)]
+pub struct Password(Zeroizing<String>);
+
+impl Password {
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+impl FromStr for Password {
+    type Err = Infallible;
+
+    fn from_str(input: &str) -> Result<Self, Self::Err> {
+        Ok(Self(Zeroizing::new(input.to_string())))
+    }
+}
+
+impl fmt::Debug for Password {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("Password(***)")
+    }
+}
+
+/// Prompt for the password of a new keystore key twice, an empty password is none.
+pub fn read_new_password(prompt: &str) -> Result<Option<String>> {
+    let password = read_password(prompt)?;
+    anyhow::ensure!(
+        read_password("Repeat the password")? == password,
+        "The passwords do not match"
+    );
+    Ok((!password.is_empty()).then_some(password))
+}
+
+/// Prompt for a password on the terminal, without echoing it where `stty` is available.
+fn read_password(prompt: &str) -> Result<String> {
+    eprint!("{prompt} (empty for none): ");
+    io::stderr().flush()?;
+    let stty = |arg: &str| {
+        std::process::Command::new("stty")
+            .arg(arg)
+            .stdin(std::process::Stdio::inherit())
+            .status()
+            .is_ok_and(|status| status.success())
+    };
+    let hidden = stty("-echo");
+    let mut password = String::new();
+    let read = io::stdin().read_line(&mut password);
+    if hidden {
+        stty("echo");
+        eprintln!();
+    }
+    read?;
+    Ok(password.trim_end_matches(['\r', '\n']).to_string())
+}
+
+/// A signing service holding the key of an account.
+#[derive(Debug, Clone)]
+pub struct RemoteSigner {
+    account: AccountId,
+    url: url::Url,
+}
+
+/// The `signRaw` request of the polkadot-js signer protocol.
+#[derive(Debug, serde::Serialize)]
+struct SignRaw {
+    address: String,
+    /// The hex encoded signer payload.
+    data: String,
+    #[serde(rename = "type")]
+    kind: &'static str,
+}
+
+/// The result of a `signRaw` request.
+#[derive(Debug, serde::Deserialize)]
+struct SignerResult {
+    /// The hex encoded SCALE encoding of the `MultiSignature`.
+    signature: String,
+}
+
+impl RemoteSigner {
+    /// The account signing the extrinsics.
+    pub fn account_id(&self) -> &AccountId {
+        &self.account
+    }
+
+    /// Have the payload signed by the service, checking the signature.
+    pub async fn sign(&self, payload: &[u8]) -> Result<MultiSignature> {
+        let request = SignRaw {
+            address: self.account.to_string(),
+            data: format!("0x{}", hex::encode(payload)),
+            kind: "payload",
+        };
+        pacing::pace().await;
+        let result: Result<SignerResult, _> = match self.url.scheme() {
+            "ws" | "wss" => {
+                WsClientBuilder::default()
+                    .build(self.url.as_str())
+                    .await?
+                    .request(SIGN_RAW_METHOD, rpc_params![request])
+                    .await
+            }
+            _ => {
+                HttpClientBuilder::default()
+                    .build(self.url.as_str())?
+                    .request(SIGN_RAW_METHOD, rpc_params![request])
+                    .await
+            }
+        };
+        let result = result.with_context(|| {
+            format!("The remote signer at {} did not sign the payload", self.url)
+        })?;
+        let signature = decode_signature(&result.signature)?;
+        let signer = sp_runtime::AccountId32::from(self.account.0);
+        anyhow::ensure!(
+            signature.verify(payload, &signer),
+            "The signature of the remote signer at {} does not match the payload and the \
+            account {}",
+            self.url,
+            self.account
+        );
+        Ok(signature)
+    }
+
+    /// Build the extrinsic, have it signed by the service and submit it.
+    pub async fn submit<Call: TxPayload>(
+        &self,
+        client: &Client,
+        call: &Call,
+        opts: &SubmitOptions,
+    ) -> Result<ExtrinsicEvents<DefaultConfig>> {
+        let (params, era) = extrinsic_params(client, opts.mortality, opts.tip).await?;
+        let partial = client
+            .tx()
+            .create_partial_signed(call, &self.account, params)
+            .await?;
+        let signature = self.sign(&partial.signer_payload()).await?;
+        let address = <DefaultConfig as Config>::Address::from(self.account.clone());
+        let extrinsic = partial.sign_with_address_and_signature(&address, &signature);
+        if opts.simulate {
+            simulate::simulate_extrinsic(client, &extrinsic).await?;
+        }
+        match watch_extrinsic(client, &extrinsic, era).await? {
+            Submission::Included(events) => Ok(events),
+            Submission::Expired(era) => {
+                Err(anyhow!(
+                    "The transaction expired: it was not included in a block between \
+                    block #{} and block #{}",
+                    era.birth,
+                    era.death
+                ))
+            }
+        }
+    }
+}
+
+/// Decode the hex encoded `MultiSignature` returned by a remote signer.
+fn decode_signature(input: &str) -> Result<MultiSignature> {
+    let bytes = hex::decode(input.trim_start_matches("0x"))
+        .context("The signature of the remote signer must be hex encoded")?;
+    MultiSignature::decode(&mut &bytes[..])
+        .context("The remote signer returned an invalid signature")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use scale::Encode;
+
+    #[test]
+    fn signers_are_parsed() {
+        assert_eq!("ledger".parse::<SignerSpec>().unwrap(), SignerSpec::Ledger);
+        assert_eq!(
+            "suri://Alice".parse::<SignerSpec>().unwrap(),
+            SignerSpec::Suri(String::from("//Alice"))
+        );
+        assert_eq!(
+            "keystore:/keys/7372".parse::<SignerSpec>().unwrap(),
+            SignerSpec::Keystore(PathBuf::from("/keys/7372"))
+        );
+        let remote =
+            "remote:5GrwvaEF5zXb26Fz9rcQpDWS57CtERHpNehXCPcNoHGKutQY@http://signer:3000"
+                .parse::<SignerSpec>()
+                .unwrap();
+        assert_eq!(remote.kind(), SignerKind::Remote);
+        assert!("remote:http://signer:3000".parse::<SignerSpec>().is_err());
+        assert!("yubikey:1".parse::<SignerSpec>().is_err());
+        assert!(SignerSpec::Ledger.remote().is_err());
+    }
+
+    #[test]
+    fn keystore_files_hold_the_secret_phrase() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("key");
+        let phrase =
+            "bottom drive obey lake curtain smoke basket hold race lonely fit walk";
+        std::fs::write(&path, serde_json::to_string(phrase).unwrap()).unwrap();
+
+        let pair = SignerSpec::Keystore(path.clone())
+            .key(Some("secret"))
+            .unwrap()
+            .unwrap();
+        let expected = sr25519::Pair::from_string(phrase, Some("secret")).unwrap();
+        assert_eq!(pair.public(), expected.public());
+
+        std::fs::write(&path, r#"{"encoded": "0x00"}"#).unwrap();
+        assert!(keystore_key(&path, Some("")).is_err());
+    }
+
+    #[test]
+    fn written_keys_are_unlocked() {
+        let dir = tempfile::tempdir().unwrap();
+        let phrase =
+            "bottom drive obey lake curtain smoke basket hold race lonely fit walk";
+        let (path, pair) = write_keystore(dir.path(), phrase, "secret").unwrap();
+        let name = path.file_name().unwrap().to_str().unwrap();
+        assert_eq!(name, format!("6163636f{}", hex::encode(pair.public())));
+        let contents = std::fs::read_to_string(&path).unwrap();
+        assert!(!contents.contains("bottom"));
+
+        let expected = sr25519::Pair::from_phrase(phrase, None).unwrap().0;
+        assert_eq!(pair.public(), expected.public());
+        let unlocked = unlock_keystore(&path, Some("secret")).unwrap();
+        assert_eq!(unlocked.public(), pair.public());
+        assert!(unlock_keystore(&path, Some("")).is_err());
+        assert!(unlock_keystore(&path, Some("other")).is_err());
+        assert!(write_keystore(dir.path(), phrase, "secret").is_err());
+        assert!(write_keystore(dir.path(), phrase, "").is_err());
+    }
+
+    #[test]
+    fn expensive_scrypt_parameters_are_rejected() {
+        let phrase =
+            "bottom drive obey lake curtain smoke basket hold race lonely fit walk";
+        let mut encrypted =
+            EncryptedKey::encrypt(phrase, "secret", String::new()).unwrap();
+        let mut encoded = BASE64.decode(&encrypted.encoded).unwrap();
+        encoded[SALT_LEN..SALT_LEN + 4].copy_from_slice(&(1u32 << 30).to_le_bytes());
+        encrypted.encoded = BASE64.encode(encoded);
+        let err = encrypted.decrypt("secret").unwrap_err();
+        assert!(err.to_string().contains("Unsupported scrypt parameters"));
+    }
+
+    #[test]
+    fn remote_signatures_are_decoded() {
+        let pair = sr25519::Pair::from_string("//Alice", None).unwrap();
+        let signature = MultiSignature::from(pair.sign(b"payload"));
+        let encoded = format!("0x{}", hex::encode(signature.encode()));
+        assert_eq!(decode_signature(&encoded).unwrap(), signature);
+        assert!(decode_signature("0x01").is_err());
+    }
+}
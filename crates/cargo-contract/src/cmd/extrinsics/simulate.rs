@@ -0,0 +1,183 @@
+// Copyright 2018-2023 Parity Technologies (UK) Ltd.
+// This file is part of cargo-contract.
+//
+// cargo-contract is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// cargo-contract is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with cargo-contract.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Simulation of the whole signed extrinsic before it is broadcast, enabled with
+//! `--simulate-extrinsic`.
+//!
+//! The dry-run of the contract call does not check the signature, nonce, fees and signed
+//! extensions of the transaction. The signed extrinsic is applied to the state of the
+//! best block with the `system_dryRun` RPC instead. Nodes which do not expose the unsafe
+//! RPC methods only validate the transaction, with the `TaggedTransactionQueue` runtime
+//! API.
+
+use super::{
+    Client,
+    DefaultConfig,
+};
+use colored::Colorize;
+use scale::{
+    Decode,
+    Encode,
+};
+use sp_runtime::transaction_validity::{
+    InvalidTransaction,
+    TransactionSource,
+    TransactionValidity,
+    TransactionValidityError,
+    UnknownTransaction,
+};
+use subxt::{
+    rpc::types::{
+        DryRunResult,
+        DryRunResultBytes,
+    },
+    tx,
+};
+
+/// Simulate the signed extrinsic at the best block, returns an error if it would fail.
+pub async fn simulate_extrinsic(
+    client: &Client,
+    extrinsic: &tx::SubmittableExtrinsic<DefaultConfig, Client>,
+) -> Result<(), subxt::Error> {
+    let best = client.blocks().at_latest().await?.hash();
+    crate::cmd::pacing::pace().await;
+    let result = match client.rpc().dry_run(extrinsic.encoded(), Some(best)).await {
+        Ok(result) => apply_result(result, &client.metadata()),
+        Err(err) => {
+            tracing::debug!("system_dryRun failed: {err}");
+            eprintln!(
+                "{} the node does not allow `system_dryRun`, only the validity of the \
+                transaction is simulated.",
+                "warning:".yellow().bold(),
+            );
+            let mut args = TransactionSource::External.encode();
+            args.extend(extrinsic.encoded());
+            args.extend(best.encode());
+            let bytes = client
+                .rpc()
+                .state_call(
+                    "TaggedTransactionQueue_validate_transaction",
+                    Some(&args),
+                    Some(best),
+                )
+                .await?;
+            match TransactionValidity::decode(&mut &bytes[..])? {
+                Ok(_) => Ok(()),
+                Err(err) => Err(validity_error(err)),
+            }
+        }
+    };
+    if result.is_err() {
+        eprintln!(
+            "{} the simulation of the extrinsic failed, it was not broadcast.",
+            "error:".red().bold(),
+        );
+    }
+    result
+}
+
+/// Check the encoded `ApplyExtrinsicResult` of the dry-run.
+#[allow(clippy::result_large_err)]
+fn apply_result(
+    result: DryRunResultBytes,
+    metadata: &subxt::Metadata,
+) -> Result<(), subxt::Error> {
+    if let Some((1, mut error)) = result.0.split_first() {
+        return Err(validity_error(TransactionValidityError::decode(
+            &mut error,
+        )?))
+    }
+    match result.into_dry_run_result(metadata)? {
+        DryRunResult::Success => Ok(()),
+        DryRunResult::DispatchError(err) => Err(subxt::Error::Runtime(err)),
+        DryRunResult::TransactionValidityError => {
+            Err(subxt::Error::Other(String::from(
+                "The transaction is invalid",
+            )))
+        }
+    }
+}
+
+fn validity_error(err: TransactionValidityError) -> subxt::Error {
+    subxt::Error::Other(format!("The transaction is invalid: {}", describe(err)))
+}
+
+/// Describe why the transaction is invalid.
+fn describe(err: TransactionValidityError) -> String {
+    let reason = match err {
+        TransactionValidityError::Invalid(InvalidTransaction::Payment) => {
+            "the signer can not pay the fees of the transaction"
+        }
+        TransactionValidityError::Invalid(InvalidTransaction::Stale) => {
+            "its nonce was already used, a transaction of the signer was included since \
+            it was signed"
+        }
+        TransactionValidityError::Invalid(InvalidTransaction::Future) => {
+            "its nonce is ahead of the nonce of the signer"
+        }
+        TransactionValidityError::Invalid(InvalidTransaction::BadProof) => {
+            "its signature is invalid, the genesis hash or runtime version may not match \
+            the chain"
+        }
+        TransactionValidityError::Invalid(InvalidTransaction::AncientBirthBlock) => {
+            "the era of the mortal transaction starts at a block the node does not know"
+        }
+        TransactionValidityError::Invalid(InvalidTransaction::ExhaustsResources) => {
+            "it would exhaust the resources of the block"
+        }
+        TransactionValidityError::Invalid(InvalidTransaction::BadSigner) => {
+            "the signer is not allowed to submit it"
+        }
+        TransactionValidityError::Unknown(UnknownTransaction::CannotLookup) => {
+            "an account could not be looked up"
+        }
+        other => return format!("{other:?}"),
+    };
+    reason.to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use subxt::ext::frame_metadata::RuntimeMetadataPrefixed;
+
+    #[test]
+    #[allow(clippy::result_large_err)]
+    fn invalid_transactions_are_reported() {
+        let bytes = include_bytes!("../runtime_api/contracts_runtime.scale");
+        let metadata = RuntimeMetadataPrefixed::decode(&mut &bytes[..]).unwrap();
+        let metadata = subxt::Metadata::try_from(metadata).unwrap();
+        let result = |result: Result<Result<(), ()>, TransactionValidityError>| {
+            // The dispatch error is never decoded in the test.
+            apply_result(DryRunResultBytes(result.encode()), &metadata)
+        };
+
+        assert!(result(Ok(Ok(()))).is_ok());
+        let err = result(Err(TransactionValidityError::Invalid(
+            InvalidTransaction::Payment,
+        )))
+        .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Other error: The transaction is invalid: the signer can not pay the fees of \
+            the transaction"
+        );
+        assert!(
+            describe(TransactionValidityError::Invalid(InvalidTransaction::Call))
+                .contains("Call")
+        );
+    }
+}
@@ -0,0 +1,236 @@
+// Copyright 2018-2023 Parity Technologies (UK) Ltd.
+// This file is part of cargo-contract.
+//
+// cargo-contract is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// cargo-contract is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with cargo-contract.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Clearing fields of the storage of a contract on development chains, to reset state
+//! without redeploying the contract.
+//!
+//! The cells of a field with its own root key, a `Lazy` value or a `Mapping`, are the
+//! cells of the child trie of the contract whose ink! storage key starts with the root
+//! key. They are removed with `System::kill_storage`, dispatched with `Sudo::sudo` by the
+//! sudo key of the chain.
+//!
+//! Stock Substrate runtimes refuse to write child tries through `System::kill_storage`,
+//! the extrinsic succeeds without removing anything. The storage is therefore read again
+//! afterwards, and the command fails if the cells are still there.
+
+use super::{
+    display_dry_run_result_warning,
+    pair_signer,
+    submit_dynamic_call,
+    sudo::{
+        ensure_sudo_key,
+        SUDO_PALLET,
+    },
+    Client,
+    DefaultConfig,
+    ErrorVariant,
+    ExtrinsicOpts,
+};
+use crate::{
+    cmd::{
+        runtime_api::api,
+        storage::{
+            field_root_key,
+            read_child_storage,
+        },
+        AsyncCommand,
+    },
+    DEFAULT_KEY_COL_WIDTH,
+};
+use anyhow::{
+    anyhow,
+    Result,
+};
+use async_trait::async_trait;
+use contract_build::name_value_println;
+use sp_core::Pair;
+use subxt::{
+    dynamic::Value,
+    Config,
+    OnlineClient,
+};
+
+type AccountId = <DefaultConfig as Config>::AccountId;
+
+#[derive(Debug, clap::Args)]
+pub struct ClearStorageCommand {
+    /// The address of the contract.
+    #[clap(long, env = "CONTRACT")]
+    contract: AccountId,
+    /// A field of the storage struct of the contract to clear, resolved with the storage
+    /// layout of the metadata. Can be repeated.
+    #[clap(long, required = true)]
+    field: Vec<String>,
+    #[clap(flatten)]
+    extrinsic_opts: ExtrinsicOpts,
+    /// Export the output in JSON format.
+    #[clap(long, conflicts_with = "verbose")]
+    output_json: bool,
+}
+
+impl ClearStorageCommand {
+    pub fn is_json(&self) -> bool {
+        self.output_json
+    }
+}
+
+#[async_trait(?Send)]
+impl AsyncCommand for ClearStorageCommand {
+    type Output = Result<(), ErrorVariant>;
+
+    async fn exec(&self) -> Result<(), ErrorVariant> {
+        let opts = &self.extrinsic_opts;
+        opts.apply_process_opts()?;
+        let artifacts = opts.contract_artifacts()?;
+        let abi = serde_json::Value::Object(artifacts.metadata()?.abi);
+        let root_keys = self
+            .field
+            .iter()
+            .map(|field| field_root_key(&abi, field))
+            .collect::<Result<Vec<_>>>()?;
+        let signer = opts.signer("storage clear")?;
+
+        let client = OnlineClient::from_url(opts.url_to_string()?).await?;
+        ensure_sudo_key(&client, &AccountId::from(signer.public().0)).await?;
+        let keys = field_cells(&client, &self.contract, &root_keys).await?;
+
+        let print_details = || {
+            name_value_println!(
+                "Call",
+                "Sudo::sudo(System::kill_storage)",
+                DEFAULT_KEY_COL_WIDTH
+            );
+            name_value_println!(
+                "Contract",
+                self.contract.to_string(),
+                DEFAULT_KEY_COL_WIDTH
+            );
+            name_value_println!("Fields", self.field.join(", "), DEFAULT_KEY_COL_WIDTH);
+            name_value_println!("Cells", keys.len().to_string(), DEFAULT_KEY_COL_WIDTH);
+        };
+        if keys.is_empty() {
+            print_details();
+            println!("The fields have no cells stored, there is nothing to clear");
+            return Ok(())
+        }
+        if !opts.execute()? {
+            print_details();
+            for key in &keys {
+                println!("  0x{}", hex::encode(key));
+            }
+            display_dry_run_result_warning("storage clear");
+            return Ok(())
+        }
+        opts.confirm(print_details)?;
+
+        let call =
+            subxt::dynamic::tx(SUDO_PALLET, "sudo", vec![kill_storage_call(&keys)]);
+        let output = submit_dynamic_call(
+            &client,
+            &call,
+            &pair_signer(signer),
+            opts,
+            self.output_json,
+        )
+        .await?;
+        let remaining = field_cells(&client, &self.contract, &root_keys).await?;
+        if !remaining.is_empty() {
+            return Err(anyhow!(
+                "The runtime did not clear {} of the {} cells: it refuses to write \
+                the child trie of the contract through `System::kill_storage`",
+                remaining.len(),
+                keys.len()
+            )
+            .into())
+        }
+        println!("{output}");
+        Ok(())
+    }
+}
+
+/// The keys in the child trie of the contract of the cells under the root keys.
+async fn field_cells(
+    client: &Client,
+    contract: &AccountId,
+    root_keys: &[Vec<u8>],
+) -> Result<Vec<Vec<u8>>> {
+    let block = client.blocks().at_latest().await?;
+    let info = block
+        .storage()
+        .fetch(&api::storage().contracts().contract_info_of(contract))
+        .await?
+        .ok_or_else(|| anyhow!("No contract was found at {contract}"))?;
+    let pairs = read_child_storage(client, &info.trie_id.0, block.hash()).await?;
+    Ok(cells_under(
+        pairs.into_iter().map(|(key, _)| key),
+        root_keys,
+    ))
+}
+
+/// Select the keys of the child trie whose ink! storage key starts with one of the root
+/// keys. The keys are `Blake2_128Concat` hashes of the ink! storage keys.
+fn cells_under(
+    keys: impl IntoIterator<Item = Vec<u8>>,
+    root_keys: &[Vec<u8>],
+) -> Vec<Vec<u8>> {
+    keys.into_iter()
+        .filter(|key| {
+            key.get(16..).is_some_and(|ink_key| {
+                root_keys
+                    .iter()
+                    .any(|root_key| ink_key.starts_with(root_key))
+            })
+        })
+        .collect()
+}
+
+/// The `System::kill_storage` call of the keys, as the call dispatched by sudo.
+fn kill_storage_call(keys: &[Vec<u8>]) -> Value {
+    Value::unnamed_variant(
+        "System",
+        [Value::named_variant(
+            "kill_storage",
+            [(
+                "keys",
+                Value::unnamed_composite(keys.iter().map(Value::from_bytes)),
+            )],
+        )],
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cmd::storage::hashed_key;
+
+    #[test]
+    fn cells_of_the_fields_are_selected_by_root_key() {
+        let balances = vec![0x7e, 0x3f, 0x7b, 0x44];
+        let cache = vec![0x01, 0x00, 0x00, 0x00];
+        let keys = [
+            hashed_key(&[0, 0, 0, 0]),
+            hashed_key(&[&balances[..], &[0xaa]].concat()),
+            hashed_key(&cache),
+            hashed_key(&[&cache[..], &[0xbb, 0xcc]].concat()),
+            vec![0; 8],
+        ];
+        assert_eq!(
+            cells_under(keys.clone(), &[cache]),
+            [keys[2].clone(), keys[3].clone()]
+        );
+        assert_eq!(cells_under(keys.clone(), &[balances]), [keys[1].clone()]);
+    }
+}
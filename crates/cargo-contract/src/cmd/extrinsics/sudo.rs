@@ -0,0 +1,236 @@
+// Copyright 2018-2023 Parity Technologies (UK) Ltd.
+// This file is part of cargo-contract.
+//
+// cargo-contract is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// cargo-contract is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with cargo-contract.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Submission of extrinsics through `Sudo::sudo` on development chains.
+//!
+//! With `--sudo` the extrinsic of `call` or `instantiate` is wrapped in `Sudo::sudo` and
+//! signed by the sudo key of the chain, so that it is dispatched with the root origin,
+//! e.g. for privileged test flows forcing code upgrades or seeding state. The runtime
+//! must accept the root origin for the wrapped extrinsic.
+//!
+//! Sudo is refused on chains which are neither of the `Development` nor of the `Local`
+//! chain type, unless `--i-know-what-i-am-doing` is given.
+
+use super::{
+    Client,
+    DefaultConfig,
+    ErrorVariant,
+};
+use crate::{
+    name_value_println,
+    DEFAULT_KEY_COL_WIDTH,
+};
+use anyhow::{
+    anyhow,
+    Result,
+};
+use colored::Colorize;
+use scale::Decode;
+use sp_runtime::DispatchError;
+use subxt::{
+    blocks::ExtrinsicEvents,
+    rpc_params,
+    tx::{
+        TxPayload,
+        ValidationDetails,
+    },
+    Config,
+    Metadata,
+};
+
+type AccountId = <DefaultConfig as Config>::AccountId;
+
+pub const SUDO_PALLET: &str = "Sudo";
+
+/// The chain types of development chains, as returned by `system_chainType`.
+const DEVELOPMENT_CHAIN_TYPES: [&str; 2] = ["Development", "Local"];
+
+/// Options for dispatching an extrinsic with the root origin through sudo.
+#[derive(Debug, Clone, Default, clap::Args)]
+pub struct SudoOpts {
+    /// Wrap the extrinsic in `Sudo::sudo`, dispatching it with the root origin. The
+    /// signer must be the sudo key of the chain. Only allowed on development chains.
+    #[clap(long)]
+    sudo: bool,
+    /// Allow `--sudo` on chains which are not development chains.
+    #[clap(long = "i-know-what-i-am-doing", requires = "sudo")]
+    force: bool,
+}
+
+impl SudoOpts {
+    /// Returns `true` if the extrinsic should be dispatched through sudo.
+    pub fn enabled(&self) -> bool {
+        self.sudo
+    }
+
+    /// Fail unless the signer is the sudo key of a development chain, or of any chain
+    /// with `--i-know-what-i-am-doing`.
+    pub async fn ensure_allowed(
+        &self,
+        client: &Client,
+        signer: &AccountId,
+    ) -> Result<()> {
+        ensure_sudo_key(client, signer).await?;
+        let chain_type: serde_json::Value = client
+            .rpc()
+            .request("system_chainType", rpc_params![])
+            .await?;
+        if !is_development_chain(&chain_type) {
+            anyhow::ensure!(
+                self.force,
+                "Refusing `--sudo` on the {chain_type} chain, which is not a development \
+                chain. Pass `--i-know-what-i-am-doing` to dispatch it anyway"
+            );
+            eprintln!(
+                "{} dispatching with the root origin on the {chain_type} chain, which is \
+                not a development chain",
+                "WARNING:".red().bold()
+            );
+        }
+        eprintln!(
+            "{} the extrinsic is dispatched with the root origin through `Sudo::sudo`",
+            "warning:".yellow().bold()
+        );
+        Ok(())
+    }
+
+    /// Print the origin of the extrinsic if it is dispatched through sudo.
+    pub fn print_details(&self) {
+        if self.sudo {
+            name_value_println!("Origin", "root, via Sudo::sudo", DEFAULT_KEY_COL_WIDTH);
+        }
+    }
+
+    /// Wrap the call in `Sudo::sudo` if `--sudo` is given.
+    pub fn wrap<Call: TxPayload>(&self, call: Call) -> SudoCall<Call> {
+        SudoCall {
+            call,
+            sudo: self.sudo,
+        }
+    }
+
+    /// Fail if the call dispatched through sudo failed. The extrinsic itself succeeds
+    /// either way, the result of the call is the `Sudo::Sudid` event.
+    pub fn check_result(
+        &self,
+        result: &ExtrinsicEvents<DefaultConfig>,
+        metadata: &Metadata,
+    ) -> Result<(), ErrorVariant> {
+        if !self.sudo {
+            return Ok(())
+        }
+        for event in result.iter() {
+            let event = event?;
+            if event.pallet_name() == SUDO_PALLET && event.variant_name() == "Sudid" {
+                return match sudo_result(event.field_bytes())? {
+                    Ok(()) => Ok(()),
+                    Err(err) => Err(ErrorVariant::from_dispatch_error(&err, metadata)?),
+                }
+            }
+        }
+        Err(anyhow!("Failed to find the Sudid event").into())
+    }
+}
+
+/// A call wrapped in `Sudo::sudo`, or the call itself without `--sudo`.
+pub struct SudoCall<Call> {
+    call: Call,
+    sudo: bool,
+}
+
+impl<Call: TxPayload> TxPayload for SudoCall<Call> {
+    fn encode_call_data_to(
+        &self,
+        metadata: &Metadata,
+        out: &mut Vec<u8>,
+    ) -> Result<(), subxt::Error> {
+        if self.sudo {
+            let pallet = metadata.pallet(SUDO_PALLET)?;
+            out.push(pallet.index());
+            out.push(pallet.call_index("sudo")?);
+        }
+        // The wrapped call is a boxed `RuntimeCall`, encoded as the call itself.
+        self.call.encode_call_data_to(metadata, out)
+    }
+
+    fn validation_details(&self) -> Option<ValidationDetails<'_>> {
+        if self.sudo {
+            None
+        } else {
+            self.call.validation_details()
+        }
+    }
+}
+
+/// Fail unless the chain has a sudo key, and it is the account of the signer.
+pub async fn ensure_sudo_key(client: &Client, signer: &AccountId) -> Result<()> {
+    if client.metadata().pallet(SUDO_PALLET).is_err() {
+        anyhow::bail!(
+            "The chain has no `Sudo` pallet, sudo is only available on development chains"
+        )
+    }
+    let address = subxt::dynamic::storage_root(SUDO_PALLET, "Key");
+    let key = client
+        .storage()
+        .at_latest()
+        .await?
+        .fetch(&address)
+        .await?
+        .ok_or_else(|| anyhow!("The chain has no sudo key"))?;
+    let key = AccountId::decode(&mut key.encoded())?;
+    anyhow::ensure!(
+        &key == signer,
+        "The signer {signer} is not the sudo key {key} of the chain"
+    );
+    Ok(())
+}
+
+/// Returns `true` if the chain type returned by `system_chainType` is of a development
+/// chain. Custom chain types are serialized as `{"Custom": "<name>"}`.
+fn is_development_chain(chain_type: &serde_json::Value) -> bool {
+    chain_type
+        .as_str()
+        .is_some_and(|chain_type| DEVELOPMENT_CHAIN_TYPES.contains(&chain_type))
+}
+
+/// Decode the `sudo_result` field of the `Sudo::Sudid` event.
+fn sudo_result(mut fields: &[u8]) -> Result<Result<(), DispatchError>> {
+    Ok(Result::<(), DispatchError>::decode(&mut fields)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use scale::Encode;
+
+    #[test]
+    fn only_development_chains_are_detected() {
+        assert!(is_development_chain(&serde_json::json!("Development")));
+        assert!(is_development_chain(&serde_json::json!("Local")));
+        assert!(!is_development_chain(&serde_json::json!("Live")));
+        assert!(!is_development_chain(
+            &serde_json::json!({ "Custom": "Development" })
+        ));
+    }
+
+    #[test]
+    fn sudid_results_are_decoded() {
+        let ok = Result::<(), DispatchError>::Ok(()).encode();
+        assert_eq!(sudo_result(&ok).unwrap(), Ok(()));
+        let err = Result::<(), DispatchError>::Err(DispatchError::BadOrigin).encode();
+        assert_eq!(sudo_result(&err).unwrap(), Err(DispatchError::BadOrigin));
+    }
+}
@@ -0,0 +1,237 @@
+// Copyright 2018-2023 Parity Technologies (UK) Ltd.
+// This file is part of cargo-contract.
+//
+// cargo-contract is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// cargo-contract is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with cargo-contract.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Transfer of the ownership of uploaded code.
+//!
+//! `pallet-contracts` does not offer a call for transferring the ownership of code, the
+//! account which uploaded it stays the owner receiving the deposit refund on removal.
+//! The transfer is therefore recorded on-chain with a `System::remark_with_event` of the
+//! current owner, following the convention
+//!
+//! ```text
+//! cargo-contract:code-owner:<code hash>:<new owner>
+//! ```
+//!
+//! and in the local registry, which tracks the current owner of the code.
+
+use super::{
+    display_dry_run_result_warning,
+    parse_code_hash,
+    submit_extrinsic,
+    Client,
+    DefaultConfig,
+    ErrorVariant,
+    ExtrinsicOpts,
+    TokenMetadata,
+};
+use crate::{
+    cmd::{
+        extrinsics::events::DisplayEvents,
+        info::fetch_code_owner,
+        registry::{
+            OwnershipTransfer,
+            Registry,
+        },
+        runtime_api::api,
+        AsyncCommand,
+        CodeHash,
+    },
+    name_value_println,
+    DEFAULT_KEY_COL_WIDTH,
+};
+use anyhow::{
+    anyhow,
+    Result,
+};
+use async_trait::async_trait;
+use subxt::{
+    Config,
+    OnlineClient,
+};
+
+type AccountId = <DefaultConfig as Config>::AccountId;
+type Hash = <DefaultConfig as Config>::Hash;
+
+/// The prefix of the remarks recording ownership transfers of code.
+pub const CODE_OWNER_REMARK_PREFIX: &str = "cargo-contract:code-owner";
+
+#[derive(Debug, clap::Args)]
+#[clap(
+    name = "transfer-code",
+    about = "Transfer the ownership of uploaded code"
+)]
+pub struct TransferCodeCommand {
+    /// The hash of the code, defaults to the hash of the contract artifacts.
+    #[clap(long, value_parser = parse_code_hash)]
+    code_hash: Option<CodeHash>,
+    /// The account to transfer the ownership to.
+    #[clap(long)]
+    new_owner: AccountId,
+    #[clap(flatten)]
+    extrinsic_opts: ExtrinsicOpts,
+    /// Export the call output as JSON.
+    #[clap(long, conflicts_with = "verbose")]
+    output_json: bool,
+}
+
+impl TransferCodeCommand {
+    pub fn is_json(&self) -> bool {
+        self.output_json
+    }
+}
+
+#[async_trait(?Send)]
+impl AsyncCommand for TransferCodeCommand {
+    type Output = Result<(), ErrorVariant>;
+
+    async fn exec(&self) -> Result<(), ErrorVariant> {
+        if self.extrinsic_opts.phala_backend()?.is_some() {
+            return Err(anyhow!(
+                "Transferring code ownership is not supported by the `phala` backend"
+            )
+            .into())
+        }
+        self.extrinsic_opts.apply_process_opts()?;
+        let code_hash = match self.code_hash {
+            Some(code_hash) => code_hash,
+            None => {
+                CodeHash::from(self.extrinsic_opts.contract_artifacts()?.code_hash()?)
+            }
+        };
+        let signer = super::pair_signer(self.extrinsic_opts.signer("transfer-code")?);
+        let network = self.extrinsic_opts.url_to_string()?;
+        let registry = Registry::load()?;
+
+        let client = OnlineClient::from_url(&network).await?;
+        let owner = match registry.code(&code_hash, &network) {
+            Some(code) => code.owner.clone(),
+            None => {
+                fetch_code_owner(&client, code_hash).await?.ok_or_else(|| {
+                    anyhow!("No code was found for the code hash {code_hash:?}")
+                })?
+            }
+        };
+        if &owner != signer.account_id() {
+            return Err(anyhow!(
+                "The code {code_hash:?} is owned by {owner}, only the owner can \
+                transfer its ownership"
+            )
+            .into())
+        }
+
+        let remark = code_owner_remark(&code_hash, &self.new_owner);
+        let print_details = || {
+            name_value_println!(
+                "Code hash",
+                format!("{code_hash:?}"),
+                DEFAULT_KEY_COL_WIDTH
+            );
+            name_value_println!("Owner", format!("{owner}"), DEFAULT_KEY_COL_WIDTH);
+            name_value_println!(
+                "New owner",
+                format!("{}", self.new_owner),
+                DEFAULT_KEY_COL_WIDTH
+            );
+            name_value_println!("Remark", remark, DEFAULT_KEY_COL_WIDTH);
+        };
+        if !self.extrinsic_opts.execute()? {
+            print_details();
+            display_dry_run_result_warning("transfer-code");
+            return Ok(())
+        }
+        self.extrinsic_opts.confirm(print_details)?;
+
+        let token_metadata = TokenMetadata::query(&client).await?;
+        let submit_options = self.extrinsic_opts.submit_options(&token_metadata)?;
+        let call = api::tx()
+            .system()
+            .remark_with_event(remark.clone().into_bytes());
+        let result = submit_extrinsic(&client, &call, &signer, &submit_options).await?;
+
+        Registry::update(|registry| {
+            registry.transfer_code_ownership(
+                code_hash,
+                &network,
+                OwnershipTransfer {
+                    from: owner,
+                    to: self.new_owner.clone(),
+                    block_hash: result.all_events_in_block().block_hash(),
+                },
+            )
+        })?;
+
+        let display_events = DisplayEvents::from_events(
+            &result,
+            None,
+            &client.metadata(),
+            &token_metadata,
+        )?;
+        let output = if self.output_json {
+            display_events.to_json()?
+        } else {
+            display_events.display_events(
+                self.extrinsic_opts.verbosity()?,
+                self.extrinsic_opts.number_format,
+                self.extrinsic_opts.max_output_bytes,
+            )?
+        };
+        println!("{output}");
+        Ok(())
+    }
+}
+
+/// The remark recording the transfer of the ownership of the code to the new owner.
+fn code_owner_remark(code_hash: &CodeHash, new_owner: &AccountId) -> String {
+    format!("{CODE_OWNER_REMARK_PREFIX}:{code_hash:?}:{new_owner}")
+}
+
+/// Check that the transfer of the ownership of the code was recorded on-chain by the
+/// previous owner, with the `System::Remarked` event of its remark in its block.
+pub async fn verify_transfer(
+    client: &Client,
+    code_hash: &CodeHash,
+    transfer: &OwnershipTransfer,
+) -> Result<bool> {
+    let remark = code_owner_remark(code_hash, &transfer.to);
+    let remark_hash = Hash::from(sp_core::hashing::blake2_256(remark.as_bytes()));
+    let events = client.events().at(transfer.block_hash).await?;
+    for remarked in events.find::<api::system::events::Remarked>() {
+        let remarked = remarked?;
+        if remarked.sender == transfer.from && remarked.hash == remark_hash {
+            return Ok(true)
+        }
+    }
+    Ok(false)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+
+    #[test]
+    fn code_owner_remark_follows_convention() {
+        let new_owner =
+            AccountId::from_str("5FHneW46xGXgs5mUiveU4sbTyGBzmstUspZC92UhjJM694ty")
+                .unwrap();
+        assert_eq!(
+            code_owner_remark(&CodeHash::repeat_byte(0xab), &new_owner),
+            "cargo-contract:code-owner:\
+            0xabababababababababababababababababababababababababababababababab:\
+            5FHneW46xGXgs5mUiveU4sbTyGBzmstUspZC92UhjJM694ty"
+        );
+    }
+}
@@ -0,0 +1,728 @@
+// Copyright 2018-2023 Parity Technologies (UK) Ltd.
+// This file is part of cargo-contract.
+//
+// cargo-contract is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// cargo-contract is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with cargo-contract.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Upgrade of the code of a contract.
+//!
+//! ink! contracts replace their code from within a message calling `set_code_hash`, the
+//! `upgrade` command calls such a message with the hash of the new code as its only
+//! argument. After a successful upgrade a receipt is written, together with the previous
+//! code and a script rolling the upgrade back. With `--canary` the new code is verified
+//! on a canary contract before the upgrade, with `--wizard` the upgrade is reviewed step
+//! by step.
+
+use super::{
+    call::CallRequest,
+    canary::CanaryOpts,
+    display_dry_run_result_warning,
+    invariants::InvariantOpts,
+    parse_code_hash,
+    state_call,
+    submit_extrinsic,
+    upgrade_wizard::{
+        print_bench,
+        print_diff,
+        verify_rebuild,
+        Step,
+        Wizard,
+        WizardState,
+    },
+    Client,
+    ContractArtifacts,
+    ContractMessageTranscoder,
+    DefaultConfig,
+    ErrorVariant,
+    ExtrinsicOpts,
+    PairSigner,
+    TokenMetadata,
+};
+use crate::{
+    cmd::{
+        extrinsics::events::DisplayEvents,
+        identity::Identities,
+        info::{
+            fetch_code_owner,
+            fetch_contract_info,
+        },
+        registry::{
+            Registry,
+            UpgradeRecord,
+        },
+        runtime_api::api,
+        AsyncCommand,
+        Balance,
+        CodeHash,
+    },
+    name_value_println,
+    DEFAULT_KEY_COL_WIDTH,
+};
+use anyhow::{
+    anyhow,
+    Context,
+    Result,
+};
+use async_trait::async_trait;
+use contract_metadata::ContractMetadata;
+use pallet_contracts_primitives::ContractExecResult;
+use sp_weights::Weight;
+use std::path::{
+    Path,
+    PathBuf,
+};
+use subxt::{
+    Config,
+    OnlineClient,
+};
+
+type AccountId = <DefaultConfig as Config>::AccountId;
+type Hash = <DefaultConfig as Config>::Hash;
+
+/// The default directory the receipts of upgrades are written to.
+pub const DEFAULT_RECEIPT_DIR: &str = "cargo-contract-upgrades";
+
+#[derive(Debug, clap::Args)]
+#[clap(name = "upgrade", about = "Upgrade the code of a contract")]
+pub struct UpgradeCommand {
+    /// The address of the contract to upgrade.
+    #[clap(name = "contract", long, env = "CONTRACT")]
+    contract: AccountId,
+    /// The hash of the uploaded code to upgrade to, defaults to the hash of the contract
+    /// artifacts.
+    #[clap(long, value_parser = parse_code_hash)]
+    code_hash: Option<CodeHash>,
+    /// The message of the contract replacing its code, called with the code hash as its
+    /// only argument.
+    #[clap(long, short, default_value = "set_code")]
+    message: String,
+    #[clap(flatten)]
+    extrinsic_opts: ExtrinsicOpts,
+    #[clap(flatten)]
+    canary: CanaryOpts,
+    #[clap(flatten)]
+    invariants: InvariantOpts,
+    /// Review the upgrade step by step, approving every step before the next: the diff
+    /// of the metadata against the deployed code, the benchmarks, the verifiable
+    /// rebuild, the canary checks and the submission. Declining a step pauses the
+    /// wizard, running the same command again resumes it.
+    #[clap(long, conflicts_with_all = ["skip_confirm", "output_json"])]
+    wizard: bool,
+    /// The `.contract` bundle or `.json` metadata file of the deployed code, which the
+    /// wizard diffs the new metadata against. Defaults to the metadata recorded for the
+    /// deployed code in the local registry.
+    #[clap(long, value_parser, requires = "wizard")]
+    previous_metadata: Option<PathBuf>,
+    /// Maximum amount of gas (execution time) to be used for the upgrade.
+    /// If not specified will perform a dry-run to estimate the gas consumed.
+    #[clap(name = "gas", long)]
+    gas_limit: Option<u64>,
+    /// Maximum proof size for the upgrade.
+    /// If not specified will perform a dry-run to estimate the proof size required.
+    #[clap(long)]
+    proof_size: Option<u64>,
+    /// The directory the receipt and the rollback plan of the upgrade are written to.
+    #[clap(long, value_name = "DIR", default_value = DEFAULT_RECEIPT_DIR)]
+    receipt_dir: PathBuf,
+    /// Export the upgrade output in JSON format.
+    #[clap(long, conflicts_with = "verbose")]
+    output_json: bool,
+}
+
+/// The receipt of a successful upgrade.
+#[derive(Debug, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct UpgradeReceipt {
+    /// The upgraded contract.
+    pub contract: AccountId,
+    /// The URL of the node of the network the contract is upgraded on.
+    pub network: String,
+    /// The code hash of the contract before the upgrade.
+    pub previous_code_hash: CodeHash,
+    /// The code hash of the contract after the upgrade.
+    pub code_hash: CodeHash,
+    /// The message which replaced the code.
+    pub message: String,
+    /// The hash of the block the upgrade was included in.
+    pub block_hash: Hash,
+    /// The canary contract the new code was verified on before the upgrade.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub canary: Option<AccountId>,
+    /// The file the previous code was saved to, if it could be fetched.
+    pub previous_code: Option<PathBuf>,
+    /// The script rolling the upgrade back.
+    pub rollback_script: PathBuf,
+}
+
+impl UpgradeCommand {
+    pub fn is_json(&self) -> bool {
+        self.output_json
+    }
+
+    /// Dry-run the upgrade, returns the gas limit for submitting it.
+    async fn gas_estimate(
+        &self,
+        client: &Client,
+        url: &str,
+        input_data: Vec<u8>,
+        signer: &PairSigner,
+        transcoder: &ContractMessageTranscoder,
+    ) -> Result<Weight, ErrorVariant> {
+        if self.extrinsic_opts.skip_dry_run {
+            return match (self.gas_limit, self.proof_size) {
+                (Some(ref_time), Some(proof_size)) => {
+                    Ok(Weight::from_parts(ref_time, proof_size))
+                }
+                _ => {
+                    Err(anyhow!(
+                        "Weight args `--gas` and `--proof-size` required if \
+                        `--skip-dry-run` specified"
+                    )
+                    .into())
+                }
+            }
+        }
+        if !self.output_json {
+            super::print_dry_running_status(&self.message);
+        }
+        let call_request = CallRequest {
+            origin: signer.account_id().clone(),
+            dest: self.contract.clone(),
+            value: 0,
+            gas_limit: None,
+            storage_deposit_limit: None,
+            input_data,
+        };
+        let result: ContractExecResult<Balance> =
+            state_call(url, "ContractsApi_call", call_request).await?;
+        match result.result {
+            Ok(ref ret_val) if ret_val.did_revert() => {
+                let value = transcoder
+                    .decode_return(&self.message, &mut &ret_val.data[..])
+                    .map(|value| value.to_string())
+                    .unwrap_or_else(|_| format!("0x{}", hex::encode(&ret_val.data)));
+                Err(ErrorVariant::revert(&self.message, value))
+            }
+            Ok(_) => {
+                if !self.output_json {
+                    super::print_gas_required_success(result.gas_required);
+                }
+                Ok(Weight::from_parts(
+                    self.gas_limit
+                        .unwrap_or_else(|| result.gas_required.ref_time()),
+                    self.proof_size
+                        .unwrap_or_else(|| result.gas_required.proof_size()),
+                ))
+            }
+            Err(ref err) => {
+                Err(ErrorVariant::from_dispatch_error(err, &client.metadata())?)
+            }
+        }
+    }
+
+    /// Walk through the steps of the wizard reviewing the new code. Returns `false` if
+    /// the wizard is paused.
+    fn review(
+        &self,
+        wizard: &mut Wizard,
+        artifacts: &ContractArtifacts,
+        url: &str,
+        previous_code_hash: CodeHash,
+        code_hash: CodeHash,
+    ) -> Result<bool> {
+        let metadata = artifacts.metadata()?;
+        if wizard.pending(Step::Diff) {
+            match self.previous_metadata(url, previous_code_hash)? {
+                Some(previous) => print_diff(&previous, &metadata)?,
+                None => {
+                    println!(
+                        "The metadata of the deployed code {previous_code_hash:?} is not \
+                        recorded in the registry, pass it with `--previous-metadata`"
+                    )
+                }
+            }
+            if !wizard.approve(Step::Diff)? {
+                return Ok(false)
+            }
+        }
+        if wizard.pending(Step::Bench) {
+            print_bench(&metadata.contract.name)?;
+            if !wizard.approve(Step::Bench)? {
+                return Ok(false)
+            }
+        }
+        if wizard.pending(Step::Verify) {
+            verify_rebuild(
+                self.extrinsic_opts.manifest_path.as_ref(),
+                &metadata,
+                code_hash,
+                self.extrinsic_opts.verbosity()?,
+            )?;
+            if !wizard.approve(Step::Verify)? {
+                return Ok(false)
+            }
+        }
+        Ok(true)
+    }
+
+    /// Load the metadata of the deployed code, from `--previous-metadata` or from the
+    /// local registry.
+    fn previous_metadata(
+        &self,
+        url: &str,
+        previous_code_hash: CodeHash,
+    ) -> Result<Option<ContractMetadata>> {
+        let path = match self.previous_metadata {
+            Some(ref path) => Some(path.clone()),
+            None => {
+                Registry::load()?
+                    .code(&previous_code_hash, url)
+                    .and_then(|code| code.metadata.clone())
+            }
+        };
+        path.map(|path| {
+            ContractArtifacts::from_manifest_or_file(None, Some(&path))?.metadata()
+        })
+        .transpose()
+    }
+
+    /// Write the receipt of the upgrade, the previous code and the rollback script to the
+    /// receipt directory.
+    async fn write_receipt(
+        &self,
+        client: &Client,
+        url: &str,
+        previous_code_hash: CodeHash,
+        code_hash: CodeHash,
+        block_hash: Hash,
+        canary: Option<AccountId>,
+    ) -> Result<UpgradeReceipt> {
+        let dir = &self.receipt_dir;
+        std::fs::create_dir_all(dir)
+            .with_context(|| format!("Failed to create {}", dir.display()))?;
+        let name = format!(
+            "{}-{}",
+            self.contract,
+            hex::encode(&block_hash.as_bytes()[..4])
+        );
+
+        let previous_code_call =
+            api::storage().contracts().pristine_code(previous_code_hash);
+        let previous_code = client
+            .storage()
+            .at_latest()
+            .await?
+            .fetch(&previous_code_call)
+            .await?;
+        let previous_code = match previous_code {
+            Some(code) => {
+                let path = dir.join(format!("{previous_code_hash:?}.wasm"));
+                write_file(&path, &code.0)?;
+                Some(path)
+            }
+            None => None,
+        };
+
+        let receipt = UpgradeReceipt {
+            contract: self.contract.clone(),
+            network: url.to_string(),
+            previous_code_hash,
+            code_hash,
+            message: self.message.clone(),
+            block_hash,
+            canary,
+            previous_code,
+            rollback_script: dir.join(format!("rollback-{name}.sh")),
+        };
+        write_file(
+            &receipt.rollback_script,
+            rollback_script(&receipt).as_bytes(),
+        )?;
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            std::fs::set_permissions(
+                &receipt.rollback_script,
+                std::fs::Permissions::from_mode(0o755),
+            )?;
+        }
+        let receipt_json = serde_json::to_string_pretty(&receipt)?;
+        write_file(
+            &dir.join(format!("upgrade-{name}.json")),
+            receipt_json.as_bytes(),
+        )?;
+        Ok(receipt)
+    }
+}
+
+#[async_trait(?Send)]
+impl AsyncCommand for UpgradeCommand {
+    type Output = Result<(), ErrorVariant>;
+
+    async fn exec(&self) -> Result<(), ErrorVariant> {
+        if self.extrinsic_opts.phala_backend()?.is_some() {
+            return Err(anyhow!(
+                "Upgrading Phat contracts is not supported by the `phala` backend"
+            )
+            .into())
+        }
+        self.extrinsic_opts.apply_process_opts()?;
+        let artifacts = self.extrinsic_opts.contract_artifacts()?;
+        let identities = Identities::default();
+        let transcoder = artifacts
+            .contract_transcoder_with_identities(&identities)?
+            .with_bytes_format(self.extrinsic_opts.bytes_format());
+        let code_hash = match self.code_hash {
+            Some(code_hash) => code_hash,
+            None => CodeHash::from(artifacts.code_hash()?),
+        };
+        let data = transcoder.encode(&self.message, [format!("{code_hash:?}")])?;
+        let signer = super::pair_signer(self.extrinsic_opts.signer("upgrade")?);
+        let url = self.extrinsic_opts.url_to_string()?;
+        let invariants = self.invariants.load()?;
+
+        let client = OnlineClient::from_url(&url).await?;
+        identities.resolve(&client).await?;
+        let previous_code_hash = fetch_contract_info(&client, &self.contract)
+            .await?
+            .ok_or_else(|| anyhow!("No contract found at {}", self.contract))?
+            .code_hash;
+        if previous_code_hash == code_hash {
+            return Err(anyhow!(
+                "The contract {} already runs the code {code_hash:?}",
+                self.contract
+            )
+            .into())
+        }
+        if fetch_code_owner(&client, code_hash).await?.is_none() {
+            return Err(anyhow!(
+                "The code {code_hash:?} is not uploaded, upload it first with \
+                `cargo contract upload`"
+            )
+            .into())
+        }
+        let mut wizard = if self.wizard {
+            let state = WizardState {
+                contract: self.contract.clone(),
+                network: url.clone(),
+                previous_code_hash,
+                code_hash,
+                approved: Vec::new(),
+                canary: None,
+            };
+            let mut wizard = Wizard::load(&self.receipt_dir, state)?;
+            if !self.review(
+                &mut wizard,
+                &artifacts,
+                &url,
+                previous_code_hash,
+                code_hash,
+            )? {
+                return Ok(())
+            }
+            Some(wizard)
+        } else {
+            None
+        };
+        let token_metadata = TokenMetadata::query(&client).await?;
+        let gas_limit = self
+            .gas_estimate(&client, &url, data.clone(), &signer, &transcoder)
+            .await?;
+        let storage_deposit_limit = self
+            .extrinsic_opts
+            .storage_deposit_limit
+            .as_ref()
+            .map(|bv| bv.denominate_balance(&token_metadata))
+            .transpose()?;
+        let canary = if self.canary.enabled() {
+            Some(self.canary.canary(
+                &client,
+                &url,
+                &signer,
+                &transcoder,
+                code_hash,
+                storage_deposit_limit,
+            )?)
+        } else {
+            None
+        };
+
+        let print_details = || {
+            name_value_println!(
+                "Contract",
+                format!("{}", self.contract),
+                DEFAULT_KEY_COL_WIDTH
+            );
+            name_value_println!(
+                "Code hash",
+                format!("{previous_code_hash:?} -> {code_hash:?}"),
+                DEFAULT_KEY_COL_WIDTH
+            );
+            name_value_println!("Message", self.message, DEFAULT_KEY_COL_WIDTH);
+            name_value_println!(
+                "Gas limit",
+                gas_limit.to_string(),
+                DEFAULT_KEY_COL_WIDTH
+            );
+            if let Some(ref canary) = canary {
+                canary.print_details();
+            }
+        };
+        if !self.extrinsic_opts.execute()? {
+            // The checks require the canary contract to exist on-chain, only its
+            // instantiation can be dry-run.
+            if let Some(ref canary) = canary {
+                canary.dry_run().await?;
+            }
+            print_details();
+            display_dry_run_result_warning("upgrade");
+            return Ok(())
+        }
+        let mut canary_report = None;
+        if let (Some(wizard), Some(canary)) = (wizard.as_mut(), canary.as_ref()) {
+            if wizard.pending(Step::Canary) {
+                self.extrinsic_opts.confirm(|| canary.print_details())?;
+                let report = canary
+                    .deploy_and_check(&self.extrinsic_opts, &token_metadata, false)
+                    .await?;
+                wizard.record_canary(report.contract.clone())?;
+                if !wizard.approve(Step::Canary)? {
+                    return Ok(())
+                }
+                canary_report = Some(report);
+            }
+        }
+        if let Some(ref wizard) = wizard {
+            wizard.pending(Step::Submit);
+        }
+        self.extrinsic_opts.confirm(print_details)?;
+        let canary_report = match canary {
+            Some(ref canary) if wizard.is_none() => {
+                Some(
+                    canary
+                        .deploy_and_check(
+                            &self.extrinsic_opts,
+                            &token_metadata,
+                            self.output_json,
+                        )
+                        .await?,
+                )
+            }
+            _ => canary_report,
+        };
+
+        let call = api::tx().contracts().call(
+            self.contract.clone().into(),
+            0,
+            gas_limit.into(),
+            self.extrinsic_opts.storage_deposit_limit(&token_metadata)?,
+            data,
+        );
+        let submit_options = self.extrinsic_opts.submit_options(&token_metadata)?;
+        let result = submit_extrinsic(&client, &call, &signer, &submit_options).await?;
+
+        let current_code_hash = fetch_contract_info(&client, &self.contract)
+            .await?
+            .map(|info| info.code_hash);
+        if current_code_hash != Some(code_hash) {
+            return Err(anyhow!(
+                "The `{}` message succeeded, but did not change the code hash of the \
+                contract to {code_hash:?}",
+                self.message
+            )
+            .into())
+        }
+
+        let block_hash = result.all_events_in_block().block_hash();
+        let block_number = client.blocks().at(block_hash).await?.number();
+        Registry::update(|registry| {
+            registry.record_upgrade(UpgradeRecord {
+                contract: self.contract.clone(),
+                network: url.clone(),
+                code_hash,
+                block_number: block_number.into(),
+            })
+        })?;
+
+        let receipt = self
+            .write_receipt(
+                &client,
+                &url,
+                previous_code_hash,
+                code_hash,
+                block_hash,
+                canary_report
+                    .as_ref()
+                    .map(|report| &report.contract)
+                    .or_else(|| wizard.as_ref().and_then(Wizard::canary))
+                    .cloned(),
+            )
+            .await?;
+        if let Some(wizard) = wizard {
+            wizard.finish()?;
+        }
+
+        let display_events = DisplayEvents::from_events(
+            &result,
+            Some(&transcoder),
+            &client.metadata(),
+            &token_metadata,
+        )?;
+        if !self.output_json {
+            println!(
+                "{}",
+                display_events.display_events(
+                    self.extrinsic_opts.verbosity()?,
+                    self.extrinsic_opts.number_format,
+                    self.extrinsic_opts.max_output_bytes,
+                )?
+            );
+            name_value_println!(
+                "Rollback",
+                format!("{}", receipt.rollback_script.display()),
+                DEFAULT_KEY_COL_WIDTH
+            );
+        }
+        let invariant_results = match invariants {
+            Some(ref invariants) => {
+                let results = invariants
+                    .check(
+                        &url,
+                        &signer,
+                        &self.contract,
+                        &transcoder,
+                        storage_deposit_limit,
+                        self.output_json,
+                    )
+                    .await
+                    .map_err(|err| {
+                        anyhow!(
+                            "{err}. Roll the upgrade back with {}",
+                            receipt.rollback_script.display()
+                        )
+                    })?;
+                Some(results)
+            }
+            None => None,
+        };
+        if self.output_json {
+            let output = serde_json::json!({
+                "events": display_events,
+                "canary": canary_report,
+                "invariants": invariant_results,
+                "receipt": receipt,
+            });
+            println!(
+                "{}",
+                serde_json::to_string_pretty(&output).map_err(anyhow::Error::from)?
+            );
+        }
+        Ok(())
+    }
+}
+
+fn write_file(path: &Path, contents: &[u8]) -> Result<()> {
+    crate::cmd::lock::write_atomic(path, contents)
+}
+
+/// The script rolling the upgrade back to the previous code, re-uploading it first in
+/// case it was removed since the upgrade.
+fn rollback_script(receipt: &UpgradeReceipt) -> String {
+    let mut script = format!(
+        "#!/bin/sh\n\
+        # Roll back the upgrade of the contract {} on {}\n\
+        # from {:?} to {:?}, included in block {:?}.\n\
+        # Set SURI to the secret key URI of the account allowed to upgrade the contract.\n\
+        set -e\n",
+        receipt.contract,
+        receipt.network,
+        receipt.previous_code_hash,
+        receipt.code_hash,
+        receipt.block_hash,
+    );
+    match receipt.previous_code {
+        Some(ref previous_code) => {
+            script.push_str(&format!(
+            "\n# Re-upload the previous code in case it was removed, this fails if it \
+                is still uploaded.\n\
+                cargo contract upload --file {} --url {} --suri \"$SURI\" --execute \
+                --skip-confirm || true\n",
+            shell_quote(&previous_code.display().to_string()),
+            shell_quote(&receipt.network),
+        ))
+        }
+        None => {
+            script.push_str(
+                "\n# The previous code could not be saved, it must still be uploaded.\n",
+            )
+        }
+    }
+    script.push_str(&format!(
+        "\ncargo contract upgrade --contract {} --code-hash {:?} --message {} --url {} \
+        --suri \"$SURI\" --execute\n",
+        receipt.contract,
+        receipt.previous_code_hash,
+        shell_quote(&receipt.message),
+        shell_quote(&receipt.network),
+    ));
+    script
+}
+
+/// Quote a value for the shell.
+fn shell_quote(value: &str) -> String {
+    format!("'{}'", value.replace('\'', r"'\''"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+
+    #[test]
+    fn rollback_script_reuploads_and_upgrades_to_previous_code() {
+        let mut receipt = UpgradeReceipt {
+            contract: AccountId::from_str(
+                "5FHneW46xGXgs5mUiveU4sbTyGBzmstUspZC92UhjJM694ty",
+            )
+            .unwrap(),
+            network: String::from("ws://localhost:9944/"),
+            previous_code_hash: CodeHash::repeat_byte(1),
+            code_hash: CodeHash::repeat_byte(2),
+            message: String::from("set_code"),
+            block_hash: Hash::repeat_byte(3),
+            canary: None,
+            previous_code: Some(PathBuf::from("upgrades/old code.wasm")),
+            rollback_script: PathBuf::from("upgrades/rollback.sh"),
+        };
+        let script = rollback_script(&receipt);
+        assert!(script.starts_with("#!/bin/sh\n"));
+        assert!(script.contains(
+            "cargo contract upload --file 'upgrades/old code.wasm' \
+            --url 'ws://localhost:9944/' --suri \"$SURI\" --execute --skip-confirm || true"
+        ));
+        assert!(script.ends_with(
+            "cargo contract upgrade --contract \
+            5FHneW46xGXgs5mUiveU4sbTyGBzmstUspZC92UhjJM694ty --code-hash \
+            0x0101010101010101010101010101010101010101010101010101010101010101 \
+            --message 'set_code' --url 'ws://localhost:9944/' --suri \"$SURI\" \
+            --execute\n"
+        ));
+
+        receipt.previous_code = None;
+        assert!(!rollback_script(&receipt).contains("cargo contract upload"));
+    }
+
+    #[test]
+    fn shell_values_are_quoted() {
+        assert_eq!(shell_quote("it's"), r"'it'\''s'");
+    }
+}
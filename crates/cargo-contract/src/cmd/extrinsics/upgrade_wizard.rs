@@ -0,0 +1,485 @@
+// Copyright 2018-2023 Parity Technologies (UK) Ltd.
+// This file is part of cargo-contract.
+//
+// cargo-contract is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// cargo-contract is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with cargo-contract.  If not, see <http://www.gnu.org/licenses/>.
+
+//! The interactive wizard of `upgrade --wizard`.
+//!
+//! The wizard walks through the review of an upgrade in steps, and asks for the approval
+//! of every step before the next one:
+//!
+//! 1. `diff`: the changes of the interface and of the storage layout of the new metadata
+//!    against the metadata of the deployed code.
+//! 2. `bench`: the changes of the metrics between the last two records of the contract in
+//!    the trends store.
+//! 3. `verify`: the rebuild of the contract with the build settings of the bundle, which
+//!    must result in the code upgraded to.
+//! 4. `canary`: the checks against a canary contract, with `--canary`.
+//! 5. `submit`: the upgrade itself, confirmed like any other extrinsic.
+//!
+//! The approved steps are recorded in a state file in the receipt directory. Declining a
+//! step pauses the wizard, running the same command again resumes it at the first step
+//! which is not approved yet. The state file is removed once the upgrade is submitted.
+
+use super::DefaultConfig;
+use crate::{
+    cmd::{
+        changelog::describe,
+        lock,
+        trends::{
+            Trend,
+            Trends,
+        },
+        verify::{
+            bundle_build_info,
+            current_environment_mismatches,
+            rebuild,
+        },
+        CodeHash,
+    },
+    name_value_println,
+    DEFAULT_KEY_COL_WIDTH,
+};
+use anyhow::{
+    Context,
+    Result,
+};
+use colored::Colorize;
+use contract_build::{
+    changelog::Interface,
+    Verbosity,
+};
+use contract_metadata::ContractMetadata;
+use serde_json::Value;
+use std::{
+    fmt,
+    io::{
+        self,
+        Write,
+    },
+    path::{
+        Path,
+        PathBuf,
+    },
+};
+use subxt::Config;
+
+type AccountId = <DefaultConfig as Config>::AccountId;
+
+/// The increase of a metric from the previous build, in percent, flagged as a
+/// regression. The default of `cargo contract trends`.
+const BENCH_THRESHOLD: f64 = 5.0;
+
+/// A step of the wizard.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Step {
+    Diff,
+    Bench,
+    Verify,
+    Canary,
+    Submit,
+}
+
+impl Step {
+    const ALL: [Step; 5] = [
+        Self::Diff,
+        Self::Bench,
+        Self::Verify,
+        Self::Canary,
+        Self::Submit,
+    ];
+
+    fn description(&self) -> &'static str {
+        match self {
+            Self::Diff => "diff the metadata against the deployed code",
+            Self::Bench => "compare the benchmarks with the previous build",
+            Self::Verify => "rebuild the contract and verify its code",
+            Self::Canary => "deploy a canary contract and run the checks",
+            Self::Submit => "submit the upgrade",
+        }
+    }
+}
+
+impl fmt::Display for Step {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Diff => write!(f, "diff"),
+            Self::Bench => write!(f, "bench"),
+            Self::Verify => write!(f, "verify"),
+            Self::Canary => write!(f, "canary"),
+            Self::Submit => write!(f, "submit"),
+        }
+    }
+}
+
+/// The state of the wizard of an upgrade, persisted between the runs of the wizard.
+#[derive(Debug, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct WizardState {
+    /// The upgraded contract.
+    pub contract: AccountId,
+    /// The URL of the node of the network the contract is upgraded on.
+    pub network: String,
+    /// The code hash of the contract before the upgrade.
+    pub previous_code_hash: CodeHash,
+    /// The code hash the contract is upgraded to.
+    pub code_hash: CodeHash,
+    /// The approved steps.
+    #[serde(default)]
+    pub approved: Vec<Step>,
+    /// The canary contract deployed by the `canary` step.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub canary: Option<AccountId>,
+}
+
+impl WizardState {
+    /// Returns `true` if the state is of the same upgrade.
+    fn is_same_upgrade(&self, other: &Self) -> bool {
+        self.contract == other.contract
+            && self.network == other.network
+            && self.previous_code_hash == other.previous_code_hash
+            && self.code_hash == other.code_hash
+    }
+}
+
+/// The wizard of an upgrade.
+pub struct Wizard {
+    path: PathBuf,
+    state: WizardState,
+}
+
+impl Wizard {
+    /// Resume the paused wizard of the upgrade from its state file in the directory, or
+    /// start a new one. The state of another upgrade of the contract is discarded.
+    pub fn load(dir: &Path, state: WizardState) -> Result<Self> {
+        let path = dir.join(format!("wizard-{}.json", state.contract));
+        let state = match std::fs::read_to_string(&path) {
+            Ok(contents) => {
+                let paused: WizardState =
+                    serde_json::from_str(&contents).with_context(|| {
+                        format!("Invalid wizard state {}", path.display())
+                    })?;
+                if paused.is_same_upgrade(&state) {
+                    println!("Resuming the wizard paused in {}", path.display());
+                    paused
+                } else {
+                    eprintln!(
+                        "{} discarding the wizard of the upgrade to {:?}, paused in {}",
+                        "warning:".yellow().bold(),
+                        paused.code_hash,
+                        path.display()
+                    );
+                    state
+                }
+            }
+            Err(err) if err.kind() == io::ErrorKind::NotFound => state,
+            Err(err) => {
+                return Err(err)
+                    .with_context(|| format!("Failed to read {}", path.display()))
+            }
+        };
+        Ok(Self { path, state })
+    }
+
+    /// Returns `true` if the step is not approved yet, and announces it.
+    pub fn pending(&self, step: Step) -> bool {
+        let position = Step::ALL
+            .iter()
+            .position(|s| *s == step)
+            .unwrap_or_default();
+        let heading = format!("Step {}/{}", position + 1, Step::ALL.len());
+        if self.state.approved.contains(&step) {
+            println!("{} {} (approved)", heading.bold(), step.description());
+            return false
+        }
+        println!("\n{} {}", heading.bright_white().bold(), step.description());
+        true
+    }
+
+    /// Ask for the approval of the step. Returns `false` if the step is declined, which
+    /// pauses the wizard.
+    pub fn approve(&mut self, step: Step) -> Result<bool> {
+        print!(
+            "{} (y/{}): ",
+            format!("Approve the {step} step?").bright_white().bold(),
+            "N".bright_white().bold()
+        );
+        let mut buf = String::new();
+        io::stdout().flush()?;
+        io::stdin().read_line(&mut buf)?;
+        let approved = matches!(buf.trim().to_lowercase().as_str(), "y" | "yes");
+        if approved {
+            self.state.approved.push(step);
+        }
+        self.save()?;
+        if !approved {
+            println!(
+                "Paused before approving the {step} step, run the same command again to \
+                resume"
+            );
+        }
+        Ok(approved)
+    }
+
+    /// The canary contract deployed by an earlier run of the wizard.
+    pub fn canary(&self) -> Option<&AccountId> {
+        self.state.canary.as_ref()
+    }
+
+    /// Record the canary contract deployed by the `canary` step.
+    pub fn record_canary(&mut self, canary: AccountId) -> Result<()> {
+        self.state.canary = Some(canary);
+        self.save()
+    }
+
+    /// Remove the state file once the upgrade is submitted.
+    pub fn finish(self) -> Result<()> {
+        match std::fs::remove_file(&self.path) {
+            Err(err) if err.kind() != io::ErrorKind::NotFound => {
+                Err(err)
+                    .with_context(|| format!("Failed to remove {}", self.path.display()))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    fn save(&self) -> Result<()> {
+        if let Some(dir) = self.path.parent() {
+            std::fs::create_dir_all(dir)
+                .with_context(|| format!("Failed to create {}", dir.display()))?;
+        }
+        let json = serde_json::to_string_pretty(&self.state)?;
+        lock::write_atomic(&self.path, json.as_bytes())
+    }
+}
+
+/// Print the changes of the interface and of the storage layout of the new metadata
+/// against the metadata of the deployed code.
+pub fn print_diff(previous: &ContractMetadata, new: &ContractMetadata) -> Result<()> {
+    let changes =
+        Interface::from_abi(&previous.abi)?.diff(&Interface::from_abi(&new.abi)?);
+    if changes.is_empty() {
+        println!("The interface is unchanged");
+    }
+    for change in &changes {
+        println!("  {}", describe(change));
+    }
+    let changes = storage_changes(
+        &Value::Object(previous.abi.clone()),
+        &Value::Object(new.abi.clone()),
+    );
+    if changes.is_empty() {
+        println!("The storage layout is unchanged");
+    }
+    for change in &changes {
+        println!("  {change}");
+    }
+    Ok(())
+}
+
+/// Print the changes of the metrics between the last two records of the contract in the
+/// trends store, flagging the regressions.
+pub fn print_bench(contract: &str) -> Result<()> {
+    let path = Trends::path();
+    if !path.exists() {
+        println!(
+            "No trends store found at {}, record the builds with `cargo contract trends \
+            record`",
+            path.display()
+        );
+        return Ok(())
+    }
+    let trends = Trends::load(&path)?;
+    let records = trends.window(contract, 2);
+    if records.len() < 2 {
+        println!(
+            "Fewer than two builds of `{contract}` are recorded, nothing to compare"
+        );
+        return Ok(())
+    }
+    for trend in Trend::collect(&records) {
+        print!("{}", trend.display(BENCH_THRESHOLD));
+    }
+    Ok(())
+}
+
+/// Rebuild the contract with the build settings of the bundle, and fail unless the code
+/// of the rebuild is the code upgraded to.
+pub fn verify_rebuild(
+    manifest_path: Option<&PathBuf>,
+    metadata: &ContractMetadata,
+    code_hash: CodeHash,
+    verbosity: Verbosity,
+) -> Result<()> {
+    let build_info = bundle_build_info(metadata)?;
+    let mismatches = current_environment_mismatches(&build_info)?;
+    for mismatch in &mismatches {
+        eprintln!("{} {mismatch}", "warning:".yellow().bold());
+    }
+    let rebuilt = CodeHash::from(rebuild(manifest_path, &build_info, verbosity)?);
+    name_value_println!("Expected", format!("{code_hash:?}"), DEFAULT_KEY_COL_WIDTH);
+    name_value_println!("Rebuilt", format!("{rebuilt:?}"), DEFAULT_KEY_COL_WIDTH);
+    anyhow::ensure!(
+        rebuilt == code_hash,
+        "Not verified: the rebuilt code does not match the code upgraded to"
+    );
+    println!("{}", "Verified: the rebuilt code matches".green().bold());
+    Ok(())
+}
+
+/// The fields of the storage struct of a contract: their names, and their own root keys
+/// unless they are packed into the root cell of the contract.
+fn storage_fields(abi: &Value) -> Vec<(String, Option<String>)> {
+    abi.pointer("/storage/root/layout/struct/fields")
+        .and_then(Value::as_array)
+        .into_iter()
+        .flatten()
+        .filter_map(|field| {
+            let name = field.get("name")?.as_str()?;
+            let key = field
+                .pointer("/layout/root/root_key")
+                .and_then(Value::as_str);
+            Some((name.to_string(), key.map(String::from)))
+        })
+        .collect()
+}
+
+/// Describe the changes of the fields of the storage struct which affect the stored
+/// state. The types of the fields are not compared.
+fn storage_changes(previous: &Value, new: &Value) -> Vec<String> {
+    let previous = storage_fields(previous);
+    let new = storage_fields(new);
+    let location = |key: &Option<String>| {
+        match key {
+            Some(key) => format!("the key {key}"),
+            None => String::from("the root cell"),
+        }
+    };
+    let mut changes = Vec::new();
+    for (name, key) in &previous {
+        match new.iter().find(|(new_name, _)| new_name == name) {
+            None => {
+                changes.push(format!(
+                    "Removed field `{name}`, its state stays in {}",
+                    location(key)
+                ))
+            }
+            Some((_, new_key)) if new_key != key => {
+                changes.push(format!(
+                    "Moved field `{name}` from {} to {}, its state is not migrated",
+                    location(key),
+                    location(new_key)
+                ))
+            }
+            Some(_) => {}
+        }
+    }
+    for (name, key) in &new {
+        if !previous
+            .iter()
+            .any(|(previous_name, _)| previous_name == name)
+        {
+            changes.push(format!("Added field `{name}` in {}", location(key)));
+        }
+    }
+    let packed = |fields: &[(String, Option<String>)]| {
+        fields
+            .iter()
+            .filter(|(_, key)| key.is_none())
+            .map(|(name, _)| name.as_str())
+            .collect::<Vec<_>>()
+            .join(", ")
+    };
+    let (previous_packed, new_packed) = (packed(&previous), packed(&new));
+    if previous_packed != new_packed {
+        changes.push(format!(
+            "The fields packed into the root cell changed from ({previous_packed}) to \
+            ({new_packed}), the stored root cell no longer decodes"
+        ));
+    }
+    changes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn abi(fields: Value) -> Value {
+        serde_json::json!({
+            "storage": {
+                "root": {
+                    "root_key": "0x00000000",
+                    "layout": { "struct": { "name": "Contract", "fields": fields } }
+                }
+            }
+        })
+    }
+
+    #[test]
+    fn storage_layout_changes_are_described() {
+        let previous = abi(serde_json::json!([
+            { "name": "value", "layout": { "leaf": { "key": "0x00000000", "ty": 0 } } },
+            { "name": "owner", "layout": { "leaf": { "key": "0x00000000", "ty": 1 } } },
+            { "name": "balances", "layout": { "root": { "root_key": "0x01000000" } } },
+        ]));
+        assert!(storage_changes(&previous, &previous).is_empty());
+
+        let new = abi(serde_json::json!([
+            { "name": "owner", "layout": { "leaf": { "key": "0x00000000", "ty": 1 } } },
+            { "name": "value", "layout": { "leaf": { "key": "0x00000000", "ty": 0 } } },
+            { "name": "balances", "layout": { "root": { "root_key": "0x02000000" } } },
+            { "name": "allowances", "layout": { "root": { "root_key": "0x03000000" } } },
+        ]));
+        assert_eq!(
+            storage_changes(&previous, &new),
+            vec![
+                "Moved field `balances` from the key 0x01000000 to the key 0x02000000, \
+                its state is not migrated",
+                "Added field `allowances` in the key 0x03000000",
+                "The fields packed into the root cell changed from (value, owner) to \
+                (owner, value), the stored root cell no longer decodes",
+            ]
+        );
+    }
+
+    #[test]
+    fn wizard_state_of_another_upgrade_is_discarded() {
+        let dir = tempfile::tempdir().unwrap();
+        let state = |code_hash| {
+            WizardState {
+                contract: AccountId::from([1u8; 32]),
+                network: String::from("ws://localhost:9944"),
+                previous_code_hash: CodeHash::repeat_byte(1),
+                code_hash,
+                approved: Vec::new(),
+                canary: None,
+            }
+        };
+        let mut wizard =
+            Wizard::load(dir.path(), state(CodeHash::repeat_byte(2))).unwrap();
+        wizard.state.approved.push(Step::Diff);
+        wizard.save().unwrap();
+
+        let resumed = Wizard::load(dir.path(), state(CodeHash::repeat_byte(2))).unwrap();
+        assert_eq!(resumed.state.approved, vec![Step::Diff]);
+        let other = Wizard::load(dir.path(), state(CodeHash::repeat_byte(3))).unwrap();
+        assert!(other.state.approved.is_empty());
+
+        resumed.finish().unwrap();
+        assert!(!dir
+            .path()
+            .join(format!("wizard-{}.json", AccountId::from([1u8; 32])))
+            .exists());
+    }
+}
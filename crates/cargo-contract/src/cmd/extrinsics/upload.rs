@@ -15,35 +15,56 @@
 // along with cargo-contract.  If not, see <http://www.gnu.org/licenses/>.
 
 use super::{
+    deployments,
     display_dry_run_result_warning,
+    ensure_mapped,
+    offline::{
+        submit_or_print_unsigned,
+        ExtrinsicSigner,
+    },
+    phala::PhalaBackend,
     state_call,
-    submit_extrinsic,
+    submit_dynamic_call,
     Client,
     DefaultConfig,
+    DisplayBalance,
     ExtrinsicOpts,
+    NumberFormat,
     PairSigner,
     TokenMetadata,
 };
 use crate::{
     cmd::{
+        chain_env,
+        chain_info::ChainLimits,
         extrinsics::{
             events::DisplayEvents,
             ErrorVariant,
             WasmCode,
         },
+        info::fetch_code_owner,
+        registry::Registry,
         runtime_api::api::{
             self,
             runtime_types::pallet_contracts::wasm::Determinism,
         },
+        schema::OutputSchema,
+        session,
+        AsyncCommand,
         Balance,
         CodeHash,
     },
     name_value_println,
 };
 use anyhow::Result;
+use async_trait::async_trait;
+use colored::Colorize as _;
 use pallet_contracts_primitives::CodeUploadResult;
 use scale::Encode;
-use std::fmt::Debug;
+use std::{
+    fmt::Debug,
+    path::PathBuf,
+};
 use subxt::{
     Config,
     OnlineClient,
@@ -57,6 +78,13 @@ pub struct UploadCommand {
     /// Export the call output in JSON format.
     #[clap(long, conflicts_with = "verbose")]
     output_json: bool,
+    /// Print the JSON Schema of the `--output-json` output and exit.
+    #[clap(long)]
+    schema: bool,
+    /// After the upload, write the code hash into the file: a module of Rust
+    /// constants if it ends with `.rs`, otherwise a `.env` file.
+    #[clap(long, value_name = "PATH")]
+    write_code_hash: Option<PathBuf>,
 }
 
 impl UploadCommand {
@@ -64,83 +92,99 @@ impl UploadCommand {
         self.output_json
     }
 
-    pub fn run(&self) -> Result<(), ErrorVariant> {
-        let artifacts = self.extrinsic_opts.contract_artifacts()?;
-        let signer = super::pair_signer(self.extrinsic_opts.signer()?);
+    /// Upload the code to the cluster of the Phala backend. Phat contracts are executed
+    /// off-chain, so the upload can not be dry-run.
+    async fn upload_phala(
+        &self,
+        phala: &PhalaBackend,
+        code: WasmCode,
+        signer: &PairSigner,
+    ) -> Result<(), ErrorVariant> {
+        let upload_result = UploadResult {
+            code_hash: format!("{:?}", CodeHash::from(code.code_hash())),
+        };
+        if !self.extrinsic_opts.execute()? {
+            name_value_println!("Cluster", format!("{:?}", phala.cluster()));
+            upload_result.print();
+            display_dry_run_result_warning("upload");
+            return Ok(())
+        }
 
-        let artifacts_path = artifacts.artifact_path().to_path_buf();
-        let code = artifacts.code.ok_or_else(|| {
-            anyhow::anyhow!(
-                "Contract code not found from artifact file {}",
-                artifacts_path.display()
-            )
-        })?;
-        let code_hash = code.code_hash();
+        let url = self.extrinsic_opts.url_to_string()?;
+        let client = OnlineClient::from_url(url).await?;
+        let events = submit_dynamic_call(
+            &client,
+            &phala.upload_code_call(code.0),
+            signer,
+            &self.extrinsic_opts,
+            false,
+        )
+        .await?;
+        session::record(&upload_result);
+        // the JSON output is the single object of the result, as for `pallet-contracts`
+        if self.output_json {
+            println!("{}", upload_result.to_json()?);
+        } else {
+            println!("{events}");
+            upload_result.print();
+        }
+        Ok(())
+    }
 
-        async_std::task::block_on(async {
-            let url = self.extrinsic_opts.url_to_string();
-            let client = OnlineClient::from_url(url.clone()).await?;
-
-            if !self.extrinsic_opts.execute {
-                match self.upload_code_rpc(code, &client, &signer).await? {
-                    Ok(result) => {
-                        let upload_result = UploadDryRunResult {
-                            result: String::from("Success!"),
-                            code_hash: format!("{:?}", result.code_hash),
-                            deposit: result.deposit,
-                        };
-                        if self.output_json {
-                            println!("{}", upload_result.to_json()?);
-                        } else {
-                            upload_result.print();
-                            display_dry_run_result_warning("upload");
-                        }
-                    }
-                    Err(err) => {
-                        let metadata = client.metadata();
-                        let err = ErrorVariant::from_dispatch_error(&err, &metadata)?;
-                        if self.output_json {
-                            return Err(err)
-                        } else {
-                            name_value_println!("Result", err);
-                        }
-                    }
-                }
-            } else if let Some(code_stored) =
-                self.upload_code(&client, code, &signer).await?
-            {
-                let upload_result = UploadResult {
-                    code_hash: format!("{:?}", code_stored.code_hash),
-                };
-                if self.output_json {
-                    println!("{}", upload_result.to_json()?);
-                } else {
-                    upload_result.print();
-                }
+    /// Report where the code is already uploaded: on the target chain, and on the
+    /// other networks recorded in the registry. Fails before submitting the upload if
+    /// the chain already stores the code, which would only pay the transaction fee.
+    async fn check_duplicate(
+        &self,
+        client: &Client,
+        code_hash: CodeHash,
+        network: &str,
+    ) -> Result<()> {
+        let registry = Registry::load()?;
+        let uploader = |account: &<DefaultConfig as Config>::AccountId| {
+            if registry.is_tracked(account) {
+                format!("{account}, an account of the registry")
             } else {
-                let code_hash = hex::encode(code_hash);
-                return Err(anyhow::anyhow!(
-                    "This contract has already been uploaded with code hash: 0x{code_hash}"
-                )
-                .into())
+                account.to_string()
             }
-            Ok(())
-        })
+        };
+        for (other, account) in registry.code_networks(&code_hash) {
+            if other != network {
+                eprintln!(
+                    "{} The code is also uploaded to {other} by {}",
+                    "note:".cyan().bold(),
+                    uploader(account)
+                );
+            }
+        }
+        let Some(owner) = fetch_code_owner(client, code_hash).await? else {
+            return Ok(())
+        };
+        let message = format!(
+            "The code {code_hash:?} is already uploaded to {network} by {}",
+            uploader(&owner)
+        );
+        if self.extrinsic_opts.execute()? {
+            return Err(anyhow::anyhow!(
+                "{message}, uploading it again would not store it a second time"
+            ))
+        }
+        eprintln!("{} {message}", "warning:".yellow().bold());
+        Ok(())
     }
 
     async fn upload_code_rpc(
         &self,
         code: WasmCode,
-        client: &Client,
-        signer: &PairSigner,
+        signer: &ExtrinsicSigner,
+        token_metadata: &TokenMetadata,
     ) -> Result<CodeUploadResult<CodeHash, Balance>> {
-        let url = self.extrinsic_opts.url_to_string();
-        let token_metadata = TokenMetadata::query(client).await?;
+        let url = self.extrinsic_opts.url_to_string()?;
         let storage_deposit_limit = self
             .extrinsic_opts
             .storage_deposit_limit
             .as_ref()
-            .map(|bv| bv.denominate_balance(&token_metadata))
+            .map(|bv| bv.denominate_balance(token_metadata))
             .transpose()?;
         let call_request = CodeUploadRequest {
             origin: signer.account_id().clone(),
@@ -155,9 +199,39 @@ impl UploadCommand {
         &self,
         client: &Client,
         code: WasmCode,
-        signer: &PairSigner,
+        signer: &ExtrinsicSigner,
     ) -> Result<Option<api::contracts::events::CodeStored>, ErrorVariant> {
         let token_metadata = TokenMetadata::query(client).await?;
+        let code_hash = CodeHash::from(code.code_hash());
+        let deposit = if self.extrinsic_opts.skip_dry_run {
+            None
+        } else {
+            if !self.output_json {
+                super::print_dry_running_status("upload");
+            }
+            match self
+                .upload_code_rpc(code.clone(), signer, &token_metadata)
+                .await?
+            {
+                Ok(result) => Some(DisplayBalance::new(result.deposit, &token_metadata)?),
+                Err(err) => {
+                    return Err(ErrorVariant::from_dispatch_error(
+                        &err,
+                        &client.metadata(),
+                    )?)
+                }
+            }
+        };
+        self.extrinsic_opts.confirm(|| {
+            name_value_println!("Code hash", format!("{code_hash:?}"));
+            if let Some(ref deposit) = deposit {
+                name_value_println!(
+                    "Deposit",
+                    deposit.format(self.extrinsic_opts.number_format)
+                );
+            }
+        })?;
+
         let storage_deposit_limit =
             self.extrinsic_opts.storage_deposit_limit(&token_metadata)?;
         let call = crate::cmd::runtime_api::api::tx().contracts().upload_code(
@@ -166,16 +240,27 @@ impl UploadCommand {
             Determinism::Deterministic,
         );
 
-        let result = submit_extrinsic(client, &call, signer).await?;
-        let display_events =
-            DisplayEvents::from_events(&result, None, &client.metadata())?;
+        let submit_options = self.extrinsic_opts.submit_options(&token_metadata)?;
+        let Some(result) =
+            submit_or_print_unsigned(client, &call, signer, &submit_options).await?
+        else {
+            return Ok(None)
+        };
+        let display_events = DisplayEvents::from_events(
+            &result,
+            None,
+            &client.metadata(),
+            &token_metadata,
+        )?;
 
         let output = if self.output_json {
             display_events.to_json()?
         } else {
-            let token_metadata = TokenMetadata::query(client).await?;
-            display_events
-                .display_events(self.extrinsic_opts.verbosity()?, &token_metadata)?
+            display_events.display_events(
+                self.extrinsic_opts.verbosity()?,
+                self.extrinsic_opts.number_format,
+                self.extrinsic_opts.max_output_bytes,
+            )?
         };
         println!("{output}");
         let code_stored = result.find_first::<api::contracts::events::CodeStored>()?;
@@ -183,13 +268,109 @@ impl UploadCommand {
     }
 }
 
+#[async_trait(?Send)]
+impl AsyncCommand for UploadCommand {
+    type Output = Result<(), ErrorVariant>;
+
+    async fn exec(&self) -> Result<(), ErrorVariant> {
+        if self.schema {
+            return Ok(OutputSchema::Upload.print()?)
+        }
+        self.extrinsic_opts.apply_process_opts()?;
+        let artifacts = self.extrinsic_opts.contract_artifacts()?;
+        let signer = self.extrinsic_opts.extrinsic_signer("upload")?;
+
+        let artifacts_path = artifacts.artifact_path().to_path_buf();
+        let contract_name = match artifacts.metadata() {
+            Ok(metadata) => metadata.contract.name,
+            Err(_) => {
+                artifacts_path
+                    .file_stem()
+                    .map(|stem| stem.to_string_lossy().into_owned())
+                    .unwrap_or_default()
+            }
+        };
+        let code = artifacts.code()?.clone();
+        let code_hash = code.code_hash();
+
+        if let Some(phala) = self.extrinsic_opts.phala_backend()? {
+            return self.upload_phala(&phala, code, signer.pair()?).await
+        }
+
+        let url = self.extrinsic_opts.url_to_string()?;
+        let client = OnlineClient::from_url(url.clone()).await?;
+        chain_env::ensure_supported_chain(&client)?;
+        ensure_mapped(&client, signer.account_id()).await?;
+        self.extrinsic_opts.preflight(&client).await?;
+        ChainLimits::from_metadata(&client.metadata())?.check_code(&code.0)?;
+        self.check_duplicate(&client, CodeHash::from(code_hash), &url)
+            .await?;
+
+        if !self.extrinsic_opts.execute()? {
+            let token_metadata = TokenMetadata::query(&client).await?;
+            match self.upload_code_rpc(code, &signer, &token_metadata).await? {
+                Ok(result) => {
+                    let upload_result = UploadDryRunResult {
+                        result: String::from("Success!"),
+                        code_hash: format!("{:?}", result.code_hash),
+                        deposit: DisplayBalance::new(result.deposit, &token_metadata)?,
+                    };
+                    session::record(&upload_result);
+                    if self.output_json {
+                        println!("{}", upload_result.to_json()?);
+                    } else {
+                        upload_result.print(self.extrinsic_opts.number_format);
+                        display_dry_run_result_warning("upload");
+                    }
+                }
+                Err(err) => {
+                    let metadata = client.metadata();
+                    let err = ErrorVariant::from_dispatch_error(&err, &metadata)?;
+                    if self.output_json {
+                        return Err(err)
+                    } else {
+                        name_value_println!("Result", err);
+                    }
+                }
+            }
+        } else if let Some(code_stored) = self.upload_code(&client, code, &signer).await?
+        {
+            if let Some(ref path) = self.write_code_hash {
+                deployments::write_code_hash(
+                    path,
+                    &contract_name,
+                    code_stored.code_hash.0,
+                )?;
+            }
+            let upload_result = UploadResult {
+                code_hash: format!("{:?}", code_stored.code_hash),
+            };
+            session::record(&upload_result);
+            if self.output_json {
+                println!("{}", upload_result.to_json()?);
+            } else {
+                upload_result.print();
+            }
+        } else if matches!(signer, ExtrinsicSigner::Offline(_)) {
+            // Only the unsigned payload of the upload was printed.
+        } else {
+            let code_hash = hex::encode(code_hash);
+            return Err(anyhow::anyhow!(
+                "This contract has already been uploaded with code hash: 0x{code_hash}"
+            )
+            .into())
+        }
+        Ok(())
+    }
+}
+
 /// A struct that encodes RPC parameters required for a call to upload a new code.
 #[derive(Encode)]
 pub struct CodeUploadRequest {
-    origin: <DefaultConfig as Config>::AccountId,
-    code: Vec<u8>,
-    storage_deposit_limit: Option<Balance>,
-    determinism: Determinism,
+    pub(super) origin: <DefaultConfig as Config>::AccountId,
+    pub(super) code: Vec<u8>,
+    pub(super) storage_deposit_limit: Option<Balance>,
+    pub(super) determinism: Determinism,
 }
 
 #[derive(serde::Serialize)]
@@ -201,7 +382,7 @@ pub struct UploadResult {
 pub struct UploadDryRunResult {
     result: String,
     code_hash: String,
-    deposit: Balance,
+    deposit: DisplayBalance,
 }
 
 impl UploadResult {
@@ -219,9 +400,9 @@ impl UploadDryRunResult {
         Ok(serde_json::to_string_pretty(self)?)
     }
 
-    pub fn print(&self) {
+    pub fn print(&self, number_format: NumberFormat) {
         name_value_println!("Result", self.result);
         name_value_println!("Code hash", format!("{:?}", self.code_hash));
-        name_value_println!("Deposit", format!("{:?}", self.deposit));
+        name_value_println!("Deposit", self.deposit.format(number_format));
     }
 }
@@ -0,0 +1,469 @@
+// Copyright 2018-2023 Parity Technologies (UK) Ltd.
+// This file is part of cargo-contract.
+//
+// cargo-contract is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// cargo-contract is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with cargo-contract.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Waiting until the return value of a message satisfies a condition.
+//!
+//! The condition compares the decoded return value, `result`, or one of its fields with
+//! a literal, and conditions are combined with `&&` and `||`:
+//!
+//! ```text
+//! result == true
+//! result.total_supply >= 1_000 && result.paused == false
+//! result[0] != None || result.owner == 5GrwvaEF5zXb26Fz9rcQpDWS57CtERHpNehXCPcNoHGKutQY
+//! ```
+//!
+//! The `Ok` wrapping the return values of ink! messages is unwrapped, numbers are
+//! compared numerically and other values by their display, without whitespace.
+
+use super::{
+    call::CallRequest,
+    invariants::{
+        as_number,
+        unwrap_ok,
+        Op,
+    },
+    state_call,
+    DefaultConfig,
+    ErrorVariant,
+    ExtrinsicOpts,
+    TokenMetadata,
+};
+use crate::{
+    cmd::{
+        identity::Identities,
+        AsyncCommand,
+        Balance,
+    },
+    DEFAULT_KEY_COL_WIDTH,
+};
+use anyhow::{
+    anyhow,
+    Result,
+};
+use async_trait::async_trait;
+use contract_build::name_value_println;
+use contract_transcode::Value;
+use pallet_contracts_primitives::ContractExecResult;
+use std::time::{
+    Duration,
+    Instant,
+};
+use subxt::{
+    Config,
+    OnlineClient,
+};
+
+#[derive(Debug, clap::Args)]
+#[clap(
+    name = "wait-until",
+    about = "Dry-run a message repeatedly until its return value satisfies a condition"
+)]
+pub struct WaitUntilCommand {
+    /// The address of the the contract to call.
+    #[clap(name = "contract", long, env = "CONTRACT")]
+    contract: <DefaultConfig as Config>::AccountId,
+    /// The name of the contract message to dry-run.
+    #[clap(long, short)]
+    message: String,
+    /// The arguments of the contract message.
+    #[clap(long, num_args = 0..)]
+    args: Vec<String>,
+    /// The condition on the return value, e.g. `result == true` or
+    /// `result.balance >= 1000 && result.paused == false`.
+    #[clap(long)]
+    until: String,
+    /// Give up with an error after the given number of seconds.
+    #[clap(long, value_name = "SECONDS", default_value_t = 60)]
+    timeout: u64,
+    /// The number of seconds between two dry-runs.
+    #[clap(long, value_name = "SECONDS", default_value_t = 2)]
+    interval: u64,
+    #[clap(flatten)]
+    extrinsic_opts: ExtrinsicOpts,
+    /// Export the outcome in JSON format.
+    #[clap(long, conflicts_with = "verbose")]
+    output_json: bool,
+}
+
+/// The outcome of waiting for the condition.
+#[derive(Debug, serde::Serialize)]
+pub struct WaitResult {
+    /// Whether the condition was satisfied before the timeout.
+    pub satisfied: bool,
+    /// The number of dry-runs of the message.
+    pub polls: u32,
+    /// The last decoded return value.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub value: Option<Value>,
+    /// Why the last dry-run did not satisfy the condition.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reason: Option<String>,
+}
+
+impl WaitUntilCommand {
+    pub fn is_json(&self) -> bool {
+        self.output_json
+    }
+}
+
+#[async_trait(?Send)]
+impl AsyncCommand for WaitUntilCommand {
+    type Output = Result<(), ErrorVariant>;
+
+    async fn exec(&self) -> Result<(), ErrorVariant> {
+        let condition = Condition::parse(&self.until)?;
+        self.extrinsic_opts.apply_process_opts()?;
+        let artifacts = self.extrinsic_opts.contract_artifacts()?;
+        let identities = Identities::default();
+        let transcoder = artifacts
+            .contract_transcoder_with_identities(&identities)?
+            .with_bytes_format(self.extrinsic_opts.bytes_format());
+        let input_data = transcoder.encode(&self.message, &self.args)?;
+        let signer = super::pair_signer(self.extrinsic_opts.signer("wait-until")?);
+
+        let url = self.extrinsic_opts.url_to_string()?;
+        let client = OnlineClient::<DefaultConfig>::from_url(url.clone()).await?;
+        identities.resolve(&client).await?;
+        let token_metadata = TokenMetadata::query(&client).await?;
+        let storage_deposit_limit = self
+            .extrinsic_opts
+            .storage_deposit_limit
+            .as_ref()
+            .map(|bv| bv.denominate_balance(&token_metadata))
+            .transpose()?;
+
+        let deadline = Instant::now() + Duration::from_secs(self.timeout);
+        let mut result = WaitResult {
+            satisfied: false,
+            polls: 0,
+            value: None,
+            reason: None,
+        };
+        loop {
+            result.polls += 1;
+            let request = CallRequest {
+                origin: signer.account_id().clone(),
+                dest: self.contract.clone(),
+                value: 0,
+                gas_limit: None,
+                storage_deposit_limit,
+                input_data: input_data.clone(),
+            };
+            let response: Result<ContractExecResult<Balance>> =
+                state_call(&url, "ContractsApi_call", request).await;
+            let outcome = match response.map(|response| response.result) {
+                Ok(Ok(ret_val)) if ret_val.did_revert() => {
+                    Err(format!("`{}` reverted", self.message))
+                }
+                Ok(Ok(ret_val)) => {
+                    let value = transcoder
+                        .decode_return(&self.message, &mut &ret_val.data[..])?;
+                    let outcome = condition.evaluate(&value);
+                    result.value = Some(value);
+                    outcome
+                }
+                Ok(Err(ref err)) => {
+                    Err(ErrorVariant::from_dispatch_error(err, &client.metadata())?
+                        .to_string())
+                }
+                // The node may be restarting, e.g. during a deployment.
+                Err(err) => Err(format!("{err:#}")),
+            };
+            match outcome {
+                Ok(()) => {
+                    result.satisfied = true;
+                    result.reason = None;
+                    break
+                }
+                Err(reason) => {
+                    if !self.output_json {
+                        eprintln!("Waiting: {reason}");
+                    }
+                    result.reason = Some(reason);
+                }
+            }
+            if Instant::now() >= deadline {
+                break
+            }
+            tokio::time::sleep(Duration::from_secs(self.interval)).await;
+        }
+
+        if self.output_json {
+            let json =
+                serde_json::to_string_pretty(&result).map_err(anyhow::Error::from)?;
+            println!("{json}");
+        } else if let Some(ref value) = result.value {
+            name_value_println!("Value", format!("{value}"), DEFAULT_KEY_COL_WIDTH);
+        }
+        if !result.satisfied {
+            return Err(anyhow!(
+                "`{}` was not satisfied after {} seconds: {}",
+                self.until,
+                self.timeout,
+                result.reason.unwrap_or_default()
+            )
+            .into())
+        }
+        if !self.output_json {
+            name_value_println!(
+                "Satisfied",
+                format!("after {} dry-runs", result.polls),
+                DEFAULT_KEY_COL_WIDTH
+            );
+        }
+        Ok(())
+    }
+}
+
+/// A condition on the return value of a message, in disjunctive normal form.
+#[derive(Debug, PartialEq, Eq)]
+struct Condition {
+    /// The alternatives of `||`, each a conjunction of `&&`.
+    any: Vec<Vec<Comparison>>,
+}
+
+/// A comparison of a part of the return value with a literal.
+#[derive(Debug, PartialEq, Eq)]
+struct Comparison {
+    path: Vec<Segment>,
+    op: Op,
+    literal: String,
+}
+
+/// A step into the return value.
+#[derive(Debug, PartialEq, Eq)]
+enum Segment {
+    /// A field of a struct or map, or with a number an element of a tuple.
+    Field(String),
+    /// An element of a sequence or tuple.
+    Index(usize),
+}
+
+impl Condition {
+    fn parse(input: &str) -> Result<Self> {
+        let any = input
+            .split("||")
+            .map(|all| all.split("&&").map(Comparison::parse).collect())
+            .collect::<Result<_>>()?;
+        Ok(Self { any })
+    }
+
+    /// Evaluate the condition, returns why it does not hold.
+    fn evaluate(&self, value: &Value) -> Result<(), String> {
+        let mut reasons = Vec::new();
+        for all in &self.any {
+            match all
+                .iter()
+                .try_for_each(|comparison| comparison.evaluate(value))
+            {
+                Ok(()) => return Ok(()),
+                Err(reason) => reasons.push(reason),
+            }
+        }
+        Err(reasons.join(", "))
+    }
+}
+
+impl Comparison {
+    fn parse(input: &str) -> Result<Self> {
+        let input = input.trim();
+        let (position, notation, op) = (0..input.len())
+            .filter(|position| input.is_char_boundary(*position))
+            .find_map(|position| {
+                Op::ALL.iter().find_map(|(notation, op)| {
+                    input[position..]
+                        .starts_with(notation)
+                        .then_some((position, *notation, *op))
+                })
+            })
+            .ok_or_else(|| {
+                anyhow!(
+                    "`{input}` must compare `result` with one of the operators ==, !=, \
+                    <=, >=, < or >"
+                )
+            })?;
+        let lhs = input[..position].trim();
+        let literal = input[position + notation.len()..].trim();
+        let path = lhs.strip_prefix("result").ok_or_else(|| {
+            anyhow!("The left hand side of `{input}` must start with `result`")
+        })?;
+        anyhow::ensure!(
+            !literal.is_empty(),
+            "`{input}` has no value to compare with"
+        );
+        Ok(Self {
+            path: parse_path(path).ok_or_else(|| anyhow!("Invalid path `{lhs}`"))?,
+            op,
+            literal: literal.to_string(),
+        })
+    }
+
+    fn evaluate(&self, value: &Value) -> Result<(), String> {
+        let mut value = unwrap_ok(value);
+        for segment in &self.path {
+            value = unwrap_ok(
+                select(value, segment)
+                    .ok_or_else(|| format!("{value} has no {segment}"))?,
+            );
+        }
+        let holds = match (as_number(value), self.literal.replace('_', "").parse()) {
+            (Some(lhs), Ok(rhs)) => self.op.holds(lhs, rhs),
+            _ => {
+                let equal = match value {
+                    Value::String(string) => string == self.literal.trim_matches('"'),
+                    value => {
+                        let strip = |s: &str| s.split_whitespace().collect::<String>();
+                        strip(&value.to_string()) == strip(&self.literal)
+                    }
+                };
+                match self.op {
+                    Op::Eq => equal,
+                    Op::Ne => !equal,
+                    op => return Err(format!("`{op}` requires numbers, not {value}")),
+                }
+            }
+        };
+        if holds {
+            Ok(())
+        } else {
+            Err(format!(
+                "{value} {} {} does not hold",
+                self.op, self.literal
+            ))
+        }
+    }
+}
+
+impl std::fmt::Display for Segment {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Field(name) => write!(f, "field `{name}`"),
+            Self::Index(index) => write!(f, "element {index}"),
+        }
+    }
+}
+
+/// Parse the path after `result`, e.g. `.balances[0].free`.
+fn parse_path(mut path: &str) -> Option<Vec<Segment>> {
+    let mut segments = Vec::new();
+    while !path.is_empty() {
+        if let Some(rest) = path.strip_prefix('.') {
+            let end = rest.find(['.', '[']).unwrap_or(rest.len());
+            let name = &rest[..end];
+            if name.is_empty() {
+                return None
+            }
+            segments.push(Segment::Field(name.to_string()));
+            path = &rest[end..];
+        } else if let Some(rest) = path.strip_prefix('[') {
+            let (index, rest) = rest.split_once(']')?;
+            segments.push(Segment::Index(index.trim().parse().ok()?));
+            path = rest;
+        } else {
+            return None
+        }
+    }
+    Some(segments)
+}
+
+/// The part of the value selected by the segment.
+fn select<'a>(value: &'a Value, segment: &Segment) -> Option<&'a Value> {
+    match (value, segment) {
+        (Value::Map(map), Segment::Field(name)) => map.get_by_str(name),
+        (Value::Tuple(tuple), Segment::Field(name)) => {
+            tuple.values().nth(name.parse().ok()?)
+        }
+        (Value::Tuple(tuple), Segment::Index(index)) => tuple.values().nth(*index),
+        (Value::Seq(seq), Segment::Index(index)) => seq.elems().get(*index),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use contract_transcode::{
+        Map,
+        Tuple,
+    };
+
+    #[test]
+    fn conditions_are_parsed_and_evaluated() {
+        let condition =
+            Condition::parse("result.supply >= 1_000 && result.paused == false").unwrap();
+        assert_eq!(condition.any.len(), 1);
+        assert_eq!(
+            condition.any[0][0],
+            Comparison {
+                path: vec![Segment::Field("supply".into())],
+                op: Op::Ge,
+                literal: "1_000".into(),
+            }
+        );
+
+        let state = |supply: u128, paused: bool| {
+            Value::Tuple(Tuple::new(
+                Some("Ok"),
+                vec![Value::Map(Map::new(
+                    Some("State"),
+                    [
+                        (Value::String("supply".into()), Value::UInt(supply)),
+                        (Value::String("paused".into()), Value::Bool(paused)),
+                        (
+                            Value::String("owners".into()),
+                            Value::Tuple(Tuple::new(
+                                None,
+                                vec![Value::String("alice".into())],
+                            )),
+                        ),
+                    ]
+                    .into_iter()
+                    .collect(),
+                ))],
+            ))
+        };
+        assert!(condition.evaluate(&state(1_000, false)).is_ok());
+        assert_eq!(
+            condition.evaluate(&state(999, false)),
+            Err(String::from("999 >= 1_000 does not hold"))
+        );
+        assert!(condition.evaluate(&state(1_000, true)).is_err());
+
+        let either =
+            Condition::parse("result.owners[0] == \"alice\" || result.supply > 5")
+                .unwrap();
+        assert!(either.evaluate(&state(0, true)).is_ok());
+        assert_eq!(
+            Condition::parse("result.owners[1] == alice")
+                .unwrap()
+                .evaluate(&state(0, true)),
+            Err(String::from("(alice,) has no element 1"))
+        );
+        assert!(Condition::parse("result == true")
+            .unwrap()
+            .evaluate(&Value::Bool(true))
+            .is_ok());
+        assert!(Condition::parse("result < yes")
+            .unwrap()
+            .evaluate(&Value::Bool(true))
+            .is_err());
+
+        assert!(Condition::parse("value == true").is_err());
+        assert!(Condition::parse("result true").is_err());
+        assert!(Condition::parse("result.[0] == 1").is_err());
+        assert!(Condition::parse("result ==").is_err());
+    }
+}
@@ -0,0 +1,331 @@
+// Copyright 2018-2023 Parity Technologies (UK) Ltd.
+// This file is part of cargo-contract.
+//
+// cargo-contract is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// cargo-contract is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with cargo-contract.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Generation of ink! end-to-end test boilerplate from the metadata of a contract.
+//!
+//! The generated module has a test instantiating the contract with every constructor,
+//! and a happy-path test calling every message, with placeholder arguments and
+//! assertions to be filled in.
+
+use super::{
+    deprecation::Deprecations,
+    extrinsics::ContractArtifacts,
+    AsyncCommand,
+};
+use anyhow::{
+    Context,
+    Result,
+};
+use async_trait::async_trait;
+use std::{
+    fmt::Write,
+    path::PathBuf,
+};
+
+#[derive(Debug, clap::Args)]
+#[clap(
+    name = "gen-e2e",
+    about = "Generate ink! end-to-end test boilerplate from the contract metadata"
+)]
+pub struct GenE2eCommand {
+    /// Path to a contract build artifact file: a `.contract` bundle or a `.json`
+    /// metadata file.
+    #[clap(value_parser, conflicts_with = "manifest_path")]
+    file: Option<PathBuf>,
+    /// Path to the `Cargo.toml` of the contract.
+    #[clap(long, value_parser)]
+    manifest_path: Option<PathBuf>,
+    /// Write the test module to the file instead of printing it.
+    #[clap(long, short)]
+    output: Option<PathBuf>,
+}
+
+#[async_trait(?Send)]
+impl AsyncCommand for GenE2eCommand {
+    type Output = Result<()>;
+
+    async fn exec(&self) -> Result<()> {
+        let artifacts = ContractArtifacts::from_manifest_or_file(
+            self.manifest_path.as_ref(),
+            self.file.as_ref(),
+        )?;
+        let metadata = artifacts.metadata()?;
+        let deprecations = Deprecations::from_metadata(&metadata);
+        let abi: Abi = serde_json::from_value(serde_json::Value::Object(metadata.abi))
+            .context("Failed to read the ink! metadata of the contract")?;
+        let module = generate(&metadata.contract.name, &abi, &deprecations);
+        match self.output {
+            Some(ref path) => {
+                std::fs::write(path, module)
+                    .with_context(|| format!("Failed to write {}", path.display()))?;
+                eprintln!("Wrote the end-to-end tests to {}", path.display());
+            }
+            None => print!("{module}"),
+        }
+        Ok(())
+    }
+}
+
+/// The parts of the ink! metadata the tests are generated from.
+#[derive(Debug, serde::Deserialize)]
+struct Abi {
+    spec: Spec,
+    storage: Option<serde_json::Value>,
+}
+
+#[derive(Debug, serde::Deserialize)]
+struct Spec {
+    constructors: Vec<Callable>,
+    messages: Vec<Callable>,
+}
+
+/// A constructor or message.
+#[derive(Debug, serde::Deserialize)]
+struct Callable {
+    label: String,
+    #[serde(default)]
+    args: Vec<Arg>,
+    #[serde(default)]
+    mutates: bool,
+}
+
+#[derive(Debug, serde::Deserialize)]
+struct Arg {
+    label: String,
+    #[serde(rename = "type")]
+    ty: ArgType,
+}
+
+#[derive(Debug, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ArgType {
+    #[serde(default)]
+    display_name: Vec<String>,
+}
+
+/// Generate the test module of the contract.
+fn generate(contract_name: &str, abi: &Abi, deprecations: &Deprecations) -> String {
+    let storage = storage_name(abi).unwrap_or_else(|| camel_case(contract_name));
+    let contract_ref = format!("{storage}Ref");
+    let var = contract_name.replace('-', "_");
+    let constructor = abi
+        .spec
+        .constructors
+        .iter()
+        .min_by_key(|constructor| constructor.args.len());
+
+    let mut out = String::new();
+    let _ = writeln!(out, "#[cfg(all(test, feature = \"e2e-tests\"))]");
+    let _ = writeln!(out, "mod e2e_tests {{");
+    let _ = writeln!(out, "    use super::*;");
+    let _ = writeln!(out, "    use ink_e2e::build_message;");
+    let _ = writeln!(out);
+    let _ = writeln!(
+        out,
+        "    type E2EResult<T> = std::result::Result<T, Box<dyn std::error::Error>>;"
+    );
+
+    for constructor in &abi.spec.constructors {
+        let _ = writeln!(out);
+        let _ = writeln!(out, "    #[ink_e2e::test]");
+        let _ = writeln!(
+            out,
+            "    async fn {}_instantiates(mut client: ink_e2e::Client<C, E>) -> E2EResult<()> {{",
+            ident(&constructor.label)
+        );
+        write_instantiate(&mut out, contract_name, &contract_ref, constructor);
+        let _ = writeln!(out, "        // TODO: assert on the initial state");
+        let _ = writeln!(out, "        let _ = contract_account_id;");
+        let _ = writeln!(out, "        Ok(())");
+        let _ = writeln!(out, "    }}");
+    }
+
+    for message in &abi.spec.messages {
+        let method = ident(message.label.rsplit("::").next().unwrap_or(&message.label));
+        let _ = writeln!(out);
+        if let Some(deprecation) = deprecations.get(&message.label) {
+            let _ = writeln!(
+                out,
+                "    // Deprecated{}",
+                deprecation
+                    .note
+                    .as_ref()
+                    .map(|note| format!(": {note}"))
+                    .unwrap_or_default()
+            );
+        }
+        let _ = writeln!(out, "    #[ink_e2e::test]");
+        let _ = writeln!(
+            out,
+            "    async fn {method}_works(mut client: ink_e2e::Client<C, E>) -> E2EResult<()> {{"
+        );
+        let _ = writeln!(out, "        // Given");
+        if let Some(constructor) = constructor {
+            write_instantiate(&mut out, contract_name, &contract_ref, constructor);
+        }
+        let _ = writeln!(out);
+        let _ = writeln!(out, "        // When");
+        let _ = writeln!(
+            out,
+            "        let {method} = build_message::<{contract_ref}>(contract_account_id.clone())"
+        );
+        let _ = writeln!(
+            out,
+            "            .call(|{var}| {var}.{method}({}));",
+            args(&message.args, "                ")
+        );
+        if message.mutates {
+            let _ = writeln!(
+                out,
+                "        let result = client\n            .call(&ink_e2e::alice(), {method}, 0, None)\n            .await\n            .expect(\"{} failed\");",
+                message.label
+            );
+            let _ = writeln!(out);
+            let _ = writeln!(out, "        // Then");
+            let _ = writeln!(out, "        // TODO: assert on the effects of the call");
+            let _ = writeln!(out, "        let _ = result;");
+        } else {
+            let _ = writeln!(
+                out,
+                "        let result = client\n            .call_dry_run(&ink_e2e::alice(), &{method}, 0, None)\n            .await;"
+            );
+            let _ = writeln!(out);
+            let _ = writeln!(out, "        // Then");
+            let _ = writeln!(out, "        // TODO: assert on the returned value");
+            let _ = writeln!(out, "        let _ = result.return_value();");
+        }
+        let _ = writeln!(out, "        Ok(())");
+        let _ = writeln!(out, "    }}");
+    }
+    let _ = writeln!(out, "}}");
+    out
+}
+
+/// Write the statements instantiating the contract with the constructor.
+fn write_instantiate(
+    out: &mut String,
+    contract_name: &str,
+    contract_ref: &str,
+    constructor: &Callable,
+) {
+    let _ = writeln!(
+        out,
+        "        let constructor = {contract_ref}::{}({});",
+        ident(&constructor.label),
+        args(&constructor.args, "            ")
+    );
+    let _ = writeln!(
+        out,
+        "        let contract_account_id = client\n            .instantiate(\"{contract_name}\", &ink_e2e::alice(), constructor, 0, None)\n            .await\n            .expect(\"instantiate failed\")\n            .account_id;"
+    );
+}
+
+/// Returns placeholder arguments, each annotated with its label and type.
+fn args(args: &[Arg], indent: &str) -> String {
+    if args.is_empty() {
+        return String::new()
+    }
+    let args = args
+        .iter()
+        .map(|arg| {
+            let ty = arg.ty.display_name.join("::");
+            format!(
+                "\n{indent}/* {}: {ty} */ {},",
+                arg.label,
+                placeholder(arg.ty.display_name.last().map_or("", String::as_str))
+            )
+        })
+        .collect::<String>();
+    format!("{args}\n{}", &indent[4..])
+}
+
+/// Returns a placeholder value of the type with the given name.
+fn placeholder(ty: &str) -> &'static str {
+    match ty {
+        "bool" => "false",
+        "u8" | "u16" | "u32" | "u64" | "u128" | "i8" | "i16" | "i32" | "i64" | "i128"
+        | "Balance" | "BlockNumber" | "Timestamp" => "0",
+        "AccountId" => "ink_e2e::account_id(ink_e2e::AccountKeyring::Bob)",
+        "String" => "String::new()",
+        "Vec" => "Vec::new()",
+        "Option" => "None",
+        _ => "Default::default()",
+    }
+}
+
+/// Returns the name of the storage struct of the contract from its layout.
+fn storage_name(abi: &Abi) -> Option<String> {
+    let layout = abi.storage.as_ref()?.get("root")?.get("layout")?;
+    Some(layout.get("struct")?.get("name")?.as_str()?.to_string())
+}
+
+/// Returns the label as a Rust identifier.
+fn ident(label: &str) -> String {
+    label
+        .chars()
+        .map(|c| if c.is_alphanumeric() { c } else { '_' })
+        .collect()
+}
+
+fn camel_case(name: &str) -> String {
+    name.split(['_', '-'])
+        .map(|part| {
+            let mut chars = part.chars();
+            chars
+                .next()
+                .map(|first| first.to_uppercase().chain(chars).collect::<String>())
+                .unwrap_or_default()
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tests_are_generated_for_constructors_and_messages() {
+        let abi: Abi = serde_json::from_value(serde_json::json!({
+            "spec": {
+                "constructors": [
+                    {
+                        "label": "new",
+                        "args": [{
+                            "label": "init_value",
+                            "type": { "displayName": ["bool"], "type": 0 }
+                        }]
+                    },
+                    { "label": "default", "args": [] }
+                ],
+                "messages": [
+                    { "label": "flip", "args": [], "mutates": true },
+                    { "label": "get", "args": [], "mutates": false }
+                ]
+            },
+            "storage": { "root": { "layout": { "struct": { "name": "Flipper" } } } }
+        }))
+        .unwrap();
+        let module = generate("flipper", &abi, &Deprecations::default());
+
+        assert!(module.contains("async fn new_instantiates("));
+        assert!(module.contains("/* init_value: bool */ false,"));
+        assert!(module.contains("let constructor = FlipperRef::default();"));
+        assert!(module.contains(".call(|flipper| flipper.flip());"));
+        assert!(module.contains(".call(&ink_e2e::alice(), flip, 0, None)"));
+        assert!(module.contains(".call_dry_run(&ink_e2e::alice(), &get, 0, None)"));
+        assert_eq!(camel_case("my_contract"), "MyContract");
+    }
+}
@@ -0,0 +1,267 @@
+// Copyright 2018-2023 Parity Technologies (UK) Ltd.
+// This file is part of cargo-contract.
+//
+// cargo-contract is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// cargo-contract is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with cargo-contract.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Hermetic builds with a pinned Rust toolchain, for machines where Docker is not
+//! allowed.
+//!
+//! The toolchain is pinned in the `[toolchain]` section of the `cargo-contract.toml`
+//! configuration file, with the SHA-256 hashes of the release archive of every host and
+//! of the `rust-src` component:
+//!
+//! ```toml
+//! [toolchain]
+//! version = "1.69.0"
+//!
+//! [toolchain.sha256]
+//! rust-src = "…"
+//! x86_64-unknown-linux-gnu = "…"
+//! aarch64-apple-darwin = "…"
+//! ```
+//!
+//! `build --hermetic` downloads the archives from `static.rust-lang.org` once, rejects
+//! them unless they match the pinned hashes, and installs them into a directory of the
+//! cache of the version. The build then runs the `cargo` and `rustc` of that directory,
+//! regardless of rustup and the toolchains installed on the machine, so that every
+//! machine builds the same code. `wasm-opt` is linked into cargo-contract, so it is
+//! pinned by the version of cargo-contract.
+
+use super::{
+    config::{
+        Config,
+        CONFIG_FILE_NAME,
+    },
+    node::{
+        cache_root,
+        download,
+        run,
+        unpack,
+        verify_sha256,
+    },
+};
+use anyhow::{
+    Context,
+    Result,
+};
+use std::{
+    collections::BTreeMap,
+    path::{
+        Path,
+        PathBuf,
+    },
+    process::{
+        Command,
+        Stdio,
+    },
+};
+
+/// The server the Rust releases are downloaded from.
+const DIST_URL: &str = "https://static.rust-lang.org/dist";
+
+/// The environment variables selecting another toolchain or wrapping `rustc`.
+const OVERRIDE_VARS: [&str; 3] = [
+    "RUSTUP_TOOLCHAIN",
+    "RUSTC_WRAPPER",
+    "RUSTC_WORKSPACE_WRAPPER",
+];
+
+/// The key of the hash of the `rust-src` component in `[toolchain.sha256]`.
+const RUST_SRC: &str = "rust-src";
+
+/// The pinned Rust toolchain, the `[toolchain]` section of the configuration file.
+#[derive(Debug, Clone, PartialEq, Eq, serde::Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ToolchainPin {
+    /// The stable Rust release, e.g. `1.69.0`.
+    pub version: String,
+    /// The hex encoded SHA-256 hashes of the release archives by host target triple,
+    /// and of the `rust-src` component.
+    #[serde(default)]
+    pub sha256: BTreeMap<String, String>,
+}
+
+/// An installed toolchain.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Toolchain {
+    dir: PathBuf,
+}
+
+/// Install the toolchain pinned in the configuration file if required, and build with it
+/// for the rest of the process.
+pub fn activate() -> Result<Toolchain> {
+    let pin = Config::load()?.toolchain.with_context(|| {
+        format!(
+            "No toolchain is pinned, add a `[toolchain]` section with the `version` and \
+            the `sha256` hashes of its archives to the `{CONFIG_FILE_NAME}` file"
+        )
+    })?;
+    let toolchain = pin.ensure_installed(&cache_root()?.join("toolchains"))?;
+    toolchain.activate();
+    Ok(toolchain)
+}
+
+impl ToolchainPin {
+    /// Returns the installed toolchain, downloading and installing it into the cache if
+    /// it is not installed yet.
+    pub fn ensure_installed(&self, cache_dir: &Path) -> Result<Toolchain> {
+        let host = host_triple()?;
+        let archives = self.archives(&host)?;
+        let dir = cache_dir.join(format!("{}-{host}", self.version));
+        let toolchain = Toolchain { dir: dir.clone() };
+        if toolchain.rustc().exists() && toolchain.cargo().exists() {
+            return Ok(toolchain)
+        }
+        std::fs::create_dir_all(&dir)
+            .with_context(|| format!("Failed to create {}", dir.display()))?;
+        let result = install(&archives, &dir).and_then(|()| toolchain.check(self));
+        if result.is_err() {
+            let _ = std::fs::remove_dir_all(&dir);
+        }
+        result.map(|()| toolchain)
+    }
+
+    /// Returns the names of the archives of the toolchain for the host, with their
+    /// pinned hashes.
+    fn archives(&self, host: &str) -> Result<Vec<(String, &str)>> {
+        let sha256 = |key: &str| {
+            self.sha256.get(key).map(String::as_str).with_context(|| {
+                format!(
+                    "No SHA-256 hash of the `{key}` archive is pinned in the \
+                    `[toolchain.sha256]` section of `{CONFIG_FILE_NAME}`"
+                )
+            })
+        };
+        Ok(vec![
+            (format!("rust-{}-{host}", self.version), sha256(host)?),
+            (format!("{RUST_SRC}-{}", self.version), sha256(RUST_SRC)?),
+        ])
+    }
+}
+
+/// Download, verify and install the archives into the directory.
+fn install(archives: &[(String, &str)], dir: &Path) -> Result<()> {
+    let downloads = dir.join("downloads");
+    std::fs::create_dir_all(&downloads)?;
+    for (name, sha256) in archives {
+        let archive = downloads.join(format!("{name}.tar.gz"));
+        download(&format!("{DIST_URL}/{name}.tar.gz"), &archive)?;
+        verify_sha256(&archive, sha256)?;
+        unpack(&archive, &downloads)
+            .with_context(|| format!("Failed to unpack {name}"))?;
+        run(Command::new("sh")
+            .arg(downloads.join(name).join("install.sh"))
+            .arg(format!("--prefix={}", dir.display()))
+            .arg("--disable-ldconfig")
+            .stdout(Stdio::null()))
+        .with_context(|| format!("Failed to install {name}"))?;
+    }
+    std::fs::remove_dir_all(&downloads)?;
+    Ok(())
+}
+
+impl Toolchain {
+    /// The directory the toolchain is installed in.
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    fn bin(&self, name: &str) -> PathBuf {
+        self.dir
+            .join("bin")
+            .join(format!("{name}{}", std::env::consts::EXE_SUFFIX))
+    }
+
+    pub fn cargo(&self) -> PathBuf {
+        self.bin("cargo")
+    }
+
+    pub fn rustc(&self) -> PathBuf {
+        self.bin("rustc")
+    }
+
+    /// Build with the toolchain for the rest of the process: the build runs `$CARGO`,
+    /// which runs `$RUSTC`. Toolchain overrides and wrappers of the machine are removed.
+    fn activate(&self) {
+        std::env::set_var("CARGO", self.cargo());
+        std::env::set_var("RUSTC", self.rustc());
+        std::env::set_var("RUSTDOC", self.bin("rustdoc"));
+        for var in OVERRIDE_VARS {
+            std::env::remove_var(var);
+        }
+        let path = std::env::var_os("PATH").unwrap_or_default();
+        let paths = std::iter::once(self.dir.join("bin"))
+            .chain(std::env::split_paths(&path))
+            .collect::<Vec<_>>();
+        if let Ok(path) = std::env::join_paths(paths) {
+            std::env::set_var("PATH", path);
+        }
+    }
+
+    /// Fail unless the installed `rustc` reports the pinned version.
+    fn check(&self, pin: &ToolchainPin) -> Result<()> {
+        let output = Command::new(self.rustc()).arg("--version").output()?;
+        let version = String::from_utf8_lossy(&output.stdout);
+        anyhow::ensure!(
+            is_version(&version, &pin.version),
+            "The installed toolchain reports the version `{}`, not `{}`",
+            version.trim(),
+            pin.version
+        );
+        Ok(())
+    }
+}
+
+/// Returns the target triple of the host in the names of the release archives.
+fn host_triple() -> Result<String> {
+    let arch = std::env::consts::ARCH;
+    match std::env::consts::OS {
+        "linux" => Ok(format!("{arch}-unknown-linux-gnu")),
+        "macos" => Ok(format!("{arch}-apple-darwin")),
+        os => Err(anyhow::anyhow!("Hermetic builds are not supported on {os}")),
+    }
+}
+
+/// Returns `true` if the output of `rustc --version` reports the version.
+fn is_version(output: &str, version: &str) -> bool {
+    output.split_whitespace().nth(1) == Some(version)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn archives_are_pinned_by_host() {
+        let pin: ToolchainPin = toml::from_str(
+            r#"
+            version = "1.69.0"
+            [sha256]
+            rust-src = "0x01"
+            x86_64-unknown-linux-gnu = "0x02"
+            "#,
+        )
+        .unwrap();
+        assert_eq!(
+            pin.archives("x86_64-unknown-linux-gnu").unwrap(),
+            vec![
+                (String::from("rust-1.69.0-x86_64-unknown-linux-gnu"), "0x02"),
+                (String::from("rust-src-1.69.0"), "0x01"),
+            ]
+        );
+        assert!(pin.archives("aarch64-apple-darwin").is_err());
+        assert!(is_version("rustc 1.69.0 (84c898d65 2023-04-16)", "1.69.0"));
+        assert!(!is_version("rustc 1.69.1 (84c898d65 2023-04-16)", "1.69.0"));
+    }
+}
@@ -0,0 +1,605 @@
+// Copyright 2018-2023 Parity Technologies (UK) Ltd.
+// This file is part of cargo-contract.
+//
+// cargo-contract is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// cargo-contract is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with cargo-contract.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Translation of the documentation of a contract.
+//!
+//! `i18n export` writes the names and docs of the constructors, messages and events of
+//! the metadata into a resource bundle, a JSON file or a gettext PO file, to be handed to
+//! translators. `i18n render` prints the documentation of the contract with the
+//! translations of a bundle, falling back to the source for missing translations and for
+//! translations whose source changed since the bundle was exported.
+
+use super::{
+    extrinsics::ContractArtifacts,
+    AsyncCommand,
+};
+use anyhow::{
+    Context,
+    Result,
+};
+use async_trait::async_trait;
+use colored::Colorize;
+use serde_json::Value;
+use std::{
+    fmt::Write as _,
+    path::{
+        Path,
+        PathBuf,
+    },
+};
+
+/// The directory bundles are looked up in by `i18n render --language`.
+const BUNDLE_DIR: &str = "i18n";
+
+/// The headings of the rendered documentation, translatable like the metadata.
+const HEADINGS: [(&str, &str); 3] = [
+    ("heading.constructors", "Constructors"),
+    ("heading.messages", "Messages"),
+    ("heading.events", "Events"),
+];
+
+#[derive(Debug, clap::Args)]
+#[clap(name = "i18n", about = "Translate the documentation of a contract")]
+pub struct I18nCommand {
+    #[clap(subcommand)]
+    action: I18nAction,
+}
+
+#[derive(Debug, clap::Subcommand)]
+enum I18nAction {
+    /// Export the names and docs of the metadata into a translatable resource bundle
+    #[clap(name = "export")]
+    Export(ExportCommand),
+    /// Print the documentation of the contract with the translations of a bundle
+    #[clap(name = "render")]
+    Render(RenderCommand),
+}
+
+#[async_trait(?Send)]
+impl AsyncCommand for I18nCommand {
+    type Output = Result<()>;
+
+    async fn exec(&self) -> Result<()> {
+        match self.action {
+            I18nAction::Export(ref export) => export.run(),
+            I18nAction::Render(ref render) => render.run(),
+        }
+    }
+}
+
+/// The format of a resource bundle.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, clap::ValueEnum)]
+pub enum BundleFormat {
+    /// A JSON file with the source and translation of every entry.
+    #[default]
+    Json,
+    /// A gettext PO file.
+    Po,
+}
+
+impl BundleFormat {
+    /// Returns the format of the bundle file, by its extension.
+    fn of_path(path: &Path) -> Self {
+        match path.extension().and_then(|ext| ext.to_str()) {
+            Some("po") | Some("pot") => Self::Po,
+            _ => Self::Json,
+        }
+    }
+}
+
+/// The paths of the contract artifacts.
+#[derive(Debug, clap::Args)]
+struct ArtifactArgs {
+    /// Path to a contract build artifact file: a `.contract` bundle or a `.json`
+    /// metadata file.
+    #[clap(long, value_parser, conflicts_with = "manifest_path")]
+    file: Option<PathBuf>,
+    /// Path to the `Cargo.toml` of the contract.
+    #[clap(long, value_parser)]
+    manifest_path: Option<PathBuf>,
+}
+
+impl ArtifactArgs {
+    fn docs(&self) -> Result<ContractDocs> {
+        let artifacts = ContractArtifacts::from_manifest_or_file(
+            self.manifest_path.as_ref(),
+            self.file.as_ref(),
+        )?;
+        let metadata = artifacts.metadata()?;
+        Ok(ContractDocs::from_abi(
+            &metadata.contract.name,
+            &Value::Object(metadata.abi),
+        ))
+    }
+}
+
+#[derive(Debug, clap::Args)]
+struct ExportCommand {
+    #[clap(flatten)]
+    artifacts: ArtifactArgs,
+    /// The format of the bundle, by default taken from the extension of `--output`.
+    #[clap(long, value_enum)]
+    format: Option<BundleFormat>,
+    /// The language the bundle is translated into, e.g. `de`.
+    #[clap(long)]
+    language: Option<String>,
+    /// Write the bundle into the file instead of printing it. The translations of an
+    /// existing file are kept for the entries whose source did not change.
+    #[clap(long, short)]
+    output: Option<PathBuf>,
+}
+
+impl ExportCommand {
+    fn run(&self) -> Result<()> {
+        let docs = self.artifacts.docs()?;
+        let format = self
+            .format
+            .or_else(|| self.output.as_deref().map(BundleFormat::of_path))
+            .unwrap_or_default();
+        let mut bundle = Bundle::from_docs(&docs, self.language.clone());
+        let existing = self.output.as_deref().filter(|path| path.exists());
+        if let Some(path) = existing {
+            let previous = Bundle::load(path)?;
+            bundle.keep_translations(&previous);
+            if bundle.language.is_none() {
+                bundle.language = previous.language;
+            }
+        }
+        let contents = match format {
+            BundleFormat::Json => serde_json::to_string_pretty(&bundle)? + "\n",
+            BundleFormat::Po => bundle.to_po(),
+        };
+        match self.output {
+            Some(ref path) => {
+                std::fs::write(path, contents)
+                    .with_context(|| format!("Failed to write {}", path.display()))?;
+                eprintln!(
+                    "Wrote {} entries to {}",
+                    bundle.entries.len(),
+                    path.display()
+                );
+            }
+            None => print!("{contents}"),
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, clap::Args)]
+struct RenderCommand {
+    #[clap(flatten)]
+    artifacts: ArtifactArgs,
+    /// The bundle with the translations.
+    #[clap(
+        long,
+        conflicts_with = "language",
+        required_unless_present = "language"
+    )]
+    bundle: Option<PathBuf>,
+    /// The language to render, taken from the `i18n/<language>.po` or
+    /// `i18n/<language>.json` bundle of the current directory.
+    #[clap(long)]
+    language: Option<String>,
+}
+
+impl RenderCommand {
+    fn run(&self) -> Result<()> {
+        let docs = self.artifacts.docs()?;
+        let path = match (&self.bundle, &self.language) {
+            (Some(path), _) => path.clone(),
+            (None, Some(language)) => {
+                ["po", "json"]
+                    .iter()
+                    .map(|ext| Path::new(BUNDLE_DIR).join(format!("{language}.{ext}")))
+                    .find(|path| path.exists())
+                    .ok_or_else(|| {
+                        anyhow::anyhow!(
+                            "No bundle for `{language}` in {BUNDLE_DIR}/, export one \
+                            with `cargo contract i18n export --language {language} \
+                            --output {BUNDLE_DIR}/{language}.po`"
+                        )
+                    })?
+            }
+            (None, None) => unreachable!("clap requires `--bundle` or `--language`"),
+        };
+        let bundle = Bundle::load(&path)?;
+        let (rendered, outdated) = docs.render(&bundle);
+        if outdated > 0 {
+            eprintln!(
+                "{} {outdated} translations of {} are outdated, their source is \
+                rendered instead. Update the bundle with `i18n export --output`.",
+                "warning:".yellow().bold(),
+                path.display()
+            );
+        }
+        print!("{rendered}");
+        Ok(())
+    }
+}
+
+/// The translatable documentation of a contract.
+#[derive(Debug, Default, PartialEq, Eq)]
+struct ContractDocs {
+    name: String,
+    docs: String,
+    constructors: Vec<Item>,
+    messages: Vec<Item>,
+    events: Vec<Item>,
+}
+
+/// A constructor, message or event.
+#[derive(Debug, PartialEq, Eq)]
+struct Item {
+    label: String,
+    docs: String,
+}
+
+impl ContractDocs {
+    fn from_abi(name: &str, abi: &Value) -> Self {
+        let spec = abi.get("spec");
+        let items = |kind: &str| {
+            spec.and_then(|spec| spec.get(kind))
+                .and_then(Value::as_array)
+                .into_iter()
+                .flatten()
+                .filter_map(|item| {
+                    Some(Item {
+                        label: item.get("label")?.as_str()?.to_string(),
+                        docs: join_docs(item.get("docs")),
+                    })
+                })
+                .collect()
+        };
+        Self {
+            name: name.to_string(),
+            docs: join_docs(spec.and_then(|spec| spec.get("docs"))),
+            constructors: items("constructors"),
+            messages: items("messages"),
+            events: items("events"),
+        }
+    }
+
+    /// Returns the sections of the documentation with their key prefix and heading.
+    fn sections(&self) -> [(&'static str, (&'static str, &'static str), &[Item]); 3] {
+        [
+            ("constructor", HEADINGS[0], &self.constructors),
+            ("message", HEADINGS[1], &self.messages),
+            ("event", HEADINGS[2], &self.events),
+        ]
+    }
+
+    /// Returns the translatable entries, as key and source.
+    fn entries(&self) -> Vec<(String, String)> {
+        let mut entries = Vec::new();
+        if !self.docs.is_empty() {
+            entries.push(("contract.docs".to_string(), self.docs.clone()));
+        }
+        for (kind, (heading_key, heading), items) in self.sections() {
+            if items.is_empty() {
+                continue
+            }
+            entries.push((heading_key.to_string(), heading.to_string()));
+            for item in items {
+                entries.push((format!("{kind}.{}.name", item.label), item.label.clone()));
+                if !item.docs.is_empty() {
+                    entries
+                        .push((format!("{kind}.{}.docs", item.label), item.docs.clone()));
+                }
+            }
+        }
+        entries
+    }
+
+    /// Render the documentation as Markdown with the translations of the bundle,
+    /// returning it with the number of outdated translations.
+    fn render(&self, bundle: &Bundle) -> (String, usize) {
+        let mut outdated = 0;
+        let mut translate = |key: &str, source: &str| -> String {
+            match bundle.entry(key) {
+                Some(entry) if entry.translation.is_empty() => source.to_string(),
+                Some(entry) if entry.source != source => {
+                    outdated += 1;
+                    source.to_string()
+                }
+                Some(entry) => entry.translation.clone(),
+                None => source.to_string(),
+            }
+        };
+        let mut out = String::new();
+        let _ = writeln!(out, "# {}", self.name);
+        if !self.docs.is_empty() {
+            let _ = writeln!(out, "\n{}", translate("contract.docs", &self.docs));
+        }
+        for (kind, (heading_key, heading), items) in self.sections() {
+            if items.is_empty() {
+                continue
+            }
+            let _ = writeln!(out, "\n## {}", translate(heading_key, heading));
+            for item in items {
+                let name = translate(&format!("{kind}.{}.name", item.label), &item.label);
+                if name == item.label {
+                    let _ = writeln!(out, "\n### `{}`", item.label);
+                } else {
+                    let _ = writeln!(out, "\n### {name} (`{}`)", item.label);
+                }
+                if !item.docs.is_empty() {
+                    let docs =
+                        translate(&format!("{kind}.{}.docs", item.label), &item.docs);
+                    let _ = writeln!(out, "\n{docs}");
+                }
+            }
+        }
+        (out, outdated)
+    }
+}
+
+/// Join the doc lines of the metadata, which ink! prefixes with a space.
+fn join_docs(docs: Option<&Value>) -> String {
+    docs.and_then(Value::as_array)
+        .into_iter()
+        .flatten()
+        .filter_map(Value::as_str)
+        .map(|line| line.strip_prefix(' ').unwrap_or(line))
+        .collect::<Vec<_>>()
+        .join("\n")
+        .trim()
+        .to_string()
+}
+
+/// A translatable resource bundle of the documentation of a contract.
+#[derive(Debug, Default, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+struct Bundle {
+    contract: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    language: Option<String>,
+    entries: Vec<BundleEntry>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+struct BundleEntry {
+    key: String,
+    source: String,
+    #[serde(default)]
+    translation: String,
+}
+
+impl Bundle {
+    fn from_docs(docs: &ContractDocs, language: Option<String>) -> Self {
+        Self {
+            contract: docs.name.clone(),
+            language,
+            entries: docs
+                .entries()
+                .into_iter()
+                .map(|(key, source)| {
+                    BundleEntry {
+                        key,
+                        source,
+                        translation: String::new(),
+                    }
+                })
+                .collect(),
+        }
+    }
+
+    fn load(path: &Path) -> Result<Self> {
+        let contents = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read {}", path.display()))?;
+        match BundleFormat::of_path(path) {
+            BundleFormat::Json => {
+                serde_json::from_str(&contents)
+                    .with_context(|| format!("Invalid bundle {}", path.display()))
+            }
+            BundleFormat::Po => {
+                Self::from_po(&contents)
+                    .with_context(|| format!("Invalid bundle {}", path.display()))
+            }
+        }
+    }
+
+    fn entry(&self, key: &str) -> Option<&BundleEntry> {
+        self.entries.iter().find(|entry| entry.key == key)
+    }
+
+    /// Take over the translations of the previous bundle of unchanged sources.
+    fn keep_translations(&mut self, previous: &Bundle) {
+        for entry in &mut self.entries {
+            if let Some(old) = previous.entry(&entry.key) {
+                if old.source == entry.source {
+                    entry.translation = old.translation.clone();
+                }
+            }
+        }
+    }
+
+    /// Returns the bundle as a gettext PO file, with the keys as message contexts.
+    fn to_po(&self) -> String {
+        let mut out = String::new();
+        let _ = writeln!(out, "# Documentation of the `{}` contract.", self.contract);
+        let _ = writeln!(out, "msgid \"\"");
+        let _ = writeln!(out, "msgstr \"\"");
+        let _ = writeln!(out, "\"Content-Type: text/plain; charset=UTF-8\\n\"");
+        let _ = writeln!(out, "\"X-Contract: {}\\n\"", po_escape(&self.contract));
+        if let Some(ref language) = self.language {
+            let _ = writeln!(out, "\"Language: {}\\n\"", po_escape(language));
+        }
+        for entry in &self.entries {
+            let _ = writeln!(out);
+            let _ = writeln!(out, "msgctxt {}", po_string(&entry.key));
+            let _ = writeln!(out, "msgid {}", po_string(&entry.source));
+            let _ = writeln!(out, "msgstr {}", po_string(&entry.translation));
+        }
+        out
+    }
+
+    /// Parse a gettext PO file written by [`Bundle::to_po`].
+    fn from_po(contents: &str) -> Result<Self> {
+        let mut bundle = Self::default();
+        let mut fields: [Option<String>; 3] = Default::default();
+        let mut current = None;
+        let finish = |fields: &mut [Option<String>; 3], bundle: &mut Bundle| {
+            match std::mem::take(fields) {
+                [Some(key), Some(source), translation] => {
+                    bundle.entries.push(BundleEntry {
+                        key,
+                        source,
+                        translation: translation.unwrap_or_default(),
+                    });
+                }
+                [None, Some(source), Some(header)] if source.is_empty() => {
+                    for line in header.lines() {
+                        match line.split_once(':') {
+                            Some(("X-Contract", value)) => {
+                                bundle.contract = value.trim().to_string()
+                            }
+                            Some(("Language", value)) => {
+                                bundle.language = Some(value.trim().to_string())
+                            }
+                            _ => {}
+                        }
+                    }
+                }
+                _ => {}
+            }
+        };
+        for (number, line) in contents.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue
+            }
+            let (field, value) = if let Some(value) = line.strip_prefix("msgctxt ") {
+                finish(&mut fields, &mut bundle);
+                (Some(0), value)
+            } else if let Some(value) = line.strip_prefix("msgid ") {
+                if fields[1].is_some() {
+                    finish(&mut fields, &mut bundle);
+                }
+                (Some(1), value)
+            } else if let Some(value) = line.strip_prefix("msgstr ") {
+                (Some(2), value)
+            } else {
+                (None, line)
+            };
+            let field = field
+                .or(current)
+                .with_context(|| format!("Unexpected line {}: {line}", number + 1))?;
+            let value = po_unescape(value)
+                .with_context(|| format!("Invalid string on line {}", number + 1))?;
+            fields[field]
+                .get_or_insert_with(String::new)
+                .push_str(&value);
+            current = Some(field);
+        }
+        finish(&mut fields, &mut bundle);
+        Ok(bundle)
+    }
+}
+
+/// Returns the string as a PO string, split into one string per line.
+fn po_string(value: &str) -> String {
+    if !value.contains('\n') {
+        return format!("\"{}\"", po_escape(value))
+    }
+    let mut out = String::from("\"\"");
+    for line in value.split_inclusive('\n') {
+        let _ = write!(out, "\n\"{}\"", po_escape(line));
+    }
+    out
+}
+
+fn po_escape(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\t', "\\t")
+        .replace('\n', "\\n")
+}
+
+fn po_unescape(quoted: &str) -> Result<String> {
+    let inner = quoted
+        .strip_prefix('"')
+        .and_then(|value| value.strip_suffix('"'))
+        .with_context(|| format!("Expected a quoted string, found {quoted}"))?;
+    let mut out = String::new();
+    let mut chars = inner.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            out.push(c);
+            continue
+        }
+        match chars.next() {
+            Some('n') => out.push('\n'),
+            Some('t') => out.push('\t'),
+            Some(c @ ('\\' | '"')) => out.push(c),
+            other => {
+                anyhow::bail!("Unsupported escape sequence \\{}", other.unwrap_or(' '))
+            }
+        }
+    }
+    Ok(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn bundles_are_exported_and_rendered() {
+        let abi = json!({
+            "spec": {
+                "docs": [" A flipper."],
+                "constructors": [{ "label": "new", "docs": [" Creates a \"flipper\".", " With a value."] }],
+                "messages": [
+                    { "label": "flip", "docs": [" Flips the value."] },
+                    { "label": "get", "docs": [] }
+                ],
+                "events": []
+            }
+        });
+        let docs = ContractDocs::from_abi("flipper", &abi);
+        let mut bundle = Bundle::from_docs(&docs, Some("de".to_string()));
+        assert_eq!(bundle.entries.len(), 8);
+
+        let parsed = Bundle::from_po(&bundle.to_po()).unwrap();
+        assert_eq!(parsed, bundle);
+
+        let translations = [
+            ("heading.messages", "Nachrichten"),
+            ("message.flip.name", "Umdrehen"),
+            ("message.flip.docs", "Dreht den Wert um."),
+            ("constructor.new.docs", "Eine veraltete Übersetzung."),
+        ];
+        for (key, translation) in translations {
+            let entry = bundle.entries.iter_mut().find(|e| e.key == key).unwrap();
+            entry.translation = translation.to_string();
+        }
+        // The constructor docs changed since the bundle was translated.
+        let entry = bundle
+            .entries
+            .iter_mut()
+            .find(|e| e.key == "constructor.new.docs");
+        entry.unwrap().source = "Creates a flipper.".to_string();
+        let (rendered, outdated) = docs.render(&bundle);
+        assert_eq!(outdated, 1);
+        assert_eq!(
+            rendered,
+            "# flipper\n\nA flipper.\n\n## Constructors\n\n### `new`\n\nCreates a \
+            \"flipper\".\nWith a value.\n\n## Nachrichten\n\n### Umdrehen (`flip`)\n\n\
+            Dreht den Wert um.\n\n### `get`\n"
+        );
+    }
+}
@@ -0,0 +1,315 @@
+// Copyright 2018-2023 Parity Technologies (UK) Ltd.
+// This file is part of cargo-contract.
+//
+// cargo-contract is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// cargo-contract is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with cargo-contract.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Resolution of accounts by their account index or on-chain identity.
+//!
+//! Account indices are resolved through the `Indices` pallet and identities through the
+//! display name registered in the `Identity` pallet, if the chain has these pallets.
+
+use super::{
+    scan::{
+        account_of_key,
+        ScanOpts,
+        StorageScan,
+    },
+    Client,
+    DefaultConfig,
+};
+use anyhow::{
+    anyhow,
+    Result,
+};
+use scale::Decode;
+use std::{
+    collections::BTreeMap,
+    fmt::{
+        Display,
+        Formatter,
+    },
+    str::FromStr,
+    sync::{
+        Arc,
+        RwLock,
+    },
+};
+use subxt::{
+    dynamic::Value,
+    ext::scale_value::{
+        At,
+        Primitive,
+        ValueDef,
+    },
+    Config,
+};
+
+type AccountId = <DefaultConfig as Config>::AccountId;
+
+const IDENTITY_PALLET: &str = "Identity";
+const INDICES_PALLET: &str = "Indices";
+
+/// A reference to an account: its address, its account index or the display name of
+/// its on-chain identity.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AccountRef {
+    /// The SS58 address of the account.
+    Id(AccountId),
+    /// The index of the account in the `Indices` pallet, e.g. `42` or `#42`.
+    Index(u32),
+    /// The display name of the account's identity in the `Identity` pallet.
+    Identity(String),
+}
+
+impl FromStr for AccountRef {
+    type Err = anyhow::Error;
+
+    fn from_str(input: &str) -> Result<Self> {
+        if input.is_empty() {
+            anyhow::bail!("Account must not be empty")
+        }
+        if let Ok(account_id) = AccountId::from_str(input) {
+            return Ok(Self::Id(account_id))
+        }
+        match input.strip_prefix('#').unwrap_or(input).parse() {
+            Ok(index) => Ok(Self::Index(index)),
+            Err(_) => Ok(Self::Identity(input.to_string())),
+        }
+    }
+}
+
+impl Display for AccountRef {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Id(account_id) => write!(f, "{account_id}"),
+            Self::Index(index) => write!(f, "#{index}"),
+            Self::Identity(name) => write!(f, "{name}"),
+        }
+    }
+}
+
+impl AccountRef {
+    /// Resolve the address of the referenced account, identities are searched with a
+    /// scan of the `Identity` pallet.
+    pub async fn resolve(&self, client: &Client, scan: &ScanOpts) -> Result<AccountId> {
+        match self {
+            Self::Id(account_id) => Ok(account_id.clone()),
+            Self::Index(index) => lookup_index(client, *index).await,
+            Self::Identity(name) => find_by_identity(client, name, scan).await,
+        }
+    }
+}
+
+/// Returns the display name of the account's identity, if the chain has the `Identity`
+/// pallet and the account has registered an identity.
+pub async fn identity_of(client: &Client, account: &AccountId) -> Result<Option<String>> {
+    if client.metadata().pallet(IDENTITY_PALLET).is_err() {
+        return Ok(None)
+    }
+    let address = subxt::dynamic::storage(
+        IDENTITY_PALLET,
+        "IdentityOf",
+        vec![Value::from_bytes(account.0)],
+    );
+    let registration = client.storage().at_latest().await?.fetch(&address).await?;
+    match registration {
+        Some(registration) => Ok(display_name(&registration.to_value()?)),
+        None => Ok(None),
+    }
+}
+
+/// The display names of the identities of accounts, displayed by the `identity`
+/// notation of account ids.
+///
+/// Values are decoded synchronously, so the names are resolved by the command with its
+/// client before it decodes values. Until then, accounts are displayed by their address.
+#[derive(Debug, Clone, Default)]
+pub struct Identities {
+    names: Arc<RwLock<BTreeMap<[u8; 32], String>>>,
+}
+
+impl Identities {
+    /// Look up the display names of the identities registered on the chain, with a scan
+    /// of the `Identity` pallet. Nothing is looked up unless the configuration uses the
+    /// `identity` notation.
+    pub async fn resolve(&self, client: &Client) -> Result<()> {
+        if !crate::cmd::config::Config::load()?.uses_identity_notation()
+            || client.metadata().pallet(IDENTITY_PALLET).is_err()
+        {
+            return Ok(())
+        }
+        let mut identities =
+            StorageScan::new(client, IDENTITY_PALLET, "IdentityOf", &ScanOpts::default())
+                .await?;
+        let mut names = BTreeMap::new();
+        while let Some(page) = identities.next_page().await? {
+            for entry in page {
+                if let Some(name) = display_name(&entry.value.to_value()?) {
+                    names.insert(account_of_key(&entry.key)?.0, name);
+                }
+            }
+        }
+        if let Ok(mut resolved) = self.names.write() {
+            *resolved = names;
+        }
+        Ok(())
+    }
+
+    /// Returns the display name of the account's identity, `None` if it has none or the
+    /// identities were not resolved.
+    pub fn get(&self, account: &AccountId) -> Option<String> {
+        self.names.read().ok()?.get(&account.0).cloned()
+    }
+}
+
+/// Look up the account with the given index in the `Indices` pallet.
+async fn lookup_index(client: &Client, index: u32) -> Result<AccountId> {
+    if client.metadata().pallet(INDICES_PALLET).is_err() {
+        anyhow::bail!("The chain does not support account indices")
+    }
+    let address = subxt::dynamic::storage(
+        INDICES_PALLET,
+        "Accounts",
+        vec![Value::u128(index.into())],
+    );
+    let entry = client
+        .storage()
+        .at_latest()
+        .await?
+        .fetch(&address)
+        .await?
+        .ok_or_else(|| anyhow!("No account found with index {index}"))?;
+    // The entry is a tuple of the account, the deposit and whether it is frozen.
+    Ok(AccountId::decode(&mut entry.encoded())?)
+}
+
+/// Find the account whose identity has the given display name.
+async fn find_by_identity(
+    client: &Client,
+    name: &str,
+    scan: &ScanOpts,
+) -> Result<AccountId> {
+    if client.metadata().pallet(IDENTITY_PALLET).is_err() {
+        anyhow::bail!("The chain does not support on-chain identities")
+    }
+    let mut identities =
+        StorageScan::new(client, IDENTITY_PALLET, "IdentityOf", scan).await?;
+    let mut found = Vec::new();
+    while let Some(page) = identities.next_page().await? {
+        for entry in page {
+            if display_name(&entry.value.to_value()?).as_deref() == Some(name) {
+                found.push(account_of_key(&entry.key)?);
+            }
+        }
+    }
+    match found.as_slice() {
+        [account] => Ok(account.clone()),
+        [] => Err(anyhow!("No account found with the identity `{name}`")),
+        _ => {
+            Err(anyhow!(
+                "The identity `{name}` is ambiguous, it is used by the accounts {}",
+                found
+                    .iter()
+                    .map(ToString::to_string)
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ))
+        }
+    }
+}
+
+/// Extract the display name from an identity registration; only names stored as raw
+/// data, as opposed to a hash, are returned.
+fn display_name<T>(registration: &Value<T>) -> Option<String> {
+    // Newer runtimes store a tuple of the registration and an optional username.
+    let info = registration
+        .at("info")
+        .or_else(|| registration.at(0)?.at("info"))?;
+    let ValueDef::Variant(ref display) = info.at("display")?.value else {
+        return None
+    };
+    if !display.name.starts_with("Raw") {
+        return None
+    }
+    let mut bytes = Vec::new();
+    for value in display.values.values() {
+        collect_bytes(value, &mut bytes);
+    }
+    String::from_utf8(bytes)
+        .ok()
+        .filter(|name| !name.is_empty())
+}
+
+/// Collect the bytes of a value which is a (nested) composite of `u8`s.
+pub(crate) fn collect_bytes<T>(value: &Value<T>, bytes: &mut Vec<u8>) {
+    match value.value {
+        ValueDef::Primitive(Primitive::U128(byte)) => {
+            bytes.extend(u8::try_from(byte).ok())
+        }
+        ValueDef::Composite(ref composite) => {
+            for value in composite.values() {
+                collect_bytes(value, bytes)
+            }
+        }
+        _ => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn registration(display: Value) -> Value {
+        Value::named_composite([
+            ("judgements", Value::unnamed_composite([])),
+            ("deposit", Value::u128(0)),
+            ("info", Value::named_composite([("display", display)])),
+        ])
+    }
+
+    #[test]
+    fn account_refs_are_parsed() {
+        let alice = "5GrwvaEF5zXb26Fz9rcQpDWS57CtERHpNehXCPcNoHGKutQY";
+        assert_eq!(
+            alice.parse::<AccountRef>().unwrap(),
+            AccountRef::Id(AccountId::from_str(alice).unwrap())
+        );
+        assert_eq!("42".parse::<AccountRef>().unwrap(), AccountRef::Index(42));
+        assert_eq!("#42".parse::<AccountRef>().unwrap(), AccountRef::Index(42));
+        assert_eq!(
+            "Parity Technologies".parse::<AccountRef>().unwrap(),
+            AccountRef::Identity(String::from("Parity Technologies"))
+        );
+        assert!("".parse::<AccountRef>().is_err());
+    }
+
+    #[test]
+    fn display_name_is_extracted() {
+        let raw = Value::unnamed_variant("Raw5", [Value::from_bytes(b"Alice")]);
+        assert_eq!(
+            display_name(&registration(raw.clone())),
+            Some(String::from("Alice"))
+        );
+        let with_username = Value::unnamed_composite([
+            registration(raw),
+            Value::unnamed_variant("None", []),
+        ]);
+        assert_eq!(display_name(&with_username), Some(String::from("Alice")));
+
+        let none = Value::unnamed_variant("None", []);
+        assert_eq!(display_name(&registration(none)), None);
+        let hash = Value::unnamed_variant("Sha256", [Value::from_bytes([0u8; 32])]);
+        assert_eq!(display_name(&registration(hash)), None);
+    }
+}
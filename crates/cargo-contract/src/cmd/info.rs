@@ -15,35 +15,101 @@
 // along with cargo-contract.  If not, see <http://www.gnu.org/licenses/>.
 
 use super::{
-    runtime_api::api::{self,},
+    runtime_api::api::{
+        self,
+    },
+    AsyncCommand,
     Client,
     DefaultConfig,
 };
 use crate::{
     cmd::{
+        chain_env,
+        checkpoint::CheckpointOpts,
+        disable_denomination,
+        extrinsics::{
+            code_hash_history,
+            fetch_legacy_contract_info,
+            storage_rent_version,
+            url_to_string,
+            verify_attestation,
+            CodeHashChange,
+        },
+        identity::{
+            identity_of,
+            AccountRef,
+        },
+        registry::{
+            Attestation,
+            Registry,
+        },
         runtime_api::api::runtime_types::pallet_contracts::storage::ContractInfo,
-        Balance,
+        scan::{
+            account_of_key,
+            ScanOpts,
+            ScanPosition,
+            StorageScan,
+        },
+        schema::OutputSchema,
         CodeHash,
+        DisplayBalance,
         ErrorVariant,
+        NumberFormat,
+        TokenMetadata,
     },
     name_value_println,
 };
 use anyhow::{
     anyhow,
+    Context,
     Result,
 };
-use std::fmt::Debug;
+use async_trait::async_trait;
+use scale::Decode;
+use std::{
+    fmt::Debug,
+    path::{
+        Path,
+        PathBuf,
+    },
+};
 use subxt::{
     Config,
     OnlineClient,
 };
 
+type AccountId = <DefaultConfig as Config>::AccountId;
+
 #[derive(Debug, clap::Args)]
 #[clap(name = "info", about = "Get infos from a contract")]
 pub struct InfoCommand {
-    /// The address of the contract to display info of.
-    #[clap(name = "contract", long, env = "CONTRACT")]
-    contract: <DefaultConfig as Config>::AccountId,
+    /// The contract to display info of: its address, its account index or the display
+    /// name of its on-chain identity.
+    #[clap(
+        name = "contract",
+        long,
+        env = "CONTRACT",
+        required_unless_present = "all"
+    )]
+    contract: Option<AccountRef>,
+    /// List all instantiated contracts of the chain instead, with their code hash and
+    /// number of storage items.
+    #[clap(long, conflicts_with_all = ["contract", "binary"])]
+    all: bool,
+    /// Download the Wasm code of the contract and write it to the given path.
+    #[clap(long, value_name = "PATH", value_parser)]
+    binary: Option<PathBuf>,
+    /// Display the timeline of the code hash changes of the contract instead, e.g. its
+    /// upgrades by `set_code_hash`, with the extrinsics which performed them.
+    #[clap(long, conflicts_with_all = ["all", "binary"])]
+    history: bool,
+    /// The first block scanned by `--history`, by default 100 blocks before the last
+    /// block.
+    #[clap(long, requires = "history")]
+    from_block: Option<u64>,
+    /// The last block scanned by `--history`, by default the latest block.
+    #[clap(long, requires = "history")]
+    to_block: Option<u64>,
     /// Websockets url of a substrate node.
     #[clap(
         name = "url",
@@ -55,69 +121,363 @@ pub struct InfoCommand {
     /// Export the instantiate output in JSON format.
     #[clap(name = "output-json", long)]
     output_json: bool,
+    /// Print the JSON Schema of the `--output-json` output and exit.
+    #[clap(long)]
+    schema: bool,
+    /// The notation used to display balances in the human readable output.
+    #[clap(long, value_enum, default_value_t)]
+    number_format: NumberFormat,
+    /// Display balances denominated by the token of the chain, e.g. `1.5mDOT`. With
+    /// `--denominate=false` they are displayed in planck, the smallest unit.
+    #[clap(
+        long,
+        value_name = "BOOL",
+        default_value_t = true,
+        num_args = 0..=1,
+        default_missing_value = "true",
+        action = clap::ArgAction::Set
+    )]
+    denominate: bool,
+    #[clap(flatten)]
+    scan: ScanOpts,
+    /// Checkpoints of the scan of `--all`.
+    #[clap(flatten)]
+    checkpoint: CheckpointOpts,
 }
 
-impl InfoCommand {
-    pub fn run(&self) -> Result<(), ErrorVariant> {
+#[async_trait(?Send)]
+impl AsyncCommand for InfoCommand {
+    type Output = Result<(), ErrorVariant>;
+
+    async fn exec(&self) -> Result<(), ErrorVariant> {
+        if self.schema {
+            return Ok(OutputSchema::Info.print()?)
+        }
+        if !self.denominate {
+            disable_denomination();
+        }
         tracing::debug!(
             "Getting contract information for AccountId {:?}",
             self.contract
         );
 
-        async_std::task::block_on(async {
-            let url = self.url.clone();
-            let client = OnlineClient::<DefaultConfig>::from_url(url).await?;
-
-            let info_result = self.fetch_contract_info(&client).await?;
-
-            match info_result {
-                Some(info_result) => {
-                    let convert_trie_id = hex::encode(info_result.trie_id.0);
-                    let info_to_json = InfoToJson {
-                        trie_id: convert_trie_id,
-                        code_hash: info_result.code_hash,
-                        storage_items: info_result.storage_items,
-                        storage_item_deposit: info_result.storage_item_deposit,
-                    };
-                    if self.output_json {
-                        println!("{}", info_to_json.to_json()?);
-                    } else {
-                        info_to_json.basic_display_format_contract_info();
-                    }
-                    Ok(())
+        let url = self.url.clone();
+        let client = OnlineClient::<DefaultConfig>::from_url(url).await?;
+        chain_env::ensure_supported_chain(&client)?;
+
+        if self.all {
+            return display_all_contracts(
+                &client,
+                &self.scan,
+                &self.checkpoint,
+                self.output_json,
+            )
+            .await
+        }
+        let contract = self
+            .contract
+            .as_ref()
+            .expect("the contract is required without `--all`; qed")
+            .resolve(&client, &self.scan)
+            .await?;
+        if self.history {
+            let changes =
+                code_hash_history(&client, &contract, self.from_block, self.to_block)
+                    .await?;
+            return display_code_hash_history(&contract, &changes, self.output_json)
+        }
+        if let Some(version) = storage_rent_version(&client).await? {
+            return display_legacy_contract_info(
+                &client,
+                &contract,
+                version,
+                self.output_json,
+            )
+            .await
+        }
+        let info_result = fetch_contract_info(&client, &contract).await?;
+        let token_metadata = TokenMetadata::query(&client).await?;
+
+        match info_result {
+            Some(info_result) => {
+                let convert_trie_id = hex::encode(info_result.trie_id.0);
+                let owner = fetch_code_owner(&client, info_result.code_hash).await?;
+                let owner_identity = match owner {
+                    Some(ref owner) => identity_of(&client, owner).await?,
+                    None => None,
+                };
+                let attestations =
+                    fetch_attestations(&client, &self.url, info_result.code_hash).await?;
+                if let Some(ref path) = self.binary {
+                    write_code(&client, info_result.code_hash, path).await?;
                 }
-                None => {
-                    Err(anyhow!(
-                        "No contract information was found for account id {}",
-                        self.contract
-                    )
-                    .into())
+                let info_to_json = InfoToJson {
+                    contract_identity: identity_of(&client, &contract).await?,
+                    contract,
+                    trie_id: convert_trie_id,
+                    code_hash: info_result.code_hash,
+                    owner,
+                    owner_identity,
+                    storage_items: info_result.storage_items,
+                    attestations,
+                    binary: self.binary.clone(),
+                    storage_item_deposit: DisplayBalance::new(
+                        info_result.storage_item_deposit,
+                        &token_metadata,
+                    )?,
+                };
+                if self.output_json {
+                    println!("{}", info_to_json.to_json()?);
+                } else {
+                    info_to_json.basic_display_format_contract_info(self.number_format);
                 }
+                Ok(())
+            }
+            None => {
+                Err(anyhow!(
+                    "No contract information was found for account id {}",
+                    contract
+                )
+                .into())
             }
-        })
+        }
+    }
+}
+
+/// Display the info of the contract on a chain whose `Contracts` pallet still charges
+/// storage rent, including whether the contract was evicted and left a tombstone.
+async fn display_legacy_contract_info(
+    client: &Client,
+    contract: &AccountId,
+    storage_version: u16,
+    output_json: bool,
+) -> Result<(), ErrorVariant> {
+    let info = fetch_legacy_contract_info(client, contract)
+        .await?
+        .ok_or_else(|| {
+            anyhow!("No contract information was found for account id {contract}")
+        })?;
+    if output_json {
+        let json = serde_json::json!({
+            "contract": contract,
+            "pallet_storage_version": storage_version,
+            "info": info,
+        });
+        println!(
+            "{}",
+            serde_json::to_string_pretty(&json).map_err(anyhow::Error::from)?
+        );
+    } else {
+        name_value_println!("Contract:", contract.to_string());
+        name_value_println!("Pallet version:", storage_version.to_string());
+        info.display();
     }
+    Ok(())
+}
 
-    async fn fetch_contract_info(&self, client: &Client) -> Result<Option<ContractInfo>> {
-        let info_contract_call =
-            api::storage().contracts().contract_info_of(&self.contract);
+/// Display the code hash changes of the contract.
+fn display_code_hash_history(
+    contract: &AccountId,
+    changes: &[CodeHashChange],
+    output_json: bool,
+) -> Result<(), ErrorVariant> {
+    if output_json {
+        let json = serde_json::json!({
+            "contract": contract,
+            "code_hash_changes": changes,
+        });
+        println!(
+            "{}",
+            serde_json::to_string_pretty(&json).map_err(anyhow::Error::from)?
+        );
+    } else if changes.is_empty() {
+        println!("The code hash of {contract} was not changed in the range of blocks");
+    } else {
+        name_value_println!("Contract:", contract.to_string());
+        for change in changes {
+            change.display();
+        }
+    }
+    Ok(())
+}
 
-        let contract_info_of = client
-            .storage()
-            .at_latest()
-            .await?
-            .fetch(&info_contract_call)
-            .await?;
+/// List all contracts of the chain, scanning the `ContractInfoOf` storage map.
+async fn display_all_contracts(
+    client: &Client,
+    scan: &ScanOpts,
+    checkpoint: &CheckpointOpts,
+    output_json: bool,
+) -> Result<(), ErrorVariant> {
+    if storage_rent_version(client).await?.is_some() {
+        return Err(anyhow!(
+            "Listing all contracts is not supported on chains charging storage rent"
+        )
+        .into())
+    }
+    let (mut checkpoint, resumed) = checkpoint.open("contracts of the chain")?;
+    let mut scan = StorageScan::new(client, "Contracts", "ContractInfoOf", scan).await?;
+    let mut state = match resumed {
+        Some(state) => {
+            scan.resume_at(state.position.clone());
+            state
+        }
+        None => {
+            ContractsCheckpoint {
+                position: scan.position(),
+                contracts: Vec::new(),
+            }
+        }
+    };
+    loop {
+        let page = match scan.next_page().await {
+            Ok(Some(page)) => page,
+            Ok(None) => break,
+            Err(err) => return Err(checkpoint.interrupted(&state, err).into()),
+        };
+        for entry in page {
+            let info = ContractInfo::decode(&mut entry.value.encoded())
+                .map_err(anyhow::Error::from)?;
+            state.contracts.push(ContractEntry {
+                contract: account_of_key(&entry.key)?,
+                code_hash: info.code_hash,
+                storage_items: info.storage_items,
+            });
+        }
+        state.position = scan.position();
+        checkpoint.save(&state)?;
+    }
+    checkpoint.finish()?;
+    let contracts = state.contracts;
+    if output_json {
+        println!(
+            "{}",
+            serde_json::to_string_pretty(&contracts).map_err(anyhow::Error::from)?
+        );
+    } else if contracts.is_empty() {
+        println!("No contracts found on the chain");
+    } else {
+        for entry in &contracts {
+            println!(
+                "{} code hash {:?}, {} storage items",
+                entry.contract, entry.code_hash, entry.storage_items
+            );
+        }
+    }
+    Ok(())
+}
+
+/// Fetch the pristine Wasm code with the hash and write it to the path.
+async fn write_code(client: &Client, code_hash: CodeHash, path: &Path) -> Result<()> {
+    let pristine_code = api::storage().contracts().pristine_code(code_hash);
+    let code = client
+        .storage()
+        .at_latest()
+        .await?
+        .fetch(&pristine_code)
+        .await?
+        .ok_or_else(|| {
+            anyhow!("No code with the hash {code_hash:?} found on the chain")
+        })?;
+    std::fs::write(path, code.0)
+        .with_context(|| format!("Failed to write the code to {}", path.display()))
+}
+
+/// Fetch the info of the contract, if there is a contract at the address.
+pub(crate) async fn fetch_contract_info(
+    client: &Client,
+    contract: &AccountId,
+) -> Result<Option<ContractInfo>> {
+    let info_contract_call = api::storage().contracts().contract_info_of(contract);
+
+    let contract_info_of = client
+        .storage()
+        .at_latest()
+        .await?
+        .fetch(&info_contract_call)
+        .await?;
+
+    Ok(contract_info_of)
+}
+
+/// Fetch the account which uploaded the code, if the code is still stored.
+pub(crate) async fn fetch_code_owner(
+    client: &Client,
+    code_hash: CodeHash,
+) -> Result<Option<AccountId>> {
+    let owner_info_call = api::storage().contracts().owner_info_of(code_hash);
+
+    let owner_info = client
+        .storage()
+        .at_latest()
+        .await?
+        .fetch(&owner_info_call)
+        .await?;
 
-        Ok(contract_info_of)
+    Ok(owner_info.map(|owner_info| owner_info.owner))
+}
+
+/// Look up the attestations of the code recorded in the local registry, and check them
+/// against the chain.
+async fn fetch_attestations(
+    client: &Client,
+    url: &url::Url,
+    code_hash: CodeHash,
+) -> Result<Vec<AttestationInfo>> {
+    let registry = Registry::load()?;
+    let Some(code) = registry.code(&code_hash, &url_to_string(url)) else {
+        return Ok(Vec::new())
+    };
+    let mut attestations = Vec::new();
+    for attestation in &code.attestations {
+        attestations.push(AttestationInfo {
+            verified: verify_attestation(client, &code_hash, attestation).await?,
+            attestation: attestation.clone(),
+        });
     }
+    Ok(attestations)
+}
+
+#[derive(serde::Serialize)]
+struct AttestationInfo {
+    #[serde(flatten)]
+    attestation: Attestation,
+    /// Whether the attestation was found on-chain.
+    verified: bool,
+}
+
+/// The progress of the scan of `--all`.
+#[derive(serde::Serialize, serde::Deserialize)]
+struct ContractsCheckpoint {
+    position: ScanPosition,
+    contracts: Vec<ContractEntry>,
+}
+
+/// A contract of the chain, as listed by `--all`.
+#[derive(serde::Serialize, serde::Deserialize)]
+struct ContractEntry {
+    contract: AccountId,
+    code_hash: CodeHash,
+    storage_items: u32,
 }
 
 #[derive(serde::Serialize)]
 struct InfoToJson {
+    contract: AccountId,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    contract_identity: Option<String>,
     trie_id: String,
     code_hash: CodeHash,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    owner: Option<AccountId>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    owner_identity: Option<String>,
     storage_items: u32,
-    storage_item_deposit: Balance,
+    storage_item_deposit: DisplayBalance,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    attestations: Vec<AttestationInfo>,
+    /// The path the Wasm code was written to, with `--binary`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    binary: Option<PathBuf>,
 }
 
 impl InfoToJson {
@@ -127,13 +487,43 @@ impl InfoToJson {
     }
 
     /// Display contract information in a formatted way
-    pub fn basic_display_format_contract_info(&self) {
+    pub fn basic_display_format_contract_info(&self, number_format: NumberFormat) {
+        name_value_println!("Contract:", format!("{}", self.contract));
+        if let Some(ref identity) = self.contract_identity {
+            name_value_println!("Identity:", identity);
+        }
         name_value_println!("TrieId:", format!("{}", self.trie_id));
         name_value_println!("Code hash:", format!("{:?}", self.code_hash));
+        if let Some(ref owner) = self.owner {
+            name_value_println!("Code owner:", format!("{owner}"));
+        }
+        if let Some(ref identity) = self.owner_identity {
+            name_value_println!("Owner identity:", identity);
+        }
         name_value_println!("Storage items:", format!("{:?}", self.storage_items));
         name_value_println!(
             "Storage deposit:",
-            format!("{:?}", self.storage_item_deposit)
+            self.storage_item_deposit.format(number_format)
         );
+        if let Some(ref path) = self.binary {
+            name_value_println!("Wasm code:", format!("written to {}", path.display()));
+        }
+        for info in &self.attestations {
+            let attestation = &info.attestation;
+            name_value_println!(
+                "Attestation:",
+                format!(
+                    "metadata {:?}, source {}, by {}{}",
+                    attestation.metadata_hash,
+                    attestation.source_cid,
+                    attestation.attester,
+                    if info.verified {
+                        ""
+                    } else {
+                        " (not found on-chain)"
+                    }
+                )
+            );
+        }
     }
 }
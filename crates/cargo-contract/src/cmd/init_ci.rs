@@ -0,0 +1,433 @@
+// Copyright 2018-2023 Parity Technologies (UK) Ltd.
+// This file is part of cargo-contract.
+//
+// cargo-contract is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// cargo-contract is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with cargo-contract.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Generation of CI pipelines for a contract, for GitHub Actions or GitLab CI.
+//!
+//! The pipeline lints, tests and builds the contract, verifies that the build is
+//! reproducible, checks the size of the code against a budget and runs a benchmark
+//! comparison. Every deployment of the `[ci]` section of the configuration file becomes
+//! a job on the default branch, guarded by an environment whose approval rules are
+//! configured on the CI provider:
+//!
+//! ```toml
+//! [ci]
+//! max-code-size = 32768
+//! bench = "cargo test --features bench -- --nocapture"
+//!
+//! [[ci.deploy]]
+//! network = "shibuya"
+//! environment = "staging"
+//! ```
+
+use super::{
+    config::Config,
+    AsyncCommand,
+};
+use anyhow::{
+    Context,
+    Result,
+};
+use async_trait::async_trait;
+use std::{
+    fmt::Write,
+    fs,
+    path::{
+        Path,
+        PathBuf,
+    },
+};
+
+/// The CI provider the pipeline is generated for.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, clap::ValueEnum)]
+pub enum Provider {
+    /// A GitHub Actions workflow.
+    #[default]
+    Github,
+    /// A GitLab CI pipeline.
+    Gitlab,
+}
+
+impl Provider {
+    /// The path the pipeline definition is read from by the provider.
+    fn default_path(&self) -> &'static str {
+        match self {
+            Self::Github => ".github/workflows/contract.yml",
+            Self::Gitlab => ".gitlab-ci.yml",
+        }
+    }
+}
+
+/// The settings of the generated pipelines, the `[ci]` section of the configuration
+/// file.
+#[derive(Debug, Clone, Default, PartialEq, Eq, serde::Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct CiConfig {
+    /// The Rust toolchain the contract is built with, `stable` by default.
+    pub toolchain: Option<String>,
+    /// The maximum size of the Wasm code in bytes.
+    #[serde(rename = "max-code-size")]
+    pub max_code_size: Option<u64>,
+    /// The command comparing the benchmarks of the contract to the default branch.
+    pub bench: Option<String>,
+    /// The deployments run on the default branch.
+    #[serde(default)]
+    pub deploy: Vec<CiDeploy>,
+}
+
+/// A deployment job of the pipeline.
+#[derive(Debug, Clone, PartialEq, Eq, serde::Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct CiDeploy {
+    /// The network profile deployed to.
+    pub network: String,
+    /// The deployment manifest, `deploy.toml` by default.
+    pub manifest: Option<String>,
+    /// The environment of the CI provider guarding the job, the network by default.
+    pub environment: Option<String>,
+    /// The secret holding the secret key URI of the deployer, `CONTRACT_SURI` by
+    /// default.
+    pub secret: Option<String>,
+}
+
+#[derive(Debug, clap::Args)]
+#[clap(
+    name = "init-ci",
+    about = "Generate a CI pipeline building, checking and deploying the contract"
+)]
+pub struct InitCiCommand {
+    /// Path to the `Cargo.toml` of the contract.
+    #[clap(long, value_parser, default_value = "Cargo.toml")]
+    manifest_path: PathBuf,
+    /// The CI provider to generate the pipeline for.
+    #[clap(long, value_enum, default_value_t)]
+    provider: Provider,
+    /// Write the pipeline to the file instead of the default path of the provider.
+    #[clap(long, short, value_parser)]
+    output: Option<PathBuf>,
+    /// Print the pipeline instead of writing it.
+    #[clap(long, conflicts_with = "output")]
+    stdout: bool,
+    /// Overwrite an existing pipeline definition.
+    #[clap(long)]
+    force: bool,
+}
+
+#[async_trait(?Send)]
+impl AsyncCommand for InitCiCommand {
+    type Output = Result<()>;
+
+    async fn exec(&self) -> Result<()> {
+        let config = Config::load()?;
+        let ci = config.ci.clone().unwrap_or_default();
+        for deploy in &ci.deploy {
+            config.network_profile(&deploy.network)?;
+        }
+        let pipeline = Pipeline::new(&self.manifest_path, ci)?;
+        let contents = match self.provider {
+            Provider::Github => pipeline.github(),
+            Provider::Gitlab => pipeline.gitlab(),
+        };
+        if self.stdout {
+            print!("{contents}");
+            return Ok(())
+        }
+
+        let path = self
+            .output
+            .clone()
+            .unwrap_or_else(|| PathBuf::from(self.provider.default_path()));
+        anyhow::ensure!(
+            self.force || !path.exists(),
+            "{} already exists, pass `--force` to overwrite it",
+            path.display()
+        );
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(&path, contents)
+            .with_context(|| format!("Failed to write {}", path.display()))?;
+        eprintln!(
+            "Wrote the pipeline of {} to {}",
+            pipeline.name,
+            path.display()
+        );
+        Ok(())
+    }
+}
+
+/// The contract and settings a pipeline is rendered from.
+#[derive(Debug)]
+struct Pipeline {
+    /// The name of the contract artifacts.
+    name: String,
+    /// The path to the manifest of the contract.
+    manifest: String,
+    /// The directory of the contract artifacts.
+    target: String,
+    ci: CiConfig,
+}
+
+impl Pipeline {
+    fn new(manifest_path: &Path, ci: CiConfig) -> Result<Self> {
+        let manifest = fs::read_to_string(manifest_path)
+            .with_context(|| format!("Failed to read {}", manifest_path.display()))?;
+        let manifest: toml::Table = toml::from_str(&manifest)
+            .with_context(|| format!("Invalid manifest {}", manifest_path.display()))?;
+        let name = manifest
+            .get("package")
+            .and_then(|package| package.get("name"))
+            .and_then(|name| name.as_str())
+            .with_context(|| {
+                format!("{} has no package name", manifest_path.display())
+            })?;
+        let dir = manifest_path
+            .parent()
+            .filter(|dir| !dir.as_os_str().is_empty());
+        let target = match dir {
+            Some(dir) => format!("{}/target/ink", dir.display()),
+            None => "target/ink".to_string(),
+        };
+        Ok(Self {
+            name: name.replace('-', "_"),
+            manifest: manifest_path.display().to_string(),
+            target,
+            ci,
+        })
+    }
+
+    fn toolchain(&self) -> &str {
+        self.ci.toolchain.as_deref().unwrap_or("stable")
+    }
+
+    /// The shell commands of the checks, by the name of their step.
+    fn checks(&self) -> Vec<(&'static str, String)> {
+        let manifest = &self.manifest;
+        let bundle = format!("{}/{}.contract", self.target, self.name);
+        let mut checks = vec![
+            (
+                "Lint",
+                format!(
+                    "cargo install cargo-dylint dylint-link --locked && cargo contract \
+                    build --lint --manifest-path {manifest}"
+                ),
+            ),
+            ("Test", format!("cargo test --manifest-path {manifest}")),
+            (
+                "Build",
+                format!("cargo contract build --release --manifest-path {manifest}"),
+            ),
+            (
+                "Verify the build",
+                format!(
+                    "cargo contract verify --manifest-path {manifest} --contract-bundle \
+                    {bundle}"
+                ),
+            ),
+        ];
+        if let Some(max) = self.ci.max_code_size {
+            checks.push((
+                "Code size budget",
+                format!(
+                    "size=$(stat -c %s {}/{}.wasm); echo \"Code size $size of {max} \
+                    bytes\"; test \"$size\" -le {max}",
+                    self.target, self.name
+                ),
+            ));
+        }
+        if let Some(ref bench) = self.ci.bench {
+            checks.push(("Benchmark diff", bench.clone()));
+        }
+        checks
+    }
+
+    /// The shell command of the deployment.
+    fn deploy_command(deploy: &CiDeploy, suri: &str) -> String {
+        format!(
+            "cargo contract deploy --manifest {} --network {} --suri \"{suri}\" --execute \
+            --skip-confirm",
+            deploy.manifest.as_deref().unwrap_or("deploy.toml"),
+            deploy.network
+        )
+    }
+
+    fn github(&self) -> String {
+        let setup = GITHUB_SETUP
+            .replace("{{TOOLCHAIN}}", self.toolchain())
+            .replace("{{VERSION}}", env!("CARGO_PKG_VERSION"));
+        let mut out = GITHUB_HEADER.replace("{{NAME}}", &self.name);
+        out.push_str(&setup);
+        for (name, command) in self.checks() {
+            let _ = write!(out, "      - name: {name}\n        run: {command}\n");
+        }
+        out.push_str(&GITHUB_UPLOAD.replace("{{TARGET}}", &self.target));
+        for deploy in &self.ci.deploy {
+            let environment = deploy.environment.as_deref().unwrap_or(&deploy.network);
+            let secret = deploy.secret.as_deref().unwrap_or("CONTRACT_SURI");
+            let _ = write!(
+                out,
+                "\n  deploy-{network}:\n    needs: check\n    if: github.ref == \
+                'refs/heads/main'\n    runs-on: ubuntu-latest\n    environment: \
+                {environment}\n",
+                network = deploy.network,
+            );
+            out.push_str(&setup);
+            out.push_str(&GITHUB_DOWNLOAD.replace("{{TARGET}}", &self.target));
+            let _ = write!(
+                out,
+                "      - name: Deploy to {network}\n        env:\n          SURI: ${{{{ \
+                secrets.{secret} }}}}\n        run: {command}\n",
+                network = deploy.network,
+                command = Self::deploy_command(deploy, "$SURI"),
+            );
+        }
+        out
+    }
+
+    fn gitlab(&self) -> String {
+        let mut out = GITLAB_HEADER
+            .replace("{{NAME}}", &self.name)
+            .replace("{{TOOLCHAIN}}", self.toolchain())
+            .replace("{{VERSION}}", env!("CARGO_PKG_VERSION"));
+        out.push_str("check:\n  stage: check\n  script:\n");
+        for (name, command) in self.checks() {
+            let _ = write!(out, "    # {name}\n    - {command}\n");
+        }
+        let _ = write!(out, "  artifacts:\n    paths:\n      - {}/\n", self.target);
+        for deploy in &self.ci.deploy {
+            let environment = deploy.environment.as_deref().unwrap_or(&deploy.network);
+            let secret = deploy.secret.as_deref().unwrap_or("CONTRACT_SURI");
+            let _ = write!(
+                out,
+                "\ndeploy-{network}:\n  stage: deploy\n  needs: [check]\n  rules:\n    - \
+                if: $CI_COMMIT_BRANCH == $CI_DEFAULT_BRANCH\n      when: manual\n  \
+                environment:\n    name: {environment}\n  script:\n    - {command}\n",
+                network = deploy.network,
+                command = Self::deploy_command(deploy, &format!("${secret}")),
+            );
+        }
+        out
+    }
+}
+
+const GITHUB_HEADER: &str = r#"# Generated by `cargo contract init-ci` for the {{NAME}} contract.
+name: contract
+
+on:
+  push:
+    branches: [main]
+  pull_request:
+
+jobs:
+  check:
+    runs-on: ubuntu-latest
+"#;
+
+const GITHUB_SETUP: &str = r#"    steps:
+      - uses: actions/checkout@v4
+      - uses: dtolnay/rust-toolchain@master
+        with:
+          toolchain: {{TOOLCHAIN}}
+          targets: wasm32-unknown-unknown
+          components: rust-src
+      - uses: Swatinem/rust-cache@v2
+      - name: Install cargo-contract
+        run: cargo install cargo-contract --version {{VERSION}} --locked
+"#;
+
+const GITHUB_UPLOAD: &str = r#"      - uses: actions/upload-artifact@v4
+        with:
+          name: contract
+          path: {{TARGET}}
+"#;
+
+const GITHUB_DOWNLOAD: &str = r#"      - uses: actions/download-artifact@v4
+        with:
+          name: contract
+          path: {{TARGET}}
+"#;
+
+const GITLAB_HEADER: &str = r#"# Generated by `cargo contract init-ci` for the {{NAME}} contract.
+stages:
+  - check
+  - deploy
+
+default:
+  image: rust:latest
+  before_script:
+    - rustup toolchain install {{TOOLCHAIN}} --target wasm32-unknown-unknown --component rust-src
+    - rustup default {{TOOLCHAIN}}
+    - cargo install cargo-contract --version {{VERSION}} --locked
+
+"#;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pipeline() -> Pipeline {
+        let dir = tempfile::tempdir().unwrap();
+        let manifest = dir.path().join("Cargo.toml");
+        fs::write(&manifest, "[package]\nname = \"my-flipper\"\n").unwrap();
+        let config = Config::parse(
+            r#"
+            [ci]
+            max-code-size = 32768
+
+            [[ci.deploy]]
+            network = "shibuya"
+            environment = "staging"
+            "#,
+        )
+        .unwrap();
+        let mut pipeline = Pipeline::new(&manifest, config.ci.unwrap()).unwrap();
+        pipeline.manifest = "Cargo.toml".into();
+        pipeline.target = "target/ink".into();
+        pipeline
+    }
+
+    #[test]
+    fn github_workflow_checks_and_deploys_the_contract() {
+        let pipeline = pipeline();
+        assert_eq!(pipeline.name, "my_flipper");
+        let workflow = pipeline.github();
+
+        assert!(workflow.contains(
+            "run: cargo contract verify --manifest-path Cargo.toml --contract-bundle \
+            target/ink/my_flipper.contract"
+        ));
+        assert!(workflow.contains("stat -c %s target/ink/my_flipper.wasm"));
+        assert!(!workflow.contains("Benchmark diff"));
+        assert!(workflow.contains("  deploy-shibuya:\n    needs: check\n"));
+        assert!(workflow.contains("    environment: staging\n"));
+        assert!(workflow.contains("SURI: ${{ secrets.CONTRACT_SURI }}"));
+        assert!(workflow.contains(
+            "run: cargo contract deploy --manifest deploy.toml --network shibuya --suri \
+            \"$SURI\" --execute --skip-confirm"
+        ));
+    }
+
+    #[test]
+    fn gitlab_deployments_are_manual_jobs_with_environments() {
+        let pipeline = pipeline();
+        let gitlab = pipeline.gitlab();
+
+        assert!(gitlab.contains("    - cargo test --manifest-path Cargo.toml\n"));
+        assert!(
+            gitlab.contains("      when: manual\n  environment:\n    name: staging\n")
+        );
+        assert!(gitlab.contains("--suri \"$CONTRACT_SURI\""));
+    }
+}
@@ -0,0 +1,205 @@
+// Copyright 2018-2023 Parity Technologies (UK) Ltd.
+// This file is part of cargo-contract.
+//
+// cargo-contract is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// cargo-contract is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with cargo-contract.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Safe access to local state shared by parallel invocations, e.g. CI jobs running in
+//! the same workspace.
+//!
+//! Files are replaced atomically, so that a reader never sees a partially written file,
+//! and read-modify-write cycles hold an advisory lock on a lock file next to the file.
+//! The operating system releases the lock when its owner exits, so that locks are never
+//! left behind by crashed processes. The lock file holds the PID of the owner, so that
+//! contention can be diagnosed.
+
+use anyhow::{
+    Context,
+    Result,
+};
+use fs2::FileExt as _;
+use std::{
+    fs::File,
+    io::{
+        Read as _,
+        Seek as _,
+        Write as _,
+    },
+    path::{
+        Path,
+        PathBuf,
+    },
+    time::{
+        Duration,
+        Instant,
+    },
+};
+
+/// How long to wait for a lock held by another process.
+const LOCK_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// How often a held lock is retried.
+const LOCK_RETRY_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Write the file by replacing it with a completely written temporary file. The
+/// permissions of an existing file are kept.
+pub fn write_atomic(path: &Path, contents: impl AsRef<[u8]>) -> Result<()> {
+    let dir = match path.parent() {
+        Some(dir) if !dir.as_os_str().is_empty() => dir,
+        _ => Path::new("."),
+    };
+    let write = || -> std::io::Result<()> {
+        let mut tmp = tempfile::NamedTempFile::new_in(dir)?;
+        if let Ok(metadata) = std::fs::metadata(path) {
+            tmp.as_file().set_permissions(metadata.permissions())?;
+        }
+        tmp.write_all(contents.as_ref())?;
+        tmp.as_file().sync_all()?;
+        // the temporary file is removed if it can not be persisted
+        tmp.persist(path).map_err(|err| err.error)?;
+        Ok(())
+    };
+    write().with_context(|| format!("Failed to write {}", path.display()))
+}
+
+/// An exclusive lock on a file, released when dropped.
+#[derive(Debug)]
+pub struct FileLock {
+    lock: File,
+}
+
+impl FileLock {
+    /// Acquire the lock on the file, waiting while another process holds it.
+    pub fn acquire(file: &Path) -> Result<Self> {
+        let path = sibling(file, "lock");
+        // the lock file is never removed, a process could lock the removed file while
+        // another one creates a new lock file
+        let mut lock = std::fs::OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .open(&path)
+            .with_context(|| {
+                format!("Failed to open the lock file {}", path.display())
+            })?;
+        let start = Instant::now();
+        let mut waiting = false;
+        while let Err(err) = lock.try_lock_exclusive() {
+            if err.raw_os_error() != fs2::lock_contended_error().raw_os_error() {
+                return Err(err).with_context(|| {
+                    format!("Failed to lock the lock file {}", path.display())
+                })
+            }
+            let mut pid = String::new();
+            let _ = lock.read_to_string(&mut pid);
+            let _ = lock.rewind();
+            let holder = pid.trim().parse::<u32>().map_or_else(
+                |_| String::from("another process"),
+                |pid| format!("PID {pid}"),
+            );
+            if start.elapsed() >= LOCK_TIMEOUT {
+                anyhow::bail!(
+                    "{} is locked, held by {holder} for more than {} seconds",
+                    file.display(),
+                    LOCK_TIMEOUT.as_secs()
+                )
+            }
+            if !waiting {
+                eprintln!(
+                    "Waiting for the lock on {}, held by {holder}",
+                    file.display()
+                );
+                waiting = true;
+            }
+            std::thread::sleep(LOCK_RETRY_INTERVAL);
+        }
+        let write_pid = |lock: &mut File| -> std::io::Result<()> {
+            lock.set_len(0)?;
+            write!(lock, "{}", std::process::id())?;
+            lock.flush()
+        };
+        write_pid(&mut lock).with_context(|| {
+            format!("Failed to write the lock file {}", path.display())
+        })?;
+        Ok(Self { lock })
+    }
+}
+
+impl Drop for FileLock {
+    fn drop(&mut self) {
+        let _ = self.lock.set_len(0);
+        let _ = self.lock.unlock();
+    }
+}
+
+/// Returns the path of a hidden file next to the file, with the extension appended.
+fn sibling(file: &Path, extension: &str) -> PathBuf {
+    let name = file
+        .file_name()
+        .map(|name| name.to_string_lossy())
+        .unwrap_or_default();
+    file.with_file_name(format!(".{name}.{extension}"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn locks_are_exclusive() {
+        let dir = tempfile::tempdir().unwrap();
+        let file = dir.path().join("registry.json");
+        let lock_file = dir.path().join(".registry.json.lock");
+
+        let lock = FileLock::acquire(&file).unwrap();
+        assert_eq!(
+            std::fs::read_to_string(&lock_file).unwrap(),
+            std::process::id().to_string()
+        );
+        // the lock is held, also against other handles of the same process
+        let other = File::open(&lock_file).unwrap();
+        assert!(other.try_lock_exclusive().is_err());
+        drop(lock);
+        assert!(other.try_lock_exclusive().is_ok());
+        other.unlock().unwrap();
+
+        // a lock file left behind is not locked, e.g. after a crash
+        std::fs::write(&lock_file, u32::MAX.to_string()).unwrap();
+        let _lock = FileLock::acquire(&file).unwrap();
+    }
+
+    #[test]
+    fn files_are_written_atomically() {
+        let dir = tempfile::tempdir().unwrap();
+        let file = dir.path().join("registry.json");
+
+        write_atomic(&file, "{}").unwrap();
+        assert_eq!(std::fs::read_to_string(&file).unwrap(), "{}");
+        assert_eq!(std::fs::read_dir(dir.path()).unwrap().count(), 1);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn permissions_are_kept() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = tempfile::tempdir().unwrap();
+        let file = dir.path().join("keys.json");
+        std::fs::write(&file, "{}").unwrap();
+        std::fs::set_permissions(&file, std::fs::Permissions::from_mode(0o640)).unwrap();
+
+        write_atomic(&file, "[]").unwrap();
+        let mode = std::fs::metadata(&file).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o640);
+    }
+}
@@ -0,0 +1,128 @@
+// Copyright 2018-2023 Parity Technologies (UK) Ltd.
+// This file is part of cargo-contract.
+//
+// cargo-contract is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// cargo-contract is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with cargo-contract.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Routing of the tracing logs.
+//!
+//! The output of the commands is kept separate: data such as `--output-json` results is
+//! printed to stdout, progress messages are printed to stderr, and the tracing logs are
+//! printed to stderr or appended to the `--log-file`.
+
+use anyhow::{
+    Context,
+    Result,
+};
+use clap::{
+    ArgAction,
+    Args,
+};
+use std::{
+    fs::OpenOptions,
+    path::PathBuf,
+    sync::Mutex,
+};
+use tracing_subscriber::{
+    filter::LevelFilter,
+    EnvFilter,
+};
+
+/// Arguments of the tracing logs, accepted by every command.
+#[derive(Debug, Default, Args)]
+pub struct LogArgs {
+    /// Increase the level of the tracing logs: `-v` for info, `-vv` for debug and
+    /// `-vvv` for trace. Without it, the level is taken from `RUST_LOG`.
+    #[clap(short = 'v', action = ArgAction::Count, global = true)]
+    log_verbosity: u8,
+    /// Append the tracing logs to the file instead of printing them to stderr.
+    #[clap(long, value_name = "PATH", global = true)]
+    log_file: Option<PathBuf>,
+}
+
+impl LogArgs {
+    /// Install the tracing subscriber.
+    pub fn init(&self) -> Result<()> {
+        let filter = match level(self.log_verbosity) {
+            Some(level) => EnvFilter::default().add_directive(level.into()),
+            None => EnvFilter::from_default_env(),
+        };
+        let subscriber = tracing_subscriber::fmt().with_env_filter(filter);
+        match self.log_file {
+            Some(ref path) => {
+                let file = OpenOptions::new()
+                    .create(true)
+                    .append(true)
+                    .open(path)
+                    .with_context(|| {
+                        format!("Failed to open the log file {}", path.display())
+                    })?;
+                subscriber
+                    .with_writer(Mutex::new(file))
+                    .with_ansi(false)
+                    .init()
+            }
+            None => subscriber.with_writer(std::io::stderr).init(),
+        }
+        Ok(())
+    }
+}
+
+/// The level of the tracing logs for the number of `-v` flags, `None` for the level of
+/// `RUST_LOG`.
+fn level(count: u8) -> Option<LevelFilter> {
+    match count {
+        0 => None,
+        1 => Some(LevelFilter::INFO),
+        2 => Some(LevelFilter::DEBUG),
+        _ => Some(LevelFilter::TRACE),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use clap::Parser;
+
+    #[derive(Debug, Parser)]
+    struct Cli {
+        #[clap(flatten)]
+        log: LogArgs,
+    }
+
+    #[test]
+    fn verbosity_flags_select_the_level() {
+        let levels = ["", "-v", "-vv", "-vvv", "-vvvv"]
+            .into_iter()
+            .map(|flags| {
+                let args = Some("cli")
+                    .into_iter()
+                    .chain(Some(flags).filter(|f| !f.is_empty()));
+                level(Cli::parse_from(args).log.log_verbosity)
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            levels,
+            [
+                None,
+                Some(LevelFilter::INFO),
+                Some(LevelFilter::DEBUG),
+                Some(LevelFilter::TRACE),
+                Some(LevelFilter::TRACE)
+            ]
+        );
+
+        let cli = Cli::parse_from(["cli", "--log-file", "contract.log"]);
+        assert_eq!(cli.log.log_file, Some(PathBuf::from("contract.log")));
+    }
+}
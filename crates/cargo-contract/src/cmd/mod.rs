@@ -14,11 +14,52 @@
 // You should have received a copy of the GNU General Public License
 // along with cargo-contract.  If not, see <http://www.gnu.org/licenses/>.
 
+pub mod agent;
+pub mod archive;
+pub mod audit_bundle;
 pub mod build;
+pub mod build_watch;
+pub mod chain_env;
+pub mod chain_info;
+pub mod chains;
+pub mod changelog;
+pub mod checkpoint;
+pub mod code_size;
+pub mod config;
+pub mod convert;
 pub mod decode;
+pub mod deprecation;
+pub mod determinism;
+pub mod doctor;
 pub mod encode;
+pub mod explain;
+pub mod gen_e2e;
+pub mod hermetic;
+pub mod i18n;
+pub mod identity;
 pub mod info;
+pub mod init_ci;
+pub mod lock;
+pub mod logging;
+pub mod new_client;
+pub mod node;
+pub mod output;
+pub mod pacing;
+pub mod permissions;
+pub mod plugin;
+pub mod provenance;
+pub mod queue;
+pub mod registry;
 pub mod runtime_api;
+pub mod scan;
+pub mod schema;
+pub mod session;
+pub mod storage;
+pub mod template;
+pub mod trends;
+pub mod typegen;
+pub mod verify;
+pub mod watch;
 
 pub(crate) use self::{
     build::{
@@ -30,12 +71,33 @@ pub(crate) use self::{
 };
 mod extrinsics;
 
+#[cfg(feature = "astar")]
+pub(crate) use self::extrinsics::AstarCommand;
+#[cfg(feature = "activity-cache")]
+pub(crate) use self::extrinsics::DashboardCommand;
 pub(crate) use self::extrinsics::{
+    disable_denomination,
+    AccountCommand,
+    AttestCommand,
     CallCommand,
+    ClearStorageCommand,
+    DeployCommand,
+    DisplayBalance,
     ErrorVariant,
+    EstimateCommand,
+    EventsCommand,
     InstantiateCommand,
+    LinkDependencyCommand,
+    MulticallCommand,
+    NumberFormat,
     RemoveCommand,
+    RentCommand,
+    SubmitCommand,
+    TokenMetadata,
+    TransferCodeCommand,
+    UpgradeCommand,
     UploadCommand,
+    WaitUntilCommand,
 };
 
 use subxt::{
@@ -47,3 +109,17 @@ pub use subxt::PolkadotConfig as DefaultConfig;
 type Client = OnlineClient<DefaultConfig>;
 type Balance = u128;
 type CodeHash = <DefaultConfig as Config>::Hash;
+
+/// A command executed on the single async runtime of `cargo-contract`.
+///
+/// Commands await the node instead of blocking on a runtime of their own, so that they
+/// can be composed by other commands, e.g. a deployment pipeline, an interactive session
+/// or a server, without nesting runtimes.
+#[async_trait::async_trait(?Send)]
+pub trait AsyncCommand {
+    /// The result of the command.
+    type Output;
+
+    /// Execute the command.
+    async fn exec(&self) -> Self::Output;
+}
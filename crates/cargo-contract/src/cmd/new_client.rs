@@ -0,0 +1,509 @@
+// Copyright 2018-2023 Parity Technologies (UK) Ltd.
+// This file is part of cargo-contract.
+//
+// cargo-contract is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// cargo-contract is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with cargo-contract.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Scaffolding of a client project for a contract deployed on-chain.
+//!
+//! The code hash of the contract is fetched from the chain and checked against the
+//! metadata, which is read from the given file or from the local registry of uploaded
+//! code. The generated project is pre-configured with the address of the contract and
+//! the url of the chain.
+
+use super::{
+    deprecation::Deprecations,
+    extrinsics::{
+        url_to_string,
+        ContractArtifacts,
+    },
+    info::fetch_contract_info,
+    registry::Registry,
+    AsyncCommand,
+    DefaultConfig,
+};
+use anyhow::{
+    anyhow,
+    Context,
+    Result,
+};
+use async_trait::async_trait;
+use std::{
+    fs,
+    path::{
+        Path,
+        PathBuf,
+    },
+};
+use subxt::{
+    Config,
+    OnlineClient,
+};
+
+type AccountId = <DefaultConfig as Config>::AccountId;
+
+/// The language of the generated client.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, clap::ValueEnum)]
+pub enum Lang {
+    /// A Rust binary using `subxt`.
+    #[default]
+    Rust,
+    /// A TypeScript project using `@polkadot/api-contract`.
+    Ts,
+}
+
+#[derive(Debug, clap::Args)]
+#[clap(
+    name = "new-client",
+    about = "Scaffold a client project for a contract deployed on-chain"
+)]
+pub struct NewClientCommand {
+    /// The address of the deployed contract.
+    #[clap(long, env = "CONTRACT")]
+    contract: AccountId,
+    /// Websockets url of a substrate node.
+    #[clap(long, default_value = "ws://localhost:9944")]
+    url: url::Url,
+    /// Path to the `.contract` bundle or `.json` metadata of the contract. By default
+    /// the metadata recorded for its code in the local registry is used.
+    #[clap(long, value_parser)]
+    metadata: Option<PathBuf>,
+    /// The language of the client.
+    #[clap(long, value_enum, default_value_t)]
+    lang: Lang,
+    /// The directory to create the project in, by default `<contract name>-client`.
+    #[clap(long, short, value_parser)]
+    output: Option<PathBuf>,
+}
+
+/// The deployment the client is generated for.
+#[derive(Debug, serde::Serialize)]
+struct Deployment {
+    contract: String,
+    url: String,
+    code_hash: String,
+}
+
+#[async_trait(?Send)]
+impl AsyncCommand for NewClientCommand {
+    type Output = Result<()>;
+
+    async fn exec(&self) -> Result<()> {
+        let url = url_to_string(&self.url);
+        let client = OnlineClient::<DefaultConfig>::from_url(&url).await?;
+        let code_hash = fetch_contract_info(&client, &self.contract)
+            .await?
+            .map(|info| info.code_hash)
+            .ok_or_else(|| {
+                anyhow!("No contract information was found for {}", self.contract)
+            })?;
+
+        let metadata_path = match self.metadata {
+            Some(ref path) => path.clone(),
+            None => {
+                Registry::load()?
+                    .code(&code_hash, &url)
+                    .and_then(|code| code.metadata.clone())
+                    .ok_or_else(|| {
+                        anyhow!(
+                            "The metadata of the code {code_hash:?} is not in the \
+                            registry, pass it with `--metadata`"
+                        )
+                    })?
+            }
+        };
+        let artifacts =
+            ContractArtifacts::from_manifest_or_file(None, Some(&metadata_path))?;
+        let metadata = artifacts.metadata()?;
+        if metadata.source.hash.0 != code_hash.0 {
+            return Err(anyhow!(
+                "The metadata is of the code 0x{}, but the contract runs the code {:?}",
+                hex::encode(metadata.source.hash.0),
+                code_hash
+            ))
+        }
+
+        let name = metadata.contract.name.clone();
+        let dir = self
+            .output
+            .clone()
+            .unwrap_or_else(|| PathBuf::from(format!("{name}-client")));
+        if dir.exists() {
+            return Err(anyhow!("{} already exists", dir.display()))
+        }
+        let deployment = Deployment {
+            contract: self.contract.to_string(),
+            url,
+            code_hash: format!("{code_hash:?}"),
+        };
+        let message =
+            read_message(&metadata.abi, &Deprecations::from_metadata(&metadata));
+        let files = match self.lang {
+            Lang::Rust => rust_files(&name, &deployment, message.as_deref()),
+            Lang::Ts => ts_files(&name, &deployment, message.as_deref()),
+        };
+
+        let metadata_json = serde_json::to_string_pretty(&metadata)?;
+        let metadata_file = match self.lang {
+            Lang::Rust => "metadata.json",
+            Lang::Ts => "src/metadata.json",
+        };
+        write(&dir, metadata_file, &metadata_json)?;
+        write(
+            &dir,
+            "deployment.json",
+            &serde_json::to_string_pretty(&deployment)?,
+        )?;
+        for (path, contents) in files {
+            write(&dir, path, &contents)?;
+        }
+        eprintln!(
+            "Created the client of {name} at {} in {}",
+            deployment.contract,
+            dir.display()
+        );
+        Ok(())
+    }
+}
+
+/// Write the file at the path relative to the directory, creating its parents.
+fn write(dir: &Path, path: &str, contents: &str) -> Result<()> {
+    let path = dir.join(path);
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    fs::write(&path, contents)
+        .with_context(|| format!("Failed to write {}", path.display()))
+}
+
+/// Returns the label of the first message which reads state without arguments and is
+/// not deprecated, which the generated client calls as an example.
+fn read_message(
+    abi: &serde_json::Map<String, serde_json::Value>,
+    deprecations: &Deprecations,
+) -> Option<String> {
+    let messages = abi.get("spec")?.get("messages")?.as_array()?;
+    messages
+        .iter()
+        .find(|message| {
+            let label = message.get("label").and_then(|label| label.as_str());
+            label.is_some_and(|label| deprecations.get(label).is_none())
+                && message.get("mutates").and_then(|m| m.as_bool()) == Some(false)
+                && message
+                    .get("args")
+                    .and_then(|args| args.as_array())
+                    .map_or(true, Vec::is_empty)
+        })
+        .and_then(|message| message.get("label")?.as_str())
+        .map(ToString::to_string)
+}
+
+/// Replace the `{{PLACEHOLDERS}}` of the template.
+fn render(template: &str, name: &str, deployment: &Deployment, message: &str) -> String {
+    template
+        .replace("{{NAME}}", name)
+        .replace("{{URL}}", &deployment.url)
+        .replace("{{CONTRACT}}", &deployment.contract)
+        .replace("{{CODE_HASH}}", &deployment.code_hash)
+        .replace("{{MESSAGE}}", message)
+        .replace("{{TS_MESSAGE}}", &ts_method(message))
+}
+
+fn rust_files(
+    name: &str,
+    deployment: &Deployment,
+    message: Option<&str>,
+) -> Vec<(&'static str, String)> {
+    let main = match message {
+        Some(message) => {
+            render(
+                &(RUST_HEADER.to_owned() + RUST_MAIN),
+                name,
+                deployment,
+                message,
+            )
+        }
+        None => {
+            render(
+                &(RUST_HEADER.to_owned() + RUST_MAIN_WITHOUT_CALL),
+                name,
+                deployment,
+                "",
+            )
+        }
+    };
+    vec![
+        ("Cargo.toml", render(RUST_MANIFEST, name, deployment, "")),
+        ("src/main.rs", main),
+    ]
+}
+
+fn ts_files(
+    name: &str,
+    deployment: &Deployment,
+    message: Option<&str>,
+) -> Vec<(&'static str, String)> {
+    let index = match message {
+        Some(message) => {
+            render(
+                &(TS_HEADER.to_owned() + TS_INDEX),
+                name,
+                deployment,
+                message,
+            )
+        }
+        None => {
+            render(
+                &(TS_HEADER.to_owned() + TS_INDEX_WITHOUT_CALL),
+                name,
+                deployment,
+                "",
+            )
+        }
+    };
+    vec![
+        ("package.json", render(TS_PACKAGE, name, deployment, "")),
+        ("tsconfig.json", TS_CONFIG.to_string()),
+        ("src/index.ts", index),
+    ]
+}
+
+/// Returns the name `@polkadot/api-contract` gives the method of the message.
+pub(super) fn ts_method(label: &str) -> String {
+    let label = label.rsplit("::").next().unwrap_or(label);
+    let mut method = String::new();
+    let mut upper = false;
+    for c in label.chars() {
+        if c == '_' {
+            upper = true;
+        } else if upper {
+            method.extend(c.to_uppercase());
+            upper = false;
+        } else {
+            method.push(c);
+        }
+    }
+    method
+}
+
+const RUST_MANIFEST: &str = r#"[package]
+name = "{{NAME}}-client"
+version = "0.1.0"
+edition = "2021"
+publish = false
+
+[dependencies]
+anyhow = "1.0"
+contract-transcode = "2.2"
+pallet-contracts-primitives = "23.0"
+scale = { package = "parity-scale-codec", version = "3.0", features = ["derive"] }
+sp-weights = "19.0"
+subxt = "0.28"
+tokio = { version = "1", features = ["macros", "rt-multi-thread"] }
+"#;
+
+const RUST_HEADER: &str = r#"use anyhow::Result;
+use contract_transcode::ContractMessageTranscoder;
+use pallet_contracts_primitives::ContractExecResult;
+use scale::{
+    Decode,
+    Encode,
+};
+use sp_weights::Weight;
+use std::str::FromStr;
+use subxt::{
+    utils::AccountId32,
+    OnlineClient,
+    PolkadotConfig,
+};
+
+/// The url of the node of the chain the contract is deployed on.
+const NODE_URL: &str = "{{URL}}";
+/// The address of the contract.
+const CONTRACT: &str = "{{CONTRACT}}";
+/// The hash of the code the contract runs, as generated from.
+const CODE_HASH: &str = "{{CODE_HASH}}";
+"#;
+
+const RUST_MAIN: &str = r#"
+/// The parameters of the `ContractsApi_call` runtime API.
+#[derive(Encode)]
+struct CallRequest {
+    origin: AccountId32,
+    dest: AccountId32,
+    value: u128,
+    gas_limit: Option<Weight>,
+    storage_deposit_limit: Option<u128>,
+    input_data: Vec<u8>,
+}
+
+#[tokio::main]
+async fn main() -> Result<()> {
+    let client = OnlineClient::<PolkadotConfig>::from_url(NODE_URL).await?;
+    let transcoder = ContractMessageTranscoder::load("metadata.json")?;
+    let contract = AccountId32::from_str(CONTRACT)?;
+    println!("{{NAME}} at {CONTRACT} runs the code {CODE_HASH}");
+
+    // Dry-run `{{MESSAGE}}`, it is not submitted on-chain.
+    let call = CallRequest {
+        origin: contract.clone(),
+        dest: contract,
+        value: 0,
+        gas_limit: None,
+        storage_deposit_limit: None,
+        input_data: transcoder.encode("{{MESSAGE}}", Vec::<String>::new())?,
+    };
+    let bytes = client
+        .rpc()
+        .state_call("ContractsApi_call", Some(&call.encode()), None)
+        .await?;
+    let result = ContractExecResult::<u128>::decode(&mut bytes.as_ref())?;
+    let data = result
+        .result
+        .map_err(|err| anyhow::anyhow!("The call failed: {err:?}"))?
+        .data;
+    let value = transcoder.decode_return("{{MESSAGE}}", &mut data.as_ref())?;
+    println!("{{MESSAGE}}: {value}");
+    Ok(())
+}
+"#;
+
+const RUST_MAIN_WITHOUT_CALL: &str = r#"
+#[tokio::main]
+async fn main() -> Result<()> {
+    let _client = OnlineClient::<PolkadotConfig>::from_url(NODE_URL).await?;
+    let _transcoder = ContractMessageTranscoder::load("metadata.json")?;
+    let _contract = AccountId32::from_str(CONTRACT)?;
+    println!("{{NAME}} at {CONTRACT} runs the code {CODE_HASH}");
+    // TODO: call the messages of the contract
+    Ok(())
+}
+"#;
+
+const TS_PACKAGE: &str = r#"{
+  "name": "{{NAME}}-client",
+  "version": "0.1.0",
+  "private": true,
+  "scripts": {
+    "start": "ts-node src/index.ts"
+  },
+  "dependencies": {
+    "@polkadot/api": "^10.9.1",
+    "@polkadot/api-contract": "^10.9.1"
+  },
+  "devDependencies": {
+    "ts-node": "^10.9.1",
+    "typescript": "^5.1.6"
+  }
+}
+"#;
+
+const TS_CONFIG: &str = r#"{
+  "compilerOptions": {
+    "target": "ES2020",
+    "module": "commonjs",
+    "strict": true,
+    "esModuleInterop": true,
+    "resolveJsonModule": true
+  }
+}
+"#;
+
+const TS_HEADER: &str = r#"import { ApiPromise, WsProvider } from "@polkadot/api";
+import { ContractPromise } from "@polkadot/api-contract";
+import metadata from "./metadata.json";
+
+/** The url of the node of the chain the contract is deployed on. */
+const NODE_URL = "{{URL}}";
+/** The address of the contract. */
+const CONTRACT = "{{CONTRACT}}";
+/** The hash of the code the contract runs, as generated from. */
+const CODE_HASH = "{{CODE_HASH}}";
+"#;
+
+const TS_INDEX: &str = r#"
+async function main() {
+  const api = await ApiPromise.create({ provider: new WsProvider(NODE_URL) });
+  const contract = new ContractPromise(api, metadata, CONTRACT);
+  console.log(`{{NAME}} at ${CONTRACT} runs the code ${CODE_HASH}`);
+
+  // Dry-run `{{MESSAGE}}`, it is not submitted on-chain.
+  const gasLimit = api.registry.createType("WeightV2", {
+    refTime: 100_000_000_000,
+    proofSize: 1_000_000,
+  }) as any;
+  const { result, output } = await contract.query.{{TS_MESSAGE}}(CONTRACT, { gasLimit });
+  if (result.isErr) {
+    throw new Error(`The call failed: ${result.asErr.toString()}`);
+  }
+  console.log(`{{MESSAGE}}: ${output?.toHuman()}`);
+  await api.disconnect();
+}
+
+main().catch((err) => {
+  console.error(err);
+  process.exit(1);
+});
+"#;
+
+const TS_INDEX_WITHOUT_CALL: &str = r#"
+async function main() {
+  const api = await ApiPromise.create({ provider: new WsProvider(NODE_URL) });
+  const contract = new ContractPromise(api, metadata, CONTRACT);
+  console.log(`{{NAME}} at ${contract.address} runs the code ${CODE_HASH}`);
+  // TODO: call the messages of the contract
+  await api.disconnect();
+}
+
+main().catch((err) => {
+  console.error(err);
+  process.exit(1);
+});
+"#;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn clients_are_configured_for_the_deployment() {
+        let abi = serde_json::json!({
+            "spec": {
+                "messages": [
+                    { "label": "flip", "args": [], "mutates": true },
+                    { "label": "Erc20::total_supply", "args": [], "mutates": false }
+                ]
+            }
+        });
+        let message = read_message(abi.as_object().unwrap(), &Deprecations::default());
+        assert_eq!(message.as_deref(), Some("Erc20::total_supply"));
+
+        let deployment = Deployment {
+            contract: String::from("5GrwvaEF5zXb26Fz9rcQpDWS57CtERHpNehXCPcNoHGKutQY"),
+            url: String::from("ws://localhost:9944/"),
+            code_hash: String::from("0x01"),
+        };
+        let files = rust_files("erc20", &deployment, message.as_deref());
+        assert!(files[0].1.contains("name = \"erc20-client\""));
+        assert!(files[1]
+            .1
+            .contains("const CONTRACT: &str = \"5GrwvaEF5zXb26Fz9rcQpDWS57CtERHpNehXCPcNoHGKutQY\";"));
+        assert!(files[1]
+            .1
+            .contains("transcoder.encode(\"Erc20::total_supply\""));
+
+        let files = ts_files("erc20", &deployment, message.as_deref());
+        assert!(files[2].1.contains("contract.query.totalSupply(CONTRACT"));
+        assert!(!files[2].1.contains("{{"));
+    }
+}
@@ -0,0 +1,645 @@
+// Copyright 2018-2023 Parity Technologies (UK) Ltd.
+// This file is part of cargo-contract.
+//
+// cargo-contract is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// cargo-contract is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with cargo-contract.  If not, see <http://www.gnu.org/licenses/>.
+
+//! A `substrate-contracts-node` release pinned by the project.
+//!
+//! The version is pinned in the `[node]` section of the `contract.toml` file of the
+//! project, with the SHA-256 hash of the release archive for the platform:
+//!
+//! ```toml
+//! [node]
+//! version = "v0.24.0"
+//! sha256 = "…"
+//! ```
+//!
+//! The release is downloaded from GitHub once, verified and cached per version, so that
+//! every machine runs the end-to-end tests of the project against the same node. The
+//! cache is locked while a release is downloaded, so that parallel runs download it
+//! only once.
+//!
+//! With `--group` the end-to-end tests are split into groups running in parallel, each
+//! against its own node with a fresh temporary state on random ports, so that tests of
+//! different groups can not interfere.
+
+use super::{
+    lock::FileLock,
+    AsyncCommand,
+};
+use anyhow::{
+    anyhow,
+    Context,
+    Result,
+};
+use async_trait::async_trait;
+use flate2::read::GzDecoder;
+use sp_core::hashing::sha2_256;
+use std::{
+    fs::File,
+    net::{
+        Ipv4Addr,
+        TcpListener,
+        TcpStream,
+    },
+    path::{
+        Path,
+        PathBuf,
+    },
+    process::{
+        Child,
+        Command,
+        ExitStatus,
+        Stdio,
+    },
+    time::{
+        Duration,
+        Instant,
+    },
+};
+
+/// The name of the node executable.
+const NODE_BINARY: &str = "substrate-contracts-node";
+
+/// The GitHub repository the node is released from.
+const RELEASES_URL: &str =
+    "https://github.com/paritytech/substrate-contracts-node/releases/download";
+
+/// The project file pinning the node, in the directory of the project or one of its
+/// ancestors.
+pub const PROJECT_FILE_NAME: &str = "contract.toml";
+
+/// The file recording the hash of the release archive a cached node was unpacked from.
+const SHA256_FILE: &str = "release.sha256";
+
+/// The environment variable overriding the directory nodes are cached in.
+pub const CACHE_DIR_ENV: &str = "CARGO_CONTRACT_CACHE_DIR";
+
+/// The environment variable ink! end-to-end tests take the path of the node from.
+const CONTRACTS_NODE_ENV: &str = "CONTRACTS_NODE";
+
+/// The environment variable ink! end-to-end tests take the url of a running node from.
+const CONTRACTS_NODE_URL_ENV: &str = "CONTRACTS_NODE_URL";
+
+/// How long to wait for an isolated node to accept connections.
+const STARTUP_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// How often an isolated node is started on other ports if it exits during startup,
+/// e.g. because another process took one of its ports in the meantime.
+const STARTUP_ATTEMPTS: usize = 3;
+
+/// The `contract.toml` file of the project.
+#[derive(Debug, Default, serde::Deserialize)]
+pub struct ProjectFile {
+    /// The pinned node release.
+    pub node: Option<NodePin>,
+}
+
+impl ProjectFile {
+    /// Load the project file of the current directory, empty if there is none.
+    pub fn load() -> Result<Self> {
+        let cwd = std::env::current_dir()?;
+        let Some(path) = cwd
+            .ancestors()
+            .map(|dir| dir.join(PROJECT_FILE_NAME))
+            .find(|path| path.exists())
+        else {
+            return Ok(Self::default())
+        };
+        let contents = std::fs::read_to_string(&path)
+            .with_context(|| format!("Failed to read {}", path.display()))?;
+        Self::parse(&contents).with_context(|| format!("Invalid {}", path.display()))
+    }
+
+    /// Parse the contents of a project file.
+    pub fn parse(contents: &str) -> Result<Self> {
+        Ok(toml::from_str(contents)?)
+    }
+}
+
+/// The pinned node release, the `[node]` section of the project file.
+#[derive(Debug, Clone, PartialEq, Eq, serde::Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct NodePin {
+    /// The release tag, e.g. `v0.24.0`.
+    #[serde(deserialize_with = "deserialize_version")]
+    pub version: String,
+    /// The hex encoded SHA-256 hash of the release archive for the platform. The
+    /// download is rejected if it does not match.
+    pub sha256: String,
+}
+
+#[derive(Debug, clap::Args)]
+#[clap(
+    name = "node",
+    about = "Run the substrate-contracts-node release pinned by the project"
+)]
+pub struct NodeCommand {
+    /// The release to use instead of the one pinned in the project file.
+    #[clap(long, requires = "sha256", value_parser = parse_version)]
+    version: Option<String>,
+    /// The hex encoded SHA-256 hash of the release archive of `--version` for the
+    /// platform.
+    #[clap(long, requires = "version")]
+    sha256: Option<String>,
+    /// Only download the node if required and print the path of its executable.
+    #[clap(long, conflicts_with = "exec")]
+    path: bool,
+    /// Run the command with the path of the node in the `CONTRACTS_NODE` environment
+    /// variable instead of starting the node, e.g. `--exec cargo test --features
+    /// e2e-tests`.
+    #[clap(long, num_args = 1.., allow_hyphen_values = true)]
+    exec: Vec<String>,
+    /// Run the `--exec` command once per group in parallel, each with the group appended
+    /// as its last argument, e.g. a test name filter, and against its own isolated node
+    /// in `CONTRACTS_NODE_URL`. Can be repeated.
+    #[clap(long = "group", value_name = "FILTER", requires = "exec")]
+    groups: Vec<String>,
+    /// The arguments passed to the node, `--dev` by default.
+    #[clap(last = true)]
+    args: Vec<String>,
+}
+
+#[async_trait(?Send)]
+impl AsyncCommand for NodeCommand {
+    type Output = Result<()>;
+
+    async fn exec(&self) -> Result<()> {
+        let pin = match (&self.version, &self.sha256) {
+            (Some(version), Some(sha256)) => {
+                NodePin {
+                    version: version.clone(),
+                    sha256: sha256.clone(),
+                }
+            }
+            _ => {
+                ProjectFile::load()?.node.ok_or_else(|| {
+                    anyhow!(
+                        "No node version is pinned, add a `[node]` section with the \
+                        `version` and `sha256` to the `{PROJECT_FILE_NAME}` file or pass \
+                        `--version` and `--sha256`"
+                    )
+                })?
+            }
+        };
+        let node = pin.ensure_downloaded(&cache_dir()?)?;
+
+        if self.path {
+            println!("{}", node.display());
+            return Ok(())
+        }
+        if !self.groups.is_empty() {
+            return run_groups(&node, &self.exec, &self.groups)
+        }
+        let status = if let Some((program, args)) = self.exec.split_first() {
+            Command::new(program)
+                .args(args)
+                .env(CONTRACTS_NODE_ENV, &node)
+                .status()
+                .with_context(|| format!("Failed to run `{program}`"))?
+        } else {
+            let args = if self.args.is_empty() {
+                vec![String::from("--dev")]
+            } else {
+                self.args.clone()
+            };
+            Command::new(&node)
+                .args(args)
+                .status()
+                .with_context(|| format!("Failed to run {}", node.display()))?
+        };
+        if !status.success() {
+            anyhow::bail!("The command failed with {status}")
+        }
+        Ok(())
+    }
+}
+
+impl NodePin {
+    /// Returns the path of the node executable, downloading the release into the cache
+    /// if it is not cached yet, or was cached from an archive with another hash.
+    pub fn ensure_downloaded(&self, cache_dir: &Path) -> Result<PathBuf> {
+        parse_version(&self.version)?;
+        let dir = cache_dir.join(&self.version);
+        let node = dir.join(NODE_BINARY);
+        std::fs::create_dir_all(cache_dir)
+            .with_context(|| format!("Failed to create {}", cache_dir.display()))?;
+        let _lock = FileLock::acquire(&dir)?;
+        if node.exists() && self.is_cached_in(&dir) {
+            return Ok(node)
+        }
+        if dir.exists() {
+            std::fs::remove_dir_all(&dir)
+                .with_context(|| format!("Failed to remove {}", dir.display()))?;
+        }
+        std::fs::create_dir_all(&dir)
+            .with_context(|| format!("Failed to create {}", dir.display()))?;
+
+        let url = release_url(&self.version, platform()?);
+        let archive = dir.join("release.tar.gz");
+        let result =
+            download(&url, &archive).and_then(|()| self.install(&archive, &dir, &node));
+        let _ = std::fs::remove_file(&archive);
+        if result.is_err() {
+            let _ = std::fs::remove_dir_all(&dir);
+        }
+        result.map(|()| node)
+    }
+
+    /// Returns `true` if the node in the directory was unpacked from the pinned archive.
+    fn is_cached_in(&self, dir: &Path) -> bool {
+        std::fs::read_to_string(dir.join(SHA256_FILE))
+            .map_or(false, |cached| is_same_hash(cached.trim(), &self.sha256))
+    }
+
+    /// Verify and unpack the downloaded archive.
+    fn install(&self, archive: &Path, dir: &Path, node: &Path) -> Result<()> {
+        verify_sha256(archive, &self.sha256)?;
+        let unpacked = dir.join("unpacked");
+        unpack(archive, &unpacked).context("Failed to unpack the release")?;
+        let binary = find_binary(&unpacked)?.ok_or_else(|| {
+            anyhow!("The release does not contain the `{NODE_BINARY}` executable")
+        })?;
+        std::fs::rename(binary, node)?;
+        std::fs::remove_dir_all(&unpacked)?;
+
+        let output = Command::new(node).arg("--version").output()?;
+        let version = String::from_utf8_lossy(&output.stdout);
+        if !is_version(&version, &self.version) {
+            anyhow::bail!(
+                "The downloaded node reports the version `{}`, not `{}`",
+                version.trim(),
+                self.version
+            )
+        }
+        std::fs::write(dir.join(SHA256_FILE), &self.sha256)?;
+        Ok(())
+    }
+}
+
+/// A child process, killed when dropped, so that no process outlives a failed run.
+struct ChildGuard(Child);
+
+impl Drop for ChildGuard {
+    fn drop(&mut self) {
+        let _ = self.0.kill();
+        let _ = self.0.wait();
+    }
+}
+
+/// A node with a fresh temporary state, killed when dropped.
+struct IsolatedNode {
+    process: ChildGuard,
+    url: String,
+}
+
+impl IsolatedNode {
+    /// Start the node on random free ports and wait until it accepts connections. The
+    /// ports are only free when they are chosen, so the node is started on other ports
+    /// if it exits during startup.
+    fn start(node: &Path) -> Result<Self> {
+        let mut attempt = 1;
+        loop {
+            match Self::try_start(node)? {
+                Ok(isolated) => return Ok(isolated),
+                Err(status) if attempt < STARTUP_ATTEMPTS => {
+                    tracing::debug!(
+                        "The node exited with {status} during startup, retrying on \
+                        other ports ({attempt})"
+                    );
+                    attempt += 1;
+                }
+                Err(status) => {
+                    anyhow::bail!("The node exited with {status} during startup")
+                }
+            }
+        }
+    }
+
+    /// Start the node once, returns the exit status if it exits during startup.
+    fn try_start(node: &Path) -> Result<Result<Self, ExitStatus>> {
+        let (rpc_port, p2p_port) = free_ports()?;
+        let process = Command::new(node)
+            .args(["--dev", "--tmp", "--rpc-port"])
+            .arg(rpc_port.to_string())
+            .arg("--port")
+            .arg(p2p_port.to_string())
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .spawn()
+            .with_context(|| format!("Failed to start {}", node.display()))?;
+        let mut node = Self {
+            process: ChildGuard(process),
+            url: format!("ws://127.0.0.1:{rpc_port}"),
+        };
+        let started = Instant::now();
+        while TcpStream::connect((Ipv4Addr::LOCALHOST, rpc_port)).is_err() {
+            if let Some(status) = node.process.0.try_wait()? {
+                return Ok(Err(status))
+            }
+            if started.elapsed() > STARTUP_TIMEOUT {
+                anyhow::bail!(
+                    "The node did not accept connections within {}s",
+                    STARTUP_TIMEOUT.as_secs()
+                )
+            }
+            std::thread::sleep(Duration::from_millis(100));
+        }
+        Ok(Ok(node))
+    }
+}
+
+/// Returns two distinct ports which are currently free, for the RPC and the p2p
+/// connections of a node.
+fn free_ports() -> Result<(u16, u16)> {
+    let rpc = TcpListener::bind((Ipv4Addr::LOCALHOST, 0))?;
+    let p2p = TcpListener::bind((Ipv4Addr::LOCALHOST, 0))?;
+    Ok((rpc.local_addr()?.port(), p2p.local_addr()?.port()))
+}
+
+/// Returns the command of the group: the `--exec` command followed by the group.
+fn group_command(exec: &[String], group: &str) -> Command {
+    let (program, args) = exec
+        .split_first()
+        .expect("clap requires `--exec` with `--group`; qed");
+    let mut command = Command::new(program);
+    command.args(args).arg(group);
+    command
+}
+
+/// Run the command for every group in parallel, each against its own isolated node.
+fn run_groups(node: &Path, exec: &[String], groups: &[String]) -> Result<()> {
+    let mut runs = Vec::new();
+    for group in groups {
+        let isolated = IsolatedNode::start(node)
+            .with_context(|| format!("Failed to start the node of group `{group}`"))?;
+        let process = group_command(exec, group)
+            .env(CONTRACTS_NODE_ENV, node)
+            .env(CONTRACTS_NODE_URL_ENV, &isolated.url)
+            .spawn()
+            .with_context(|| format!("Failed to run the command of group `{group}`"))?;
+        runs.push((group, isolated, ChildGuard(process)));
+    }
+    let mut failed = Vec::new();
+    for (group, isolated, mut process) in runs {
+        let status = process.0.wait()?;
+        drop(isolated);
+        if !status.success() {
+            failed.push(group.as_str());
+        }
+    }
+    if !failed.is_empty() {
+        anyhow::bail!(
+            "{} of {} groups failed: {}",
+            failed.len(),
+            groups.len(),
+            failed.join(", ")
+        )
+    }
+    Ok(())
+}
+
+/// Parse a release tag, which names the cache directory of the release and is part of
+/// its url, so only letters, digits, `.`, `_` and `-` are accepted.
+fn parse_version(input: &str) -> Result<String> {
+    let valid = !input.is_empty()
+        && input != "."
+        && input != ".."
+        && input
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '.' | '_' | '-'));
+    anyhow::ensure!(
+        valid,
+        "Invalid node version `{input}`, expected a release tag, e.g. `v0.24.0`"
+    );
+    Ok(input.to_string())
+}
+
+fn deserialize_version<'de, D>(deserializer: D) -> Result<String, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    let version = <String as serde::Deserialize>::deserialize(deserializer)?;
+    parse_version(&version).map_err(serde::de::Error::custom)
+}
+
+/// Returns the directory the node releases are cached in.
+fn cache_dir() -> Result<PathBuf> {
+    Ok(cache_root()?.join("nodes"))
+}
+
+/// Returns the directory downloads of cargo-contract are cached in.
+pub(crate) fn cache_root() -> Result<PathBuf> {
+    if let Some(dir) = std::env::var_os(CACHE_DIR_ENV) {
+        return Ok(PathBuf::from(dir))
+    }
+    let cache = match std::env::var_os("XDG_CACHE_HOME") {
+        Some(dir) => PathBuf::from(dir),
+        None => {
+            let home = std::env::var_os("HOME").ok_or_else(|| {
+                anyhow!("Failed to find the cache directory, set `{CACHE_DIR_ENV}`")
+            })?;
+            PathBuf::from(home).join(".cache")
+        }
+    };
+    Ok(cache.join("cargo-contract"))
+}
+
+/// Returns the name of the current platform in the names of the release archives.
+fn platform() -> Result<&'static str> {
+    match std::env::consts::OS {
+        "linux" => Ok("linux"),
+        "macos" => Ok("mac-universal"),
+        os => Err(anyhow!("substrate-contracts-node is not released for {os}")),
+    }
+}
+
+/// Returns the url of the release archive for the platform.
+fn release_url(version: &str, platform: &str) -> String {
+    format!("{RELEASES_URL}/{version}/{NODE_BINARY}-{platform}.tar.gz")
+}
+
+/// Returns `true` if the output of `--version` reports the release tag.
+fn is_version(output: &str, tag: &str) -> bool {
+    let version = tag.trim_start_matches('v');
+    output.split_whitespace().any(|word| {
+        word == version
+            || word
+                .strip_prefix(version)
+                .is_some_and(|rest| rest.starts_with('-'))
+    })
+}
+
+/// Find the node executable in the unpacked release.
+fn find_binary(dir: &Path) -> Result<Option<PathBuf>> {
+    for entry in std::fs::read_dir(dir)? {
+        let path = entry?.path();
+        if path.is_dir() {
+            if let Some(binary) = find_binary(&path)? {
+                return Ok(Some(binary))
+            }
+        } else if path.file_name() == Some(NODE_BINARY.as_ref()) {
+            return Ok(Some(path))
+        }
+    }
+    Ok(None)
+}
+
+/// Download the url to the file.
+pub(crate) fn download(url: &str, file: &Path) -> Result<()> {
+    eprintln!("Downloading {url}");
+    let downloaded = || -> Result<()> {
+        let response = ureq::get(url).call()?;
+        let mut output = File::create(file)?;
+        std::io::copy(&mut response.into_reader(), &mut output)?;
+        Ok(output.sync_all()?)
+    };
+    downloaded().with_context(|| format!("Failed to download {url}"))
+}
+
+/// Unpack the `.tar.gz` archive into the directory.
+pub(crate) fn unpack(archive: &Path, dir: &Path) -> Result<()> {
+    std::fs::create_dir_all(dir)?;
+    let archive = File::open(archive)?;
+    tar::Archive::new(GzDecoder::new(archive)).unpack(dir)?;
+    Ok(())
+}
+
+/// Fail unless the hex encoded SHA-256 hash of the downloaded file is the expected hash.
+pub(crate) fn verify_sha256(file: &Path, expected: &str) -> Result<()> {
+    let contents = std::fs::read(file)?;
+    let actual = hex::encode(sha2_256(&contents));
+    if !is_same_hash(&actual, expected) {
+        anyhow::bail!(
+            "The SHA-256 hash {actual} of the download {} does not match the pinned hash \
+            {expected}",
+            file.display()
+        )
+    }
+    Ok(())
+}
+
+/// Returns `true` if the hex encoded hashes are equal, ignoring a `0x` prefix and the
+/// case.
+fn is_same_hash(a: &str, b: &str) -> bool {
+    a.trim_start_matches("0x")
+        .eq_ignore_ascii_case(b.trim_start_matches("0x"))
+}
+
+pub(crate) fn run(command: &mut Command) -> Result<()> {
+    let status = command.status()?;
+    anyhow::ensure!(status.success(), "exited with {status}");
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn releases_are_located_and_verified_by_version() {
+        assert_eq!(
+            release_url("v0.24.0", "linux"),
+            "https://github.com/paritytech/substrate-contracts-node/releases/download/\
+            v0.24.0/substrate-contracts-node-linux.tar.gz"
+        );
+        assert!(is_version(
+            "substrate-contracts-node 0.24.0-0d4a3a1ffd2",
+            "v0.24.0"
+        ));
+        assert!(is_version("substrate-contracts-node 0.24.0", "0.24.0"));
+        assert!(!is_version("substrate-contracts-node 0.24.01", "v0.24.0"));
+        assert!(!is_version("substrate-contracts-node 0.23.0", "v0.24.0"));
+    }
+
+    #[test]
+    fn groups_are_appended_to_the_command() {
+        let exec = ["cargo", "test", "--features", "e2e-tests"].map(String::from);
+        let command = group_command(&exec, "erc20::");
+        assert_eq!(command.get_program(), "cargo");
+        assert_eq!(
+            command.get_args().collect::<Vec<_>>(),
+            ["test", "--features", "e2e-tests", "erc20::"]
+        );
+    }
+
+    #[test]
+    fn versions_are_release_tags() {
+        assert_eq!(parse_version("v0.24.0").unwrap(), "v0.24.0");
+        assert!(parse_version("v1.0.0-rc_1").is_ok());
+        for invalid in ["", ".", "..", "../..", "/tmp", "v1/../..", "v1 0"] {
+            assert!(parse_version(invalid).is_err(), "{invalid}");
+        }
+        let pin = NodePin {
+            version: String::from(".."),
+            sha256: String::from("00"),
+        };
+        let cache = tempfile::tempdir().unwrap();
+        assert!(pin.ensure_downloaded(cache.path()).is_err());
+    }
+
+    #[test]
+    fn isolated_nodes_get_distinct_ports() {
+        let (rpc_port, p2p_port) = free_ports().unwrap();
+        assert_ne!(rpc_port, p2p_port);
+    }
+
+    #[test]
+    fn cached_nodes_are_not_downloaded_again() {
+        let cache = tempfile::tempdir().unwrap();
+        let dir = cache.path().join("v0.24.0");
+        let node = dir.join(NODE_BINARY);
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(&node, "").unwrap();
+        std::fs::write(dir.join(SHA256_FILE), "ab01").unwrap();
+        let pin = NodePin {
+            version: String::from("v0.24.0"),
+            sha256: String::from("0xAB01"),
+        };
+        assert!(pin.is_cached_in(&dir));
+        assert_eq!(pin.ensure_downloaded(cache.path()).unwrap(), node);
+
+        let other = NodePin {
+            sha256: String::from("cd02"),
+            ..pin
+        };
+        assert!(!other.is_cached_in(&dir));
+    }
+
+    #[test]
+    fn node_pin_is_parsed() {
+        let project = ProjectFile::parse(
+            r#"
+            [node]
+            version = "v0.24.0"
+            sha256 = "ab01"
+            "#,
+        )
+        .expect("project file must be valid");
+        assert_eq!(
+            project.node.unwrap(),
+            NodePin {
+                version: String::from("v0.24.0"),
+                sha256: String::from("ab01"),
+            }
+        );
+        assert!(ProjectFile::parse("[node]\nversion = \"v1\"").is_err());
+        assert!(
+            ProjectFile::parse("[node]\nversion = \"../..\"\nsha256 = \"00\"").is_err()
+        );
+        assert!(
+            ProjectFile::parse("[node]\nversion = \"v1\"\nchecksum = \"00\"").is_err()
+        );
+    }
+}
@@ -0,0 +1,150 @@
+// Copyright 2018-2023 Parity Technologies (UK) Ltd.
+// This file is part of cargo-contract.
+//
+// cargo-contract is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// cargo-contract is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with cargo-contract.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Displaying decoded values which can be very large, e.g. return values or storage
+//! values holding vectors of several MB.
+//!
+//! Values are formatted in chunks into the destination, so neither truncating a value
+//! nor writing it into a file builds the whole formatted value in memory.
+
+use anyhow::{
+    Context,
+    Result,
+};
+use std::{
+    fmt::{
+        self,
+        Display,
+        Write as _,
+    },
+    io::{
+        self,
+        Write as _,
+    },
+    path::Path,
+};
+
+/// The default maximum size of a value in the human readable output.
+pub const DEFAULT_MAX_OUTPUT_BYTES: usize = 64 * 1024;
+
+/// Returns the value formatted up to `max` bytes, followed by a notice with its full
+/// size if it is larger. A `max` of `0` never truncates.
+pub fn truncate(value: &impl Display, max: usize) -> String {
+    let mut out = Truncated {
+        value: String::new(),
+        max,
+        len: 0,
+    };
+    let _ = write!(out, "{value}");
+    if out.len > out.value.len() {
+        let _ = write!(
+            out.value,
+            "… ({} of {} bytes shown, see `--max-output-bytes` and `--out`)",
+            out.value.len(),
+            out.len
+        );
+    }
+    out.value
+}
+
+/// Write the formatted value into the file, returning its size in bytes.
+pub fn write_to_file(path: &Path, value: &impl Display) -> Result<u64> {
+    let file = std::fs::File::create(path)
+        .with_context(|| format!("Failed to create {}", path.display()))?;
+    let mut out = CountingWriter {
+        inner: io::BufWriter::new(file),
+        len: 0,
+    };
+    writeln!(out, "{value}")
+        .and_then(|()| out.flush())
+        .with_context(|| format!("Failed to write {}", path.display()))?;
+    Ok(out.len)
+}
+
+/// Keeps the first `max` bytes written and counts the rest.
+struct Truncated {
+    value: String,
+    max: usize,
+    /// The number of bytes written, including the ones not kept.
+    len: usize,
+}
+
+impl fmt::Write for Truncated {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        let truncated = self.len > self.value.len();
+        self.len += s.len();
+        if truncated {
+            return Ok(())
+        }
+        let remaining = self.max.saturating_sub(self.value.len());
+        if self.max == 0 || remaining >= s.len() {
+            self.value.push_str(s);
+        } else {
+            let mut end = remaining;
+            while !s.is_char_boundary(end) {
+                end -= 1;
+            }
+            self.value.push_str(&s[..end]);
+        }
+        Ok(())
+    }
+}
+
+struct CountingWriter<W> {
+    inner: W,
+    len: u64,
+}
+
+impl<W: io::Write> io::Write for CountingWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let written = self.inner.write(buf)?;
+        self.len += written as u64;
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn large_values_are_truncated() {
+        let value = format!("[{}]", "0x00, ".repeat(1000));
+        assert_eq!(truncate(&value, 0), value);
+        assert_eq!(truncate(&value, value.len()), value);
+        assert_eq!(
+            truncate(&value, 10),
+            format!(
+                "[0x00, 0x0… (10 of {} bytes shown, see `--max-output-bytes` and `--out`)",
+                value.len()
+            )
+        );
+        // Never cut a character in half.
+        assert!(truncate(&"ä".repeat(10), 3).starts_with("ä…"));
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("value.txt");
+        assert_eq!(
+            write_to_file(&path, &value).unwrap(),
+            value.len() as u64 + 1
+        );
+        assert_eq!(std::fs::read_to_string(path).unwrap(), value + "\n");
+    }
+}
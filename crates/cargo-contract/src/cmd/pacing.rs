@@ -0,0 +1,138 @@
+// Copyright 2018-2023 Parity Technologies (UK) Ltd.
+// This file is part of cargo-contract.
+//
+// cargo-contract is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// cargo-contract is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with cargo-contract.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Pacing of RPC requests.
+//!
+//! Community RPC providers ban clients sending bursts of requests. In the public endpoint
+//! mode, enabled with `--public-endpoint` or the `public-endpoint` setting of a network
+//! profile, all requests of dry-runs and storage scans share a single conservative rate
+//! limit.
+
+use std::{
+    sync::{
+        atomic::{
+            AtomicBool,
+            Ordering,
+        },
+        Mutex,
+    },
+    time::{
+        Duration,
+        Instant,
+    },
+};
+
+/// The maximum number of RPC requests per second in the public endpoint mode.
+pub const PUBLIC_ENDPOINT_RATE_LIMIT: u32 = 2;
+
+static PUBLIC_ENDPOINT: AtomicBool = AtomicBool::new(false);
+
+static PUBLIC_ENDPOINT_PACER: Mutex<RateLimiter> =
+    Mutex::new(RateLimiter::per_second(PUBLIC_ENDPOINT_RATE_LIMIT));
+
+/// Enable the public endpoint mode for the rest of the process.
+pub fn enable_public_endpoint() {
+    PUBLIC_ENDPOINT.store(true, Ordering::Relaxed);
+}
+
+/// Returns `true` if the public endpoint mode is enabled.
+pub fn public_endpoint() -> bool {
+    PUBLIC_ENDPOINT.load(Ordering::Relaxed)
+}
+
+/// Wait for the next slot of the shared rate limit before sending a request, if the
+/// public endpoint mode is enabled.
+pub async fn pace() {
+    if !public_endpoint() {
+        return
+    }
+    let delay = PUBLIC_ENDPOINT_PACER
+        .lock()
+        .expect("the pacer never panics while locked; qed")
+        .reserve(Instant::now());
+    if !delay.is_zero() {
+        tokio::time::sleep(delay).await;
+    }
+}
+
+/// Spaces requests evenly to stay below a number of requests per second.
+pub struct RateLimiter {
+    interval: Option<Duration>,
+    next: Option<Instant>,
+}
+
+impl RateLimiter {
+    pub fn new(requests_per_second: Option<u32>) -> Self {
+        Self {
+            interval: requests_per_second
+                .map(|requests| Duration::from_secs(1) / requests),
+            next: None,
+        }
+    }
+
+    const fn per_second(requests: u32) -> Self {
+        Self {
+            interval: Some(Duration::from_nanos(1_000_000_000 / requests as u64)),
+            next: None,
+        }
+    }
+
+    /// Reserve the next slot for a request, returns how long to wait from `now` until it.
+    fn reserve(&mut self, now: Instant) -> Duration {
+        let Some(interval) = self.interval else {
+            return Duration::ZERO
+        };
+        let slot = self.next.map_or(now, |next| next.max(now));
+        self.next = Some(slot + interval);
+        slot - now
+    }
+
+    /// Wait for the next slot, and for the shared rate limit of the public endpoint mode.
+    pub async fn wait(&mut self) {
+        let delay = self.reserve(Instant::now());
+        if !delay.is_zero() {
+            tokio::time::sleep(delay).await;
+        }
+        pace().await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rate_limiter_spaces_requests() {
+        let now = Instant::now();
+        let mut limiter = RateLimiter::new(Some(4));
+        assert_eq!(limiter.reserve(now), Duration::ZERO);
+        assert_eq!(limiter.reserve(now), Duration::from_millis(250));
+        assert_eq!(limiter.reserve(now), Duration::from_millis(500));
+        // Time passed since the last reserved slot does not accumulate.
+        assert_eq!(
+            limiter.reserve(now + Duration::from_secs(2)),
+            Duration::ZERO
+        );
+
+        let mut unlimited = RateLimiter::new(None);
+        assert_eq!(unlimited.reserve(now), Duration::ZERO);
+        assert_eq!(unlimited.reserve(now), Duration::ZERO);
+
+        let mut public = RateLimiter::per_second(PUBLIC_ENDPOINT_RATE_LIMIT);
+        assert_eq!(public.reserve(now), Duration::ZERO);
+        assert_eq!(public.reserve(now), Duration::from_millis(500));
+    }
+}
@@ -0,0 +1,379 @@
+// Copyright 2018-2023 Parity Technologies (UK) Ltd.
+// This file is part of cargo-contract.
+//
+// cargo-contract is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// cargo-contract is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with cargo-contract.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Signed whitelists of the messages which may be called on a network by a role.
+//!
+//! A `permissions.toml` file distributed with a deployment lists, by network profile and
+//! by role, the messages `call` may submit:
+//!
+//! ```toml
+//! [network.production.roles]
+//! operator = ["pause", "unpause"]
+//! admin = ["*"]
+//! ```
+//!
+//! The file is signed with an sr25519 key by `permissions sign`, which writes the
+//! signature to `permissions.toml.sig`. It is enforced once the `cargo-contract.toml`
+//! configuration file names the keys trusted to sign it:
+//!
+//! ```toml
+//! [permissions]
+//! signers = ["5GrwvaEF5zXb26Fz9rcQpDWS57CtERHpNehXCPcNoHGKutQY"]
+//! ```
+//!
+//! Submitting a call to a network listed in the file then requires a `--role` allowed to
+//! call the message. A missing file, a missing signature or a signature by another key
+//! forbid submitting any call, so that an edited whitelist is never silently trusted.
+//! Dry-runs are never restricted.
+
+use super::{
+    config::{
+        Config,
+        CONFIG_FILE_NAME,
+    },
+    AsyncCommand,
+};
+use anyhow::{
+    Context,
+    Result,
+};
+use async_trait::async_trait;
+use colored::Colorize;
+use sp_core::{
+    crypto::Ss58Codec,
+    sr25519,
+    Pair,
+};
+use std::{
+    collections::BTreeMap,
+    fs,
+    path::{
+        Path,
+        PathBuf,
+    },
+};
+use subxt::utils::AccountId32;
+
+/// The default file name of the permissions, relative to the configuration file.
+pub const PERMISSIONS_FILE_NAME: &str = "permissions.toml";
+
+/// The role allowed to call any message of a network.
+const ANY_MESSAGE: &str = "*";
+
+/// The `permissions` section of the configuration file.
+#[derive(Debug, Clone, Default, PartialEq, Eq, serde::Deserialize)]
+pub struct PermissionsConfig {
+    /// The path of the permissions file, relative to the configuration file.
+    pub file: Option<PathBuf>,
+    /// The keys trusted to sign the permissions file.
+    pub signers: Vec<AccountId32>,
+}
+
+impl PermissionsConfig {
+    /// Returns the path of the permissions file.
+    pub fn path(&self) -> Result<PathBuf> {
+        let file = self
+            .file
+            .clone()
+            .unwrap_or_else(|| PathBuf::from(PERMISSIONS_FILE_NAME));
+        let dir = Config::path()?
+            .and_then(|path| path.parent().map(Path::to_path_buf))
+            .unwrap_or_default();
+        Ok(dir.join(file))
+    }
+
+    /// Load the permissions file, if it is signed by one of the trusted keys.
+    pub fn load(&self) -> Result<Permissions> {
+        let path = self.path()?;
+        let contents = fs::read(&path).with_context(|| {
+            format!(
+                "Failed to read the permissions file {}, required by the `permissions` \
+                section of `{CONFIG_FILE_NAME}`",
+                path.display()
+            )
+        })?;
+        let signature_path = signature_path(&path);
+        let signature = fs::read_to_string(&signature_path).with_context(|| {
+            format!(
+                "Failed to read the signature of the permissions file {}, sign it with \
+                `cargo contract permissions sign`",
+                signature_path.display()
+            )
+        })?;
+        verify(&contents, &signature, &self.signers)
+            .with_context(|| format!("Untrusted permissions file {}", path.display()))?;
+        let contents = String::from_utf8(contents)?;
+        Permissions::parse(&contents)
+            .with_context(|| format!("Invalid {}", path.display()))
+    }
+}
+
+/// The contents of the `permissions.toml` file.
+#[derive(Debug, Default, PartialEq, Eq, serde::Deserialize)]
+pub struct Permissions {
+    /// The permissions of the roles by network profile.
+    #[serde(default)]
+    pub network: BTreeMap<String, NetworkPermissions>,
+}
+
+/// The permissions of the roles on a network.
+#[derive(Debug, Default, PartialEq, Eq, serde::Deserialize)]
+pub struct NetworkPermissions {
+    /// The messages each role may call, `*` for any message.
+    #[serde(default)]
+    pub roles: BTreeMap<String, Vec<String>>,
+}
+
+impl Permissions {
+    /// Parse the permissions from the contents of a permissions file.
+    pub fn parse(contents: &str) -> Result<Self> {
+        Ok(toml::from_str(contents)?)
+    }
+
+    /// Returns an error if the role is not allowed to call the message on the network.
+    /// Networks not listed in the file, and calls without a network profile, are not
+    /// restricted.
+    pub fn check(
+        &self,
+        network: Option<&str>,
+        role: Option<&str>,
+        message: &str,
+    ) -> Result<()> {
+        let Some((network, permissions)) =
+            network.and_then(|name| self.network.get_key_value(name))
+        else {
+            return Ok(())
+        };
+        let roles = || {
+            permissions
+                .roles
+                .keys()
+                .map(|role| format!("`{role}`"))
+                .collect::<Vec<_>>()
+                .join(", ")
+        };
+        let Some(role) = role else {
+            anyhow::bail!(
+                "Calls on the network `{network}` require a `--role`, one of: {}",
+                roles()
+            )
+        };
+        let messages = permissions.roles.get(role).with_context(|| {
+            format!(
+                "The role `{role}` may not call any message on the network `{network}`, \
+                roles: {}",
+                roles()
+            )
+        })?;
+        anyhow::ensure!(
+            messages
+                .iter()
+                .any(|allowed| allowed == message || allowed == ANY_MESSAGE),
+            "The role `{role}` may not call `{message}` on the network `{network}`, \
+            allowed: {}",
+            messages
+                .iter()
+                .map(|message| format!("`{message}`"))
+                .collect::<Vec<_>>()
+                .join(", ")
+        );
+        Ok(())
+    }
+}
+
+/// The path of the signature of the permissions file, `<file>.sig`.
+pub fn signature_path(path: &Path) -> PathBuf {
+    let mut path = path.as_os_str().to_owned();
+    path.push(".sig");
+    PathBuf::from(path)
+}
+
+/// Sign the contents of a permissions file, returns the hex encoded signature.
+pub fn sign(contents: &[u8], key: &sr25519::Pair) -> String {
+    format!("0x{}\n", hex::encode(key.sign(contents).0))
+}
+
+/// Check the hex encoded signature of the contents of a permissions file, returns the
+/// trusted key which signed it.
+pub fn verify(
+    contents: &[u8],
+    signature: &str,
+    signers: &[AccountId32],
+) -> Result<AccountId32> {
+    anyhow::ensure!(
+        !signers.is_empty(),
+        "No trusted signers are configured in the `permissions` section of \
+        `{CONFIG_FILE_NAME}`"
+    );
+    let signature = signature.trim();
+    let bytes: [u8; 64] = hex::decode(signature.trim_start_matches("0x"))
+        .context("Invalid hex signature")?
+        .try_into()
+        .map_err(|_| anyhow::anyhow!("The signature is not 64 bytes"))?;
+    let signature = sr25519::Signature::from_raw(bytes);
+    signers
+        .iter()
+        .find(|signer| {
+            let public = sr25519::Public::from_raw(signer.0);
+            sr25519::Pair::verify(&signature, contents, &public)
+        })
+        .cloned()
+        .context("The file is not signed by any of the trusted signers")
+}
+
+#[derive(Debug, clap::Args)]
+#[clap(
+    name = "permissions",
+    about = "Sign and verify the messages whitelisted by permissions.toml"
+)]
+pub struct PermissionsCommand {
+    #[clap(subcommand)]
+    action: PermissionsAction,
+    /// Path to the permissions file, by default the file of the `permissions` section
+    /// of `cargo-contract.toml`.
+    #[clap(long, value_parser)]
+    file: Option<PathBuf>,
+}
+
+#[derive(Debug, clap::Subcommand)]
+enum PermissionsAction {
+    /// Sign the permissions file, writing the signature to `<file>.sig`
+    Sign {
+        /// Secret key URI of the key signing the file.
+        #[clap(long, short)]
+        suri: String,
+        /// Password for the secret key.
+        #[clap(long, short)]
+        password: Option<String>,
+    },
+    /// Check the signature of the permissions file and list the whitelisted messages
+    Verify,
+}
+
+#[async_trait(?Send)]
+impl AsyncCommand for PermissionsCommand {
+    type Output = Result<()>;
+
+    async fn exec(&self) -> Result<()> {
+        let config = Config::load()?.permissions.unwrap_or_default();
+        let path = match self.file {
+            Some(ref file) => file.clone(),
+            None => config.path()?,
+        };
+        let contents = fs::read(&path)
+            .with_context(|| format!("Failed to read {}", path.display()))?;
+        match self.action {
+            PermissionsAction::Sign {
+                ref suri,
+                ref password,
+            } => {
+                Permissions::parse(&String::from_utf8(contents.clone())?)
+                    .with_context(|| format!("Invalid {}", path.display()))?;
+                let key = sr25519::Pair::from_string(suri, password.as_deref())
+                    .map_err(|_| anyhow::anyhow!("Secret string error"))?;
+                let signature_path = signature_path(&path);
+                fs::write(&signature_path, sign(&contents, &key)).with_context(|| {
+                    format!("Failed to write {}", signature_path.display())
+                })?;
+                println!(
+                    "{} {} with {}",
+                    "Signed".green().bold(),
+                    path.display(),
+                    key.public().to_ss58check()
+                );
+                if !config.signers.contains(&AccountId32::from(key.public().0)) {
+                    eprintln!(
+                        "{} the key is not a trusted signer in the `permissions` section \
+                        of `{CONFIG_FILE_NAME}`",
+                        "warning:".yellow().bold()
+                    );
+                }
+            }
+            PermissionsAction::Verify => {
+                let signature_path = signature_path(&path);
+                let signature =
+                    fs::read_to_string(&signature_path).with_context(|| {
+                        format!("Failed to read {}", signature_path.display())
+                    })?;
+                let signer = verify(&contents, &signature, &config.signers)?;
+                let permissions = Permissions::parse(&String::from_utf8(contents)?)?;
+                println!(
+                    "{} {} signed by {signer}",
+                    "Verified".green().bold(),
+                    path.display()
+                );
+                for (network, permissions) in &permissions.network {
+                    println!("{}", network.bold());
+                    for (role, messages) in &permissions.roles {
+                        println!("  {role}: {}", messages.join(", "));
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PERMISSIONS: &str = r#"
+        [network.production.roles]
+        operator = ["pause", "unpause"]
+        admin = ["*"]
+    "#;
+
+    #[test]
+    fn messages_are_whitelisted_by_role_and_network() {
+        let permissions = Permissions::parse(PERMISSIONS).expect("valid permissions");
+        let production = Some("production");
+
+        assert!(permissions
+            .check(production, Some("operator"), "pause")
+            .is_ok());
+        assert!(permissions
+            .check(production, Some("admin"), "terminate")
+            .is_ok());
+        assert!(permissions
+            .check(production, Some("operator"), "terminate")
+            .is_err());
+        assert!(permissions
+            .check(production, Some("auditor"), "pause")
+            .is_err());
+        assert!(permissions.check(production, None, "pause").is_err());
+        assert!(permissions.check(Some("local"), None, "terminate").is_ok());
+        assert!(permissions.check(None, None, "terminate").is_ok());
+    }
+
+    #[test]
+    fn only_files_signed_by_a_trusted_signer_are_accepted() {
+        let alice = sr25519::Pair::from_string("//Alice", None).unwrap();
+        let bob = sr25519::Pair::from_string("//Bob", None).unwrap();
+        let trusted = [AccountId32::from(alice.public().0)];
+        let signature = sign(PERMISSIONS.as_bytes(), &alice);
+
+        assert_eq!(
+            verify(PERMISSIONS.as_bytes(), &signature, &trusted).unwrap(),
+            trusted[0]
+        );
+        let tampered = PERMISSIONS.replace("\"pause\"", "\"terminate\"");
+        assert!(verify(tampered.as_bytes(), &signature, &trusted).is_err());
+        let signature = sign(PERMISSIONS.as_bytes(), &bob);
+        assert!(verify(PERMISSIONS.as_bytes(), &signature, &trusted).is_err());
+        assert!(verify(PERMISSIONS.as_bytes(), &signature, &[]).is_err());
+    }
+}
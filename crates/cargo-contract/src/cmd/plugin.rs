@@ -0,0 +1,179 @@
+// Copyright 2018-2023 Parity Technologies (UK) Ltd.
+// This file is part of cargo-contract.
+//
+// cargo-contract is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// cargo-contract is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with cargo-contract.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Support for custom subcommands shipped as separate executables.
+//!
+//! Running `cargo contract <name> [args]` for an unknown `<name>` looks up an executable
+//! named `cargo-contract-<name>` in `PATH` and runs it with the remaining arguments, in
+//! the same way as `cargo` itself handles `cargo-<name>` executables.
+
+use anyhow::{
+    Context,
+    Result,
+};
+use std::{
+    ffi::OsString,
+    path::{
+        Path,
+        PathBuf,
+    },
+    process::Command,
+};
+
+/// The prefix of executables providing custom subcommands.
+pub const PLUGIN_PREFIX: &str = "cargo-contract-";
+
+/// The environment variable set for plugins to the path of the `cargo-contract`
+/// executable, so that they can invoke the built-in commands.
+pub const CARGO_CONTRACT_ENV: &str = "CARGO_CONTRACT";
+
+/// Run the plugin for the external subcommand `args[0]` with the remaining arguments.
+pub fn exec(args: &[String]) -> Result<()> {
+    let (name, args) = args
+        .split_first()
+        .context("No subcommand name was supplied")?;
+    let plugin = find(name, std::env::var_os("PATH")).with_context(|| {
+        format!(
+            "No such command: `{name}`.\n\n\
+            Custom commands are run from `{PLUGIN_PREFIX}<name>` executables in `PATH`."
+        )
+    })?;
+    tracing::debug!("Running plugin `{}`", plugin.display());
+
+    let mut cmd = Command::new(&plugin);
+    cmd.args(args);
+    if let Ok(current_exe) = std::env::current_exe() {
+        cmd.env(CARGO_CONTRACT_ENV, current_exe);
+    }
+    let status = cmd
+        .status()
+        .with_context(|| format!("Failed to run `{}`", plugin.display()))?;
+    if !status.success() {
+        // exit like the plugin did, so that scripts can tell its failures apart
+        match status.code() {
+            Some(code) => std::process::exit(code),
+            None => anyhow::bail!("`{}` failed with {status}", plugin.display()),
+        }
+    }
+    Ok(())
+}
+
+/// Find the executable for the plugin `name` in the given search `paths`, formatted like
+/// the `PATH` environment variable.
+pub fn find(name: &str, paths: Option<OsString>) -> Option<PathBuf> {
+    let cwd = std::env::current_dir().ok()?;
+    which::which_in(format!("{PLUGIN_PREFIX}{name}"), paths, cwd).ok()
+}
+
+/// Returns the names of all plugins found in the given search `paths`.
+pub fn list(paths: Option<OsString>) -> Vec<String> {
+    let mut plugins = paths
+        .map(|paths| std::env::split_paths(&paths).collect::<Vec<_>>())
+        .unwrap_or_default()
+        .iter()
+        .filter_map(|dir| std::fs::read_dir(dir).ok())
+        .flatten()
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path())
+        .filter(|path| is_executable(path))
+        .filter_map(|path| plugin_name(&path))
+        .collect::<Vec<_>>();
+    plugins.sort();
+    plugins.dedup();
+    plugins
+}
+
+/// Returns the name of the plugin if the file name has the plugin prefix.
+///
+/// Only the executable suffix of the platform is removed, e.g. `.exe` on Windows, so
+/// that the name is the one [`find`] resolves.
+fn plugin_name(path: &Path) -> Option<String> {
+    let file_name = path.file_name()?.to_str()?;
+    let file_name = file_name
+        .strip_suffix(std::env::consts::EXE_SUFFIX)
+        .unwrap_or(file_name);
+    file_name
+        .strip_prefix(PLUGIN_PREFIX)
+        .filter(|name| !name.is_empty())
+        .map(ToString::to_string)
+}
+
+/// Returns `true` if `path` is a file which can be executed.
+#[cfg(unix)]
+fn is_executable(path: &Path) -> bool {
+    use std::os::unix::fs::PermissionsExt;
+
+    std::fs::metadata(path)
+        .map(|metadata| metadata.is_file() && metadata.permissions().mode() & 0o111 != 0)
+        .unwrap_or(false)
+}
+
+/// Returns `true` if `path` is a file which can be executed.
+#[cfg(not(unix))]
+fn is_executable(path: &Path) -> bool {
+    path.is_file()
+        && path
+            .extension()
+            .map_or(false, |extension| extension == "exe")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn plugin_names_are_parsed_from_file_names() {
+        assert_eq!(
+            plugin_name(Path::new("/usr/bin/cargo-contract-astar")),
+            Some(String::from("astar"))
+        );
+        assert_eq!(
+            plugin_name(Path::new(&format!(
+                "cargo-contract-astar{}",
+                std::env::consts::EXE_SUFFIX
+            ))),
+            Some(String::from("astar"))
+        );
+        assert_eq!(
+            plugin_name(Path::new("/usr/bin/cargo-contract-astar.v2")),
+            Some(String::from("astar.v2"))
+        );
+        assert_eq!(plugin_name(Path::new("/usr/bin/cargo-contract")), None);
+        assert_eq!(plugin_name(Path::new("/usr/bin/cargo-contract-")), None);
+        assert_eq!(plugin_name(Path::new("/usr/bin/cargo")), None);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn plugins_are_found_in_path() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = tempfile::tempdir().expect("temporary directory creation failed");
+        let plugin = dir.path().join("cargo-contract-hello");
+        std::fs::write(&plugin, "#!/bin/sh\necho hello\n")
+            .expect("writing plugin failed");
+        std::fs::set_permissions(&plugin, std::fs::Permissions::from_mode(0o755))
+            .expect("setting permissions failed");
+        // not executable, hence not a plugin
+        std::fs::write(dir.path().join("cargo-contract-notes"), "some notes\n")
+            .expect("writing notes failed");
+
+        let paths = Some(dir.path().as_os_str().to_owned());
+        assert_eq!(find("hello", paths.clone()), Some(plugin));
+        assert_eq!(find("missing", paths.clone()), None);
+        assert_eq!(list(paths), vec![String::from("hello")]);
+    }
+}
@@ -0,0 +1,618 @@
+// Copyright 2018-2023 Parity Technologies (UK) Ltd.
+// This file is part of cargo-contract.
+//
+// cargo-contract is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// cargo-contract is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with cargo-contract.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Signed provenance statements of contract builds.
+//!
+//! With `--provenance-suri` the `build` command writes a `<name>.provenance.json` file
+//! next to the artifacts: an [in-toto statement](https://in-toto.io/Statement/v1) with a
+//! [SLSA provenance](https://slsa.dev/provenance/v1) predicate, describing the builder,
+//! the digest of the source, the build parameters and the digests of the artifacts. The
+//! statement is wrapped in a [DSSE envelope](https://github.com/secure-systems-lab/dsse)
+//! signed with the sr25519 key of the URI. `verify-provenance` checks the signature and
+//! the digests of the artifacts.
+
+use super::AsyncCommand;
+use anyhow::{
+    Context,
+    Result,
+};
+use async_trait::async_trait;
+use base64::{
+    engine::general_purpose::STANDARD as BASE64,
+    Engine as _,
+};
+use colored::Colorize;
+use contract_build::{
+    name_value_println,
+    BuildResult,
+    ManifestPath,
+    DEFAULT_KEY_COL_WIDTH,
+};
+use contract_metadata::ContractMetadata;
+use serde_json::{
+    json,
+    Value,
+};
+use sp_core::{
+    crypto::{
+        AccountId32,
+        Ss58Codec,
+    },
+    hashing::sha2_256,
+    sr25519,
+    Pair,
+};
+use std::{
+    collections::BTreeMap,
+    fs,
+    path::{
+        Path,
+        PathBuf,
+    },
+    process::Command,
+    time::{
+        SystemTime,
+        UNIX_EPOCH,
+    },
+};
+
+/// The type of the payload of the envelope.
+const PAYLOAD_TYPE: &str = "application/vnd.in-toto+json";
+/// The type of the in-toto statement.
+const STATEMENT_TYPE: &str = "https://in-toto.io/Statement/v1";
+/// The type of the SLSA provenance predicate.
+const PREDICATE_TYPE: &str = "https://slsa.dev/provenance/v1";
+/// The type of the builds described by the provenance.
+const BUILD_TYPE: &str = "https://github.com/paritytech/cargo-contract/build/v1";
+/// The name of the resolved dependency with the digest of the source.
+const SOURCE_DEPENDENCY: &str = "source";
+
+/// A DSSE envelope of a signed statement.
+#[derive(Debug, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Envelope {
+    pub payload_type: String,
+    /// The base64 encoded statement.
+    pub payload: String,
+    pub signatures: Vec<EnvelopeSignature>,
+}
+
+/// A signature of the envelope.
+#[derive(Debug, serde::Serialize, serde::Deserialize)]
+pub struct EnvelopeSignature {
+    /// The SS58 address of the sr25519 key.
+    pub keyid: String,
+    /// The base64 encoded signature.
+    pub sig: String,
+}
+
+/// An in-toto statement about the artifacts of a build.
+#[derive(Debug, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Statement {
+    #[serde(rename = "_type")]
+    pub statement_type: String,
+    pub subject: Vec<Subject>,
+    pub predicate_type: String,
+    pub predicate: Value,
+}
+
+/// An artifact of a build, identified by its file name.
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct Subject {
+    pub name: String,
+    pub digest: BTreeMap<String, String>,
+}
+
+impl Subject {
+    fn from_file(path: &Path) -> Result<Self> {
+        let name = path
+            .file_name()
+            .context("Artifact without a file name")?
+            .to_string_lossy()
+            .to_string();
+        let digest = BTreeMap::from([("sha256".to_string(), file_digest(path)?)]);
+        Ok(Self { name, digest })
+    }
+}
+
+impl Envelope {
+    /// Sign the statement with the key.
+    pub fn sign(statement: &Statement, key: &sr25519::Pair) -> Result<Self> {
+        let payload = serde_json::to_vec(statement)?;
+        let signature = key.sign(&pre_auth_encoding(PAYLOAD_TYPE, &payload));
+        Ok(Self {
+            payload_type: PAYLOAD_TYPE.to_string(),
+            payload: BASE64.encode(payload),
+            signatures: vec![EnvelopeSignature {
+                keyid: key.public().to_ss58check(),
+                sig: BASE64.encode(signature.0),
+            }],
+        })
+    }
+
+    /// Check the signatures of the envelope, returns the signers and the statement.
+    pub fn verify(&self) -> Result<(Vec<AccountId32>, Statement)> {
+        anyhow::ensure!(
+            self.payload_type == PAYLOAD_TYPE,
+            "Unsupported payload type `{}`, expected `{PAYLOAD_TYPE}`",
+            self.payload_type
+        );
+        anyhow::ensure!(!self.signatures.is_empty(), "The envelope is not signed");
+        let payload = BASE64
+            .decode(&self.payload)
+            .context("Invalid base64 payload")?;
+        let message = pre_auth_encoding(&self.payload_type, &payload);
+        let mut signers = Vec::new();
+        for signature in &self.signatures {
+            let public =
+                sr25519::Public::from_ss58check(&signature.keyid).map_err(|err| {
+                    anyhow::anyhow!("Invalid key id {}: {err:?}", signature.keyid)
+                })?;
+            let bytes: [u8; 64] = BASE64
+                .decode(&signature.sig)
+                .context("Invalid base64 signature")?
+                .try_into()
+                .map_err(|_| anyhow::anyhow!("The signature is not 64 bytes"))?;
+            anyhow::ensure!(
+                sr25519::Pair::verify(
+                    &sr25519::Signature::from_raw(bytes),
+                    &message,
+                    &public
+                ),
+                "Invalid signature by {}",
+                signature.keyid
+            );
+            signers.push(AccountId32::from(public.0));
+        }
+        let statement: Statement =
+            serde_json::from_slice(&payload).context("Invalid statement")?;
+        anyhow::ensure!(
+            statement.statement_type == STATEMENT_TYPE
+                && statement.predicate_type == PREDICATE_TYPE,
+            "The envelope does not contain a SLSA provenance statement"
+        );
+        Ok((signers, statement))
+    }
+}
+
+/// The DSSE pre-authentication encoding of the payload, which is what is signed.
+fn pre_auth_encoding(payload_type: &str, payload: &[u8]) -> Vec<u8> {
+    let mut encoding = format!(
+        "DSSEv1 {} {payload_type} {} ",
+        payload_type.len(),
+        payload.len()
+    )
+    .into_bytes();
+    encoding.extend_from_slice(payload);
+    encoding
+}
+
+/// Write the signed provenance statement of the build next to its contract bundle,
+/// returns the path of the statement.
+pub fn write(
+    result: &BuildResult,
+    manifest_path: &ManifestPath,
+    started: SystemTime,
+    key: &sr25519::Pair,
+) -> Result<PathBuf> {
+    let metadata_result = result.metadata_result.as_ref().context(
+        "A provenance statement requires the metadata of the contract, build it with \
+        `--generate all`",
+    )?;
+    let metadata = ContractMetadata::load(&metadata_result.dest_metadata)?;
+
+    let mut artifacts: Vec<&Path> = Vec::new();
+    artifacts.extend(result.dest_wasm.as_deref());
+    artifacts.push(&metadata_result.dest_metadata);
+    artifacts.push(&metadata_result.dest_bundle);
+    artifacts.extend(metadata_result.dest_compressed_bundle.as_deref());
+    let subject = artifacts
+        .into_iter()
+        .map(Subject::from_file)
+        .collect::<Result<Vec<_>>>()?;
+
+    let source_dir = manifest_path.absolute_directory()?;
+    let mut dependencies = vec![json!({
+        "name": SOURCE_DEPENDENCY,
+        "digest": { "sha256": source_digest(&source_dir)? },
+    })];
+    if let Some(lockfile) = find_lockfile(&source_dir) {
+        dependencies.push(json!({
+            "name": "Cargo.lock",
+            "digest": { "sha256": file_digest(&lockfile)? },
+        }));
+    }
+    if let Some((commit, dirty)) = git_commit(&source_dir) {
+        dependencies.push(json!({
+            "name": "git",
+            "digest": { "gitCommit": commit },
+            "annotations": { "dirty": dirty },
+        }));
+    }
+
+    let version = env!("CARGO_PKG_VERSION");
+    let statement = Statement {
+        statement_type: STATEMENT_TYPE.to_string(),
+        subject,
+        predicate_type: PREDICATE_TYPE.to_string(),
+        predicate: json!({
+            "buildDefinition": {
+                "buildType": BUILD_TYPE,
+                "externalParameters": {
+                    "package": metadata.contract.name,
+                    "version": metadata.contract.version.to_string(),
+                    "buildInfo": metadata.source.build_info,
+                },
+                "internalParameters": {
+                    "codeHash": format!("0x{}", hex::encode(metadata.source.hash.0)),
+                    "language": metadata.source.language.to_string(),
+                    "compiler": metadata.source.compiler.to_string(),
+                },
+                "resolvedDependencies": dependencies,
+            },
+            "runDetails": {
+                "builder": {
+                    "id": format!("https://github.com/paritytech/cargo-contract@{version}"),
+                    "version": { "cargo-contract": version },
+                },
+                "metadata": {
+                    "startedOn": rfc3339(started),
+                    "finishedOn": rfc3339(SystemTime::now()),
+                },
+            },
+        }),
+    };
+
+    let envelope = Envelope::sign(&statement, key)?;
+    let path = metadata_result
+        .dest_bundle
+        .with_extension("provenance.json");
+    fs::write(&path, serde_json::to_string_pretty(&envelope)?)
+        .context(format!("Failed to write {}", path.display()))?;
+    Ok(path)
+}
+
+/// The hex encoded SHA-256 digest of the file.
+fn file_digest(path: &Path) -> Result<String> {
+    let contents =
+        fs::read(path).context(format!("Failed to read {}", path.display()))?;
+    Ok(hex::encode(sha2_256(&contents)))
+}
+
+/// The digest of the source of a crate: the SHA-256 digest of the `sha256sum` listing of
+/// its files, sorted by path. Hidden files and the `target` directory are skipped.
+fn source_digest(dir: &Path) -> Result<String> {
+    fn collect(
+        dir: &Path,
+        prefix: &str,
+        files: &mut Vec<(String, PathBuf)>,
+    ) -> Result<()> {
+        for entry in fs::read_dir(dir)? {
+            let entry = entry?;
+            let name = entry.file_name().to_string_lossy().to_string();
+            if name.starts_with('.') || (prefix.is_empty() && name == "target") {
+                continue
+            }
+            let relative = format!("{prefix}{name}");
+            if entry.file_type()?.is_dir() {
+                collect(&entry.path(), &format!("{relative}/"), files)?;
+            } else {
+                files.push((relative, entry.path()));
+            }
+        }
+        Ok(())
+    }
+    let mut files = Vec::new();
+    collect(dir, "", &mut files)?;
+    files.sort();
+    let mut listing = String::new();
+    for (relative, path) in files {
+        listing.push_str(&format!("{}  {relative}\n", file_digest(&path)?));
+    }
+    Ok(hex::encode(sha2_256(listing.as_bytes())))
+}
+
+/// The `Cargo.lock` of the crate, in its directory or of its workspace.
+fn find_lockfile(dir: &Path) -> Option<PathBuf> {
+    dir.ancestors()
+        .map(|dir| dir.join("Cargo.lock"))
+        .find(|path| path.exists())
+}
+
+/// The checked out commit of the git repository of the directory, and whether the
+/// directory has uncommitted changes.
+pub fn git_commit(dir: &Path) -> Option<(String, bool)> {
+    let git = |args: &[&str]| {
+        Command::new("git")
+            .args(args)
+            .current_dir(dir)
+            .output()
+            .ok()
+            .filter(|output| output.status.success())
+            .map(|output| String::from_utf8_lossy(&output.stdout).trim().to_string())
+    };
+    let commit = git(&["rev-parse", "HEAD"])?;
+    let dirty = !git(&["status", "--porcelain", "--", "."])?.is_empty();
+    Some((commit, dirty))
+}
+
+/// Format the time as an RFC 3339 UTC timestamp, e.g. `2023-11-14T22:13:20Z`.
+fn rfc3339(time: SystemTime) -> String {
+    let secs = time
+        .duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_secs())
+        .unwrap_or_default();
+    contract_transcode::rfc3339(secs * 1_000)
+}
+
+/// Verify the signature of a provenance statement and the digests of the artifacts it
+/// describes.
+#[derive(Debug, clap::Args)]
+#[clap(name = "verify-provenance")]
+pub struct VerifyProvenanceCommand {
+    /// The `<name>.provenance.json` file written by `build --provenance-suri`.
+    #[clap(value_parser)]
+    provenance: PathBuf,
+    /// The SS58 address of the key the statement must be signed with.
+    #[clap(long)]
+    signer: Option<AccountId32>,
+    /// The directory of the artifacts, defaults to the directory of the statement.
+    #[clap(long, value_parser)]
+    artifacts_dir: Option<PathBuf>,
+    /// Path to the `Cargo.toml` of the contract, to also verify the digest of its
+    /// source.
+    #[clap(long, value_parser)]
+    manifest_path: Option<PathBuf>,
+    /// Export the verification result in JSON format.
+    #[clap(long)]
+    output_json: bool,
+}
+
+/// The result of verifying a provenance statement.
+#[derive(Debug, serde::Serialize)]
+pub struct ProvenanceVerification {
+    /// `true` if the signer and all the digests match.
+    pub verified: bool,
+    /// The signers of the statement.
+    pub signers: Vec<String>,
+    /// The builder which produced the artifacts.
+    pub builder: Option<String>,
+    pub subjects: Vec<SubjectVerification>,
+    /// Whether the digest of the source matches, if `--manifest-path` is given.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub source_matches: Option<bool>,
+}
+
+/// The result of verifying the digest of an artifact.
+#[derive(Debug, serde::Serialize)]
+pub struct SubjectVerification {
+    pub name: String,
+    pub expected: String,
+    /// The digest of the artifact, `None` if it does not exist.
+    pub actual: Option<String>,
+}
+
+impl SubjectVerification {
+    fn matches(&self) -> bool {
+        self.actual.as_ref() == Some(&self.expected)
+    }
+}
+
+#[async_trait(?Send)]
+impl AsyncCommand for VerifyProvenanceCommand {
+    type Output = Result<()>;
+
+    async fn exec(&self) -> Result<()> {
+        let contents = fs::read_to_string(&self.provenance)
+            .context(format!("Failed to read {}", self.provenance.display()))?;
+        let envelope: Envelope =
+            serde_json::from_str(&contents).context("Invalid provenance envelope")?;
+        let (signers, statement) = envelope.verify()?;
+        if let Some(ref signer) = self.signer {
+            anyhow::ensure!(
+                signers.contains(signer),
+                "The statement is not signed by {signer}"
+            );
+        }
+
+        let artifacts_dir = match self.artifacts_dir {
+            Some(ref dir) => dir.clone(),
+            None => {
+                self.provenance
+                    .parent()
+                    .map(Path::to_path_buf)
+                    .unwrap_or_default()
+            }
+        };
+        let subjects = verify_subjects(&statement.subject, &artifacts_dir)?;
+        let source_matches = self
+            .manifest_path
+            .as_ref()
+            .map(|manifest_path| {
+                let manifest_path = ManifestPath::try_from(Some(manifest_path))?;
+                let actual = source_digest(&manifest_path.absolute_directory()?)?;
+                Ok::<_, anyhow::Error>(recorded_source_digest(&statement) == Some(actual))
+            })
+            .transpose()?;
+
+        let result = ProvenanceVerification {
+            verified: subjects.iter().all(SubjectVerification::matches)
+                && source_matches != Some(false),
+            signers: signers.iter().map(ToString::to_string).collect(),
+            builder: statement.predicate["runDetails"]["builder"]["id"]
+                .as_str()
+                .map(ToString::to_string),
+            subjects,
+            source_matches,
+        };
+
+        if self.output_json {
+            println!("{}", serde_json::to_string_pretty(&result)?);
+        } else {
+            name_value_println!(
+                "Signed by",
+                result.signers.join(", "),
+                DEFAULT_KEY_COL_WIDTH
+            );
+            if let Some(ref builder) = result.builder {
+                name_value_println!("Builder", builder, DEFAULT_KEY_COL_WIDTH);
+            }
+            for subject in &result.subjects {
+                let status = match subject.actual {
+                    _ if subject.matches() => "matches".green(),
+                    Some(_) => "digest mismatch".red(),
+                    None => "missing".red(),
+                };
+                name_value_println!(
+                    "Artifact",
+                    format!("{} {status}", subject.name),
+                    DEFAULT_KEY_COL_WIDTH
+                );
+            }
+            if let Some(matches) = result.source_matches {
+                let status = if matches {
+                    "matches".green()
+                } else {
+                    "digest mismatch".red()
+                };
+                name_value_println!("Source", status, DEFAULT_KEY_COL_WIDTH);
+            }
+        }
+        if !result.verified {
+            anyhow::bail!(
+                "Not verified: the artifacts do not match the provenance statement"
+            )
+        }
+        if !self.output_json {
+            println!(
+                "{}",
+                "Verified: the artifacts match the signed provenance statement"
+                    .green()
+                    .bold()
+            );
+        }
+        Ok(())
+    }
+}
+
+/// Compare the SHA-256 digests of the subjects to the artifacts in the directory.
+fn verify_subjects(subjects: &[Subject], dir: &Path) -> Result<Vec<SubjectVerification>> {
+    subjects
+        .iter()
+        .map(|subject| {
+            let expected = subject.digest.get("sha256").cloned().context(format!(
+                "No SHA-256 digest of the artifact `{}`",
+                subject.name
+            ))?;
+            let path = dir.join(&subject.name);
+            let actual = path.exists().then(|| file_digest(&path)).transpose()?;
+            Ok(SubjectVerification {
+                name: subject.name.clone(),
+                expected,
+                actual,
+            })
+        })
+        .collect()
+}
+
+/// The digest of the source recorded in the resolved dependencies of the provenance.
+fn recorded_source_digest(statement: &Statement) -> Option<String> {
+    statement.predicate["buildDefinition"]["resolvedDependencies"]
+        .as_array()?
+        .iter()
+        .find(|dependency| dependency["name"] == SOURCE_DEPENDENCY)?["digest"]["sha256"]
+        .as_str()
+        .map(ToString::to_string)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn statement(dir: &Path) -> Statement {
+        Statement {
+            statement_type: STATEMENT_TYPE.to_string(),
+            subject: vec![Subject::from_file(&dir.join("flipper.wasm")).unwrap()],
+            predicate_type: PREDICATE_TYPE.to_string(),
+            predicate: json!({
+                "buildDefinition": {
+                    "resolvedDependencies": [{
+                        "name": SOURCE_DEPENDENCY,
+                        "digest": { "sha256": source_digest(dir).unwrap() },
+                    }],
+                },
+            }),
+        }
+    }
+
+    #[test]
+    fn signed_statement_is_verified() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(dir.path().join("flipper.wasm"), b"\0asm").unwrap();
+        let statement = statement(dir.path());
+        let key = sr25519::Pair::from_string("//Alice", None).unwrap();
+
+        let envelope = Envelope::sign(&statement, &key).unwrap();
+        let (signers, verified) = envelope.verify().unwrap();
+        assert_eq!(signers, [AccountId32::from(key.public().0)]);
+        assert_eq!(verified, statement);
+        assert_eq!(
+            recorded_source_digest(&verified),
+            Some(source_digest(dir.path()).unwrap())
+        );
+
+        let subjects = verify_subjects(&verified.subject, dir.path()).unwrap();
+        assert!(subjects.iter().all(SubjectVerification::matches));
+        fs::write(dir.path().join("flipper.wasm"), b"\0asm\x01").unwrap();
+        let subjects = verify_subjects(&verified.subject, dir.path()).unwrap();
+        assert!(!subjects[0].matches());
+
+        let mut tampered = envelope;
+        tampered.payload = BASE64.encode(
+            serde_json::to_vec(&Statement {
+                subject: vec![],
+                ..verified
+            })
+            .unwrap(),
+        );
+        assert!(tampered.verify().is_err());
+    }
+
+    #[test]
+    fn source_digest_skips_build_output() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::create_dir_all(dir.path().join("src")).unwrap();
+        fs::write(dir.path().join("src/lib.rs"), "#![no_std]").unwrap();
+        let digest = source_digest(dir.path()).unwrap();
+
+        fs::create_dir_all(dir.path().join("target")).unwrap();
+        fs::write(dir.path().join("target/flipper.wasm"), b"\0asm").unwrap();
+        fs::write(dir.path().join(".gitignore"), "target").unwrap();
+        assert_eq!(source_digest(dir.path()).unwrap(), digest);
+
+        fs::write(dir.path().join("src/lib.rs"), "#![no_std] ").unwrap();
+        assert_ne!(source_digest(dir.path()).unwrap(), digest);
+    }
+
+    #[test]
+    fn timestamps_are_rfc3339() {
+        let at = |secs| rfc3339(UNIX_EPOCH + std::time::Duration::from_secs(secs));
+        assert_eq!(at(0), "1970-01-01T00:00:00Z");
+        assert_eq!(at(951_782_400), "2000-02-29T00:00:00Z");
+        assert_eq!(at(1_700_000_000), "2023-11-14T22:13:20Z");
+    }
+}
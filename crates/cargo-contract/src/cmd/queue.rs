@@ -0,0 +1,337 @@
+// Copyright 2018-2023 Parity Technologies (UK) Ltd.
+// This file is part of cargo-contract.
+//
+// cargo-contract is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// cargo-contract is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with cargo-contract.  If not, see <http://www.gnu.org/licenses/>.
+
+//! An on-disk queue of transactions, for submitting them once the node is reachable.
+//!
+//! A queued transaction is stored as the arguments of the extrinsic command submitting
+//! it, e.g. `call --contract <ADDRESS> --message flip`. The secret key URI is never
+//! written to the queue file, it is supplied when the queue is flushed with
+//! `cargo contract queue submit-all --suri <SURI>`.
+
+use super::lock::{
+    self,
+    FileLock,
+};
+use anyhow::{
+    Context,
+    Result,
+};
+use colored::Colorize;
+use std::{
+    future::Future,
+    path::{
+        Path,
+        PathBuf,
+    },
+};
+
+/// The default path of the queue file, relative to the current directory.
+pub const DEFAULT_QUEUE_FILE: &str = "cargo-contract-queue.json";
+
+/// The commands which can be queued.
+const QUEUEABLE_COMMANDS: [&str; 7] = [
+    "upload",
+    "instantiate",
+    "call",
+    "remove",
+    "transfer-code",
+    "upgrade",
+    "astar",
+];
+
+/// The arguments holding secrets, which must not be written to the queue file.
+const SECRET_ARGS: [&str; 4] = ["--suri", "-s", "--password", "-p"];
+
+/// The arguments added when the queue is flushed, which are dropped when queueing.
+pub(crate) const SUBMIT_ARGS: [&str; 5] =
+    ["--execute", "-x", "--skip-confirm", "--yes", "-y"];
+
+#[derive(Debug, clap::Args)]
+#[clap(name = "queue", about = "Queue transactions and submit them later")]
+pub struct QueueCommand {
+    #[clap(subcommand)]
+    action: QueueAction,
+    /// Path to the queue file.
+    #[clap(long, env = "CARGO_CONTRACT_QUEUE", default_value = DEFAULT_QUEUE_FILE)]
+    queue_file: PathBuf,
+}
+
+#[derive(Debug, clap::Subcommand)]
+enum QueueAction {
+    /// Add a transaction to the queue, given as the arguments of an `upload`,
+    /// `instantiate`, `call`, `remove` or `astar` command without `--suri`
+    Add {
+        #[clap(required = true, trailing_var_arg = true, allow_hyphen_values = true)]
+        args: Vec<String>,
+    },
+    /// List the queued transactions
+    List,
+    /// Remove a transaction from the queue
+    Remove {
+        /// The id of the queued transaction.
+        id: u64,
+    },
+    /// Submit all queued transactions in order, stopping at the first failure
+    SubmitAll {
+        /// Secret key URI for the account submitting the transactions.
+        #[clap(long, short)]
+        suri: String,
+        /// Password for the secret key.
+        #[clap(long, short)]
+        password: Option<String>,
+    },
+}
+
+/// A transaction in the queue.
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct QueuedTransaction {
+    /// The id of the transaction in the queue.
+    pub id: u64,
+    /// The arguments of the command submitting the transaction.
+    pub args: Vec<String>,
+}
+
+/// The transactions waiting for submission, in order.
+#[derive(Debug, Default, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct Queue {
+    transactions: Vec<QueuedTransaction>,
+}
+
+impl Queue {
+    /// Load the queue from the file at the given path, the queue is empty if there is no
+    /// such file.
+    pub fn load(path: &Path) -> Result<Self> {
+        if !path.exists() {
+            return Ok(Self::default())
+        }
+        let contents = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read {}", path.display()))?;
+        serde_json::from_str(&contents)
+            .with_context(|| format!("Invalid queue file {}", path.display()))
+    }
+
+    /// Write the queue to the file at the given path.
+    pub fn save(&self, path: &Path) -> Result<()> {
+        let contents = serde_json::to_string_pretty(self)?;
+        lock::write_atomic(path, contents)
+    }
+
+    /// Add a transaction with the arguments of the command submitting it, returns its
+    /// id.
+    pub fn push(&mut self, args: Vec<String>) -> Result<u64> {
+        let command = args.first().context("No command was supplied")?;
+        if !QUEUEABLE_COMMANDS.contains(&command.as_str()) {
+            anyhow::bail!(
+                "The `{command}` command can not be queued, only {} can",
+                QUEUEABLE_COMMANDS.join(", ")
+            )
+        }
+        if let Some(secret) = args.iter().find(|arg| is_secret_arg(arg)) {
+            anyhow::bail!(
+                "`{secret}` must not be queued, supply it to `queue submit-all` instead"
+            )
+        }
+        let args = args
+            .into_iter()
+            .filter(|arg| !SUBMIT_ARGS.contains(&arg.as_str()))
+            .collect();
+        let id = self.transactions.iter().map(|tx| tx.id).max().unwrap_or(0) + 1;
+        self.transactions.push(QueuedTransaction { id, args });
+        Ok(id)
+    }
+
+    /// Remove the transaction with the given id.
+    pub fn remove(&mut self, id: u64) -> Result<QueuedTransaction> {
+        let index = self
+            .transactions
+            .iter()
+            .position(|tx| tx.id == id)
+            .with_context(|| format!("No queued transaction with id {id}"))?;
+        Ok(self.transactions.remove(index))
+    }
+
+    /// The queued transactions, in order.
+    pub fn transactions(&self) -> &[QueuedTransaction] {
+        &self.transactions
+    }
+}
+
+/// Returns `true` if the argument passes a secret, e.g. `--suri` or `--suri=//Alice`.
+pub(crate) fn is_secret_arg(arg: &str) -> bool {
+    let name = arg.split('=').next().unwrap_or(arg);
+    SECRET_ARGS.contains(&name)
+}
+
+impl QueueCommand {
+    /// Run the queue action, `submit` executes the command with the given arguments.
+    pub async fn run<F, Fut>(&self, mut submit: F) -> Result<()>
+    where
+        F: FnMut(Vec<String>) -> Fut,
+        Fut: Future<Output = Result<()>>,
+    {
+        // Held until the queue is flushed, so that parallel flushes do not submit the
+        // same transactions.
+        let _lock = FileLock::acquire(&self.queue_file)?;
+        let mut queue = Queue::load(&self.queue_file)?;
+        match self.action {
+            QueueAction::Add { ref args } => {
+                let id = queue.push(args.clone())?;
+                queue.save(&self.queue_file)?;
+                println!("Queued transaction #{id}");
+            }
+            QueueAction::List => {
+                for tx in queue.transactions() {
+                    println!("#{} {}", tx.id, tx.args.join(" "));
+                }
+            }
+            QueueAction::Remove { id } => {
+                queue.remove(id)?;
+                queue.save(&self.queue_file)?;
+                println!("Removed transaction #{id}");
+            }
+            QueueAction::SubmitAll {
+                ref suri,
+                ref password,
+            } => {
+                while let Some(tx) = queue.transactions().first().cloned() {
+                    println!(
+                        "{} transaction #{}: {}",
+                        "Submitting".green().bold(),
+                        tx.id,
+                        tx.args.join(" ")
+                    );
+                    let mut args = tx.args.clone();
+                    args.extend(
+                        ["--execute", "--skip-confirm", "--suri", suri].map(String::from),
+                    );
+                    if let Some(password) = password {
+                        args.extend(["--password".to_string(), password.clone()]);
+                    }
+                    submit(args).await.with_context(|| {
+                        format!(
+                            "Failed to submit transaction #{}, it and the following \
+                            transactions remain queued",
+                            tx.id
+                        )
+                    })?;
+                    // Persist the progress, so that a later failure does not resubmit it.
+                    queue.remove(tx.id)?;
+                    queue.save(&self.queue_file)?;
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(args: &str) -> Vec<String> {
+        args.split_whitespace().map(String::from).collect()
+    }
+
+    #[test]
+    fn transactions_are_queued_in_order() {
+        let mut queue = Queue::default();
+        assert_eq!(queue.push(args("upload -x")).unwrap(), 1);
+        assert_eq!(
+            queue
+                .push(args("call --contract 5A --message flip --skip-confirm"))
+                .unwrap(),
+            2
+        );
+        queue.remove(1).unwrap();
+        assert_eq!(queue.push(args("remove")).unwrap(), 3);
+
+        assert_eq!(
+            queue.transactions(),
+            &[
+                QueuedTransaction {
+                    id: 2,
+                    args: args("call --contract 5A --message flip"),
+                },
+                QueuedTransaction {
+                    id: 3,
+                    args: args("remove"),
+                },
+            ]
+        );
+        assert!(queue.remove(1).is_err());
+    }
+
+    #[test]
+    fn secrets_and_other_commands_are_rejected() {
+        let mut queue = Queue::default();
+        assert!(queue.push(args("upload --suri //Alice")).is_err());
+        assert!(queue.push(args("upload --password=secret")).is_err());
+        assert!(queue.push(args("build --release")).is_err());
+        assert!(queue.push(Vec::new()).is_err());
+        assert!(queue.transactions().is_empty());
+    }
+
+    #[test]
+    fn queue_is_persisted() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join(DEFAULT_QUEUE_FILE);
+        assert_eq!(Queue::load(&path).unwrap(), Queue::default());
+
+        let mut queue = Queue::default();
+        queue.push(args("upload")).unwrap();
+        queue.save(&path).unwrap();
+        assert_eq!(Queue::load(&path).unwrap(), queue);
+    }
+
+    #[tokio::test]
+    async fn submit_all_stops_at_first_failure() {
+        let dir = tempfile::tempdir().unwrap();
+        let queue_file = dir.path().join(DEFAULT_QUEUE_FILE);
+        let mut queue = Queue::default();
+        queue.push(args("upload")).unwrap();
+        queue.push(args("call --message fail")).unwrap();
+        queue.push(args("remove")).unwrap();
+        queue.save(&queue_file).unwrap();
+
+        let cmd = QueueCommand {
+            action: QueueAction::SubmitAll {
+                suri: String::from("//Alice"),
+                password: None,
+            },
+            queue_file: queue_file.clone(),
+        };
+        let mut submitted = Vec::new();
+        let result = cmd
+            .run(|args| {
+                submitted.push(args.clone());
+                async move {
+                    anyhow::ensure!(!args.contains(&String::from("fail")), "call failed");
+                    Ok(())
+                }
+            })
+            .await;
+
+        assert!(result.is_err());
+        assert_eq!(
+            submitted[0],
+            args("upload --execute --skip-confirm --suri //Alice")
+        );
+        assert_eq!(submitted.len(), 2);
+        let remaining = Queue::load(&queue_file).unwrap();
+        let ids: Vec<_> = remaining.transactions().iter().map(|tx| tx.id).collect();
+        assert_eq!(ids, [2, 3]);
+    }
+}
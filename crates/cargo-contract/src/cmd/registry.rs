@@ -0,0 +1,521 @@
+// Copyright 2018-2023 Parity Technologies (UK) Ltd.
+// This file is part of cargo-contract.
+//
+// cargo-contract is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// cargo-contract is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with cargo-contract.  If not, see <http://www.gnu.org/licenses/>.
+
+//! The local registry of uploaded code and instantiation salts, stored in the
+//! `cargo-contract-registry.json` file of the current directory, or in the file at the
+//! path in the `CARGO_CONTRACT_REGISTRY` environment variable.
+//!
+//! The registry is meant to be checked in with the project, so that the history of the
+//! deployments is shared by the team. Updates hold a lock on the file, so that parallel
+//! jobs in the same workspace do not lose each other's records.
+
+use super::{
+    lock::{
+        self,
+        FileLock,
+    },
+    CodeHash,
+    DefaultConfig,
+};
+use anyhow::{
+    Context,
+    Result,
+};
+use sp_core::Bytes;
+use std::{
+    collections::BTreeMap,
+    path::{
+        Path,
+        PathBuf,
+    },
+};
+use subxt::Config;
+
+type AccountId = <DefaultConfig as Config>::AccountId;
+
+/// The default file name of the registry, relative to the current directory.
+pub const REGISTRY_FILE_NAME: &str = "cargo-contract-registry.json";
+
+/// The environment variable overriding the path of the registry file.
+pub const REGISTRY_PATH_ENV: &str = "CARGO_CONTRACT_REGISTRY";
+
+/// The contents of the local registry.
+#[derive(Debug, Default, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct Registry {
+    /// The uploaded code, per network.
+    #[serde(default)]
+    pub codes: Vec<CodeRecord>,
+    /// The salts used to instantiate contracts, per network, deployer and code.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub salts: Vec<SaltRecord>,
+    /// The changes of the code of contracts, per network and contract.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub upgrades: Vec<UpgradeRecord>,
+}
+
+/// The record of code uploaded to a network.
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct CodeRecord {
+    /// The hash of the code.
+    pub code_hash: CodeHash,
+    /// The URL of the node of the network the code is uploaded to.
+    pub network: String,
+    /// The current owner of the code.
+    pub owner: AccountId,
+    /// The ownership transfers of the code, oldest first.
+    #[serde(default)]
+    pub transfers: Vec<OwnershipTransfer>,
+    /// The path of the metadata of the code, used by `new-client`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub metadata: Option<PathBuf>,
+    /// The attestations of the metadata and source of the code, oldest first.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub attestations: Vec<Attestation>,
+}
+
+/// The transfer of the ownership of code between two accounts.
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct OwnershipTransfer {
+    /// The previous owner.
+    pub from: AccountId,
+    /// The new owner.
+    pub to: AccountId,
+    /// The hash of the block the transfer was recorded in on-chain.
+    pub block_hash: <DefaultConfig as Config>::Hash,
+}
+
+/// The attestation of the metadata and source the code was built from, recorded on-chain
+/// with a `System::remark_with_event` of the attester.
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct Attestation {
+    /// The account which attested the code.
+    pub attester: AccountId,
+    /// The hash of the metadata of the code.
+    pub metadata_hash: <DefaultConfig as Config>::Hash,
+    /// The content identifier of the source code, e.g. an IPFS CID.
+    pub source_cid: String,
+    /// The hash of the block the attestation was recorded in on-chain.
+    pub block_hash: <DefaultConfig as Config>::Hash,
+}
+
+/// The salt of a contract instantiated by a deployer.
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct SaltRecord {
+    /// The hash of the code the contract was instantiated from.
+    pub code_hash: CodeHash,
+    /// The URL of the node of the network the contract is instantiated on.
+    pub network: String,
+    /// The account which instantiated the contract.
+    pub deployer: AccountId,
+    /// The salt used in the address derivation.
+    pub salt: Bytes,
+    /// The address of the instantiated contract.
+    pub contract: AccountId,
+}
+
+/// The change of the code of a contract, e.g. by `upgrade`.
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct UpgradeRecord {
+    /// The upgraded contract.
+    pub contract: AccountId,
+    /// The URL of the node of the network the contract is upgraded on.
+    pub network: String,
+    /// The hash of the code of the contract from the upgrade on.
+    pub code_hash: CodeHash,
+    /// The number of the block the upgrade was included in.
+    pub block_number: u64,
+}
+
+impl Registry {
+    /// Returns the path of the registry file.
+    pub fn path() -> Result<PathBuf> {
+        match std::env::var_os(REGISTRY_PATH_ENV) {
+            Some(path) => Ok(PathBuf::from(path)),
+            None => Ok(std::env::current_dir()?.join(REGISTRY_FILE_NAME)),
+        }
+    }
+
+    /// Load the registry, returns an empty registry if there is no registry file yet.
+    pub fn load() -> Result<Self> {
+        Self::load_from(&Self::path()?)
+    }
+
+    /// Load the registry from the file at the given path.
+    pub fn load_from(path: &Path) -> Result<Self> {
+        if !path.exists() {
+            return Ok(Self::default())
+        }
+        let contents = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read {}", path.display()))?;
+        serde_json::from_str(&contents)
+            .with_context(|| format!("Invalid registry file {}", path.display()))
+    }
+
+    /// Apply the update to the registry file, holding a lock on it.
+    pub fn update(update: impl FnOnce(&mut Self)) -> Result<()> {
+        Self::update_at(&Self::path()?, update)
+    }
+
+    /// Apply the update to the registry in the file at the given path, holding a lock
+    /// on it.
+    pub fn update_at(path: &Path, update: impl FnOnce(&mut Self)) -> Result<()> {
+        let _lock = FileLock::acquire(path)?;
+        let mut registry = Self::load_from(path)?;
+        update(&mut registry);
+        registry.save_to(path)
+    }
+
+    /// Write the registry to the file at the given path.
+    fn save_to(&self, path: &Path) -> Result<()> {
+        let contents = serde_json::to_string_pretty(self)?;
+        lock::write_atomic(path, contents)
+    }
+
+    /// Returns the record of the code uploaded to the network.
+    pub fn code(&self, code_hash: &CodeHash, network: &str) -> Option<&CodeRecord> {
+        self.codes
+            .iter()
+            .find(|code| &code.code_hash == code_hash && code.network == network)
+    }
+
+    /// Returns the networks the code is recorded on, with the account which uploaded it:
+    /// the owner of the code record, or else the deployer of a contract instantiated
+    /// from it.
+    pub fn code_networks(&self, code_hash: &CodeHash) -> BTreeMap<&str, &AccountId> {
+        let codes = self
+            .codes
+            .iter()
+            .filter(|code| &code.code_hash == code_hash);
+        let mut networks: BTreeMap<_, _> = codes
+            .map(|code| (code.network.as_str(), &code.owner))
+            .collect();
+        let salts = self
+            .salts
+            .iter()
+            .filter(|salt| &salt.code_hash == code_hash);
+        for salt in salts {
+            networks
+                .entry(salt.network.as_str())
+                .or_insert(&salt.deployer);
+        }
+        networks
+    }
+
+    /// Returns `true` if the account is tracked by the registry: it owned, attested or
+    /// instantiated code recorded in it.
+    pub fn is_tracked(&self, account: &AccountId) -> bool {
+        self.codes.iter().any(|code| {
+            &code.owner == account
+                || code
+                    .transfers
+                    .iter()
+                    .any(|transfer| &transfer.from == account || &transfer.to == account)
+                || code
+                    .attestations
+                    .iter()
+                    .any(|attestation| &attestation.attester == account)
+        }) || self.salts.iter().any(|salt| &salt.deployer == account)
+    }
+
+    /// Returns the hashes of the code versions of the contract: the code of the contract
+    /// at the block, by default the latest block, followed by the other versions from the
+    /// newest to the oldest. The first version is the code the contract was instantiated
+    /// from, the others are the recorded upgrades.
+    ///
+    /// The address of a contract is derived from the hash of its code, hence the lookup
+    /// does not depend on the network.
+    pub fn contract_code_hashes(
+        &self,
+        contract: &AccountId,
+        block_number: Option<u64>,
+    ) -> Vec<CodeHash> {
+        let mut upgrades = self
+            .upgrades
+            .iter()
+            .filter(|upgrade| &upgrade.contract == contract)
+            .collect::<Vec<_>>();
+        upgrades.sort_by_key(|upgrade| upgrade.block_number);
+        let instantiated = self
+            .salts
+            .iter()
+            .find(|salt| &salt.contract == contract)
+            .map(|salt| salt.code_hash);
+        let at_block = upgrades
+            .iter()
+            .rev()
+            .find(|upgrade| block_number.map_or(true, |n| upgrade.block_number <= n))
+            .map(|upgrade| upgrade.code_hash)
+            .or(instantiated);
+        let mut code_hashes = Vec::new();
+        let versions = upgrades.iter().rev().map(|upgrade| upgrade.code_hash);
+        for code_hash in at_block.into_iter().chain(versions).chain(instantiated) {
+            if !code_hashes.contains(&code_hash) {
+                code_hashes.push(code_hash);
+            }
+        }
+        code_hashes
+    }
+
+    /// Returns the path of the metadata recorded for the code on any network.
+    pub fn code_metadata(&self, code_hash: &CodeHash) -> Option<&Path> {
+        self.codes
+            .iter()
+            .filter(|code| &code.code_hash == code_hash)
+            .find_map(|code| code.metadata.as_deref())
+    }
+
+    /// Returns the salts used by the deployer to instantiate the code on the network.
+    pub fn salts<'a>(
+        &'a self,
+        code_hash: &'a CodeHash,
+        network: &'a str,
+        deployer: &'a AccountId,
+    ) -> impl Iterator<Item = &'a SaltRecord> {
+        self.salts.iter().filter(move |salt| {
+            &salt.code_hash == code_hash
+                && salt.network == network
+                && &salt.deployer == deployer
+        })
+    }
+
+    /// Record the change of the code of a contract.
+    pub fn record_upgrade(&mut self, record: UpgradeRecord) {
+        if !self.upgrades.contains(&record) {
+            self.upgrades.push(record);
+        }
+    }
+
+    /// Record the salt of an instantiated contract.
+    pub fn record_salt(&mut self, record: SaltRecord) {
+        self.salts.retain(|salt| salt.contract != record.contract);
+        self.salts.push(record);
+    }
+
+    /// Record the transfer of the ownership of the code on the network, creating the
+    /// record of the code if it does not exist yet.
+    pub fn transfer_code_ownership(
+        &mut self,
+        code_hash: CodeHash,
+        network: &str,
+        transfer: OwnershipTransfer,
+    ) {
+        match self
+            .codes
+            .iter_mut()
+            .find(|code| code.code_hash == code_hash && code.network == network)
+        {
+            Some(code) => {
+                code.owner = transfer.to.clone();
+                code.transfers.push(transfer);
+            }
+            None => {
+                self.codes.push(CodeRecord {
+                    code_hash,
+                    network: network.to_string(),
+                    owner: transfer.to.clone(),
+                    transfers: vec![transfer],
+                    metadata: None,
+                    attestations: Vec::new(),
+                })
+            }
+        }
+    }
+
+    /// Record the attestation of the code on the network, creating the record of the
+    /// code with the given owner if it does not exist yet.
+    pub fn attest_code(
+        &mut self,
+        code_hash: CodeHash,
+        network: &str,
+        owner: &AccountId,
+        attestation: Attestation,
+    ) {
+        match self
+            .codes
+            .iter_mut()
+            .find(|code| code.code_hash == code_hash && code.network == network)
+        {
+            Some(code) => code.attestations.push(attestation),
+            None => {
+                self.codes.push(CodeRecord {
+                    code_hash,
+                    network: network.to_string(),
+                    owner: owner.clone(),
+                    transfers: Vec::new(),
+                    metadata: None,
+                    attestations: vec![attestation],
+                })
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+
+    const NETWORK: &str = "ws://localhost:9944/";
+
+    fn account(ss58: &str) -> AccountId {
+        AccountId::from_str(ss58).unwrap()
+    }
+
+    #[test]
+    fn ownership_transfers_are_recorded() {
+        let alice = account("5GrwvaEF5zXb26Fz9rcQpDWS57CtERHpNehXCPcNoHGKutQY");
+        let bob = account("5FHneW46xGXgs5mUiveU4sbTyGBzmstUspZC92UhjJM694ty");
+        let code_hash = CodeHash::repeat_byte(1);
+        let mut registry = Registry::default();
+
+        for (from, to) in [(&alice, &bob), (&bob, &alice)] {
+            registry.transfer_code_ownership(
+                code_hash,
+                NETWORK,
+                OwnershipTransfer {
+                    from: from.clone(),
+                    to: to.clone(),
+                    block_hash: Default::default(),
+                },
+            );
+        }
+
+        let code = registry.code(&code_hash, NETWORK).unwrap();
+        assert_eq!(code.owner, alice);
+        assert_eq!(code.transfers.len(), 2);
+        assert_eq!(code.transfers[0].to, bob);
+        assert!(registry.code(&code_hash, "ws://other:9944/").is_none());
+    }
+
+    #[test]
+    fn code_is_found_on_every_network() {
+        let alice = account("5GrwvaEF5zXb26Fz9rcQpDWS57CtERHpNehXCPcNoHGKutQY");
+        let bob = account("5FHneW46xGXgs5mUiveU4sbTyGBzmstUspZC92UhjJM694ty");
+        let code_hash = CodeHash::repeat_byte(1);
+        let mut registry = Registry::default();
+        assert!(registry.code_networks(&code_hash).is_empty());
+
+        registry.codes.push(CodeRecord {
+            code_hash,
+            network: NETWORK.to_string(),
+            owner: alice.clone(),
+            transfers: Vec::new(),
+            metadata: None,
+            attestations: Vec::new(),
+        });
+        for (i, network) in [NETWORK, "ws://other:9944/"].into_iter().enumerate() {
+            registry.record_salt(SaltRecord {
+                code_hash,
+                network: network.to_string(),
+                deployer: bob.clone(),
+                salt: Bytes(vec![i as u8]),
+                contract: AccountId::from([i as u8 + 1; 32]),
+            });
+        }
+
+        let networks = registry.code_networks(&code_hash);
+        assert_eq!(networks.len(), 2);
+        assert_eq!(networks[NETWORK], &alice);
+        assert_eq!(networks["ws://other:9944/"], &bob);
+        assert!(registry.is_tracked(&alice));
+        assert!(registry.is_tracked(&bob));
+        assert!(!registry.is_tracked(&AccountId::from([0u8; 32])));
+    }
+
+    #[test]
+    fn contract_metadata_is_found_by_the_salt_of_the_contract() {
+        let alice = account("5GrwvaEF5zXb26Fz9rcQpDWS57CtERHpNehXCPcNoHGKutQY");
+        let contract = account("5FHneW46xGXgs5mUiveU4sbTyGBzmstUspZC92UhjJM694ty");
+        let code_hash = CodeHash::repeat_byte(1);
+        let mut registry = Registry::default();
+        registry.record_salt(SaltRecord {
+            code_hash,
+            network: NETWORK.to_string(),
+            deployer: alice.clone(),
+            salt: Bytes(vec![1]),
+            contract: contract.clone(),
+        });
+        assert_eq!(
+            registry.contract_code_hashes(&contract, None),
+            vec![code_hash]
+        );
+        assert_eq!(registry.code_metadata(&code_hash), None);
+
+        registry.codes.push(CodeRecord {
+            code_hash,
+            network: "ws://other:9944/".to_string(),
+            owner: alice.clone(),
+            transfers: Vec::new(),
+            metadata: Some(PathBuf::from("flipper.json")),
+            attestations: Vec::new(),
+        });
+        assert_eq!(
+            registry.code_metadata(&code_hash),
+            Some(Path::new("flipper.json"))
+        );
+        assert!(registry.contract_code_hashes(&alice, None).is_empty());
+    }
+
+    #[test]
+    fn code_versions_are_selected_by_block() {
+        let contract = account("5FHneW46xGXgs5mUiveU4sbTyGBzmstUspZC92UhjJM694ty");
+        let [v1, v2, v3] = [1, 2, 3].map(CodeHash::repeat_byte);
+        let mut registry = Registry::default();
+        registry.record_salt(SaltRecord {
+            code_hash: v1,
+            network: NETWORK.to_string(),
+            deployer: contract.clone(),
+            salt: Bytes(vec![1]),
+            contract: contract.clone(),
+        });
+        for (code_hash, block_number) in [(v3, 200), (v2, 100), (v3, 200)] {
+            registry.record_upgrade(UpgradeRecord {
+                contract: contract.clone(),
+                network: NETWORK.to_string(),
+                code_hash,
+                block_number,
+            });
+        }
+        assert_eq!(registry.upgrades.len(), 2);
+
+        let versions = |block| registry.contract_code_hashes(&contract, block);
+        assert_eq!(versions(None), vec![v3, v2, v1]);
+        assert_eq!(versions(Some(250)), vec![v3, v2, v1]);
+        assert_eq!(versions(Some(150)), vec![v2, v3, v1]);
+        assert_eq!(versions(Some(100)), vec![v2, v3, v1]);
+        assert_eq!(versions(Some(50)), vec![v1, v3, v2]);
+    }
+
+    #[test]
+    fn registry_is_persisted() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join(REGISTRY_FILE_NAME);
+        assert_eq!(Registry::load_from(&path).unwrap(), Registry::default());
+
+        let mut registry = Registry::default();
+        registry.transfer_code_ownership(
+            CodeHash::repeat_byte(1),
+            NETWORK,
+            OwnershipTransfer {
+                from: account("5GrwvaEF5zXb26Fz9rcQpDWS57CtERHpNehXCPcNoHGKutQY"),
+                to: account("5FHneW46xGXgs5mUiveU4sbTyGBzmstUspZC92UhjJM694ty"),
+                block_hash: Default::default(),
+            },
+        );
+        registry.save_to(&path).unwrap();
+        assert_eq!(Registry::load_from(&path).unwrap(), registry);
+    }
+}
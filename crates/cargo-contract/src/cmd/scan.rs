@@ -0,0 +1,291 @@
+// Copyright 2018-2023 Parity Technologies (UK) Ltd.
+// This file is part of cargo-contract.
+//
+// cargo-contract is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// cargo-contract is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with cargo-contract.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Paged iteration over the storage entries of the chain.
+//!
+//! Scanning a storage map of a large chain takes many RPC requests. The keys are fetched
+//! a page at a time and the values of a page are fetched by concurrent requests, limited
+//! to a configurable number of requests per second so that public RPC endpoints are not
+//! overloaded. All pages are read at the block the scan started at, and an interrupted
+//! scan can be resumed after the last key it processed with `--cursor`, or from the
+//! [`ScanPosition`] recorded in a checkpoint.
+
+use super::{
+    pacing::{
+        self,
+        RateLimiter,
+    },
+    Client,
+    DefaultConfig,
+};
+use anyhow::{
+    Context,
+    Result,
+};
+use scale::Decode;
+use std::collections::HashMap;
+use subxt::{
+    dynamic::DecodedValueThunk,
+    ext::frame_metadata::StorageEntryType,
+    metadata::DecodeWithMetadata,
+    Config,
+};
+
+type AccountId = <DefaultConfig as Config>::AccountId;
+type Hash = <DefaultConfig as Config>::Hash;
+
+/// The default number of keys fetched per page.
+pub const DEFAULT_PAGE_SIZE: u32 = 100;
+
+/// The default number of concurrent requests fetching the values of a page.
+pub const DEFAULT_CONCURRENCY: usize = 4;
+
+/// Options for scanning the storage of the chain.
+#[derive(Debug, Clone, clap::Args)]
+pub struct ScanOpts {
+    /// The number of storage keys fetched per RPC request when scanning storage.
+    #[clap(long, default_value_t = DEFAULT_PAGE_SIZE, value_parser = clap::value_parser!(u32).range(1..))]
+    page_size: u32,
+    /// The maximum number of concurrent RPC requests when scanning storage.
+    #[clap(long, default_value_t = DEFAULT_CONCURRENCY, value_parser = parse_concurrency)]
+    concurrency: usize,
+    /// The maximum number of RPC requests per second when scanning storage, unlimited by
+    /// default.
+    #[clap(long, value_name = "REQUESTS", value_parser = clap::value_parser!(u32).range(1..))]
+    rate_limit: Option<u32>,
+    /// Pace all requests conservatively and fetch each page with a single request, for
+    /// community RPC providers banning clients sending bursts of requests.
+    #[clap(long)]
+    public_endpoint: bool,
+    /// Resume an interrupted storage scan after the given storage key.
+    #[clap(long, value_name = "KEY", value_parser = parse_cursor)]
+    cursor: Option<Vec<u8>>,
+}
+
+impl Default for ScanOpts {
+    fn default() -> Self {
+        Self {
+            page_size: DEFAULT_PAGE_SIZE,
+            concurrency: DEFAULT_CONCURRENCY,
+            rate_limit: None,
+            public_endpoint: false,
+            cursor: None,
+        }
+    }
+}
+
+fn parse_concurrency(input: &str) -> Result<usize> {
+    let concurrency = input.parse()?;
+    anyhow::ensure!(concurrency > 0, "The concurrency must be at least 1");
+    Ok(concurrency)
+}
+
+fn parse_cursor(input: &str) -> Result<Vec<u8>> {
+    hex::decode(input.trim_start_matches("0x")).context("The cursor must be hex encoded")
+}
+
+/// A storage entry found by a scan.
+pub struct ScannedEntry {
+    /// The full storage key of the entry.
+    pub key: Vec<u8>,
+    /// The value of the entry, decoded lazily with the metadata of the chain.
+    pub value: DecodedValueThunk,
+}
+
+/// Decode the account a storage entry is stored under, the keys of maps hashed with
+/// `Twox64Concat` end with the encoded account.
+pub fn account_of_key(key: &[u8]) -> Result<AccountId> {
+    let start = key
+        .len()
+        .checked_sub(32)
+        .context("The storage key is too short for an account")?;
+    Ok(AccountId::decode(&mut &key[start..])?)
+}
+
+/// The position of a scan: the block it reads and the last key it processed.
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct ScanPosition {
+    pub block_hash: Hash,
+    pub cursor: Option<sp_core::Bytes>,
+}
+
+/// A paged scan over the entries of a storage map.
+pub struct StorageScan {
+    client: Client,
+    prefix: Vec<u8>,
+    value_type: u32,
+    block_hash: Hash,
+    page_size: u32,
+    concurrency: usize,
+    rate_limiter: RateLimiter,
+    cursor: Option<Vec<u8>>,
+    done: bool,
+}
+
+impl StorageScan {
+    /// Start a scan over the entries of the storage map of the pallet, at the latest
+    /// block.
+    pub async fn new(
+        client: &Client,
+        pallet: &str,
+        entry: &str,
+        opts: &ScanOpts,
+    ) -> Result<Self> {
+        let metadata = client.metadata();
+        let value_type = match metadata.pallet(pallet)?.storage(entry)?.ty {
+            StorageEntryType::Plain(ref ty) => ty.id,
+            StorageEntryType::Map { ref value, .. } => value.id,
+        };
+        let prefix = subxt::dynamic::storage_root(pallet, entry).to_root_bytes();
+        if opts.public_endpoint {
+            pacing::enable_public_endpoint();
+        }
+        let concurrency = if pacing::public_endpoint() {
+            1
+        } else {
+            opts.concurrency
+        };
+        pacing::pace().await;
+        let block_hash = client.blocks().at_latest().await?.hash();
+        Ok(Self {
+            client: client.clone(),
+            prefix,
+            value_type,
+            block_hash,
+            page_size: opts.page_size,
+            concurrency,
+            rate_limiter: RateLimiter::new(opts.rate_limit),
+            cursor: opts.cursor.clone(),
+            done: false,
+        })
+    }
+
+    /// The position of the scan after the pages returned so far.
+    pub fn position(&self) -> ScanPosition {
+        ScanPosition {
+            block_hash: self.block_hash,
+            cursor: self.cursor.clone().map(sp_core::Bytes),
+        }
+    }
+
+    /// Continue the scan from the position of a previous scan, reading the block that
+    /// scan started at.
+    pub fn resume_at(&mut self, position: ScanPosition) {
+        self.block_hash = position.block_hash;
+        self.cursor = position.cursor.map(|cursor| cursor.0);
+    }
+
+    /// Fetch the next page of entries, returns `None` once all entries were returned.
+    pub async fn next_page(&mut self) -> Result<Option<Vec<ScannedEntry>>> {
+        if self.done {
+            return Ok(None)
+        }
+        self.fetch_page().await.with_context(|| {
+            match self.cursor {
+                Some(ref cursor) => {
+                    format!(
+                        "Storage scan interrupted, resume it with `--cursor 0x{}`",
+                        hex::encode(cursor)
+                    )
+                }
+                None => String::from("Storage scan interrupted"),
+            }
+        })
+    }
+
+    async fn fetch_page(&mut self) -> Result<Option<Vec<ScannedEntry>>> {
+        self.rate_limiter.wait().await;
+        let keys = self
+            .client
+            .rpc()
+            .storage_keys_paged(
+                &self.prefix,
+                self.page_size,
+                self.cursor.as_deref(),
+                Some(self.block_hash),
+            )
+            .await?;
+        if keys.len() < self.page_size as usize {
+            self.done = true;
+        }
+        let Some(last_key) = keys.last() else {
+            return Ok(None)
+        };
+        let last_key = last_key.0.clone();
+
+        let keys: Vec<Vec<u8>> = keys.into_iter().map(|key| key.0).collect();
+        let chunk_size = keys.len().div_ceil(self.concurrency);
+        let mut requests = Vec::new();
+        for chunk in keys.chunks(chunk_size) {
+            self.rate_limiter.wait().await;
+            let client = self.client.clone();
+            let chunk = chunk.to_vec();
+            let block_hash = self.block_hash;
+            requests.push(tokio::spawn(async move {
+                client
+                    .rpc()
+                    .query_storage_at(chunk.iter().map(Vec::as_slice), Some(block_hash))
+                    .await
+            }));
+        }
+        let mut values = HashMap::new();
+        for request in requests {
+            for change_set in request.await?? {
+                for (key, value) in change_set.changes {
+                    if let Some(value) = value {
+                        values.insert(key.0, value.0);
+                    }
+                }
+            }
+        }
+
+        let metadata = self.client.metadata();
+        let mut entries = Vec::new();
+        for key in keys {
+            // Entries removed since the keys were fetched have no value.
+            if let Some(value) = values.remove(&key) {
+                let value = DecodedValueThunk::decode_with_metadata(
+                    &mut &value[..],
+                    self.value_type,
+                    &metadata,
+                )?;
+                entries.push(ScannedEntry { key, value });
+            }
+        }
+        self.cursor = Some(last_key);
+        Ok(Some(entries))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn cursor_is_parsed_as_hex() {
+        assert_eq!(parse_cursor("0x00ff").unwrap(), vec![0x00, 0xff]);
+        assert_eq!(parse_cursor("00ff").unwrap(), vec![0x00, 0xff]);
+        assert!(parse_cursor("0xzz").is_err());
+        assert!(parse_concurrency("0").is_err());
+    }
+
+    #[test]
+    fn accounts_are_decoded_from_the_end_of_the_key() {
+        let key = [&[0xaa; 32][..], &[0xbb; 8], &[7; 32]].concat();
+        assert_eq!(account_of_key(&key).unwrap(), AccountId::from([7; 32]));
+        assert!(account_of_key(&[7; 31]).is_err());
+    }
+}
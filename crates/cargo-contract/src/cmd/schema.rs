@@ -0,0 +1,449 @@
+// Copyright 2018-2023 Parity Technologies (UK) Ltd.
+// This file is part of cargo-contract.
+//
+// cargo-contract is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// cargo-contract is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with cargo-contract.  If not, see <http://www.gnu.org/licenses/>.
+
+//! The JSON Schemas of the `--output-json` output of the commands, printed with
+//! `cargo contract <command> --schema`.
+//!
+//! The schemas are versioned with [`SCHEMA_VERSION`], which is part of their `$id`. The
+//! version is increased whenever a change of the output could break consumers, i.e. a
+//! field is removed, renamed or changes its type. Adding optional fields is not a
+//! breaking change.
+
+use serde_json::{
+    json,
+    Value,
+};
+use std::ffi::OsString;
+
+/// The version of the output schemas.
+pub const SCHEMA_VERSION: u32 = 1;
+
+/// A command output with a JSON Schema.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OutputSchema {
+    Info,
+    Call,
+    Instantiate,
+    Upload,
+    /// The events of an extrinsic, printed by `remove`.
+    Events,
+}
+
+impl OutputSchema {
+    /// Returns the output schema of the command, if it has one.
+    fn of_command(command: &str) -> Option<Self> {
+        match command {
+            "info" => Some(Self::Info),
+            "call" => Some(Self::Call),
+            "instantiate" => Some(Self::Instantiate),
+            "upload" => Some(Self::Upload),
+            "remove" => Some(Self::Events),
+            _ => None,
+        }
+    }
+
+    /// Returns the schema requested with `cargo contract <command> --schema`, so that
+    /// it is printed without the required arguments of the command.
+    pub fn requested(args: &[OsString]) -> Option<Self> {
+        if args.get(1).map_or(true, |arg| arg != "contract") {
+            return None
+        }
+        let schema = Self::of_command(args.get(2)?.to_str()?)?;
+        args[3..]
+            .iter()
+            .take_while(|arg| *arg != "--")
+            .any(|arg| arg == "--schema")
+            .then_some(schema)
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            Self::Info => "info",
+            Self::Call => "call",
+            Self::Instantiate => "instantiate",
+            Self::Upload => "upload",
+            Self::Events => "events",
+        }
+    }
+
+    /// Returns the JSON Schema of the output.
+    pub fn schema(self) -> Value {
+        let (title, schema) = match self {
+            Self::Info => ("The on-chain information of a contract", info()),
+            Self::Call => {
+                (
+                    "The result of a dry-run of a message, or the events of the call",
+                    json!({ "oneOf": [call_dry_run(), events()] }),
+                )
+            }
+            Self::Instantiate => {
+                (
+                    "The result of a dry-run of a constructor, or the instantiated \
+                    contract",
+                    json!({ "oneOf": [instantiate_dry_run(), instantiate()] }),
+                )
+            }
+            Self::Upload => {
+                (
+                    "The result of a dry-run of the upload, or the uploaded code",
+                    json!({ "oneOf": [upload_dry_run(), upload()] }),
+                )
+            }
+            Self::Events => ("The events of an extrinsic", events()),
+        };
+        let mut schema = schema;
+        let header = json!({
+            "$schema": "https://json-schema.org/draft/2020-12/schema",
+            "$id": format!("urn:cargo-contract:schema:v{SCHEMA_VERSION}:{}", self.name()),
+            "title": title,
+        });
+        if let (Value::Object(schema), Value::Object(header)) = (&mut schema, header) {
+            schema.extend(header);
+        }
+        schema
+    }
+
+    /// Print the JSON Schema of the output.
+    pub fn print(self) -> anyhow::Result<()> {
+        println!("{}", serde_json::to_string_pretty(&self.schema())?);
+        Ok(())
+    }
+}
+
+/// An object with the required and optional properties.
+fn object(required: &[(&str, Value)], optional: &[(&str, Value)]) -> Value {
+    let properties = required
+        .iter()
+        .chain(optional)
+        .map(|(name, schema)| (name.to_string(), schema.clone()))
+        .collect::<serde_json::Map<_, _>>();
+    json!({
+        "type": "object",
+        "properties": properties,
+        "required": required.iter().map(|(name, _)| *name).collect::<Vec<_>>(),
+    })
+}
+
+fn string(description: &str) -> Value {
+    json!({ "type": "string", "description": description })
+}
+
+fn hash() -> Value {
+    json!({ "type": "string", "pattern": "^0x[0-9a-f]{64}$" })
+}
+
+fn account() -> Value {
+    string("An SS58 address")
+}
+
+/// A decoded value, e.g. the return value of a message, in the notation of `--args`.
+fn decoded_value() -> Value {
+    json!({ "description": "A decoded value with the structure of its type" })
+}
+
+fn balance() -> Value {
+    object(
+        &[
+            (
+                "planck",
+                string("The amount in the smallest unit, as a decimal number"),
+            ),
+            (
+                "denominated",
+                string("The amount denominated with the token symbol"),
+            ),
+        ],
+        &[],
+    )
+}
+
+fn weight() -> Value {
+    object(
+        &[
+            ("ref_time", json!({ "type": "integer", "minimum": 0 })),
+            ("proof_size", json!({ "type": "integer", "minimum": 0 })),
+        ],
+        &[],
+    )
+}
+
+fn storage_deposit() -> Value {
+    json!({
+        "oneOf": [
+            object(&[("Charge", balance())], &[]),
+            object(&[("Refund", balance())], &[]),
+        ]
+    })
+}
+
+fn events() -> Value {
+    let raw_contract_event = object(
+        &[
+            ("contract", account()),
+            ("topics", json!({ "type": "array", "items": hash() })),
+            ("data", string("The hex encoded data of the event")),
+        ],
+        &[(
+            "candidates",
+            json!({
+                "type": "array",
+                "items": string("The name of an event with the signature topic"),
+            }),
+        )],
+    );
+    let field = object(
+        &[
+            ("name", string("The name of the field")),
+            ("value", decoded_value()),
+        ],
+        &[
+            ("balance", balance()),
+            ("raw_contract_event", raw_contract_event),
+        ],
+    );
+    json!({
+        "type": "array",
+        "items": object(
+            &[
+                ("pallet", string("The pallet emitting the event")),
+                ("name", string("The name of the event")),
+                ("fields", json!({ "type": "array", "items": field })),
+            ],
+            &[],
+        ),
+    })
+}
+
+fn info() -> Value {
+    let attestation = object(
+        &[
+            ("attester", account()),
+            ("metadata_hash", hash()),
+            (
+                "source_cid",
+                string("The content identifier of the source code"),
+            ),
+            ("block_hash", hash()),
+            ("verified", json!({ "type": "boolean" })),
+        ],
+        &[],
+    );
+    object(
+        &[
+            ("contract", account()),
+            ("trie_id", string("The trie id of the contract storage")),
+            ("code_hash", hash()),
+            ("storage_items", json!({ "type": "integer", "minimum": 0 })),
+            ("storage_item_deposit", balance()),
+        ],
+        &[
+            (
+                "contract_identity",
+                string("The on-chain identity of the contract"),
+            ),
+            ("owner", account()),
+            (
+                "owner_identity",
+                string("The on-chain identity of the code owner"),
+            ),
+            (
+                "attestations",
+                json!({ "type": "array", "items": attestation }),
+            ),
+            (
+                "binary",
+                string("The path the Wasm code was written to, with `--binary`"),
+            ),
+        ],
+    )
+}
+
+fn call_dry_run() -> Value {
+    let access_check = object(
+        &[
+            ("origin", account()),
+            ("denied", json!({ "type": "boolean" })),
+        ],
+        &[
+            (
+                "admin_getter",
+                string("The message queried for the allowed account"),
+            ),
+            ("allowed", decoded_value()),
+            ("error", string("Why the admin getter could not be queried")),
+        ],
+    );
+    object(
+        &[
+            ("result", string("`Success!`")),
+            ("reverted", json!({ "type": "boolean" })),
+            ("data", decoded_value()),
+            ("gas_consumed", weight()),
+            ("gas_required", weight()),
+            ("storage_deposit", storage_deposit()),
+        ],
+        &[("access_check", access_check)],
+    )
+}
+
+fn instantiate_dry_run() -> Value {
+    object(
+        &[
+            ("result", string("`Success!`")),
+            ("contract", account()),
+            ("reverted", json!({ "type": "boolean" })),
+            (
+                "data",
+                string("The raw return value of the constructor, in hex"),
+            ),
+            ("gas_consumed", weight()),
+            ("gas_required", weight()),
+            ("storage_deposit", storage_deposit()),
+        ],
+        &[("minimal_value", balance())],
+    )
+}
+
+fn instantiate() -> Value {
+    object(
+        &[("events", events())],
+        &[("contract", account()), ("code_hash", hash())],
+    )
+}
+
+fn upload_dry_run() -> Value {
+    object(
+        &[
+            ("result", string("`Success!`")),
+            ("code_hash", hash()),
+            ("deposit", balance()),
+        ],
+        &[],
+    )
+}
+
+fn upload() -> Value {
+    object(&[("code_hash", hash())], &[])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Check the value against the subset of JSON Schema used by the output schemas.
+    fn validate(schema: &Value, value: &Value) -> bool {
+        if let Some(schemas) = schema.get("oneOf").and_then(Value::as_array) {
+            return schemas.iter().filter(|s| validate(s, value)).count() == 1
+        }
+        match schema.get("type").and_then(Value::as_str) {
+            Some("object") => {
+                let Some(object) = value.as_object() else {
+                    return false
+                };
+                let required = schema["required"].as_array().unwrap();
+                required
+                    .iter()
+                    .all(|name| object.contains_key(name.as_str().unwrap()))
+                    && object.iter().all(|(name, value)| {
+                        schema["properties"]
+                            .get(name)
+                            .is_some_and(|schema| validate(schema, value))
+                    })
+            }
+            Some("array") => {
+                value.as_array().is_some_and(|items| {
+                    items.iter().all(|item| validate(&schema["items"], item))
+                })
+            }
+            Some("string") => value.is_string(),
+            Some("integer") => value.is_u64(),
+            Some("boolean") => value.is_boolean(),
+            _ => true,
+        }
+    }
+
+    #[test]
+    fn outputs_match_their_schemas() {
+        let balance = json!({ "planck": "1000", "denominated": "1 nUNIT" });
+        let weight = json!({ "ref_time": 100, "proof_size": 10 });
+        let hash = format!("0x{}", "ab".repeat(32));
+        let events = json!([{
+            "pallet": "Balances",
+            "name": "Withdraw",
+            "fields": [{ "name": "amount", "value": 1000, "balance": balance }]
+        }]);
+        let call = OutputSchema::Call.schema();
+        assert!(validate(
+            &call,
+            &json!({
+                "result": "Success!",
+                "reverted": false,
+                "data": { "Ok": true },
+                "gas_consumed": weight,
+                "gas_required": weight,
+                "storage_deposit": { "Charge": balance },
+            })
+        ));
+        assert!(validate(&call, &events));
+        assert!(!validate(&call, &json!({ "result": "Success!" })));
+
+        let upload = OutputSchema::Upload.schema();
+        assert!(validate(&upload, &json!({ "code_hash": hash })));
+        assert!(validate(
+            &upload,
+            &json!({ "result": "Success!", "code_hash": hash, "deposit": balance })
+        ));
+        assert!(validate(
+            &OutputSchema::Info.schema(),
+            &json!({
+                "contract": "5GrwvaEF5zXb26Fz9rcQpDWS57CtERHpNehXCPcNoHGKutQY",
+                "trie_id": "0x00",
+                "code_hash": hash,
+                "storage_items": 1,
+                "storage_item_deposit": balance,
+            })
+        ));
+        assert_eq!(
+            OutputSchema::Events.schema()["$id"],
+            format!("urn:cargo-contract:schema:v{SCHEMA_VERSION}:events")
+        );
+    }
+
+    #[test]
+    fn schema_is_requested_before_the_arguments_are_parsed() {
+        let args = |args: &str| {
+            args.split_whitespace()
+                .map(OsString::from)
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(
+            OutputSchema::requested(&args("cargo-contract contract call --schema")),
+            Some(OutputSchema::Call)
+        );
+        assert_eq!(
+            OutputSchema::requested(&args("cargo-contract contract remove --schema")),
+            Some(OutputSchema::Events)
+        );
+        assert_eq!(
+            OutputSchema::requested(&args("cargo-contract contract call -- --schema")),
+            None
+        );
+        assert_eq!(
+            OutputSchema::requested(&args("cargo-contract contract build --schema")),
+            None
+        );
+    }
+}
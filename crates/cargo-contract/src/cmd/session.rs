@@ -0,0 +1,503 @@
+// Copyright 2018-2023 Parity Technologies (UK) Ltd.
+// This file is part of cargo-contract.
+//
+// cargo-contract is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// cargo-contract is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with cargo-contract.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Recorded interaction sessions with contracts.
+//!
+//! While the `CARGO_CONTRACT_SESSION` environment variable holds the path of a session
+//! file, every `upload`, `instantiate` and `call` appends a step to it: the arguments of
+//! the command without secrets, whether it was executed or dry-run, and its output. The
+//! session is exported into a scenario file, which `session replay` runs again against a
+//! chain, and into a markdown notebook documenting the calls with their inputs, outputs
+//! and gas.
+
+use super::{
+    lock,
+    queue::{
+        is_secret_arg,
+        SUBMIT_ARGS,
+    },
+};
+use anyhow::{
+    Context,
+    Result,
+};
+use colored::Colorize;
+use serde_json::Value;
+use std::{
+    fmt::Write as _,
+    future::Future,
+    io::Write as _,
+    path::{
+        Path,
+        PathBuf,
+    },
+    sync::Mutex,
+};
+
+/// The environment variable holding the path of the session file to record into.
+pub const SESSION_PATH_ENV: &str = "CARGO_CONTRACT_SESSION";
+
+/// The commands recorded into the session.
+const RECORDED_COMMANDS: [&str; 3] = ["upload", "instantiate", "call"];
+
+/// The arguments of the running command, see [`begin`].
+static ARGS: Mutex<Vec<String>> = Mutex::new(Vec::new());
+
+/// A step of a session: a command and its output.
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct Step {
+    /// The arguments of the command, without secrets and `--execute`.
+    pub args: Vec<String>,
+    /// Whether the command was executed, or only dry-run.
+    pub executed: bool,
+    /// The structured output of the command.
+    pub output: Value,
+}
+
+impl Step {
+    /// The step of the command with the given arguments, `None` if the command is not
+    /// recorded.
+    fn new(args: &[String], output: Value) -> Option<Self> {
+        if !RECORDED_COMMANDS.contains(&args.first()?.as_str()) {
+            return None
+        }
+        let mut executed = false;
+        let mut recorded = Vec::new();
+        let mut args = args.iter();
+        while let Some(arg) = args.next() {
+            if is_secret_arg(arg) {
+                // Skip the value of the secret, unless given as `--suri=<SURI>`.
+                if !arg.contains('=') {
+                    args.next();
+                }
+            } else if SUBMIT_ARGS.contains(&arg.as_str()) {
+                executed |= arg != "--skip-confirm";
+            } else {
+                recorded.push(arg.clone());
+            }
+        }
+        Some(Self {
+            args: recorded,
+            executed,
+            output,
+        })
+    }
+
+    /// The value of the option of the command, e.g. `--message`.
+    fn option(&self, names: &[&str]) -> Option<&str> {
+        self.args
+            .iter()
+            .position(|arg| names.contains(&arg.as_str()))
+            .and_then(|index| self.args.get(index + 1))
+            .map(String::as_str)
+    }
+
+    /// The values of `--args`, up to the next option.
+    fn call_args(&self) -> Vec<&str> {
+        self.args
+            .iter()
+            .skip_while(|arg| *arg != "--args")
+            .skip(1)
+            .take_while(|arg| !arg.starts_with("--"))
+            .map(String::as_str)
+            .collect()
+    }
+}
+
+/// Set the arguments of the running command, without the binary and `contract`, which
+/// are recorded with its output.
+pub fn begin(args: Vec<String>) {
+    *ARGS.lock().unwrap_or_else(|err| err.into_inner()) = args;
+}
+
+/// Record the output of the running command into the session file, if a session is
+/// being recorded. Failures are logged but do not fail the command.
+pub fn record(output: &impl serde::Serialize) {
+    let Some(path) = std::env::var_os(SESSION_PATH_ENV) else {
+        return
+    };
+    if let Err(err) = try_record(Path::new(&path), output) {
+        tracing::warn!("Failed to record the step in the session: {err:?}");
+    }
+}
+
+fn try_record(path: &Path, output: &impl serde::Serialize) -> Result<()> {
+    let args = ARGS.lock().unwrap_or_else(|err| err.into_inner()).clone();
+    let Some(step) = Step::new(&args, serde_json::to_value(output)?) else {
+        return Ok(())
+    };
+    let _lock = lock::FileLock::acquire(path)?;
+    let mut file = std::fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+        .with_context(|| format!("Failed to open {}", path.display()))?;
+    writeln!(file, "{}", serde_json::to_string(&step)?)?;
+    Ok(())
+}
+
+/// Load the steps of the session file, one JSON object per line.
+pub fn load(path: &Path) -> Result<Vec<Step>> {
+    let contents = std::fs::read_to_string(path)
+        .with_context(|| format!("Failed to read {}", path.display()))?;
+    contents
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .enumerate()
+        .map(|(index, line)| {
+            serde_json::from_str(line).with_context(|| {
+                format!(
+                    "Invalid step {} of the session {}",
+                    index + 1,
+                    path.display()
+                )
+            })
+        })
+        .collect()
+}
+
+/// A reproducible scenario exported from a session.
+#[derive(Debug, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct Scenario {
+    /// The version of `cargo-contract` which recorded the scenario.
+    pub cargo_contract_version: String,
+    pub steps: Vec<Step>,
+}
+
+impl Scenario {
+    pub fn load(path: &Path) -> Result<Self> {
+        let contents = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read {}", path.display()))?;
+        serde_json::from_str(&contents)
+            .with_context(|| format!("Invalid scenario file {}", path.display()))
+    }
+
+    /// The arguments of the commands replaying the steps, signed with the secret key
+    /// URI.
+    fn replay_args(&self, suri: &str, password: Option<&str>) -> Vec<Vec<String>> {
+        self.steps
+            .iter()
+            .map(|step| {
+                let mut args = step.args.clone();
+                if step.executed {
+                    args.extend(["--execute", "--skip-confirm"].map(String::from));
+                }
+                args.extend(["--suri", suri].map(String::from));
+                if let Some(password) = password {
+                    args.extend(["--password", password].map(String::from));
+                }
+                args
+            })
+            .collect()
+    }
+}
+
+/// Render the steps as a markdown notebook.
+pub fn notebook(title: &str, scenario_path: Option<&Path>, steps: &[Step]) -> String {
+    let mut markdown = format!("# {title}\n\n");
+    let _ = writeln!(
+        markdown,
+        "Recorded with `cargo-contract` {}.",
+        env!("CARGO_PKG_VERSION")
+    );
+    if let Some(path) = scenario_path {
+        let _ = writeln!(
+            markdown,
+            "Replay the steps with `cargo contract session replay {} --suri <SURI>`.",
+            path.display()
+        );
+    }
+    for (number, step) in (1..).zip(steps) {
+        let command = step.args.first().map(String::as_str).unwrap_or_default();
+        let name = step.option(&["--message", "-m", "--constructor"]);
+        let mode = if step.executed { "executed" } else { "dry-run" };
+        let _ = write!(markdown, "\n## {number}. `{command}`");
+        if let Some(name) = name {
+            let _ = write!(markdown, " `{name}`");
+        }
+        let _ = writeln!(markdown, " ({mode})\n");
+        let _ = writeln!(
+            markdown,
+            "```sh\ncargo contract {}\n```\n",
+            step.args.join(" ")
+        );
+
+        let mut rows = Vec::new();
+        let call_args = step.call_args();
+        if !call_args.is_empty() {
+            rows.push(("Inputs", format!("`{}`", call_args.join(" "))));
+        }
+        if let Some(value) = step.option(&["--value"]) {
+            rows.push(("Value", format!("`{value}`")));
+        }
+        for (key, label) in [
+            ("contract", "Contract"),
+            ("code_hash", "Code hash"),
+            ("reverted", "Reverted"),
+            ("data", "Output"),
+            ("gas_consumed", "Gas consumed"),
+            ("gas_required", "Gas required"),
+            ("storage_deposit", "Storage deposit"),
+            ("deposit", "Deposit"),
+        ] {
+            if let Some(value) = step.output.get(key).filter(|value| !value.is_null()) {
+                rows.push((label, format!("`{}`", display_value(value))));
+            }
+        }
+        let events = step
+            .output
+            .get("events")
+            .or_else(|| step.output.is_array().then_some(&step.output))
+            .and_then(Value::as_array);
+        if let Some(events) = events {
+            let names = events
+                .iter()
+                .filter_map(|event| {
+                    Some(format!(
+                        "`{}::{}`",
+                        event.get("pallet")?.as_str()?,
+                        event.get("name")?.as_str()?
+                    ))
+                })
+                .collect::<Vec<_>>();
+            rows.push(("Events", names.join(", ")));
+        }
+        if !rows.is_empty() {
+            markdown.push_str("| | |\n|---|---|\n");
+            for (label, value) in rows {
+                let _ = writeln!(markdown, "| {label} | {} |", value.replace('|', "\\|"));
+            }
+        }
+    }
+    markdown
+}
+
+/// Display a value of the output compactly, strings without quotes.
+fn display_value(value: &Value) -> String {
+    match value {
+        Value::String(string) => string.clone(),
+        Value::Object(object) if object.contains_key("ref_time") => {
+            format!(
+                "ref_time: {}, proof_size: {}",
+                object["ref_time"], object["proof_size"]
+            )
+        }
+        other => other.to_string(),
+    }
+}
+
+#[derive(Debug, clap::Args)]
+#[clap(
+    name = "session",
+    about = "Export and replay recorded interaction sessions"
+)]
+pub struct SessionCommand {
+    #[clap(subcommand)]
+    action: SessionAction,
+}
+
+#[derive(Debug, clap::Subcommand)]
+enum SessionAction {
+    /// Export a session recorded with `CARGO_CONTRACT_SESSION` into a scenario file and
+    /// a markdown notebook
+    Export {
+        /// The session file.
+        session: PathBuf,
+        /// The scenario file to write, `<session>.scenario.json` by default.
+        #[clap(long)]
+        scenario: Option<PathBuf>,
+        /// The markdown notebook to write, `<session>.md` by default.
+        #[clap(long)]
+        notebook: Option<PathBuf>,
+        /// The title of the notebook, the name of the session file by default.
+        #[clap(long)]
+        title: Option<String>,
+    },
+    /// Run the steps of a scenario in order, stopping at the first failure
+    Replay {
+        /// The scenario file.
+        scenario: PathBuf,
+        /// Secret key URI for the account running the steps.
+        #[clap(long, short)]
+        suri: String,
+        /// Password for the secret key.
+        #[clap(long, short)]
+        password: Option<String>,
+    },
+}
+
+impl SessionCommand {
+    /// Run the session action, `run` executes the command with the given arguments.
+    pub async fn run<F, Fut>(&self, mut run: F) -> Result<()>
+    where
+        F: FnMut(Vec<String>) -> Fut,
+        Fut: Future<Output = Result<()>>,
+    {
+        match self.action {
+            SessionAction::Export {
+                ref session,
+                ref scenario,
+                ref notebook,
+                ref title,
+            } => {
+                let steps = load(session)?;
+                let scenario_path = scenario
+                    .clone()
+                    .unwrap_or_else(|| session.with_extension("scenario.json"));
+                let notebook_path = notebook
+                    .clone()
+                    .unwrap_or_else(|| session.with_extension("md"));
+                let title = title.clone().unwrap_or_else(|| {
+                    session
+                        .file_stem()
+                        .map(|stem| stem.to_string_lossy().into_owned())
+                        .unwrap_or_default()
+                });
+                let markdown = self::notebook(&title, Some(&scenario_path), &steps);
+                let scenario = Scenario {
+                    cargo_contract_version: env!("CARGO_PKG_VERSION").to_string(),
+                    steps,
+                };
+                lock::write_atomic(
+                    &scenario_path,
+                    serde_json::to_string_pretty(&scenario)?,
+                )?;
+                lock::write_atomic(&notebook_path, markdown)?;
+                println!(
+                    "Exported {} steps into {} and {}",
+                    scenario.steps.len(),
+                    scenario_path.display(),
+                    notebook_path.display()
+                );
+            }
+            SessionAction::Replay {
+                ref scenario,
+                ref suri,
+                ref password,
+            } => {
+                let scenario = Scenario::load(scenario)?;
+                let steps = scenario.replay_args(suri, password.as_deref());
+                for (number, (step, args)) in (1..).zip(scenario.steps.iter().zip(steps))
+                {
+                    println!(
+                        "{} step #{number}: {}",
+                        "Running".green().bold(),
+                        step.args.join(" ")
+                    );
+                    run(args).await.with_context(|| {
+                        format!("Step #{number} of the scenario failed")
+                    })?;
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(args: &str) -> Vec<String> {
+        args.split_whitespace().map(String::from).collect()
+    }
+
+    #[test]
+    fn steps_are_recorded_without_secrets() {
+        let step = Step::new(
+            &args("call --contract 5A -m flip --suri //Alice -x --skip-confirm"),
+            Value::Null,
+        )
+        .unwrap();
+        assert_eq!(step.args, args("call --contract 5A -m flip"));
+        assert!(step.executed);
+
+        let step =
+            Step::new(&args("instantiate --suri=//Bob --args 1 2"), Value::Null).unwrap();
+        assert_eq!(step.args, args("instantiate --args 1 2"));
+        assert!(!step.executed);
+        assert_eq!(step.call_args(), ["1", "2"]);
+
+        assert!(Step::new(&args("build --release"), Value::Null).is_none());
+    }
+
+    #[tokio::test]
+    async fn sessions_are_exported_and_replayed() {
+        let dir = tempfile::tempdir().unwrap();
+        let session = dir.path().join("flipper.jsonl");
+        let steps = [
+            Step::new(
+                &args("instantiate --args true --suri //Alice -x"),
+                serde_json::json!({ "contract": "5C", "events": [] }),
+            )
+            .unwrap(),
+            Step::new(
+                &args("call --contract 5C --message get --suri //Alice"),
+                serde_json::json!({
+                    "data": "Ok(true)",
+                    "gas_consumed": { "ref_time": 100, "proof_size": 10 },
+                }),
+            )
+            .unwrap(),
+        ];
+        let lines = steps
+            .iter()
+            .map(|step| serde_json::to_string(step).unwrap() + "\n")
+            .collect::<String>();
+        std::fs::write(&session, lines).unwrap();
+
+        let export = SessionCommand {
+            action: SessionAction::Export {
+                session: session.clone(),
+                scenario: None,
+                notebook: None,
+                title: None,
+            },
+        };
+        export.run(|_| async { unreachable!() }).await.unwrap();
+        let scenario_path = dir.path().join("flipper.scenario.json");
+        assert_eq!(Scenario::load(&scenario_path).unwrap().steps, steps);
+        let notebook = std::fs::read_to_string(dir.path().join("flipper.md")).unwrap();
+        assert!(notebook.starts_with("# flipper\n"));
+        assert!(notebook.contains("## 1. `instantiate` (executed)"));
+        assert!(notebook.contains("## 2. `call` `get` (dry-run)"));
+        assert!(notebook.contains("| Output | `Ok(true)` |"));
+        assert!(notebook.contains("| Gas consumed | `ref_time: 100, proof_size: 10` |"));
+
+        let replay = SessionCommand {
+            action: SessionAction::Replay {
+                scenario: scenario_path,
+                suri: "//Bob".to_string(),
+                password: None,
+            },
+        };
+        let mut replayed = Vec::new();
+        replay
+            .run(|args| {
+                replayed.push(args.join(" "));
+                async { Ok(()) }
+            })
+            .await
+            .unwrap();
+        assert_eq!(
+            replayed,
+            [
+                "instantiate --args true --execute --skip-confirm --suri //Bob",
+                "call --contract 5C --message get --suri //Bob",
+            ]
+        );
+    }
+}
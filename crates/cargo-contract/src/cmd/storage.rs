@@ -0,0 +1,1165 @@
+// Copyright 2018-2023 Parity Technologies (UK) Ltd.
+// This file is part of cargo-contract.
+//
+// cargo-contract is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// cargo-contract is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with cargo-contract.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Commands on the storage of contracts.
+//!
+//! The storage of a contract is the child trie of its `trie_id`. An ink! storage key is
+//! stored in the child trie under its `Blake2_128Concat` hash, and the root of the child
+//! trie is stored in the state trie under `:child_storage:default:<trie_id>`. A proof of
+//! contract storage is therefore made of two proofs: one of the child root in the state
+//! trie, verifiable against the state root of the block, and one of the entries in the
+//! child trie, verifiable against the child root.
+
+use super::{
+    archive::{
+        ArchiveOpts,
+        DataSource,
+        Sourced,
+    },
+    checkpoint::CheckpointOpts,
+    extrinsics::{
+        parse_code_hash,
+        ContractArtifacts,
+    },
+    identity::Identities,
+    output,
+    pacing,
+    runtime_api::api,
+    scan::DEFAULT_PAGE_SIZE,
+    AsyncCommand,
+    ClearStorageCommand,
+    Client,
+    DefaultConfig,
+    ErrorVariant,
+};
+use anyhow::{
+    anyhow,
+    Context,
+    Result,
+};
+use async_trait::async_trait;
+use sp_core::{
+    hashing::blake2_128,
+    Blake2Hasher,
+    H256,
+};
+use sp_trie::{
+    LayoutV1,
+    StorageProof,
+};
+use std::{
+    collections::BTreeMap,
+    path::PathBuf,
+};
+use subxt::{
+    rpc::types::{
+        Bytes,
+        ReadProof,
+    },
+    rpc_params,
+    Config,
+    OnlineClient,
+};
+
+type AccountId = <DefaultConfig as Config>::AccountId;
+type Hash = <DefaultConfig as Config>::Hash;
+
+/// The prefix of the keys of default child tries in the state trie.
+const CHILD_STORAGE_PREFIX: &[u8] = b":child_storage:default:";
+
+#[derive(Debug, clap::Args)]
+#[clap(name = "storage", about = "Inspect the storage of a contract")]
+pub struct StorageCommand {
+    #[clap(subcommand)]
+    action: StorageAction,
+}
+
+#[derive(Debug, clap::Subcommand)]
+#[allow(clippy::large_enum_variant)]
+enum StorageAction {
+    /// Dump all storage cells of a contract, decoded with the storage layout of its
+    /// metadata
+    #[clap(name = "dump")]
+    Dump(DumpCommand),
+    /// Generate a proof of storage entries of a contract, verifiable against the state
+    /// root of a block
+    #[clap(name = "proof")]
+    Proof(ProofCommand),
+    /// Compare the decoded storage of two deployments of the same code, e.g. on staging
+    /// and production networks
+    #[clap(name = "diff")]
+    Diff(DiffCommand),
+    /// Report the storage fields each message and constructor reads and writes, as
+    /// analyzed when the contract was built
+    #[clap(name = "access")]
+    Access(AccessCommand),
+    /// Clear the cells of storage fields of a contract with sudo, on development chains
+    #[clap(name = "clear")]
+    Clear(ClearStorageCommand),
+}
+
+impl StorageCommand {
+    pub fn is_json(&self) -> bool {
+        matches!(self.action, StorageAction::Clear(ref clear) if clear.is_json())
+    }
+}
+
+#[async_trait(?Send)]
+impl AsyncCommand for StorageCommand {
+    type Output = Result<(), ErrorVariant>;
+
+    async fn exec(&self) -> Result<(), ErrorVariant> {
+        match self.action {
+            StorageAction::Dump(ref dump) => Ok(dump.run().await?),
+            StorageAction::Proof(ref proof) => Ok(proof.run().await?),
+            StorageAction::Diff(ref diff) => Ok(diff.run().await?),
+            StorageAction::Access(ref access) => Ok(access.run()?),
+            StorageAction::Clear(ref clear) => clear.exec().await,
+        }
+    }
+}
+
+#[derive(Debug, clap::Args)]
+struct DumpCommand {
+    /// The address of the contract.
+    #[clap(long, env = "CONTRACT")]
+    contract: AccountId,
+    /// Websockets url of a substrate node.
+    #[clap(long, default_value = "ws://localhost:9944")]
+    url: url::Url,
+    /// Path to a contract build artifact file, a `.contract` bundle or a `.json`
+    /// metadata file, to decode the storage with.
+    #[clap(long, value_parser, conflicts_with = "manifest_path")]
+    file: Option<PathBuf>,
+    /// Path to the `Cargo.toml` of the contract, to decode the storage with.
+    #[clap(long, value_parser)]
+    manifest_path: Option<PathBuf>,
+    /// Dump the raw ink! storage keys and values in hex, without decoding them.
+    #[clap(long)]
+    raw: bool,
+    /// The hash of the block to dump the storage at, by default the best block.
+    #[clap(long, value_parser = parse_code_hash)]
+    at: Option<Hash>,
+    #[clap(flatten)]
+    archive: ArchiveOpts,
+    /// Export the storage in JSON format.
+    #[clap(long)]
+    output_json: bool,
+    /// Truncate values larger than the given number of bytes in the human readable
+    /// output, `0` to never truncate.
+    #[clap(long, value_name = "BYTES", default_value_t = output::DEFAULT_MAX_OUTPUT_BYTES)]
+    max_output_bytes: usize,
+    #[clap(flatten)]
+    checkpoint: CheckpointOpts,
+}
+
+/// The storage of a contract.
+#[derive(Debug, serde::Serialize)]
+struct StorageDump {
+    contract: String,
+    code_hash: Hash,
+    /// The decoded fields by their path, or the raw cells by their key with `--raw`.
+    /// Cells not described by the storage layout are kept by their key.
+    entries: BTreeMap<String, String>,
+    /// The node the storage was read from.
+    source: DataSource,
+}
+
+impl DumpCommand {
+    async fn run(&self) -> Result<()> {
+        let identities = Identities::default();
+        let layout = if self.raw {
+            None
+        } else {
+            let artifacts = ContractArtifacts::from_manifest_or_file(
+                self.manifest_path.as_ref(),
+                self.file.as_ref(),
+            )
+            .context("Pass `--raw` to dump the storage without the metadata")?;
+            let transcoder =
+                artifacts.contract_transcoder_with_identities(&identities)?;
+            let abi = serde_json::Value::Object(artifacts.metadata()?.abi);
+            Some((transcoder, abi))
+        };
+        let Sourced {
+            value: storage,
+            source,
+        } = self
+            .archive
+            .fetch(&self.url, |client| {
+                let identities = &identities;
+                async move {
+                    if !self.raw {
+                        identities.resolve(&client).await?;
+                    }
+                    read_storage(&client, &self.contract, self.at, &self.checkpoint).await
+                }
+            })
+            .await?;
+        let entries = match layout {
+            Some((ref transcoder, ref abi)) => {
+                let root = abi.pointer("/storage/root").ok_or_else(|| {
+                    anyhow!("The metadata has no ink! 4 storage layout")
+                })?;
+                let decode = |ty: u32, input: &mut &[u8]| -> Result<String> {
+                    Ok(transcoder.decode(ty, input)?.to_string())
+                };
+                decode_storage(root, &storage.cells, &decode)
+            }
+            None => raw_entries(&storage.cells),
+        };
+        let dump = StorageDump {
+            contract: self.contract.to_string(),
+            code_hash: storage.code_hash,
+            entries,
+            source,
+        };
+
+        if self.output_json {
+            println!("{}", serde_json::to_string_pretty(&dump)?);
+        } else if dump.entries.is_empty() {
+            println!("The storage of {} is empty", dump.contract);
+        } else {
+            if dump.source.is_archive() {
+                println!("Read from the {}", dump.source);
+            }
+            println!(
+                "Storage of {} (code hash {:?}):",
+                dump.contract, dump.code_hash
+            );
+            for (key, value) in &dump.entries {
+                println!(
+                    "  {key}: {}",
+                    output::truncate(value, self.max_output_bytes)
+                );
+            }
+        }
+        Ok(())
+    }
+}
+
+/// The cells by their ink! storage key, in hex.
+fn raw_entries(cells: &BTreeMap<Vec<u8>, Vec<u8>>) -> BTreeMap<String, String> {
+    cells
+        .iter()
+        .map(|(key, value)| {
+            (
+                format!("0x{}", hex::encode(key)),
+                format!("0x{}", hex::encode(value)),
+            )
+        })
+        .collect()
+}
+
+#[derive(Debug, clap::Args)]
+struct ProofCommand {
+    /// The address of the contract.
+    #[clap(long, env = "CONTRACT")]
+    contract: AccountId,
+    /// A field of the storage struct of the contract to prove, resolved with the
+    /// storage layout of the metadata. Can be repeated.
+    #[clap(long)]
+    field: Vec<String>,
+    /// A raw ink! storage key to prove in hex, e.g. the root key of a `Mapping`
+    /// followed by the SCALE encoded key of an entry. Can be repeated.
+    #[clap(long, value_parser = contract_build::util::decode_hex)]
+    key: Vec<Vec<u8>>,
+    /// The hash of the block to prove the storage at, by default the best block.
+    #[clap(long, value_parser = parse_code_hash)]
+    at: Option<Hash>,
+    #[clap(flatten)]
+    archive: ArchiveOpts,
+    /// Websockets url of a substrate node.
+    #[clap(long, default_value = "ws://localhost:9944")]
+    url: url::Url,
+    /// Path to a contract build artifact file, a `.contract` bundle or a `.json`
+    /// metadata file, to resolve `--field` with.
+    #[clap(long, value_parser, conflicts_with = "manifest_path")]
+    file: Option<PathBuf>,
+    /// Path to the `Cargo.toml` of the contract, to resolve `--field` with.
+    #[clap(long, value_parser)]
+    manifest_path: Option<PathBuf>,
+}
+
+/// A proof of storage entries of a contract.
+#[derive(Debug, serde::Serialize)]
+struct ContractStorageProof {
+    block_hash: Hash,
+    state_root: H256,
+    contract: String,
+    trie_id: String,
+    /// The key of the child root in the state trie.
+    child_storage_key: String,
+    child_root: H256,
+    /// The proof of the child root in the state trie.
+    state_proof: Vec<String>,
+    /// The proof of the entries in the child trie.
+    child_proof: Vec<String>,
+    entries: Vec<ProvenEntry>,
+}
+
+/// A storage entry of a contract, as read from the proof.
+#[derive(Debug, serde::Serialize)]
+struct ProvenEntry {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    field: Option<String>,
+    /// The ink! storage key.
+    key: String,
+    /// The key of the entry in the child trie.
+    hashed_key: String,
+    /// The SCALE encoded value, `null` if the proof shows the entry does not exist.
+    value: Option<String>,
+}
+
+impl ProofCommand {
+    async fn run(&self) -> Result<()> {
+        let mut keys: Vec<(Option<String>, Vec<u8>)> = Vec::new();
+        if !self.field.is_empty() {
+            let artifacts = ContractArtifacts::from_manifest_or_file(
+                self.manifest_path.as_ref(),
+                self.file.as_ref(),
+            )?;
+            let abi = serde_json::Value::Object(artifacts.metadata()?.abi);
+            for field in &self.field {
+                keys.push((Some(field.clone()), field_key(&abi, field)?));
+            }
+        }
+        keys.extend(self.key.iter().map(|key| (None, key.clone())));
+        if keys.is_empty() {
+            return Err(anyhow!(
+                "Pass the storage to prove with `--field` or `--key`"
+            ))
+        }
+
+        let proof = self
+            .archive
+            .fetch(&self.url, |client| {
+                let keys = keys.clone();
+                async move { prove(&client, &self.contract, self.at, keys).await }
+            })
+            .await?;
+        println!("{}", serde_json::to_string_pretty(&proof)?);
+        Ok(())
+    }
+}
+
+/// Fetch the proofs of the keys of the contract at the block, and read the entries from
+/// them to make sure they verify.
+async fn prove(
+    client: &Client,
+    contract: &AccountId,
+    at: Option<Hash>,
+    keys: Vec<(Option<String>, Vec<u8>)>,
+) -> Result<ContractStorageProof> {
+    let block = match at {
+        Some(hash) => client.blocks().at(hash).await?,
+        None => client.blocks().at_latest().await?,
+    };
+    let block_hash = block.hash();
+    let state_root = block.header().state_root;
+    let info = block
+        .storage()
+        .fetch(&api::storage().contracts().contract_info_of(contract))
+        .await?
+        .ok_or_else(|| {
+            anyhow!("No contract was found at {contract} in {block_hash:?}")
+        })?;
+    let trie_id = info.trie_id.0;
+    let child_storage_key = [CHILD_STORAGE_PREFIX, &trie_id].concat();
+
+    let state_proof = client
+        .rpc()
+        .read_proof([child_storage_key.as_slice()], Some(block_hash))
+        .await?;
+    let hashed_keys = keys
+        .iter()
+        .map(|(_, key)| hashed_key(key))
+        .collect::<Vec<_>>();
+    let hex_keys = hashed_keys
+        .iter()
+        .map(|key| format!("0x{}", hex::encode(key)))
+        .collect::<Vec<_>>();
+    let child_proof: ReadProof<Hash> = client
+        .rpc()
+        .request(
+            "state_getChildReadProof",
+            rpc_params![
+                format!("0x{}", hex::encode(&child_storage_key)),
+                hex_keys,
+                block_hash
+            ],
+        )
+        .await
+        .context("The node does not serve child storage proofs")?;
+
+    let state_nodes = nodes(&state_proof);
+    let child_nodes = nodes(&child_proof);
+    let child_root = read_proof(&state_nodes, &state_root, &child_storage_key)?
+        .ok_or_else(|| anyhow!("The storage of the contract is empty"))?;
+    let child_root = H256::from_slice(
+        child_root
+            .get(..32)
+            .ok_or_else(|| anyhow!("Invalid child root in the state proof"))?,
+    );
+    let mut entries = Vec::new();
+    for ((field, key), hashed_key) in keys.into_iter().zip(hashed_keys) {
+        let value = read_proof(&child_nodes, &child_root, &hashed_key)?;
+        entries.push(ProvenEntry {
+            field,
+            key: format!("0x{}", hex::encode(&key)),
+            hashed_key: format!("0x{}", hex::encode(&hashed_key)),
+            value: value.map(|value| format!("0x{}", hex::encode(value))),
+        });
+    }
+
+    let encode = |nodes: Vec<Vec<u8>>| {
+        nodes
+            .into_iter()
+            .map(|node| format!("0x{}", hex::encode(node)))
+            .collect()
+    };
+    Ok(ContractStorageProof {
+        block_hash,
+        state_root,
+        contract: contract.to_string(),
+        trie_id: format!("0x{}", hex::encode(&trie_id)),
+        child_storage_key: format!("0x{}", hex::encode(&child_storage_key)),
+        child_root,
+        state_proof: encode(state_nodes),
+        child_proof: encode(child_nodes),
+        entries,
+    })
+}
+
+fn nodes(proof: &ReadProof<Hash>) -> Vec<Vec<u8>> {
+    proof.proof.iter().map(|node| node.0.clone()).collect()
+}
+
+/// Read the value of the key from the proof of the trie with the given root.
+fn read_proof(nodes: &[Vec<u8>], root: &H256, key: &[u8]) -> Result<Option<Vec<u8>>> {
+    let db = StorageProof::new(nodes.iter().cloned()).into_memory_db::<Blake2Hasher>();
+    sp_trie::read_trie_value::<LayoutV1<Blake2Hasher>, _>(&db, root, key, None, None)
+        .map_err(|err| anyhow!("The proof does not verify: {err}"))
+}
+
+/// Returns the key of the ink! storage key in the child trie of the contract.
+pub(crate) fn hashed_key(key: &[u8]) -> Vec<u8> {
+    [blake2_128(key).as_slice(), key].concat()
+}
+
+/// Resolve the storage key of the field of the storage struct of the contract.
+///
+/// Fields with their own root key, `Lazy` values and `Mapping`s, are stored under it,
+/// the other fields are packed into the cell of the root key of the contract.
+fn field_key(abi: &serde_json::Value, field: &str) -> Result<Vec<u8>> {
+    let (key, _) = resolve_field(abi, field)?;
+    Ok(key)
+}
+
+/// Resolve the root key of a field of the storage struct of the contract, failing if the
+/// field is packed into the cell of the root key of the contract.
+pub(crate) fn field_root_key(abi: &serde_json::Value, field: &str) -> Result<Vec<u8>> {
+    match resolve_field(abi, field)? {
+        (key, true) => Ok(key),
+        (_, false) => {
+            Err(anyhow!(
+                "The field `{field}` is packed into the root cell of the contract, only \
+                fields with their own storage key, `Lazy` values and `Mapping`s, can be \
+                resolved on their own"
+            ))
+        }
+    }
+}
+
+/// Returns the storage key of the field, and whether it is the own root key of the field.
+fn resolve_field(abi: &serde_json::Value, field: &str) -> Result<(Vec<u8>, bool)> {
+    let root = abi
+        .pointer("/storage/root")
+        .ok_or_else(|| anyhow!("The metadata has no ink! 4 storage layout"))?;
+    let root_key = root
+        .get("root_key")
+        .and_then(|key| key.as_str())
+        .ok_or_else(|| anyhow!("The storage layout has no root key"))?;
+    let fields = root
+        .pointer("/layout/struct/fields")
+        .and_then(|fields| fields.as_array())
+        .ok_or_else(|| anyhow!("The storage of the contract is not a struct"))?;
+    let layout = fields
+        .iter()
+        .find(|f| f.get("name").and_then(|name| name.as_str()) == Some(field))
+        .and_then(|f| f.get("layout"))
+        .ok_or_else(|| anyhow!("The storage struct has no field `{field}`"))?;
+    let own_key = layout
+        .pointer("/root/root_key")
+        .and_then(|key| key.as_str());
+    let key = own_key.unwrap_or(root_key);
+    let key = contract_build::util::decode_hex(key)
+        .with_context(|| format!("Invalid storage key {key}"))?;
+    Ok((key, own_key.is_some()))
+}
+
+#[derive(Debug, clap::Args)]
+struct DiffCommand {
+    /// The addresses of the two contracts to compare, given twice.
+    #[clap(long, required = true, num_args = 1)]
+    contract: Vec<AccountId>,
+    /// Websockets urls of the nodes of the networks of the contracts, in the order of
+    /// `--contract`. A single url is used for both contracts.
+    #[clap(long, num_args = 1)]
+    url: Vec<url::Url>,
+    /// Path to a contract build artifact file, a `.contract` bundle or a `.json`
+    /// metadata file, to decode the storage with.
+    #[clap(long, value_parser, conflicts_with = "manifest_path")]
+    file: Option<PathBuf>,
+    /// Path to the `Cargo.toml` of the contract, to decode the storage with.
+    #[clap(long, value_parser)]
+    manifest_path: Option<PathBuf>,
+    /// Export the divergent fields in JSON format.
+    #[clap(long)]
+    output_json: bool,
+    /// Truncate values larger than the given number of bytes in the human readable
+    /// output, `0` to never truncate.
+    #[clap(long, value_name = "BYTES", default_value_t = output::DEFAULT_MAX_OUTPUT_BYTES)]
+    max_output_bytes: usize,
+}
+
+/// A field whose value differs between the two deployments.
+#[derive(Debug, PartialEq, Eq, serde::Serialize)]
+struct DivergentField {
+    field: String,
+    /// The value in the first deployment, `null` if it has no such entry.
+    a: Option<String>,
+    /// The value in the second deployment, `null` if it has no such entry.
+    b: Option<String>,
+    /// The messages writing the field, according to the storage accesses of the
+    /// metadata.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    written_by: Vec<String>,
+}
+
+impl DiffCommand {
+    async fn run(&self) -> Result<()> {
+        let [ref contract_a, ref contract_b] = self.contract[..] else {
+            return Err(anyhow!(
+                "Pass the two contracts to compare with `--contract`"
+            ))
+        };
+        let default_url = url::Url::parse("ws://localhost:9944")?;
+        let (url_a, url_b) = match self.url[..] {
+            [] => (&default_url, &default_url),
+            [ref url] => (url, url),
+            [ref url_a, ref url_b] => (url_a, url_b),
+            _ => return Err(anyhow!("Pass at most two urls with `--url`")),
+        };
+        let artifacts = ContractArtifacts::from_manifest_or_file(
+            self.manifest_path.as_ref(),
+            self.file.as_ref(),
+        )?;
+        let transcoder = artifacts.contract_transcoder()?;
+        let metadata = artifacts.metadata()?;
+        let storage_access = metadata.storage_access;
+        let abi = serde_json::Value::Object(metadata.abi);
+        let root = abi
+            .pointer("/storage/root")
+            .ok_or_else(|| anyhow!("The metadata has no ink! 4 storage layout"))?;
+        let decode = |ty: u32, input: &mut &[u8]| -> Result<String> {
+            Ok(transcoder.decode(ty, input)?.to_string())
+        };
+
+        let client_a = OnlineClient::<DefaultConfig>::from_url(url_a.as_str()).await?;
+        let client_b = if url_a == url_b {
+            client_a.clone()
+        } else {
+            OnlineClient::<DefaultConfig>::from_url(url_b.as_str()).await?
+        };
+        let no_checkpoint = CheckpointOpts::default();
+        let storage_a = read_storage(&client_a, contract_a, None, &no_checkpoint).await?;
+        let storage_b = read_storage(&client_b, contract_b, None, &no_checkpoint).await?;
+        if storage_a.code_hash != storage_b.code_hash {
+            eprintln!(
+                "warning: the contracts run different code, {:?} and {:?}",
+                storage_a.code_hash, storage_b.code_hash
+            );
+        }
+        let fields_a = decode_storage(root, &storage_a.cells, &decode);
+        let fields_b = decode_storage(root, &storage_b.cells, &decode);
+        let mut divergent = diff(&fields_a, &fields_b);
+        if let Some(ref access) = storage_access {
+            for field in &mut divergent {
+                // The accesses are of the top-level fields, e.g. `config` of
+                // `config.fee`.
+                let root = field.field.split(['.', '[']).next().unwrap_or_default();
+                field.written_by = access.writers(root).map(String::from).collect();
+            }
+        }
+
+        if self.output_json {
+            println!("{}", serde_json::to_string_pretty(&divergent)?);
+        } else if divergent.is_empty() {
+            println!("The storage of {contract_a} and {contract_b} is the same");
+        } else {
+            let missing = String::from("<none>");
+            println!(
+                "Divergent fields of {contract_a} ({url_a}) and {contract_b} ({url_b}):"
+            );
+            for field in &divergent {
+                let truncate = |value: &Option<String>| {
+                    output::truncate(
+                        value.as_ref().unwrap_or(&missing),
+                        self.max_output_bytes,
+                    )
+                };
+                let written_by = if field.written_by.is_empty() {
+                    String::new()
+                } else {
+                    format!(" (written by {})", field.written_by.join(", "))
+                };
+                println!(
+                    "  {}: {} | {}{written_by}",
+                    field.field,
+                    truncate(&field.a),
+                    truncate(&field.b)
+                );
+            }
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, clap::Args)]
+struct AccessCommand {
+    /// Path to a contract build artifact file, a `.contract` bundle or a `.json`
+    /// metadata file.
+    #[clap(long, value_parser, conflicts_with = "manifest_path")]
+    file: Option<PathBuf>,
+    /// Path to the `Cargo.toml` of the contract.
+    #[clap(long, value_parser)]
+    manifest_path: Option<PathBuf>,
+    /// Export the storage accesses in JSON format.
+    #[clap(long)]
+    output_json: bool,
+}
+
+impl AccessCommand {
+    fn run(&self) -> Result<()> {
+        let artifacts = ContractArtifacts::from_manifest_or_file(
+            self.manifest_path.as_ref(),
+            self.file.as_ref(),
+        )?;
+        let access = artifacts.metadata()?.storage_access.ok_or_else(|| {
+            anyhow!(
+                "The metadata has no storage accesses, rebuild the contract with this \
+                version of cargo-contract"
+            )
+        })?;
+        if self.output_json {
+            println!("{}", serde_json::to_string_pretty(&access)?);
+            return Ok(())
+        }
+        let fields = |fields: &std::collections::BTreeSet<String>| {
+            if fields.is_empty() {
+                String::from("-")
+            } else {
+                fields.iter().cloned().collect::<Vec<_>>().join(", ")
+            }
+        };
+        for (heading, callables) in [
+            ("Constructors", &access.constructors),
+            ("Messages", &access.messages),
+        ] {
+            println!("{heading}:");
+            for (label, access) in callables {
+                println!(
+                    "  {label}: reads {}, writes {}",
+                    fields(&access.reads),
+                    fields(&access.writes)
+                );
+            }
+        }
+        Ok(())
+    }
+}
+
+/// The raw storage of a contract.
+struct ContractStorage {
+    code_hash: Hash,
+    /// The values of the cells by their ink! storage key.
+    cells: BTreeMap<Vec<u8>, Vec<u8>>,
+}
+
+/// Read all storage cells of the contract at the best block.
+///
+/// The progress is written to the checkpoint, a resumed read continues at the block of
+/// the checkpoint.
+async fn read_storage(
+    client: &Client,
+    contract: &AccountId,
+    at: Option<Hash>,
+    checkpoint: &CheckpointOpts,
+) -> Result<ContractStorage> {
+    let (mut checkpoint, resumed) = checkpoint.open(&format!("storage of {contract}"))?;
+    let mut state = match resumed {
+        Some(state) => state,
+        None => {
+            let block = match at {
+                Some(hash) => client.blocks().at(hash).await?,
+                None => client.blocks().at_latest().await?,
+            };
+            let info = block
+                .storage()
+                .fetch(&api::storage().contracts().contract_info_of(contract))
+                .await?
+                .ok_or_else(|| anyhow!("No contract was found at {contract}"))?;
+            StorageCheckpoint {
+                code_hash: info.code_hash,
+                trie_id: Bytes(info.trie_id.0),
+                scan: ChildStorageScan::new(block.hash()),
+            }
+        }
+    };
+    loop {
+        match state.scan.next_page(client, &state.trie_id.0).await {
+            Ok(true) => checkpoint.save(&state)?,
+            Ok(false) => break,
+            Err(err) => return Err(checkpoint.interrupted(&state, err)),
+        }
+    }
+    checkpoint.finish()?;
+    let cells = state
+        .scan
+        .into_pairs()
+        .into_iter()
+        // The keys are `Blake2_128Concat` hashes of the ink! storage keys.
+        .filter_map(|(key, value)| Some((key.get(16..)?.to_vec(), value)))
+        .collect();
+    Ok(ContractStorage {
+        code_hash: state.code_hash,
+        cells,
+    })
+}
+
+/// The progress of reading the storage of a contract.
+#[derive(serde::Serialize, serde::Deserialize)]
+struct StorageCheckpoint {
+    code_hash: Hash,
+    trie_id: Bytes,
+    scan: ChildStorageScan,
+}
+
+/// Read the raw keys and values of the child trie at the block.
+pub(crate) async fn read_child_storage(
+    client: &Client,
+    trie_id: &[u8],
+    block_hash: Hash,
+) -> Result<Vec<(Vec<u8>, Vec<u8>)>> {
+    let mut scan = ChildStorageScan::new(block_hash);
+    while scan.next_page(client, trie_id).await? {}
+    Ok(scan.into_pairs())
+}
+
+/// A read of the keys and values of a child trie at a block, a page of keys at a time.
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+struct ChildStorageScan {
+    block_hash: Hash,
+    /// The last key read, the next page starts after it.
+    start_key: Option<Bytes>,
+    /// The keys and values read so far.
+    pairs: Vec<(Bytes, Bytes)>,
+    done: bool,
+}
+
+impl ChildStorageScan {
+    fn new(block_hash: Hash) -> Self {
+        Self {
+            block_hash,
+            start_key: None,
+            pairs: Vec::new(),
+            done: false,
+        }
+    }
+
+    /// Read the next page of keys and their values, returns `false` once all pages were
+    /// read.
+    async fn next_page(&mut self, client: &Client, trie_id: &[u8]) -> Result<bool> {
+        if self.done {
+            return Ok(false)
+        }
+        let child_storage_key = format!(
+            "0x{}",
+            hex::encode([CHILD_STORAGE_PREFIX, trie_id].concat())
+        );
+        let start_key = self
+            .start_key
+            .as_ref()
+            .map(|key| format!("0x{}", hex::encode(&key.0)));
+        pacing::pace().await;
+        let keys: Vec<Bytes> = client
+            .rpc()
+            .request(
+                "childstate_getKeysPaged",
+                rpc_params![
+                    &child_storage_key,
+                    "0x",
+                    DEFAULT_PAGE_SIZE,
+                    &start_key,
+                    self.block_hash
+                ],
+            )
+            .await
+            .context("The node does not serve the keys of child storage")?;
+        let mut pairs = Vec::new();
+        for key in &keys {
+            pacing::pace().await;
+            let value: Option<Bytes> = client
+                .rpc()
+                .request(
+                    "childstate_getStorage",
+                    rpc_params![&child_storage_key, key, self.block_hash],
+                )
+                .await?;
+            if let Some(value) = value {
+                pairs.push((key.clone(), value));
+            }
+        }
+        // The page is only recorded once all of its values were read.
+        self.pairs.extend(pairs);
+        self.done = keys.len() < DEFAULT_PAGE_SIZE as usize;
+        if let Some(last) = keys.last() {
+            self.start_key = Some(last.clone());
+        }
+        Ok(!self.done)
+    }
+
+    /// The keys and values read.
+    fn into_pairs(self) -> Vec<(Vec<u8>, Vec<u8>)> {
+        self.pairs
+            .into_iter()
+            .map(|(key, value)| (key.0, value.0))
+            .collect()
+    }
+}
+
+/// Decode the cells of a contract with the storage layout of its root, into the values
+/// of its fields by their path, e.g. `owner`, `config.fee` or `balances[0x…]`.
+///
+/// The entries of a `Mapping` are the cells under its root key followed by the key of
+/// the entry. Cells not described by the layout are kept by their key.
+fn decode_storage(
+    root: &serde_json::Value,
+    cells: &BTreeMap<Vec<u8>, Vec<u8>>,
+    decode: &dyn Fn(u32, &mut &[u8]) -> Result<String>,
+) -> BTreeMap<String, String> {
+    let mut decoder = LayoutDecoder {
+        cells,
+        decode,
+        used: Default::default(),
+        fields: Default::default(),
+    };
+    decoder.root(root, "");
+    let LayoutDecoder {
+        used, mut fields, ..
+    } = decoder;
+    for (key, value) in cells {
+        if !used.contains(key) {
+            fields.insert(
+                format!("0x{}", hex::encode(key)),
+                format!("0x{}", hex::encode(value)),
+            );
+        }
+    }
+    fields
+}
+
+struct LayoutDecoder<'a> {
+    cells: &'a BTreeMap<Vec<u8>, Vec<u8>>,
+    decode: &'a dyn Fn(u32, &mut &[u8]) -> Result<String>,
+    used: std::collections::HashSet<Vec<u8>>,
+    fields: BTreeMap<String, String>,
+}
+
+impl LayoutDecoder<'_> {
+    /// Decode the cells under the root key of a `root` layout.
+    fn root(&mut self, root: &serde_json::Value, path: &str) {
+        let Some(key) = root
+            .get("root_key")
+            .and_then(|key| key.as_str())
+            .and_then(|key| contract_build::util::decode_hex(key).ok())
+        else {
+            return
+        };
+        let Some(layout) = root.get("layout") else {
+            return
+        };
+        let cells = self.cells;
+        for (cell_key, value) in cells.range(key.clone()..) {
+            if !cell_key.starts_with(&key) {
+                break
+            }
+            self.used.insert(cell_key.clone());
+            let path = if cell_key.len() == key.len() {
+                path.to_string()
+            } else {
+                format!("{path}[0x{}]", hex::encode(&cell_key[key.len()..]))
+            };
+            let mut input = Some(value.as_slice());
+            self.packed(layout, &path, &mut input);
+        }
+    }
+
+    /// Decode the packed layout from the input, `None` once it can not be decoded.
+    fn packed(
+        &mut self,
+        layout: &serde_json::Value,
+        path: &str,
+        input: &mut Option<&[u8]>,
+    ) {
+        let join = |name: &str| {
+            if path.is_empty() {
+                name.to_string()
+            } else {
+                format!("{path}.{name}")
+            }
+        };
+        if let Some(leaf) = layout.get("leaf") {
+            let value = match (input.as_mut(), leaf.get("ty").and_then(|ty| ty.as_u64()))
+            {
+                (Some(data), Some(ty)) => (self.decode)(ty as u32, data).ok(),
+                _ => None,
+            };
+            if value.is_none() {
+                *input = None;
+            }
+            self.fields.insert(
+                path.to_string(),
+                value.unwrap_or_else(|| String::from("<undecodable>")),
+            );
+        } else if let Some(fields) =
+            layout.pointer("/struct/fields").and_then(|f| f.as_array())
+        {
+            for field in fields {
+                let name = field
+                    .get("name")
+                    .and_then(|name| name.as_str())
+                    .unwrap_or("?");
+                if let Some(layout) = field.get("layout") {
+                    self.packed(layout, &join(name), input);
+                }
+            }
+        } else if let Some(root) = layout.get("root") {
+            // Stored in the cells under its own root key, not packed.
+            self.root(root, path);
+        } else if let Some(variants) = layout.pointer("/enum/variants") {
+            let discriminant = input.as_mut().and_then(|data| {
+                let (first, rest) = data.split_first()?;
+                *data = rest;
+                Some(*first)
+            });
+            match discriminant.and_then(|d| variants.get(d.to_string())) {
+                Some(variant) => {
+                    let name =
+                        variant.get("name").and_then(|n| n.as_str()).unwrap_or("?");
+                    self.fields.insert(path.to_string(), name.to_string());
+                    for field in variant
+                        .get("fields")
+                        .and_then(|f| f.as_array())
+                        .into_iter()
+                        .flatten()
+                    {
+                        let field_name = field
+                            .get("name")
+                            .and_then(|name| name.as_str())
+                            .unwrap_or("?");
+                        if let Some(layout) = field.get("layout") {
+                            self.packed(
+                                layout,
+                                &join(&format!("{name}.{field_name}")),
+                                input,
+                            );
+                        }
+                    }
+                }
+                None => {
+                    *input = None;
+                    self.fields
+                        .insert(path.to_string(), String::from("<undecodable>"));
+                }
+            }
+        } else if let Some(array) = layout.get("array") {
+            let len = array.get("len").and_then(|len| len.as_u64()).unwrap_or(0);
+            if let Some(layout) = array.get("layout") {
+                for i in 0..len {
+                    self.packed(layout, &format!("{path}[{i}]"), input);
+                }
+            }
+        } else {
+            *input = None;
+            self.fields
+                .insert(path.to_string(), String::from("<unsupported layout>"));
+        }
+    }
+}
+
+/// Returns the fields whose values differ, by their path.
+fn diff(
+    a: &BTreeMap<String, String>,
+    b: &BTreeMap<String, String>,
+) -> Vec<DivergentField> {
+    let paths = a
+        .keys()
+        .chain(b.keys())
+        .collect::<std::collections::BTreeSet<_>>();
+    paths
+        .into_iter()
+        .filter(|path| a.get(*path) != b.get(*path))
+        .map(|path| {
+            DivergentField {
+                field: path.clone(),
+                a: a.get(path).cloned(),
+                b: b.get(path).cloned(),
+                written_by: Vec::new(),
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use sp_trie::{
+        trie_types::TrieDBMutBuilderV1,
+        MemoryDB,
+        TrieMut,
+    };
+
+    #[test]
+    fn fields_resolve_to_their_cell() {
+        let abi = serde_json::json!({
+            "storage": { "root": {
+                "root_key": "0x00000000",
+                "layout": { "struct": { "name": "Erc20", "fields": [
+                    { "name": "total_supply", "layout": { "leaf": { "key": "0x00000000", "ty": 0 } } },
+                    { "name": "balances", "layout": { "root": {
+                        "root_key": "0x7e3f7b44",
+                        "layout": { "leaf": { "key": "0x7e3f7b44", "ty": 0 } }
+                    } } }
+                ] } }
+            } }
+        });
+        assert_eq!(field_key(&abi, "total_supply").unwrap(), [0, 0, 0, 0]);
+        assert_eq!(
+            field_key(&abi, "balances").unwrap(),
+            [0x7e, 0x3f, 0x7b, 0x44]
+        );
+        assert!(field_key(&abi, "allowances").is_err());
+        assert!(field_root_key(&abi, "total_supply").is_err());
+        assert_eq!(
+            field_root_key(&abi, "balances").unwrap(),
+            [0x7e, 0x3f, 0x7b, 0x44]
+        );
+    }
+
+    #[test]
+    fn divergent_fields_are_decoded() {
+        let root = serde_json::json!({
+            "root_key": "0x00000000",
+            "layout": { "struct": { "name": "Token", "fields": [
+                { "name": "owner", "layout": { "leaf": { "key": "0x00000000", "ty": 0 } } },
+                { "name": "paused", "layout": { "leaf": { "key": "0x00000000", "ty": 0 } } },
+                { "name": "balances", "layout": { "root": {
+                    "root_key": "0x01000000",
+                    "layout": { "leaf": { "key": "0x01000000", "ty": 0 } }
+                } } }
+            ] } }
+        });
+        // Every value is decoded as a single byte.
+        let decode = |_: u32, input: &mut &[u8]| -> Result<String> {
+            let (first, rest) = input.split_first().ok_or_else(|| anyhow!("empty"))?;
+            *input = rest;
+            Ok(first.to_string())
+        };
+        let cells = |paused: u8, balance: Option<u8>| {
+            let mut cells = BTreeMap::from([(vec![0, 0, 0, 0], vec![7, paused])]);
+            if let Some(balance) = balance {
+                cells.insert(vec![1, 0, 0, 0, 0xaa], vec![balance]);
+            }
+            cells.insert(vec![9, 9, 9, 9], vec![1]);
+            cells
+        };
+        let a = decode_storage(&root, &cells(0, Some(5)), &decode);
+        let b = decode_storage(&root, &cells(1, None), &decode);
+        assert_eq!(a.get("owner").map(String::as_str), Some("7"));
+        assert_eq!(a.get("balances[0xaa]").map(String::as_str), Some("5"));
+        assert_eq!(a.get("0x09090909").map(String::as_str), Some("0x01"));
+
+        assert_eq!(
+            diff(&a, &b),
+            [
+                DivergentField {
+                    field: String::from("balances[0xaa]"),
+                    a: Some(String::from("5")),
+                    b: None,
+                    written_by: Vec::new(),
+                },
+                DivergentField {
+                    field: String::from("paused"),
+                    a: Some(String::from("0")),
+                    b: Some(String::from("1")),
+                    written_by: Vec::new(),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn raw_cells_are_dumped_in_hex() {
+        let cells = BTreeMap::from([
+            (vec![0, 0, 0, 0], vec![1, 2]),
+            (vec![0x7e, 0x3f, 0x7b, 0x44, 0xaa], vec![]),
+        ]);
+        assert_eq!(
+            raw_entries(&cells),
+            BTreeMap::from([
+                (String::from("0x00000000"), String::from("0x0102")),
+                (String::from("0x7e3f7b44aa"), String::from("0x")),
+            ])
+        );
+    }
+
+    #[test]
+    fn entries_are_read_from_proofs() {
+        let key = hashed_key(&[0, 0, 0, 0]);
+        assert_eq!(&key[16..], [0, 0, 0, 0]);
+
+        let mut db = MemoryDB::<Blake2Hasher>::default();
+        let mut root = H256::zero();
+        {
+            let mut trie = TrieDBMutBuilderV1::new(&mut db, &mut root).build();
+            trie.insert(&key, b"value").unwrap();
+            trie.insert(&hashed_key(&[1, 0, 0, 0]), b"other").unwrap();
+        }
+        let nodes = db
+            .drain()
+            .into_values()
+            .filter(|(_, rc)| *rc > 0)
+            .map(|(node, _)| node)
+            .collect::<Vec<_>>();
+        assert_eq!(
+            read_proof(&nodes, &root, &key).unwrap(),
+            Some(b"value".to_vec())
+        );
+        assert!(read_proof(&nodes[..0], &root, &key).is_err());
+    }
+}
@@ -0,0 +1,222 @@
+// Copyright 2018-2023 Parity Technologies (UK) Ltd.
+// This file is part of cargo-contract.
+//
+// cargo-contract is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// cargo-contract is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with cargo-contract.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Rendering the results of the commands through [Tera](https://keats.github.io/tera/)
+//! templates.
+//!
+//! The command is run again with `--output-json` in a child process, and its JSON result
+//! is rendered through the template as `result`, together with the `command` name, e.g.
+//! into a markdown deployment announcement.
+
+use anyhow::{
+    Context,
+    Result,
+};
+use clap::Args;
+use std::{
+    ffi::OsString,
+    path::PathBuf,
+    process::{
+        Command,
+        Stdio,
+    },
+};
+
+/// Arguments rendering the result of a command through a template, accepted by every
+/// command with an `--output-json` flag.
+#[derive(Debug, Default, Args)]
+pub struct TemplateArgs {
+    /// Render the JSON result of the command through the Tera template, e.g.
+    /// `"Deployed {{ result.contract }}"`.
+    #[clap(
+        long,
+        value_name = "TERA_TEMPLATE",
+        global = true,
+        conflicts_with = "format_template_file"
+    )]
+    format_template: Option<String>,
+    /// Render the JSON result of the command through the Tera template in the file.
+    #[clap(long, value_name = "PATH", global = true)]
+    format_template_file: Option<PathBuf>,
+}
+
+impl TemplateArgs {
+    /// The template given, if any.
+    pub fn template(&self) -> Result<Option<String>> {
+        match (&self.format_template, &self.format_template_file) {
+            (Some(template), _) => Ok(Some(template.clone())),
+            (None, Some(path)) => {
+                let template = std::fs::read_to_string(path).with_context(|| {
+                    format!("Failed to read the template {}", path.display())
+                })?;
+                Ok(Some(template))
+            }
+            (None, None) => Ok(None),
+        }
+    }
+}
+
+/// Run the command of the arguments with `--output-json` and print its result rendered
+/// through the template, returns the exit code of the command.
+///
+/// If the command fails, its output is printed unchanged.
+pub fn run_rendered(
+    cli: &clap::Command,
+    args: Vec<OsString>,
+    template: &str,
+) -> Result<i32> {
+    let matches = cli.clone().try_get_matches_from(&args)?;
+    let (path, command) = leaf_command(cli, &matches);
+    anyhow::ensure!(
+        command
+            .get_arguments()
+            .any(|arg| arg.get_id() == "output_json"),
+        "`{path}` has no JSON output to render with `--format-template`"
+    );
+
+    let mut args = strip_template_args(args);
+    // Arguments after `--` are passed on, e.g. to the node.
+    let end = args
+        .iter()
+        .position(|arg| arg == "--")
+        .unwrap_or(args.len());
+    args.insert(end, OsString::from("--output-json"));
+    let output = Command::new(std::env::current_exe()?)
+        .args(&args[1..])
+        .stdin(Stdio::inherit())
+        .stderr(Stdio::inherit())
+        .output()
+        .context("Failed to run the command")?;
+    if !output.status.success() {
+        print!("{}", String::from_utf8_lossy(&output.stdout));
+        return Ok(output.status.code().unwrap_or(1))
+    }
+    let result: serde_json::Value = serde_json::from_slice(&output.stdout)
+        .with_context(|| format!("The output of `{path}` is not JSON"))?;
+    print!("{}", render(template, &path, result)?);
+    Ok(0)
+}
+
+/// Render the result of the command through the template.
+pub fn render(
+    template: &str,
+    command: &str,
+    result: serde_json::Value,
+) -> Result<String> {
+    let mut context = tera::Context::new();
+    context.insert("command", command);
+    context.insert("result", &result);
+    tera::Tera::one_off(template, &context, false).map_err(|err| {
+        // The cause holds the position of a syntax error in the template.
+        let cause = std::error::Error::source(&err)
+            .map(|cause| format!(": {cause}"))
+            .unwrap_or_default();
+        anyhow::anyhow!("Failed to render the template{cause}")
+    })
+}
+
+/// The names and the definition of the innermost subcommand of the matches, below the
+/// `contract` subcommand.
+fn leaf_command<'a>(
+    cli: &'a clap::Command,
+    matches: &clap::ArgMatches,
+) -> (String, &'a clap::Command) {
+    let mut names = Vec::new();
+    let (mut command, mut matches) = (cli, matches);
+    while let Some((name, sub_matches)) = matches.subcommand() {
+        let Some(subcommand) = command.find_subcommand(name) else {
+            break
+        };
+        if command.get_name() != cli.get_name() {
+            names.push(name);
+        }
+        (command, matches) = (subcommand, sub_matches);
+    }
+    (names.join(" "), command)
+}
+
+/// Remove the template arguments, so that the child process prints the JSON result.
+fn strip_template_args(args: Vec<OsString>) -> Vec<OsString> {
+    let mut stripped = Vec::with_capacity(args.len());
+    let mut args = args.into_iter();
+    while let Some(arg) = args.next() {
+        if arg == "--" {
+            stripped.push(arg);
+            stripped.extend(args.by_ref());
+        } else if arg == "--format-template" || arg == "--format-template-file" {
+            args.next();
+        } else if !arg.to_str().is_some_and(|arg| {
+            arg.starts_with("--format-template=")
+                || arg.starts_with("--format-template-file=")
+        }) {
+            stripped.push(arg);
+        }
+    }
+    stripped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn results_are_rendered_through_templates() {
+        let result = serde_json::json!({
+            "contract": "5GrwvaEF5zXb26Fz9rcQpDWS57CtERHpNehXCPcNoHGKutQY",
+            "events": [{ "name": "Instantiated" }, { "name": "ContractEmitted" }],
+        });
+        let rendered = render(
+            "`{{ command }}`: {{ result.contract }} with {{ result.events | length }} \
+             events{% for event in result.events %}, {{ event.name }}{% endfor %}",
+            "instantiate",
+            result,
+        )
+        .unwrap();
+        assert_eq!(
+            rendered,
+            "`instantiate`: 5GrwvaEF5zXb26Fz9rcQpDWS57CtERHpNehXCPcNoHGKutQY with 2 \
+             events, Instantiated, ContractEmitted"
+        );
+        assert!(render("{{ result.", "info", serde_json::json!({})).is_err());
+
+        let args = [
+            "cargo",
+            "contract",
+            "info",
+            "--format-template",
+            "{{ result }}",
+            "--format-template-file=announce.md",
+            "--contract",
+            "5Grw",
+            "--",
+            "--format-template",
+        ]
+        .map(OsString::from);
+        assert_eq!(
+            strip_template_args(args.to_vec()),
+            [
+                "cargo",
+                "contract",
+                "info",
+                "--contract",
+                "5Grw",
+                "--",
+                "--format-template"
+            ]
+            .map(OsString::from)
+        );
+    }
+}
@@ -0,0 +1,551 @@
+// Copyright 2018-2023 Parity Technologies (UK) Ltd.
+// This file is part of cargo-contract.
+//
+// cargo-contract is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// cargo-contract is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with cargo-contract.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Trends of the code size, build time and benchmarks of contracts over the commits of
+//! their repository.
+//!
+//! `trends record` appends the metrics of the last build of a contract to the
+//! `contract-trends.jsonl` file of the current directory, one JSON record per line keyed
+//! by the git commit, or to the file at the path in the `CARGO_CONTRACT_TRENDS`
+//! environment variable. Recording a commit again replaces its record. Benchmark results
+//! are recorded with `--metric NAME=VALUE` or from a JSON object of `--bench`.
+//!
+//! `trends` renders the changes of the metrics over the last commits and flags the
+//! regressions, or exports them as the time series of a Grafana JSON data source.
+
+use super::{
+    code_size::CodeSize,
+    extrinsics::ContractArtifacts,
+    lock::{
+        self,
+        FileLock,
+    },
+    provenance::git_commit,
+    AsyncCommand,
+};
+use anyhow::{
+    Context,
+    Result,
+};
+use async_trait::async_trait;
+use colored::Colorize;
+use contract_build::{
+    BuildLog,
+    CrateMetadata,
+    ManifestPath,
+    Target,
+    BUILD_LOG_FILE,
+};
+use std::{
+    collections::BTreeMap,
+    fmt::Write as _,
+    fs,
+    path::{
+        Path,
+        PathBuf,
+    },
+    time::{
+        SystemTime,
+        UNIX_EPOCH,
+    },
+};
+
+/// The default file name of the trends store, relative to the current directory.
+pub const TRENDS_FILE_NAME: &str = "contract-trends.jsonl";
+
+/// The environment variable overriding the path of the trends store.
+pub const TRENDS_PATH_ENV: &str = "CARGO_CONTRACT_TRENDS";
+
+/// The metrics of a contract at a commit.
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct TrendRecord {
+    /// The git commit the contract was built from.
+    pub commit: String,
+    /// Whether the working tree had uncommitted changes.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub dirty: bool,
+    /// When the record was written, in milliseconds since the Unix epoch.
+    pub timestamp: u64,
+    /// The name of the contract.
+    pub contract: String,
+    /// The metrics by name, e.g. `code_size` in bytes or `build_secs`. Lower values are
+    /// better.
+    pub metrics: BTreeMap<String, f64>,
+}
+
+/// The records of the trends store, in the order they were recorded.
+#[derive(Debug, Default, PartialEq)]
+pub struct Trends {
+    pub records: Vec<TrendRecord>,
+}
+
+impl Trends {
+    /// Returns the path of the trends store.
+    pub fn path() -> PathBuf {
+        std::env::var_os(TRENDS_PATH_ENV)
+            .map(PathBuf::from)
+            .unwrap_or_else(|| PathBuf::from(TRENDS_FILE_NAME))
+    }
+
+    /// Parse the records of a JSON Lines store.
+    pub fn parse(contents: &str) -> Result<Self> {
+        let records = contents
+            .lines()
+            .enumerate()
+            .filter(|(_, line)| !line.trim().is_empty())
+            .map(|(index, line)| {
+                serde_json::from_str(line)
+                    .with_context(|| format!("Invalid record on line {}", index + 1))
+            })
+            .collect::<Result<_>>()?;
+        Ok(Self { records })
+    }
+
+    /// Load the store, an empty store if the file does not exist.
+    pub fn load(path: &Path) -> Result<Self> {
+        if !path.exists() {
+            return Ok(Self::default())
+        }
+        let contents = fs::read_to_string(path)
+            .with_context(|| format!("Failed to read {}", path.display()))?;
+        Self::parse(&contents).with_context(|| format!("Invalid {}", path.display()))
+    }
+
+    /// Serialize the records as JSON Lines.
+    pub fn to_json_lines(&self) -> Result<String> {
+        let mut contents = String::new();
+        for record in &self.records {
+            contents.push_str(&serde_json::to_string(record)?);
+            contents.push('\n');
+        }
+        Ok(contents)
+    }
+
+    /// Add the record, replacing the record of the same contract at the same commit.
+    pub fn insert(&mut self, record: TrendRecord) {
+        self.records.retain(|existing| {
+            existing.commit != record.commit || existing.contract != record.contract
+        });
+        self.records.push(record)
+    }
+
+    /// Append the record to the store at the path, holding a lock on the file.
+    pub fn record(path: &Path, record: TrendRecord) -> Result<()> {
+        let _lock = FileLock::acquire(path)?;
+        let mut trends = Self::load(path)?;
+        trends.insert(record);
+        lock::write_atomic(path, trends.to_json_lines()?)
+            .with_context(|| format!("Failed to write {}", path.display()))
+    }
+
+    /// Returns the records of the contract at its last `last` commits, the oldest
+    /// first.
+    pub fn window(&self, contract: &str, last: usize) -> Vec<&TrendRecord> {
+        let records = self
+            .records
+            .iter()
+            .filter(|record| record.contract == contract)
+            .collect::<Vec<_>>();
+        let skip = records.len().saturating_sub(last);
+        records.into_iter().skip(skip).collect()
+    }
+
+    /// Returns the names of the recorded contracts, in the order they were first
+    /// recorded.
+    pub fn contracts(&self) -> Vec<&str> {
+        let mut contracts = Vec::<&str>::new();
+        for record in &self.records {
+            if !contracts.contains(&record.contract.as_str()) {
+                contracts.push(&record.contract)
+            }
+        }
+        contracts
+    }
+}
+
+/// The value of a metric at a commit, and its change from the previous commit.
+#[derive(Debug, Clone, PartialEq, serde::Serialize)]
+pub struct TrendPoint {
+    pub commit: String,
+    pub timestamp: u64,
+    pub value: f64,
+    /// The change from the previous commit in percent, `None` for the first commit.
+    pub change: Option<f64>,
+}
+
+/// The values of a metric of a contract over the commits.
+#[derive(Debug, Clone, PartialEq, serde::Serialize)]
+pub struct Trend {
+    pub contract: String,
+    pub metric: String,
+    pub points: Vec<TrendPoint>,
+}
+
+impl Trend {
+    /// Collect the trends of every metric of the records, which are of one contract.
+    pub fn collect(records: &[&TrendRecord]) -> Vec<Self> {
+        let mut trends = BTreeMap::<&str, Self>::new();
+        for record in records {
+            for (metric, value) in &record.metrics {
+                let trend = trends.entry(metric).or_insert_with(|| {
+                    Self {
+                        contract: record.contract.clone(),
+                        metric: metric.clone(),
+                        points: Vec::new(),
+                    }
+                });
+                let change = trend.points.last().and_then(|previous| {
+                    (previous.value != 0.0)
+                        .then(|| (value - previous.value) / previous.value * 100.0)
+                });
+                trend.points.push(TrendPoint {
+                    commit: record.commit.clone(),
+                    timestamp: record.timestamp,
+                    value: *value,
+                    change,
+                })
+            }
+        }
+        trends.into_values().collect()
+    }
+
+    /// Returns the points increasing the metric by more than `threshold` percent.
+    pub fn regressions(&self, threshold: f64) -> Vec<&TrendPoint> {
+        self.points
+            .iter()
+            .filter(|point| point.change.is_some_and(|change| change > threshold))
+            .collect()
+    }
+
+    /// Display the values of the metric by commit, flagging the regressions.
+    pub fn display(&self, threshold: f64) -> String {
+        let mut out = format!("{} {}\n", self.contract.bold(), self.metric);
+        for point in &self.points {
+            let commit = &point.commit[..point.commit.len().min(10)];
+            let change = match point.change {
+                Some(change) if change > threshold => {
+                    format!("{change:+.1}% regression").red().bold().to_string()
+                }
+                Some(change) => format!("{change:+.1}%"),
+                None => String::new(),
+            };
+            let _ = writeln!(out, "  {commit:<10} {:>14} {change}", point.value);
+        }
+        out
+    }
+}
+
+/// A time series of a Grafana JSON data source.
+#[derive(Debug, PartialEq, serde::Serialize)]
+pub struct GrafanaSeries {
+    /// The name of the series, `<contract> <metric>`.
+    pub target: String,
+    /// The `[value, timestamp in milliseconds]` pairs, the oldest first.
+    pub datapoints: Vec<(f64, u64)>,
+}
+
+impl From<&Trend> for GrafanaSeries {
+    fn from(trend: &Trend) -> Self {
+        Self {
+            target: format!("{} {}", trend.contract, trend.metric),
+            datapoints: trend
+                .points
+                .iter()
+                .map(|point| (point.value, point.timestamp))
+                .collect(),
+        }
+    }
+}
+
+/// Parse a `NAME=VALUE` metric.
+fn parse_metric(metric: &str) -> Result<(String, f64)> {
+    let (name, value) = metric
+        .split_once('=')
+        .context("Expected a metric as `NAME=VALUE`")?;
+    let value = value
+        .trim()
+        .parse()
+        .with_context(|| format!("Invalid value of the metric `{name}`"))?;
+    Ok((name.trim().to_string(), value))
+}
+
+#[derive(Debug, clap::Args)]
+#[clap(
+    name = "trends",
+    about = "Record and render the code size, build time and benchmarks by commit"
+)]
+pub struct TrendsCommand {
+    #[clap(subcommand)]
+    action: Option<TrendsAction>,
+    /// Path to the trends store, by default `contract-trends.jsonl` of the current
+    /// directory.
+    #[clap(long, value_parser, env = TRENDS_PATH_ENV)]
+    store: Option<PathBuf>,
+    /// The number of most recent commits rendered.
+    #[clap(long, default_value_t = 10)]
+    last: usize,
+    /// The increase from the previous commit, in percent, flagged as a regression.
+    #[clap(long, default_value_t = 5.0)]
+    threshold: f64,
+    /// Only render the trends of the contract with the given name.
+    #[clap(long)]
+    contract: Option<String>,
+    /// Fail if any metric of the most recent commit regressed.
+    #[clap(long)]
+    fail_on_regression: bool,
+    /// Export the trends as the time series of a Grafana JSON data source.
+    #[clap(long, conflicts_with = "output_csv")]
+    output_json: bool,
+    /// Export the trends as CSV, one row per contract, metric and commit.
+    #[clap(long)]
+    output_csv: bool,
+}
+
+#[derive(Debug, clap::Subcommand)]
+enum TrendsAction {
+    /// Record the code size and build time of the last build of the contract at the
+    /// checked out commit
+    Record {
+        /// Path to the `Cargo.toml` of the contract.
+        #[clap(long, value_parser)]
+        manifest_path: Option<PathBuf>,
+        /// A benchmark result to record, as `NAME=VALUE`. Can be repeated.
+        #[clap(long = "metric", value_name = "NAME=VALUE")]
+        metrics: Vec<String>,
+        /// A JSON object of benchmark results to record, by name.
+        #[clap(long, value_parser)]
+        bench: Option<PathBuf>,
+    },
+}
+
+#[async_trait(?Send)]
+impl AsyncCommand for TrendsCommand {
+    type Output = Result<()>;
+
+    async fn exec(&self) -> Result<()> {
+        let path = self.store.clone().unwrap_or_else(Trends::path);
+        match self.action {
+            Some(TrendsAction::Record {
+                ref manifest_path,
+                ref metrics,
+                ref bench,
+            }) => {
+                let record = record(manifest_path.as_ref(), metrics, bench.as_deref())?;
+                let (contract, commit) = (record.contract.clone(), record.commit.clone());
+                Trends::record(&path, record)?;
+                println!(
+                    "{} the metrics of {contract} at {commit} in {}",
+                    "Recorded".green().bold(),
+                    path.display()
+                );
+                Ok(())
+            }
+            None => self.render(&Trends::load(&path)?),
+        }
+    }
+}
+
+impl TrendsCommand {
+    /// Render the trends of the last commits of the recorded contracts.
+    fn render(&self, store: &Trends) -> Result<()> {
+        let trends = store
+            .contracts()
+            .into_iter()
+            .filter(|contract| {
+                self.contract
+                    .as_ref()
+                    .map_or(true, |selected| selected == contract)
+            })
+            .flat_map(|contract| Trend::collect(&store.window(contract, self.last)))
+            .collect::<Vec<_>>();
+        anyhow::ensure!(
+            !trends.is_empty(),
+            "No trends are recorded, record them with `cargo contract trends record`"
+        );
+
+        if self.output_json {
+            let series = trends.iter().map(GrafanaSeries::from).collect::<Vec<_>>();
+            println!("{}", serde_json::to_string_pretty(&series)?);
+        } else if self.output_csv {
+            println!("contract,metric,commit,timestamp,value");
+            for trend in &trends {
+                for point in &trend.points {
+                    println!(
+                        "{},{},{},{},{}",
+                        trend.contract,
+                        trend.metric,
+                        point.commit,
+                        point.timestamp,
+                        point.value
+                    );
+                }
+            }
+        } else {
+            for trend in &trends {
+                println!("{}", trend.display(self.threshold));
+            }
+        }
+
+        let regressed = trends
+            .iter()
+            .filter(|trend| {
+                trend
+                    .points
+                    .last()
+                    .and_then(|point| point.change)
+                    .is_some_and(|change| change > self.threshold)
+            })
+            .map(|trend| format!("{} {}", trend.contract, trend.metric))
+            .collect::<Vec<_>>();
+        if self.fail_on_regression && !regressed.is_empty() {
+            anyhow::bail!(
+                "The most recent commit regressed by more than {}%: {}",
+                self.threshold,
+                regressed.join(", ")
+            )
+        }
+        Ok(())
+    }
+}
+
+/// Collect the metrics of the last build of the contract at the checked out commit.
+fn record(
+    manifest_path: Option<&PathBuf>,
+    metrics: &[String],
+    bench: Option<&Path>,
+) -> Result<TrendRecord> {
+    let manifest = ManifestPath::try_from(manifest_path)?;
+    let crate_metadata = CrateMetadata::collect(&manifest, Target::Wasm)?;
+    let dir = manifest.absolute_directory()?;
+    let (commit, dirty) = git_commit(&dir)
+        .with_context(|| format!("Failed to find the git commit of {}", dir.display()))?;
+
+    let mut recorded = BTreeMap::new();
+    let artifacts = ContractArtifacts::from_manifest_or_file(manifest_path, None)?;
+    if let Some(ref code) = artifacts.code {
+        let size = CodeSize::analyze(code.as_bytes())?;
+        recorded.insert("code_size".to_string(), size.total as f64);
+    }
+    let build_log = crate_metadata.target_directory.join(BUILD_LOG_FILE);
+    if build_log.exists() {
+        let contents = fs::read_to_string(&build_log)?;
+        let build_log: BuildLog = serde_json::from_str(&contents)
+            .with_context(|| format!("Invalid {}", build_log.display()))?;
+        recorded.insert("build_secs".to_string(), build_log.total_secs());
+    }
+    if let Some(bench) = bench {
+        let contents = fs::read_to_string(bench)
+            .with_context(|| format!("Failed to read {}", bench.display()))?;
+        let results: BTreeMap<String, f64> = serde_json::from_str(&contents)
+            .with_context(|| format!("Invalid benchmark results {}", bench.display()))?;
+        recorded.extend(results);
+    }
+    for metric in metrics {
+        let (name, value) = parse_metric(metric)?;
+        recorded.insert(name, value);
+    }
+    if dirty {
+        eprintln!(
+            "{} the working tree has uncommitted changes, the metrics may not match the \
+            commit {commit}",
+            "warning:".yellow().bold()
+        );
+    }
+
+    let timestamp = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_millis() as u64)
+        .unwrap_or_default();
+    Ok(TrendRecord {
+        commit,
+        dirty,
+        timestamp,
+        contract: crate_metadata.contract_artifact_name,
+        metrics: recorded,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn record(commit: &str, timestamp: u64, code_size: f64) -> TrendRecord {
+        TrendRecord {
+            commit: commit.to_string(),
+            dirty: false,
+            timestamp,
+            contract: "flipper".to_string(),
+            metrics: BTreeMap::from([("code_size".to_string(), code_size)]),
+        }
+    }
+
+    #[test]
+    fn records_are_keyed_by_commit() {
+        let mut trends = Trends::default();
+        trends.insert(record("a", 1, 1000.0));
+        trends.insert(record("b", 2, 1100.0));
+        trends.insert(record("a", 3, 900.0));
+
+        let parsed = Trends::parse(&trends.to_json_lines().unwrap()).unwrap();
+        assert_eq!(parsed, trends);
+        assert_eq!(
+            parsed
+                .window("flipper", 1)
+                .iter()
+                .map(|record| record.commit.as_str())
+                .collect::<Vec<_>>(),
+            ["a"]
+        );
+        assert_eq!(parsed.window("flipper", 10).len(), 2);
+    }
+
+    #[test]
+    fn regressions_are_flagged_against_the_previous_commit() {
+        let records = [
+            record("a", 1, 1000.0),
+            record("b", 2, 1020.0),
+            record("c", 3, 1224.0),
+        ];
+        let trends = Trend::collect(&records.iter().collect::<Vec<_>>());
+        assert_eq!(trends.len(), 1);
+        let changes = trends[0]
+            .points
+            .iter()
+            .map(|point| point.change.map(|change| change.round()))
+            .collect::<Vec<_>>();
+        assert_eq!(changes, [None, Some(2.0), Some(20.0)]);
+        let regressions = trends[0].regressions(5.0);
+        assert_eq!(regressions.len(), 1);
+        assert_eq!(regressions[0].commit, "c");
+
+        let series = serde_json::to_value(GrafanaSeries::from(&trends[0])).unwrap();
+        let expected = serde_json::json!({
+            "target": "flipper code_size",
+            "datapoints": [[1000.0, 1], [1020.0, 2], [1224.0, 3]],
+        });
+        assert_eq!(series, expected);
+    }
+
+    #[test]
+    fn metrics_are_parsed() {
+        assert_eq!(
+            parse_metric("flip_gas = 1250").unwrap(),
+            ("flip_gas".to_string(), 1250.0)
+        );
+        assert!(parse_metric("flip_gas").is_err());
+        assert!(parse_metric("flip_gas=fast").is_err());
+    }
+}
@@ -0,0 +1,992 @@
+// Copyright 2018-2023 Parity Technologies (UK) Ltd.
+// This file is part of cargo-contract.
+//
+// cargo-contract is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// cargo-contract is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with cargo-contract.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Generation of typed client bindings from the metadata of a contract.
+//!
+//! The Rust bindings are a module with a struct or enum for every custom type of the
+//! arguments and return values, deriving the SCALE codec, and a function for every
+//! constructor and message encoding its input data and decoding its output. The
+//! TypeScript bindings are definitions of the types and of the constructors and messages,
+//! for `@polkadot/api-contract` users.
+
+use super::{
+    extrinsics::ContractArtifacts,
+    new_client::ts_method,
+    AsyncCommand,
+};
+use anyhow::{
+    anyhow,
+    Context,
+    Result,
+};
+use async_trait::async_trait;
+use scale_info::{
+    form::PortableForm,
+    Field,
+    PortableRegistry,
+    Type,
+    TypeDef,
+    TypeDefPrimitive,
+};
+use std::{
+    collections::{
+        BTreeMap,
+        BTreeSet,
+    },
+    fmt::Write,
+    path::PathBuf,
+};
+
+/// The language of the generated bindings.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, clap::ValueEnum)]
+pub enum Lang {
+    /// A Rust module using `parity-scale-codec`.
+    #[default]
+    Rust,
+    /// TypeScript definitions.
+    Ts,
+}
+
+#[derive(Debug, clap::Args)]
+#[clap(
+    name = "typegen",
+    about = "Generate typed client bindings from the contract metadata"
+)]
+pub struct TypegenCommand {
+    /// Path to a contract build artifact file: a `.contract` bundle or a `.json`
+    /// metadata file.
+    #[clap(value_parser, conflicts_with = "manifest_path")]
+    file: Option<PathBuf>,
+    /// Path to the `Cargo.toml` of the contract.
+    #[clap(long, value_parser)]
+    manifest_path: Option<PathBuf>,
+    /// The language of the bindings.
+    #[clap(long, value_enum, default_value_t)]
+    lang: Lang,
+    /// Write the bindings to the file instead of printing them.
+    #[clap(long, short)]
+    output: Option<PathBuf>,
+}
+
+#[async_trait(?Send)]
+impl AsyncCommand for TypegenCommand {
+    type Output = Result<()>;
+
+    async fn exec(&self) -> Result<()> {
+        let artifacts = ContractArtifacts::from_manifest_or_file(
+            self.manifest_path.as_ref(),
+            self.file.as_ref(),
+        )?;
+        let metadata = artifacts.metadata()?;
+        let abi: Abi = serde_json::from_value(serde_json::Value::Object(metadata.abi))
+            .context("Failed to read the ink! metadata of the contract")?;
+        let name = &metadata.contract.name;
+        let bindings = match self.lang {
+            Lang::Rust => rust_bindings(name, &abi)?,
+            Lang::Ts => ts_bindings(name, &abi)?,
+        };
+        match self.output {
+            Some(ref path) => {
+                std::fs::write(path, bindings)
+                    .with_context(|| format!("Failed to write {}", path.display()))?;
+                eprintln!("Wrote the bindings to {}", path.display());
+            }
+            None => print!("{bindings}"),
+        }
+        Ok(())
+    }
+}
+
+/// The parts of the ink! metadata the bindings are generated from.
+#[derive(Debug, serde::Deserialize)]
+struct Abi {
+    spec: Spec,
+    /// The registry is flattened into the `types` field of the metadata.
+    #[serde(flatten)]
+    registry: PortableRegistry,
+}
+
+#[derive(Debug, serde::Deserialize)]
+struct Spec {
+    constructors: Vec<Callable>,
+    messages: Vec<Callable>,
+}
+
+/// A constructor or message.
+#[derive(Debug, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Callable {
+    label: String,
+    selector: String,
+    #[serde(default)]
+    args: Vec<Arg>,
+    /// Constructors of ink! versions before 4.1 have no return type.
+    #[serde(default)]
+    return_type: Option<TypeRef>,
+    #[serde(default)]
+    mutates: bool,
+    #[serde(default)]
+    payable: bool,
+    #[serde(default)]
+    docs: Vec<String>,
+}
+
+#[derive(Debug, serde::Deserialize)]
+struct Arg {
+    label: String,
+    #[serde(rename = "type")]
+    ty: TypeRef,
+}
+
+#[derive(Debug, serde::Deserialize)]
+struct TypeRef {
+    #[serde(rename = "type")]
+    id: u32,
+}
+
+impl Abi {
+    /// The constructors and messages, with the names of their functions.
+    fn callables(&self) -> [Vec<(String, &Callable)>; 2] {
+        [&self.spec.constructors, &self.spec.messages].map(|callables| {
+            let short = |callable: &Callable| {
+                let label = &callable.label;
+                label.rsplit("::").next().unwrap_or(label).to_string()
+            };
+            callables
+                .iter()
+                .map(|callable| {
+                    // Trait messages are prefixed with their trait if their names clash.
+                    let clashes = callables
+                        .iter()
+                        .filter(|other| short(other) == short(callable))
+                        .count();
+                    let name = if clashes > 1 {
+                        callable.label.replace("::", "_")
+                    } else {
+                        short(callable)
+                    };
+                    (name, callable)
+                })
+                .collect()
+        })
+    }
+}
+
+/// The types of the metadata used by the constructors and messages.
+struct Types<'a> {
+    registry: &'a PortableRegistry,
+    /// The names of the types generated as structs and enums, by type id.
+    names: BTreeMap<u32, String>,
+}
+
+/// The types of the standard library which are not generated.
+enum Builtin {
+    Option(u32),
+    Result(u32, u32),
+}
+
+impl<'a> Types<'a> {
+    fn new(abi: &'a Abi) -> Result<Self> {
+        let registry = &abi.registry;
+        let mut reachable = BTreeSet::new();
+        let mut pending = abi
+            .spec
+            .constructors
+            .iter()
+            .chain(&abi.spec.messages)
+            .flat_map(|callable| {
+                let args = callable.args.iter().map(|arg| arg.ty.id);
+                args.chain(callable.return_type.as_ref().map(|ty| ty.id))
+            })
+            .collect::<Vec<_>>();
+        while let Some(id) = pending.pop() {
+            if reachable.insert(id) {
+                pending.extend(children(resolve(registry, id)?));
+            }
+        }
+
+        let mut named = BTreeMap::<String, Vec<u32>>::new();
+        for id in reachable {
+            let ty = resolve(registry, id)?;
+            let generated =
+                matches!(ty.type_def, TypeDef::Composite(_) | TypeDef::Variant(_));
+            if generated && builtin(ty).is_none() {
+                let name = match ty.path.segments.last() {
+                    Some(name) => name.clone(),
+                    None => format!("Type{id}"),
+                };
+                named.entry(name).or_default().push(id);
+            }
+        }
+        let mut names = BTreeMap::new();
+        for (name, ids) in named {
+            // Types with the same name, e.g. instances of a generic type, are told apart
+            // by their ids.
+            let unique = ids.len() == 1;
+            for id in ids {
+                let name = if unique {
+                    name.clone()
+                } else {
+                    format!("{name}{id}")
+                };
+                names.insert(id, name);
+            }
+        }
+        Ok(Self { registry, names })
+    }
+
+    fn resolve(&self, id: u32) -> Result<&'a Type<PortableForm>> {
+        resolve(self.registry, id)
+    }
+}
+
+fn resolve(registry: &PortableRegistry, id: u32) -> Result<&Type<PortableForm>> {
+    registry
+        .resolve(id)
+        .ok_or_else(|| anyhow!("The type {id} is missing from the metadata"))
+}
+
+/// The ids of the types the type is composed of.
+fn children(ty: &Type<PortableForm>) -> Vec<u32> {
+    let fields = |fields: &[Field<PortableForm>]| {
+        fields.iter().map(|field| field.ty.id).collect::<Vec<_>>()
+    };
+    match ty.type_def {
+        TypeDef::Composite(ref composite) => fields(&composite.fields),
+        TypeDef::Variant(ref variant) => {
+            variant
+                .variants
+                .iter()
+                .flat_map(|variant| fields(&variant.fields))
+                .collect()
+        }
+        TypeDef::Sequence(ref sequence) => vec![sequence.type_param.id],
+        TypeDef::Array(ref array) => vec![array.type_param.id],
+        TypeDef::Tuple(ref tuple) => tuple.fields.iter().map(|ty| ty.id).collect(),
+        TypeDef::Compact(ref compact) => vec![compact.type_param.id],
+        TypeDef::Primitive(_) | TypeDef::BitSequence(_) => Vec::new(),
+    }
+}
+
+/// Returns the type of the standard library the type is an instance of, if any.
+fn builtin(ty: &Type<PortableForm>) -> Option<Builtin> {
+    let TypeDef::Variant(ref variant) = ty.type_def else {
+        return None
+    };
+    let field = |name: &str| {
+        variant
+            .variants
+            .iter()
+            .find(|variant| variant.name == name)
+            .and_then(|variant| variant.fields.first())
+            .map(|field| field.ty.id)
+    };
+    match ty.path.segments.as_slice() {
+        [name] if name == "Option" => Some(Builtin::Option(field("Some")?)),
+        [name] if name == "Result" => Some(Builtin::Result(field("Ok")?, field("Err")?)),
+        _ => None,
+    }
+}
+
+/// Returns whether the type is a sequence or array of bytes.
+fn is_bytes(types: &Types, type_param: u32) -> Result<bool> {
+    Ok(matches!(
+        types.resolve(type_param)?.type_def,
+        TypeDef::Primitive(TypeDefPrimitive::U8)
+    ))
+}
+
+/// The Rust keywords which are not valid argument or field names.
+const RUST_KEYWORDS: &[&str] = &[
+    "as", "async", "await", "break", "const", "continue", "crate", "dyn", "else", "enum",
+    "extern", "false", "fn", "for", "if", "impl", "in", "let", "loop", "match", "mod",
+    "move", "mut", "pub", "ref", "return", "static", "struct", "trait", "true", "type",
+    "unsafe", "use", "where", "while", "yield",
+];
+
+/// Returns the label as a Rust identifier.
+fn rust_ident(label: &str) -> String {
+    let ident = label
+        .chars()
+        .map(|c| if c.is_alphanumeric() { c } else { '_' })
+        .collect::<String>()
+        .to_lowercase();
+    if RUST_KEYWORDS.contains(&ident.as_str()) {
+        format!("r#{ident}")
+    } else {
+        ident
+    }
+}
+
+/// Returns the Rust type of the type with the given id.
+fn rust_type(types: &Types, id: u32) -> Result<String> {
+    if let Some(name) = types.names.get(&id) {
+        return Ok(name.clone())
+    }
+    let ty = types.resolve(id)?;
+    if let Some(builtin) = builtin(ty) {
+        return match builtin {
+            Builtin::Option(some) => Ok(format!("Option<{}>", rust_type(types, some)?)),
+            Builtin::Result(ok, err) => {
+                Ok(format!(
+                    "Result<{}, {}>",
+                    rust_type(types, ok)?,
+                    rust_type(types, err)?
+                ))
+            }
+        }
+    }
+    Ok(match ty.type_def {
+        TypeDef::Sequence(ref sequence) => {
+            format!("Vec<{}>", rust_type(types, sequence.type_param.id)?)
+        }
+        TypeDef::Array(ref array) => {
+            format!(
+                "[{}; {}]",
+                rust_type(types, array.type_param.id)?,
+                array.len
+            )
+        }
+        TypeDef::Tuple(ref tuple) => {
+            let fields = tuple
+                .fields
+                .iter()
+                .map(|field| rust_type(types, field.id))
+                .collect::<Result<Vec<_>>>()?;
+            match fields.as_slice() {
+                [field] => format!("({field},)"),
+                fields => format!("({})", fields.join(", ")),
+            }
+        }
+        TypeDef::Compact(ref compact) => {
+            format!(
+                "scale::Compact<{}>",
+                rust_type(types, compact.type_param.id)?
+            )
+        }
+        TypeDef::Primitive(ref primitive) => {
+            match primitive {
+                TypeDefPrimitive::Str => "String".to_string(),
+                TypeDefPrimitive::U256 | TypeDefPrimitive::I256 => "[u8; 32]".to_string(),
+                primitive => format!("{primitive:?}").to_lowercase(),
+            }
+        }
+        TypeDef::BitSequence(_) => {
+            return Err(anyhow!("Bit sequences are not supported by the bindings"))
+        }
+        TypeDef::Composite(_) | TypeDef::Variant(_) => {
+            unreachable!("named types are generated")
+        }
+    })
+}
+
+/// Returns the fields as the body of a Rust struct or enum variant, with the given
+/// visibility of the fields.
+fn rust_fields(
+    types: &Types,
+    fields: &[Field<PortableForm>],
+    visibility: &str,
+) -> Result<String> {
+    if fields.is_empty() {
+        return Ok(String::new())
+    }
+    if fields.iter().all(|field| field.name.is_some()) {
+        let mut out = String::from(" {\n");
+        for field in fields {
+            let name = rust_ident(field.name.as_deref().unwrap_or_default());
+            let ty = rust_type(types, field.ty.id)?;
+            let _ = writeln!(out, "    {visibility}{name}: {ty},");
+        }
+        out.push('}');
+        return Ok(out)
+    }
+    let fields = fields
+        .iter()
+        .map(|field| Ok(format!("{visibility}{}", rust_type(types, field.ty.id)?)))
+        .collect::<Result<Vec<_>>>()?;
+    Ok(format!("({})", fields.join(", ")))
+}
+
+/// Write the `///` doc comment lines with the given indentation.
+fn write_docs(out: &mut String, docs: &[String], indent: &str, prefix: &str) {
+    for line in docs.iter().map(|line| line.trim()) {
+        if line.is_empty() {
+            let _ = writeln!(out, "{indent}{prefix}");
+        } else {
+            let _ = writeln!(out, "{indent}{prefix} {line}");
+        }
+    }
+}
+
+/// The attributes of a constructor or message for its documentation, e.g.
+/// "Selector `0x633aa551`, mutates".
+fn attributes(callable: &Callable) -> String {
+    let mut attributes = format!("Selector `{}`", callable.selector);
+    for (flag, set) in [("mutates", callable.mutates), ("payable", callable.payable)] {
+        if set {
+            attributes.push_str(", ");
+            attributes.push_str(flag);
+        }
+    }
+    attributes
+}
+
+/// The bytes of a selector as a Rust array, e.g. `[0x63, 0x3a, 0xa5, 0x51]`.
+fn selector_bytes(selector: &str) -> Result<String> {
+    let bytes = hex::decode(selector.trim_start_matches("0x"))
+        .with_context(|| format!("Invalid selector {selector}"))?;
+    let bytes = bytes
+        .iter()
+        .map(|byte| format!("0x{byte:02x}"))
+        .collect::<Vec<_>>();
+    Ok(format!("[{}]", bytes.join(", ")))
+}
+
+const RUST_HEADER: &str = r#"#![allow(dead_code)]
+
+use scale::{
+    Decode,
+    Encode,
+};
+
+/// A call of a constructor or message of the contract: its SCALE encoded input data,
+/// and the type its output decodes to.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ContractCall<R> {
+    pub data: Vec<u8>,
+    output: core::marker::PhantomData<R>,
+}
+
+impl<R: Decode> ContractCall<R> {
+    fn new(selector: [u8; 4], args: impl Encode) -> Self {
+        let mut data = selector.to_vec();
+        args.encode_to(&mut data);
+        Self {
+            data,
+            output: core::marker::PhantomData,
+        }
+    }
+
+    /// Decode the output data returned by the contract.
+    pub fn decode_output(&self, mut output: &[u8]) -> Result<R, scale::Error> {
+        R::decode(&mut output)
+    }
+}
+"#;
+
+/// Generate the Rust module of the bindings.
+fn rust_bindings(contract: &str, abi: &Abi) -> Result<String> {
+    let types = Types::new(abi)?;
+    let mut out = format!(
+        "//! Bindings of the `{contract}` contract, generated by `cargo contract typegen`.\n\n"
+    );
+    out.push_str(RUST_HEADER);
+
+    for (&id, name) in &types.names {
+        let ty = types.resolve(id)?;
+        let _ = writeln!(out);
+        write_docs(&mut out, &ty.docs, "", "///");
+        let _ = writeln!(
+            out,
+            "#[derive(Debug, Clone, PartialEq, Eq, Encode, Decode)]"
+        );
+        match ty.type_def {
+            TypeDef::Composite(ref composite) => {
+                let fields = rust_fields(&types, &composite.fields, "pub ")?;
+                let end = if fields.ends_with('}') { "" } else { ";" };
+                let _ = writeln!(out, "pub struct {name}{fields}{end}");
+            }
+            TypeDef::Variant(ref variant) => {
+                let _ = writeln!(out, "pub enum {name} {{");
+                for variant in &variant.variants {
+                    write_docs(&mut out, &variant.docs, "    ", "///");
+                    let _ = writeln!(out, "    #[codec(index = {})]", variant.index);
+                    let fields = rust_fields(&types, &variant.fields, "")?;
+                    let _ = writeln!(
+                        out,
+                        "    {}{},",
+                        variant.name,
+                        fields
+                            .replace("\n    ", "\n        ")
+                            .replace("\n}", "\n    }")
+                    );
+                }
+                let _ = writeln!(out, "}}");
+            }
+            _ => unreachable!("only composites and variants are named"),
+        }
+    }
+
+    let [constructors, messages] = abi.callables();
+    for (module, callables) in [("constructors", constructors), ("messages", messages)] {
+        let _ = writeln!(out);
+        let _ = writeln!(out, "pub mod {module} {{");
+        let _ = writeln!(out, "    use super::*;");
+        for (name, callable) in callables {
+            let _ = writeln!(out);
+            write_docs(&mut out, &callable.docs, "    ", "///");
+            if !callable.docs.is_empty() {
+                let _ = writeln!(out, "    ///");
+            }
+            let _ = writeln!(out, "    /// {}.", attributes(callable));
+            let args = callable
+                .args
+                .iter()
+                .map(|arg| Ok((rust_ident(&arg.label), rust_type(&types, arg.ty.id)?)))
+                .collect::<Result<Vec<_>>>()?;
+            let params = args
+                .iter()
+                .map(|(name, ty)| format!("{name}: {ty}"))
+                .collect::<Vec<_>>();
+            let values = args.iter().map(|(name, _)| format!("{name},"));
+            let output = match callable.return_type {
+                Some(ref ty) => rust_type(&types, ty.id)?,
+                None => "()".to_string(),
+            };
+            let _ = writeln!(
+                out,
+                "    pub fn {}({}) -> ContractCall<{output}> {{",
+                rust_ident(&name),
+                params.join(", ")
+            );
+            let _ = writeln!(
+                out,
+                "        ContractCall::new({}, ({}))",
+                selector_bytes(&callable.selector)?,
+                values.collect::<Vec<_>>().join(" ")
+            );
+            let _ = writeln!(out, "    }}");
+        }
+        let _ = writeln!(out, "}}");
+    }
+    Ok(out)
+}
+
+/// The TypeScript reserved words which are not valid parameter names.
+const TS_RESERVED: &[&str] = &[
+    "break",
+    "case",
+    "catch",
+    "class",
+    "const",
+    "continue",
+    "debugger",
+    "default",
+    "delete",
+    "do",
+    "else",
+    "enum",
+    "export",
+    "extends",
+    "false",
+    "finally",
+    "for",
+    "function",
+    "if",
+    "import",
+    "in",
+    "instanceof",
+    "new",
+    "null",
+    "return",
+    "super",
+    "switch",
+    "this",
+    "throw",
+    "true",
+    "try",
+    "typeof",
+    "var",
+    "void",
+    "while",
+    "with",
+];
+
+/// Returns the label as a TypeScript parameter name, in camel case as the arguments of
+/// `@polkadot/api-contract`.
+fn ts_param(label: &str) -> String {
+    let param = ts_method(label);
+    if TS_RESERVED.contains(&param.as_str()) {
+        format!("{param}_")
+    } else {
+        param
+    }
+}
+
+/// Returns the TypeScript type of the type with the given id.
+fn ts_type(types: &Types, id: u32) -> Result<String> {
+    if let Some(name) = types.names.get(&id) {
+        return Ok(name.clone())
+    }
+    let ty = types.resolve(id)?;
+    if let Some(builtin) = builtin(ty) {
+        return match builtin {
+            Builtin::Option(some) => Ok(format!("{} | null", ts_type(types, some)?)),
+            Builtin::Result(ok, err) => {
+                Ok(format!(
+                    "{{ Ok: {} }} | {{ Err: {} }}",
+                    ts_type(types, ok)?,
+                    ts_type(types, err)?
+                ))
+            }
+        }
+    }
+    Ok(match ty.type_def {
+        TypeDef::Sequence(ref sequence) if is_bytes(types, sequence.type_param.id)? => {
+            "string".to_string()
+        }
+        TypeDef::Array(ref array) if is_bytes(types, array.type_param.id)? => {
+            "string".to_string()
+        }
+        TypeDef::Sequence(ref sequence) => {
+            format!("Array<{}>", ts_type(types, sequence.type_param.id)?)
+        }
+        TypeDef::Array(ref array) => {
+            format!("Array<{}>", ts_type(types, array.type_param.id)?)
+        }
+        TypeDef::Tuple(ref tuple) if tuple.fields.is_empty() => "null".to_string(),
+        TypeDef::Tuple(ref tuple) => {
+            let fields = tuple
+                .fields
+                .iter()
+                .map(|field| ts_type(types, field.id))
+                .collect::<Result<Vec<_>>>()?;
+            format!("[{}]", fields.join(", "))
+        }
+        TypeDef::Compact(ref compact) => ts_type(types, compact.type_param.id)?,
+        TypeDef::Primitive(ref primitive) => {
+            match primitive {
+                TypeDefPrimitive::Bool => "boolean",
+                TypeDefPrimitive::Char | TypeDefPrimitive::Str => "string",
+                TypeDefPrimitive::U8
+                | TypeDefPrimitive::U16
+                | TypeDefPrimitive::U32
+                | TypeDefPrimitive::I8
+                | TypeDefPrimitive::I16
+                | TypeDefPrimitive::I32 => "number",
+                _ => "WideInt",
+            }
+            .to_string()
+        }
+        TypeDef::BitSequence(_) => {
+            return Err(anyhow!("Bit sequences are not supported by the bindings"))
+        }
+        TypeDef::Composite(_) | TypeDef::Variant(_) => {
+            unreachable!("named types are generated")
+        }
+    })
+}
+
+/// Returns the fields as a TypeScript type: an object of named fields, the type of a
+/// single unnamed field or a tuple of unnamed fields.
+fn ts_fields(types: &Types, fields: &[Field<PortableForm>]) -> Result<String> {
+    if fields.is_empty() {
+        return Ok("null".to_string())
+    }
+    if fields.iter().all(|field| field.name.is_some()) {
+        let fields = fields
+            .iter()
+            .map(|field| {
+                Ok(format!(
+                    "{}: {}",
+                    field.name.as_deref().unwrap_or_default(),
+                    ts_type(types, field.ty.id)?
+                ))
+            })
+            .collect::<Result<Vec<_>>>()?;
+        return Ok(format!("{{ {} }}", fields.join("; ")))
+    }
+    let fields = fields
+        .iter()
+        .map(|field| ts_type(types, field.ty.id))
+        .collect::<Result<Vec<_>>>()?;
+    match fields.as_slice() {
+        [field] => Ok(field.clone()),
+        fields => Ok(format!("[{}]", fields.join(", "))),
+    }
+}
+
+/// Generate the TypeScript definitions of the bindings.
+fn ts_bindings(contract: &str, abi: &Abi) -> Result<String> {
+    let types = Types::new(abi)?;
+    let mut out = format!(
+        "// Type definitions of the `{contract}` contract, generated by `cargo contract typegen`.\n\n"
+    );
+    out.push_str(
+        "/** An integer wider than 32 bits, which does not fit into a `number`. */\n\
+        export type WideInt = bigint | string | number;\n",
+    );
+
+    for (&id, name) in &types.names {
+        let ty = types.resolve(id)?;
+        let _ = writeln!(out);
+        if !ty.docs.is_empty() {
+            let _ = writeln!(out, "/**");
+            write_docs(&mut out, &ty.docs, " ", "*");
+            let _ = writeln!(out, " */");
+        }
+        let definition = match ty.type_def {
+            TypeDef::Composite(ref composite) => {
+                format!(" {}", ts_fields(&types, &composite.fields)?)
+            }
+            TypeDef::Variant(ref variant) if variant.variants.is_empty() => {
+                " never".to_string()
+            }
+            TypeDef::Variant(ref variant) => {
+                let variants = variant
+                    .variants
+                    .iter()
+                    .map(|variant| {
+                        if variant.fields.is_empty() {
+                            Ok(format!("\"{}\"", variant.name))
+                        } else {
+                            Ok(format!(
+                                "{{ {}: {} }}",
+                                variant.name,
+                                ts_fields(&types, &variant.fields)?
+                            ))
+                        }
+                    })
+                    .collect::<Result<Vec<_>>>()?;
+                format!("\n  | {}", variants.join("\n  | "))
+            }
+            _ => unreachable!("only composites and variants are named"),
+        };
+        let _ = writeln!(out, "export type {name} ={definition};");
+    }
+
+    let [constructors, messages] = abi.callables();
+    let mut selectors = Vec::new();
+    for (interface, callables) in [("Constructors", constructors), ("Messages", messages)]
+    {
+        let _ = writeln!(out);
+        let _ = writeln!(out, "export interface {interface} {{");
+        let mut entries = Vec::new();
+        for (name, callable) in callables {
+            let method = ts_method(&name);
+            let _ = writeln!(out, "  /**");
+            write_docs(&mut out, &callable.docs, "   ", "*");
+            if !callable.docs.is_empty() {
+                let _ = writeln!(out, "   *");
+            }
+            let _ = writeln!(out, "   * {}.", attributes(callable));
+            let _ = writeln!(out, "   */");
+            let params = callable
+                .args
+                .iter()
+                .map(|arg| {
+                    Ok(format!(
+                        "{}: {}",
+                        ts_param(&arg.label),
+                        ts_type(&types, arg.ty.id)?
+                    ))
+                })
+                .collect::<Result<Vec<_>>>()?;
+            let output = match callable.return_type {
+                Some(ref ty) => ts_type(&types, ty.id)?,
+                None => "null".to_string(),
+            };
+            // An unquoted `new` method would declare a construct signature.
+            let key = if method == "new" {
+                format!("\"{method}\"")
+            } else {
+                method.clone()
+            };
+            let _ = writeln!(out, "  {key}({}): {output};", params.join(", "));
+            entries.push(format!("    {method}: \"{}\",", callable.selector));
+        }
+        let _ = writeln!(out, "}}");
+        selectors.push((interface.to_lowercase(), entries));
+    }
+
+    let _ = writeln!(out);
+    let _ = writeln!(out, "export const SELECTORS = {{");
+    for (interface, entries) in selectors {
+        let _ = writeln!(out, "  {interface}: {{");
+        for entry in entries {
+            let _ = writeln!(out, "{entry}");
+        }
+        let _ = writeln!(out, "  }},");
+    }
+    let _ = writeln!(out, "}} as const;");
+    Ok(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn abi() -> Abi {
+        serde_json::from_value(serde_json::json!({
+            "spec": {
+                "constructors": [{
+                    "label": "new",
+                    "selector": "0x9bae9d5e",
+                    "payable": false,
+                    "args": [{
+                        "label": "type",
+                        "type": { "displayName": ["bool"], "type": 0 }
+                    }],
+                    "returnType": { "displayName": ["ink", "ConstructorResult"], "type": 2 },
+                    "docs": ["Creates a new flipper."]
+                }],
+                "messages": [
+                    {
+                        "label": "flip",
+                        "selector": "0x633aa551",
+                        "mutates": true,
+                        "args": [],
+                        "returnType": { "displayName": ["ink", "MessageResult"], "type": 2 },
+                        "docs": []
+                    },
+                    {
+                        "label": "get",
+                        "selector": "0x2f865bd9",
+                        "mutates": false,
+                        "args": [],
+                        "returnType": { "displayName": ["ink", "MessageResult"], "type": 5 },
+                        "docs": []
+                    }
+                ]
+            },
+            "types": [
+                { "id": 0, "type": { "def": { "primitive": "bool" } } },
+                { "id": 1, "type": { "def": { "tuple": [] } } },
+                {
+                    "id": 2,
+                    "type": {
+                        "path": ["Result"],
+                        "params": [{ "name": "T", "type": 1 }, { "name": "E", "type": 3 }],
+                        "def": { "variant": { "variants": [
+                            { "name": "Ok", "fields": [{ "type": 1 }], "index": 0 },
+                            { "name": "Err", "fields": [{ "type": 3 }], "index": 1 }
+                        ] } }
+                    }
+                },
+                {
+                    "id": 3,
+                    "type": {
+                        "path": ["ink_primitives", "LangError"],
+                        "def": { "variant": { "variants": [
+                            { "name": "CouldNotReadInput", "index": 1 }
+                        ] } }
+                    }
+                },
+                {
+                    "id": 4,
+                    "type": {
+                        "path": ["flipper", "State"],
+                        "docs": ["The state of the flipper."],
+                        "def": { "composite": { "fields": [
+                            { "name": "value", "type": 0 },
+                            { "name": "owner", "type": 6 }
+                        ] } }
+                    }
+                },
+                {
+                    "id": 5,
+                    "type": {
+                        "path": ["Result"],
+                        "def": { "variant": { "variants": [
+                            { "name": "Ok", "fields": [{ "type": 4 }], "index": 0 },
+                            { "name": "Err", "fields": [{ "type": 3 }], "index": 1 }
+                        ] } }
+                    }
+                },
+                { "id": 6, "type": { "def": { "array": { "len": 32, "type": 7 } } } },
+                { "id": 7, "type": { "def": { "primitive": "u8" } } }
+            ]
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn rust_bindings_are_generated() {
+        let bindings = rust_bindings("flipper", &abi()).unwrap();
+
+        assert!(bindings.contains(
+            "/// The state of the flipper.\n\
+            #[derive(Debug, Clone, PartialEq, Eq, Encode, Decode)]\n\
+            pub struct State {\n    pub value: bool,\n    pub owner: [u8; 32],\n}\n"
+        ));
+        assert!(bindings.contains(
+            "pub enum LangError {\n    #[codec(index = 1)]\n    CouldNotReadInput,\n}"
+        ));
+        assert!(bindings.contains(
+            "    /// Creates a new flipper.\n    ///\n    /// Selector `0x9bae9d5e`.\n    \
+            pub fn new(r#type: bool) -> ContractCall<Result<(), LangError>> {\n        \
+            ContractCall::new([0x9b, 0xae, 0x9d, 0x5e], (r#type,))\n    }"
+        ));
+        assert!(bindings.contains(
+            "pub fn flip() -> ContractCall<Result<(), LangError>> {\n        \
+            ContractCall::new([0x63, 0x3a, 0xa5, 0x51], ())"
+        ));
+        assert!(
+            bindings.contains("pub fn get() -> ContractCall<Result<State, LangError>>")
+        );
+    }
+
+    #[test]
+    fn ts_bindings_are_generated() {
+        let bindings = ts_bindings("flipper", &abi()).unwrap();
+
+        assert!(bindings.contains("export type LangError =\n  | \"CouldNotReadInput\";"));
+        assert!(
+            bindings.contains("export type State = { value: boolean; owner: string };")
+        );
+        assert!(bindings
+            .contains("  \"new\"(type: boolean): { Ok: null } | { Err: LangError };"));
+        assert!(bindings.contains("  get(): { Ok: State } | { Err: LangError };"));
+        assert!(bindings.contains("    flip: \"0x633aa551\","));
+        assert_eq!(ts_param("init_value"), "initValue");
+        assert_eq!(ts_param("default"), "default_");
+    }
+
+    #[test]
+    fn clashing_trait_messages_are_prefixed() {
+        let callable = |label: &str| {
+            Callable {
+                label: label.to_string(),
+                selector: String::from("0x00000000"),
+                args: Vec::new(),
+                return_type: None,
+                mutates: false,
+                payable: false,
+                docs: Vec::new(),
+            }
+        };
+        let abi = Abi {
+            spec: Spec {
+                constructors: Vec::new(),
+                messages: vec![
+                    callable("PSP22::transfer"),
+                    callable("Ownable::transfer"),
+                    callable("PSP22::total_supply"),
+                ],
+            },
+            registry: abi().registry,
+        };
+        let [_, messages] = abi.callables();
+        let names = messages
+            .into_iter()
+            .map(|(name, _)| name)
+            .collect::<Vec<_>>();
+        assert_eq!(
+            names,
+            ["PSP22_transfer", "Ownable_transfer", "total_supply"]
+        );
+    }
+}
@@ -0,0 +1,352 @@
+// Copyright 2018-2023 Parity Technologies (UK) Ltd.
+// This file is part of cargo-contract.
+//
+// cargo-contract is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// cargo-contract is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with cargo-contract.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Verifying that the code of a contract bundle, or uploaded on-chain, is built from the
+//! source of the contract.
+//!
+//! The source is rebuilt with the settings recorded in the `build_info` of the bundle
+//! metadata, and the hash of the rebuilt code is compared to the expected code hash. The
+//! rebuild is only reproducible with the same Rust toolchain and `cargo-contract`
+//! version, differences are reported as the likely cause of a mismatch.
+
+use super::{
+    extrinsics::{
+        parse_code_hash,
+        ContractArtifacts,
+    },
+    runtime_api::api,
+    AsyncCommand,
+    Client,
+};
+use anyhow::{
+    Context,
+    Result,
+};
+use async_trait::async_trait;
+use colored::Colorize;
+use contract_build::{
+    name_value_println,
+    BuildArtifacts,
+    BuildInfo,
+    BuildMode,
+    ExecuteArgs,
+    Features,
+    ManifestPath,
+    Network,
+    OptimizationPasses,
+    OutputType,
+    Target,
+    UnstableFlags,
+    Verbosity,
+    VerbosityFlags,
+    WasmOptSettings,
+    DEFAULT_KEY_COL_WIDTH,
+};
+use contract_metadata::ContractMetadata;
+use semver::Version;
+use std::path::PathBuf;
+use subxt::{
+    Config,
+    OnlineClient,
+};
+
+type Hash = <super::DefaultConfig as Config>::Hash;
+
+/// Rebuild the contract and verify that its code matches a contract bundle or the code
+/// uploaded on-chain.
+#[derive(Debug, clap::Args)]
+#[clap(name = "verify")]
+pub struct VerifyCommand {
+    /// Path to the `Cargo.toml` of the contract to rebuild.
+    #[clap(long, value_parser)]
+    manifest_path: Option<PathBuf>,
+    /// The `.contract` or `.contract.zst` bundle to verify. The contract is rebuilt with
+    /// the build settings of its metadata.
+    #[clap(long, value_parser, required_unless_present = "code_hash")]
+    contract_bundle: Option<PathBuf>,
+    /// The hash of the code uploaded on-chain to verify. The contract is rebuilt with
+    /// the build settings of `--contract-bundle` if given, otherwise in release mode
+    /// with the default settings.
+    #[clap(long, value_parser = parse_code_hash, requires = "url")]
+    code_hash: Option<Hash>,
+    /// Websockets url of a node of the chain holding the code of `--code-hash`.
+    #[clap(long, value_parser)]
+    url: Option<url::Url>,
+    #[clap(flatten)]
+    verbosity: VerbosityFlags,
+    /// Export the verification result in JSON format.
+    #[clap(long, conflicts_with = "verbose")]
+    output_json: bool,
+}
+
+/// The result of the verification of a contract.
+#[derive(Debug, serde::Serialize)]
+pub struct VerificationResult {
+    /// `true` if the rebuilt code has the expected code hash.
+    pub verified: bool,
+    pub expected_code_hash: String,
+    pub rebuilt_code_hash: String,
+    /// The settings the contract was rebuilt with.
+    pub build_info: BuildInfo,
+    /// The differences between the build environment of the expected code and of the
+    /// rebuild, the likely causes of a mismatch.
+    pub environment_mismatches: Vec<String>,
+}
+
+impl VerifyCommand {
+    /// Returns an error if no code with the hash is uploaded to the chain.
+    async fn ensure_uploaded(&self, code_hash: Hash) -> Result<()> {
+        let url = self
+            .url
+            .as_ref()
+            .context("`--code-hash` requires `--url`")?;
+        let client: Client = OnlineClient::from_url(url.as_str()).await?;
+        let pristine_code = api::storage().contracts().pristine_code(code_hash);
+        let code = client
+            .storage()
+            .at_latest()
+            .await?
+            .fetch(&pristine_code)
+            .await?;
+        anyhow::ensure!(
+            code.is_some(),
+            "No code with the hash {code_hash:?} found on the chain"
+        );
+        Ok(())
+    }
+}
+
+#[async_trait(?Send)]
+impl AsyncCommand for VerifyCommand {
+    type Output = Result<()>;
+
+    async fn exec(&self) -> Result<()> {
+        let mut verbosity: Verbosity = TryFrom::try_from(&self.verbosity)?;
+        if self.output_json {
+            verbosity = Verbosity::Quiet;
+        }
+
+        let bundle = self
+            .contract_bundle
+            .as_ref()
+            .map(|path| ContractArtifacts::from_manifest_or_file(None, Some(path)))
+            .transpose()?
+            .map(|artifacts| artifacts.metadata())
+            .transpose()?;
+        let build_info = match bundle {
+            Some(ref metadata) => bundle_build_info(metadata)?,
+            None => default_build_info()?,
+        };
+        let expected_code_hash = match (self.code_hash, bundle) {
+            (Some(code_hash), _) => {
+                self.ensure_uploaded(code_hash).await?;
+                code_hash.0
+            }
+            (None, Some(metadata)) => metadata.source.hash.0,
+            (None, None) => {
+                unreachable!("`--contract-bundle` or `--code-hash` is required")
+            }
+        };
+
+        let environment_mismatches = current_environment_mismatches(&build_info)?;
+        for mismatch in &environment_mismatches {
+            maybe_warn(verbosity, mismatch);
+        }
+
+        let rebuilt_code_hash =
+            rebuild(self.manifest_path.as_ref(), &build_info, verbosity)?;
+        let result = VerificationResult {
+            verified: rebuilt_code_hash == expected_code_hash,
+            expected_code_hash: format!("0x{}", hex::encode(expected_code_hash)),
+            rebuilt_code_hash: format!("0x{}", hex::encode(rebuilt_code_hash)),
+            build_info,
+            environment_mismatches,
+        };
+
+        if self.output_json {
+            println!("{}", serde_json::to_string_pretty(&result)?);
+        } else {
+            name_value_println!(
+                "Expected",
+                result.expected_code_hash,
+                DEFAULT_KEY_COL_WIDTH
+            );
+            name_value_println!(
+                "Rebuilt",
+                result.rebuilt_code_hash,
+                DEFAULT_KEY_COL_WIDTH
+            );
+        }
+        if !result.verified {
+            let hint = if result.environment_mismatches.is_empty() {
+                String::new()
+            } else {
+                format!(
+                    ", the build environment differs: {}",
+                    result.environment_mismatches.join(", ")
+                )
+            };
+            anyhow::bail!("Not verified: the rebuilt code does not match{hint}")
+        }
+        if !self.output_json {
+            println!(
+                "{}",
+                "Verified: the rebuilt code matches the expected code hash"
+                    .green()
+                    .bold()
+            );
+        }
+        Ok(())
+    }
+}
+
+/// Rebuild the contract with the build settings, returns the hash of the code.
+pub(crate) fn rebuild(
+    manifest_path: Option<&PathBuf>,
+    build_info: &BuildInfo,
+    verbosity: Verbosity,
+) -> Result<[u8; 32]> {
+    let mut features = Features::default();
+    for feature in &build_info.features {
+        features.push(feature);
+    }
+    let args = ExecuteArgs {
+        manifest_path: ManifestPath::try_from(manifest_path)?,
+        verbosity,
+        build_mode: build_info.build_mode,
+        features,
+        network: Network::Online,
+        build_artifact: BuildArtifacts::All,
+        unstable_flags: UnstableFlags::default(),
+        optimization_passes: Some(build_info.wasm_opt_settings.optimization_passes),
+        keep_debug_symbols: build_info.wasm_opt_settings.keep_debug_symbols,
+        lint: false,
+        output_type: OutputType::default(),
+        skip_wasm_validation: false,
+        target: build_info.target,
+        compress: false,
+    };
+    let result = contract_build::execute(args)?;
+    let dest_wasm = result
+        .dest_wasm
+        .context("The rebuild produced no contract code")?;
+    let code = std::fs::read(&dest_wasm)
+        .with_context(|| format!("Failed to read {}", dest_wasm.display()))?;
+    Ok(contract_build::code_hash(&code))
+}
+
+/// Returns the build settings recorded in the metadata of a contract bundle.
+pub(crate) fn bundle_build_info(metadata: &ContractMetadata) -> Result<BuildInfo> {
+    let build_info = metadata.source.build_info.clone().context(
+        "The contract bundle has no build info, it was built by a version of \
+        `cargo-contract` without support for verifiable builds",
+    )?;
+    serde_json::from_value(serde_json::Value::Object(build_info))
+        .context("Invalid build info in the contract bundle")
+}
+
+/// The settings of `cargo contract build --release`, used without a contract bundle.
+fn default_build_info() -> Result<BuildInfo> {
+    Ok(BuildInfo {
+        rust_toolchain: contract_build::util::rust_toolchain()?,
+        cargo_contract_version: Version::parse(env!("CARGO_PKG_VERSION"))?,
+        build_mode: BuildMode::Release,
+        wasm_opt_settings: WasmOptSettings {
+            optimization_passes: OptimizationPasses::default(),
+            keep_debug_symbols: false,
+        },
+        features: Vec::new(),
+        target: Target::default(),
+    })
+}
+
+/// The differences between the build environment of the build info and the current one.
+pub(crate) fn current_environment_mismatches(
+    build_info: &BuildInfo,
+) -> Result<Vec<String>> {
+    let toolchain = contract_build::util::rust_toolchain()?;
+    let version = Version::parse(env!("CARGO_PKG_VERSION"))?;
+    Ok(environment_mismatches(build_info, &toolchain, &version))
+}
+
+/// The differences between the build environment of the build info and the given one.
+fn environment_mismatches(
+    build_info: &BuildInfo,
+    toolchain: &str,
+    version: &Version,
+) -> Vec<String> {
+    let mut mismatches = Vec::new();
+    if build_info.rust_toolchain != toolchain {
+        mismatches.push(format!(
+            "built with the Rust toolchain {}, rebuilt with {toolchain}",
+            build_info.rust_toolchain
+        ));
+    }
+    if build_info.cargo_contract_version != *version {
+        mismatches.push(format!(
+            "built with cargo-contract {}, rebuilt with {version}",
+            build_info.cargo_contract_version
+        ));
+    }
+    mismatches
+}
+
+fn maybe_warn(verbosity: Verbosity, message: &str) {
+    if !matches!(verbosity, Verbosity::Quiet) {
+        eprintln!("{} {message}", "warning:".yellow().bold());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn build_environment_mismatches_are_reported() {
+        let build_info: BuildInfo = serde_json::from_value(serde_json::json!({
+            "rust_toolchain": "stable-x86_64-unknown-linux-gnu",
+            "cargo_contract_version": "2.2.1",
+            "build_mode": "Release",
+            "wasm_opt_settings": {
+                "optimization_passes": "Z",
+                "keep_debug_symbols": false
+            }
+        }))
+        .expect("build info of previous versions must be valid");
+        assert!(build_info.features.is_empty());
+        assert_eq!(build_info.target, Target::Wasm);
+
+        let version = Version::new(2, 2, 1);
+        assert!(environment_mismatches(
+            &build_info,
+            "stable-x86_64-unknown-linux-gnu",
+            &version
+        )
+        .is_empty());
+        assert_eq!(
+            environment_mismatches(
+                &build_info,
+                "nightly-x86_64-unknown-linux-gnu",
+                &Version::new(2, 3, 0)
+            ),
+            [
+                "built with the Rust toolchain stable-x86_64-unknown-linux-gnu, rebuilt \
+                with nightly-x86_64-unknown-linux-gnu",
+                "built with cargo-contract 2.2.1, rebuilt with 2.3.0"
+            ]
+        );
+    }
+}
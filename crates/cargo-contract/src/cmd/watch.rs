@@ -0,0 +1,443 @@
+// Copyright 2018-2023 Parity Technologies (UK) Ltd.
+// This file is part of cargo-contract.
+//
+// cargo-contract is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// cargo-contract is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with cargo-contract.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Monitoring of deployed contracts for unexpected changes.
+//!
+//! The contracts instantiated on the network, as recorded in the local registry, are
+//! checked periodically for a code hash other than the one they were instantiated with,
+//! their termination, their free balance dropping below a threshold and spikes of their
+//! storage deposit. Every change is alerted once, on the terminal, to a webhook and with
+//! `--once` in the exit code.
+//!
+//! With the `activity-cache` feature the events of the contracts pallet in the blocks
+//! produced while watching are recorded in the local activity cache.
+
+use super::{
+    extrinsics::{
+        url_to_string,
+        BalanceVariant,
+    },
+    info::fetch_contract_info,
+    registry::Registry,
+    runtime_api::api,
+    AsyncCommand,
+    Balance,
+    Client,
+    CodeHash,
+    DefaultConfig,
+    TokenMetadata,
+};
+use anyhow::{
+    Context,
+    Result,
+};
+use async_trait::async_trait;
+use colored::Colorize;
+use std::{
+    io::Write as _,
+    process::{
+        Command,
+        Stdio,
+    },
+    time::Duration,
+};
+use subxt::{
+    Config,
+    OnlineClient,
+};
+
+type AccountId = <DefaultConfig as Config>::AccountId;
+
+#[derive(Debug, clap::Args)]
+#[clap(
+    name = "watch-deployments",
+    about = "Monitor the deployed contracts for unexpected changes"
+)]
+pub struct WatchDeploymentsCommand {
+    /// Websockets url of a substrate node.
+    #[clap(long, value_parser, default_value = "ws://localhost:9944")]
+    url: url::Url,
+    /// The contracts to watch, by default the contracts instantiated on the network
+    /// according to the local registry.
+    #[clap(long)]
+    contract: Vec<AccountId>,
+    /// The number of seconds between two checks.
+    #[clap(long, default_value_t = 60)]
+    interval: u64,
+    /// Check once and exit, with an error if there are alerts.
+    #[clap(long)]
+    once: bool,
+    /// Alert when the free balance of a contract drops below the given balance.
+    #[clap(long)]
+    min_balance: Option<BalanceVariant>,
+    /// Alert when the storage deposit of a contract grows by more than the given
+    /// percentage between two alerts.
+    #[clap(long, default_value_t = 50)]
+    max_deposit_increase: u32,
+    /// Send the alerts as a JSON `POST` request to the url, e.g. of a chat integration.
+    #[clap(long, value_name = "URL")]
+    webhook: Option<url::Url>,
+}
+
+#[async_trait(?Send)]
+impl AsyncCommand for WatchDeploymentsCommand {
+    type Output = Result<()>;
+
+    async fn exec(&self) -> Result<()> {
+        let network = url_to_string(&self.url);
+        let mut watched = Vec::<Watched>::new();
+        let mut watch = |contract: AccountId, code_hash: Option<CodeHash>| {
+            if !watched.iter().any(|w| w.contract == contract) {
+                watched.push(Watched::new(contract, code_hash));
+            }
+        };
+        if self.contract.is_empty() {
+            for record in Registry::load()?
+                .salts
+                .into_iter()
+                .filter(|record| record.network == network)
+            {
+                watch(record.contract, Some(record.code_hash));
+            }
+        } else {
+            for contract in &self.contract {
+                watch(contract.clone(), None);
+            }
+        }
+        if watched.is_empty() {
+            anyhow::bail!(
+                "The local registry has no contracts instantiated on {network}, specify \
+                them with `--contract`"
+            )
+        }
+
+        let client = OnlineClient::<DefaultConfig>::from_url(self.url.clone()).await?;
+        let token_metadata = TokenMetadata::query(&client).await?;
+        let thresholds = Thresholds {
+            min_balance: self
+                .min_balance
+                .as_ref()
+                .map(|balance| balance.denominate_balance(&token_metadata))
+                .transpose()?,
+            max_deposit_increase: self.max_deposit_increase,
+        };
+        #[cfg(feature = "activity-cache")]
+        let mut recorder = ActivityRecorder::new(&client, token_metadata.clone())?;
+        eprintln!("Watching {} contracts on {network}", watched.len());
+        loop {
+            #[cfg(feature = "activity-cache")]
+            recorder.record(&client).await;
+            let mut alerts = Vec::new();
+            for watched in &mut watched {
+                let snapshot = Snapshot::fetch(&client, &watched.contract).await?;
+                alerts.extend(watched.check(snapshot.as_ref(), &thresholds));
+            }
+            for alert in &alerts {
+                eprintln!(
+                    "{} {}: {}",
+                    "alert:".red().bold(),
+                    alert.contract,
+                    alert.message
+                );
+            }
+            if let (Some(webhook), false) = (&self.webhook, alerts.is_empty()) {
+                if let Err(err) = send_webhook(webhook, &network, &alerts) {
+                    eprintln!("{} {err:#}", "warning:".yellow().bold());
+                }
+            }
+            if self.once {
+                anyhow::ensure!(alerts.is_empty(), "{} alerts", alerts.len());
+                return Ok(())
+            }
+            tokio::time::sleep(Duration::from_secs(self.interval)).await;
+        }
+    }
+}
+
+/// Records the events of the blocks produced since the previous check.
+#[cfg(feature = "activity-cache")]
+struct ActivityRecorder {
+    cache: super::extrinsics::ActivityCache,
+    metadata: subxt::Metadata,
+    token_metadata: TokenMetadata,
+    /// The first block not recorded yet, `None` before the first check.
+    next_block: Option<u64>,
+}
+
+#[cfg(feature = "activity-cache")]
+impl ActivityRecorder {
+    fn new(client: &Client, token_metadata: TokenMetadata) -> Result<Self> {
+        Ok(Self {
+            cache: super::extrinsics::ActivityCache::open_for(client)?,
+            metadata: client.metadata(),
+            token_metadata,
+            next_block: None,
+        })
+    }
+
+    /// Record the new blocks, failures are only reported.
+    async fn record(&mut self, client: &Client) {
+        if let Err(err) = self.try_record(client).await {
+            eprintln!(
+                "{} failed to record the activity: {err:#}",
+                "warning:".yellow().bold()
+            );
+        }
+    }
+
+    async fn try_record(&mut self, client: &Client) -> Result<()> {
+        let latest = u64::from(client.blocks().at_latest().await?.number());
+        let from_block = self.next_block.unwrap_or(latest);
+        let decoder = super::extrinsics::EventDecoder::new(
+            None,
+            &self.metadata,
+            &self.token_metadata,
+        );
+        super::extrinsics::cache_blocks(
+            &mut self.cache,
+            client,
+            &decoder,
+            from_block..=latest,
+        )
+        .await?;
+        self.next_block = Some(latest + 1);
+        Ok(())
+    }
+}
+
+/// The thresholds of the alerts.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Thresholds {
+    min_balance: Option<Balance>,
+    max_deposit_increase: u32,
+}
+
+/// The on-chain state of a contract.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Snapshot {
+    code_hash: CodeHash,
+    free_balance: Balance,
+    storage_deposit: Balance,
+}
+
+impl Snapshot {
+    /// Fetch the state of the contract, `None` if there is no contract at the address.
+    async fn fetch(client: &Client, contract: &AccountId) -> Result<Option<Self>> {
+        let Some(info) = fetch_contract_info(client, contract).await? else {
+            return Ok(None)
+        };
+        let account = client
+            .storage()
+            .at_latest()
+            .await?
+            .fetch_or_default(&api::storage().system().account(contract))
+            .await?;
+        Ok(Some(Self {
+            code_hash: info.code_hash,
+            free_balance: account.data.free,
+            storage_deposit: info
+                .storage_byte_deposit
+                .saturating_add(info.storage_item_deposit)
+                .saturating_add(info.storage_base_deposit),
+        }))
+    }
+}
+
+/// A watched contract and the state its changes are alerted against.
+#[derive(Debug)]
+struct Watched {
+    contract: AccountId,
+    /// The expected code hash, the first one seen if `None`.
+    code_hash: Option<CodeHash>,
+    /// The state at the previous check, `None` before the first check.
+    previous: Option<Option<Snapshot>>,
+    /// The storage deposit an increase is alerted against.
+    deposit_baseline: Option<Balance>,
+}
+
+/// An unexpected change of a contract.
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize)]
+struct Alert {
+    contract: String,
+    kind: AlertKind,
+    message: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize)]
+#[serde(rename_all = "snake_case")]
+enum AlertKind {
+    CodeHashChanged,
+    Terminated,
+    LowBalance,
+    StorageDepositSpike,
+}
+
+impl Watched {
+    fn new(contract: AccountId, code_hash: Option<CodeHash>) -> Self {
+        Self {
+            contract,
+            code_hash,
+            previous: None,
+            deposit_baseline: None,
+        }
+    }
+
+    /// Returns the alerts of the changes since the previous check.
+    fn check(
+        &mut self,
+        current: Option<&Snapshot>,
+        thresholds: &Thresholds,
+    ) -> Vec<Alert> {
+        let mut alerts = Vec::new();
+        let mut alert = |kind, message: String| {
+            alerts.push(Alert {
+                contract: self.contract.to_string(),
+                kind,
+                message,
+            })
+        };
+        let previous = self.previous.replace(current.cloned());
+        let Some(current) = current else {
+            if previous.is_none() || previous.is_some_and(|previous| previous.is_some()) {
+                alert(
+                    AlertKind::Terminated,
+                    "There is no contract at the address, it was terminated".to_string(),
+                );
+            }
+            return alerts
+        };
+
+        let expected = *self.code_hash.get_or_insert(current.code_hash);
+        if current.code_hash != expected {
+            alert(
+                AlertKind::CodeHashChanged,
+                format!(
+                    "The code hash changed from {expected:?} to {:?}",
+                    current.code_hash
+                ),
+            );
+            self.code_hash = Some(current.code_hash);
+        }
+
+        if let Some(min_balance) = thresholds.min_balance {
+            let was_low = previous
+                .flatten()
+                .is_some_and(|previous| previous.free_balance < min_balance);
+            if current.free_balance < min_balance && !was_low {
+                alert(
+                    AlertKind::LowBalance,
+                    format!(
+                        "The free balance of {} planck dropped below {min_balance}",
+                        current.free_balance
+                    ),
+                );
+            }
+        }
+
+        let baseline = *self.deposit_baseline.get_or_insert(current.storage_deposit);
+        let limit = baseline
+            .saturating_mul(100 + u128::from(thresholds.max_deposit_increase))
+            / 100;
+        if baseline == 0 || current.storage_deposit < baseline {
+            self.deposit_baseline = Some(current.storage_deposit);
+        } else if current.storage_deposit > limit {
+            alert(
+                AlertKind::StorageDepositSpike,
+                format!(
+                    "The storage deposit grew from {baseline} to {} planck, by more than \
+                    {}%",
+                    current.storage_deposit, thresholds.max_deposit_increase
+                ),
+            );
+            self.deposit_baseline = Some(current.storage_deposit);
+        }
+        alerts
+    }
+}
+
+/// Send the alerts to the webhook, as a JSON `POST` request made by `curl`.
+fn send_webhook(webhook: &url::Url, network: &str, alerts: &[Alert]) -> Result<()> {
+    let body = serde_json::json!({ "network": network, "alerts": alerts });
+    let mut curl = Command::new("curl")
+        .args([
+            "--fail",
+            "--silent",
+            "--show-error",
+            "--request",
+            "POST",
+            "--header",
+            "Content-Type: application/json",
+            "--data-binary",
+            "@-",
+        ])
+        .arg(webhook.as_str())
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .spawn()
+        .context("Failed to run `curl` to send the alerts to the webhook")?;
+    if let Some(mut stdin) = curl.stdin.take() {
+        stdin.write_all(body.to_string().as_bytes())?;
+    }
+    let status = curl.wait()?;
+    anyhow::ensure!(
+        status.success(),
+        "Failed to send the alerts to the webhook {webhook}, `curl` exited with {status}"
+    );
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn changes_are_alerted_once() {
+        let thresholds = Thresholds {
+            min_balance: Some(100),
+            max_deposit_increase: 50,
+        };
+        let snapshot = |code_hash: u8, free_balance, storage_deposit| {
+            Snapshot {
+                code_hash: CodeHash::repeat_byte(code_hash),
+                free_balance,
+                storage_deposit,
+            }
+        };
+        let mut watched =
+            Watched::new(AccountId::from([0; 32]), Some(CodeHash::repeat_byte(1)));
+        let mut kinds = |current: Option<Snapshot>| {
+            watched
+                .check(current.as_ref(), &thresholds)
+                .into_iter()
+                .map(|alert| alert.kind)
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(kinds(Some(snapshot(1, 1000, 1000))), []);
+        assert_eq!(kinds(Some(snapshot(1, 1000, 1400))), []);
+        assert_eq!(
+            kinds(Some(snapshot(2, 50, 1600))),
+            [
+                AlertKind::CodeHashChanged,
+                AlertKind::LowBalance,
+                AlertKind::StorageDepositSpike
+            ]
+        );
+        assert_eq!(kinds(Some(snapshot(2, 40, 1700))), []);
+        assert_eq!(kinds(None), [AlertKind::Terminated]);
+        assert_eq!(kinds(None), []);
+    }
+}
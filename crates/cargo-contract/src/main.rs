@@ -19,25 +19,70 @@
 mod cmd;
 
 use self::cmd::{
+    AccountCommand,
+    AsyncCommand,
+    AttestCommand,
     BuildCommand,
     CallCommand,
     CheckCommand,
     DecodeCommand,
+    DeployCommand,
     ErrorVariant,
+    EstimateCommand,
+    EventsCommand,
     InfoCommand,
     InstantiateCommand,
+    LinkDependencyCommand,
+    MulticallCommand,
     RemoveCommand,
+    RentCommand,
+    SubmitCommand,
+    TransferCodeCommand,
+    UpgradeCommand,
     UploadCommand,
+    WaitUntilCommand,
+};
+use cmd::{
+    agent::AgentCommand,
+    audit_bundle::AuditBundleCommand,
+    chain_info::ChainInfoCommand,
+    chains::ChainsCommand,
+    changelog::ChangelogCommand,
+    code_size::CodeSizeCommand,
+    convert::ConvertCommand,
+    determinism::ScanDeterminismCommand,
+    doctor::DoctorCommand,
+    encode::EncodeCommand,
+    explain::ExplainErrorCommand,
+    gen_e2e::GenE2eCommand,
+    i18n::I18nCommand,
+    init_ci::InitCiCommand,
+    logging::LogArgs,
+    new_client::NewClientCommand,
+    node::NodeCommand,
+    permissions::PermissionsCommand,
+    plugin,
+    provenance::VerifyProvenanceCommand,
+    queue::QueueCommand,
+    session::SessionCommand,
+    storage::StorageCommand,
+    template::TemplateArgs,
+    trends::TrendsCommand,
+    typegen::TypegenCommand,
+    verify::VerifyCommand,
+    watch::WatchDeploymentsCommand,
 };
-use cmd::encode::EncodeCommand;
 use contract_build::{
     name_value_println,
     util::DEFAULT_KEY_COL_WIDTH,
     OutputType,
 };
 use std::{
+    ffi::OsString,
     fmt::Debug,
+    future::Future,
     path::PathBuf,
+    pin::Pin,
     str::FromStr,
 };
 
@@ -66,9 +111,6 @@ use regex as _;
 #[cfg(test)]
 use tempfile as _;
 
-// Only used on windows.
-use which as _;
-
 #[derive(Debug, Parser)]
 #[clap(bin_name = "cargo")]
 #[clap(version = env!("CARGO_CONTRACT_CLI_IMPL_VERSION"))]
@@ -82,6 +124,10 @@ pub(crate) enum Opts {
 
 #[derive(Debug, Args)]
 pub(crate) struct ContractArgs {
+    #[clap(flatten)]
+    log: LogArgs,
+    #[clap(flatten)]
+    template: TemplateArgs,
     #[clap(subcommand)]
     cmd: Command,
 }
@@ -116,6 +162,24 @@ enum Command {
     /// artifact to the `target/` directory
     #[clap(name = "check")]
     Check(CheckCommand),
+    /// Scan the Wasm code for instructions failing the deterministic validation
+    #[clap(name = "scan-determinism")]
+    ScanDeterminism(ScanDeterminismCommand),
+    /// Report the size of the Wasm code and its biggest contributors
+    #[clap(name = "code-size")]
+    CodeSize(CodeSizeCommand),
+    /// Record and render the code size, build time and benchmarks of contracts by commit
+    #[clap(name = "trends")]
+    Trends(TrendsCommand),
+    /// Rebuild the contract and verify that its code matches a bundle or on-chain code
+    #[clap(name = "verify")]
+    Verify(VerifyCommand),
+    /// Verify the signed provenance statement of a build against its artifacts
+    #[clap(name = "verify-provenance")]
+    VerifyProvenance(VerifyProvenanceCommand),
+    /// Render the changelog of the contract interface recorded by the builds
+    #[clap(name = "changelog")]
+    Changelog(ChangelogCommand),
     /// Upload contract code
     #[clap(name = "upload")]
     Upload(UploadCommand),
@@ -125,26 +189,173 @@ enum Command {
     /// Call a contract
     #[clap(name = "call")]
     Call(CallCommand),
+    /// Estimate the gas, storage deposit and fee of a message or constructor
+    #[clap(name = "estimate")]
+    Estimate(EstimateCommand),
+    /// Deploy the contracts of a deployment manifest in dependency order
+    #[clap(name = "deploy")]
+    Deploy(DeployCommand),
+    /// Broadcast an extrinsic signed offline
+    #[clap(name = "submit")]
+    Submit(SubmitCommand),
+    /// Dry-run many messages of a contract in one RPC round trip
+    #[clap(name = "multicall")]
+    Multicall(MulticallCommand),
+    /// Dry-run a message repeatedly until its return value satisfies a condition
+    #[clap(name = "wait-until")]
+    WaitUntil(WaitUntilCommand),
+    /// Register or unregister a delegate dependency of a contract
+    #[clap(name = "link-dependency")]
+    LinkDependency(LinkDependencyCommand),
     /// Encodes a contracts input calls and their arguments
     #[clap(name = "encode")]
     Encode(EncodeCommand),
     /// Decodes a contracts input or output data (supplied in hex-encoding)
     #[clap(name = "decode")]
     Decode(DecodeCommand),
+    /// Generate ink! end-to-end test boilerplate from the contract metadata
+    #[clap(name = "gen-e2e")]
+    GenE2e(GenE2eCommand),
+    /// Generate typed client bindings from the contract metadata
+    #[clap(name = "typegen")]
+    Typegen(TypegenCommand),
+    /// Translate the documentation of a contract
+    #[clap(name = "i18n")]
+    I18n(I18nCommand),
+    /// Generate a CI pipeline building, checking and deploying the contract
+    #[clap(name = "init-ci")]
+    InitCi(InitCiCommand),
+    /// Scaffold a client project for a contract deployed on-chain
+    #[clap(name = "new-client")]
+    NewClient(NewClientCommand),
     /// Remove contract code
     #[clap(name = "remove")]
     Remove(RemoveCommand),
+    /// Transfer the ownership of uploaded code
+    #[clap(name = "transfer-code")]
+    TransferCode(TransferCodeCommand),
+    /// Attest the metadata and source of uploaded code on-chain
+    #[clap(name = "attest")]
+    Attest(AttestCommand),
+    /// Generate deployment keys, and map accounts to contract addresses
+    #[clap(name = "account")]
+    Account(AccountCommand),
+    /// Hold unlocked keystore keys in memory for a session
+    #[clap(name = "agent")]
+    Agent(AgentCommand),
+    /// Bundle the local deployment records, confirmed on-chain, into a signed file
+    #[clap(name = "audit-bundle")]
+    AuditBundle(AuditBundleCommand),
+    /// Upgrade the code of a contract
+    #[clap(name = "upgrade")]
+    Upgrade(UpgradeCommand),
     /// Display information about a contract
     #[clap(name = "info")]
     Info(InfoCommand),
+    /// Inspect the storage of a contract
+    #[clap(name = "storage")]
+    Storage(StorageCommand),
+    /// Monitor the deployed contracts for unexpected changes
+    #[clap(name = "watch-deployments")]
+    WatchDeployments(WatchDeploymentsCommand),
+    /// Display the limits and prices of pallet-contracts on the chain
+    #[clap(name = "chain-info")]
+    ChainInfo(ChainInfoCommand),
+    /// List the known contracts-enabled chains and compare them with their entries
+    #[clap(name = "chains")]
+    Chains(ChainsCommand),
+    /// Convert amounts, block counts, durations and timestamps with the chain constants
+    #[clap(name = "convert")]
+    Convert(ConvertCommand),
+    /// Check the toolchain, the node and the contract for setup problems
+    #[clap(name = "doctor")]
+    Doctor(DoctorCommand),
+    /// Explain an error of pallet-contracts or ink! and its likely fix
+    #[clap(name = "explain-error")]
+    ExplainError(ExplainErrorCommand),
+    /// Display the events of the contracts pallet in a range of blocks
+    #[clap(name = "events")]
+    Events(EventsCommand),
+    /// Display a summary of the cached activity of the contracts
+    #[cfg(feature = "activity-cache")]
+    #[clap(name = "dashboard")]
+    Dashboard(cmd::DashboardCommand),
+    /// Storage rent helpers for chains running a legacy pallet-contracts
+    #[clap(name = "rent")]
+    Rent(RentCommand),
+    /// dApp staking helpers for Astar family chains
+    #[cfg(feature = "astar")]
+    #[clap(name = "astar")]
+    Astar(cmd::AstarCommand),
+    /// Run the substrate-contracts-node release pinned by the project
+    #[clap(name = "node")]
+    Node(NodeCommand),
+    /// Queue transactions on disk and submit them once the node is reachable
+    #[clap(name = "queue")]
+    Queue(QueueCommand),
+    /// Export and replay interaction sessions recorded with `CARGO_CONTRACT_SESSION`
+    #[clap(name = "session")]
+    Session(SessionCommand),
+    /// Sign and verify the messages whitelisted by `permissions.toml` per network and
+    /// role
+    #[clap(name = "permissions")]
+    Permissions(PermissionsCommand),
+    /// List the custom commands provided by `cargo-contract-<name>` executables in
+    /// `PATH`
+    #[clap(name = "plugins")]
+    Plugins,
+    /// Run a custom command provided by a `cargo-contract-<name>` executable in `PATH`
+    #[clap(external_subcommand)]
+    External(Vec<String>),
 }
 
-fn main() {
-    tracing_subscriber::fmt::init();
-
-    let Opts::Contract(args) = Opts::parse();
+#[tokio::main]
+async fn main() {
+    let args = match expand_alias(std::env::args_os().collect()) {
+        Ok(args) => args,
+        Err(err) => {
+            eprintln!("{err:?}");
+            std::process::exit(1);
+        }
+    };
+    // The schema is printed without the required arguments of the command.
+    if let Some(schema) = cmd::schema::OutputSchema::requested(&args) {
+        if let Err(err) = schema.print() {
+            eprintln!("{err:?}");
+            std::process::exit(1);
+        }
+        return
+    }
+    let Opts::Contract(contract_args) = Opts::parse_from(args.clone());
+    if let Err(err) = contract_args.log.init() {
+        eprintln!("{err:?}");
+        std::process::exit(1);
+    }
+    match contract_args.template.template() {
+        Ok(None) => {}
+        Ok(Some(template)) => {
+            let cli = <Opts as clap::CommandFactory>::command();
+            match cmd::template::run_rendered(&cli, args, &template) {
+                Ok(code) => std::process::exit(code),
+                Err(err) => {
+                    eprintln!("{err:?}");
+                    std::process::exit(1);
+                }
+            }
+        }
+        Err(err) => {
+            eprintln!("{err:?}");
+            std::process::exit(1);
+        }
+    }
 
-    match exec(args.cmd) {
+    cmd::session::begin(
+        args.iter()
+            .skip(2)
+            .map(|arg| arg.to_string_lossy().into_owned())
+            .collect(),
+    );
+    match exec(contract_args.cmd).await {
         Ok(()) => {}
         Err(err) => {
             eprintln!("{err:?}");
@@ -153,13 +364,73 @@ fn main() {
     }
 }
 
-fn exec(cmd: Command) -> Result<()> {
+/// Expand the command if it is an alias of the `cargo-contract.toml` configuration file.
+fn expand_alias(mut args: Vec<OsString>) -> Result<Vec<OsString>> {
+    let Some(command) = args
+        .get(2)
+        .filter(|_| args.get(1).is_some_and(|arg| arg == "contract"))
+        .and_then(|command| command.to_str())
+    else {
+        return Ok(args)
+    };
+    let cli = <Opts as clap::CommandFactory>::command();
+    let is_builtin = |name: &str| {
+        name == "help"
+            || cli
+                .find_subcommand("contract")
+                .is_some_and(|contract| contract.find_subcommand(name).is_some())
+    };
+    if is_builtin(command) {
+        return Ok(args)
+    }
+    let config = cmd::config::Config::load()?;
+    if let Some(expanded) = config.expand_alias(command, is_builtin)? {
+        args.splice(2..3, expanded.into_iter().map(OsString::from));
+    }
+    Ok(args)
+}
+
+async fn exec(cmd: Command) -> Result<()> {
     match &cmd {
         Command::New { name, target_dir } => {
             contract_build::new_contract_project(name, target_dir.as_ref())?;
             println!("Created contract {name}");
             Ok(())
         }
+        Command::Build(build) if build.watch() => build.exec_watch().map_err(format_err),
+        Command::Build(build) if build.is_workspace() => {
+            let results = build.exec_workspace().map_err(format_err)?;
+            if results
+                .iter()
+                .any(|built| matches!(built.result.output_type, OutputType::Json))
+            {
+                println!("{}", serde_json::to_string_pretty(&results)?)
+            } else {
+                for built in &results {
+                    if built.result.verbosity.is_verbose() {
+                        println!("{}\n{}", built.contract.bold(), built.result.display())
+                    }
+                    if build.timings() {
+                        println!("{}", built.result.build_log.display_timings())
+                    }
+                }
+                if results
+                    .iter()
+                    .any(|built| built.result.verbosity.is_verbose())
+                {
+                    println!(
+                        "Built {} contracts: {}",
+                        results.len(),
+                        results
+                            .iter()
+                            .map(|built| built.contract.as_str())
+                            .collect::<Vec<_>>()
+                            .join(", ")
+                    );
+                }
+            }
+            Ok(())
+        }
         Command::Build(build) => {
             let result = build.exec().map_err(format_err)?;
 
@@ -168,6 +439,9 @@ fn exec(cmd: Command) -> Result<()> {
             } else if result.verbosity.is_verbose() {
                 println!("{}", result.display())
             }
+            if build.timings() {
+                println!("{}", result.build_log.display_timings())
+            }
             Ok(())
         }
         Command::Check(check) => {
@@ -181,31 +455,159 @@ fn exec(cmd: Command) -> Result<()> {
             }
             Ok(())
         }
+        Command::ScanDeterminism(scan) => scan.exec().await.map_err(format_err),
+        Command::Verify(verify) => verify.exec().await.map_err(format_err),
+        Command::VerifyProvenance(verify) => verify.exec().await.map_err(format_err),
+        Command::Changelog(changelog) => changelog.exec().await.map_err(format_err),
         Command::Upload(upload) => {
             upload
-                .run()
+                .exec()
+                .await
                 .map_err(|err| map_extrinsic_err(err, upload.is_json()))
         }
         Command::Instantiate(instantiate) => {
             instantiate
-                .run()
+                .exec()
+                .await
                 .map_err(|err| map_extrinsic_err(err, instantiate.is_json()))
         }
         Command::Call(call) => {
-            call.run()
+            call.exec()
+                .await
                 .map_err(|err| map_extrinsic_err(err, call.is_json()))
         }
-        Command::Encode(encode) => encode.run().map_err(format_err),
-        Command::Decode(decode) => decode.run().map_err(format_err),
+        Command::Estimate(estimate) => {
+            estimate
+                .exec()
+                .await
+                .map_err(|err| map_extrinsic_err(err, estimate.is_json()))
+        }
+        Command::Deploy(deploy) => {
+            deploy
+                .exec()
+                .await
+                .map_err(|err| map_extrinsic_err(err, deploy.is_json()))
+        }
+        Command::Submit(submit) => submit.exec().await.map_err(format_err),
+        Command::Multicall(multicall) => {
+            multicall
+                .exec()
+                .await
+                .map_err(|err| map_extrinsic_err(err, multicall.is_json()))
+        }
+        Command::WaitUntil(wait_until) => {
+            wait_until
+                .exec()
+                .await
+                .map_err(|err| map_extrinsic_err(err, wait_until.is_json()))
+        }
+        Command::LinkDependency(link_dependency) => {
+            link_dependency
+                .exec()
+                .await
+                .map_err(|err| map_extrinsic_err(err, link_dependency.is_json()))
+        }
+        Command::Encode(encode) => encode.exec().await.map_err(format_err),
+        Command::Decode(decode) => decode.exec().await.map_err(format_err),
+        Command::CodeSize(code_size) => code_size.exec().await.map_err(format_err),
+        Command::Trends(trends) => trends.exec().await.map_err(format_err),
+        Command::GenE2e(gen_e2e) => gen_e2e.exec().await.map_err(format_err),
+        Command::InitCi(init_ci) => init_ci.exec().await.map_err(format_err),
+        Command::Typegen(typegen) => typegen.exec().await.map_err(format_err),
+        Command::I18n(i18n) => i18n.exec().await.map_err(format_err),
+        Command::NewClient(new_client) => new_client.exec().await.map_err(format_err),
         Command::Remove(remove) => {
             remove
-                .run()
+                .exec()
+                .await
                 .map_err(|err| map_extrinsic_err(err, remove.is_json()))
         }
-        Command::Info(info) => info.run().map_err(format_err),
+        Command::TransferCode(transfer) => {
+            transfer
+                .exec()
+                .await
+                .map_err(|err| map_extrinsic_err(err, transfer.is_json()))
+        }
+        Command::Account(account) => {
+            account
+                .exec()
+                .await
+                .map_err(|err| map_extrinsic_err(err, account.is_json()))
+        }
+        Command::Agent(agent) => agent.exec().await.map_err(format_err),
+        Command::AuditBundle(audit_bundle) => {
+            audit_bundle.exec().await.map_err(format_err)
+        }
+        Command::Attest(attest) => {
+            attest
+                .exec()
+                .await
+                .map_err(|err| map_extrinsic_err(err, attest.is_json()))
+        }
+        Command::Upgrade(upgrade) => {
+            upgrade
+                .exec()
+                .await
+                .map_err(|err| map_extrinsic_err(err, upgrade.is_json()))
+        }
+        Command::Info(info) => info.exec().await.map_err(format_err),
+        Command::ChainInfo(chain_info) => chain_info.exec().await.map_err(format_err),
+        Command::Chains(chains) => chains.exec().await.map_err(format_err),
+        Command::Storage(storage) => {
+            storage
+                .exec()
+                .await
+                .map_err(|err| map_extrinsic_err(err, storage.is_json()))
+        }
+        Command::WatchDeployments(watch) => watch.exec().await.map_err(format_err),
+        Command::Convert(convert) => convert.exec().await.map_err(format_err),
+        Command::Doctor(doctor) => doctor.exec().await.map_err(format_err),
+        Command::ExplainError(explain) => explain.exec().await.map_err(format_err),
+        Command::Events(events) => events.exec().await.map_err(format_err),
+        #[cfg(feature = "activity-cache")]
+        Command::Dashboard(dashboard) => dashboard.exec().await.map_err(format_err),
+        Command::Rent(rent) => {
+            rent.exec()
+                .await
+                .map_err(|err| map_extrinsic_err(err, rent.is_json()))
+        }
+        #[cfg(feature = "astar")]
+        Command::Astar(astar) => {
+            astar
+                .exec()
+                .await
+                .map_err(|err| map_extrinsic_err(err, astar.is_json()))
+        }
+        Command::Node(node) => node.exec().await.map_err(format_err),
+        Command::Queue(queue) => queue.run(exec_args).await,
+        Command::Session(session) => session.run(exec_args).await,
+        Command::Permissions(permissions) => permissions.exec().await.map_err(format_err),
+        Command::Plugins => {
+            for name in plugin::list(std::env::var_os("PATH")) {
+                println!("{name}");
+            }
+            Ok(())
+        }
+        Command::External(args) => plugin::exec(args).map_err(format_err),
     }
 }
 
+/// Execute the command with the given arguments, e.g. a queued transaction.
+///
+/// The future is boxed, since the commands executing other commands make it recursive.
+fn exec_args(args: Vec<String>) -> Pin<Box<dyn Future<Output = Result<()>>>> {
+    Box::pin(async move {
+        let Opts::Contract(contract_args) = Opts::try_parse_from(
+            ["cargo", "contract"]
+                .map(String::from)
+                .into_iter()
+                .chain(args.clone()),
+        )?;
+        cmd::session::begin(args);
+        exec(contract_args.cmd).await
+    })
+}
+
 fn map_extrinsic_err(err: ErrorVariant, is_json: bool) -> Error {
     if is_json {
         anyhow!(
@@ -81,6 +81,10 @@ use serde_json::{
     Value,
 };
 use std::{
+    collections::{
+        BTreeMap,
+        BTreeSet,
+    },
     fmt::{
         Display,
         Formatter,
@@ -102,6 +106,9 @@ pub struct ContractMetadata {
     /// Additional user-defined metadata.
     #[serde(skip_serializing_if = "Option::is_none")]
     pub user: Option<User>,
+    /// The storage fields accessed by the constructors and messages.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub storage_access: Option<StorageAccess>,
     /// Raw JSON of the contract's abi metadata, generated during contract compilation.
     #[serde(flatten)]
     pub abi: Map<String, Value>,
@@ -119,6 +126,7 @@ impl ContractMetadata {
             source,
             contract,
             user,
+            storage_access: None,
             abi,
         }
     }
@@ -477,6 +485,35 @@ impl User {
     }
 }
 
+/// The top-level fields of the storage struct which the constructors and messages read
+/// and write, approximated by a static analysis of the source code of the contract.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Deserialize, Serialize)]
+pub struct StorageAccess {
+    /// The accessed fields of the constructors, by label.
+    pub constructors: BTreeMap<String, FieldAccess>,
+    /// The accessed fields of the messages, by label.
+    pub messages: BTreeMap<String, FieldAccess>,
+}
+
+impl StorageAccess {
+    /// Returns the labels of the messages which write the field.
+    pub fn writers<'a>(&'a self, field: &'a str) -> impl Iterator<Item = &'a str> + 'a {
+        self.messages
+            .iter()
+            .filter(move |(_, access)| access.writes.contains(field))
+            .map(|(label, _)| label.as_str())
+    }
+}
+
+/// The storage fields read and written by a constructor or message.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Deserialize, Serialize)]
+pub struct FieldAccess {
+    /// The fields which are only read.
+    pub reads: BTreeSet<String>,
+    /// The fields which are written, and possibly read.
+    pub writes: BTreeSet<String>,
+}
+
 /// Builder for contract metadata
 #[derive(Default)]
 pub struct ContractBuilder {
@@ -901,4 +938,31 @@ mod tests {
         let decoded = serde_json::from_value::<ContractMetadata>(json);
         assert!(decoded.is_ok())
     }
+
+    #[test]
+    fn storage_access_round_trips() {
+        let json = json! {
+            {
+                "source": {
+                    "hash": "0x0000000000000000000000000000000000000000000000000000000000000000",
+                    "language": "ink! 4.2.0",
+                    "compiler": "rustc 1.69.0"
+                },
+                "contract": { "name": "flipper", "version": "0.1.0", "authors": [] },
+                "storage_access": {
+                    "constructors": { "new": { "reads": [], "writes": ["value"] } },
+                    "messages": {
+                        "flip": { "reads": [], "writes": ["value"] },
+                        "get": { "reads": ["value"], "writes": [] }
+                    }
+                },
+                "spec": {}
+            }
+        };
+        let metadata = serde_json::from_value::<ContractMetadata>(json.clone()).unwrap();
+        let access = metadata.storage_access.as_ref().unwrap();
+        assert_eq!(access.writers("value").collect::<Vec<_>>(), ["flip"]);
+        assert!(!metadata.abi.contains_key("storage_access"));
+        assert_eq!(serde_json::to_value(&metadata).unwrap(), json);
+    }
 }
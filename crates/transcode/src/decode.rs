@@ -17,10 +17,12 @@
 use super::{
     env_types::EnvTypesTranscoder,
     scon::{
+        Hex,
         Map,
         Tuple,
         Value,
     },
+    AccountId32,
     CompositeTypeFields,
 };
 use anyhow::{
@@ -46,19 +48,33 @@ use scale_info::{
     TypeDefVariant,
 };
 
+/// How sequences and arrays of bytes are decoded.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum BytesFormat {
+    /// Bytes are decoded as a sequence of integers.
+    #[default]
+    Raw,
+    /// `Vec<u8>` which is printable UTF-8 is decoded as a string, `[u8; 32]` fields with
+    /// a type name ending in `AccountId` as an SS58 address and other bytes as hex.
+    Pretty,
+}
+
 pub struct Decoder<'a> {
     registry: &'a PortableRegistry,
     env_types: &'a EnvTypesTranscoder,
+    bytes_format: BytesFormat,
 }
 
 impl<'a> Decoder<'a> {
     pub fn new(
         registry: &'a PortableRegistry,
         env_types: &'a EnvTypesTranscoder,
+        bytes_format: BytesFormat,
     ) -> Self {
         Self {
             registry,
             env_types,
+            bytes_format,
         }
     }
 
@@ -80,10 +96,43 @@ impl<'a> Decoder<'a> {
         }
     }
 
+    /// Decode a field, using the name of its type as a hint for the format of bytes.
+    fn decode_field(
+        &self,
+        field: &Field<PortableForm>,
+        input: &mut &[u8],
+    ) -> Result<Value> {
+        let is_account_id = field
+            .type_name
+            .as_ref()
+            .is_some_and(|name| name.ends_with("AccountId"));
+        if is_account_id && self.bytes_format == BytesFormat::Pretty {
+            let ty = self.registry.resolve(field.ty.id).ok_or_else(|| {
+                anyhow::anyhow!("Failed to resolve type with id `{:?}`", field.ty.id)
+            })?;
+            if let TypeDef::Array(array) = &ty.type_def {
+                if array.len == 32 && self.is_byte(&array.type_param) {
+                    let account_id = AccountId32::decode(input)?;
+                    return Ok(Value::Literal(account_id.to_ss58check()))
+                }
+            }
+        }
+        self.decode(field.ty.id, input)
+    }
+
+    /// Returns `true` if the type is `u8`.
+    fn is_byte(&self, ty: &<PortableForm as Form>::Type) -> bool {
+        matches!(
+            self.registry.resolve(ty.id).map(|ty| &ty.type_def),
+            Some(TypeDef::Primitive(TypeDefPrimitive::U8))
+        )
+    }
+
     fn decode_seq(
         &self,
         ty: &<PortableForm as Form>::Type,
         len: usize,
+        is_vec: bool,
         input: &mut &[u8],
     ) -> Result<Value> {
         let type_id = ty.id;
@@ -91,6 +140,17 @@ impl<'a> Decoder<'a> {
             anyhow::anyhow!("Failed to find type with id '{}'", type_id)
         })?;
 
+        if self.bytes_format == BytesFormat::Pretty
+            && matches!(ty.type_def, TypeDef::Primitive(TypeDefPrimitive::U8))
+        {
+            if input.len() < len {
+                anyhow::bail!("Not enough data to decode {len} bytes")
+            }
+            let (bytes, rest) = input.split_at(len);
+            *input = rest;
+            return Ok(pretty_bytes(bytes, is_vec))
+        }
+
         let mut elems = Vec::new();
         while elems.len() < len {
             let elem = self.decode_type(type_id, ty, input)?;
@@ -123,11 +183,11 @@ impl<'a> Decoder<'a> {
             }
             TypeDef::Variant(variant) => self.decode_variant_type(variant, input),
             TypeDef::Array(array) => {
-                self.decode_seq(&array.type_param, array.len as usize, input)
+                self.decode_seq(&array.type_param, array.len as usize, false, input)
             }
             TypeDef::Sequence(sequence) => {
                 let len = <Compact<u32>>::decode(input)?;
-                self.decode_seq(&sequence.type_param, len.0 as usize, input)
+                self.decode_seq(&sequence.type_param, len.0 as usize, true, input)
             }
             TypeDef::Primitive(primitive) => self.decode_primitive(primitive, input),
             TypeDef::Compact(compact) => self.decode_compact(compact, input),
@@ -150,7 +210,7 @@ impl<'a> Decoder<'a> {
             CompositeTypeFields::Named(fields) => {
                 let mut map = Vec::new();
                 for field in fields {
-                    let value = self.decode(field.field().ty.id, input)?;
+                    let value = self.decode_field(field.field(), input)?;
                     map.push((Value::String(field.name().to_string()), value));
                 }
                 Ok(Value::Map(Map::new(ident, map.into_iter().collect())))
@@ -158,7 +218,7 @@ impl<'a> Decoder<'a> {
             CompositeTypeFields::Unnamed(fields) => {
                 let mut tuple = Vec::new();
                 for field in &fields {
-                    let value = self.decode(field.ty.id, input)?;
+                    let value = self.decode_field(field, input)?;
                     tuple.push(value);
                 }
                 Ok(Value::Tuple(Tuple::new(
@@ -189,7 +249,7 @@ impl<'a> Decoder<'a> {
         let mut named = Vec::new();
         let mut unnamed = Vec::new();
         for field in &variant.fields {
-            let value = self.decode(field.ty.id, input)?;
+            let value = self.decode_field(field, input)?;
             if let Some(name) = &field.name {
                 named.push((Value::String(name.to_owned()), value));
             } else {
@@ -330,6 +390,23 @@ impl<'a> Decoder<'a> {
     }
 }
 
+/// Decode bytes as a string if they are a `Vec<u8>` of printable UTF-8, else as hex.
+fn pretty_bytes(bytes: &[u8], is_vec: bool) -> Value {
+    match std::str::from_utf8(bytes) {
+        Ok(string) if is_vec && is_printable(string) => Value::String(string.to_string()),
+        _ => Value::Hex(Hex::from(bytes.to_vec())),
+    }
+}
+
+/// Returns `true` if the string is non-empty and has no control characters other than
+/// whitespace.
+fn is_printable(string: &str) -> bool {
+    !string.is_empty()
+        && string
+            .chars()
+            .all(|c| !c.is_control() || c.is_ascii_whitespace())
+}
+
 fn decode_uint<T>(input: &mut &[u8]) -> Result<Value>
 where
     T: Decode + Into<u128>,
@@ -39,6 +39,7 @@ use std::{
     collections::HashMap,
     convert::TryFrom,
     str::FromStr,
+    sync::Arc,
 };
 
 /// Provides custom encoding and decoding for predefined environment types.
@@ -127,6 +128,13 @@ impl From<&Path<PortableForm>> for PathKey {
     }
 }
 
+impl From<&str> for PathKey {
+    /// The key of a path in Rust notation, e.g. `my_contract::Balance`.
+    fn from(path: &str) -> Self {
+        PathKey(path.split("::").map(ToString::to_string).collect())
+    }
+}
+
 pub type TypesByPath = HashMap<PathKey, u32>;
 
 /// Implement this trait to define custom encoding for a type in a `scale-info` type
@@ -194,6 +202,95 @@ impl CustomTypeDecoder for AccountId {
     }
 }
 
+/// Custom encoding/decoding for a type encoded as `u128` which holds an amount of tokens
+/// with the given number of decimals, e.g. a newtype wrapping a `Balance`.
+///
+/// Enables the amount to be input as a decimal number of tokens, e.g. `"1.5"` or `2`,
+/// and to be output with the token symbol, e.g. `1.5 UNIT`.
+#[derive(Clone)]
+pub struct TokenAmount {
+    decimals: u8,
+    symbol: Option<String>,
+}
+
+impl TokenAmount {
+    /// Create a transcoder for amounts of tokens with the given number of decimals.
+    pub fn new(decimals: u8, symbol: Option<String>) -> Result<Self> {
+        10u128
+            .checked_pow(decimals.into())
+            .with_context(|| format!("{decimals} decimals do not fit into a u128"))?;
+        Ok(Self { decimals, symbol })
+    }
+
+    fn unit(&self) -> u128 {
+        10u128.pow(self.decimals.into())
+    }
+
+    /// Convert a decimal number of tokens into the smallest unit.
+    fn parse_amount(&self, amount: &str) -> Result<u128> {
+        let (integer, fraction) = amount.split_once('.').unwrap_or((amount, ""));
+        let decimals = usize::from(self.decimals);
+        if fraction.len() > decimals {
+            anyhow::bail!(
+                "Token amount `{amount}` has more than {decimals} decimal places"
+            )
+        }
+        let integer: u128 = if integer.is_empty() {
+            0
+        } else {
+            integer.parse()?
+        };
+        let fraction: u128 = if fraction.is_empty() {
+            0
+        } else {
+            format!("{fraction:0<decimals$}").parse()?
+        };
+        integer
+            .checked_mul(self.unit())
+            .and_then(|integer| integer.checked_add(fraction))
+            .with_context(|| format!("Token amount `{amount}` does not fit into a u128"))
+    }
+}
+
+impl CustomTypeEncoder for TokenAmount {
+    fn encode_value(&self, value: &Value) -> Result<Vec<u8>> {
+        let amount = match value {
+            Value::UInt(tokens) => {
+                tokens.checked_mul(self.unit()).with_context(|| {
+                    format!("Token amount `{tokens}` does not fit into a u128")
+                })?
+            }
+            Value::String(amount) | Value::Literal(amount) => {
+                self.parse_amount(amount)
+                    .with_context(|| format!("Error parsing token amount `{amount}`"))?
+            }
+            _ => {
+                return Err(anyhow::anyhow!(
+                    "Expected an integer or a string for a token amount"
+                ))
+            }
+        };
+        Ok(amount.encode())
+    }
+}
+
+impl CustomTypeDecoder for TokenAmount {
+    fn decode_value(&self, input: &mut &[u8]) -> Result<Value> {
+        let amount = u128::decode(input)?;
+        let (integer, fraction) = (amount / self.unit(), amount % self.unit());
+        let mut formatted = integer.to_string();
+        if fraction > 0 {
+            let fraction =
+                format!("{fraction:0>width$}", width = usize::from(self.decimals));
+            formatted = format!("{formatted}.{}", fraction.trim_end_matches('0'));
+        }
+        if let Some(ref symbol) = self.symbol {
+            formatted = format!("{formatted} {symbol}");
+        }
+        Ok(Value::Literal(formatted))
+    }
+}
+
 /// Custom decoding for the `Hash` or `[u8; 32]` type so that it is displayed as a hex
 /// encoded string.
 pub struct Hash;
@@ -204,3 +301,103 @@ impl CustomTypeDecoder for Hash {
         Ok(Value::Hex(Hex::from_str(&format!("{hash:?}"))?))
     }
 }
+
+/// Custom encoding/decoding for a 256 bit unsigned integer, e.g. `primitive_types::U256`
+/// or a newtype wrapping a `[u64; 4]`.
+///
+/// Enables the integer to be input and output in decimal notation, e.g.
+/// `"115792089237316195423570985008687907853269984665640564039457584007913129639935"`.
+#[derive(Clone)]
+pub struct U256Decimal;
+
+impl CustomTypeEncoder for U256Decimal {
+    fn encode_value(&self, value: &Value) -> Result<Vec<u8>> {
+        let integer = match value {
+            Value::UInt(integer) => primitive_types::U256::from(*integer),
+            Value::String(integer) | Value::Literal(integer) => {
+                primitive_types::U256::from_dec_str(integer).map_err(|err| {
+                    anyhow::anyhow!("Error parsing U256 from `{integer}`: {err:?}")
+                })?
+            }
+            _ => {
+                return Err(anyhow::anyhow!(
+                    "Expected an integer or a string for a U256"
+                ))
+            }
+        };
+        Ok(integer.encode())
+    }
+}
+
+impl CustomTypeDecoder for U256Decimal {
+    fn decode_value(&self, input: &mut &[u8]) -> Result<Value> {
+        let integer = primitive_types::U256::decode(input)?;
+        Ok(Value::Literal(integer.to_string()))
+    }
+}
+
+/// The unit of the `u64` values decoded by [`Timestamp`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum TimestampUnit {
+    /// Milliseconds since the Unix epoch, the unit of the ink! `Timestamp`.
+    #[default]
+    Milliseconds,
+    /// Seconds since the Unix epoch.
+    Seconds,
+}
+
+/// Custom decoding for a timestamp encoded as `u64`, rendered as an RFC 3339 UTC date and
+/// time, e.g. `2023-11-14T22:13:20.500Z`.
+#[derive(Clone)]
+pub struct Timestamp {
+    unit: TimestampUnit,
+}
+
+impl Timestamp {
+    /// Create a decoder for timestamps in the given unit.
+    pub fn new(unit: TimestampUnit) -> Self {
+        Self { unit }
+    }
+}
+
+impl CustomTypeDecoder for Timestamp {
+    fn decode_value(&self, input: &mut &[u8]) -> Result<Value> {
+        let timestamp = u64::decode(input)?;
+        let millis = match self.unit {
+            TimestampUnit::Milliseconds => timestamp,
+            TimestampUnit::Seconds => timestamp.saturating_mul(1_000),
+        };
+        Ok(Value::Literal(crate::util::rfc3339(millis)))
+    }
+}
+
+/// Custom decoding for the Substrate `AccountId` type, rendering the SS58 address
+/// followed by the name of the account returned by a lookup, e.g. the display name of
+/// its on-chain identity: `5GrwvaEF5zXb26Fz9rcQpDWS57CtERHpNehXCPcNoHGKutQY (Alice)`.
+#[derive(Clone)]
+pub struct NamedAccountId {
+    lookup: Arc<dyn Fn(&AccountId32) -> Option<String> + Send + Sync>,
+}
+
+impl NamedAccountId {
+    /// Create a decoder looking up the names of the accounts with the given function.
+    pub fn new<F>(lookup: F) -> Self
+    where
+        F: Fn(&AccountId32) -> Option<String> + Send + Sync + 'static,
+    {
+        Self {
+            lookup: Arc::new(lookup),
+        }
+    }
+}
+
+impl CustomTypeDecoder for NamedAccountId {
+    fn decode_value(&self, input: &mut &[u8]) -> Result<Value> {
+        let account_id = AccountId32::decode(input)?;
+        let address = account_id.to_ss58check();
+        match (self.lookup)(&account_id) {
+            Some(name) => Ok(Value::Literal(format!("{address} ({name})"))),
+            None => Ok(Value::Literal(address)),
+        }
+    }
+}
@@ -108,6 +108,7 @@ mod util;
 
 pub use self::{
     account_id::AccountId32,
+    decode::BytesFormat,
     scon::{
         Hex,
         Map,
@@ -118,6 +119,7 @@ pub use self::{
         Transcoder,
         TranscoderBuilder,
     },
+    util::rfc3339,
 };
 
 use anyhow::{
@@ -155,16 +157,46 @@ pub struct ContractMessageTranscoder {
 
 impl ContractMessageTranscoder {
     pub fn new(metadata: InkProject) -> Self {
-        let transcoder = TranscoderBuilder::new(metadata.registry())
-            .register_custom_type_transcoder::<<ink_env::DefaultEnvironment as ink_env::Environment>::AccountId, _>(env_types::AccountId)
-            .register_custom_type_decoder::<<ink_env::DefaultEnvironment as ink_env::Environment>::Hash, _>(env_types::Hash)
-            .done();
+        let mut transcoder = Self::builder(&metadata).done();
+        transcoder.set_bytes_format(BytesFormat::Pretty);
         Self {
             metadata,
             transcoder,
         }
     }
 
+    /// Create a transcoder from the contract metadata, registering additional custom
+    /// transcoders, e.g. for types configured by the user.
+    pub fn try_from_with_custom_types<F>(
+        metadata: contract_metadata::ContractMetadata,
+        register: F,
+    ) -> Result<Self>
+    where
+        F: FnOnce(TranscoderBuilder) -> Result<TranscoderBuilder>,
+    {
+        let metadata: InkProject =
+            serde_json::from_value(serde_json::Value::Object(metadata.abi))?;
+        let mut transcoder = register(Self::builder(&metadata))?.done();
+        transcoder.set_bytes_format(BytesFormat::Pretty);
+        Ok(Self {
+            metadata,
+            transcoder,
+        })
+    }
+
+    fn builder(metadata: &InkProject) -> TranscoderBuilder {
+        TranscoderBuilder::new(metadata.registry())
+            .register_custom_type_transcoder::<<ink_env::DefaultEnvironment as ink_env::Environment>::AccountId, _>(env_types::AccountId)
+            .register_custom_type_decoder::<<ink_env::DefaultEnvironment as ink_env::Environment>::Hash, _>(env_types::Hash)
+    }
+
+    /// Set how sequences and arrays of bytes are decoded, by default they are pretty
+    /// printed.
+    pub fn with_bytes_format(mut self, bytes_format: BytesFormat) -> Self {
+        self.transcoder.set_bytes_format(bytes_format);
+        self
+    }
+
     /// Attempt to create a [`ContractMessageTranscoder`] from the metadata file at the
     /// given path.
     pub fn load<P>(metadata_path: P) -> Result<Self>
@@ -221,7 +253,7 @@ impl ContractMessageTranscoder {
 
         let mut encoded = selector.to_bytes().to_vec();
         for (spec, arg) in spec_args.iter().zip(args) {
-            let value = scon::parse_value(arg.as_ref())?;
+            let value = scon::parse_arg(arg.as_ref())?;
             self.transcoder.encode(
                 self.metadata.registry(),
                 spec.ty().ty().id,
@@ -385,9 +417,7 @@ impl TryFrom<contract_metadata::ContractMetadata> for ContractMessageTranscoder
     fn try_from(
         metadata: contract_metadata::ContractMetadata,
     ) -> Result<Self, Self::Error> {
-        Ok(Self::new(serde_json::from_value(
-            serde_json::Value::Object(metadata.abi),
-        )?))
+        Self::try_from_with_custom_types(metadata, Ok)
     }
 }
 
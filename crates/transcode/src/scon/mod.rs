@@ -44,7 +44,7 @@ use serde::{
     Serialize,
 };
 
-pub use self::parse::parse_value;
+pub use self::parse::parse_arg;
 
 #[derive(Clone, Debug, Eq, Hash, Ord, PartialEq, PartialOrd, Serialize)]
 pub enum Value {
@@ -240,6 +240,15 @@ impl FromStr for Hex {
     }
 }
 
+impl From<Vec<u8>> for Hex {
+    fn from(bytes: Vec<u8>) -> Self {
+        Self {
+            s: hex::encode(&bytes),
+            bytes,
+        }
+    }
+}
+
 impl Hex {
     pub fn as_str(&self) -> &str {
         &self.s
@@ -62,6 +62,46 @@ pub fn parse_value(input: &str) -> anyhow::Result<Value> {
     Ok(value)
 }
 
+/// Attempt to parse an argument, which is either a SCON value or bytes given in one of
+/// the convenience encodings:
+///
+/// - `str:hello` the UTF-8 bytes of a string, optionally quoted: `str:"hello world"`
+/// - `hex:0xdead` hex encoded bytes, with or without the `0x` prefix
+/// - `file:payload.bin` the contents of a file
+/// - `b58:<...>` base58 encoded bytes
+pub fn parse_arg(input: &str) -> anyhow::Result<Value> {
+    let Some((encoding, data)) = input.trim().split_once(':') else {
+        return parse_value(input)
+    };
+    let bytes = match encoding {
+        "str" => {
+            let quoted = data.len() >= 2 && data.starts_with('"') && data.ends_with('"');
+            let data = if quoted {
+                &data[1..data.len() - 1]
+            } else {
+                data
+            };
+            data.as_bytes().to_vec()
+        }
+        "hex" => {
+            crate::util::decode_hex(data)
+                .map_err(|err| anyhow::anyhow!("Invalid hex bytes `{data}`: {err}"))?
+        }
+        "file" => {
+            std::fs::read(data)
+                .map_err(|err| anyhow::anyhow!("Failed to read file `{data}`: {err}"))?
+        }
+        "b58" => {
+            use base58::FromBase58 as _;
+            data.from_base58().map_err(|err| {
+                anyhow::anyhow!("Invalid base58 bytes `{data}`: {err:?}")
+            })?
+        }
+        _ => return parse_value(input),
+    };
+    Ok(Value::Hex(Hex::from(bytes)))
+}
+
 fn scon_value(input: &str) -> IResult<&str, Value, ErrorTree<&str>> {
     ws(alt((
         scon_unit,
@@ -271,6 +311,33 @@ mod tests {
         assert_eq!(parse_value("true").unwrap(), Value::Bool(true))
     }
 
+    #[test]
+    fn test_parse_arg_bytes_encodings() {
+        let hello = Value::Hex(Hex::from(b"hello".to_vec()));
+        assert_eq!(parse_arg("str:hello").unwrap(), hello);
+        assert_eq!(parse_arg(r#"str:"hello""#).unwrap(), hello);
+        assert_eq!(parse_arg("hex:0x68656c6c6f").unwrap(), hello);
+        assert_eq!(parse_arg("hex:68656c6c6f").unwrap(), hello);
+        assert_eq!(parse_arg("b58:Cn8eVZg").unwrap(), hello);
+
+        let dir = std::env::temp_dir().join("contract-transcode-parse-arg");
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("payload.bin");
+        std::fs::write(&path, b"hello").unwrap();
+        assert_eq!(
+            parse_arg(&format!("file:{}", path.display())).unwrap(),
+            hello
+        );
+
+        assert!(parse_arg("hex:xyz").is_err());
+        assert!(parse_arg("file:does-not-exist.bin").is_err());
+        assert_eq!(parse_arg("42").unwrap(), Value::UInt(42));
+        assert_eq!(
+            parse_arg(r#""str:quoted""#).unwrap(),
+            Value::String("str:quoted".to_string())
+        );
+    }
+
     #[test]
     fn test_unit() {
         assert_eq!(scon_value("()").unwrap(), ("", Value::Unit));
@@ -15,7 +15,10 @@
 // along with cargo-contract.  If not, see <http://www.gnu.org/licenses/>.
 
 use super::{
-    decode::Decoder,
+    decode::{
+        BytesFormat,
+        Decoder,
+    },
     encode::Encoder,
     env_types::{
         self,
@@ -33,6 +36,8 @@ use anyhow::Result;
 use scale::Output;
 use scale_info::{
     PortableRegistry,
+    TypeDef,
+    TypeDefPrimitive,
     TypeInfo,
 };
 use std::{
@@ -44,11 +49,21 @@ use std::{
 /// Decode SCALE encoded input into `Value` objects.
 pub struct Transcoder {
     env_types: EnvTypesTranscoder,
+    bytes_format: BytesFormat,
 }
 
 impl Transcoder {
     pub fn new(env_types: EnvTypesTranscoder) -> Self {
-        Self { env_types }
+        Self {
+            env_types,
+            bytes_format: BytesFormat::default(),
+        }
+    }
+
+    /// Set how sequences and arrays of bytes are decoded, by default they are decoded
+    /// as raw sequences of integers.
+    pub fn set_bytes_format(&mut self, bytes_format: BytesFormat) {
+        self.bytes_format = bytes_format;
     }
 
     pub fn encode<O>(
@@ -71,13 +86,14 @@ impl Transcoder {
         type_id: u32,
         input: &mut &[u8],
     ) -> Result<Value> {
-        let decoder = Decoder::new(registry, &self.env_types);
+        let decoder = Decoder::new(registry, &self.env_types, self.bytes_format);
         decoder.decode(type_id, input)
     }
 }
 
 /// Construct a [`Transcoder`], allows registering custom transcoders for certain types.
 pub struct TranscoderBuilder {
+    registry: PortableRegistry,
     types_by_path: TypesByPath,
     encoders: HashMap<u32, Box<dyn CustomTypeEncoder>>,
     decoders: HashMap<u32, Box<dyn CustomTypeDecoder>>,
@@ -91,12 +107,28 @@ impl TranscoderBuilder {
             .map(|ty| (PathKey::from(&ty.ty.path), ty.id))
             .collect::<TypesByPath>();
         Self {
+            registry: registry.clone(),
             types_by_path,
             encoders: HashMap::new(),
             decoders: HashMap::new(),
         }
     }
 
+    /// Returns the primitive type the type with the given path is encoded as, if it is a
+    /// primitive or a composite wrapping a single primitive, e.g. a newtype.
+    pub fn primitive_type_of(&self, path_key: &PathKey) -> Option<TypeDefPrimitive> {
+        let mut ty = self.registry.resolve(*self.types_by_path.get(path_key)?)?;
+        loop {
+            match ty.type_def {
+                TypeDef::Primitive(ref primitive) => return Some(primitive.clone()),
+                TypeDef::Composite(ref composite) if composite.fields.len() == 1 => {
+                    ty = self.registry.resolve(composite.fields[0].ty.id)?;
+                }
+                _ => return None,
+            }
+        }
+    }
+
     pub fn with_default_custom_type_transcoders(self) -> Self {
         self.register_custom_type_transcoder::<AccountId32, _>(env_types::AccountId)
             .register_custom_type_decoder::<primitive_types::H256, _>(env_types::Hash)
@@ -115,10 +147,34 @@ impl TranscoderBuilder {
     where
         T: TypeInfo + 'static,
         U: CustomTypeEncoder + 'static,
+    {
+        self.register_custom_type_encoder_for_path(PathKey::from_type::<T>(), encoder)
+    }
+
+    /// Register a custom transcoder for the type with the given path, e.g. for types only
+    /// known at runtime.
+    pub fn register_custom_type_transcoder_for_path<U>(
+        self,
+        path_key: PathKey,
+        transcoder: U,
+    ) -> Self
+    where
+        U: CustomTypeEncoder + CustomTypeDecoder + Clone + 'static,
+    {
+        self.register_custom_type_encoder_for_path(path_key.clone(), transcoder.clone())
+            .register_custom_type_decoder_for_path(path_key, transcoder)
+    }
+
+    pub fn register_custom_type_encoder_for_path<U>(
+        self,
+        path_key: PathKey,
+        encoder: U,
+    ) -> Self
+    where
+        U: CustomTypeEncoder + 'static,
     {
         let mut this = self;
 
-        let path_key = PathKey::from_type::<T>();
         let type_id = this.types_by_path.get(&path_key);
 
         match type_id {
@@ -140,14 +196,49 @@ impl TranscoderBuilder {
         this
     }
 
+    /// Register a custom decoder rendering the values of the type with the given path,
+    /// replacing the decoder registered for the type before, e.g. the default decoder of
+    /// account ids. How values of the type are encoded is not affected.
+    pub fn register_custom_type_renderer_for_path<U>(
+        self,
+        path_key: PathKey,
+        renderer: U,
+    ) -> Self
+    where
+        U: CustomTypeDecoder + 'static,
+    {
+        let mut this = self;
+
+        match this.types_by_path.get(&path_key) {
+            Some(type_id) => {
+                this.decoders.insert(*type_id, Box::new(renderer));
+                tracing::debug!("Registered custom renderer for type `{:?}`", type_id);
+            }
+            None => {
+                tracing::debug!("No matching type in registry for path {:?}.", path_key);
+            }
+        }
+        this
+    }
+
     pub fn register_custom_type_decoder<T, U>(self, encoder: U) -> Self
     where
         T: TypeInfo + 'static,
         U: CustomTypeDecoder + 'static,
+    {
+        self.register_custom_type_decoder_for_path(PathKey::from_type::<T>(), encoder)
+    }
+
+    pub fn register_custom_type_decoder_for_path<U>(
+        self,
+        path_key: PathKey,
+        encoder: U,
+    ) -> Self
+    where
+        U: CustomTypeDecoder + 'static,
     {
         let mut this = self;
 
-        let path_key = PathKey::from_type::<T>();
         let type_id = this.types_by_path.get(&path_key);
 
         match type_id {
@@ -216,7 +307,7 @@ mod tests {
             .with_default_custom_type_transcoders()
             .done();
 
-        let value = scon::parse_value(input)?;
+        let value = scon::parse_arg(input)?;
 
         let mut output = Vec::new();
         transcoder.encode(&registry, ty, &value, &mut output)?;
@@ -394,6 +485,68 @@ mod tests {
         )
     }
 
+    fn decode_pretty<T>(input: &str) -> Result<Value>
+    where
+        T: scale_info::TypeInfo + 'static,
+    {
+        let (registry, ty) = registry_with_type::<T>()?;
+        let mut transcoder = TranscoderBuilder::new(&registry).done();
+        let value = scon::parse_arg(input)?;
+        let mut output = Vec::new();
+        transcoder.encode(&registry, ty, &value, &mut output)?;
+        transcoder.set_bytes_format(BytesFormat::Pretty);
+        transcoder.decode(&registry, ty, &mut &output[..])
+    }
+
+    #[test]
+    fn decode_bytes_pretty() -> Result<()> {
+        type AccountId = [u8; 32];
+
+        #[allow(dead_code)]
+        #[derive(TypeInfo)]
+        struct S {
+            owner: AccountId,
+            hash: [u8; 32],
+        }
+
+        assert_eq!(
+            decode_pretty::<Vec<u8>>("str:hello")?,
+            Value::String("hello".to_string())
+        );
+        assert_eq!(
+            decode_pretty::<Vec<u8>>("0x00ff")?,
+            Value::Hex(Hex::from_str("0x00ff")?)
+        );
+        assert_eq!(
+            decode_pretty::<[u8; 4]>("0xDEADBEEF")?,
+            Value::Hex(Hex::from_str("0xdeadbeef")?)
+        );
+        let alice = "0xd43593c715fdd31c61141abd04a99fd6822c8558854ccde39a5684e7a56da27d";
+        let decoded = decode_pretty::<S>(&format!("S(owner: {alice}, hash: {alice})"))?;
+        assert_eq!(
+            decoded,
+            Value::Map(Map::new(
+                Some("S"),
+                vec![
+                    (
+                        Value::String("owner".to_string()),
+                        Value::Literal(
+                            "5GrwvaEF5zXb26Fz9rcQpDWS57CtERHpNehXCPcNoHGKutQY"
+                                .to_string()
+                        ),
+                    ),
+                    (
+                        Value::String("hash".to_string()),
+                        Value::Hex(Hex::from_str(alice)?),
+                    ),
+                ]
+                .into_iter()
+                .collect(),
+            ))
+        );
+        Ok(())
+    }
+
     #[test]
     fn transcode_composite_struct() -> Result<()> {
         #[allow(dead_code)]
@@ -847,4 +1000,113 @@ mod tests {
             )),
         )
     }
+
+    #[test]
+    fn transcode_token_amount_for_path() -> Result<()> {
+        #[allow(dead_code)]
+        #[derive(TypeInfo)]
+        struct Amount(u128);
+
+        let (registry, ty) = registry_with_type::<Amount>()?;
+        let path_key = PathKey::from("contract_transcode::transcoder::tests::Amount");
+        let token_amount = env_types::TokenAmount::new(12, Some("UNIT".to_string()))?;
+        let builder = TranscoderBuilder::new(&registry);
+        assert_eq!(
+            builder.primitive_type_of(&path_key),
+            Some(TypeDefPrimitive::U128)
+        );
+        let transcoder = builder
+            .register_custom_type_transcoder_for_path(path_key, token_amount)
+            .done();
+
+        for (input, planck, output) in [
+            (r#""1.5""#, 1_500_000_000_000u128, "1.5 UNIT"),
+            ("2", 2_000_000_000_000, "2 UNIT"),
+            (r#"".000000000001""#, 1, "0.000000000001 UNIT"),
+        ] {
+            let mut encoded = Vec::new();
+            transcoder.encode(&registry, ty, &scon::parse_arg(input)?, &mut encoded)?;
+            assert_eq!(encoded, planck.encode());
+            let decoded = transcoder.decode(&registry, ty, &mut &encoded[..])?;
+            assert_eq!(decoded, Value::Literal(output.to_string()));
+        }
+
+        let value = scon::parse_arg(r#""0.0000000000001""#)?;
+        assert!(transcoder
+            .encode(&registry, ty, &value, &mut Vec::new())
+            .is_err());
+        Ok(())
+    }
+    #[test]
+    fn transcode_u256_as_decimal() -> Result<()> {
+        let (registry, ty) = registry_with_type::<primitive_types::U256>()?;
+        let transcoder = TranscoderBuilder::new(&registry)
+            .register_custom_type_transcoder_for_path(
+                PathKey::from("primitive_types::U256"),
+                env_types::U256Decimal,
+            )
+            .done();
+
+        let max = primitive_types::U256::MAX.to_string();
+        let max_input = format!("\"{max}\"");
+        for (input, output) in [(r#""42""#, "42"), (max_input.as_str(), max.as_str())] {
+            let mut encoded = Vec::new();
+            transcoder.encode(&registry, ty, &scon::parse_arg(input)?, &mut encoded)?;
+            assert_eq!(encoded.len(), 32);
+            let decoded = transcoder.decode(&registry, ty, &mut &encoded[..])?;
+            assert_eq!(decoded, Value::Literal(output.to_string()));
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn renderers_replace_the_default_decoders() -> Result<()> {
+        #[allow(dead_code)]
+        #[derive(TypeInfo)]
+        struct Moment(u64);
+
+        let (registry, ty) = registry_with_type::<Moment>()?;
+        let transcoder = TranscoderBuilder::new(&registry)
+            .register_custom_type_decoder_for_path(
+                PathKey::from("contract_transcode::transcoder::tests::Moment"),
+                env_types::Hash,
+            )
+            .register_custom_type_renderer_for_path(
+                PathKey::from("contract_transcode::transcoder::tests::Moment"),
+                env_types::Timestamp::new(env_types::TimestampUnit::Milliseconds),
+            )
+            .done();
+
+        let encoded = 1_700_000_000_500u64.encode();
+        let decoded = transcoder.decode(&registry, ty, &mut &encoded[..])?;
+        assert_eq!(
+            decoded,
+            Value::Literal("2023-11-14T22:13:20.500Z".to_string())
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn account_ids_are_rendered_with_their_names() -> Result<()> {
+        let (registry, ty) = registry_with_type::<AccountId32>()?;
+        let alice =
+            AccountId32::from_str("5GrwvaEF5zXb26Fz9rcQpDWS57CtERHpNehXCPcNoHGKutQY")?;
+        let named = alice.clone();
+        let transcoder = TranscoderBuilder::new(&registry)
+            .with_default_custom_type_transcoders()
+            .register_custom_type_renderer_for_path(
+                PathKey::from_type::<AccountId32>(),
+                env_types::NamedAccountId::new(move |account| {
+                    (*account == named).then(|| "Alice".to_string())
+                }),
+            )
+            .done();
+
+        let decoded = transcoder.decode(&registry, ty, &mut &alice.encode()[..])?;
+        assert_eq!(
+            decoded,
+            Value::Literal(format!("{} (Alice)", alice.to_ss58check()))
+        );
+        Ok(())
+    }
 }